@@ -56,6 +56,41 @@ pub enum Commands {
         /// optional - The path to run the command in, default will be where the command is run.
         #[clap(long, short)]
         path: Option<String>,
+
+        /// optional - Record every RPC response into this directory so the run can be replayed.
+        #[clap(long, conflicts_with = "replay")]
+        record: Option<String>,
+
+        /// optional - Replay the RPC responses recorded with `--record` instead of calling the
+        /// RPCs.
+        #[clap(long)]
+        replay: Option<String>,
+
+        /// optional - Paths of more no-code projects to run in the same process, separated by
+        /// commas. Each indexes into its own schemas and they share the RPC providers.
+        #[clap(long, value_delimiter = ',')]
+        projects: Vec<String>,
+
+        /// optional - Apply the schema changes to a database which already has tables without
+        /// asking, the plan is still printed.
+        #[clap(long)]
+        auto_approve: bool,
+    },
+
+    /// Runs a local anvil node, deploys the contracts with `dev.foundry_artifact` set from their
+    /// Foundry build artifacts and indexes them, redeploying and restarting on changes.
+    ///
+    /// Example:
+    /// `rindexer dev`
+    #[clap(name = "dev")]
+    Dev {
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+
+        /// optional - The port anvil listens on, a free port is picked by default.
+        #[clap(long)]
+        port: Option<u16>,
     },
 
     /// Add elements such as contracts to the rindexer.yaml file.
@@ -78,7 +113,8 @@ pub enum Commands {
     ///
     /// Example:
     /// `rindexer codegen typings` or `rindexer codegen handlers` or `rindexer codegen graphql
-    /// --endpoint=graphql_api` or `rindexer codegen rust-all`
+    /// --endpoint=graphql_api` or `rindexer codegen graphql-typescript` or `rindexer codegen
+    /// rust-all`
     #[clap(name = "codegen")]
     Codegen {
         #[clap(subcommand)]
@@ -88,6 +124,128 @@ pub enum Commands {
         #[clap(long, short)]
         path: Option<String>,
     },
+    /// Pauses fetching an event of the running indexer on every network, indexing carries on
+    /// from the last synced block once resumed. Needs `admin` set in the rindexer.yaml file.
+    ///
+    /// Example:
+    /// `rindexer pause --contract RocketPoolETH --event Transfer`
+    #[clap(name = "pause")]
+    Pause {
+        /// The contract name as in the rindexer.yaml file.
+        #[clap(long)]
+        contract: String,
+
+        /// The event name as in the ABI.
+        #[clap(long)]
+        event: String,
+
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+
+    /// Resumes an event paused with `rindexer pause`.
+    ///
+    /// Example:
+    /// `rindexer resume --contract RocketPoolETH --event Transfer`
+    #[clap(name = "resume")]
+    Resume {
+        /// The contract name as in the rindexer.yaml file.
+        #[clap(long)]
+        contract: String,
+
+        /// The event name as in the ABI.
+        #[clap(long)]
+        event: String,
+
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+
+    /// Prints the RPC calls the running indexer has made per network and method and the Alchemy
+    /// compute units they are estimated to cost. Needs `admin` set in the rindexer.yaml file.
+    ///
+    /// Example:
+    /// `rindexer usage`
+    #[clap(name = "usage")]
+    Usage {
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+
+    /// Seeds the event tables of a contract from parquet files of a public dataset, the BigQuery
+    /// `crypto_ethereum.logs` table exported to parquet or cryo `logs` dumps, so a popular
+    /// contract does not have to be indexed from its start block over the RPC. Rows are checked
+    /// against the contract's ABI and indexing carries on after the last imported block. Needs
+    /// postgres storage enabled and the indexer stopped.
+    ///
+    /// Example:
+    /// `rindexer import --contract RocketPoolETH --network ethereum logs/*.parquet`
+    #[clap(name = "import")]
+    Import {
+        /// The contract name as in the rindexer.yaml file.
+        #[clap(long)]
+        contract: String,
+
+        /// The network the files hold logs of.
+        #[clap(long)]
+        network: String,
+
+        /// optional - The last block the files cover, the highest block found in them by default.
+        /// The files have to hold every log of the contract from its start block up to it.
+        #[clap(long)]
+        to_block: Option<u64>,
+
+        /// The parquet files, imported in the order given.
+        #[clap(required = true)]
+        files: Vec<String>,
+
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+
+    /// Decodes the archived raw logs of an event again and rewrites its table.
+    ///
+    /// Needs `archive_raw_logs` on the contract, recovers from a wrong ABI or a decoder bug
+    /// without fetching the logs again. Stop the indexer while redecoding.
+    ///
+    /// Example:
+    /// `rindexer redecode Transfer --contract RocketPoolETH`
+    #[clap(name = "redecode")]
+    Redecode {
+        /// The event name as in the ABI.
+        event: String,
+
+        /// The contract name as in the rindexer.yaml file.
+        #[clap(long)]
+        contract: String,
+
+        /// optional - Only redecode the logs of this network, all networks of the contract by
+        /// default.
+        #[clap(long)]
+        network: Option<String>,
+
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+
+    /// Estimates what backfilling the manifest takes before starting it.
+    ///
+    /// Samples the log density of every event over its configured range and reports the expected
+    /// logs, `eth_getLogs` calls, storage size and time per event. Nothing is written.
+    ///
+    /// Example:
+    /// `rindexer estimate`
+    Estimate {
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+
     /// Delete data from the postgres database or csv files.
     ///
     /// This command deletes rindexer project data from the postgres database or csv files.
@@ -117,6 +275,52 @@ pub enum Commands {
         #[clap(long, short)]
         path: Option<String>,
     },
+    /// Convert projects from other indexers into a rindexer project
+    ///
+    /// This command maps the data sources of other indexers into a rindexer.yaml file.
+    ///
+    /// Example:
+    /// `rindexer convert subgraph --subgraph-path ./subgraph.yaml`
+    #[clap(name = "convert")]
+    Convert {
+        #[clap(subcommand)]
+        subcommand: ConvertSubcommands,
+
+        /// optional - The path to create the project in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+
+    /// Imports a subgraph.yaml as a no-code rindexer.yaml, copying over the ABIs. The same as
+    /// `rindexer convert subgraph`.
+    ///
+    /// Example:
+    /// `rindexer import-subgraph --subgraph-path ./subgraph.yaml`
+    #[clap(name = "import-subgraph")]
+    ImportSubgraph {
+        /// The path to the subgraph.yaml file
+        #[clap(long)]
+        subgraph_path: String,
+
+        /// optional - The path to create the project in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+
+    /// Writes the schemas rindexer generates to files for review, frontend codegen or
+    /// infrastructure as code.
+    ///
+    /// Example:
+    /// `rindexer schema sql --output ./schema.sql`
+    #[clap(name = "schema")]
+    Schema {
+        #[clap(subcommand)]
+        subcommand: SchemaSubcommands,
+
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -169,6 +373,16 @@ pub enum StartSubcommands {
         #[clap(short, long, help = "Specify the port number for all services")]
         port: Option<String>,
     },
+
+    /// Starts a worker which only takes historic backfill jobs, needs `backfill_jobs` in the
+    /// rindexer.yaml file.
+    ///
+    /// Run as many as you like next to `rindexer start indexer`, they exit once the jobs are done.
+    ///
+    /// Example:
+    /// `rindexer start backfill-worker`
+    #[clap(name = "backfill-worker")]
+    BackfillWorker,
 }
 
 #[derive(Subcommand, Debug)]
@@ -214,6 +428,76 @@ pub enum CodegenSubcommands {
         #[clap(long, help = "The graphql endpoint - defaults to localhost:3001")]
         endpoint: Option<String>,
     },
+
+    /// Generates a typed typescript client from a GraphQL schema
+    ///
+    /// Gives frontends consuming the indexer typed queries and results
+    ///
+    /// Example:
+    /// `rindexer codegen graphql-typescript`
+    #[clap(name = "graphql-typescript")]
+    GraphQLTypescript {
+        #[clap(long, help = "The graphql endpoint - defaults to localhost:3001")]
+        endpoint: Option<String>,
+    },
+
+    /// Checks the generated postgres schema and optionally the GraphQL schema against the
+    /// snapshots in the `snapshots` folder, failing with a diff when they changed
+    ///
+    /// Run with `--update` to write the snapshots, commit them and run the check in CI
+    ///
+    /// Example:
+    /// `rindexer codegen snapshot --update --graphql-endpoint http://localhost:3001/graphql`
+    #[clap(name = "snapshot")]
+    Snapshot {
+        #[clap(long, help = "Write the snapshots instead of checking them")]
+        update: bool,
+
+        #[clap(long, help = "Also snapshot the GraphQL schema served at this endpoint")]
+        graphql_endpoint: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConvertSubcommands {
+    /// Converts a subgraph.yaml into a no-code rindexer.yaml, copying over the ABIs.
+    ///
+    /// Mappings are not converted, templates (dynamic data sources) need adding manually.
+    ///
+    /// Example:
+    /// `rindexer convert subgraph --subgraph-path ./subgraph.yaml`
+    #[clap(name = "subgraph")]
+    Subgraph {
+        /// The path to the subgraph.yaml file
+        #[clap(long)]
+        subgraph_path: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SchemaSubcommands {
+    /// Writes the GraphQL SDL served by the running GraphQL API.
+    ///
+    /// Example:
+    /// `rindexer schema graphql --endpoint http://localhost:3001/graphql`
+    #[clap(name = "graphql")]
+    GraphQL {
+        #[clap(long, help = "The graphql endpoint - defaults to localhost:3001")]
+        endpoint: Option<String>,
+
+        #[clap(long, help = "The file to write - defaults to schema.graphql in the project")]
+        output: Option<String>,
+    },
+
+    /// Writes the postgres DDL rindexer runs on startup for the manifest, no database is needed.
+    ///
+    /// Example:
+    /// `rindexer schema sql`
+    #[clap(name = "sql")]
+    Sql {
+        #[clap(long, help = "The file to write - defaults to schema.sql in the project")]
+        output: Option<String>,
+    },
 }
 
 #[derive(Args, Debug)]
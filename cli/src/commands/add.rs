@@ -179,11 +179,26 @@ pub async fn handle_add_contract_command(
             abi: StringOrArray::Single(abi_path_relative),
             include_events: None,
             index_event_in_order: None,
+            group_events_by_block: None,
+            event_concurrency: None,
+            failure_policy: None,
             dependency_events: None,
+            retention: None,
+            normalize_decimals: None,
+            enrichment: None,
+            encrypted_inputs: None,
+            table_storage: None,
+            params_storage: None,
+            archive_raw_logs: None,
+            deduplicate_logs: None,
+            write_ahead_log: None,
             reorg_safe_distance: None,
             generate_csv: None,
             streams: None,
             chat: None,
+            dev: None,
+            table_group: None,
+            spam_filter: None,
         });
 
         write_manifest(&manifest, &rindexer_yaml_path).map_err(|e| {
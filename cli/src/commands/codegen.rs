@@ -1,8 +1,15 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use rindexer::{
-    format_all_files_for_project, generate_graphql_queries,
-    generator::build::{generate_rindexer_handlers, generate_rindexer_typings},
+    fetch_graphql_sdl, format_all_files_for_project, generate_graphql_queries,
+    generate_graphql_typescript_client,
+    generator::{
+        build::{generate_rindexer_handlers, generate_rindexer_typings},
+        snapshot::{
+            check_snapshot, postgres_schema_snapshot, SnapshotResult, GRAPHQL_SNAPSHOT_FILE,
+            POSTGRES_SNAPSHOT_FILE, SNAPSHOTS_FOLDER,
+        },
+    },
     manifest::{
         core::ProjectType,
         yaml::{read_manifest, YAML_CONFIG_NAME},
@@ -11,10 +18,69 @@ use rindexer::{
 
 use crate::{
     cli_interface::CodegenSubcommands,
-    console::{print_error_message, print_success_message},
+    console::{print_error_message, print_success_message, print_warn_message},
     rindexer_yaml::validate_rindexer_yaml_exist,
 };
 
+/// Returns false when the snapshot changed and was not updated
+fn report_snapshot(
+    path: &Path,
+    actual: &str,
+    update: bool,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    match check_snapshot(path, actual, update)? {
+        SnapshotResult::Unchanged => {
+            print_success_message(&format!("{} is up to date.", path.display()));
+            Ok(true)
+        }
+        SnapshotResult::Written => {
+            print_success_message(&format!("Wrote {}.", path.display()));
+            Ok(true)
+        }
+        SnapshotResult::Changed(diff) => {
+            print_warn_message(&format!("{} changed:\n{}", path.display(), diff));
+            Ok(false)
+        }
+    }
+}
+
+async fn handle_snapshot_command(
+    project_path: &Path,
+    update: bool,
+    graphql_endpoint: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    validate_rindexer_yaml_exist(project_path);
+
+    let manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could not read the rindexer.yaml file: {}", e));
+        e
+    })?;
+
+    let snapshots_path = project_path.join(SNAPSHOTS_FOLDER);
+    let mut up_to_date = true;
+
+    if manifest.storage.postgres_enabled() {
+        let sql = postgres_schema_snapshot(project_path, &manifest)?;
+        up_to_date &= report_snapshot(&snapshots_path.join(POSTGRES_SNAPSHOT_FILE), &sql, update)?;
+    }
+
+    if let Some(endpoint) = graphql_endpoint {
+        let sdl = fetch_graphql_sdl(endpoint).await.map_err(|e| {
+            print_error_message(&format!("Failed to fetch the graphql schema: {}", e));
+            e
+        })?;
+        up_to_date &= report_snapshot(&snapshots_path.join(GRAPHQL_SNAPSHOT_FILE), &sdl, update)?;
+    }
+
+    if !up_to_date {
+        let error = "The generated schema does not match the snapshots, run with --update if the change is expected";
+        print_error_message(error);
+        return Err(error.into());
+    }
+
+    Ok(())
+}
+
 pub async fn handle_codegen_command(
     project_path: PathBuf,
     subcommand: &CodegenSubcommands,
@@ -31,6 +97,22 @@ pub async fn handle_codegen_command(
         return Ok(());
     }
 
+    if let CodegenSubcommands::GraphQLTypescript { endpoint } = subcommand {
+        let url = endpoint.as_deref().unwrap_or("http://localhost:3001");
+        generate_graphql_typescript_client(url, &project_path).await.map_err(|e| {
+            print_error_message(&format!("Failed to generate graphql typescript client: {}", e));
+            e
+        })?;
+
+        print_success_message("Generated graphql typescript client.");
+
+        return Ok(());
+    }
+
+    if let CodegenSubcommands::Snapshot { update, graphql_endpoint } = subcommand {
+        return handle_snapshot_command(&project_path, *update, graphql_endpoint.as_deref()).await;
+    }
+
     validate_rindexer_yaml_exist(&project_path);
 
     let rindexer_yaml_path = project_path.join(YAML_CONFIG_NAME);
@@ -65,7 +147,9 @@ pub async fn handle_codegen_command(
             format_all_files_for_project(project_path);
             print_success_message("Generated rindexer indexer handlers.");
         }
-        CodegenSubcommands::GraphQL { endpoint: _endpoint } => {
+        CodegenSubcommands::GraphQL { .. } |
+        CodegenSubcommands::GraphQLTypescript { .. } |
+        CodegenSubcommands::Snapshot { .. } => {
             unreachable!("This should not be reachable");
         }
     }
@@ -0,0 +1,86 @@
+use std::{fs, path::PathBuf};
+
+use rindexer::{
+    manifest::{
+        core::ProjectType,
+        subgraph::convert_subgraph_manifest,
+        yaml::{write_manifest, YAML_CONFIG_NAME},
+    },
+    write_file,
+};
+
+use crate::{
+    cli_interface::ConvertSubcommands,
+    console::{print_error_message, print_success_message, print_warn_message, prompt_for_input},
+    rindexer_yaml::rindexer_yaml_exists,
+};
+
+pub fn handle_convert_command(
+    project_path: PathBuf,
+    subcommand: &ConvertSubcommands,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match subcommand {
+        ConvertSubcommands::Subgraph { subgraph_path } => {
+            convert_subgraph(project_path, PathBuf::from(subgraph_path))
+        }
+    }
+}
+
+pub fn convert_subgraph(
+    project_path: PathBuf,
+    subgraph_path: PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if rindexer_yaml_exists(&project_path) {
+        let error = "rindexer.yaml already exists in this directory, please run the command in an empty directory.";
+        print_error_message(error);
+        return Err(error.into());
+    }
+
+    let project_name = prompt_for_input(
+        "Project Name",
+        Some(r"^[a-zA-Z][a-zA-Z0-9]*$"),
+        Some("No spaces, special characters are allowed, and the first letter cannot be a number"),
+        None,
+    );
+
+    let converted = convert_subgraph_manifest(&subgraph_path, &project_name, ProjectType::NoCode)
+        .map_err(|e| {
+        print_error_message(&format!("Failed to convert subgraph manifest: {}", e));
+        e
+    })?;
+
+    fs::create_dir_all(project_path.join("abis")).map_err(|e| {
+        print_error_message(&format!("Failed to create abis directory: {}", e));
+        e
+    })?;
+
+    for (from, to) in &converted.abis_to_copy {
+        fs::copy(from, project_path.join(to)).map_err(|e| {
+            print_error_message(&format!("Failed to copy ABI {}: {}", from.display(), e));
+            e
+        })?;
+    }
+
+    write_manifest(&converted.manifest, &project_path.join(YAML_CONFIG_NAME))?;
+
+    let mut env =
+        vec!["DATABASE_URL=postgresql://[user[:password]@][host][:port][/dbname]".to_string()];
+    for network in &converted.manifest.networks {
+        env.push(format!("{}=", network.rpc.trim_start_matches("${").trim_end_matches('}')));
+    }
+    write_file(&project_path.join(".env"), &env.join("\n")).map_err(|e| {
+        print_error_message(&format!("Failed to write .env file: {}", e));
+        e
+    })?;
+
+    print_warn_message(
+        "Subgraph mappings are not converted - the events will be indexed into postgres with no-code, fill in the RPC urls in the .env file before starting.",
+    );
+    print_success_message(&format!(
+        "Converted subgraph into rindexer project {} with {} contracts.",
+        project_name,
+        converted.manifest.contracts.len()
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::{env, path::PathBuf, time::Duration};
+
+use rindexer::{
+    dev::{watched_files, DevChain, FileChanges},
+    manifest::{
+        core::ProjectType,
+        yaml::{read_manifest, YAML_CONFIG_NAME},
+    },
+    rindexer_info, setup_info_logger,
+};
+use tokio::process::{Child, Command};
+
+use crate::{
+    console::{print_error_message, print_warn_message},
+    rindexer_yaml::validate_rindexer_yaml_exist,
+};
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Runs the indexer as a child process so a restart starts from a clean slate
+fn spawn_indexer(dev_project_path: &PathBuf) -> Result<Child, Box<dyn std::error::Error>> {
+    let child = Command::new(env::current_exe()?)
+        .arg("start")
+        .arg("--path")
+        .arg(dev_project_path)
+        .arg("indexer")
+        .kill_on_drop(true)
+        .spawn()?;
+
+    Ok(child)
+}
+
+pub async fn handle_dev_command(
+    project_path: PathBuf,
+    port: Option<u16>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    setup_info_logger();
+
+    validate_rindexer_yaml_exist(&project_path);
+
+    let manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could not read the rindexer.yaml file: {}", e));
+        e
+    })?;
+    if manifest.project_type == ProjectType::Rust {
+        let error = "rindexer dev only supports no-code projects at the moment";
+        print_error_message(error);
+        return Err(error.into());
+    }
+
+    let chain = DevChain::spawn(port)?;
+    let mut changes = FileChanges::default();
+    let mut files = watched_files(&project_path)?;
+    changes.changed(&files);
+
+    loop {
+        let mut indexer = match chain.deploy_project(&project_path).await {
+            Ok(dev_project_path) => Some(spawn_indexer(&dev_project_path)?),
+            Err(e) => {
+                print_error_message(&format!("{} - waiting for changes", e));
+                None
+            }
+        };
+
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {
+                    if let Some(mut indexer) = indexer {
+                        let _ = indexer.kill().await;
+                    }
+                    return Ok(());
+                }
+                status = async { indexer.as_mut().expect("checked by the guard").wait().await }, if indexer.is_some() => {
+                    print_warn_message(&format!(
+                        "The indexer exited ({}) - waiting for changes",
+                        status.map_or_else(|e| e.to_string(), |s| s.to_string())
+                    ));
+                    indexer = None;
+                }
+                _ = tokio::time::sleep(WATCH_INTERVAL) => {
+                    // a half written manifest fails to parse, keep watching the files we know
+                    if let Ok(latest) = watched_files(&project_path) {
+                        files = latest;
+                    }
+                    if changes.changed(&files) {
+                        rindexer_info!("Change detected - redeploying and restarting the indexer");
+                        if let Some(mut indexer) = indexer.take() {
+                            let _ = indexer.kill().await;
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
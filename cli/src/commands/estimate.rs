@@ -0,0 +1,72 @@
+use std::{path::PathBuf, time::Duration};
+
+use rindexer::{
+    indexer::{estimate::estimate_manifest, format_eta},
+    manifest::yaml::{read_manifest, YAML_CONFIG_NAME},
+};
+
+use crate::console::{print_error_message, print_success_message, print_warn_message};
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Prints what backfilling every event is expected to take, nothing is written to the storage
+pub async fn handle_estimate_command(
+    project_path: PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could not read the rindexer.yaml file: {}", e));
+        e
+    })?;
+
+    print_warn_message(
+        "Sampling the log density of every event, the estimates extrapolate a few windows of each range so clustered events can be well off",
+    );
+
+    let estimates = estimate_manifest(&project_path, &manifest).await.map_err(|e| {
+        print_error_message(&format!("Estimate failed: {}", e));
+        e
+    })?;
+
+    let (mut logs, mut rpc_calls, mut storage_bytes) = (0, 0, 0);
+    let mut longest = Duration::ZERO;
+    for estimate in &estimates {
+        println!(
+            "{}::{} on {} - blocks {} - {}: ~{} logs ({} in {} sampled blocks), ~{} eth_getLogs calls, ~{}, ~{}",
+            estimate.contract_name,
+            estimate.event_name,
+            estimate.network,
+            estimate.from_block,
+            estimate.to_block,
+            estimate.logs,
+            estimate.sampled_logs,
+            estimate.sampled_blocks,
+            estimate.rpc_calls,
+            format_bytes(estimate.storage_bytes),
+            format_eta(estimate.duration)
+        );
+        logs += estimate.logs;
+        rpc_calls += estimate.rpc_calls;
+        storage_bytes += estimate.storage_bytes;
+        longest = longest.max(estimate.duration);
+    }
+
+    // events backfill side by side so the slowest one bounds the wall clock time
+    print_success_message(&format!(
+        "Total: ~{} logs, ~{} eth_getLogs calls, ~{} of storage, ~{} until the slowest event is backfilled",
+        logs,
+        rpc_calls,
+        format_bytes(storage_bytes),
+        format_eta(longest)
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use rindexer::{
+    indexer::import::import_logs,
+    manifest::yaml::{read_manifest, YAML_CONFIG_NAME},
+    setup_info_logger,
+};
+
+use crate::console::{print_error_message, print_success_message, print_warn_message};
+
+/// Seeds the event tables of a contract from parquet dumps of a public dataset, the indexer
+/// should not be running while importing
+pub async fn handle_import_command(
+    project_path: PathBuf,
+    contract: &str,
+    network: &str,
+    to_block: Option<u64>,
+    files: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could not read the rindexer.yaml file: {}", e));
+        e
+    })?;
+
+    setup_info_logger();
+
+    let files: Vec<PathBuf> = files.iter().map(PathBuf::from).collect();
+    let summary =
+        import_logs(&project_path, &manifest, contract, network, &files, to_block.map(Into::into))
+            .await
+            .map_err(|e| {
+                print_error_message(&format!("Import failed: {}", e));
+                e
+            })?;
+
+    if summary.rejected > 0 {
+        print_warn_message(&format!(
+            "{} rows could not be read or did not decode with the {} ABI and were left out",
+            summary.rejected, contract
+        ));
+    }
+    match summary.checkpoint {
+        Some(checkpoint) => print_success_message(&format!(
+            "Imported {} rows of {} on {}, indexing will carry on from block {}",
+            summary.imported,
+            contract,
+            network,
+            checkpoint + 1
+        )),
+        None => print_warn_message(&format!("No rows of {} found on {}", contract, network)),
+    }
+
+    Ok(())
+}
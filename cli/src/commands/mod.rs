@@ -1,8 +1,16 @@
 pub mod add;
 pub mod codegen;
+pub mod convert;
 pub mod delete;
+pub mod dev;
+pub mod estimate;
+pub mod import;
 pub mod new;
+pub mod pause;
 pub mod phantom;
+pub mod redecode;
+pub mod schema;
 pub mod start;
+pub mod usage;
 
 const BACKUP_ETHERSCAN_API_KEY: &str = "DHBPB1EJ84JMSWP7C86387NK7IIRRQJVV1";
@@ -56,6 +56,7 @@ fn write_gitignore(path: &Path) -> Result<(), WriteFileError> {
     write_file(
         &path.join(".gitignore"),
         r#".rindexer
+    .rindexer-dev
     generated_csv/**/*.txt
     "#,
     )
@@ -141,6 +142,14 @@ pub fn handle_new_command(
             compute_units_per_second: None,
             max_block_range: None,
             disable_logs_bloom_checks: None,
+            rpc_headers: None,
+            rpc_bearer_token: None,
+            logs_bloom_scan: None,
+            historical_source: None,
+            cache: None,
+            validate_chain_id: None,
+            fallback_rpcs: None,
+            rpc_retry: None,
         }],
         contracts: vec![Contract {
             name: "RocketPoolETH".to_string(),
@@ -158,11 +167,26 @@ pub fn handle_new_command(
             abi: StringOrArray::Single(abi_example_path.display().to_string()),
             include_events: Some(vec!["Transfer".to_string(), "Approval".to_string()]),
             index_event_in_order: None,
+            group_events_by_block: None,
+            event_concurrency: None,
+            failure_policy: None,
             dependency_events: None,
+            retention: None,
+            normalize_decimals: None,
+            enrichment: None,
+            encrypted_inputs: None,
+            table_storage: None,
+            params_storage: None,
+            archive_raw_logs: None,
+            deduplicate_logs: None,
+            write_ahead_log: None,
             reorg_safe_distance: None,
             generate_csv: None,
             streams: None,
             chat: None,
+            dev: None,
+            table_group: None,
+            spam_filter: None,
         }],
         phantom: None,
         global: None,
@@ -174,6 +198,15 @@ pub fn handle_new_command(
                     relationships: None,
                     indexes: None,
                     disable_create_tables: None,
+                    materialized_views: None,
+                    soft_delete_reorged_rows: None,
+                    schema_evolution: None,
+                    notify_channel: None,
+                    column_encryption: None,
+                    entities: None,
+                    rollups: None,
+                    state_samples: None,
+                    block_timestamps: None,
                 })
             } else {
                 None
@@ -189,6 +222,18 @@ pub fn handle_new_command(
             },
         },
         graphql: None,
+        flight: None,
+        admin: None,
+        metrics: None,
+        notifications: None,
+        alerts: None,
+        sentry: None,
+        watchdog: None,
+        leader_election: None,
+        backfill_jobs: None,
+        tuning: None,
+        labels: None,
+        modules: None,
     };
 
     // Write the rindexer.yaml file
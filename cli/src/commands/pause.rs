@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use rindexer::{
+    manifest::yaml::{read_manifest, YAML_CONFIG_NAME},
+    set_event_paused,
+};
+
+use crate::console::{print_error_message, print_success_message, print_warn_message};
+
+/// Pauses or resumes an event of the running indexer, which needs `admin` set in the
+/// rindexer.yaml
+pub async fn handle_pause_command(
+    project_path: PathBuf,
+    contract: &str,
+    event: &str,
+    paused: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could not read the rindexer.yaml file: {}", e));
+        e
+    })?;
+
+    let Some(admin) = &manifest.admin else {
+        let error = "Pausing a running indexer needs admin set in the rindexer.yaml file";
+        print_error_message(error);
+        return Err(error.into());
+    };
+
+    let changed = set_event_paused(admin, contract, event, paused)
+        .await
+        .inspect_err(|e| print_error_message(&e.to_string()))?;
+
+    let action = if paused { "paused" } else { "resumed" };
+    if changed {
+        print_success_message(&format!("{} {} {}", contract, event, action));
+    } else {
+        print_warn_message(&format!("{} {} was already {}", contract, event, action));
+    }
+
+    Ok(())
+}
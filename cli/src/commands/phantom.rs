@@ -548,6 +548,14 @@ async fn handle_phantom_deploy(
                             Some(U64::from(2_000))
                         },
                         disable_logs_bloom_checks: None,
+                        rpc_headers: None,
+                        rpc_bearer_token: None,
+                        logs_bloom_scan: None,
+                        historical_source: None,
+                        cache: None,
+                        validate_chain_id: None,
+                        fallback_rpcs: None,
+                        rpc_retry: None,
                     });
                 }
 
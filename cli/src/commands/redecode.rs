@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+
+use rindexer::{
+    indexer::redecode::redecode_event,
+    manifest::yaml::{read_manifest, YAML_CONFIG_NAME},
+    setup_info_logger,
+};
+
+use crate::console::{print_error_message, print_success_message};
+
+/// Rewrites the table of an event from its archived raw logs, the indexer should not be running
+/// while redecoding
+pub async fn handle_redecode_command(
+    project_path: PathBuf,
+    contract: &str,
+    event: &str,
+    network: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could not read the rindexer.yaml file: {}", e));
+        e
+    })?;
+
+    setup_info_logger();
+
+    let summary =
+        redecode_event(&project_path, &manifest, contract, event, network).await.map_err(|e| {
+            print_error_message(&format!("Redecode failed: {}", e));
+            e
+        })?;
+
+    print_success_message(&format!(
+        "Redecoded {} archived logs of {}::{} - replaced {} rows",
+        summary.redecoded, contract, event, summary.removed
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use rindexer::{
+    fetch_graphql_sdl,
+    generator::snapshot::postgres_schema_snapshot,
+    manifest::yaml::{read_manifest, YAML_CONFIG_NAME},
+};
+
+use crate::{
+    cli_interface::SchemaSubcommands,
+    console::{print_error_message, print_success_message},
+    rindexer_yaml::validate_rindexer_yaml_exist,
+};
+
+fn write_schema(path: &Path, schema: &str) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(path, schema).map_err(|e| {
+        print_error_message(&format!("Could not write {}: {}", path.display(), e));
+        e
+    })?;
+    print_success_message(&format!("Wrote {}.", path.display()));
+
+    Ok(())
+}
+
+pub async fn handle_schema_command(
+    project_path: PathBuf,
+    subcommand: &SchemaSubcommands,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match subcommand {
+        SchemaSubcommands::GraphQL { endpoint, output } => {
+            let endpoint = endpoint.as_deref().unwrap_or("http://localhost:3001");
+            let sdl = fetch_graphql_sdl(endpoint).await.map_err(|e| {
+                print_error_message(&format!(
+                    "Failed to fetch the graphql schema, make sure the indexer is running with the GraphQL API: {}",
+                    e
+                ));
+                e
+            })?;
+
+            let path =
+                output.as_ref().map_or_else(|| project_path.join("schema.graphql"), PathBuf::from);
+            write_schema(&path, &sdl)
+        }
+        SchemaSubcommands::Sql { output } => {
+            validate_rindexer_yaml_exist(&project_path);
+            let manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+                print_error_message(&format!("Could not read the rindexer.yaml file: {}", e));
+                e
+            })?;
+            if !manifest.storage.postgres_enabled() {
+                let error = "Postgres storage is not enabled in the rindexer.yaml file";
+                print_error_message(error);
+                return Err(error.into());
+            }
+
+            let sql = postgres_schema_snapshot(&project_path, &manifest).map_err(|e| {
+                print_error_message(&format!("Failed to generate the postgres schema: {}", e));
+                e
+            })?;
+
+            let path =
+                output.as_ref().map_or_else(|| project_path.join("schema.sql"), PathBuf::from);
+            write_schema(&path, &sql)
+        }
+    }
+}
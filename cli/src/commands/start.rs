@@ -1,12 +1,21 @@
-use std::{env, path::PathBuf, process::Command, thread, time::Duration};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::Duration,
+};
 
 use rindexer::{
+    load_env_from_project_path,
     manifest::{
         core::ProjectType,
         yaml::{read_manifest, YAML_CONFIG_NAME},
     },
+    provider_recording::{RpcRecordingMode, RPC_RECORD_DIR_ENV, RPC_REPLAY_DIR_ENV},
     rindexer_error, rindexer_info, setup_info_logger, start_rindexer_no_code,
-    GraphqlOverrideSettings, IndexerNoCodeDetails, PostgresClient, StartNoCodeDetails,
+    start_rindexer_no_code_projects, GraphqlOverrideSettings, IndexerNoCodeDetails, PostgresClient,
+    StartNoCodeDetails, AUTO_APPROVE_SCHEMA_CHANGES_ENV,
 };
 
 use crate::{
@@ -111,9 +120,68 @@ fn start_docker_compose(project_path: &PathBuf) -> Result<(), String> {
     check_docker_compose_status(project_path, 200)
 }
 
+/// Runs the project and the other no-code projects together, rust projects are their own binary
+/// so can not join
+async fn start_projects(
+    project_path: &Path,
+    projects: Vec<PathBuf>,
+    command: &StartSubcommands,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut manifest_paths = vec![project_path.join(YAML_CONFIG_NAME)];
+    for project in projects {
+        validate_rindexer_yaml_exist(&project);
+        load_env_from_project_path(&project);
+        manifest_paths.push(project.join(YAML_CONFIG_NAME));
+    }
+
+    for manifest_path in &manifest_paths {
+        let manifest = read_manifest(manifest_path).map_err(|e| {
+            print_error_message(&format!("Could not read {}: {}", manifest_path.display(), e));
+            e
+        })?;
+        if manifest.project_type != ProjectType::NoCode {
+            return Err(format!(
+                "{} is a rust project, only no-code projects can run with --projects",
+                manifest_path.display()
+            )
+            .into());
+        }
+    }
+
+    let (indexing, graphql, port) = match command {
+        StartSubcommands::Indexer | StartSubcommands::BackfillWorker => (true, false, None),
+        StartSubcommands::Graphql { port } => (false, true, port.as_ref()),
+        StartSubcommands::All { port } => (true, true, port.as_ref()),
+    };
+    if port.is_some() {
+        return Err(
+            "--port can not be used with --projects, set graphql.port in each rindexer.yaml".into(),
+        );
+    }
+
+    let details = manifest_paths
+        .iter()
+        .map(|manifest_path| StartNoCodeDetails {
+            manifest_path,
+            indexing_details: IndexerNoCodeDetails { enabled: indexing },
+            graphql_details: GraphqlOverrideSettings { enabled: graphql, override_port: None },
+        })
+        .collect();
+
+    start_rindexer_no_code_projects(details).await.map_err(|e| {
+        print_error_message(&format!("Error starting the projects: {}", e));
+        e
+    })?;
+
+    Ok(())
+}
+
 pub async fn start(
     project_path: PathBuf,
     command: &StartSubcommands,
+    recording: Option<RpcRecordingMode>,
+    projects: Vec<PathBuf>,
+    auto_approve: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     setup_info_logger();
 
@@ -148,6 +216,30 @@ pub async fn start(
         }
     }
 
+    // picked up when the providers are created, inherited by the rust project process as well
+    match recording {
+        Some(RpcRecordingMode::Record(dir)) => env::set_var(RPC_RECORD_DIR_ENV, dir),
+        Some(RpcRecordingMode::Replay(dir)) => env::set_var(RPC_REPLAY_DIR_ENV, dir),
+        None => {}
+    }
+
+    // picked up when postgres is set up, inherited by the rust project process as well
+    if auto_approve {
+        env::set_var(AUTO_APPROVE_SCHEMA_CHANGES_ENV, "true");
+    }
+
+    if let StartSubcommands::BackfillWorker = command {
+        if manifest.backfill_jobs.is_none() {
+            return Err("backfill-worker needs backfill_jobs set in the rindexer.yaml file".into());
+        }
+        // picked up by the indexer and inherited by the rust project process
+        env::set_var("RINDEXER_BACKFILL_WORKER", "true");
+    }
+
+    if !projects.is_empty() {
+        return start_projects(&project_path, projects, command).await;
+    }
+
     match manifest.project_type {
         ProjectType::Rust => {
             let project_cargo_manifest_path = project_path.join("Cargo.toml");
@@ -156,7 +248,9 @@ pub async fn start(
                 .arg("--manifest-path")
                 .arg(project_cargo_manifest_path)
                 .arg(match command {
-                    StartSubcommands::Indexer => "-- --indexer".to_string(),
+                    StartSubcommands::Indexer | StartSubcommands::BackfillWorker => {
+                        "-- --indexer".to_string()
+                    }
                     StartSubcommands::Graphql { port } => match port {
                         Some(port) => format!("-- --graphql --port={}", port),
                         None => "-- --graphql".to_string(),
@@ -174,7 +268,7 @@ pub async fn start(
             }
         }
         ProjectType::NoCode => match command {
-            StartSubcommands::Indexer => {
+            StartSubcommands::Indexer | StartSubcommands::BackfillWorker => {
                 let details = StartNoCodeDetails {
                     manifest_path: &project_path.join(YAML_CONFIG_NAME),
                     indexing_details: IndexerNoCodeDetails { enabled: true },
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use rindexer::{
+    fetch_provider_usage,
+    manifest::yaml::{read_manifest, YAML_CONFIG_NAME},
+};
+
+use crate::console::{print_error_message, print_warn_message};
+
+/// Prints the RPC calls of the running indexer per network, which needs `admin` set in the
+/// rindexer.yaml
+pub async fn handle_usage_command(project_path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could not read the rindexer.yaml file: {}", e));
+        e
+    })?;
+
+    let Some(admin) = &manifest.admin else {
+        let error =
+            "Reading the RPC usage of a running indexer needs admin set in the rindexer.yaml file";
+        print_error_message(error);
+        return Err(error.into());
+    };
+
+    let report =
+        fetch_provider_usage(admin).await.inspect_err(|e| print_error_message(&e.to_string()))?;
+    if report.is_empty() {
+        print_warn_message("The indexer has not made any RPC calls yet");
+        return Ok(());
+    }
+
+    for usage in &report {
+        println!("{}", usage.network);
+        for method in &usage.methods {
+            println!(
+                "  {:<28} {:>12} calls {:>14} compute units",
+                method.method, method.calls, method.compute_units
+            );
+        }
+        println!("  estimated {} compute units", usage.estimated_compute_units);
+    }
+    println!(
+        "Estimated {} compute units in total",
+        report.iter().map(|usage| usage.estimated_compute_units).sum::<u64>()
+    );
+
+    Ok(())
+}
@@ -15,14 +15,27 @@ mod rindexer_yaml;
 use std::{path::PathBuf, str::FromStr, sync::Once};
 
 use clap::Parser;
-use rindexer::{load_env_from_project_path, manifest::core::ProjectType};
+use rindexer::{
+    load_env_from_project_path, manifest::core::ProjectType, provider_recording::RpcRecordingMode,
+};
 
 use crate::{
     cli_interface::{AddSubcommands, Commands, NewSubcommands, CLI},
     commands::{
-        add::handle_add_contract_command, codegen::handle_codegen_command,
-        delete::handle_delete_command, new::handle_new_command, phantom::handle_phantom_commands,
+        add::handle_add_contract_command,
+        codegen::handle_codegen_command,
+        convert::{convert_subgraph, handle_convert_command},
+        delete::handle_delete_command,
+        dev::handle_dev_command,
+        estimate::handle_estimate_command,
+        import::handle_import_command,
+        new::handle_new_command,
+        pause::handle_pause_command,
+        phantom::handle_phantom_commands,
+        redecode::handle_redecode_command,
+        schema::handle_schema_command,
         start::start,
+        usage::handle_usage_command,
     },
     console::print_error_message,
 };
@@ -88,6 +101,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             handle_new_command(resolved_path, project_type)
         }
+        Commands::Dev { path, port } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            handle_dev_command(resolved_path, *port).await
+        }
         Commands::Add { subcommand, path } => {
             let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
             load_env_from_project_path(&resolved_path);
@@ -101,10 +119,46 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             load_env_from_project_path(&resolved_path);
             handle_codegen_command(resolved_path, subcommand).await
         }
-        Commands::Start { subcommand, path } => {
+        Commands::Start { subcommand, path, record, replay, projects, auto_approve } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            let recording = match (record, replay) {
+                (Some(dir), _) => Some(RpcRecordingMode::Record(resolved_path.join(dir))),
+                (_, Some(dir)) => Some(RpcRecordingMode::Replay(resolved_path.join(dir))),
+                _ => None,
+            };
+            let projects = projects.iter().map(PathBuf::from).collect();
+            start(resolved_path, subcommand, recording, projects, *auto_approve).await
+        }
+        Commands::Pause { contract, event, path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            handle_pause_command(resolved_path, contract, event, true).await
+        }
+        Commands::Resume { contract, event, path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            handle_pause_command(resolved_path, contract, event, false).await
+        }
+        Commands::Usage { path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            handle_usage_command(resolved_path).await
+        }
+        Commands::Import { contract, network, to_block, files, path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            handle_import_command(resolved_path, contract, network, *to_block, files).await
+        }
+        Commands::Redecode { event, contract, network, path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            handle_redecode_command(resolved_path, contract, event, network.as_deref()).await
+        }
+        Commands::Estimate { path } => {
             let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
             load_env_from_project_path(&resolved_path);
-            start(resolved_path, subcommand).await
+            handle_estimate_command(resolved_path).await
         }
         Commands::Delete { path } => {
             let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
@@ -116,5 +170,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             load_env_from_project_path(&resolved_path);
             handle_phantom_commands(resolved_path, subcommand).await
         }
+        Commands::Convert { subcommand, path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            handle_convert_command(resolved_path, subcommand)
+        }
+        Commands::ImportSubgraph { subgraph_path, path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            convert_subgraph(resolved_path, PathBuf::from(subgraph_path))
+        }
+        Commands::Schema { subcommand, path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            handle_schema_command(resolved_path, subcommand).await
+        }
     }
 }
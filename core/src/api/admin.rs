@@ -0,0 +1,495 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use ethers::types::{Address, ValueOrArray, U64};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use crate::{
+    abi::{ABIItem, ReadAbiError},
+    database::postgres::{
+        client::{PostgresClient, PostgresConnectionError, PostgresError},
+        generate::{generate_tables_for_indexer_sql, GenerateTablesForIndexerSqlError},
+        schema_plan::{approve_unattended_schema_plan, SchemaPlanError},
+    },
+    event::callback_registry::EventCallbackRegistry,
+    helpers::{write_file, WriteFileError},
+    indexer::{
+        no_code::{process_events, ProcessIndexersError},
+        pause::{pause_event, paused_events, resume_event},
+        start::start_indexing,
+    },
+    is_running,
+    manifest::{
+        admin::AdminSettings,
+        contract::{Contract, ContractDetails},
+        core::Manifest,
+        yaml::{
+            prepare_manifest, read_manifest_raw, write_manifest, ReadManifestError,
+            WriteManifestError,
+        },
+    },
+    provider::{CreateNetworkProvider, RetryClientError},
+    provider_usage::{provider_usage_report, NetworkUsage},
+    types::single_or_array::StringOrArray,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum StartAdminServerError {
+    #[error("Could not connect to postgres: {0}")]
+    Postgres(#[from] PostgresConnectionError),
+
+    #[error("Could not serve the admin API: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AdminError {
+    #[error("Missing or wrong api key")]
+    Unauthorized,
+
+    #[error("A contract named {0} is already indexed")]
+    ContractExists(String),
+
+    #[error(
+        "Contract name {0} must start with a letter and only use letters, digits and underscores"
+    )]
+    InvalidName(String),
+
+    #[error("Network {0} is not in the manifest")]
+    UnknownNetwork(String),
+
+    #[error("Contract {0} is not indexed")]
+    UnknownContract(String),
+
+    #[error("Contract {0} has no event {1}")]
+    UnknownEvent(String, String),
+
+    #[error("Could not read the ABI: {0}")]
+    ReadAbi(#[from] ReadAbiError),
+
+    #[error("{0}")]
+    InvalidContract(#[from] ReadManifestError),
+
+    #[error("Could not write the ABI: {0}")]
+    WriteAbi(#[from] WriteFileError),
+
+    #[error("Could not parse the ABI: {0}")]
+    InvalidAbi(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    Provider(#[from] RetryClientError),
+
+    #[error("Could not create the tables: {0}")]
+    GenerateTables(#[from] GenerateTablesForIndexerSqlError),
+
+    #[error("Could not create the tables: {0}")]
+    CreateTables(#[from] PostgresError),
+
+    #[error("{0}")]
+    SchemaPlan(#[from] SchemaPlanError),
+
+    #[error("Could not register the events: {0}")]
+    RegisterEvents(#[from] ProcessIndexersError),
+
+    #[error("Could not save the contract to the manifest: {0}")]
+    Persist(#[from] WriteManifestError),
+}
+
+impl IntoResponse for AdminError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            AdminError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AdminError::ContractExists(_) |
+            AdminError::SchemaPlan(SchemaPlanError::NotApproved(_)) => StatusCode::CONFLICT,
+            AdminError::UnknownContract(_) | AdminError::UnknownEvent(_, _) => {
+                StatusCode::NOT_FOUND
+            }
+            AdminError::InvalidName(_) |
+            AdminError::UnknownNetwork(_) |
+            AdminError::InvalidContract(_) |
+            AdminError::InvalidAbi(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        if status == StatusCode::INTERNAL_SERVER_ERROR {
+            error!("Admin API error: {}", self);
+        }
+
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct AddContractRequest {
+    name: String,
+    network: String,
+    address: ValueOrArray<Address>,
+    /// The json ABI, looked up by address like a manifest contract without one when left out
+    #[serde(default)]
+    abi: Option<Value>,
+    #[serde(default)]
+    start_block: Option<U64>,
+    #[serde(default)]
+    end_block: Option<U64>,
+}
+
+#[derive(Deserialize)]
+struct EventRequest {
+    contract: String,
+    event: String,
+}
+
+struct AdminState {
+    api_key: String,
+    indexer_name: String,
+    project_path: PathBuf,
+    manifest_path: Option<PathBuf>,
+    postgres: Option<Arc<PostgresClient>>,
+    /// The contracts indexed so far, adds are serialised by the lock so two with the same name
+    /// can not race
+    manifest: Mutex<Manifest>,
+}
+
+impl AdminState {
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), AdminError> {
+        let token = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        match token {
+            Some(token) if !token.is_empty() && token == self.api_key => Ok(()),
+            _ => Err(AdminError::Unauthorized),
+        }
+    }
+}
+
+/// The name ends up in the ABI file path and the schema and table names so only an identifier
+/// is accepted
+fn is_valid_contract_name(name: &str) -> bool {
+    name.len() <= 63 &&
+        name.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) &&
+        name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A manifest with only the contract in it, so the setup and indexing of a whole manifest run
+/// for just that contract
+fn contract_manifest(manifest: &Manifest, contract: Contract) -> Manifest {
+    let mut contract_manifest = manifest.clone();
+    contract_manifest.contracts = vec![contract];
+    // the ones of the running indexer already cover every contract
+    contract_manifest.alerts = None;
+    contract_manifest.modules = None;
+    contract_manifest
+}
+
+async fn add_contract(
+    State(state): State<Arc<AdminState>>,
+    headers: HeaderMap,
+    Json(request): Json<AddContractRequest>,
+) -> Result<(StatusCode, Json<Value>), AdminError> {
+    state.authorize(&headers)?;
+    if !is_valid_contract_name(&request.name) {
+        return Err(AdminError::InvalidName(request.name));
+    }
+
+    let mut manifest = state.manifest.lock().await;
+    if manifest.contracts.iter().any(|contract| contract.name == request.name) {
+        return Err(AdminError::ContractExists(request.name));
+    }
+    if !manifest.networks.iter().any(|network| network.name == request.network) {
+        return Err(AdminError::UnknownNetwork(request.network));
+    }
+
+    let abi = match &request.abi {
+        Some(abi) => {
+            let abi_path = format!("./abis/{}.abi.json", request.name);
+            write_file(&state.project_path.join(&abi_path), &serde_json::to_string_pretty(abi)?)?;
+            StringOrArray::Single(abi_path)
+        }
+        None => StringOrArray::default(),
+    };
+    let contract = Contract::new(
+        &request.name,
+        vec![ContractDetails::new_with_address(
+            request.network,
+            request.address,
+            None,
+            request.start_block,
+            request.end_block,
+        )],
+        abi,
+    );
+
+    // checked along with the other contracts as relationships and table groups span them,
+    // resolving a missing ABI blocks on the lookups
+    let mut full_manifest = manifest.clone();
+    full_manifest.contracts.push(contract);
+    let project_path = state.project_path.clone();
+    let mut full_manifest = tokio::task::spawn_blocking(move || {
+        prepare_manifest(&project_path, &mut full_manifest).map(|_| full_manifest)
+    })
+    .await
+    .expect("prepare manifest task panicked")?;
+    let contract = full_manifest.contracts.pop().expect("the added contract is last");
+    let mut new_manifest = contract_manifest(&manifest, contract.clone());
+
+    if let Some(postgres) = &state.postgres {
+        if !new_manifest.storage.postgres_disable_create_tables() {
+            approve_unattended_schema_plan(postgres, &state.project_path, &new_manifest).await?;
+            let sql = generate_tables_for_indexer_sql(
+                &state.project_path,
+                &new_manifest.to_indexer(),
+                false,
+                new_manifest.storage.postgres_soft_delete_reorged_rows(),
+                new_manifest.storage.postgres_notify_channel(),
+                new_manifest.storage.postgres_column_encryption(),
+                new_manifest.labels.is_some(),
+            )?;
+            postgres.batch_execute(sql.as_str()).await?;
+        }
+    }
+
+    let network_providers = CreateNetworkProvider::create(&new_manifest)?;
+    let events = process_events(
+        &state.project_path,
+        &mut new_manifest,
+        state.postgres.clone(),
+        &network_providers,
+    )
+    .await?;
+    let event_names: Vec<String> = events.iter().map(|event| event.event_name.clone()).collect();
+    let registry = EventCallbackRegistry { events };
+
+    // saved before indexing starts so a contract which could not be saved is not indexed
+    if let Some(manifest_path) = &state.manifest_path {
+        let mut raw_manifest = read_manifest_raw(manifest_path)?;
+        raw_manifest.contracts.push(contract.clone());
+        write_manifest(&raw_manifest, manifest_path)?;
+    }
+    manifest.contracts.push(contract.clone());
+
+    info!("Admin API added {} indexing {}", contract.name, event_names.join(", "));
+    let project_path = state.project_path.clone();
+    tokio::spawn(async move {
+        if let Err(e) =
+            start_indexing(&new_manifest, &project_path, &[], false, registry.complete()).await
+        {
+            error!(
+                "Indexing of the added contract {} failed: {}",
+                new_manifest.contracts[0].name, e
+            );
+        }
+    });
+
+    Ok((StatusCode::CREATED, Json(json!({ "name": contract.name, "events": event_names }))))
+}
+
+/// The name the pipelines of the event run under, filter contracts run under a `Filter` suffix
+async fn event_contract_name(
+    state: &AdminState,
+    request: &EventRequest,
+) -> Result<String, AdminError> {
+    let manifest = state.manifest.lock().await;
+    let contract = manifest
+        .contracts
+        .iter()
+        .find(|contract| contract.name == request.contract)
+        .ok_or_else(|| AdminError::UnknownContract(request.contract.clone()))?;
+
+    let abi_items = ABIItem::read_abi_items(&state.project_path, contract)?;
+    if !abi_items.iter().any(|item| item.type_ == "event" && item.name == request.event) {
+        return Err(AdminError::UnknownEvent(request.contract.clone(), request.event.clone()));
+    }
+
+    Ok(contract.before_modify_name_if_filter_readonly().into_owned())
+}
+
+async fn pause(
+    State(state): State<Arc<AdminState>>,
+    headers: HeaderMap,
+    Json(request): Json<EventRequest>,
+) -> Result<Json<Value>, AdminError> {
+    state.authorize(&headers)?;
+    let contract_name = event_contract_name(&state, &request).await?;
+
+    let paused = pause_event(&state.indexer_name, &contract_name, &request.event);
+    if paused {
+        info!("Admin API paused {} {}", request.contract, request.event);
+    }
+
+    Ok(Json(json!({ "contract": request.contract, "event": request.event, "changed": paused })))
+}
+
+async fn resume(
+    State(state): State<Arc<AdminState>>,
+    headers: HeaderMap,
+    Json(request): Json<EventRequest>,
+) -> Result<Json<Value>, AdminError> {
+    state.authorize(&headers)?;
+    let contract_name = event_contract_name(&state, &request).await?;
+
+    let resumed = resume_event(&state.indexer_name, &contract_name, &request.event);
+    if resumed {
+        info!("Admin API resumed {} {}", request.contract, request.event);
+    }
+
+    Ok(Json(json!({ "contract": request.contract, "event": request.event, "changed": resumed })))
+}
+
+async fn paused(
+    State(state): State<Arc<AdminState>>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, AdminError> {
+    state.authorize(&headers)?;
+
+    Ok(Json(json!({ "paused": paused_events(&state.indexer_name) })))
+}
+
+async fn usage(
+    State(state): State<Arc<AdminState>>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, AdminError> {
+    state.authorize(&headers)?;
+
+    Ok(Json(json!({ "networks": provider_usage_report() })))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AdminClientError {
+    #[error("Could not reach the admin API, is the indexer running? {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("The admin API refused with {0}: {1}")]
+    Refused(u16, String),
+
+    #[error("The admin API sent an unexpected response: {0}")]
+    InvalidResponse(#[from] serde_json::Error),
+}
+
+async fn admin_response(response: reqwest::Response) -> Result<Value, AdminClientError> {
+    let status = response.status();
+    let body: Value = response.json().await?;
+    if !status.is_success() {
+        let message = body["error"].as_str().unwrap_or_default().to_string();
+        return Err(AdminClientError::Refused(status.as_u16(), message));
+    }
+
+    Ok(body)
+}
+
+/// Pauses or resumes an event of the indexer running on this machine through its admin API,
+/// returns false when it already was
+pub async fn set_event_paused(
+    settings: &AdminSettings,
+    contract_name: &str,
+    event_name: &str,
+    paused: bool,
+) -> Result<bool, AdminClientError> {
+    let action = if paused { "pause" } else { "resume" };
+    let response = reqwest::Client::new()
+        .post(format!("http://localhost:{}/events/{}", settings.port, action))
+        .bearer_auth(&settings.api_key)
+        .json(&json!({ "contract": contract_name, "event": event_name }))
+        .send()
+        .await?;
+
+    let body = admin_response(response).await?;
+    Ok(body["changed"].as_bool().unwrap_or_default())
+}
+
+/// The RPC calls the indexer running on this machine has made per network through its admin API
+pub async fn fetch_provider_usage(
+    settings: &AdminSettings,
+) -> Result<Vec<NetworkUsage>, AdminClientError> {
+    let response = reqwest::Client::new()
+        .get(format!("http://localhost:{}/usage", settings.port))
+        .bearer_auth(&settings.api_key)
+        .send()
+        .await?;
+
+    let body = admin_response(response).await?;
+    Ok(serde_json::from_value(body["networks"].clone())?)
+}
+
+/// Serves the admin API, `POST /contracts` starts indexing a new contract and adds it to the
+/// manifest so it is still indexed after a restart. Added contracts index like no-code ones, the
+/// reorg watcher, retention and relationships pick them up on the next restart.
+///
+/// `POST /events/pause` and `POST /events/resume` with a `{"contract": .., "event": ..}` body
+/// stop and restart fetching an event on every network, `GET /events/paused` lists the paused
+/// ones. Pauses do not survive a restart.
+///
+/// `GET /usage` returns the RPC calls made per network and the compute units they are estimated
+/// to cost.
+pub async fn start_admin_server(
+    settings: &AdminSettings,
+    project_path: &Path,
+    manifest: &Manifest,
+    manifest_path: Option<PathBuf>,
+) -> Result<(), StartAdminServerError> {
+    let postgres = if manifest.storage.postgres_enabled() {
+        Some(Arc::new(PostgresClient::new().await?))
+    } else {
+        None
+    };
+    let state = Arc::new(AdminState {
+        api_key: settings.api_key.clone(),
+        indexer_name: manifest.name.clone(),
+        project_path: project_path.to_path_buf(),
+        manifest_path,
+        postgres,
+        manifest: Mutex::new(manifest.clone()),
+    });
+
+    let app = Router::new()
+        .route("/contracts", post(add_contract))
+        .route("/events/pause", post(pause))
+        .route("/events/resume", post(resume))
+        .route("/events/paused", get(paused))
+        .route("/usage", get(usage))
+        .with_state(state);
+
+    let address = SocketAddr::from(([0, 0, 0, 0], settings.port));
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    info!("Admin API listening on {}", address);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            while is_running() {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contract_name_is_an_identifier() {
+        assert!(is_valid_contract_name("RocketPoolETH"));
+        assert!(is_valid_contract_name("uniswap_v3_pool"));
+        assert!(!is_valid_contract_name(""));
+        assert!(!is_valid_contract_name("1inch"));
+        assert!(!is_valid_contract_name("../../etc/passwd"));
+        assert!(!is_valid_contract_name("pool\"; DROP SCHEMA public; --"));
+        assert!(!is_valid_contract_name(&"a".repeat(64)));
+    }
+}
@@ -0,0 +1,311 @@
+use std::{net::SocketAddr, path::Path, sync::Arc, time::Duration};
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap},
+    response::{Html, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::{error, info, warn};
+
+use crate::{
+    abi::{ABIItem, ParamTypeError, ReadAbiError},
+    api::{export::ExportFormat, generate_schema::fetch_schema, graphql::send_proxied},
+    indexer::{published_progress, Indexer},
+    is_running,
+    manifest::graphql::GraphQLSettings,
+};
+
+/// How many of the latest events the example queries fetch
+const EXAMPLE_EVENTS: usize = 10;
+
+const EXPLORER_PAGE: &str = include_str!("explorer.html");
+
+#[derive(thiserror::Error, Debug)]
+pub enum StartGraphQLExplorerError {
+    #[error("Could not read the ABI: {0}")]
+    ReadAbi(#[from] ReadAbiError),
+
+    #[error("{0}")]
+    ParamType(#[from] ParamTypeError),
+
+    #[error("Could not serve the GraphQL explorer: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Serialize, Debug)]
+struct ExampleQuery {
+    name: String,
+    query: String,
+}
+
+#[derive(Serialize)]
+struct EventLag {
+    contract_name: String,
+    event_name: String,
+    network: String,
+    status: String,
+    last_synced_block: u64,
+    head_block: Option<u64>,
+    blocks_behind_head: Option<u64>,
+    logs_processed: u64,
+    blocks_per_second: f64,
+    logs_per_second: f64,
+    /// Unset until the event has synced some blocks
+    eta_seconds: Option<u64>,
+}
+
+struct ExplorerState {
+    client: Client,
+    graphql_url: String,
+    indexer_name: String,
+    queries: Vec<ExampleQuery>,
+}
+
+/// The paged query of a type, named `all` and the plural of the type the same way the generated
+/// operations expect
+fn paged_query_name<'a>(schema: &'a Value, type_name: &str) -> Option<&'a str> {
+    schema["types"].as_array()?.iter().find(|t| t["name"] == "Query")?["fields"]
+        .as_array()?
+        .iter()
+        .filter_map(|field| field["name"].as_str())
+        .find(|name| {
+            name.strip_prefix("all")
+                .and_then(|plural| plural.get(..plural.len().saturating_sub(1)))
+                .is_some_and(|singular| singular.eq_ignore_ascii_case(type_name))
+        })
+}
+
+fn is_scalar(type_ref: &Value) -> bool {
+    match type_ref["kind"].as_str() {
+        Some("NON_NULL") => is_scalar(&type_ref["ofType"]),
+        Some("SCALAR") | Some("ENUM") => true,
+        _ => false,
+    }
+}
+
+/// Relations need a selection of their own so only the scalar columns are queried
+fn scalar_fields(schema: &Value, type_name: &str) -> Vec<String> {
+    schema["types"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|t| t["name"].as_str().is_some_and(|name| name.eq_ignore_ascii_case(type_name)))
+        .and_then(|t| t["fields"].as_array())
+        .into_iter()
+        .flatten()
+        .filter(|field| is_scalar(&field["type"]))
+        .filter_map(|field| field["name"].as_str().map(|name| name.to_string()))
+        .collect()
+}
+
+// yes it is meant to be formatted like the below to make the graphql query readable
+fn latest_events_query(query_name: &str, fields: &[String]) -> String {
+    format!(
+        r#"query Latest{}($first: Int = {}) {{
+    {}(first: $first, orderBy: BLOCK_NUMBER_DESC) {{
+        nodes {{
+            {}
+        }}
+    }}
+}}"#,
+        query_name.trim_start_matches("all"),
+        EXAMPLE_EVENTS,
+        query_name,
+        fields.join("\n            ")
+    )
+}
+
+/// A query for the latest rows of every event table of the manifest, looked up in the served
+/// schema as clashing event names are exposed with the contract name in front
+fn example_queries(
+    project_path: &Path,
+    indexer: &Indexer,
+    schema: &Value,
+) -> Result<Vec<ExampleQuery>, StartGraphQLExplorerError> {
+    let mut queries: Vec<ExampleQuery> = vec![];
+    for contract in &indexer.contracts {
+        // contracts in a table group write into the same tables
+        let table_name = contract.table_group.as_deref().unwrap_or(&contract.name);
+        let abi_items = ABIItem::read_abi_items(project_path, contract)?;
+        for event in ABIItem::extract_event_names_and_signatures_from_abi(abi_items)? {
+            let name = format!("{} {}", table_name, event.name);
+            if queries.iter().any(|query| query.name == name) {
+                continue;
+            }
+
+            let found = [format!("{}{}", table_name, event.name), event.name.clone()]
+                .into_iter()
+                .find_map(|type_name| {
+                    paged_query_name(schema, &type_name).map(|query_name| (type_name, query_name))
+                });
+            let Some((type_name, query_name)) = found else {
+                continue;
+            };
+
+            let fields = scalar_fields(schema, &type_name);
+            if fields.is_empty() {
+                continue;
+            }
+
+            queries.push(ExampleQuery { name, query: latest_events_query(query_name, &fields) });
+        }
+    }
+
+    Ok(queries)
+}
+
+async fn serve_page() -> Html<&'static str> {
+    Html(EXPLORER_PAGE)
+}
+
+async fn serve_queries(State(state): State<Arc<ExplorerState>>) -> Json<Value> {
+    Json(json!({ "queries": state.queries }))
+}
+
+/// How far every event trails the head and how fast it syncs, only known when the indexer runs
+/// in this process
+async fn serve_status(State(state): State<Arc<ExplorerState>>) -> Json<Value> {
+    let Some(progress) = published_progress(&state.indexer_name) else {
+        return Json(json!({ "indexing": false, "events": [] }));
+    };
+
+    let events: Vec<EventLag> = progress
+        .lock()
+        .await
+        .events
+        .iter()
+        .map(|event| EventLag {
+            contract_name: event.contract_name.clone(),
+            event_name: event.event_name.clone(),
+            network: event.network.clone(),
+            status: event.status.as_str().to_string(),
+            last_synced_block: event.last_synced_block.as_u64(),
+            head_block: event.head_block.map(|block| block.as_u64()),
+            blocks_behind_head: event.blocks_behind_head,
+            logs_processed: event.logs_processed,
+            blocks_per_second: event.blocks_per_second,
+            logs_per_second: event.logs_per_second,
+            eta_seconds: event.eta.map(|eta| eta.as_secs()),
+        })
+        .collect();
+    Json(json!({ "indexing": true, "events": events }))
+}
+
+/// The page posts its queries here so it works without the GraphQL server allowing other
+/// origins, the api key of a role is passed on as it is. Asking for `text/csv` or
+/// `application/x-ndjson` returns the rows of the query instead of the json response
+async fn proxy_graphql(
+    State(state): State<Arc<ExplorerState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let mut request = state
+        .client
+        .post(&state.graphql_url)
+        .header("content-type", "application/json")
+        .body(body.to_vec());
+    if let Some(authorization) = headers.get(AUTHORIZATION).and_then(|value| value.to_str().ok()) {
+        request = request.header("authorization", authorization);
+    }
+
+    send_proxied(request, ExportFormat::from_headers(&headers)).await
+}
+
+/// Serves a page to run queries against the GraphQL API with an example query for the latest
+/// events of every event table and the lag of the indexer, start it once the GraphQL server
+/// answers so the example queries can be looked up in its schema
+pub async fn start_graphql_explorer(
+    project_path: &Path,
+    indexer: &Indexer,
+    settings: &GraphQLSettings,
+) -> Result<(), StartGraphQLExplorerError> {
+    let graphql_url = format!("http://localhost:{}/graphql", settings.port);
+    // with roles the schema can only be read when there is an anonymous role
+    let queries = match fetch_schema(&graphql_url).await {
+        Ok(schema) => example_queries(project_path, indexer, &schema)?,
+        Err(e) => {
+            warn!("Could not read the GraphQL schema, the explorer has no example queries: {}", e);
+            vec![]
+        }
+    };
+
+    let port = settings.explorer_port();
+    info!(
+        "🦀 GraphQL explorer with {} example queries at http://localhost:{} 🦀",
+        queries.len(),
+        port
+    );
+    let state = Arc::new(ExplorerState {
+        client: Client::new(),
+        graphql_url,
+        indexer_name: indexer.name.clone(),
+        queries,
+    });
+
+    let app = Router::new()
+        .route("/", get(serve_page))
+        .route("/queries", get(serve_queries))
+        .route("/status", get(serve_status))
+        .route("/graphql", post(proxy_graphql))
+        .with_state(state);
+
+    let address = SocketAddr::from(([0, 0, 0, 0], port));
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    if let Err(e) = axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            while is_running() {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        })
+        .await
+    {
+        error!("GraphQL explorer stopped: {}", e);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_events_query_from_schema() {
+        let schema = json!({
+            "types": [
+                {
+                    "name": "Query",
+                    "fields": [{ "name": "allApprovals" }, { "name": "allTransfers" }]
+                },
+                {
+                    "name": "Transfer",
+                    "fields": [
+                        { "name": "from", "type": { "kind": "NON_NULL", "ofType": { "kind": "SCALAR", "name": "String" } } },
+                        { "name": "blockNumber", "type": { "kind": "SCALAR", "name": "BigFloat" } },
+                        { "name": "tokenByFrom", "type": { "kind": "OBJECT", "name": "Token" } }
+                    ]
+                }
+            ]
+        });
+
+        assert_eq!(paged_query_name(&schema, "transfer"), Some("allTransfers"));
+        assert_eq!(paged_query_name(&schema, "Erc20Transfer"), None);
+        assert_eq!(
+            latest_events_query("allTransfers", &scalar_fields(&schema, "Transfer")),
+            r#"query LatestTransfers($first: Int = 10) {
+    allTransfers(first: $first, orderBy: BLOCK_NUMBER_DESC) {
+        nodes {
+            from
+            blockNumber
+        }
+    }
+}"#
+        );
+    }
+}
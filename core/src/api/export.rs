@@ -0,0 +1,240 @@
+use std::{collections::HashMap, fmt};
+
+use axum::http::{header::ACCEPT, HeaderMap};
+use serde::{
+    de::{IgnoredAny, MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::Value;
+
+/// The formats besides json a query result can be asked for with the accept header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// The export format when it is accepted before json, anything else is served as the GraphQL
+    /// server answers
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get(ACCEPT)?
+            .to_str()
+            .ok()?
+            .split(',')
+            .map(|media_type| media_type.split(';').next().unwrap_or_default().trim())
+            .find_map(|media_type| match media_type {
+                "text/csv" => Some(Some(ExportFormat::Csv)),
+                "application/x-ndjson" => Some(Some(ExportFormat::Ndjson)),
+                "application/json" | "application/graphql-response+json" => Some(None),
+                _ => None,
+            })
+            .flatten()
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+    #[error("The GraphQL response is not json: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+
+    #[error("Only a query selecting a single list can be served as {0}")]
+    NotAList(&'static str),
+
+    #[error("Could not write the csv: {0}")]
+    Csv(#[from] csv::Error),
+}
+
+/// A row with its fields in the order the query selected them, a json map would sort them
+#[derive(Debug)]
+struct Row(Vec<(String, Value)>);
+
+impl Row {
+    fn get(&self, column: &str) -> Option<&Value> {
+        self.0.iter().find(|(name, _)| name == column).map(|(_, value)| value)
+    }
+}
+
+impl<'de> Deserialize<'de> for Row {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RowVisitor;
+
+        impl<'de> Visitor<'de> for RowVisitor {
+            type Value = Row;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Row, A::Error> {
+                let mut fields = vec![];
+                while let Some(field) = map.next_entry()? {
+                    fields.push(field);
+                }
+                Ok(Row(fields))
+            }
+        }
+
+        deserializer.deserialize_map(RowVisitor)
+    }
+}
+
+impl Serialize for Row {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in &self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum QueryField {
+    List(Vec<Row>),
+    Connection { nodes: Vec<Row> },
+    Other(IgnoredAny),
+}
+
+#[derive(Deserialize)]
+struct GraphQLResponse {
+    data: Option<HashMap<String, QueryField>>,
+    errors: Option<Value>,
+}
+
+/// The rows of a query with a single field, either the list itself or the nodes of a connection
+fn result_rows(data: HashMap<String, QueryField>) -> Option<Vec<Row>> {
+    if data.len() != 1 {
+        return None;
+    }
+
+    match data.into_values().next()? {
+        QueryField::List(rows) | QueryField::Connection { nodes: rows } => Some(rows),
+        QueryField::Other(_) => None,
+    }
+}
+
+/// Relations and lists stay json so every row has the same columns
+fn csv_cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(value)) => value.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+fn write_csv(rows: &[Row]) -> Result<Vec<u8>, ExportError> {
+    // the columns are in the order they are first selected
+    let mut columns: Vec<&str> = vec![];
+    for row in rows {
+        for (column, _) in &row.0 {
+            if !columns.contains(&column.as_str()) {
+                columns.push(column);
+            }
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(&columns)?;
+    for row in rows {
+        writer.write_record(columns.iter().map(|column| csv_cell(row.get(column))))?;
+    }
+
+    writer.into_inner().map_err(|e| ExportError::Csv(e.into_error().into()))
+}
+
+fn write_ndjson(rows: &[Row]) -> Result<Vec<u8>, ExportError> {
+    let mut body = vec![];
+    for row in rows {
+        serde_json::to_writer(&mut body, row)?;
+        body.push(b'\n');
+    }
+    Ok(body)
+}
+
+/// The rows of the GraphQL response in the format, a query which failed is `None` so its errors
+/// are passed on as json
+pub fn export_response(body: &[u8], format: ExportFormat) -> Result<Option<Vec<u8>>, ExportError> {
+    let response: GraphQLResponse = serde_json::from_slice(body)?;
+    if response.errors.is_some() {
+        return Ok(None);
+    }
+
+    let rows =
+        response.data.and_then(result_rows).ok_or(ExportError::NotAList(format.content_type()))?;
+    match format {
+        ExportFormat::Csv => write_csv(&rows).map(Some),
+        ExportFormat::Ndjson => write_ndjson(&rows).map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_export_response_of_a_connection() {
+        // a json map would sort the fields so the response is written out as the server sends it
+        let body = r#"{
+            "data": {
+                "allTransfers": {
+                    "nodes": [
+                        { "from": "0x01", "value": "1000", "blockNumber": 10 },
+                        { "from": "0x02, \"quoted\"", "value": null, "blockNumber": 11 }
+                    ]
+                }
+            }
+        }"#;
+
+        let csv = export_response(body.as_bytes(), ExportFormat::Csv).unwrap().unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "from,value,blockNumber\n0x01,1000,10\n\"0x02, \"\"quoted\"\"\",,11\n"
+        );
+
+        let ndjson = export_response(body.as_bytes(), ExportFormat::Ndjson).unwrap().unwrap();
+        assert_eq!(
+            String::from_utf8(ndjson).unwrap(),
+            "{\"from\":\"0x01\",\"value\":\"1000\",\"blockNumber\":10}\n{\"from\":\"0x02, \\\"quoted\\\"\",\"value\":null,\"blockNumber\":11}\n"
+        );
+    }
+
+    #[test]
+    fn test_export_response_needs_a_single_list() {
+        let errors = json!({ "errors": [{ "message": "syntax error" }], "data": null }).to_string();
+        assert!(export_response(errors.as_bytes(), ExportFormat::Csv).unwrap().is_none());
+
+        let two_fields = json!({ "data": { "a": [], "b": [] } }).to_string();
+        assert!(matches!(
+            export_response(two_fields.as_bytes(), ExportFormat::Ndjson),
+            Err(ExportError::NotAList(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_format_from_the_accept_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(ExportFormat::from_headers(&headers), None);
+
+        headers.insert(ACCEPT, "text/csv;q=0.9, application/json".parse().unwrap());
+        assert_eq!(ExportFormat::from_headers(&headers), Some(ExportFormat::Csv));
+
+        headers.insert(ACCEPT, "application/json, text/csv".parse().unwrap());
+        assert_eq!(ExportFormat::from_headers(&headers), None);
+
+        headers.insert(ACCEPT, "application/x-ndjson".parse().unwrap());
+        assert_eq!(ExportFormat::from_headers(&headers), Some(ExportFormat::Ndjson));
+    }
+}
@@ -0,0 +1,444 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use arrow_array::{
+    builder::{BooleanBuilder, Float64Builder, Int64Builder, StringBuilder},
+    ArrayRef, RecordBatch,
+};
+use arrow_flight::{
+    encode::FlightDataEncoderBuilder,
+    error::FlightError,
+    flight_service_server::{FlightService, FlightServiceServer},
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PollInfo, PutResult, SchemaResult, Ticket,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use futures::{stream, stream::BoxStream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Row;
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+use tracing::info;
+
+use crate::{
+    api::served_schemas,
+    database::postgres::client::{PostgresClient, PostgresConnectionError},
+    indexer::Indexer,
+    is_running,
+    manifest::flight::FlightSettings,
+};
+
+/// Rows fetched from the cursor per record batch
+const ROWS_PER_BATCH: usize = 65_536;
+
+#[derive(thiserror::Error, Debug)]
+pub enum StartFlightServerError {
+    #[error("Could not connect to postgres: {0}")]
+    Postgres(#[from] PostgresConnectionError),
+
+    #[error("Could not serve arrow flight: {0}")]
+    Transport(#[from] tonic::transport::Error),
+}
+
+/// What a ticket reads, a table as `schema.table` with optional network and block range filters
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct TableTicket {
+    table: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    network: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    from_block: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    to_block: Option<u64>,
+}
+
+impl TableTicket {
+    /// Tickets and descriptor commands are the json of a `TableTicket` or just the table name
+    fn parse(bytes: &[u8]) -> Result<Self, Status> {
+        serde_json::from_slice(bytes).or_else(|_| {
+            let table = std::str::from_utf8(bytes)
+                .map_err(|_| Status::invalid_argument("The ticket is not utf8"))?;
+            Ok(TableTicket {
+                table: table.trim().to_string(),
+                network: None,
+                from_block: None,
+                to_block: None,
+            })
+        })
+    }
+
+    fn from_descriptor(descriptor: &FlightDescriptor) -> Result<Self, Status> {
+        match descriptor.path.first() {
+            Some(table) => Self::parse(table.as_bytes()),
+            None => Self::parse(&descriptor.cmd),
+        }
+    }
+}
+
+/// Columns which fit an arrow type are sent as one, everything else including the uint256 values
+/// stored as text and numeric goes as a string so no precision is lost
+fn arrow_type(postgres_type: &str) -> (DataType, &'static str) {
+    match postgres_type {
+        "bigint" | "integer" | "smallint" => (DataType::Int64, "INT8"),
+        "boolean" => (DataType::Boolean, "BOOLEAN"),
+        "double precision" | "real" => (DataType::Float64, "FLOAT8"),
+        _ => (DataType::Utf8, "TEXT"),
+    }
+}
+
+fn internal_error(e: impl std::fmt::Display) -> Status {
+    Status::internal(e.to_string())
+}
+
+fn rows_to_batch(schema: &SchemaRef, rows: &[Row]) -> Result<RecordBatch, FlightError> {
+    let columns = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(index, field)| -> ArrayRef {
+            match field.data_type() {
+                DataType::Int64 => {
+                    let mut builder = Int64Builder::with_capacity(rows.len());
+                    rows.iter()
+                        .for_each(|row| builder.append_option(row.get::<_, Option<i64>>(index)));
+                    Arc::new(builder.finish())
+                }
+                DataType::Boolean => {
+                    let mut builder = BooleanBuilder::with_capacity(rows.len());
+                    rows.iter()
+                        .for_each(|row| builder.append_option(row.get::<_, Option<bool>>(index)));
+                    Arc::new(builder.finish())
+                }
+                DataType::Float64 => {
+                    let mut builder = Float64Builder::with_capacity(rows.len());
+                    rows.iter()
+                        .for_each(|row| builder.append_option(row.get::<_, Option<f64>>(index)));
+                    Arc::new(builder.finish())
+                }
+                _ => {
+                    let mut builder = StringBuilder::new();
+                    rows.iter()
+                        .for_each(|row| builder.append_option(row.get::<_, Option<String>>(index)));
+                    Arc::new(builder.finish())
+                }
+            }
+        })
+        .collect();
+
+    Ok(RecordBatch::try_new(Arc::clone(schema), columns)?)
+}
+
+struct TableColumns {
+    schema: SchemaRef,
+    select: Vec<String>,
+    has_block_number: bool,
+}
+
+struct RindexerFlightService {
+    client: Arc<PostgresClient>,
+    schemas: Vec<String>,
+    networks: Vec<String>,
+}
+
+impl RindexerFlightService {
+    async fn tables(&self) -> Result<Vec<String>, Status> {
+        let rows = self
+            .client
+            .query(
+                "SELECT n.nspname || '.' || c.relname FROM pg_class c \
+                 JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 WHERE n.nspname = ANY($1) AND c.relkind IN ('r', 'v', 'm', 'p') ORDER BY 1",
+                &[&self.schemas],
+            )
+            .await
+            .map_err(internal_error)?;
+
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    /// Only tables in the served schemas can be read, which also keeps the table name safe to put
+    /// in the query
+    async fn table_columns(&self, table: &str) -> Result<TableColumns, Status> {
+        if !self.tables().await?.iter().any(|t| t == table) {
+            return Err(Status::not_found(format!("No table {} is served", table)));
+        }
+
+        let rows = self
+            .client
+            .query(
+                "SELECT a.attname::TEXT, format_type(a.atttypid, a.atttypmod) FROM pg_attribute a \
+                 WHERE a.attrelid = $1::TEXT::regclass AND a.attnum > 0 AND NOT a.attisdropped \
+                 ORDER BY a.attnum",
+                &[&table],
+            )
+            .await
+            .map_err(internal_error)?;
+
+        let mut fields = vec![];
+        let mut select = vec![];
+        for row in rows {
+            let name: String = row.get(0);
+            let (data_type, cast) = arrow_type(&row.get::<_, String>(1));
+            select.push(format!("\"{}\"::{}", name, cast));
+            fields.push(Field::new(name, data_type, true));
+        }
+
+        Ok(TableColumns {
+            has_block_number: fields.iter().any(|field| field.name() == "block_number"),
+            schema: Arc::new(Schema::new(fields)),
+            select,
+        })
+    }
+
+    async fn flight_info(&self, ticket: TableTicket) -> Result<FlightInfo, Status> {
+        let columns = self.table_columns(&ticket.table).await?;
+        let ticket_bytes = serde_json::to_vec(&ticket).map_err(internal_error)?;
+
+        Ok(FlightInfo::new()
+            .try_with_schema(&columns.schema)
+            .map_err(internal_error)?
+            .with_descriptor(FlightDescriptor::new_path(vec![ticket.table]))
+            .with_endpoint(FlightEndpoint::new().with_ticket(Ticket::new(ticket_bytes))))
+    }
+
+    fn where_clause(&self, ticket: &TableTicket, columns: &TableColumns) -> Result<String, Status> {
+        let mut conditions = vec![];
+        if let Some(network) = &ticket.network {
+            // only known network names get into the query
+            if !self.networks.contains(network) {
+                return Err(Status::invalid_argument(format!("Unknown network {}", network)));
+            }
+            conditions.push(format!("network = '{}'", network.replace('\'', "''")));
+        }
+        if ticket.from_block.is_some() || ticket.to_block.is_some() {
+            if !columns.has_block_number {
+                return Err(Status::invalid_argument(format!(
+                    "{} has no block_number to filter on",
+                    ticket.table
+                )));
+            }
+            if let Some(from_block) = ticket.from_block {
+                conditions.push(format!("block_number >= {}", from_block));
+            }
+            if let Some(to_block) = ticket.to_block {
+                conditions.push(format!("block_number <= {}", to_block));
+            }
+        }
+
+        Ok(if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl FlightService for RindexerFlightService {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoActionStream = BoxStream<'static, Result<arrow_flight::Result, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("No authentication is needed"))
+    }
+
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        let mut infos = vec![];
+        for table in self.tables().await? {
+            infos.push(
+                self.flight_info(TableTicket {
+                    table,
+                    network: None,
+                    from_block: None,
+                    to_block: None,
+                })
+                .await,
+            );
+        }
+
+        Ok(Response::new(stream::iter(infos).boxed()))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let ticket = TableTicket::from_descriptor(request.get_ref())?;
+        Ok(Response::new(self.flight_info(ticket).await?))
+    }
+
+    async fn poll_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("Flights are always ready, use get_flight_info"))
+    }
+
+    async fn get_schema(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        let ticket = TableTicket::from_descriptor(request.get_ref())?;
+        let info = self.flight_info(ticket).await?;
+        Ok(Response::new(SchemaResult { schema: info.schema }))
+    }
+
+    /// Reads the table through a cursor in a read only transaction so a pull sees one snapshot
+    /// however long it takes, with a record batch per fetch
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        let ticket = TableTicket::parse(&request.get_ref().ticket)?;
+        let columns = self.table_columns(&ticket.table).await?;
+        let where_clause = self.where_clause(&ticket, &columns)?;
+
+        let connection = self.client.dedicated_connection().await.map_err(internal_error)?;
+        connection
+            .batch_execute(&format!(
+                "BEGIN READ ONLY; DECLARE rindexer_flight NO SCROLL CURSOR FOR SELECT {} FROM {}{}",
+                columns.select.join(", "),
+                ticket.table,
+                where_clause
+            ))
+            .await
+            .map_err(internal_error)?;
+
+        info!("Arrow flight streaming {}{}", ticket.table, where_clause);
+
+        let schema = Arc::clone(&columns.schema);
+        let batches = stream::try_unfold(Some(connection), move |connection| {
+            let schema = Arc::clone(&schema);
+            async move {
+                let Some(connection) = connection else {
+                    return Ok(None);
+                };
+
+                let rows = connection
+                    .query(&format!("FETCH {} FROM rindexer_flight", ROWS_PER_BATCH), &[])
+                    .await
+                    .map_err(|e| FlightError::ExternalError(Box::new(e)))?;
+                if rows.is_empty() {
+                    connection
+                        .batch_execute("COMMIT")
+                        .await
+                        .map_err(|e| FlightError::ExternalError(Box::new(e)))?;
+                    return Ok(None);
+                }
+
+                let batch = rows_to_batch(&schema, &rows)?;
+                Ok(Some((batch, Some(connection))))
+            }
+        });
+
+        let flight_data = FlightDataEncoderBuilder::new()
+            .with_schema(Arc::clone(&columns.schema))
+            .build(batches)
+            .map_err(Status::from);
+
+        Ok(Response::new(flight_data.boxed()))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("The indexed tables are read only"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("No actions are supported"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(stream::empty().boxed()))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("The indexed tables are read only"))
+    }
+}
+
+/// Serves every table GraphQL exposes over Arrow Flight, `list_flights` lists them and a ticket
+/// of `schema.table` or `{"table": .., "network": .., "from_block": .., "to_block": ..}` reads one
+pub async fn start_flight_server(
+    indexer: &Indexer,
+    settings: &FlightSettings,
+    networks: Vec<String>,
+    extra_schemas: Vec<String>,
+) -> Result<(), StartFlightServerError> {
+    let service = RindexerFlightService {
+        client: Arc::new(PostgresClient::new_read_replica().await?),
+        schemas: served_schemas(indexer, extra_schemas),
+        networks,
+    };
+
+    let address = SocketAddr::from(([0, 0, 0, 0], settings.port));
+    info!("Arrow flight server listening on {}", address);
+
+    Server::builder()
+        .add_service(FlightServiceServer::new(service))
+        .serve_with_shutdown(address, async {
+            while is_running() {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_ticket_parse() {
+        assert_eq!(
+            TableTicket::parse(b"rindexer_erc20.transfer").unwrap(),
+            TableTicket {
+                table: "rindexer_erc20.transfer".to_string(),
+                network: None,
+                from_block: None,
+                to_block: None,
+            }
+        );
+
+        let ticket = TableTicket::parse(
+            br#"{"table": "rindexer_erc20.transfer", "network": "ethereum", "from_block": 10}"#,
+        )
+        .unwrap();
+        assert_eq!(ticket.network.as_deref(), Some("ethereum"));
+        assert_eq!(ticket.from_block, Some(10));
+        assert_eq!(ticket.to_block, None);
+    }
+
+    #[test]
+    fn test_arrow_type() {
+        assert_eq!(arrow_type("bigint"), (DataType::Int64, "INT8"));
+        assert_eq!(arrow_type("boolean"), (DataType::Boolean, "BOOLEAN"));
+        assert_eq!(arrow_type("character varying(78)"), (DataType::Utf8, "TEXT"));
+        assert_eq!(arrow_type("numeric"), (DataType::Utf8, "TEXT"));
+    }
+}
@@ -15,7 +15,7 @@ pub enum GenerateOperationsError {
     SchemaGeneration(String),
 }
 
-fn generate_query(name: &str, fields: &[String]) -> String {
+pub(crate) fn generate_query(name: &str, fields: &[String]) -> String {
     let base_name = name.trim_start_matches("all");
     let condition_type = format!("{}Condition", &base_name[..base_name.len() - 1]);
     let order_by_type = format!("{}OrderBy", base_name);
@@ -76,7 +76,7 @@ fn generate_query(name: &str, fields: &[String]) -> String {
     }
 }
 
-fn extract_node_fields(singular_type_name: &str, schema: &Value) -> Vec<String> {
+pub(crate) fn extract_node_fields(singular_type_name: &str, schema: &Value) -> Vec<String> {
     if let Some(types) = schema["types"].as_array() {
         for type_obj in types {
             if let Some(type_name) = type_obj["name"].as_str() {
@@ -3,7 +3,10 @@ use std::path::Path;
 use reqwest::Client;
 use serde_json::Value;
 
-use crate::api::generate_operations::{generate_operations, GenerateOperationsError};
+use crate::api::{
+    generate_operations::{generate_operations, GenerateOperationsError},
+    generate_typescript::generate_typescript_client,
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum GenerateGraphqlQueriesError {
@@ -23,10 +26,7 @@ pub enum GenerateGraphqlQueriesError {
     GenerateOperationsError(#[from] GenerateOperationsError),
 }
 
-pub async fn generate_graphql_queries(
-    endpoint: &str,
-    generate_path: &Path,
-) -> Result<(), GenerateGraphqlQueriesError> {
+pub(super) async fn fetch_schema(endpoint: &str) -> Result<Value, GenerateGraphqlQueriesError> {
     let client = Client::new();
     let introspection_query = r#"
     {
@@ -96,11 +96,106 @@ pub async fn generate_graphql_queries(
         return Err(GenerateGraphqlQueriesError::NoData);
     }
 
+    Ok(schema)
+}
+
+/// Renders an introspected type reference the way it is written in SDL, for example `[Int!]!`
+fn sdl_type(type_ref: &Value) -> String {
+    match type_ref["kind"].as_str() {
+        Some("NON_NULL") => format!("{}!", sdl_type(&type_ref["ofType"])),
+        Some("LIST") => format!("[{}]", sdl_type(&type_ref["ofType"])),
+        _ => type_ref["name"].as_str().unwrap_or("Unknown").to_string(),
+    }
+}
+
+/// Prints the object types of the introspected schema as SDL, sorted by name so the output only
+/// changes when the schema does
+fn print_sdl(schema: &Value) -> String {
+    let mut types: Vec<&Value> = schema["types"]
+        .as_array()
+        .map(|types| {
+            types
+                .iter()
+                .filter(|t| {
+                    t["fields"].is_array() && !t["name"].as_str().unwrap_or("__").starts_with("__")
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    types.sort_by_key(|t| t["name"].as_str().unwrap_or_default().to_string());
+
+    types
+        .iter()
+        .map(|t| {
+            let mut fields: Vec<String> = t["fields"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|field| {
+                    let args: Vec<String> = field["args"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .map(|arg| {
+                            format!(
+                                "{}: {}",
+                                arg["name"].as_str().unwrap_or_default(),
+                                sdl_type(&arg["type"])
+                            )
+                        })
+                        .collect();
+                    let args = if args.is_empty() {
+                        String::new()
+                    } else {
+                        format!("({})", args.join(", "))
+                    };
+
+                    format!(
+                        "  {}{}: {}",
+                        field["name"].as_str().unwrap_or_default(),
+                        args,
+                        sdl_type(&field["type"])
+                    )
+                })
+                .collect();
+            fields.sort();
+
+            format!("type {} {{\n{}\n}}", t["name"].as_str().unwrap_or_default(), fields.join("\n"))
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n") +
+        "\n"
+}
+
+/// The GraphQL schema served at the endpoint as SDL
+pub async fn fetch_graphql_sdl(endpoint: &str) -> Result<String, GenerateGraphqlQueriesError> {
+    let schema = fetch_schema(endpoint).await?;
+
+    Ok(print_sdl(&schema))
+}
+
+pub async fn generate_graphql_queries(
+    endpoint: &str,
+    generate_path: &Path,
+) -> Result<(), GenerateGraphqlQueriesError> {
+    let schema = fetch_schema(endpoint).await?;
+
     generate_operations(&schema, generate_path)?;
 
     Ok(())
 }
 
+pub async fn generate_graphql_typescript_client(
+    endpoint: &str,
+    generate_path: &Path,
+) -> Result<(), GenerateGraphqlQueriesError> {
+    let schema = fetch_schema(endpoint).await?;
+
+    generate_typescript_client(&schema, generate_path)?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use mockito::mock;
@@ -108,6 +203,40 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_print_sdl() {
+        let schema = serde_json::json!({
+            "types": [
+                { "name": "__Schema", "fields": [] },
+                { "name": "Cursor", "fields": null },
+                {
+                    "name": "Transfer",
+                    "fields": [
+                        { "name": "value", "args": [], "type": { "kind": "SCALAR", "name": "BigFloat" } },
+                        {
+                            "name": "from",
+                            "args": [],
+                            "type": { "kind": "NON_NULL", "name": null, "ofType": { "kind": "SCALAR", "name": "String" } }
+                        }
+                    ]
+                },
+                {
+                    "name": "Query",
+                    "fields": [{
+                        "name": "allTransfers",
+                        "args": [{ "name": "first", "type": { "kind": "SCALAR", "name": "Int" } }],
+                        "type": { "kind": "LIST", "name": null, "ofType": { "kind": "OBJECT", "name": "Transfer" } }
+                    }]
+                }
+            ]
+        });
+
+        assert_eq!(
+            print_sdl(&schema),
+            "type Query {\n  allTransfers(first: Int): [Transfer]\n}\n\ntype Transfer {\n  from: String!\n  value: BigFloat\n}\n"
+        );
+    }
+
     #[tokio::test]
     async fn test_generate_graphql_queries_no_data() {
         let _mock = mock("POST", "/")
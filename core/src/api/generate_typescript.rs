@@ -0,0 +1,287 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use serde_json::Value;
+
+use crate::api::generate_operations::{
+    extract_node_fields, generate_query, GenerateOperationsError,
+};
+
+fn graphql_scalar_to_typescript(name: &str) -> String {
+    match name {
+        "Int" | "Float" => "number".to_string(),
+        "Boolean" => "boolean".to_string(),
+        "String" | "ID" | "Cursor" | "BigInt" | "BigFloat" | "Datetime" | "Date" | "UUID" => {
+            "string".to_string()
+        }
+        "JSON" => "unknown".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Resolves an introspected type reference (including NON_NULL and LIST wrappers) to a
+/// typescript type
+fn typescript_type(type_ref: &Value) -> String {
+    match type_ref["kind"].as_str() {
+        Some("NON_NULL") => {
+            let inner = typescript_type(&type_ref["ofType"]);
+            inner.trim_end_matches(" | null").to_string()
+        }
+        Some("LIST") => format!("Array<{}> | null", typescript_type(&type_ref["ofType"])),
+        _ => match type_ref["name"].as_str() {
+            Some(name) => format!("{} | null", graphql_scalar_to_typescript(name)),
+            None => "unknown | null".to_string(),
+        },
+    }
+}
+
+/// The types the client prelude declares itself
+const PRELUDE_TYPES: [&str; 4] = ["PageInfo", "Connection", "PagedVariables", "RindexerClient"];
+
+/// The named type under the NON_NULL and LIST wrappers of a type reference
+fn named_type(type_ref: &Value) -> Option<&str> {
+    match type_ref["kind"].as_str() {
+        Some("NON_NULL") | Some("LIST") => named_type(&type_ref["ofType"]),
+        _ => type_ref["name"].as_str(),
+    }
+}
+
+/// The type a query returns the rows of, for a paged query the type of the nodes of its
+/// connection
+fn query_node_type<'a>(schema: &'a Value, field: &'a Value) -> Option<&'a str> {
+    let type_name = named_type(&field["type"])?;
+    if !type_name.ends_with("Connection") {
+        return Some(type_name);
+    }
+
+    let connection =
+        schema["types"].as_array()?.iter().find(|type_obj| type_obj["name"] == type_name)?;
+    let nodes = connection["fields"].as_array()?.iter().find(|field| field["name"] == "nodes")?;
+    named_type(&nodes["type"])
+}
+
+fn generate_type_interface(type_obj: &Value) -> Option<String> {
+    let name = type_obj["name"].as_str()?;
+    if name.starts_with("__") || name == "Query" || name == "Node" {
+        return None;
+    }
+
+    let fields = type_obj["fields"].as_array()?;
+    let fields: Vec<String> = fields
+        .iter()
+        .filter_map(|field| {
+            let field_name = field["name"].as_str()?;
+            Some(format!("  {}: {};", field_name, typescript_type(&field["type"])))
+        })
+        .collect();
+
+    Some(format!("export interface {} {{\n{}\n}}", name, fields.join("\n")))
+}
+
+fn generate_client_function(field_name: &str, node_type: &str, node_fields: &[String]) -> String {
+    let query = generate_query(field_name, node_fields);
+
+    if field_name.starts_with("all") {
+        format!(
+            r#"export const {field_name}Query = `{query}`;
+
+export async function {field_name}(
+  client: RindexerClient,
+  variables: PagedVariables<{node_type}> = {{}},
+): Promise<Connection<{node_type}>> {{
+  const data = await client.request<{{ {field_name}: Connection<{node_type}> }}>({field_name}Query, variables);
+  return data.{field_name};
+}}"#
+        )
+    } else {
+        format!(
+            r#"export const {field_name}Query = `{query}`;
+
+export async function {field_name}(
+  client: RindexerClient,
+  nodeId: string,
+): Promise<{node_type} | null> {{
+  const data = await client.request<{{ {field_name}: {node_type} | null }}>({field_name}Query, {{ nodeId }});
+  return data.{field_name};
+}}"#
+        )
+    }
+}
+
+const CLIENT_PRELUDE: &str = r#"// This file is generated by rindexer - do not edit manually.
+import type * as types from "./types";
+
+export interface PageInfo {
+  endCursor: string | null;
+  hasNextPage: boolean;
+  hasPreviousPage: boolean;
+  startCursor: string | null;
+}
+
+export interface Connection<T> {
+  nodes: T[];
+  pageInfo: PageInfo;
+}
+
+export interface PagedVariables<T> {
+  after?: string;
+  first?: number;
+  condition?: Partial<T>;
+  orderBy?: string[];
+}
+
+export class RindexerClient {
+  constructor(
+    private readonly endpoint: string,
+    private readonly headers: Record<string, string> = {},
+  ) {}
+
+  async request<T>(query: string, variables: Record<string, unknown> = {}): Promise<T> {
+    const response = await fetch(this.endpoint, {
+      method: "POST",
+      headers: { "content-type": "application/json", ...this.headers },
+      body: JSON.stringify({ query, variables }),
+    });
+    if (!response.ok) {
+      throw new Error(`rindexer graphql request failed with status ${response.status}`);
+    }
+    const json = await response.json();
+    if (json.errors?.length) {
+      throw new Error(json.errors.map((e: { message: string }) => e.message).join(", "));
+    }
+    return json.data as T;
+  }
+}
+"#;
+
+/// Generates a typed typescript client for the GraphQL API, the types are in `types.ts` and a
+/// function per query in `index.ts`.
+pub fn generate_typescript_client(
+    schema: &Value,
+    generate_path: &Path,
+) -> Result<(), GenerateOperationsError> {
+    let client_path = generate_path.join("typescript");
+    fs::create_dir_all(&client_path)?;
+
+    let types = schema["types"].as_array().ok_or_else(|| {
+        GenerateOperationsError::SchemaGeneration("Invalid schema format".to_string())
+    })?;
+
+    let mut type_names: Vec<String> = vec![];
+    let mut interfaces: Vec<String> = vec![];
+    let mut functions: Vec<String> = vec![];
+
+    for type_obj in types {
+        let Some(type_name) = type_obj["name"].as_str() else {
+            continue;
+        };
+
+        if type_name == "Query" {
+            let fields = type_obj["fields"].as_array().ok_or_else(|| {
+                GenerateOperationsError::SchemaGeneration("Invalid fields format".to_string())
+            })?;
+            for field in fields {
+                let (Some(field_name), Some(node_type)) =
+                    (field["name"].as_str(), query_node_type(schema, field))
+                else {
+                    continue;
+                };
+
+                let node_fields = extract_node_fields(node_type, schema);
+                if node_fields.is_empty() {
+                    continue;
+                }
+
+                functions.push(generate_client_function(field_name, node_type, &node_fields));
+            }
+        } else if type_name.ends_with("Connection") ||
+            type_name.ends_with("Edge") ||
+            PRELUDE_TYPES.contains(&type_name)
+        {
+            continue;
+        } else if let Some(interface) = generate_type_interface(type_obj) {
+            type_names.push(type_name.to_string());
+            interfaces.push(interface);
+        }
+    }
+
+    let types_file = format!(
+        "// This file is generated by rindexer - do not edit manually.\n\n{}\n",
+        interfaces.join("\n\n")
+    );
+    File::create(client_path.join("types.ts"))?.write_all(types_file.as_bytes())?;
+
+    let index_file = format!(
+        "{}\n{}\n\n{}\n",
+        CLIENT_PRELUDE,
+        type_names
+            .iter()
+            .map(|name| format!("export type {} = types.{};", name, name))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        functions.join("\n\n")
+    );
+    File::create(client_path.join("index.ts"))?.write_all(index_file.as_bytes())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_typescript_type() {
+        let non_null_string =
+            json!({"kind": "NON_NULL", "ofType": {"kind": "SCALAR", "name": "String"}});
+        assert_eq!(typescript_type(&non_null_string), "string");
+
+        let nullable_int = json!({"kind": "SCALAR", "name": "Int"});
+        assert_eq!(typescript_type(&nullable_int), "number | null");
+
+        let list = json!({
+            "kind": "NON_NULL",
+            "ofType": {"kind": "LIST", "ofType": {"kind": "OBJECT", "name": "Transfer"}}
+        });
+        assert_eq!(typescript_type(&list), "Array<Transfer | null>");
+    }
+
+    #[test]
+    fn test_client_uses_the_connection_node_type_and_declares_page_info_once() {
+        let non_null =
+            |name: &str| json!({"kind": "NON_NULL", "ofType": {"kind": "OBJECT", "name": name}});
+        let schema = json!({"types": [
+            {"name": "Query", "fields": [
+                {"name": "allStatuses", "type": {"kind": "OBJECT", "name": "StatusesConnection"}},
+                {"name": "status", "type": {"kind": "OBJECT", "name": "Status"}}
+            ]},
+            {"name": "StatusesConnection", "fields": [
+                {"name": "nodes", "type": {"kind": "NON_NULL", "ofType": {"kind": "LIST", "ofType": non_null("Status")}}},
+                {"name": "pageInfo", "type": non_null("PageInfo")}
+            ]},
+            {"name": "Status", "fields": [
+                {"name": "nodeId", "type": {"kind": "NON_NULL", "ofType": {"kind": "SCALAR", "name": "ID"}}}
+            ]},
+            {"name": "PageInfo", "fields": [
+                {"name": "hasNextPage", "type": {"kind": "NON_NULL", "ofType": {"kind": "SCALAR", "name": "Boolean"}}}
+            ]}
+        ]});
+        let dir = tempfile::tempdir().unwrap();
+        generate_typescript_client(&schema, dir.path()).unwrap();
+
+        let index = fs::read_to_string(dir.path().join("typescript").join("index.ts")).unwrap();
+        assert!(index.contains("Promise<Connection<Status>>"));
+        assert!(index.contains("Promise<Status | null>"));
+        assert!(!index.contains("Statuse>"));
+        assert_eq!(index.matches("PageInfo {").count(), 1);
+        assert!(!index.contains("export type PageInfo"));
+
+        let types = fs::read_to_string(dir.path().join("typescript").join("types.ts")).unwrap();
+        assert!(!types.contains("PageInfo"));
+    }
+}
@@ -1,5 +1,7 @@
 use std::{
+    collections::HashMap,
     env,
+    net::{SocketAddr, TcpListener},
     path::{Path, PathBuf},
     process::{Child, Command, Stdio},
     sync::{
@@ -9,17 +11,31 @@ use std::{
     time::Duration,
 };
 
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        HeaderMap, HeaderValue, Method, StatusCode, Uri,
+    },
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use reqwest::{Client, Error};
 use serde_json::{json, Value};
 use tokio::sync::{oneshot, oneshot::Sender};
 use tracing::{error, info};
 
 use crate::{
-    database::postgres::{
-        client::connection_string, generate::generate_indexer_contract_schema_name,
+    api::{
+        export::{export_response, ExportFormat},
+        served_schemas,
     },
+    database::postgres::client::read_connection_string,
     helpers::{kill_process_on_port, set_thread_no_logging},
     indexer::Indexer,
+    is_running,
     manifest::graphql::GraphQLSettings,
 };
 
@@ -85,7 +101,8 @@ fn get_graphql_exe() -> Result<PathBuf, ()> {
 }
 #[allow(dead_code)]
 pub struct GraphQLServer {
-    pid: u32,
+    /// One server per role when roles are set
+    pids: Vec<u32>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -95,29 +112,45 @@ pub enum StartGraphqlServerError {
 
     #[error("Could not start up GraphQL server {0}")]
     GraphQLServerStartupError(String),
+
+    #[error("Could not serve the GraphQL roles: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// The session of the connection switches to the role so postgres enforces what it can read,
+/// only works for connection strings in the URL form
+fn role_connection_string(connection_string: &str, role: &str) -> String {
+    let separator = if connection_string.contains('?') { '&' } else { '?' };
+    format!(
+        "{}{}options=-c%20role%3D{}",
+        connection_string,
+        separator,
+        utf8_percent_encode(role, NON_ALPHANUMERIC)
+    )
+}
+
+/// Where the server the clients query listens
+const PUBLIC_HOST: &str = "0.0.0.0";
+
+/// The role servers are only reached through the proxy which checks the api keys, listening on
+/// loopback keeps them from being queried directly
+const ROLE_SERVER_HOST: &str = "127.0.0.1";
+
+fn free_local_port() -> Result<u16, std::io::Error> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
 }
 
 pub async fn start_graphql_server(
     indexer: &Indexer,
     settings: &GraphQLSettings,
+    extra_schemas: Vec<String>,
 ) -> Result<GraphQLServer, StartGraphqlServerError> {
     info!("Starting GraphQL server");
 
-    let schemas: Vec<String> = indexer
-        .contracts
-        .iter()
-        .map(move |contract| {
-            generate_indexer_contract_schema_name(
-                &indexer.name,
-                &contract.before_modify_name_if_filter_readonly(),
-            )
-        })
-        .collect();
+    let schemas = served_schemas(indexer, extra_schemas);
 
-    let connection_string = connection_string()?;
+    let connection_string = read_connection_string()?;
     let port = settings.port;
-    let graphql_endpoint = format!("http://localhost:{}/graphql", &port);
-    let graphql_playground = format!("http://localhost:{}/playground", &port);
 
     let rindexer_graphql_exe = get_graphql_exe().map_err(|_| {
         StartGraphqlServerError::GraphQLServerStartupError(
@@ -128,6 +161,172 @@ pub async fn start_graphql_server(
     // kill any existing process on the port
     kill_process_on_port(port).map_err(StartGraphqlServerError::GraphQLServerStartupError)?;
 
+    let roles = settings.roles();
+    if roles.is_empty() {
+        let pid = start_server_process(
+            settings,
+            rindexer_graphql_exe,
+            connection_string,
+            schemas.join(","),
+            PUBLIC_HOST,
+            port,
+        )
+        .await?;
+        return Ok(GraphQLServer { pids: vec![pid] });
+    }
+
+    // every role gets a server of its own which the requests are proxied to by api key
+    let mut pids = vec![];
+    let mut api_keys: HashMap<String, u16> = HashMap::new();
+    let mut anonymous_port = None;
+    for role in roles {
+        let role_port = free_local_port()?;
+        pids.push(
+            start_server_process(
+                settings,
+                rindexer_graphql_exe.clone(),
+                role_connection_string(&connection_string, &role.name),
+                schemas.join(","),
+                ROLE_SERVER_HOST,
+                role_port,
+            )
+            .await?,
+        );
+        for api_key in &role.api_keys {
+            api_keys.insert(api_key.clone(), role_port);
+        }
+        if settings.anonymous_role.as_ref() == Some(&role.name) {
+            anonymous_port = Some(role_port);
+        }
+    }
+
+    let state = Arc::new(RolesProxyState { client: Client::new(), api_keys, anonymous_port });
+    let address = SocketAddr::from(([0, 0, 0, 0], port));
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    info!("🦀 GraphQL API serving {} roles at http://localhost:{}/graphql 🦀", roles.len(), port);
+    tokio::spawn(async move {
+        let app = Router::new().fallback(proxy_to_role).with_state(state);
+        if let Err(e) = axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                while is_running() {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            })
+            .await
+        {
+            error!("GraphQL roles proxy stopped: {}", e);
+        }
+    });
+
+    Ok(GraphQLServer { pids })
+}
+
+struct RolesProxyState {
+    client: Client,
+    /// The port of the server of the role each api key belongs to
+    api_keys: HashMap<String, u16>,
+    anonymous_port: Option<u16>,
+}
+
+/// The role servers listen on loopback ports picked on start so are only reached through here
+async fn proxy_to_role(
+    State(state): State<Arc<RolesProxyState>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let port = match token {
+        Some(token) => state.api_keys.get(token).copied(),
+        None => state.anonymous_port,
+    };
+    let Some(port) = port else {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "errors": [{ "message": "Missing or wrong api key" }] })),
+        )
+            .into_response();
+    };
+
+    let url = format!(
+        "http://{}:{}{}",
+        ROLE_SERVER_HOST,
+        port,
+        uri.path_and_query().map_or("/", |path_and_query| path_and_query.as_str())
+    );
+    // reqwest and axum are on different versions of the http types
+    let method =
+        reqwest::Method::from_bytes(method.as_str().as_bytes()).unwrap_or(reqwest::Method::GET);
+    let mut request = state.client.request(method, url).body(body.to_vec());
+    if let Some(content_type) = headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok()) {
+        request = request.header("content-type", content_type);
+    }
+
+    send_proxied(request, ExportFormat::from_headers(&headers)).await
+}
+
+/// Sends the request on and hands back the response of the server behind the proxy, a query
+/// result is turned into the export format when the client asked for one
+pub(super) async fn send_proxied(
+    request: reqwest::RequestBuilder,
+    export_format: Option<ExportFormat>,
+) -> Response {
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => return (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+    };
+    let status =
+        StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| HeaderValue::from_str(value).ok());
+    match response.bytes().await {
+        Ok(body) => {
+            if let Some(format) = export_format.filter(|_| status.is_success()) {
+                match export_response(&body, format) {
+                    Ok(Some(exported)) => {
+                        return ([(CONTENT_TYPE, format.content_type())], exported).into_response()
+                    }
+                    // the errors of a failed query are passed on as json
+                    Ok(None) => {}
+                    Err(e) => {
+                        return (
+                            StatusCode::NOT_ACCEPTABLE,
+                            Json(json!({ "errors": [{ "message": e.to_string() }] })),
+                        )
+                            .into_response()
+                    }
+                }
+            }
+
+            let mut proxied = (status, body.to_vec()).into_response();
+            if let Some(content_type) = content_type {
+                proxied.headers_mut().insert(CONTENT_TYPE, content_type);
+            }
+            proxied
+        }
+        Err(e) => (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+    }
+}
+
+/// Starts the server and waits until it answers queries
+async fn start_server_process(
+    settings: &GraphQLSettings,
+    rindexer_graphql_exe: PathBuf,
+    connection_string: String,
+    schemas: String,
+    host: &'static str,
+    port: u16,
+) -> Result<u32, StartGraphqlServerError> {
+    let graphql_endpoint = format!("http://localhost:{}/graphql", &port);
+    let graphql_playground = format!("http://localhost:{}/playground", &port);
+
     let (tx, rx) = oneshot::channel();
     let tx_arc = Arc::new(Mutex::new(Some(tx)));
 
@@ -135,7 +334,8 @@ pub async fn start_graphql_server(
         tx_arc,
         rindexer_graphql_exe,
         connection_string,
-        schemas.join(","),
+        schemas,
+        host,
         Arc::new(port),
         settings.filter_only_on_indexed_columns,
         settings.disable_advanced_filters,
@@ -154,16 +354,18 @@ pub async fn start_graphql_server(
 
     perform_health_check(&graphql_endpoint, &graphql_playground).await?;
 
-    Ok(GraphQLServer { pid })
+    Ok(pid)
 }
 
 static MANUAL_STOP: AtomicBool = AtomicBool::new(false);
 
+#[allow(clippy::too_many_arguments)]
 fn spawn_start_server(
     tx_arc: Arc<Mutex<Option<Sender<u32>>>>,
     rindexer_graphql_exe: PathBuf,
     connection_string: String,
     schemas: String,
+    host: &'static str,
     port: Arc<u16>,
     filter_only_on_indexed_columns: bool,
     disable_advanced_filters: bool,
@@ -178,6 +380,7 @@ fn spawn_start_server(
                 &rindexer_graphql_exe,
                 &connection_string,
                 &schemas,
+                host,
                 &port,
                 filter_only_on_indexed_columns,
                 disable_advanced_filters,
@@ -254,6 +457,7 @@ async fn start_server(
     rindexer_graphql_exe: &Path,
     connection_string: &str,
     schemas: &str,
+    host: &str,
     port: &u16,
     filter_only_on_indexed_columns: bool,
     disable_advanced_filters: bool,
@@ -268,6 +472,7 @@ async fn start_server(
         .arg("10000")
         .arg(filter_only_on_indexed_columns.to_string())
         .arg(disable_advanced_filters.to_string())
+        .arg(host)
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()
@@ -0,0 +1,43 @@
+use std::{net::SocketAddr, time::Duration};
+
+use axum::{http::header::CONTENT_TYPE, response::IntoResponse, routing::get, Router};
+use tracing::info;
+
+use crate::{
+    indexer::render_progress_metrics, is_running, manifest::metrics::MetricsSettings,
+    provider_latency::render_rpc_latency_metrics, provider_usage::render_provider_usage_metrics,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum StartMetricsServerError {
+    #[error("Could not serve the metrics: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+async fn metrics() -> impl IntoResponse {
+    let mut metrics = render_rpc_latency_metrics();
+    metrics.push_str(&render_progress_metrics().await);
+    metrics.push_str(&render_provider_usage_metrics());
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], metrics)
+}
+
+/// The metrics cover every project in the process so only one server should be started
+pub async fn start_metrics_server(
+    settings: &MetricsSettings,
+) -> Result<(), StartMetricsServerError> {
+    let app = Router::new().route("/metrics", get(metrics));
+
+    let address = SocketAddr::from(([0, 0, 0, 0], settings.port));
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    info!("Metrics listening on {}/metrics", address);
+
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            while is_running() {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        })
+        .await?;
+
+    Ok(())
+}
@@ -1,6 +1,39 @@
+mod admin;
+mod explorer;
+mod export;
+mod flight;
 mod generate_operations;
 mod generate_schema;
+mod generate_typescript;
 mod graphql;
+mod metrics;
 
-pub use generate_schema::generate_graphql_queries;
+pub use admin::{fetch_provider_usage, set_event_paused, start_admin_server, AdminClientError};
+pub use explorer::start_graphql_explorer;
+pub use flight::start_flight_server;
+pub use generate_schema::{
+    fetch_graphql_sdl, generate_graphql_queries, generate_graphql_typescript_client,
+};
 pub use graphql::{start_graphql_server, GraphqlOverrideSettings, StartGraphqlServerError};
+pub use metrics::start_metrics_server;
+
+use crate::{
+    database::postgres::generate::generate_indexer_contract_schema_name, indexer::Indexer,
+};
+
+/// The schemas the APIs expose, one per contract and the ones of views, modules, entities and
+/// rollups
+fn served_schemas(indexer: &Indexer, extra_schemas: Vec<String>) -> Vec<String> {
+    let mut schemas: Vec<String> = vec![];
+    for contract in &indexer.contracts {
+        // contracts in a table group share the schema
+        let schema =
+            generate_indexer_contract_schema_name(&indexer.name, &contract.table_name_readonly());
+        if !schemas.contains(&schema) {
+            schemas.push(schema);
+        }
+    }
+    // materialized views and modules live in their own schemas
+    schemas.extend(extra_schemas);
+    schemas
+}
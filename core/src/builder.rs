@@ -0,0 +1,313 @@
+use std::{any::Any, future::Future, path::PathBuf, sync::Arc, time::Duration};
+
+use ethers::{
+    abi::RawLog,
+    contract::EthEvent,
+    types::{Bytes, H256},
+};
+use futures::FutureExt;
+use tracing::error;
+
+use crate::{
+    api::GraphqlOverrideSettings,
+    event::{
+        bus::{self, BusMessage},
+        callback_registry::{
+            Decoder, EventCallbackRegistry, EventCallbackRegistryInformation, EventCallbackResult,
+            EventCallbackType, EventResult, HandlerError, TxInformation,
+        },
+        contract_setup::{ContractInformation, CreateContractInformationError},
+    },
+    generate_random_id,
+    indexer::spam::SpamReason,
+    is_running,
+    manifest::{
+        contract::Contract,
+        core::{Manifest, ProjectType},
+        network::Network,
+        storage::{CsvDetails, PostgresDetails, Storage},
+        yaml::{prepare_manifest, ReadManifestError},
+    },
+    provider::{CreateNetworkProvider, RetryClientError},
+    start::{start_rindexer_with_manifest, IndexingDetails, StartRindexerError},
+};
+
+/// The most bus messages a subscriber handler gets at once
+const SUBSCRIBER_BATCH_SIZE: usize = 1000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RindexerBuilderError {
+    #[error("Contract {0} has a handler but was never added with `contract`")]
+    ContractNotFound(String),
+
+    #[error("{0}")]
+    Manifest(#[from] ReadManifestError),
+
+    #[error("{0}")]
+    Provider(#[from] RetryClientError),
+
+    #[error("{0}")]
+    ContractInformation(#[from] CreateContractInformationError),
+
+    #[error("{0}")]
+    Start(#[from] StartRindexerError),
+}
+
+/// A decoded event and where it was emitted, what the generated `{Event}Result` structs hold
+#[derive(Debug, Clone)]
+pub struct DecodedEvent<E> {
+    pub event_data: E,
+    pub tx_information: TxInformation,
+    /// Why the spam filter flagged the contract which emitted the event
+    pub spam: Option<SpamReason>,
+}
+
+type RegisterEvent = Box<
+    dyn FnOnce(
+            &Manifest,
+            &[CreateNetworkProvider],
+            &mut EventCallbackRegistry,
+        ) -> Result<(), RindexerBuilderError>
+        + Send,
+>;
+
+type StartSubscriber = Box<dyn FnOnce() + Send>;
+
+pub struct Rindexer;
+
+impl Rindexer {
+    /// Builds an indexer in code instead of from a rindexer.yaml
+    pub fn builder(name: &str) -> RindexerBuilder {
+        RindexerBuilder {
+            project_path: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            manifest: Manifest {
+                name: name.to_string(),
+                description: None,
+                repository: None,
+                project_type: ProjectType::Rust,
+                networks: vec![],
+                storage: Storage::default(),
+                contracts: vec![],
+                phantom: None,
+                global: None,
+                graphql: None,
+                flight: None,
+                admin: None,
+                metrics: None,
+                notifications: None,
+                alerts: None,
+                sentry: None,
+                watchdog: None,
+                leader_election: None,
+                backfill_jobs: None,
+                tuning: None,
+                labels: None,
+                modules: None,
+            },
+            events: vec![],
+            subscribers: vec![],
+            graphql: GraphqlOverrideSettings { enabled: false, override_port: None },
+        }
+    }
+}
+
+/// Every manifest setting is still available through `manifest`, the other methods cover what an
+/// indexer needs to run
+pub struct RindexerBuilder {
+    project_path: PathBuf,
+    manifest: Manifest,
+    events: Vec<RegisterEvent>,
+    subscribers: Vec<StartSubscriber>,
+    graphql: GraphqlOverrideSettings,
+}
+
+impl RindexerBuilder {
+    /// Where relative paths like the contract ABIs and the csv folder resolve from, defaults to
+    /// the current directory
+    pub fn project_path(mut self, project_path: impl Into<PathBuf>) -> Self {
+        self.project_path = project_path.into();
+        self
+    }
+
+    pub fn network(mut self, network: Network) -> Self {
+        self.manifest.networks.push(network);
+        self
+    }
+
+    pub fn contract(mut self, contract: Contract) -> Self {
+        self.manifest.contracts.push(contract);
+        self
+    }
+
+    /// Stores the events in postgres, connecting with the `DATABASE_URL` environment variable
+    pub fn postgres(mut self) -> Self {
+        self.manifest.storage.postgres = Some(PostgresDetails {
+            enabled: true,
+            drop_each_run: None,
+            relationships: None,
+            indexes: None,
+            disable_create_tables: None,
+            materialized_views: None,
+            soft_delete_reorged_rows: None,
+            schema_evolution: None,
+            notify_channel: None,
+            column_encryption: None,
+            entities: None,
+            rollups: None,
+            state_samples: None,
+            block_timestamps: None,
+        });
+        self
+    }
+
+    pub fn csv(mut self, path: &str) -> Self {
+        self.manifest.storage.csv = Some(CsvDetails {
+            enabled: true,
+            path: path.to_string(),
+            disable_create_headers: None,
+        });
+        self
+    }
+
+    /// Serves GraphQL over the postgres tables, on the port of the graphql settings when `None`
+    pub fn graphql(mut self, port: Option<u16>) -> Self {
+        self.graphql = GraphqlOverrideSettings { enabled: true, override_port: port };
+        self
+    }
+
+    /// For the settings without a method of their own
+    pub fn manifest(mut self, configure: impl FnOnce(&mut Manifest)) -> Self {
+        configure(&mut self.manifest);
+        self
+    }
+
+    /// Calls the handler with the `E` events of the contract, `E` is an ethers event such as the
+    /// ones `abigen!` generates. Events which do not decode as `E` are left out.
+    pub fn event<E, F, Fut>(mut self, contract_name: &str, handler: F) -> Self
+    where
+        E: EthEvent + Clone + 'static,
+        F: Fn(Vec<DecodedEvent<E>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = EventCallbackResult<()>> + Send + 'static,
+    {
+        let contract_name = contract_name.to_string();
+        let handler = Arc::new(handler);
+
+        self.events.push(Box::new(move |manifest, network_providers, registry| {
+            let contract = manifest
+                .contracts
+                .iter()
+                .find(|c| c.name == contract_name)
+                .ok_or_else(|| RindexerBuilderError::ContractNotFound(contract_name.clone()))?;
+
+            let decoder: Decoder = Arc::new(|topics: Vec<H256>, data: Bytes| {
+                match <E as EthEvent>::decode_log(&RawLog { topics, data: data.to_vec() }) {
+                    Ok(event) => Arc::new(event) as Arc<dyn Any + Send + Sync>,
+                    Err(error) => Arc::new(error) as Arc<dyn Any + Send + Sync>,
+                }
+            });
+
+            let callback: EventCallbackType = Arc::new(move |events: Vec<EventResult>| {
+                let handler = Arc::clone(&handler);
+                async move {
+                    let events = events
+                        .into_iter()
+                        .filter_map(|item| {
+                            item.decoded_data.downcast::<E>().ok().map(|event| DecodedEvent {
+                                event_data: (*event).clone(),
+                                tx_information: item.tx_information,
+                                spam: item.spam,
+                            })
+                        })
+                        .collect();
+                    handler(events).await
+                }
+                .boxed()
+            });
+
+            let event_name = E::name().into_owned();
+            registry.register_event(EventCallbackRegistryInformation {
+                id: generate_random_id(10),
+                indexer_name: manifest.name.clone(),
+                topic_id: E::signature(),
+                index_event_in_order: contract.is_event_indexed_in_order(&event_name),
+                group_by_block: contract.is_event_grouped_by_block(&event_name),
+                max_concurrent_callbacks: contract.max_concurrent_callbacks(&event_name),
+                failure_policy: contract.failure_policy(&event_name),
+                archive_raw_logs: contract.archive_raw_logs(),
+                event_name,
+                contract: ContractInformation::create(contract, network_providers, decoder)?,
+                callback,
+            });
+
+            Ok(())
+        }));
+        self
+    }
+
+    /// Calls the handler with the `M` messages other handlers publish with
+    /// [`bus::publish`](crate::event::bus::publish), in the order of the events they came from.
+    /// A `Retry` error calls it again with the same messages, `Halt` stops the subscriber.
+    pub fn subscribe<M, F, Fut>(mut self, handler: F) -> Self
+    where
+        M: Clone + Send + Sync + 'static,
+        F: Fn(Vec<BusMessage<M>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = EventCallbackResult<()>> + Send + 'static,
+    {
+        self.subscribers.push(Box::new(move || {
+            // subscribed before indexing starts so no message is published before it
+            let mut subscription = bus::subscribe::<M>();
+            tokio::spawn(async move {
+                loop {
+                    let messages = subscription.recv_batch(SUBSCRIBER_BATCH_SIZE).await;
+                    loop {
+                        match handler(messages.clone()).await {
+                            Ok(()) => break,
+                            Err(HandlerError::Retry(e)) => {
+                                error!("Bus subscriber failed, retrying: {}", e);
+                                if !is_running() {
+                                    return;
+                                }
+                                tokio::time::sleep(Duration::from_secs(1)).await;
+                            }
+                            Err(HandlerError::Skip(e)) => {
+                                error!("Bus subscriber skipped {} messages: {}", messages.len(), e);
+                                break;
+                            }
+                            Err(HandlerError::Halt(e)) => {
+                                error!("Bus subscriber halted: {}", e);
+                                return;
+                            }
+                        }
+                    }
+                }
+            });
+        }));
+        self
+    }
+
+    /// Checks the configuration like a manifest is checked on read and indexes until shutdown
+    pub async fn start(self) -> Result<(), RindexerBuilderError> {
+        let mut manifest = self.manifest;
+        prepare_manifest(&self.project_path, &mut manifest)?;
+
+        let network_providers = CreateNetworkProvider::create(&manifest)?;
+        let mut registry = EventCallbackRegistry::new();
+        for register in self.events {
+            register(&manifest, &network_providers, &mut registry)?;
+        }
+        for start_subscriber in self.subscribers {
+            start_subscriber();
+        }
+
+        start_rindexer_with_manifest(
+            &self.project_path,
+            manifest,
+            None,
+            Some(IndexingDetails { registry }),
+            self.graphql,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
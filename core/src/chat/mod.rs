@@ -2,6 +2,8 @@ mod clients;
 pub use clients::ChatClients;
 
 mod discord;
+mod notifications;
+pub use notifications::NotificationClients;
 mod slack;
 mod telegram;
 mod template;
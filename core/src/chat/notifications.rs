@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use serenity::all::ChannelId;
+use teloxide::{types::ChatId, utils::markdown::escape};
+
+use crate::{
+    chat::{clients::ChatError, discord::DiscordBot, slack::SlackBot, telegram::TelegramBot},
+    manifest::chat::{NotificationDestination, NotificationSink},
+};
+
+enum NotificationClient {
+    Telegram { client: TelegramBot, chat_id: i64 },
+    Discord { client: DiscordBot, channel_id: u64 },
+    Slack { client: SlackBot, channel: String },
+}
+
+/// Clients for the named notification destinations, used for operational messages which are
+/// not tied to an indexed event
+pub struct NotificationClients {
+    clients: HashMap<String, NotificationClient>,
+}
+
+impl NotificationClients {
+    pub fn new(destinations: &[NotificationDestination]) -> Self {
+        let clients = destinations
+            .iter()
+            .map(|destination| {
+                let client = match &destination.sink {
+                    NotificationSink::Telegram { bot_token, chat_id } => {
+                        NotificationClient::Telegram {
+                            client: TelegramBot::new(bot_token),
+                            chat_id: *chat_id,
+                        }
+                    }
+                    NotificationSink::Discord { bot_token, channel_id } => {
+                        NotificationClient::Discord {
+                            client: DiscordBot::new(bot_token),
+                            channel_id: *channel_id,
+                        }
+                    }
+                    NotificationSink::Slack { bot_token, channel } => NotificationClient::Slack {
+                        client: SlackBot::new(bot_token.clone()),
+                        channel: channel.clone(),
+                    },
+                };
+                (destination.name.clone(), client)
+            })
+            .collect();
+
+        Self { clients }
+    }
+
+    pub async fn send(&self, destination: &str, message: &str) -> Result<(), ChatError> {
+        match self.clients.get(destination) {
+            Some(NotificationClient::Telegram { client, chat_id }) => {
+                client.send_message(ChatId(*chat_id), &escape(message)).await?
+            }
+            Some(NotificationClient::Discord { client, channel_id }) => {
+                client.send_message(ChannelId::new(*channel_id), message).await?
+            }
+            Some(NotificationClient::Slack { client, channel }) => {
+                client.send_message(channel, message).await?
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+}
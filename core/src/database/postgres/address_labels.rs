@@ -0,0 +1,311 @@
+use std::{collections::HashMap, fs, path::Path, str::FromStr, sync::Arc};
+
+use ethers::types::Address;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::{
+    database::postgres::client::{PostgresClient, PostgresError},
+    helpers::{camel_to_snake, get_full_path},
+    manifest::labels::LabelSource,
+    types::code::Code,
+    EthereumSqlTypeWrapper,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum AddressLabelsError {
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("Could not read the labels of {0}: {1}")]
+    CouldNotRead(String, String),
+
+    #[error("Label source {0} has an invalid address {1}")]
+    InvalidAddress(String, String),
+}
+
+/// A row of a label file or URL, labels without a network apply to every network
+#[derive(Debug, Deserialize, PartialEq)]
+struct LabelRecord {
+    address: String,
+    label: String,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+pub fn address_labels_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_address_labels", camel_to_snake(indexer_name))
+}
+
+/// An empty network means the label applies to every network, it is part of the key so it can not
+/// be null
+pub fn generate_address_labels_table_sql(indexer_name: &str) -> Code {
+    Code::new(format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            address TEXT NOT NULL,
+            network TEXT NOT NULL DEFAULT '',
+            label TEXT NOT NULL,
+            source TEXT,
+            PRIMARY KEY (address, network)
+        );
+        CREATE INDEX IF NOT EXISTS idx_{indexer_name}_address_labels_source ON {table} (source);
+    "#,
+        table = address_labels_table_name(indexer_name),
+        indexer_name = camel_to_snake(indexer_name),
+    ))
+}
+
+pub fn drop_address_labels_table_sql(indexer_name: &str) -> Code {
+    Code::new(format!("DROP TABLE IF EXISTS {} CASCADE;", address_labels_table_name(indexer_name)))
+}
+
+/// GraphQL exposes a function taking a table row as a computed column, so every address column
+/// gets a `{column}Label` field which prefers the label for the row network
+pub fn address_label_functions_sql(
+    table_name: &str,
+    address_columns: &[String],
+    labels_table: &str,
+) -> String {
+    address_columns
+        .iter()
+        .map(|column| {
+            format!(
+                "CREATE OR REPLACE FUNCTION {table}_{column}_label(e {table}) RETURNS TEXT AS $$ \
+                 SELECT label FROM {labels_table} WHERE address = e.\"{column}\" \
+                 AND network IN ('', e.network) ORDER BY network DESC LIMIT 1 \
+                 $$ LANGUAGE sql STABLE;",
+                table = table_name,
+                column = column,
+                labels_table = labels_table
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Addresses are stored the way the event tables store them so they can be compared
+fn normalize_address(source: &str, address: &str) -> Result<String, AddressLabelsError> {
+    Address::from_str(address.trim())
+        .map(|address| format!("{:?}", address))
+        .map_err(|_| AddressLabelsError::InvalidAddress(source.to_string(), address.to_string()))
+}
+
+fn read_label_file(path: &Path) -> Result<Vec<LabelRecord>, String> {
+    if path.extension().is_some_and(|extension| extension == "csv") {
+        let mut reader = csv::Reader::from_path(path).map_err(|e| e.to_string())?;
+        reader.deserialize().collect::<Result<Vec<LabelRecord>, _>>().map_err(|e| e.to_string())
+    } else {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&contents).map_err(|e| e.to_string())
+    }
+}
+
+async fn fetch_label_url(url: &str) -> Result<Vec<LabelRecord>, String> {
+    reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+async fn replace_source_labels(
+    client: &PostgresClient,
+    table_name: &str,
+    source: &LabelSource,
+    records: Vec<LabelRecord>,
+) -> Result<usize, AddressLabelsError> {
+    let mut addresses = vec![];
+    let mut networks = vec![];
+    let mut labels = vec![];
+    for record in records {
+        addresses.push(normalize_address(&source.name, &record.address)?);
+        networks.push(record.network.or_else(|| source.network.clone()).unwrap_or_default());
+        labels.push(record.label);
+    }
+    let count = addresses.len();
+
+    let source_name = EthereumSqlTypeWrapper::String(source.name.clone());
+    let mut statements =
+        vec![(format!("DELETE FROM {} WHERE source = $1", table_name), vec![source_name.clone()])];
+    if count > 0 {
+        statements.push((
+            format!(
+                "INSERT INTO {} (address, network, label, source) \
+                 SELECT address, network, label, $4 FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[]) \
+                 AS labels(address, network, label) \
+                 ON CONFLICT (address, network) DO UPDATE SET label = EXCLUDED.label, source = EXCLUDED.source",
+                table_name
+            ),
+            vec![
+                EthereumSqlTypeWrapper::VecString(addresses),
+                EthereumSqlTypeWrapper::VecString(networks),
+                EthereumSqlTypeWrapper::VecString(labels),
+                source_name,
+            ],
+        ));
+    }
+
+    client.execute_in_transaction(&statements).await?;
+    Ok(count)
+}
+
+/// Loads every label source into the labels table. A file which can not be read fails the start
+/// as the manifest is wrong, a URL which can not be reached keeps the labels it loaded last time.
+pub async fn load_address_labels(
+    client: &PostgresClient,
+    project_path: &Path,
+    indexer_name: &str,
+    sources: &[LabelSource],
+) -> Result<(), AddressLabelsError> {
+    let table_name = address_labels_table_name(indexer_name);
+    for source in sources {
+        let records = if let Some(file) = &source.file {
+            let path = get_full_path(project_path, file).map_err(|e| {
+                AddressLabelsError::CouldNotRead(source.name.clone(), e.to_string())
+            })?;
+            read_label_file(&path)
+                .map_err(|e| AddressLabelsError::CouldNotRead(source.name.clone(), e))?
+        } else if let Some(url) = &source.url {
+            match fetch_label_url(url).await {
+                Ok(records) => records,
+                Err(e) => {
+                    error!(
+                        "Could not fetch the labels of {} - keeping the last ones: {}",
+                        source.name, e
+                    );
+                    continue;
+                }
+            }
+        } else {
+            continue;
+        };
+
+        let count = replace_source_labels(client, &table_name, source, records).await?;
+        info!("Loaded {} address labels from {}", count, source.name);
+    }
+
+    Ok(())
+}
+
+/// Address labels for handlers to tag what they index with, labels set here are kept until a
+/// label source loads a label for the same address
+#[derive(Clone)]
+pub struct AddressLabels {
+    client: Arc<PostgresClient>,
+    table_name: String,
+}
+
+impl AddressLabels {
+    pub fn new(client: Arc<PostgresClient>, indexer_name: &str) -> Self {
+        Self { client, table_name: address_labels_table_name(indexer_name) }
+    }
+
+    /// The label for the network, falling back to the label for every network
+    pub async fn get(
+        &self,
+        address: &Address,
+        network: &str,
+    ) -> Result<Option<String>, AddressLabelsError> {
+        Ok(self.get_many(&[*address], network).await?.remove(address))
+    }
+
+    pub async fn get_many(
+        &self,
+        addresses: &[Address],
+        network: &str,
+    ) -> Result<HashMap<Address, String>, AddressLabelsError> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT DISTINCT ON (address) address, label FROM {} \
+                     WHERE address = ANY($1) AND network IN ('', $2) ORDER BY address, network DESC",
+                    self.table_name
+                ),
+                &[
+                    &EthereumSqlTypeWrapper::VecString(
+                        addresses.iter().map(|address| format!("{:?}", address)).collect(),
+                    ),
+                    &network,
+                ],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let address: String = row.get(0);
+                Address::from_str(&address).ok().map(|address| (address, row.get(1)))
+            })
+            .collect())
+    }
+
+    /// Sets the label, for every network when `network` is not given
+    pub async fn set(
+        &self,
+        address: &Address,
+        network: Option<&str>,
+        label: &str,
+    ) -> Result<(), AddressLabelsError> {
+        self.client
+            .execute(
+                &format!(
+                    "INSERT INTO {} (address, network, label) VALUES ($1, $2, $3) \
+                     ON CONFLICT (address, network) DO UPDATE SET label = EXCLUDED.label, source = NULL",
+                    self.table_name
+                ),
+                &[&format!("{:?}", address), &network.unwrap_or_default(), &label],
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_read_label_file() {
+        let dir = tempdir().unwrap();
+        let csv_path = dir.path().join("exchanges.csv");
+        fs::write(
+            &csv_path,
+            "address,label,network\n0x28C6c06298d514Db089934071355E5743bf21d60,Binance 14,ethereum\n0x21a31Ee1afC51d94C2eFcCAa2092aD1028285549,Binance 15,\n",
+        )
+        .unwrap();
+        let json_path = dir.path().join("routers.json");
+        fs::write(
+            &json_path,
+            r#"[{"address": "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D", "label": "Uniswap V2 Router"}]"#,
+        )
+        .unwrap();
+
+        let records = read_label_file(&csv_path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].network.as_deref(), Some("ethereum"));
+        assert_eq!(records[1].label, "Binance 15");
+
+        let records = read_label_file(&json_path).unwrap();
+        assert_eq!(
+            records,
+            vec![LabelRecord {
+                address: "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D".to_string(),
+                label: "Uniswap V2 Router".to_string(),
+                network: None,
+            }]
+        );
+
+        assert_eq!(
+            normalize_address("routers", "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D").unwrap(),
+            "0x7a250d5630b4cf539739df2c5dacb4c659f2488d"
+        );
+        assert!(normalize_address("routers", "not an address").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use ethers::{providers::ProviderError, types::U64};
+use futures::{stream, StreamExt, TryStreamExt};
+use tracing::{error, info};
+
+use crate::{
+    database::postgres::client::{PostgresClient, PostgresError},
+    helpers::camel_to_snake,
+    indexer::reorg_safe_distance_for_chain,
+    is_running,
+    manifest::{contract::Contract, storage::BlockTimestamps},
+    provider::JsonRpcCachedProvider,
+    EthereumSqlTypeWrapper,
+};
+
+const FILL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Bounds the blocks a single fill fetches, a fill catching up runs again straight away
+const MAX_BLOCKS_PER_FILL: usize = 500;
+
+/// How many block timestamps are fetched at the same time
+const TIMESTAMP_CONCURRENCY: usize = 10;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BlockTimestampsError {
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("{0}")]
+    ProviderError(#[from] ProviderError),
+
+    #[error("Invalid block number stored: {0}")]
+    InvalidBlock(String),
+}
+
+pub fn block_timestamps_schema_name(indexer_name: &str) -> String {
+    format!("{}_block_timestamps", camel_to_snake(indexer_name))
+}
+
+fn block_timestamps_table_name(indexer_name: &str) -> String {
+    format!("{}.blocks", block_timestamps_schema_name(indexer_name))
+}
+
+/// GraphQL exposes `block_at_time` as the `blockAtTime(network, at)` query
+pub fn generate_block_timestamps_tables_sql(indexer_name: &str) -> String {
+    format!(
+        r#"
+        CREATE SCHEMA IF NOT EXISTS {schema};
+        CREATE TABLE IF NOT EXISTS {table} (
+            network TEXT NOT NULL,
+            block_number NUMERIC NOT NULL,
+            block_timestamp TIMESTAMPTZ NOT NULL,
+            PRIMARY KEY (network, block_number)
+        );
+        CREATE INDEX IF NOT EXISTS blocks_network_timestamp_idx ON {table} (network, block_timestamp);
+        CREATE OR REPLACE FUNCTION {schema}.block_at_time(network TEXT, at TIMESTAMPTZ) RETURNS NUMERIC AS $$
+            SELECT b.block_number FROM {table} b
+            WHERE b.network = $1 AND b.block_timestamp <= $2
+            ORDER BY b.block_timestamp DESC, b.block_number DESC
+            LIMIT 1
+        $$ LANGUAGE sql STABLE;
+        COMMENT ON FUNCTION {schema}.block_at_time(TEXT, TIMESTAMPTZ) IS
+            E'The last recorded block of the network at or before the time, blocks are recorded every few blocks so it can be up to that many blocks behind';
+    "#,
+        schema = block_timestamps_schema_name(indexer_name),
+        table = block_timestamps_table_name(indexer_name)
+    )
+}
+
+pub fn drop_block_timestamps_tables_sql(indexer_name: &str) -> String {
+    format!("DROP SCHEMA IF EXISTS {} CASCADE;", block_timestamps_schema_name(indexer_name))
+}
+
+/// The blocks recorded are the multiples of `every_blocks`, so a run which starts from a
+/// different block carries on from the same anchors
+fn blocks_to_record(from_block: u64, to_block: u64, every_blocks: u64) -> Vec<u64> {
+    let first = from_block - from_block % every_blocks;
+    (first..=to_block).step_by(every_blocks as usize).take(MAX_BLOCKS_PER_FILL).collect()
+}
+
+async fn last_recorded_block(
+    client: &PostgresClient,
+    table_name: &str,
+    network: &str,
+) -> Result<Option<u64>, BlockTimestampsError> {
+    let row = client
+        .query_one_or_none(
+            &format!("SELECT MAX(block_number)::TEXT FROM {} WHERE network = $1", table_name),
+            &[&network],
+        )
+        .await?;
+    let block: Option<String> = row.and_then(|row| row.get(0));
+    block
+        .map(|block| block.parse::<u64>().map_err(|_| BlockTimestampsError::InvalidBlock(block)))
+        .transpose()
+}
+
+/// Records the timestamps after the last recorded block up to the reorg safe distance from the
+/// head, returns whether it stopped short so a network which is behind fills again straight away
+async fn fill_network(
+    client: &PostgresClient,
+    table_name: &str,
+    network: &str,
+    provider: &JsonRpcCachedProvider,
+    every_blocks: u64,
+    start_block: Option<u64>,
+) -> Result<bool, BlockTimestampsError> {
+    let chain_id = provider.get_chain_id().await?;
+    let safe_head = provider
+        .get_block_number()
+        .await?
+        .saturating_sub(reorg_safe_distance_for_chain(&chain_id))
+        .as_u64();
+
+    let from_block = match last_recorded_block(client, table_name, network).await? {
+        Some(last) => last + every_blocks,
+        None => start_block.unwrap_or(safe_head),
+    };
+    if from_block > safe_head {
+        return Ok(false);
+    }
+
+    let blocks = blocks_to_record(from_block, safe_head, every_blocks);
+    let behind = blocks.last().is_some_and(|last| last + every_blocks <= safe_head);
+
+    let (block_numbers, timestamps): (Vec<String>, Vec<String>) = stream::iter(blocks)
+        .map(|block_number| async move {
+            let block = provider.get_block(U64::from(block_number)).await?.ok_or_else(|| {
+                ProviderError::CustomError(format!("Block {} not found", block_number))
+            })?;
+            Ok::<_, ProviderError>((block_number.to_string(), block.timestamp.to_string()))
+        })
+        .buffer_unordered(TIMESTAMP_CONCURRENCY)
+        .try_collect::<Vec<_>>()
+        .await?
+        .into_iter()
+        .unzip();
+
+    client
+        .execute(
+            &format!(
+                "INSERT INTO {} (network, block_number, block_timestamp) \
+                 SELECT $1, b.block_number::NUMERIC, to_timestamp(b.ts::BIGINT) \
+                 FROM UNNEST($2::TEXT[], $3::TEXT[]) AS b(block_number, ts) \
+                 ON CONFLICT (network, block_number) DO NOTHING",
+                table_name
+            ),
+            &[
+                &network,
+                &EthereumSqlTypeWrapper::VecString(block_numbers),
+                &EthereumSqlTypeWrapper::VecString(timestamps),
+            ],
+        )
+        .await?;
+
+    Ok(behind)
+}
+
+async fn run_network(
+    client: Arc<PostgresClient>,
+    table_name: String,
+    network: String,
+    provider: Arc<JsonRpcCachedProvider>,
+    every_blocks: u64,
+    start_block: Option<u64>,
+) {
+    while is_running() {
+        match fill_network(&client, &table_name, &network, &provider, every_blocks, start_block)
+            .await
+        {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(e) => error!("Could not record the block timestamps of {}: {}", network, e),
+        }
+        tokio::time::sleep(FILL_INTERVAL).await;
+    }
+}
+
+/// Records the block timestamps of every indexed network in the background, starting from the
+/// earliest start block of the contracts on the network - only the indexer which owns the tables
+/// should run this
+pub fn start_block_timestamps_recorder(
+    client: Arc<PostgresClient>,
+    indexer_name: &str,
+    settings: &BlockTimestamps,
+    contracts: &[Contract],
+    providers: &HashMap<String, Arc<JsonRpcCachedProvider>>,
+) {
+    let table_name = block_timestamps_table_name(indexer_name);
+    for (network, provider) in providers {
+        let start_block = contracts
+            .iter()
+            .flat_map(|contract| &contract.details)
+            .filter(|details| &details.network == network)
+            .filter_map(|details| details.start_block.map(|block| block.as_u64()))
+            .min();
+
+        info!(
+            "Recording the block timestamps of {} every {} blocks",
+            network,
+            settings.every_blocks()
+        );
+        tokio::spawn(run_network(
+            Arc::clone(&client),
+            table_name.clone(),
+            network.clone(),
+            Arc::clone(provider),
+            settings.every_blocks(),
+            start_block,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_to_record_are_multiples_of_every_blocks() {
+        assert_eq!(blocks_to_record(1_050, 1_420, 100), vec![1_000, 1_100, 1_200, 1_300, 1_400]);
+        assert_eq!(blocks_to_record(7, 7, 1), vec![7]);
+        assert_eq!(blocks_to_record(0, 10_000_000, 1).len(), MAX_BLOCKS_PER_FILL);
+    }
+}
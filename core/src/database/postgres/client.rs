@@ -1,6 +1,6 @@
 use std::{env, future::Future, time::Duration};
 
-use bb8::{Pool, RunError};
+use bb8::{Pool, PooledConnection, RunError};
 use bb8_postgres::PostgresConnectionManager;
 use bytes::Buf;
 use dotenv::dotenv;
@@ -25,6 +25,17 @@ pub fn connection_string() -> Result<String, env::VarError> {
     Ok(connection)
 }
 
+/// The database the GraphQL and arrow flight servers read from, a read replica set in
+/// `DATABASE_READ_URL` keeps heavy queries off the primary the indexer writes to. Reads from a
+/// replica lag the primary by its replication delay. Falls back to `DATABASE_URL`.
+pub fn read_connection_string() -> Result<String, env::VarError> {
+    dotenv().ok();
+    match env::var("DATABASE_READ_URL") {
+        Ok(connection) if !connection.is_empty() => Ok(connection),
+        _ => connection_string(),
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum PostgresConnectionError {
     #[error("The database connection string is wrong please check your environment: {0}")]
@@ -91,8 +102,19 @@ pub struct PostgresClient {
 
 impl PostgresClient {
     pub async fn new() -> Result<Self, PostgresConnectionError> {
-        async fn _new(disable_ssl: bool) -> Result<PostgresClient, PostgresConnectionError> {
-            let connection_str = connection_string()?;
+        Self::connect(connection_string()?).await
+    }
+
+    /// A client for the read replica in `DATABASE_READ_URL`, the primary when there is none
+    pub async fn new_read_replica() -> Result<Self, PostgresConnectionError> {
+        Self::connect(read_connection_string()?).await
+    }
+
+    async fn connect(connection_str: String) -> Result<Self, PostgresConnectionError> {
+        async fn _new(
+            connection_str: &str,
+            disable_ssl: bool,
+        ) -> Result<PostgresClient, PostgresConnectionError> {
             let mut config: Config = connection_str
                 .parse()
                 .map_err(|_| PostgresConnectionError::CouldNotParseConnectionString)?;
@@ -118,7 +140,7 @@ impl PostgresClient {
                             config.get_ssl_mode() != SslMode::Disable &&
                             !connection_str.contains("sslmode=require")
                         {
-                            return Box::pin(_new(true)).await;
+                            return Box::pin(_new(connection_str, true)).await;
                         }
                         error!("Error connecting to database: {}", e);
                         return Err(PostgresConnectionError::CanNotConnectToDatabase);
@@ -153,7 +175,16 @@ impl PostgresClient {
             Ok(PostgresClient { pool })
         }
 
-        _new(false).await
+        _new(&connection_str, false).await
+    }
+
+    /// Takes a connection out of the pool for good, for session state like advisory locks which
+    /// has to stay on the same connection
+    pub async fn dedicated_connection(
+        &self,
+    ) -> Result<PooledConnection<'static, PostgresConnectionManager<MakeTlsConnector>>, PostgresError>
+    {
+        Ok(self.pool.get_owned().await?)
     }
 
     pub async fn batch_execute(&self, sql: &str) -> Result<(), PostgresError> {
@@ -205,6 +236,51 @@ impl PostgresClient {
         Ok(result)
     }
 
+    /// Runs every statement inside a single transaction, nothing is written if any fail
+    pub async fn execute_in_transaction(
+        &self,
+        statements: &[(String, Vec<EthereumSqlTypeWrapper>)],
+    ) -> Result<(), PostgresError> {
+        let mut conn = self.pool.get().await?;
+        let transaction = conn.transaction().await.map_err(PostgresError::PgError)?;
+
+        for (query, params) in statements {
+            let params_refs: Vec<&(dyn ToSql + Sync)> =
+                params.iter().map(|param| param as &(dyn ToSql + Sync)).collect();
+            transaction
+                .execute(query.as_str(), &params_refs)
+                .await
+                .map_err(PostgresError::PgError)?;
+        }
+
+        transaction.commit().await.map_err(PostgresError::PgError)?;
+        Ok(())
+    }
+
+    /// Runs the script and then the statements inside a single transaction, nothing is written if
+    /// any fail
+    pub async fn batch_execute_in_transaction(
+        &self,
+        sql: &str,
+        statements: &[(String, Vec<EthereumSqlTypeWrapper>)],
+    ) -> Result<(), PostgresError> {
+        let mut conn = self.pool.get().await?;
+        let transaction = conn.transaction().await.map_err(PostgresError::PgError)?;
+
+        transaction.batch_execute(sql).await.map_err(PostgresError::PgError)?;
+        for (query, params) in statements {
+            let params_refs: Vec<&(dyn ToSql + Sync)> =
+                params.iter().map(|param| param as &(dyn ToSql + Sync)).collect();
+            transaction
+                .execute(query.as_str(), &params_refs)
+                .await
+                .map_err(PostgresError::PgError)?;
+        }
+
+        transaction.commit().await.map_err(PostgresError::PgError)?;
+        Ok(())
+    }
+
     pub async fn query<T>(
         &self,
         query: &T,
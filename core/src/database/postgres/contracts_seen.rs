@@ -0,0 +1,66 @@
+/// The contracts seen table of an event table, filter contracts index every address emitting the
+/// event so the table is how many and which contracts they picked up
+pub fn contracts_seen_table_name(table_name: &str) -> String {
+    format!("{}_contracts_seen", table_name)
+}
+
+/// Indexes the emitting address and keeps a row per contract with the first and last block it
+/// was seen in and how many events it emitted, filled from a statement trigger so bulk inserts
+/// update it once. Rows removed by a reorg are not taken off the counts. A table which already
+/// has rows is counted once when the contracts seen table is created.
+pub fn contracts_seen_sql(table_name: &str, index_prefix: &str) -> String {
+    let seen_table = contracts_seen_table_name(table_name);
+    format!(
+        r#"CREATE INDEX IF NOT EXISTS {index_prefix}_contract_address_idx ON {table} (contract_address);
+        DO $$ BEGIN
+            IF to_regclass('{seen}') IS NULL THEN
+                CREATE TABLE {seen} (
+                    network VARCHAR(50) NOT NULL,
+                    contract_address CHAR(66) NOT NULL,
+                    first_seen_block NUMERIC NOT NULL,
+                    last_seen_block NUMERIC NOT NULL,
+                    events BIGINT NOT NULL,
+                    PRIMARY KEY (network, contract_address)
+                );
+                INSERT INTO {seen} (network, contract_address, first_seen_block, last_seen_block, events)
+                SELECT network, contract_address, MIN(block_number), MAX(block_number), COUNT(*)
+                FROM {table} GROUP BY network, contract_address;
+            END IF;
+        END $$;
+        CREATE OR REPLACE FUNCTION {seen}_record() RETURNS trigger AS $$
+        BEGIN
+            INSERT INTO {seen} AS seen (network, contract_address, first_seen_block, last_seen_block, events)
+            SELECT network, contract_address, MIN(block_number), MAX(block_number), COUNT(*)
+            FROM inserted GROUP BY network, contract_address
+            ON CONFLICT (network, contract_address) DO UPDATE SET
+                first_seen_block = LEAST(seen.first_seen_block, EXCLUDED.first_seen_block),
+                last_seen_block = GREATEST(seen.last_seen_block, EXCLUDED.last_seen_block),
+                events = seen.events + EXCLUDED.events;
+            RETURN NULL;
+        END $$ LANGUAGE plpgsql;
+        DROP TRIGGER IF EXISTS rindexer_contracts_seen ON {table};
+        CREATE TRIGGER rindexer_contracts_seen AFTER INSERT ON {table}
+            REFERENCING NEW TABLE AS inserted FOR EACH STATEMENT EXECUTE FUNCTION {seen}_record();
+        COMMENT ON TABLE {seen} IS E'The contracts which emitted the event, the total count is the distinct contracts seen';"#,
+        index_prefix = index_prefix,
+        table = table_name,
+        seen = seen_table
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contracts_seen_sql() {
+        let sql = contracts_seen_sql("indexer_erc20_filter.transfer", "transfer");
+        assert!(sql.starts_with(
+            "CREATE INDEX IF NOT EXISTS transfer_contract_address_idx ON indexer_erc20_filter.transfer (contract_address);"
+        ));
+        assert!(sql.contains("CREATE TABLE indexer_erc20_filter.transfer_contracts_seen ("));
+        assert!(sql.contains(
+            "FOR EACH STATEMENT EXECUTE FUNCTION indexer_erc20_filter.transfer_contracts_seen_record();"
+        ));
+    }
+}
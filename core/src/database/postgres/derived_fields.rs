@@ -0,0 +1,69 @@
+use crate::{
+    database::postgres::{entities::entity_table_name, generate::generate_event_table_full_name},
+    helpers::camel_to_snake,
+    manifest::{
+        contract::Contract,
+        graphql::{DerivedField, DerivedFieldTable},
+        storage::Entity,
+    },
+};
+
+/// The table and column a side of a derived field reads, event inputs are stored snake cased
+fn table_and_column(
+    indexer_name: &str,
+    contracts: &[Contract],
+    entities: &[Entity],
+    table: &DerivedFieldTable,
+) -> Option<(String, String)> {
+    if let Some(entity_name) = &table.entity {
+        let entity = entities.iter().find(|e| e.name == *entity_name)?;
+        return Some((entity_table_name(indexer_name, entity), table.column.clone()));
+    }
+
+    let contract = contracts.iter().find(|c| Some(&c.name) == table.contract_name.as_ref())?;
+    Some((
+        generate_event_table_full_name(
+            indexer_name,
+            &contract.table_name_readonly(),
+            table.event_name.as_deref()?,
+        ),
+        camel_to_snake(&table.column),
+    ))
+}
+
+/// GraphQL exposes a function taking a table row and returning a set of rows as a paginated
+/// connection, so every derived field becomes a `{name}` field on the parent rows
+pub fn derived_field_functions_sql(
+    indexer_name: &str,
+    contracts: &[Contract],
+    entities: &[Entity],
+    derived_fields: &[DerivedField],
+) -> String {
+    derived_fields
+        .iter()
+        .filter_map(|field| {
+            let (parent_table, parent_column) =
+                table_and_column(indexer_name, contracts, entities, &field.parent)?;
+            let (children_table, children_column) =
+                table_and_column(indexer_name, contracts, entities, &field.children)?;
+            let function_name = format!("{}_{}", parent_table, camel_to_snake(&field.name));
+            let (_, children_table_name) = children_table.split_once('.')?;
+
+            Some(format!(
+                "CREATE OR REPLACE FUNCTION {function}(p {parent}) RETURNS SETOF {children} AS $$ \
+                 SELECT c.* FROM {children} c WHERE c.\"{children_column}\" = p.\"{parent_column}\" \
+                 AND c.network = p.network \
+                 $$ LANGUAGE sql STABLE;\
+                 COMMENT ON FUNCTION {function}({parent}) IS E'@sortable\\n@filterable';\
+                 CREATE INDEX IF NOT EXISTS {children_table_name}_{children_column}_derived_idx ON {children} (network, \"{children_column}\");",
+                function = function_name,
+                parent = parent_table,
+                children = children_table,
+                children_column = children_column,
+                parent_column = parent_column,
+                children_table_name = children_table_name,
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,161 @@
+use crate::{
+    database::postgres::{
+        client::{PostgresClient, PostgresError},
+        generate::DECODED_PARAMS_COLUMN,
+    },
+    helpers::camel_to_snake,
+    manifest::contract::{EncryptedInputs, ParamsStorage},
+    types::code::Code,
+};
+
+pub fn column_encryption_key_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_column_encryption_key", camel_to_snake(indexer_name))
+}
+
+/// The key table holds a single row and no role but its owner can read it, the encrypt and
+/// decrypt functions read it as its owner
+pub fn generate_column_encryption_sql(indexer_name: &str) -> Code {
+    Code::new(format!(
+        r#"
+        CREATE EXTENSION IF NOT EXISTS pgcrypto;
+        CREATE TABLE IF NOT EXISTS {table} (
+            id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+            key TEXT NOT NULL
+        );
+        REVOKE ALL ON {table} FROM PUBLIC;
+    "#,
+        table = column_encryption_key_table_name(indexer_name)
+    ))
+}
+
+pub async fn store_column_encryption_key(
+    client: &PostgresClient,
+    indexer_name: &str,
+    key: &str,
+) -> Result<(), PostgresError> {
+    client
+        .execute(
+            &format!(
+                "INSERT INTO {} (id, key) VALUES (TRUE, $1) ON CONFLICT (id) DO UPDATE SET key = EXCLUDED.key",
+                column_encryption_key_table_name(indexer_name)
+            ),
+            &[&key],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Adds the ciphertext columns and the trigger which moves the inputs into them before a row is
+/// written, so rows inserted with COPY are encrypted as well. GraphQL exposes the decrypt
+/// functions as `{column}Decrypted` fields which only the decrypt roles can execute.
+pub fn encrypted_inputs_sql(
+    table_name: &str,
+    key_table: &str,
+    inputs: &[String],
+    params_storage: ParamsStorage,
+    decrypt_roles: &[String],
+) -> String {
+    let mut encrypt_statements: Vec<String> = vec![];
+    let mut sql: Vec<String> = vec![];
+
+    for input in inputs {
+        let column = camel_to_snake(input);
+        let encrypted_column = EncryptedInputs::encrypted_column_name(input);
+        let input_literal = input.replace('\'', "''");
+
+        sql.push(format!(
+            "ALTER TABLE {} ADD COLUMN IF NOT EXISTS \"{}\" BYTEA;",
+            table_name, encrypted_column
+        ));
+
+        let plaintext = if params_storage.typed_columns() {
+            format!("NEW.\"{}\"::text", column)
+        } else {
+            format!("NEW.{}->>'{}'", DECODED_PARAMS_COLUMN, input_literal)
+        };
+        encrypt_statements.push(format!(
+            "NEW.\"{}\" := pgp_sym_encrypt({}, encryption_key);",
+            encrypted_column, plaintext
+        ));
+        if params_storage.typed_columns() {
+            encrypt_statements.push(format!("NEW.\"{}\" := NULL;", column));
+        }
+        if params_storage.jsonb() {
+            encrypt_statements.push(format!(
+                "NEW.{column} := NEW.{column} - '{input}';",
+                column = DECODED_PARAMS_COLUMN,
+                input = input_literal
+            ));
+        }
+
+        let decrypt_function = format!("{}_{}_decrypted", table_name, column);
+        sql.push(format!(
+            "CREATE OR REPLACE FUNCTION {function}(e {table}) RETURNS TEXT AS $$ \
+             SELECT pgp_sym_decrypt(e.\"{encrypted_column}\", key) FROM {key_table} \
+             $$ LANGUAGE sql STABLE SECURITY DEFINER SET search_path = public, pg_temp;",
+            function = decrypt_function,
+            table = table_name,
+            encrypted_column = encrypted_column,
+            key_table = key_table
+        ));
+        sql.push(format!(
+            "REVOKE ALL ON FUNCTION {}({}) FROM PUBLIC;",
+            decrypt_function, table_name
+        ));
+        for role in decrypt_roles {
+            sql.push(format!(
+                "GRANT EXECUTE ON FUNCTION {}({}) TO \"{}\";",
+                decrypt_function, table_name, role
+            ));
+        }
+    }
+
+    let encrypt_function = format!("{}_encrypt_inputs", table_name);
+    sql.push(format!(
+        "CREATE OR REPLACE FUNCTION {function}() RETURNS TRIGGER AS $$ \
+         DECLARE encryption_key TEXT := (SELECT key FROM {key_table}); \
+         BEGIN \
+         {statements} \
+         RETURN NEW; \
+         END; \
+         $$ LANGUAGE plpgsql SECURITY DEFINER SET search_path = public, pg_temp;",
+        function = encrypt_function,
+        key_table = key_table,
+        statements = encrypt_statements.join(" ")
+    ));
+    sql.push(format!(
+        "DROP TRIGGER IF EXISTS rindexer_encrypt_inputs ON {table};\n\
+         CREATE TRIGGER rindexer_encrypt_inputs BEFORE INSERT ON {table} FOR EACH ROW \
+         EXECUTE FUNCTION {function}();",
+        table = table_name,
+        function = encrypt_function
+    ));
+
+    sql.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypted_inputs_are_moved_out_of_the_plaintext_columns() {
+        let sql = encrypted_inputs_sql(
+            "indexer_orders.order_placed",
+            "rindexer_internal.indexer_column_encryption_key",
+            &["referenceId".to_string()],
+            ParamsStorage::Both,
+            &["internal_api".to_string()],
+        );
+
+        assert!(sql.contains("ADD COLUMN IF NOT EXISTS \"reference_id_encrypted\" BYTEA"));
+        assert!(sql.contains(
+            "NEW.\"reference_id_encrypted\" := pgp_sym_encrypt(NEW.\"reference_id\"::text, encryption_key); \
+             NEW.\"reference_id\" := NULL; NEW.decoded_params := NEW.decoded_params - 'referenceId';"
+        ));
+        assert!(sql.contains(
+            "GRANT EXECUTE ON FUNCTION indexer_orders.order_placed_reference_id_decrypted(indexer_orders.order_placed) TO \"internal_api\";"
+        ));
+    }
+}
@@ -0,0 +1,744 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use ethers::types::{I256, U64};
+use once_cell::sync::Lazy;
+use serde_json::{Map, Value};
+use tokio::sync::Mutex;
+use tracing::error;
+
+use crate::{
+    database::postgres::{
+        client::{PostgresClient, PostgresError},
+        generate::generate_event_table_full_name,
+    },
+    helpers::camel_to_snake,
+    indexer::reorg_safe_distance_for_chain,
+    is_running,
+    manifest::{
+        contract::Contract,
+        storage::{Entity, EntityColumnType},
+    },
+    provider::JsonRpcCachedProvider,
+    EthereumSqlTypeWrapper,
+};
+
+const BUILD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Bounds the rows a single build reads, a build catching up runs again straight away
+const MAX_BLOCKS_PER_BUILD: u64 = 10_000;
+
+/// A build and a rollback of the same entities can not interleave, a build reading rows the
+/// rollback removes would otherwise write them back
+static BUILD_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(thiserror::Error, Debug)]
+pub enum EntitiesError {
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("Entity {0} could not apply {1}: {2}")]
+    InvalidValue(String, String, String),
+
+    #[error("Could not parse block {0}")]
+    InvalidBlock(String),
+}
+
+pub fn entities_schema_name(indexer_name: &str) -> String {
+    format!("{}_entities", camel_to_snake(indexer_name))
+}
+
+pub fn entity_table_name(indexer_name: &str, entity: &Entity) -> String {
+    format!("{}.{}", entities_schema_name(indexer_name), camel_to_snake(&entity.name))
+}
+
+fn cursors_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_entity_cursors", camel_to_snake(indexer_name))
+}
+
+fn history_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_entity_history", camel_to_snake(indexer_name))
+}
+
+fn quoted_columns<'a>(columns: impl Iterator<Item = &'a String>) -> String {
+    columns.map(|column| format!("\"{}\"", column)).collect::<Vec<_>>().join(", ")
+}
+
+/// The `SET` of an upsert replacing every column of the entity row
+fn update_columns_sql(entity: &Entity) -> String {
+    entity
+        .columns
+        .iter()
+        .map(|column| column.name.as_str())
+        .chain(std::iter::once("last_updated_block"))
+        .map(|column| format!("\"{column}\" = EXCLUDED.\"{column}\"", column = column))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn generate_entities_tables_sql(indexer_name: &str, entities: &[Entity]) -> String {
+    if entities.is_empty() {
+        return String::new();
+    }
+
+    let mut sql = format!(
+        r#"
+        CREATE SCHEMA IF NOT EXISTS {schema};
+        CREATE TABLE IF NOT EXISTS {cursors} (
+            entity TEXT NOT NULL,
+            network TEXT NOT NULL,
+            block_number NUMERIC NOT NULL,
+            PRIMARY KEY (entity, network)
+        );
+        CREATE TABLE IF NOT EXISTS {history} (
+            id BIGSERIAL PRIMARY KEY,
+            entity TEXT NOT NULL,
+            network TEXT NOT NULL,
+            key JSONB NOT NULL,
+            block_number NUMERIC NOT NULL,
+            previous_value JSONB
+        );
+        CREATE INDEX IF NOT EXISTS idx_{indexer_name}_entity_history_block ON {history} (entity, network, block_number);
+        "#,
+        schema = entities_schema_name(indexer_name),
+        cursors = cursors_table_name(indexer_name),
+        history = history_table_name(indexer_name),
+        indexer_name = camel_to_snake(indexer_name),
+    );
+
+    for entity in entities {
+        let columns = entity
+            .columns
+            .iter()
+            .map(|column| {
+                format!(
+                    "\"{}\" {}{}",
+                    column.name,
+                    column.column_type.sql_type(),
+                    if entity.key.contains(&column.name) { " NOT NULL" } else { "" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        sql.push_str(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (network VARCHAR(50) NOT NULL, {}, last_updated_block NUMERIC NOT NULL, PRIMARY KEY (network, {}));",
+            entity_table_name(indexer_name, entity),
+            columns,
+            quoted_columns(entity.key.iter())
+        ));
+    }
+
+    sql
+}
+
+pub fn drop_entities_tables_sql(indexer_name: &str) -> String {
+    format!(
+        "DROP SCHEMA IF EXISTS {} CASCADE;DROP TABLE IF EXISTS {} CASCADE;DROP TABLE IF EXISTS {} CASCADE;",
+        entities_schema_name(indexer_name),
+        cursors_table_name(indexer_name),
+        history_table_name(indexer_name)
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operation {
+    Set,
+    Add,
+    Subtract,
+}
+
+/// An event table an entity is built from, the values of a row are the key columns followed by
+/// the columns of the updates
+struct EntitySource {
+    table_name: String,
+    last_synced_table_name: String,
+    networks: Vec<String>,
+    key_columns: Vec<String>,
+    updates: Vec<(usize, Operation, String)>,
+}
+
+impl EntitySource {
+    fn value_columns(&self) -> impl Iterator<Item = &String> {
+        self.key_columns.iter().chain(self.updates.iter().map(|(_, _, column)| column))
+    }
+}
+
+fn entity_sources(
+    indexer_name: &str,
+    contracts: &[Contract],
+    entity: &Entity,
+) -> Vec<EntitySource> {
+    let column_index = |name: &str| entity.columns.iter().position(|column| column.name == name);
+
+    entity
+        .events
+        .iter()
+        .filter_map(|event| {
+            let contract = contracts.iter().find(|c| c.name == event.contract_name)?;
+            let mut updates = vec![];
+            for (mapping, operation) in [
+                (&event.set, Operation::Set),
+                (&event.add, Operation::Add),
+                (&event.subtract, Operation::Subtract),
+            ] {
+                for (column, input) in mapping.iter().flatten() {
+                    updates.push((column_index(column)?, operation, camel_to_snake(input)));
+                }
+            }
+
+            Some(EntitySource {
+                table_name: generate_event_table_full_name(
+                    indexer_name,
+                    &contract.table_name_readonly(),
+                    &event.event_name,
+                ),
+                last_synced_table_name: format!(
+                    "rindexer_internal.{}_{}_{}",
+                    camel_to_snake(indexer_name),
+                    camel_to_snake(&contract.before_modify_name_if_filter_readonly()),
+                    camel_to_snake(&event.event_name)
+                ),
+                networks: contract.details.iter().map(|d| d.network.clone()).collect(),
+                key_columns: entity.key.iter().map(|key| camel_to_snake(&event.key[key])).collect(),
+                updates,
+            })
+        })
+        .collect()
+}
+
+/// The new value of a column, numbers are kept as text so they keep their full precision
+fn apply_operation(
+    column_type: EntityColumnType,
+    current: Option<&str>,
+    operation: Operation,
+    value: &str,
+) -> Result<String, String> {
+    if operation == Operation::Set || column_type != EntityColumnType::Number {
+        return Ok(value.to_string());
+    }
+
+    let parse = |value: &str| {
+        I256::from_dec_str(value).map_err(|e| format!("{} is not a number: {}", value, e))
+    };
+    let current = parse(current.unwrap_or("0"))?;
+    let value = parse(value)?;
+    let result = match operation {
+        Operation::Add => current.checked_add(value),
+        _ => current.checked_sub(value),
+    };
+
+    result.map(|result| result.to_string()).ok_or_else(|| "the result overflows".to_string())
+}
+
+/// The source the event came from, its block number, the entity key and the other values
+type SourceEvent = (usize, String, Vec<String>, Vec<Option<String>>);
+
+/// A row of the entity table as text, in the order of the entity columns
+#[derive(Clone)]
+struct EntityRow {
+    values: Vec<Option<String>>,
+    last_updated_block: String,
+}
+
+impl EntityRow {
+    fn to_json(&self, entity: &Entity, network: &str) -> Value {
+        let mut object = Map::new();
+        object.insert("network".to_string(), Value::String(network.to_string()));
+        for (column, value) in entity.columns.iter().zip(&self.values) {
+            object.insert(
+                column.name.clone(),
+                value.clone().map(Value::String).unwrap_or(Value::Null),
+            );
+        }
+        object.insert(
+            "last_updated_block".to_string(),
+            Value::String(self.last_updated_block.clone()),
+        );
+        Value::Object(object)
+    }
+}
+
+fn key_json(entity: &Entity, key: &[String]) -> Value {
+    Value::Object(
+        entity
+            .key
+            .iter()
+            .cloned()
+            .zip(key.iter().cloned().map(Value::String))
+            .collect::<Map<_, _>>(),
+    )
+}
+
+async fn entity_cursor(
+    client: &PostgresClient,
+    indexer_name: &str,
+    entity: &Entity,
+    network: &str,
+) -> Result<U64, EntitiesError> {
+    let row = client
+        .query_one_or_none(
+            &format!(
+                "SELECT block_number::TEXT FROM {} WHERE entity = $1 AND network = $2",
+                cursors_table_name(indexer_name)
+            ),
+            &[&entity.name, &network],
+        )
+        .await?;
+
+    match row {
+        Some(row) => {
+            let block: String = row.get(0);
+            U64::from_dec_str(&block).map_err(|_| EntitiesError::InvalidBlock(block))
+        }
+        None => Ok(U64::zero()),
+    }
+}
+
+/// Events are only applied up to the lowest block every source has synced to, so an event is
+/// never applied before an earlier event of another source
+async fn synced_watermark(
+    client: &PostgresClient,
+    sources: &[&EntitySource],
+    network: &str,
+) -> Result<U64, EntitiesError> {
+    let query = sources
+        .iter()
+        .map(|source| {
+            format!(
+                "SELECT last_synced_block FROM {} WHERE network = $1",
+                source.last_synced_table_name
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ");
+    let row = client
+        .query_one_or_none(
+            &format!("SELECT COALESCE(MIN(last_synced_block), 0)::TEXT FROM ({}) s", query),
+            &[&network],
+        )
+        .await?;
+
+    match row {
+        Some(row) => {
+            let block: String = row.get(0);
+            U64::from_dec_str(&block).map_err(|_| EntitiesError::InvalidBlock(block))
+        }
+        None => Ok(U64::zero()),
+    }
+}
+
+/// Applies the events after the cursor up to the watermark in chain order, returns whether there
+/// were blocks to build so a build which is behind runs again straight away
+async fn build_entity(
+    client: &PostgresClient,
+    indexer_name: &str,
+    entity: &Entity,
+    sources: &[&EntitySource],
+    network: &str,
+    soft_delete: bool,
+) -> Result<bool, EntitiesError> {
+    let cursor = entity_cursor(client, indexer_name, entity, network).await?;
+    let watermark = synced_watermark(client, sources, network).await?;
+    if watermark <= cursor {
+        return Ok(false);
+    }
+    let to_block = watermark.min(cursor + MAX_BLOCKS_PER_BUILD);
+
+    let events_query = sources
+        .iter()
+        .enumerate()
+        .map(|(index, source)| {
+            format!(
+                "SELECT {index} AS source, block_number, log_index, ARRAY[{values}] AS vals FROM {table} \
+                 WHERE network = $1 AND block_number > $2 AND block_number <= $3{soft_delete}",
+                index = index,
+                values = source
+                    .value_columns()
+                    .map(|column| format!("\"{}\"::TEXT", column))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                table = source.table_name,
+                soft_delete = if soft_delete { " AND NOT removed_by_reorg" } else { "" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ");
+    let rows = client
+        .query(
+            &format!(
+                "SELECT source, block_number::TEXT, vals FROM ({}) e ORDER BY block_number, log_index",
+                events_query
+            ),
+            &[
+                &network,
+                &EthereumSqlTypeWrapper::U64(cursor),
+                &EthereumSqlTypeWrapper::U64(to_block),
+            ],
+        )
+        .await?;
+
+    let events: Vec<SourceEvent> = rows
+        .iter()
+        .filter_map(|row| {
+            let source: i32 = row.get(0);
+            let block_number: String = row.get(1);
+            let values: Vec<Option<String>> = row.get(2);
+            let key_length = sources[source as usize].key_columns.len();
+            // an event without a key can not be applied to an entity
+            let key: Vec<String> = values[..key_length].iter().cloned().collect::<Option<_>>()?;
+            Some((source as usize, block_number, key, values[key_length..].to_vec()))
+        })
+        .collect();
+
+    let mut entities: HashMap<Vec<String>, Option<EntityRow>> =
+        load_entities(client, indexer_name, entity, network, &events).await?;
+    let mut history: Vec<Value> = vec![];
+    let mut recorded: HashSet<(Vec<String>, String)> = HashSet::new();
+    for (source, block_number, key, values) in events {
+        let current = entities.entry(key.clone()).or_default();
+        // the value before the first change in a block is what a rollback of the block restores
+        if recorded.insert((key.clone(), block_number.clone())) {
+            history.push(serde_json::json!({
+                "key": key_json(entity, &key),
+                "block_number": block_number,
+                "previous_value": current.as_ref().map(|row| row.to_json(entity, network)),
+            }));
+        }
+
+        let row = current.get_or_insert_with(|| EntityRow {
+            values: entity
+                .columns
+                .iter()
+                .map(|column| {
+                    if let Some(position) = entity.key.iter().position(|k| *k == column.name) {
+                        Some(key[position].clone())
+                    } else if column.column_type == EntityColumnType::Number {
+                        Some("0".to_string())
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+            last_updated_block: block_number.clone(),
+        });
+        for ((column, operation, input), value) in sources[source].updates.iter().zip(values) {
+            let Some(value) = value else {
+                continue;
+            };
+            let column_type = entity.columns[*column].column_type;
+            row.values[*column] = Some(
+                apply_operation(column_type, row.values[*column].as_deref(), *operation, &value)
+                    .map_err(|e| {
+                        EntitiesError::InvalidValue(entity.name.clone(), input.clone(), e)
+                    })?,
+            );
+        }
+        row.last_updated_block = block_number;
+    }
+
+    let table_name = entity_table_name(indexer_name, entity);
+    let mut statements = vec![];
+    let changed: Vec<Value> =
+        entities.values().flatten().map(|row| row.to_json(entity, network)).collect();
+    if !changed.is_empty() {
+        statements.push((
+            format!(
+                "INSERT INTO {table} SELECT * FROM jsonb_populate_recordset(NULL::{table}, $1::TEXT::JSONB) \
+                 ON CONFLICT (network, {key}) DO UPDATE SET {updates}",
+                table = table_name,
+                key = quoted_columns(entity.key.iter()),
+                updates = update_columns_sql(entity)
+            ),
+            vec![EthereumSqlTypeWrapper::String(Value::Array(changed).to_string())],
+        ));
+    }
+    if !history.is_empty() {
+        statements.push((
+            format!(
+                "INSERT INTO {} (entity, network, key, block_number, previous_value) \
+                 SELECT $1, $2, h.key, h.block_number, h.previous_value \
+                 FROM jsonb_to_recordset($3::TEXT::JSONB) AS h(key JSONB, block_number NUMERIC, previous_value JSONB)",
+                history_table_name(indexer_name)
+            ),
+            vec![
+                EthereumSqlTypeWrapper::String(entity.name.clone()),
+                EthereumSqlTypeWrapper::String(network.to_string()),
+                EthereumSqlTypeWrapper::String(Value::Array(history).to_string()),
+            ],
+        ));
+    }
+    statements.push((
+        format!(
+            "INSERT INTO {} (entity, network, block_number) VALUES ($1, $2, $3) \
+             ON CONFLICT (entity, network) DO UPDATE SET block_number = EXCLUDED.block_number",
+            cursors_table_name(indexer_name)
+        ),
+        vec![
+            EthereumSqlTypeWrapper::String(entity.name.clone()),
+            EthereumSqlTypeWrapper::String(network.to_string()),
+            EthereumSqlTypeWrapper::U64(to_block),
+        ],
+    ));
+    client.execute_in_transaction(&statements).await?;
+
+    Ok(true)
+}
+
+/// The current rows of the entities the events change, `None` for the ones which do not exist
+async fn load_entities(
+    client: &PostgresClient,
+    indexer_name: &str,
+    entity: &Entity,
+    network: &str,
+    events: &[SourceEvent],
+) -> Result<HashMap<Vec<String>, Option<EntityRow>>, EntitiesError> {
+    let keys: HashSet<&Vec<String>> = events.iter().map(|(_, _, key, _)| key).collect();
+    if keys.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let key_columns =
+        entity.key.iter().map(|key| format!("t.\"{}\"::TEXT", key)).collect::<Vec<_>>().join(", ");
+    let rows = client
+        .query(
+            &format!(
+                "SELECT {columns}, t.last_updated_block::TEXT FROM {table} t \
+                 WHERE t.network = $1 AND ({key_columns}) IN (SELECT {keys} FROM jsonb_to_recordset($2::TEXT::JSONB) AS k({key_types}))",
+                columns = entity
+                    .columns
+                    .iter()
+                    .map(|column| format!("t.\"{}\"::TEXT", column.name))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                table = entity_table_name(indexer_name, entity),
+                key_columns = key_columns,
+                keys = quoted_columns(entity.key.iter()),
+                key_types = entity
+                    .key
+                    .iter()
+                    .map(|key| format!("\"{}\" TEXT", key))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            &[
+                &network,
+                &Value::Array(keys.iter().map(|key| key_json(entity, key)).collect()).to_string(),
+            ],
+        )
+        .await?;
+
+    let mut entities = HashMap::new();
+    for row in rows {
+        let values: Vec<Option<String>> = (0..entity.columns.len()).map(|i| row.get(i)).collect();
+        let key = entity
+            .key
+            .iter()
+            .filter_map(|key| {
+                let position = entity.columns.iter().position(|column| column.name == *key)?;
+                values[position].clone()
+            })
+            .collect();
+        entities.insert(
+            key,
+            Some(EntityRow { values, last_updated_block: row.get(entity.columns.len()) }),
+        );
+    }
+
+    Ok(entities)
+}
+
+/// History older than the reorg safe distance can not be rolled back to anymore
+async fn prune_history(
+    client: &PostgresClient,
+    indexer_name: &str,
+    entity: &Entity,
+    network: &str,
+    provider: &JsonRpcCachedProvider,
+) -> Result<(), EntitiesError> {
+    let Ok(chain_id) = provider.get_chain_id().await else {
+        return Ok(());
+    };
+    let cursor = entity_cursor(client, indexer_name, entity, network).await?;
+    let prune_below = cursor.saturating_sub(reorg_safe_distance_for_chain(&chain_id));
+
+    client
+        .execute(
+            &format!(
+                "DELETE FROM {} WHERE entity = $1 AND network = $2 AND block_number < $3",
+                history_table_name(indexer_name)
+            ),
+            &[&entity.name, &network, &EthereumSqlTypeWrapper::U64(prune_below)],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Builds the entities from their event tables in the background, each network as far as all the
+/// sources of an entity have synced
+pub fn start_entities_builder(
+    client: Arc<PostgresClient>,
+    indexer_name: &str,
+    contracts: &[Contract],
+    entities: Vec<Entity>,
+    providers: HashMap<String, Arc<JsonRpcCachedProvider>>,
+    soft_delete: bool,
+) {
+    let indexer_name = indexer_name.to_string();
+    let builds: Vec<(Entity, Vec<EntitySource>)> = entities
+        .into_iter()
+        .map(|entity| {
+            let sources = entity_sources(&indexer_name, contracts, &entity);
+            (entity, sources)
+        })
+        .collect();
+
+    tokio::spawn(async move {
+        while is_running() {
+            for (entity, sources) in &builds {
+                let mut networks: Vec<&String> =
+                    sources.iter().flat_map(|source| &source.networks).collect();
+                networks.sort();
+                networks.dedup();
+
+                for network in networks {
+                    let network_sources: Vec<&EntitySource> =
+                        sources.iter().filter(|source| source.networks.contains(network)).collect();
+
+                    loop {
+                        let _lock = BUILD_LOCK.lock().await;
+                        match build_entity(
+                            &client,
+                            &indexer_name,
+                            entity,
+                            &network_sources,
+                            network,
+                            soft_delete,
+                        )
+                        .await
+                        {
+                            Ok(true) if is_running() => continue,
+                            Ok(_) => break,
+                            Err(e) => {
+                                error!(
+                                    "Could not build entity {} on network {}: {}",
+                                    entity.name, network, e
+                                );
+                                break;
+                            }
+                        }
+                    }
+
+                    if let Some(provider) = providers.get(network) {
+                        if let Err(e) =
+                            prune_history(&client, &indexer_name, entity, network, provider).await
+                        {
+                            error!("Could not prune the history of entity {}: {}", entity.name, e);
+                        }
+                    }
+                }
+            }
+
+            tokio::time::sleep(BUILD_INTERVAL).await;
+        }
+    });
+}
+
+/// Restores the entities of the network to how they were before `from_block` and moves their
+/// cursors back so the canonical events are applied again
+pub async fn rollback_entities(
+    client: &PostgresClient,
+    indexer_name: &str,
+    entities: &[Entity],
+    network: &str,
+    from_block: U64,
+) -> Result<(), EntitiesError> {
+    let _lock = BUILD_LOCK.lock().await;
+    let history_table = history_table_name(indexer_name);
+    for entity in entities {
+        let table_name = entity_table_name(indexer_name, entity);
+        let earliest = format!(
+            "WITH earliest AS (SELECT DISTINCT ON (key) key, previous_value FROM {} \
+             WHERE entity = $1 AND network = $2 AND block_number >= $3 ORDER BY key, id)",
+            history_table
+        );
+        let key_matches = entity
+            .key
+            .iter()
+            .map(|key| format!("t.\"{key}\"::TEXT = earliest.key->>'{key}'", key = key))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let params = vec![
+            EthereumSqlTypeWrapper::String(entity.name.clone()),
+            EthereumSqlTypeWrapper::String(network.to_string()),
+            EthereumSqlTypeWrapper::U64(from_block),
+        ];
+
+        client
+            .execute_in_transaction(&[
+                (
+                    format!(
+                        "{earliest} INSERT INTO {table} SELECT (jsonb_populate_record(NULL::{table}, previous_value)).* \
+                         FROM earliest WHERE previous_value IS NOT NULL \
+                         ON CONFLICT (network, {key}) DO UPDATE SET {updates}",
+                        earliest = earliest,
+                        table = table_name,
+                        key = quoted_columns(entity.key.iter()),
+                        updates = update_columns_sql(entity)
+                    ),
+                    params.clone(),
+                ),
+                (
+                    format!(
+                        "{} DELETE FROM {} t USING earliest \
+                         WHERE earliest.previous_value IS NULL AND t.network = $2 AND {}",
+                        earliest, table_name, key_matches
+                    ),
+                    params.clone(),
+                ),
+                (
+                    format!(
+                        "DELETE FROM {} WHERE entity = $1 AND network = $2 AND block_number >= $3",
+                        history_table
+                    ),
+                    params.clone(),
+                ),
+                (
+                    format!(
+                        "UPDATE {} SET block_number = LEAST(block_number, $3 - 1) \
+                         WHERE entity = $1 AND network = $2",
+                        cursors_table_name(indexer_name)
+                    ),
+                    params,
+                ),
+            ])
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_operation() {
+        let number = EntityColumnType::Number;
+        assert_eq!(apply_operation(number, None, Operation::Add, "5").unwrap(), "5");
+        assert_eq!(apply_operation(number, Some("5"), Operation::Subtract, "8").unwrap(), "-3");
+        assert_eq!(apply_operation(number, Some("5"), Operation::Set, "1").unwrap(), "1");
+        assert_eq!(
+            apply_operation(number, Some(&I256::MAX.to_string()), Operation::Add, "1").unwrap_err(),
+            "the result overflows"
+        );
+        assert!(apply_operation(number, Some("1"), Operation::Add, "abc").is_err());
+
+        // only numbers can be added to, anything else is set
+        let address = EntityColumnType::Address;
+        assert_eq!(apply_operation(address, Some("0x01"), Operation::Add, "0x02").unwrap(), "0x02");
+    }
+}
@@ -4,12 +4,42 @@ use tracing::{error, info};
 
 use crate::{
     abi::{ABIInput, ABIItem, EventInfo, GenerateAbiPropertiesType, ParamTypeError, ReadAbiError},
+    database::postgres::{
+        address_labels::{
+            address_label_functions_sql, address_labels_table_name, drop_address_labels_table_sql,
+            generate_address_labels_table_sql,
+        },
+        contracts_seen::contracts_seen_sql,
+        encryption::{
+            column_encryption_key_table_name, encrypted_inputs_sql, generate_column_encryption_sql,
+        },
+        kv_store::{drop_kv_store_tables_sql, generate_kv_store_tables_sql},
+        materialized_views::materialized_views_schema_name,
+        notify::{generate_notify_function_sql, notify_trigger_sql},
+        ranges::{drop_ranges_for_indexer_sql, generate_ranges_table_sql},
+        raw_logs::{drop_raw_logs_table_sql, generate_raw_logs_table_sql},
+        reorg::soft_delete_columns_sql,
+        retry_queue::{drop_retry_queue_table_sql, generate_retry_queue_table_sql},
+        spam_contracts::{drop_spam_contracts_table_sql, generate_spam_contracts_table_sql},
+    },
     helpers::camel_to_snake,
-    indexer::Indexer,
-    manifest::contract::Contract,
+    indexer::{enrichment::enrichment_column_type, Indexer},
+    manifest::{
+        contract::{
+            Contract, EncryptedInputs, EventDecimals, EventEnrichment, EventTableStorage,
+            ParamsStorage,
+        },
+        storage::ColumnEncryption,
+    },
     types::code::Code,
 };
 
+/// The JSONB column holding every decoded param when the contract stores its params as jsonb
+pub const DECODED_PARAMS_COLUMN: &str = "decoded_params";
+
+/// Why the spam filter flagged the contract which emitted the event, NULL for any other contract
+pub const SPAM_REASON_COLUMN: &str = "spam_reason";
+
 fn generate_columns(inputs: &[ABIInput], property_type: &GenerateAbiPropertiesType) -> Vec<String> {
     ABIInput::generate_abi_name_properties(inputs, property_type, None)
         .into_iter()
@@ -25,7 +55,23 @@ fn generate_columns_names_only(inputs: &[ABIInput]) -> Vec<String> {
     generate_columns(inputs, &GenerateAbiPropertiesType::PostgresColumnsNamesOnly)
 }
 
-pub fn generate_column_names_only_with_base_properties(inputs: &[ABIInput]) -> Vec<String> {
+/// The event columns as column name and postgres type pairs
+pub fn generate_columns_names_and_types(inputs: &[ABIInput]) -> Vec<(String, String)> {
+    ABIInput::generate_abi_name_properties(
+        inputs,
+        &GenerateAbiPropertiesType::PostgresColumnsNamesOnly,
+        None,
+    )
+    .into_iter()
+    .map(|m| (m.value, solidity_type_to_db_type(&m.abi_type)))
+    .collect()
+}
+
+/// Tables shared by a table group have a `chain_id` column after `network`
+pub fn generate_column_names_only_with_base_properties(
+    inputs: &[ABIInput],
+    include_chain_id: bool,
+) -> Vec<String> {
     let mut column_names: Vec<String> = vec!["contract_address".to_string()];
     column_names.extend(generate_columns_names_only(inputs));
     column_names.extend(vec![
@@ -33,16 +79,55 @@ pub fn generate_column_names_only_with_base_properties(inputs: &[ABIInput]) -> V
         "block_number".to_string(),
         "block_hash".to_string(),
         "network".to_string(),
-        "tx_index".to_string(),
-        "log_index".to_string(),
     ]);
+    if include_chain_id {
+        column_names.push("chain_id".to_string());
+    }
+    column_names.extend(vec!["tx_index".to_string(), "log_index".to_string()]);
     column_names
 }
 
+/// The storage parameters and the compression of every column whose type postgres can compress,
+/// the parameters are checked when the manifest is validated
+fn table_storage_sql(table_name: &str, storage: &EventTableStorage) -> String {
+    let mut sql: Vec<String> = vec![];
+    if let Ok(parameters) = storage.parameters_sql() {
+        if !parameters.is_empty() {
+            sql.push(format!("ALTER TABLE {} SET ({});", table_name, parameters.join(", ")));
+        }
+    }
+    if let Some(compression) = storage.compression {
+        sql.push(format!(
+            "DO $$ DECLARE c record; BEGIN \
+             FOR c IN SELECT a.attname FROM pg_attribute a JOIN pg_type t ON t.oid = a.atttypid \
+             WHERE a.attrelid = '{table}'::regclass AND a.attnum > 0 AND NOT a.attisdropped AND t.typstorage <> 'p' \
+             LOOP EXECUTE format('ALTER TABLE {table} ALTER COLUMN %I SET COMPRESSION {compression}', c.attname); END LOOP; \
+             END $$;",
+            table = table_name,
+            compression = compression.as_sql()
+        ));
+    }
+
+    sql.join("\n")
+}
+
+#[allow(clippy::too_many_arguments)]
 fn generate_event_table_sql_with_comments(
     abi_inputs: &[EventInfo],
     contract_name: &str,
     schema_name: &str,
+    include_chain_id: bool,
+    params_storage: ParamsStorage,
+    soft_delete_reorged_rows: bool,
+    notify_channel: Option<&str>,
+    normalize_decimals: &[EventDecimals],
+    enrichment: &[EventEnrichment],
+    encrypted_inputs: &[EncryptedInputs],
+    column_encryption: Option<(&str, &[String])>,
+    table_storage: &[EventTableStorage],
+    address_labels_table: Option<&str>,
+    capture_contract_addresses: bool,
+    flag_spam: bool,
     apply_full_name_comment_for_events: Vec<String>,
 ) -> String {
     abi_inputs
@@ -50,7 +135,7 @@ fn generate_event_table_sql_with_comments(
         .map(|event_info| {
             let table_name = format!("{}.{}", schema_name, camel_to_snake(&event_info.name));
             info!("Creating table if not exists: {}", table_name);
-            let event_columns = if event_info.inputs.is_empty() {
+            let event_columns = if event_info.inputs.is_empty() || !params_storage.typed_columns() {
                 "".to_string()
             } else {
                 generate_columns_with_data_types(&event_info.inputs).join(", ") + ","
@@ -65,12 +150,162 @@ fn generate_event_table_sql_with_comments(
                 block_number NUMERIC NOT NULL, \
                 block_hash CHAR(66) NOT NULL, \
                 network VARCHAR(50) NOT NULL, \
+                {} \
                 tx_index NUMERIC NOT NULL, \
                 log_index VARCHAR(78) NOT NULL\
             );",
-                table_name, event_columns
+                table_name,
+                event_columns,
+                if include_chain_id { "chain_id BIGINT NOT NULL," } else { "" }
+            );
+
+            let create_table_sql = if include_chain_id {
+                // the index lives in the table schema so the event name is unique enough
+                format!(
+                    "{}\nCREATE INDEX IF NOT EXISTS {}_chain_id_idx ON {} (chain_id);",
+                    create_table_sql,
+                    camel_to_snake(&event_info.name),
+                    table_name
+                )
+            } else {
+                create_table_sql
+            };
+
+            let create_table_sql = if soft_delete_reorged_rows {
+                format!("{}\n{}", create_table_sql, soft_delete_columns_sql(&table_name))
+            } else {
+                create_table_sql
+            };
+
+            let create_table_sql = format!(
+                "{}\n{}",
+                create_table_sql,
+                notify_trigger_sql(&table_name, notify_channel)
             );
 
+            // added after the table so it can be turned on for a table which already exists
+            let create_table_sql = if params_storage.jsonb() {
+                format!(
+                    "{}\nALTER TABLE {} ADD COLUMN IF NOT EXISTS {} JSONB;",
+                    create_table_sql, table_name, DECODED_PARAMS_COLUMN
+                )
+            } else {
+                create_table_sql
+            };
+
+            // added after the table so flagging can be turned on for a table which already exists
+            let create_table_sql = if flag_spam {
+                format!(
+                    "{}\nALTER TABLE {} ADD COLUMN IF NOT EXISTS {} TEXT;",
+                    create_table_sql, table_name, SPAM_REASON_COLUMN
+                )
+            } else {
+                create_table_sql
+            };
+
+            // added after the table so they can be turned on for tables which already exist
+            let normalized_columns: Vec<String> = normalize_decimals
+                .iter()
+                .filter(|decimals| decimals.event_name == event_info.name)
+                .flat_map(|decimals| &decimals.inputs)
+                .map(|input| {
+                    format!(
+                        "ADD COLUMN IF NOT EXISTS \"{}\" NUMERIC",
+                        EventDecimals::normalized_column_name(input)
+                    )
+                })
+                .collect();
+            let create_table_sql = if normalized_columns.is_empty() {
+                create_table_sql
+            } else {
+                format!(
+                    "{}\nALTER TABLE {} {};",
+                    create_table_sql,
+                    table_name,
+                    normalized_columns.join(", ")
+                )
+            };
+
+            let enrichment_columns: Vec<String> = enrichment
+                .iter()
+                .filter(|enrichment| enrichment.event_name == event_info.name)
+                .flat_map(|enrichment| &enrichment.stages)
+                .map(|stage| {
+                    format!(
+                        "ADD COLUMN IF NOT EXISTS \"{}\" {}",
+                        stage.column_name(),
+                        enrichment_column_type(stage)
+                    )
+                })
+                .collect();
+            let create_table_sql = if enrichment_columns.is_empty() {
+                create_table_sql
+            } else {
+                format!(
+                    "{}\nALTER TABLE {} {};",
+                    create_table_sql,
+                    table_name,
+                    enrichment_columns.join(", ")
+                )
+            };
+
+            let event_encrypted_inputs = encrypted_inputs
+                .iter()
+                .find(|encrypted| encrypted.event_name == event_info.name)
+                .map(|encrypted| encrypted.inputs.as_slice())
+                .unwrap_or_default();
+            let create_table_sql = match column_encryption {
+                Some((key_table, decrypt_roles)) if !event_encrypted_inputs.is_empty() => format!(
+                    "{}\n{}",
+                    create_table_sql,
+                    encrypted_inputs_sql(
+                        &table_name,
+                        key_table,
+                        event_encrypted_inputs,
+                        params_storage,
+                        decrypt_roles
+                    )
+                ),
+                _ => create_table_sql,
+            };
+
+            // after every column is added so the compression covers them all
+            let create_table_sql =
+                match table_storage.iter().find(|storage| storage.event_name == event_info.name) {
+                    Some(storage) => {
+                        format!("{}\n{}", create_table_sql, table_storage_sql(&table_name, storage))
+                    }
+                    None => create_table_sql,
+                };
+
+            let create_table_sql = match address_labels_table {
+                Some(labels_table) => {
+                    let mut address_columns = vec!["contract_address".to_string()];
+                    address_columns.extend(
+                        generate_columns_names_and_types(&event_info.inputs)
+                            .into_iter()
+                            .filter(|(_, sql_type)| sql_type == "CHAR(42)")
+                            .map(|(column, _)| column),
+                    );
+                    format!(
+                        "{}\n{}",
+                        create_table_sql,
+                        address_label_functions_sql(&table_name, &address_columns, labels_table)
+                    )
+                }
+                None => create_table_sql,
+            };
+
+            let create_table_sql = if capture_contract_addresses {
+                format!(
+                    "{}\n{}",
+                    create_table_sql,
+                    contracts_seen_sql(&table_name, &camel_to_snake(&event_info.name))
+                )
+            } else {
+                create_table_sql
+            };
+
             if !apply_full_name_comment_for_events.contains(&event_info.name) {
                 return create_table_sql;
             }
@@ -87,33 +322,42 @@ fn generate_event_table_sql_with_comments(
         .join("\n")
 }
 
-fn generate_internal_event_table_sql(
-    abi_inputs: &[EventInfo],
+/// The table the last synced block of an event is tracked in for each network, along with how
+/// far into the block after it the handlers got
+pub fn generate_last_synced_table_sql(
     schema_name: &str,
-    networks: Vec<&str>,
+    event_name: &str,
+    networks: &[&str],
 ) -> String {
-    abi_inputs.iter().map(|event_info| {
-        let table_name = format!(
-            "rindexer_internal.{}_{}",
-            schema_name,
-            camel_to_snake(&event_info.name)
-        );
+    let table_name = format!("rindexer_internal.{}_{}", schema_name, camel_to_snake(event_name));
 
-        let create_table_query = format!(
-            r#"CREATE TABLE IF NOT EXISTS {} ("network" TEXT PRIMARY KEY, "last_synced_block" NUMERIC);"#,
-            table_name
-        );
+    let create_table_query = format!(
+        r#"CREATE TABLE IF NOT EXISTS {table} ("network" TEXT PRIMARY KEY, "last_synced_block" NUMERIC);
+        ALTER TABLE {table} ADD COLUMN IF NOT EXISTS "last_synced_log_index" NUMERIC;"#,
+        table = table_name
+    );
 
-        let insert_queries = networks.iter().map(|network| {
-            format!(
-                r#"INSERT INTO {} ("network", "last_synced_block") VALUES ('{}', 0) ON CONFLICT ("network") DO NOTHING;"#,
-                table_name,
-                network
-            )
-        }).collect::<Vec<_>>().join("\n");
+    let insert_queries = networks.iter().map(|network| {
+        format!(
+            r#"INSERT INTO {} ("network", "last_synced_block") VALUES ('{}', 0) ON CONFLICT ("network") DO NOTHING;"#,
+            table_name,
+            network
+        )
+    }).collect::<Vec<_>>().join("\n");
+
+    format!("{}\n{}", create_table_query, insert_queries)
+}
 
-        format!("{}\n{}", create_table_query, insert_queries)
-    }).collect::<Vec<_>>().join("\n")
+fn generate_internal_event_table_sql(
+    abi_inputs: &[EventInfo],
+    schema_name: &str,
+    networks: Vec<&str>,
+) -> String {
+    abi_inputs
+        .iter()
+        .map(|event_info| generate_last_synced_table_sql(schema_name, &event_info.name, &networks))
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -140,6 +384,13 @@ fn find_clashing_event_names(
             continue;
         }
 
+        // contracts in the same table group write into the same tables
+        if current_contract.table_group.is_some() &&
+            other_contract.table_group == current_contract.table_group
+        {
+            continue;
+        }
+
         let other_abi_items = ABIItem::read_abi_items(project_path, other_contract)?;
         let other_event_names =
             ABIItem::extract_event_names_and_signatures_from_abi(other_abi_items)?;
@@ -160,8 +411,22 @@ pub fn generate_tables_for_indexer_sql(
     project_path: &Path,
     indexer: &Indexer,
     disable_event_tables: bool,
+    soft_delete_reorged_rows: bool,
+    notify_channel: Option<&str>,
+    column_encryption: Option<&ColumnEncryption>,
+    address_labels: bool,
 ) -> Result<Code, GenerateTablesForIndexerSqlError> {
     let mut sql = "CREATE SCHEMA IF NOT EXISTS rindexer_internal;".to_string();
+    // before the event tables as their notify triggers call it
+    sql.push_str(&generate_notify_function_sql());
+    // before the event tables as their label functions read it
+    sql.push_str(generate_address_labels_table_sql(&indexer.name).as_str());
+    let labels_table = address_labels_table_name(&indexer.name);
+    // before the event tables as their decrypt functions read the key table
+    if column_encryption.is_some() {
+        sql.push_str(generate_column_encryption_sql(&indexer.name).as_str());
+    }
+    let encryption_key_table = column_encryption_key_table_name(&indexer.name);
 
     for contract in &indexer.contracts {
         let contract_name = contract.before_modify_name_if_filter_readonly();
@@ -171,8 +436,11 @@ pub fn generate_tables_for_indexer_sql(
         let networks: Vec<&str> = contract.details.iter().map(|d| d.network.as_str()).collect();
 
         if !disable_event_tables {
-            sql.push_str(format!("CREATE SCHEMA IF NOT EXISTS {};", schema_name).as_str());
-            info!("Creating schema if not exists: {}", schema_name);
+            let table_name = contract.table_name_readonly();
+            let table_schema_name =
+                generate_indexer_contract_schema_name(&indexer.name, &table_name);
+            sql.push_str(format!("CREATE SCHEMA IF NOT EXISTS {};", table_schema_name).as_str());
+            info!("Creating schema if not exists: {}", table_schema_name);
 
             let event_matching_name_on_other = find_clashing_event_names(
                 project_path,
@@ -183,12 +451,27 @@ pub fn generate_tables_for_indexer_sql(
 
             sql.push_str(&generate_event_table_sql_with_comments(
                 &event_names,
-                &contract.name,
-                &schema_name,
+                contract.table_group.as_deref().unwrap_or(&contract.name),
+                &table_schema_name,
+                contract.table_group.is_some(),
+                contract.params_storage(),
+                soft_delete_reorged_rows,
+                notify_channel,
+                contract.normalize_decimals.as_deref().unwrap_or_default(),
+                contract.enrichment.as_deref().unwrap_or_default(),
+                contract.encrypted_inputs.as_deref().unwrap_or_default(),
+                column_encryption
+                    .map(|encryption| (encryption_key_table.as_str(), encryption.decrypt_roles())),
+                contract.table_storage.as_deref().unwrap_or_default(),
+                address_labels.then_some(labels_table.as_str()),
+                // filter contracts index any address so which ones emitted is worth keeping
+                contract.is_filter(),
+                contract.flags_spam(),
                 event_matching_name_on_other,
             ));
         }
-        // we still need to create the internal tables for the contract
+        // we still need to create the internal tables for the contract, these stay per contract
+        // even in a table group as the last synced block is tracked per contract
         sql.push_str(&generate_internal_event_table_sql(&event_names, &schema_name, networks));
     }
 
@@ -212,6 +495,16 @@ pub fn generate_tables_for_indexer_sql(
         indexer_name = camel_to_snake(&indexer.name)
     ));
 
+    sql.push_str(generate_kv_store_tables_sql(&indexer.name).as_str());
+    sql.push_str(generate_retry_queue_table_sql(&indexer.name).as_str());
+    sql.push_str(generate_ranges_table_sql().as_str());
+    if indexer.contracts.iter().any(|contract| contract.archive_raw_logs()) {
+        sql.push_str(generate_raw_logs_table_sql(&indexer.name).as_str());
+    }
+    if indexer.contracts.iter().any(|contract| contract.spam_filter.is_some()) {
+        sql.push_str(generate_spam_contracts_table_sql(&indexer.name).as_str());
+    }
+
     Ok(Code::new(sql))
 }
 
@@ -238,11 +531,23 @@ pub fn drop_tables_for_indexer_sql(project_path: &Path, indexer: &Indexer) -> Co
         camel_to_snake(&indexer.name)
     );
     sql.push_str(format!("DROP TABLE IF EXISTS rindexer_internal.{}_last_known_relationship_dropping_sql CASCADE;", camel_to_snake(&indexer.name)).as_str());
+    sql.push_str(drop_kv_store_tables_sql(&indexer.name).as_str());
+    sql.push_str(drop_address_labels_table_sql(&indexer.name).as_str());
+    sql.push_str(drop_retry_queue_table_sql(&indexer.name).as_str());
+    sql.push_str(drop_raw_logs_table_sql(&indexer.name).as_str());
+    sql.push_str(drop_spam_contracts_table_sql(&indexer.name).as_str());
+    sql.push_str(drop_ranges_for_indexer_sql(&indexer.name).as_str());
+    sql.push_str(
+        format!("DROP SCHEMA IF EXISTS {} CASCADE;", materialized_views_schema_name(&indexer.name))
+            .as_str(),
+    );
 
     for contract in &indexer.contracts {
         let contract_name = contract.before_modify_name_if_filter_readonly();
         let schema_name = generate_indexer_contract_schema_name(&indexer.name, &contract_name);
-        sql.push_str(format!("DROP SCHEMA IF EXISTS {} CASCADE;", schema_name).as_str());
+        let table_schema_name =
+            generate_indexer_contract_schema_name(&indexer.name, &contract.table_name_readonly());
+        sql.push_str(format!("DROP SCHEMA IF EXISTS {} CASCADE;", table_schema_name).as_str());
 
         // drop last synced blocks for contracts
         let abi_items = ABIItem::read_abi_items(project_path, contract);
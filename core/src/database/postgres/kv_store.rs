@@ -0,0 +1,353 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use ethers::types::U64;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{debug, error};
+
+use crate::{
+    database::postgres::{
+        client::{PostgresClient, PostgresError},
+        reorg::reorg_check_from_block,
+    },
+    helpers::camel_to_snake,
+    is_running,
+    provider::JsonRpcCachedProvider,
+    types::code::Code,
+    EthereumSqlTypeWrapper,
+};
+
+const HISTORY_PRUNE_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeyValueStoreError {
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("Could not serialize or deserialize value: {0}")]
+    SerdeError(#[from] serde_json::Error),
+}
+
+pub fn kv_store_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_kv_store", camel_to_snake(indexer_name))
+}
+
+pub fn kv_store_history_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_kv_store_history", camel_to_snake(indexer_name))
+}
+
+pub fn generate_kv_store_tables_sql(indexer_name: &str) -> Code {
+    Code::new(format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {store_table} (
+            namespace TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value JSONB NOT NULL,
+            PRIMARY KEY (namespace, key)
+        );
+        CREATE TABLE IF NOT EXISTS {history_table} (
+            id BIGSERIAL PRIMARY KEY,
+            namespace TEXT NOT NULL,
+            key TEXT NOT NULL,
+            network TEXT NOT NULL,
+            block_number NUMERIC NOT NULL,
+            previous_value JSONB
+        );
+        CREATE INDEX IF NOT EXISTS idx_{indexer_name}_kv_store_history_network_block ON {history_table} (network, block_number);
+    "#,
+        store_table = kv_store_table_name(indexer_name),
+        history_table = kv_store_history_table_name(indexer_name),
+        indexer_name = camel_to_snake(indexer_name),
+    ))
+}
+
+pub fn drop_kv_store_tables_sql(indexer_name: &str) -> Code {
+    Code::new(format!(
+        "DROP TABLE IF EXISTS {} CASCADE;DROP TABLE IF EXISTS {} CASCADE;",
+        kv_store_table_name(indexer_name),
+        kv_store_history_table_name(indexer_name)
+    ))
+}
+
+/// Writes `$3` under the key and records the value it replaced, NULL when there was none
+fn set_sql(store_table: &str, history_table: &str) -> String {
+    format!(
+        r#"
+        WITH previous AS (
+            SELECT value FROM {store_table} WHERE namespace = $1 AND key = $2
+        ), history AS (
+            INSERT INTO {history_table} (namespace, key, network, block_number, previous_value)
+            VALUES ($1, $2, $4, $5, (SELECT value FROM previous))
+        )
+        INSERT INTO {store_table} (namespace, key, value)
+        VALUES ($1, $2, CAST($3::TEXT AS JSONB))
+        ON CONFLICT (namespace, key) DO UPDATE SET value = EXCLUDED.value
+        "#
+    )
+}
+
+/// Removes the key and records the value it held, a key which is not set records nothing
+fn delete_sql(store_table: &str, history_table: &str) -> String {
+    format!(
+        r#"
+        WITH removed AS (
+            DELETE FROM {store_table} WHERE namespace = $1 AND key = $2 RETURNING value
+        )
+        INSERT INTO {history_table} (namespace, key, network, block_number, previous_value)
+        SELECT $1, $2, $3, $4, value FROM removed
+        "#
+    )
+}
+
+/// Puts back the value each key held before its earliest write from `$2` on, keys which did not
+/// exist before it are removed
+fn rollback_sql(store_table: &str, history_table: &str) -> String {
+    format!(
+        r#"
+        WITH reverted AS (
+            DELETE FROM {history_table} WHERE network = $1 AND block_number >= $2
+            RETURNING id, namespace, key, previous_value
+        ), earliest AS (
+            SELECT DISTINCT ON (namespace, key) namespace, key, previous_value
+            FROM reverted
+            ORDER BY namespace, key, id
+        ), restored AS (
+            INSERT INTO {store_table} (namespace, key, value)
+            SELECT namespace, key, previous_value FROM earliest WHERE previous_value IS NOT NULL
+            ON CONFLICT (namespace, key) DO UPDATE SET value = EXCLUDED.value
+        )
+        DELETE FROM {store_table} s
+        USING earliest e
+        WHERE s.namespace = e.namespace AND s.key = e.key AND e.previous_value IS NULL
+        "#
+    )
+}
+
+fn prune_history_sql(history_table: &str) -> String {
+    format!("DELETE FROM {} WHERE network = $1 AND block_number < $2", history_table)
+}
+
+/// A persistent key value store scoped to a namespace which handlers can use to keep state
+/// between invocations. Every write records the value it replaced alongside the block it was
+/// written in, so writes made in blocks which get reorged out can be rolled back.
+#[derive(Clone)]
+pub struct KeyValueStore {
+    client: Arc<PostgresClient>,
+    store_table: String,
+    history_table: String,
+    namespace: String,
+}
+
+impl KeyValueStore {
+    pub fn new(client: Arc<PostgresClient>, indexer_name: &str, namespace: &str) -> Self {
+        Self {
+            client,
+            store_table: kv_store_table_name(indexer_name),
+            history_table: kv_store_history_table_name(indexer_name),
+            namespace: namespace.to_string(),
+        }
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Result<Option<T>, KeyValueStoreError> {
+        let row = self
+            .client
+            .query_one_or_none(
+                &format!(
+                    "SELECT value::TEXT AS value FROM {} WHERE namespace = $1 AND key = $2",
+                    self.store_table
+                ),
+                &[&self.namespace, &key],
+            )
+            .await?;
+
+        match row {
+            Some(row) => {
+                let value: String = row.get("value");
+                Ok(Some(serde_json::from_str(&value)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Writes the value and records the previous one in the history table in a single statement
+    /// so a rollback can always restore what was there before this block.
+    pub async fn set<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        network: &str,
+        block_number: U64,
+    ) -> Result<(), KeyValueStoreError> {
+        let value = serde_json::to_string(value)?;
+        self.client
+            .execute(
+                &set_sql(&self.store_table, &self.history_table),
+                &[
+                    &self.namespace,
+                    &key,
+                    &value,
+                    &network,
+                    &EthereumSqlTypeWrapper::U64(block_number),
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(
+        &self,
+        key: &str,
+        network: &str,
+        block_number: U64,
+    ) -> Result<(), KeyValueStoreError> {
+        self.client
+            .execute(
+                &delete_sql(&self.store_table, &self.history_table),
+                &[&self.namespace, &key, &network, &EthereumSqlTypeWrapper::U64(block_number)],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Reverts every write made on the network at or after `from_block` across all namespaces of
+    /// the indexer, restoring the value each key held before the earliest reverted write.
+    pub async fn rollback(
+        client: &PostgresClient,
+        indexer_name: &str,
+        network: &str,
+        from_block: U64,
+    ) -> Result<(), KeyValueStoreError> {
+        client
+            .execute(
+                &rollback_sql(
+                    &kv_store_table_name(indexer_name),
+                    &kv_store_history_table_name(indexer_name),
+                ),
+                &[&network, &EthereumSqlTypeWrapper::U64(from_block)],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// History older than the reorg safe distance can never be rolled back so can be pruned
+    pub async fn prune_history(
+        client: &PostgresClient,
+        indexer_name: &str,
+        network: &str,
+        below_block: U64,
+    ) -> Result<u64, KeyValueStoreError> {
+        let pruned = client
+            .execute(
+                &prune_history_sql(&kv_store_history_table_name(indexer_name)),
+                &[&network, &EthereumSqlTypeWrapper::U64(below_block)],
+            )
+            .await?;
+
+        Ok(pruned)
+    }
+}
+
+/// Keeps pruning the key value store history below the blocks the reorg watcher still checks, in
+/// the background whether the watcher runs or not so the history does not grow without bound
+pub fn start_kv_store_history_pruner(
+    client: Arc<PostgresClient>,
+    indexer_name: &str,
+    providers: HashMap<String, Arc<JsonRpcCachedProvider>>,
+) {
+    let indexer_name = indexer_name.to_string();
+    tokio::spawn(async move {
+        while is_running() {
+            tokio::time::sleep(HISTORY_PRUNE_INTERVAL).await;
+
+            for (network, provider) in &providers {
+                let below_block = match reorg_check_from_block(provider).await {
+                    Ok(below_block) => below_block,
+                    Err(e) => {
+                        error!(
+                            "Could not get the head of {} to prune the key value store history: {}",
+                            network, e
+                        );
+                        continue;
+                    }
+                };
+
+                match KeyValueStore::prune_history(&client, &indexer_name, network, below_block)
+                    .await
+                {
+                    Ok(pruned) if pruned > 0 => debug!(
+                        "Pruned {} key value store history rows before block {} on {}",
+                        pruned, below_block, network
+                    ),
+                    Ok(_) => {}
+                    Err(e) => {
+                        error!("Could not prune the key value store history of {}: {}", network, e)
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::testing::postgres::TestPostgres;
+
+    const INDEXER: &str = "KvIndexer";
+
+    async fn value(store: &KeyValueStore, key: &str) -> Option<u64> {
+        store.get(key).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rollback_and_prune_history() {
+        let postgres = TestPostgres::start().await.unwrap();
+        let client = Arc::new(PostgresClient::new().await.unwrap());
+        client.batch_execute("CREATE SCHEMA IF NOT EXISTS rindexer_internal;").await.unwrap();
+        client.batch_execute(generate_kv_store_tables_sql(INDEXER).as_str()).await.unwrap();
+        let store = KeyValueStore::new(Arc::clone(&client), INDEXER, "counts");
+        let other = KeyValueStore::new(Arc::clone(&client), INDEXER, "other");
+
+        store.set("count", &1u64, "ethereum", U64::from(100)).await.unwrap();
+        store.set("count", &2u64, "ethereum", U64::from(101)).await.unwrap();
+        store.set("count", &3u64, "ethereum", U64::from(102)).await.unwrap();
+        store.set("removed", &7u64, "ethereum", U64::from(99)).await.unwrap();
+        store.delete("removed", "ethereum", U64::from(101)).await.unwrap();
+        store.set("added", &5u64, "ethereum", U64::from(102)).await.unwrap();
+        other.set("count", &9u64, "ethereum", U64::from(101)).await.unwrap();
+        store.set("base", &4u64, "base", U64::from(101)).await.unwrap();
+
+        KeyValueStore::rollback(&client, INDEXER, "ethereum", U64::from(101)).await.unwrap();
+
+        // each key is back to what it held before block 101, in every namespace
+        assert_eq!(value(&store, "count").await, Some(1));
+        assert_eq!(value(&store, "removed").await, Some(7));
+        assert_eq!(value(&store, "added").await, None);
+        assert_eq!(value(&other, "count").await, None);
+        // writes on other networks are left alone
+        assert_eq!(value(&store, "base").await, Some(4));
+
+        store.set("count", &2u64, "ethereum", U64::from(101)).await.unwrap();
+        store.set("count", &3u64, "ethereum", U64::from(102)).await.unwrap();
+        let pruned = KeyValueStore::prune_history(&client, INDEXER, "ethereum", U64::from(101))
+            .await
+            .unwrap();
+        // the writes of block 99 and 100
+        assert_eq!(pruned, 2);
+
+        // the history from the block pruned at on is still there to roll back
+        KeyValueStore::rollback(&client, INDEXER, "ethereum", U64::from(102)).await.unwrap();
+        assert_eq!(value(&store, "count").await, Some(2));
+
+        drop(postgres);
+    }
+}
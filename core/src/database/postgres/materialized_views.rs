@@ -0,0 +1,240 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use ethers::utils::keccak256;
+use tracing::{error, info};
+
+use crate::{
+    database::postgres::client::{PostgresClient, PostgresError},
+    helpers::camel_to_snake,
+    is_running,
+    manifest::storage::MaterializedView,
+    provider::JsonRpcCachedProvider,
+};
+
+/// How often the chain head is checked for views refreshed every N blocks
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The view comment holds a hash of the definition so a changed query gets the view recreated
+const DEFINITION_COMMENT_PREFIX: &str = "rindexer:";
+
+pub fn materialized_views_schema_name(indexer_name: &str) -> String {
+    format!("{}_views", camel_to_snake(indexer_name))
+}
+
+fn definition_comment(view: &MaterializedView) -> String {
+    let definition = format!("{}{:?}", view.query.trim(), view.unique_key);
+    format!("{}{}", DEFINITION_COMMENT_PREFIX, hex::encode(keccak256(definition.as_bytes())))
+}
+
+fn create_materialized_view_sql(schema_name: &str, view: &MaterializedView) -> String {
+    let view_name = format!("{}.{}", schema_name, view.name);
+    let mut sql = format!(
+        "DROP MATERIALIZED VIEW IF EXISTS {view_name} CASCADE;\
+         CREATE MATERIALIZED VIEW {view_name} AS {query};",
+        view_name = view_name,
+        query = view.query.trim().trim_end_matches(';')
+    );
+
+    if let Some(unique_key) = &view.unique_key {
+        sql.push_str(&format!(
+            "CREATE UNIQUE INDEX {}_unique_idx ON {} ({});",
+            view.name,
+            view_name,
+            unique_key
+                .iter()
+                .map(|column| format!("\"{}\"", column))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    sql.push_str(&format!(
+        "COMMENT ON MATERIALIZED VIEW {} IS '{}';",
+        view_name,
+        definition_comment(view)
+    ));
+
+    sql
+}
+
+/// rindexer owns the views schema, views no longer in the manifest are dropped and views whose
+/// definition changed are recreated, the rest are left alone so a restart does not rebuild them
+pub async fn create_materialized_views(
+    client: &PostgresClient,
+    indexer_name: &str,
+    views: &[MaterializedView],
+) -> Result<(), PostgresError> {
+    let schema_name = materialized_views_schema_name(indexer_name);
+    client.batch_execute(&format!("CREATE SCHEMA IF NOT EXISTS {};", schema_name)).await?;
+
+    let existing: HashMap<String, Option<String>> = client
+        .query(
+            "SELECT c.relname::TEXT, obj_description(c.oid, 'pg_class') FROM pg_class c \
+             JOIN pg_namespace n ON n.oid = c.relnamespace \
+             WHERE n.nspname = $1 AND c.relkind = 'm'",
+            &[&schema_name],
+        )
+        .await?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+
+    let mut sql = String::new();
+    for name in existing.keys() {
+        if !views.iter().any(|view| &view.name == name) {
+            info!("Dropping materialized view {}.{} as it is no longer defined", schema_name, name);
+            sql.push_str(&format!(
+                "DROP MATERIALIZED VIEW IF EXISTS {}.{} CASCADE;",
+                schema_name, name
+            ));
+        }
+    }
+
+    for view in views {
+        if existing.get(&view.name).cloned().flatten() == Some(definition_comment(view)) {
+            continue;
+        }
+
+        info!("Creating materialized view {}.{}", schema_name, view.name);
+        sql.push_str(&create_materialized_view_sql(&schema_name, view));
+    }
+
+    if !sql.is_empty() {
+        // one batch runs as a single transaction so a bad query leaves the old views in place
+        client.batch_execute(&sql).await?;
+    }
+
+    Ok(())
+}
+
+async fn refresh_materialized_view(
+    client: &PostgresClient,
+    schema_name: &str,
+    view: &MaterializedView,
+) {
+    // concurrently needs the unique index but does not block readers
+    let sql = format!(
+        "REFRESH MATERIALIZED VIEW {}{}.{};",
+        if view.unique_key.is_some() { "CONCURRENTLY " } else { "" },
+        schema_name,
+        view.name
+    );
+
+    match client.batch_execute(&sql).await {
+        Ok(()) => info!("Refreshed materialized view {}.{}", schema_name, view.name),
+        Err(e) => {
+            error!("Could not refresh materialized view {}.{}: {}", schema_name, view.name, e)
+        }
+    }
+}
+
+async fn refresh_on_interval(
+    client: Arc<PostgresClient>,
+    schema_name: String,
+    view: MaterializedView,
+    interval: Duration,
+) {
+    while is_running() {
+        tokio::time::sleep(interval).await;
+        refresh_materialized_view(&client, &schema_name, &view).await;
+    }
+}
+
+async fn refresh_every_blocks(
+    client: Arc<PostgresClient>,
+    schema_name: String,
+    view: MaterializedView,
+    blocks: u64,
+    provider: Arc<JsonRpcCachedProvider>,
+) {
+    let mut last_refreshed_block: Option<u64> = None;
+    while is_running() {
+        tokio::time::sleep(BLOCK_POLL_INTERVAL).await;
+
+        let head = match provider.get_block_number().await {
+            Ok(head) => head.as_u64(),
+            Err(e) => {
+                error!("Could not get the block number to refresh {}: {}", view.name, e);
+                continue;
+            }
+        };
+
+        match last_refreshed_block {
+            // the view was built on startup so the first head is the starting point
+            None => last_refreshed_block = Some(head),
+            Some(last) if head >= last + blocks => {
+                refresh_materialized_view(&client, &schema_name, &view).await;
+                last_refreshed_block = Some(head);
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Refreshes every view in the background on its own schedule, only the indexer which owns the
+/// tables should run this
+pub fn start_materialized_views_refresh(
+    client: Arc<PostgresClient>,
+    indexer_name: &str,
+    views: &[MaterializedView],
+    providers: &HashMap<String, Arc<JsonRpcCachedProvider>>,
+) {
+    let schema_name = materialized_views_schema_name(indexer_name);
+    for view in views {
+        if let Some(interval_seconds) = view.refresh.interval_seconds {
+            tokio::spawn(refresh_on_interval(
+                Arc::clone(&client),
+                schema_name.clone(),
+                view.clone(),
+                Duration::from_secs(interval_seconds),
+            ));
+        } else if let Some(every_blocks) = view.refresh.every_blocks {
+            let network = view.refresh.network.as_deref().unwrap_or_default();
+            let Some(provider) = providers.get(network) else {
+                error!(
+                    "Materialized view {} refreshes on network {} blocks but nothing is indexed on it - it will not be refreshed",
+                    view.name, network
+                );
+                continue;
+            };
+
+            tokio::spawn(refresh_every_blocks(
+                Arc::clone(&client),
+                schema_name.clone(),
+                view.clone(),
+                every_blocks,
+                Arc::clone(provider),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::storage::MaterializedViewRefresh;
+
+    #[test]
+    fn test_create_materialized_view_sql() {
+        let view = MaterializedView {
+            name: "daily_volume".to_string(),
+            query: "SELECT network, COUNT(*) AS transfers FROM a.transfer GROUP BY network;"
+                .to_string(),
+            unique_key: Some(vec!["network".to_string()]),
+            refresh: MaterializedViewRefresh {
+                interval_seconds: Some(60),
+                every_blocks: None,
+                network: None,
+            },
+        };
+
+        let sql = create_materialized_view_sql("indexer_views", &view);
+        assert!(sql.contains(
+            "CREATE MATERIALIZED VIEW indexer_views.daily_volume AS SELECT network, COUNT(*) AS transfers FROM a.transfer GROUP BY network;"
+        ));
+        assert!(sql.contains(
+            "CREATE UNIQUE INDEX daily_volume_unique_idx ON indexer_views.daily_volume (\"network\");"
+        ));
+        assert!(sql.ends_with(&format!("IS '{}';", definition_comment(&view))));
+    }
+}
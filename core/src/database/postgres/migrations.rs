@@ -0,0 +1,155 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ethers::utils::keccak256;
+use tracing::info;
+
+use crate::{
+    database::postgres::{
+        client::{PostgresClient, PostgresError},
+        sql_type_wrapper::EthereumSqlTypeWrapper,
+    },
+    helpers::camel_to_snake,
+};
+
+/// Project SQL migrations live here, applied in file name order before indexing starts
+pub const MIGRATIONS_FOLDER: &str = "migrations";
+
+#[derive(thiserror::Error, Debug)]
+pub enum MigrationsError {
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("Could not read the migrations: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Migration {0} failed: {1}")]
+    MigrationFailed(String, PostgresError),
+
+    #[error("Migration {0} was changed after it was applied - add a new migration instead")]
+    MigrationChanged(String),
+}
+
+/// A SQL file from the migrations folder, the name is the file name
+#[derive(Debug, PartialEq)]
+struct Migration {
+    name: String,
+    sql: String,
+    checksum: String,
+}
+
+fn migrations_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_migrations", camel_to_snake(indexer_name))
+}
+
+fn read_migrations(folder: &Path) -> Result<Vec<Migration>, std::io::Error> {
+    if !folder.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(folder)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "sql"))
+        .collect();
+    // prefix the files with a number or timestamp to control the order
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let sql = fs::read_to_string(&path)?;
+            Ok(Migration {
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                checksum: hex::encode(keccak256(sql.as_bytes())),
+                sql,
+            })
+        })
+        .collect()
+}
+
+/// Applies the SQL files in the project `migrations` folder which have not been applied yet, each
+/// in its own transaction alongside recording it, so a failed migration can be fixed and retried.
+/// Migrations should not manage transactions themselves.
+pub async fn run_migrations(
+    client: &PostgresClient,
+    project_path: &Path,
+    indexer_name: &str,
+) -> Result<(), MigrationsError> {
+    let migrations = read_migrations(&project_path.join(MIGRATIONS_FOLDER))?;
+    if migrations.is_empty() {
+        return Ok(());
+    }
+
+    let table_name = migrations_table_name(indexer_name);
+    client
+        .batch_execute(&format!(
+            "CREATE SCHEMA IF NOT EXISTS rindexer_internal;
+             CREATE TABLE IF NOT EXISTS {} (
+                name TEXT PRIMARY KEY,
+                checksum TEXT NOT NULL,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+             );",
+            table_name
+        ))
+        .await?;
+
+    let applied: Vec<(String, String)> = client
+        .query(&format!("SELECT name, checksum FROM {}", table_name), &[])
+        .await?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+
+    for migration in migrations {
+        match applied.iter().find(|(name, _)| *name == migration.name) {
+            Some((_, checksum)) if *checksum != migration.checksum => {
+                return Err(MigrationsError::MigrationChanged(migration.name));
+            }
+            Some(_) => continue,
+            None => {}
+        }
+
+        info!("Applying migration {}", migration.name);
+        client
+            .batch_execute_in_transaction(
+                &migration.sql,
+                &[(
+                    format!("INSERT INTO {} (name, checksum) VALUES ($1, $2)", table_name),
+                    vec![
+                        EthereumSqlTypeWrapper::String(migration.name.clone()),
+                        EthereumSqlTypeWrapper::String(migration.checksum.clone()),
+                    ],
+                )],
+            )
+            .await
+            .map_err(|e| MigrationsError::MigrationFailed(migration.name.clone(), e))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_read_migrations_in_name_order() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("002_views.sql"), "CREATE VIEW b AS SELECT 1;").unwrap();
+        fs::write(dir.path().join("001_tables.sql"), "CREATE TABLE a (id INT);").unwrap();
+        fs::write(dir.path().join("notes.md"), "not a migration").unwrap();
+
+        let migrations = read_migrations(dir.path()).unwrap();
+        let names: Vec<&str> = migrations.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["001_tables.sql", "002_views.sql"]);
+
+        assert!(read_migrations(&dir.path().join("missing")).unwrap().is_empty());
+    }
+}
@@ -1,6 +1,27 @@
+pub mod address_labels;
+pub mod block_timestamps;
 pub mod client;
+pub mod contracts_seen;
+pub mod derived_fields;
+pub mod encryption;
+pub mod entities;
 pub mod generate;
 pub mod indexes;
+pub mod kv_store;
+pub mod materialized_views;
+pub mod migrations;
+pub mod notify;
+pub mod ranges;
+pub mod raw_logs;
 pub mod relationship;
+pub mod reorg;
+pub mod retention;
+pub mod retry_queue;
+pub mod roles;
+pub mod rollups;
+pub mod schema_evolution;
+pub mod schema_plan;
 pub mod setup;
+pub mod spam_contracts;
 pub mod sql_type_wrapper;
+pub mod state_samples;
@@ -0,0 +1,55 @@
+const NOTIFY_FUNCTION: &str = "rindexer_internal.notify_new_event";
+
+const NOTIFY_TRIGGER: &str = "rindexer_notify_new_event";
+
+/// The trigger function the event tables share, the channel is the trigger argument and the
+/// payload is `{"table": "<schema>.<table>", "rindexer_id": <id>}`
+pub fn generate_notify_function_sql() -> String {
+    format!(
+        "CREATE OR REPLACE FUNCTION {function}() RETURNS TRIGGER AS $$ \
+         BEGIN \
+         PERFORM pg_notify(TG_ARGV[0], json_build_object('table', TG_TABLE_SCHEMA || '.' || TG_TABLE_NAME, 'rindexer_id', NEW.rindexer_id)::text); \
+         RETURN NULL; \
+         END; \
+         $$ LANGUAGE plpgsql;",
+        function = NOTIFY_FUNCTION
+    )
+}
+
+/// Notifies the channel for every row inserted into the table, a trigger rather than a NOTIFY
+/// from the insert so rows written with COPY get their primary key in the payload too. Without a
+/// channel the trigger is dropped so turning it off in the manifest takes effect on the next run.
+pub fn notify_trigger_sql(table_name: &str, channel: Option<&str>) -> String {
+    let drop_trigger = format!("DROP TRIGGER IF EXISTS {} ON {};", NOTIFY_TRIGGER, table_name);
+    match channel {
+        Some(channel) => format!(
+            "{drop_trigger}\nCREATE TRIGGER {trigger} AFTER INSERT ON {table} FOR EACH ROW \
+             EXECUTE FUNCTION {function}('{channel}');",
+            drop_trigger = drop_trigger,
+            trigger = NOTIFY_TRIGGER,
+            table = table_name,
+            function = NOTIFY_FUNCTION,
+            channel = channel.replace('\'', "''")
+        ),
+        None => drop_trigger,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notify_trigger_is_dropped_without_a_channel() {
+        assert_eq!(
+            notify_trigger_sql("indexer_erc20.transfer", None),
+            "DROP TRIGGER IF EXISTS rindexer_notify_new_event ON indexer_erc20.transfer;"
+        );
+
+        let sql = notify_trigger_sql("indexer_erc20.transfer", Some("new_'events"));
+        assert!(sql.starts_with("DROP TRIGGER IF EXISTS"));
+        assert!(
+            sql.ends_with("EXECUTE FUNCTION rindexer_internal.notify_new_event('new_''events');")
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use ethers::types::U64;
+use rust_decimal::Decimal;
+
+use crate::{
+    database::postgres::client::{PostgresClient, PostgresError},
+    types::code::Code,
+    EthereumSqlTypeWrapper,
+};
+
+const RANGES_TABLE: &str = "rindexer_internal.ranges";
+
+pub fn generate_ranges_table_sql() -> Code {
+    Code::new(format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            indexer_name TEXT NOT NULL,
+            contract_name TEXT NOT NULL,
+            event_name TEXT NOT NULL,
+            network TEXT NOT NULL,
+            from_block NUMERIC NOT NULL,
+            to_block NUMERIC NOT NULL,
+            completed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (indexer_name, contract_name, event_name, network, from_block, to_block)
+        );
+    "#,
+        table = RANGES_TABLE
+    ))
+}
+
+/// The table is shared by every indexer in the database so only the rows of the indexer go
+pub fn drop_ranges_for_indexer_sql(indexer_name: &str) -> Code {
+    Code::new(format!(
+        "DO $$ BEGIN IF to_regclass('{table}') IS NOT NULL THEN DELETE FROM {table} WHERE indexer_name = '{indexer_name}'; END IF; END $$;",
+        table = RANGES_TABLE,
+        indexer_name = indexer_name.replace('\'', "''")
+    ))
+}
+
+fn decimal_to_u64(value: Decimal) -> U64 {
+    U64::from_dec_str(&value.to_string()).expect("Failed to parse range block number")
+}
+
+/// The ranges of an event on a network which completed after its checkpoint, ranges finish out
+/// of order when they are processed concurrently so the checkpoint can only move up to the
+/// first one still missing
+#[derive(Clone)]
+pub struct CompletedRangesStore {
+    client: Arc<PostgresClient>,
+    indexer_name: String,
+    contract_name: String,
+    event_name: String,
+    network: String,
+}
+
+impl CompletedRangesStore {
+    pub fn new(
+        client: Arc<PostgresClient>,
+        indexer_name: &str,
+        contract_name: &str,
+        event_name: &str,
+        network: &str,
+    ) -> Self {
+        Self {
+            client,
+            indexer_name: indexer_name.to_string(),
+            contract_name: contract_name.to_string(),
+            event_name: event_name.to_string(),
+            network: network.to_string(),
+        }
+    }
+
+    pub async fn insert(&self, from_block: U64, to_block: U64) -> Result<(), PostgresError> {
+        self.client
+            .execute(
+                &format!(
+                    "INSERT INTO {} (indexer_name, contract_name, event_name, network, from_block, to_block) \
+                     VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING",
+                    RANGES_TABLE
+                ),
+                &[
+                    &self.indexer_name,
+                    &self.contract_name,
+                    &self.event_name,
+                    &self.network,
+                    &EthereumSqlTypeWrapper::U64(from_block),
+                    &EthereumSqlTypeWrapper::U64(to_block),
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Drops the ranges the checkpoint covers
+    pub async fn prune(&self, up_to_block: U64) -> Result<(), PostgresError> {
+        self.client
+            .execute(
+                &format!(
+                    "DELETE FROM {} WHERE indexer_name = $1 AND contract_name = $2 AND event_name = $3 AND network = $4 AND to_block <= $5",
+                    RANGES_TABLE
+                ),
+                &[
+                    &self.indexer_name,
+                    &self.contract_name,
+                    &self.event_name,
+                    &self.network,
+                    &EthereumSqlTypeWrapper::U64(up_to_block),
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// The ranges which completed past the block
+    pub async fn after(&self, block: U64) -> Result<Vec<(U64, U64)>, PostgresError> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT from_block, to_block FROM {} WHERE indexer_name = $1 AND contract_name = $2 AND event_name = $3 AND network = $4 AND to_block >= $5 ORDER BY from_block",
+                    RANGES_TABLE
+                ),
+                &[
+                    &self.indexer_name,
+                    &self.contract_name,
+                    &self.event_name,
+                    &self.network,
+                    &EthereumSqlTypeWrapper::U64(block),
+                ],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (decimal_to_u64(row.get("from_block")), decimal_to_u64(row.get("to_block"))))
+            .collect())
+    }
+}
@@ -0,0 +1,222 @@
+use std::{str::FromStr, sync::Arc};
+
+use ethers::types::{Address, Bytes, Log, H256, U256, U64};
+use rust_decimal::Decimal;
+use tokio_postgres::{types::ToSql, Row};
+
+use crate::{
+    database::postgres::client::{PostgresClient, PostgresError},
+    helpers::camel_to_snake,
+    types::code::Code,
+    EthereumSqlTypeWrapper,
+};
+
+/// Rows per insert, well under the postgres limit of 65535 parameters
+const ARCHIVE_CHUNK_SIZE: usize = 1000;
+
+pub fn raw_logs_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_raw_logs", camel_to_snake(indexer_name))
+}
+
+pub fn generate_raw_logs_table_sql(indexer_name: &str) -> Code {
+    Code::new(format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            contract_name TEXT NOT NULL,
+            event_name TEXT NOT NULL,
+            network TEXT NOT NULL,
+            address CHAR(42) NOT NULL,
+            topics TEXT[],
+            data BYTEA NOT NULL,
+            tx_hash CHAR(66) NOT NULL,
+            tx_index NUMERIC NOT NULL,
+            block_number NUMERIC NOT NULL,
+            block_hash CHAR(66) NOT NULL,
+            log_index NUMERIC NOT NULL,
+            PRIMARY KEY (contract_name, event_name, network, block_hash, log_index)
+        );
+        CREATE INDEX IF NOT EXISTS {indexer_name}_raw_logs_block_number_idx ON {table} (contract_name, event_name, network, block_number, log_index);
+    "#,
+        table = raw_logs_table_name(indexer_name),
+        indexer_name = camel_to_snake(indexer_name)
+    ))
+}
+
+pub fn drop_raw_logs_table_sql(indexer_name: &str) -> Code {
+    Code::new(format!("DROP TABLE IF EXISTS {} CASCADE;", raw_logs_table_name(indexer_name)))
+}
+
+fn decimal_to_u64(value: Decimal) -> U64 {
+    U64::from_dec_str(&value.to_string()).expect("Failed to parse archived log number")
+}
+
+fn parse_hash(value: &str) -> H256 {
+    H256::from_str(value.trim()).expect("Failed to parse archived log hash")
+}
+
+fn row_to_log(row: &Row) -> Log {
+    let topics: Option<Vec<String>> = row.get("topics");
+    let log_index = decimal_to_u64(row.get("log_index"));
+    Log {
+        address: Address::from_str(row.get::<_, String>("address").trim())
+            .expect("Failed to parse archived log address"),
+        topics: topics.unwrap_or_default().iter().map(|topic| parse_hash(topic)).collect(),
+        data: Bytes::from(row.get::<_, Vec<u8>>("data")),
+        block_hash: Some(parse_hash(row.get("block_hash"))),
+        block_number: Some(decimal_to_u64(row.get("block_number"))),
+        transaction_hash: Some(parse_hash(row.get("tx_hash"))),
+        transaction_index: Some(decimal_to_u64(row.get("tx_index"))),
+        log_index: Some(U256::from(log_index.as_u64())),
+        transaction_log_index: None,
+        log_type: None,
+        removed: Some(false),
+    }
+}
+
+/// Removes the archived logs of a block which was reorged out
+pub async fn remove_orphaned_raw_logs(
+    client: &PostgresClient,
+    table: &str,
+    network: &str,
+    block_number: U64,
+    block_hash: H256,
+) -> Result<u64, PostgresError> {
+    client
+        .execute(
+            &format!(
+                "DELETE FROM {} WHERE network = $1 AND block_number = $2 AND block_hash = $3",
+                table
+            ),
+            &[&network, &EthereumSqlTypeWrapper::U64(block_number), &format!("{:?}", block_hash)],
+        )
+        .await
+}
+
+/// The undecoded logs of an event as they came from the node, kept so the event can be decoded
+/// again after an ABI fix without fetching the logs again
+#[derive(Clone)]
+pub struct RawLogArchive {
+    client: Arc<PostgresClient>,
+    table: String,
+    contract_name: String,
+    event_name: String,
+}
+
+impl RawLogArchive {
+    pub fn new(
+        client: Arc<PostgresClient>,
+        indexer_name: &str,
+        contract_name: &str,
+        event_name: &str,
+    ) -> Self {
+        Self {
+            client,
+            table: raw_logs_table_name(indexer_name),
+            contract_name: contract_name.to_string(),
+            event_name: event_name.to_string(),
+        }
+    }
+
+    /// Logs archived already, when a range is indexed again, are left as they are
+    pub async fn archive(&self, network: &str, logs: &[&Log]) -> Result<(), PostgresError> {
+        for chunk in logs.chunks(ARCHIVE_CHUNK_SIZE) {
+            let rows: Vec<Vec<EthereumSqlTypeWrapper>> = chunk
+                .iter()
+                .map(|log| {
+                    vec![
+                        EthereumSqlTypeWrapper::String(self.contract_name.clone()),
+                        EthereumSqlTypeWrapper::String(self.event_name.clone()),
+                        EthereumSqlTypeWrapper::String(network.to_string()),
+                        EthereumSqlTypeWrapper::Address(log.address),
+                        EthereumSqlTypeWrapper::VecH256(log.topics.clone()),
+                        EthereumSqlTypeWrapper::Bytes(log.data.clone()),
+                        EthereumSqlTypeWrapper::H256(log.transaction_hash.unwrap_or_default()),
+                        EthereumSqlTypeWrapper::U64(log.transaction_index.unwrap_or_default()),
+                        EthereumSqlTypeWrapper::U64(log.block_number.unwrap_or_default()),
+                        EthereumSqlTypeWrapper::H256(log.block_hash.unwrap_or_default()),
+                        EthereumSqlTypeWrapper::U64(U64::from(
+                            log.log_index.unwrap_or_default().low_u64(),
+                        )),
+                    ]
+                })
+                .collect();
+
+            let columns = rows.first().map_or(0, |row| row.len());
+            let placeholders = (0..rows.len())
+                .map(|row| {
+                    let row_placeholders = (1..=columns)
+                        .map(|column| format!("${}", row * columns + column))
+                        .collect::<Vec<_>>();
+                    format!("({})", row_placeholders.join(","))
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            let params: Vec<&(dyn ToSql + Sync)> =
+                rows.iter().flatten().map(|param| param as &(dyn ToSql + Sync)).collect();
+
+            self.client
+                .execute(
+                    &format!(
+                        "INSERT INTO {} (contract_name, event_name, network, address, topics, data, tx_hash, tx_index, block_number, block_hash, log_index) \
+                         VALUES {} ON CONFLICT DO NOTHING",
+                        self.table, placeholders
+                    ),
+                    &params,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// The archived logs of a network in block order, after the `(block_number, log_index)` of
+    /// the last log of the previous page
+    pub async fn read_page(
+        &self,
+        network: &str,
+        after: Option<(U64, U64)>,
+        limit: i64,
+    ) -> Result<Vec<Log>, PostgresError> {
+        // the first page starts below any block
+        let (block_number, log_index) = after.map_or(
+            (Decimal::NEGATIVE_ONE, Decimal::NEGATIVE_ONE),
+            |(block_number, log_index)| {
+                (Decimal::from(block_number.as_u64()), Decimal::from(log_index.as_u64()))
+            },
+        );
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT address, topics, data, tx_hash, tx_index, block_number, block_hash, log_index FROM {} \
+                     WHERE contract_name = $1 AND event_name = $2 AND network = $3 AND (block_number, log_index) > ($4, $5) \
+                     ORDER BY block_number, log_index LIMIT $6",
+                    self.table
+                ),
+                &[&self.contract_name, &self.event_name, &network, &block_number, &log_index, &limit],
+            )
+            .await?;
+
+        Ok(rows.iter().map(row_to_log).collect())
+    }
+
+    /// Deletes the rows of the event table which were decoded from the archived logs of the
+    /// network, rows indexed before the archive was turned on are left alone
+    pub async fn remove_decoded_rows(
+        &self,
+        event_table: &str,
+        network: &str,
+    ) -> Result<u64, PostgresError> {
+        self.client
+            .execute(
+                &format!(
+                    "DELETE FROM {} t USING {} r \
+                     WHERE r.contract_name = $1 AND r.event_name = $2 AND r.network = $3 \
+                     AND t.network = r.network AND t.tx_hash = r.tx_hash AND t.log_index = r.log_index::TEXT",
+                    event_table, self.table
+                ),
+                &[&self.contract_name, &self.event_name, &network],
+            )
+            .await
+    }
+}
@@ -0,0 +1,494 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use ethers::{
+    providers::ProviderError,
+    types::{H256, U64},
+};
+use serde_json::Value;
+use tracing::{error, warn};
+
+use crate::{
+    abi::ABIItem,
+    database::postgres::{
+        client::{PostgresClient, PostgresError},
+        entities::{rollback_entities, EntitiesError},
+        generate::generate_event_table_full_name,
+        kv_store::{KeyValueStore, KeyValueStoreError},
+        raw_logs::{raw_logs_table_name, remove_orphaned_raw_logs},
+        rollups::{rollback_rollups, RollupsError},
+        sql_type_wrapper::EthereumSqlTypeWrapper,
+    },
+    indexer::{
+        chain_family, pause::event_pause_key, reorg_safe_distance_for_chain, request_reorg_rewind,
+        rewind_checkpoint,
+    },
+    is_running,
+    manifest::{
+        contract::Contract,
+        storage::{Entity, Rollup},
+        stream::StreamsConfig,
+    },
+    provider::JsonRpcCachedProvider,
+    streams::StreamsClients,
+};
+
+const REORG_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Only the most recent rows can be in blocks which are still reorgable
+const RECENT_ROWS_CHECKED: i64 = 1_000;
+
+#[derive(thiserror::Error, Debug)]
+enum ReorgCheckError {
+    #[error("{0}")]
+    Provider(#[from] ProviderError),
+
+    #[error("{0}")]
+    Postgres(#[from] PostgresError),
+
+    #[error("{0}")]
+    KeyValueStore(#[from] KeyValueStoreError),
+
+    #[error("{0}")]
+    Entities(#[from] EntitiesError),
+
+    #[error("{0}")]
+    Rollups(#[from] RollupsError),
+
+    #[error("Could not parse the indexed block {0}")]
+    InvalidIndexedBlock(String),
+}
+
+/// The columns soft deleted rows are marked with, added to existing tables as well
+pub fn soft_delete_columns_sql(table_name: &str) -> String {
+    format!(
+        "ALTER TABLE {} ADD COLUMN IF NOT EXISTS removed_by_reorg BOOLEAN NOT NULL DEFAULT FALSE, \
+         ADD COLUMN IF NOT EXISTS replaced_by_block_hash CHAR(66);",
+        table_name
+    )
+}
+
+/// A block rows were indexed from which is no longer on the canonical chain
+#[derive(Debug, PartialEq, Eq)]
+struct OrphanedBlock {
+    block_number: U64,
+    orphaned_hash: H256,
+    canonical_hash: H256,
+}
+
+/// An event table and the contract event its rows come from
+struct WatchedTable {
+    name: String,
+    contract_name: String,
+    event_name: String,
+    /// The names the event is indexed under by each contract writing to the table, filter
+    /// contracts are indexed under their filter name
+    indexed_contract_names: Vec<String>,
+}
+
+/// Contracts indexed with `reorg_safe_distance` never see a reorg so only the event tables of
+/// contracts indexing at the head are watched, grouped by network
+fn reorg_watch_tables(
+    project_path: &Path,
+    indexer_name: &str,
+    contracts: &[Contract],
+) -> HashMap<String, Vec<WatchedTable>> {
+    let mut tables: HashMap<String, Vec<WatchedTable>> = HashMap::new();
+    for contract in contracts {
+        if contract.reorg_safe_distance.unwrap_or_default() {
+            continue;
+        }
+
+        let events = ABIItem::read_abi_items(project_path, contract)
+            .map_err(|e| e.to_string())
+            .and_then(|items| {
+                ABIItem::extract_event_names_and_signatures_from_abi(items)
+                    .map_err(|e| e.to_string())
+            });
+        let events = match events {
+            Ok(events) => events,
+            Err(e) => {
+                error!("Reorg watcher could not read the ABI for {}: {}", contract.name, e);
+                continue;
+            }
+        };
+
+        for details in &contract.details {
+            let network_tables = tables.entry(details.network.clone()).or_default();
+            for event in &events {
+                let table_name = generate_event_table_full_name(
+                    indexer_name,
+                    &contract.table_name_readonly(),
+                    &event.name,
+                );
+                let indexed_contract_name = contract.before_modify_name_if_filter_readonly();
+                match network_tables.iter_mut().find(|table| table.name == table_name) {
+                    Some(table) => {
+                        if !table.indexed_contract_names.iter().any(|n| *n == indexed_contract_name)
+                        {
+                            table.indexed_contract_names.push(indexed_contract_name.to_string());
+                        }
+                    }
+                    None => network_tables.push(WatchedTable {
+                        name: table_name,
+                        contract_name: contract.name.clone(),
+                        event_name: event.name.clone(),
+                        indexed_contract_names: vec![indexed_contract_name.to_string()],
+                    }),
+                }
+            }
+        }
+    }
+
+    tables
+}
+
+/// The distinct blocks of the most recent rows of the table from `from_block`
+async fn recent_indexed_blocks(
+    client: &PostgresClient,
+    table_name: &str,
+    network: &str,
+    from_block: U64,
+    soft_delete: bool,
+) -> Result<Vec<(U64, H256)>, ReorgCheckError> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT DISTINCT block_number::TEXT, block_hash::TEXT FROM (\
+                 SELECT block_number, block_hash FROM {} WHERE network = $1{} \
+                 ORDER BY rindexer_id DESC LIMIT $2) recent WHERE block_number >= $3",
+                table_name,
+                if soft_delete { " AND NOT removed_by_reorg" } else { "" }
+            ),
+            &[&network, &RECENT_ROWS_CHECKED, &EthereumSqlTypeWrapper::U64(from_block)],
+        )
+        .await?;
+
+    rows.iter()
+        .map(|row| {
+            let block_number: String = row.get(0);
+            let block_hash: String = row.get(1);
+            let block_number = U64::from_dec_str(&block_number)
+                .map_err(|_| ReorgCheckError::InvalidIndexedBlock(block_number.clone()))?;
+            let indexed_hash = H256::from_str(block_hash.trim())
+                .map_err(|_| ReorgCheckError::InvalidIndexedBlock(block_hash.clone()))?;
+            Ok((block_number, indexed_hash))
+        })
+        .collect()
+}
+
+/// The indexed blocks whose number the chain now has another block at, a block the node does
+/// not have yet says nothing about a reorg
+fn find_orphaned_blocks(
+    indexed: &[(U64, H256)],
+    canonical_hashes: &HashMap<U64, H256>,
+) -> Vec<OrphanedBlock> {
+    indexed
+        .iter()
+        .filter_map(|(block_number, indexed_hash)| {
+            let canonical_hash = canonical_hashes.get(block_number)?;
+            (canonical_hash != indexed_hash).then_some(OrphanedBlock {
+                block_number: *block_number,
+                orphaned_hash: *indexed_hash,
+                canonical_hash: *canonical_hash,
+            })
+        })
+        .collect()
+}
+
+/// Every block is looked up once however many tables have rows in it, through the block cache
+/// of the network which the indexing of the head fills already
+async fn canonical_block_hashes(
+    provider: &JsonRpcCachedProvider,
+    block_numbers: BTreeSet<U64>,
+) -> Result<HashMap<U64, H256>, ProviderError> {
+    let mut hashes = HashMap::new();
+    for block_number in block_numbers {
+        if let Some(hash) = provider.get_block(block_number).await?.and_then(|block| block.hash) {
+            hashes.insert(block_number, hash);
+        }
+    }
+    Ok(hashes)
+}
+
+fn remove_orphaned_rows_sql(table_name: &str, soft_delete: bool) -> String {
+    if soft_delete {
+        format!(
+            "UPDATE {} t SET removed_by_reorg = TRUE, replaced_by_block_hash = $4 \
+             WHERE network = $1 AND block_number = $2 AND block_hash = $3 AND NOT removed_by_reorg \
+             RETURNING to_jsonb(t)::TEXT",
+            table_name
+        )
+    } else {
+        format!(
+            "DELETE FROM {} t WHERE network = $1 AND block_number = $2 AND block_hash = $3 \
+             RETURNING to_jsonb(t)::TEXT",
+            table_name
+        )
+    }
+}
+
+/// The removed rows as json so they can be streamed as deletes
+async fn remove_orphaned_rows(
+    client: &PostgresClient,
+    table_name: &str,
+    network: &str,
+    orphaned: &OrphanedBlock,
+    soft_delete: bool,
+) -> Result<Vec<Value>, PostgresError> {
+    let block_number = EthereumSqlTypeWrapper::U64(orphaned.block_number);
+    let orphaned_hash = format!("{:?}", orphaned.orphaned_hash);
+    let sql = remove_orphaned_rows_sql(table_name, soft_delete);
+
+    let rows = if soft_delete {
+        let canonical_hash = format!("{:?}", orphaned.canonical_hash);
+        client.query(&sql, &[&network, &block_number, &orphaned_hash, &canonical_hash]).await?
+    } else {
+        client.query(&sql, &[&network, &block_number, &orphaned_hash]).await?
+    };
+
+    Ok(rows.iter().filter_map(|row| serde_json::from_str(&row.get::<_, String>(0)).ok()).collect())
+}
+
+/// The first block which can still be reorged out, the blocks before it are final
+pub async fn reorg_check_from_block(
+    provider: &JsonRpcCachedProvider,
+) -> Result<U64, ProviderError> {
+    let chain_id = provider.get_chain_id().await?;
+    let head = provider.get_block_number().await?;
+    let mut from_block = head.saturating_sub(reorg_safe_distance_for_chain(&chain_id));
+    // a rollup block can still change until L1 backs it, which can take longer than the distance
+    // when the batches are posted late
+    if chain_family(&chain_id).is_l2() {
+        if let Some(safe_block) = provider.get_safe_block_number().await {
+            from_block = from_block.min(safe_block);
+        }
+    }
+
+    Ok(from_block)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn check_network(
+    client: &Arc<PostgresClient>,
+    provider: &JsonRpcCachedProvider,
+    indexer_name: &str,
+    network: &str,
+    tables: &[WatchedTable],
+    entities: &[Entity],
+    rollups: &[Rollup],
+    streams: &HashMap<String, StreamsClients>,
+    raw_logs_table: Option<&str>,
+    soft_delete: bool,
+) -> Result<(), ReorgCheckError> {
+    let from_block = reorg_check_from_block(provider).await?;
+
+    let mut indexed_blocks = Vec::with_capacity(tables.len());
+    for table in tables {
+        indexed_blocks.push(
+            recent_indexed_blocks(client, &table.name, network, from_block, soft_delete).await?,
+        );
+    }
+    let canonical_hashes = canonical_block_hashes(
+        provider,
+        indexed_blocks.iter().flatten().map(|(block_number, _)| *block_number).collect(),
+    )
+    .await?;
+
+    let mut rolled_back_from: Option<U64> = None;
+    for (table, indexed) in tables.iter().zip(&indexed_blocks) {
+        let table_name = &table.name;
+        let orphaned_blocks = find_orphaned_blocks(indexed, &canonical_hashes);
+        let Some(first_orphaned) = orphaned_blocks.iter().map(|o| o.block_number).min() else {
+            continue;
+        };
+
+        for orphaned in orphaned_blocks {
+            let removed_rows =
+                remove_orphaned_rows(client, table_name, network, &orphaned, soft_delete).await?;
+            let rows = removed_rows.len();
+            warn!(
+                "Reorg on network {} - block {} {:?} was replaced by {:?}, {} {} rows from {}",
+                network,
+                orphaned.block_number,
+                orphaned.orphaned_hash,
+                orphaned.canonical_hash,
+                if soft_delete { "marked" } else { "deleted" },
+                rows,
+                table_name
+            );
+            rolled_back_from = Some(
+                rolled_back_from.map_or(orphaned.block_number, |b| b.min(orphaned.block_number)),
+            );
+            if let Some(raw_logs_table) = raw_logs_table {
+                remove_orphaned_raw_logs(
+                    client,
+                    raw_logs_table,
+                    network,
+                    orphaned.block_number,
+                    orphaned.orphaned_hash,
+                )
+                .await?;
+            }
+
+            // the rows are gone already, consumers missing the delete is not worth retrying
+            // the whole check for
+            if let Some(streams) = streams.get(&table.contract_name) {
+                if let Err(e) =
+                    streams.stream_reorg_removals(&table.event_name, network, &removed_rows).await
+                {
+                    error!(
+                        "Reorg watcher could not stream the removed rows of {}: {}",
+                        table_name, e
+                    );
+                }
+            }
+        }
+
+        // every block after a replaced one is replaced too, so the events are indexed again
+        // from the first one both by the running live indexing and after a restart
+        for contract_name in &table.indexed_contract_names {
+            rewind_checkpoint(
+                client,
+                indexer_name,
+                contract_name,
+                &table.event_name,
+                network,
+                first_orphaned - 1,
+            )
+            .await?;
+            request_reorg_rewind(
+                &event_pause_key(indexer_name, contract_name, &table.event_name),
+                network,
+                first_orphaned,
+            );
+        }
+    }
+
+    // handler state, entities and rollups built from the orphaned blocks are not valid anymore
+    // either
+    if let Some(rolled_back_from) = rolled_back_from {
+        KeyValueStore::rollback(client, indexer_name, network, rolled_back_from).await?;
+        rollback_entities(client, indexer_name, entities, network, rolled_back_from).await?;
+        rollback_rollups(client, indexer_name, rollups, network, rolled_back_from).await?;
+    }
+
+    Ok(())
+}
+
+/// Watches the recent rows of contracts indexed at the head and rolls back the ones whose block
+/// was reorged out, deleting them or with `soft_delete` marking them `removed_by_reorg` with the
+/// hash of the replacing block. The checkpoint of the event is moved back before the first
+/// replaced block and live indexing fetches the events of the replacing blocks.
+#[allow(clippy::too_many_arguments)]
+pub fn start_reorg_watcher(
+    client: Arc<PostgresClient>,
+    project_path: &Path,
+    indexer_name: &str,
+    contracts: &[Contract],
+    entities: Vec<Entity>,
+    rollups: Vec<Rollup>,
+    providers: HashMap<String, Arc<JsonRpcCachedProvider>>,
+    soft_delete: bool,
+) {
+    let tables = reorg_watch_tables(project_path, indexer_name, contracts);
+    if tables.is_empty() {
+        return;
+    }
+
+    let debezium_streams: Vec<(String, StreamsConfig)> = contracts
+        .iter()
+        .filter_map(|contract| {
+            let streams = contract.streams.as_ref()?.debezium_only()?;
+            Some((contract.name.clone(), streams))
+        })
+        .collect();
+
+    let raw_logs_table = contracts
+        .iter()
+        .any(|contract| contract.archive_raw_logs())
+        .then(|| raw_logs_table_name(indexer_name));
+    let indexer_name = indexer_name.to_string();
+    tokio::spawn(async move {
+        let mut streams = HashMap::new();
+        for (contract_name, config) in debezium_streams {
+            streams.insert(contract_name, StreamsClients::new(config).await);
+        }
+
+        while is_running() {
+            tokio::time::sleep(REORG_CHECK_INTERVAL).await;
+
+            for (network, network_tables) in &tables {
+                let Some(provider) = providers.get(network) else {
+                    continue;
+                };
+
+                if let Err(e) = check_network(
+                    &client,
+                    provider,
+                    &indexer_name,
+                    network,
+                    network_tables,
+                    &entities,
+                    &rollups,
+                    &streams,
+                    raw_logs_table.as_deref(),
+                    soft_delete,
+                )
+                .await
+                {
+                    error!("Reorg watcher could not check network {}: {}", network, e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_orphaned_blocks() {
+        let indexed = vec![
+            (U64::from(100), H256::repeat_byte(1)),
+            (U64::from(101), H256::repeat_byte(2)),
+            (U64::from(102), H256::repeat_byte(3)),
+        ];
+        // 102 is not on the node yet
+        let canonical_hashes = HashMap::from([
+            (U64::from(100), H256::repeat_byte(1)),
+            (U64::from(101), H256::repeat_byte(9)),
+        ]);
+
+        assert_eq!(
+            find_orphaned_blocks(&indexed, &canonical_hashes),
+            vec![OrphanedBlock {
+                block_number: U64::from(101),
+                orphaned_hash: H256::repeat_byte(2),
+                canonical_hash: H256::repeat_byte(9),
+            }]
+        );
+        assert!(find_orphaned_blocks(&indexed[..1], &canonical_hashes).is_empty());
+    }
+
+    #[test]
+    fn test_remove_orphaned_rows_sql() {
+        let deleted = remove_orphaned_rows_sql("indexer_erc20.transfer", false);
+        assert!(deleted.starts_with("DELETE FROM indexer_erc20.transfer t WHERE network = $1"));
+        assert!(deleted.contains("block_number = $2 AND block_hash = $3"));
+        assert!(!deleted.contains("$4"));
+
+        let marked = remove_orphaned_rows_sql("indexer_erc20.transfer", true);
+        assert!(marked.starts_with("UPDATE indexer_erc20.transfer t SET removed_by_reorg = TRUE"));
+        assert!(marked.contains("replaced_by_block_hash = $4"));
+        // rows marked by an earlier check are not returned to be streamed again
+        assert!(marked.contains("AND NOT removed_by_reorg"));
+        assert!(marked.ends_with("RETURNING to_jsonb(t)::TEXT"));
+    }
+}
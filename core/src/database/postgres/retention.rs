@@ -0,0 +1,184 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ethers::{providers::ProviderError, types::U64};
+use tracing::{error, info};
+
+use crate::{
+    database::postgres::{
+        client::{PostgresClient, PostgresError},
+        generate::generate_event_table_full_name,
+    },
+    is_running,
+    manifest::contract::Contract,
+    provider::JsonRpcCachedProvider,
+};
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Rows are deleted in batches so a large backlog does not hold a long lock on the table
+const PRUNE_BATCH_SIZE: u64 = 10_000;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(thiserror::Error, Debug)]
+enum PruneError {
+    #[error("{0}")]
+    Provider(#[from] ProviderError),
+
+    #[error("{0}")]
+    Postgres(#[from] PostgresError),
+}
+
+/// An event table to prune and how long its rows are kept on each network
+#[derive(Debug, Clone)]
+struct PruneTarget {
+    table_name: String,
+    networks: Vec<String>,
+    keep_blocks: Option<u64>,
+    keep_days: Option<u64>,
+}
+
+fn prune_targets(indexer_name: &str, contracts: &[Contract]) -> Vec<PruneTarget> {
+    contracts
+        .iter()
+        .flat_map(|contract| {
+            contract.retention.iter().flatten().map(move |retention| PruneTarget {
+                table_name: generate_event_table_full_name(
+                    indexer_name,
+                    &contract.table_name_readonly(),
+                    &retention.event_name,
+                ),
+                networks: contract.details.iter().map(|d| d.network.clone()).collect(),
+                keep_blocks: retention.keep_blocks,
+                keep_days: retention.keep_days,
+            })
+        })
+        .collect()
+}
+
+async fn block_timestamp(
+    provider: &JsonRpcCachedProvider,
+    block_number: u64,
+) -> Result<Option<u64>, ProviderError> {
+    let block = provider.get_block(U64::from(block_number)).await?;
+    Ok(block.map(|block| block.timestamp.as_u64()))
+}
+
+/// The first block at or after the timestamp, a binary search so a handful of calls is enough
+async fn first_block_at_or_after(
+    provider: &JsonRpcCachedProvider,
+    head: u64,
+    timestamp: u64,
+) -> Result<u64, ProviderError> {
+    let (mut low, mut high) = (0, head);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        match block_timestamp(provider, middle).await? {
+            Some(middle_timestamp) if middle_timestamp >= timestamp => high = middle,
+            _ => low = middle + 1,
+        }
+    }
+
+    Ok(low)
+}
+
+/// Rows below this block are outside the retention, when both limits are set the stricter wins
+async fn prune_below_block(
+    provider: &JsonRpcCachedProvider,
+    target: &PruneTarget,
+) -> Result<u64, ProviderError> {
+    let head = provider.get_block_number().await?.as_u64();
+    let mut below_block = 0;
+
+    if let Some(keep_blocks) = target.keep_blocks {
+        below_block = below_block.max(head.saturating_sub(keep_blocks));
+    }
+
+    if let Some(keep_days) = target.keep_days {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let cutoff = now.saturating_sub(keep_days * SECONDS_PER_DAY);
+        below_block = below_block.max(first_block_at_or_after(provider, head, cutoff).await?);
+    }
+
+    Ok(below_block)
+}
+
+async fn prune_network(
+    client: &PostgresClient,
+    provider: &JsonRpcCachedProvider,
+    target: &PruneTarget,
+    network: &str,
+) -> Result<u64, PruneError> {
+    let below_block = prune_below_block(provider, target).await?;
+    if below_block == 0 {
+        return Ok(0);
+    }
+
+    let sql = format!(
+        "DELETE FROM {table} WHERE ctid IN (SELECT ctid FROM {table} WHERE network = $1 AND block_number < {below_block} LIMIT {limit})",
+        table = target.table_name,
+        below_block = below_block,
+        limit = PRUNE_BATCH_SIZE
+    );
+
+    let mut deleted = 0;
+    loop {
+        let batch = client.execute(sql.as_str(), &[&network]).await?;
+        deleted += batch;
+        if batch < PRUNE_BATCH_SIZE || !is_running() {
+            return Ok(deleted);
+        }
+    }
+}
+
+async fn prune(
+    client: &PostgresClient,
+    targets: &[PruneTarget],
+    providers: &HashMap<String, Arc<JsonRpcCachedProvider>>,
+) {
+    for target in targets {
+        for network in &target.networks {
+            let Some(provider) = providers.get(network) else {
+                continue;
+            };
+
+            match prune_network(client, provider, target, network).await {
+                Ok(0) => {}
+                Ok(deleted) => info!(
+                    "Retention pruned {} rows from {} on network {}",
+                    deleted, target.table_name, network
+                ),
+                Err(e) => error!(
+                    "Retention could not prune {} on network {}: {}",
+                    target.table_name, network, e
+                ),
+            }
+        }
+    }
+}
+
+/// Keeps deleting the rows which fall outside the event retention in the background, only the
+/// indexer which owns the tables should run this
+pub fn start_retention_pruner(
+    client: Arc<PostgresClient>,
+    indexer_name: &str,
+    contracts: &[Contract],
+    providers: HashMap<String, Arc<JsonRpcCachedProvider>>,
+) {
+    let targets = prune_targets(indexer_name, contracts);
+    if targets.is_empty() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        // the first pass waits as well so the tables exist by then
+        while is_running() {
+            tokio::time::sleep(PRUNE_INTERVAL).await;
+            prune(&client, &targets, &providers).await;
+        }
+    });
+}
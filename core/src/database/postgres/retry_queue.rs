@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use ethers::types::U64;
+use rust_decimal::Decimal;
+
+use crate::{
+    database::postgres::client::{PostgresClient, PostgresError},
+    helpers::camel_to_snake,
+    types::code::Code,
+    EthereumSqlTypeWrapper,
+};
+
+pub fn retry_queue_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_retry_queue", camel_to_snake(indexer_name))
+}
+
+pub fn generate_retry_queue_table_sql(indexer_name: &str) -> Code {
+    Code::new(format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            contract_name TEXT NOT NULL,
+            event_name TEXT NOT NULL,
+            network TEXT NOT NULL,
+            from_block NUMERIC NOT NULL,
+            to_block NUMERIC NOT NULL,
+            attempts INT NOT NULL DEFAULT 1,
+            last_error TEXT NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (contract_name, event_name, network, from_block, to_block)
+        );
+    "#,
+        table = retry_queue_table_name(indexer_name)
+    ))
+}
+
+pub fn drop_retry_queue_table_sql(indexer_name: &str) -> Code {
+    Code::new(format!("DROP TABLE IF EXISTS {} CASCADE;", retry_queue_table_name(indexer_name)))
+}
+
+fn decimal_to_u64(value: Decimal) -> U64 {
+    U64::from_dec_str(&value.to_string()).expect("Failed to parse retry queue block number")
+}
+
+/// Durable record of the block ranges an event handler is retrying, a range stays queued until
+/// its handler succeeds so retries which die with the process are replayed on the next start.
+#[derive(Clone)]
+pub struct RetryQueue {
+    client: Arc<PostgresClient>,
+    table: String,
+    contract_name: String,
+    event_name: String,
+    network: String,
+}
+
+impl RetryQueue {
+    pub fn new(
+        client: Arc<PostgresClient>,
+        indexer_name: &str,
+        contract_name: &str,
+        event_name: &str,
+        network: &str,
+    ) -> Self {
+        Self {
+            client,
+            table: retry_queue_table_name(indexer_name),
+            contract_name: contract_name.to_string(),
+            event_name: event_name.to_string(),
+            network: network.to_string(),
+        }
+    }
+
+    pub async fn enqueue(
+        &self,
+        from_block: U64,
+        to_block: U64,
+        error: &str,
+    ) -> Result<(), PostgresError> {
+        self.client
+            .execute(
+                &format!(
+                    r#"
+                    INSERT INTO {table} (contract_name, event_name, network, from_block, to_block, last_error)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                    ON CONFLICT (contract_name, event_name, network, from_block, to_block)
+                    DO UPDATE SET attempts = {table}.attempts + 1, last_error = EXCLUDED.last_error
+                    "#,
+                    table = self.table
+                ),
+                &[
+                    &self.contract_name,
+                    &self.event_name,
+                    &self.network,
+                    &EthereumSqlTypeWrapper::U64(from_block),
+                    &EthereumSqlTypeWrapper::U64(to_block),
+                    &error,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove(&self, from_block: U64, to_block: U64) -> Result<(), PostgresError> {
+        self.client
+            .execute(
+                &format!(
+                    "DELETE FROM {} WHERE contract_name = $1 AND event_name = $2 AND network = $3 AND from_block = $4 AND to_block = $5",
+                    self.table
+                ),
+                &[
+                    &self.contract_name,
+                    &self.event_name,
+                    &self.network,
+                    &EthereumSqlTypeWrapper::U64(from_block),
+                    &EthereumSqlTypeWrapper::U64(to_block),
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Drops the ranges at or after the block, the pipeline processes those again anyway
+    pub async fn remove_from(&self, from_block: U64) -> Result<(), PostgresError> {
+        self.client
+            .execute(
+                &format!(
+                    "DELETE FROM {} WHERE contract_name = $1 AND event_name = $2 AND network = $3 AND to_block >= $4",
+                    self.table
+                ),
+                &[
+                    &self.contract_name,
+                    &self.event_name,
+                    &self.network,
+                    &EthereumSqlTypeWrapper::U64(from_block),
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn pending(&self) -> Result<Vec<(U64, U64)>, PostgresError> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT from_block, to_block FROM {} WHERE contract_name = $1 AND event_name = $2 AND network = $3 ORDER BY from_block",
+                    self.table
+                ),
+                &[&self.contract_name, &self.event_name, &self.network],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (decimal_to_u64(row.get("from_block")), decimal_to_u64(row.get("to_block"))))
+            .collect())
+    }
+}
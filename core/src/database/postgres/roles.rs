@@ -0,0 +1,102 @@
+use crate::{
+    database::postgres::generate::generate_indexer_contract_schema_name,
+    helpers::camel_to_snake,
+    manifest::{contract::Contract, graphql::GraphQLRole},
+};
+
+fn quote_identifier(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+/// The primary key is always readable as GraphQL builds the node ids and cursors from it
+fn event_grant_sql(table_name: &str, role: &str, columns: Option<&[String]>) -> String {
+    match columns {
+        Some(columns) => {
+            let mut granted = vec![quote_identifier("rindexer_id")];
+            granted.extend(columns.iter().map(|column| quote_identifier(&camel_to_snake(column))));
+            format!(
+                "GRANT SELECT ({}) ON {} TO {};",
+                granted.join(", "),
+                table_name,
+                quote_identifier(role)
+            )
+        }
+        None => format!("GRANT SELECT ON {} TO {};", table_name, quote_identifier(role)),
+    }
+}
+
+/// Creates the roles the GraphQL requests run as and grants them the events they can read, the
+/// grants are revoked first so events and columns taken out of a role stop being readable
+pub fn graphql_roles_sql(
+    indexer_name: &str,
+    contracts: &[Contract],
+    roles: &[GraphQLRole],
+) -> String {
+    let mut schemas: Vec<String> = vec![];
+    for contract in contracts {
+        let schema =
+            generate_indexer_contract_schema_name(indexer_name, &contract.table_name_readonly());
+        if !schemas.contains(&schema) {
+            schemas.push(schema);
+        }
+    }
+
+    let mut sql: Vec<String> = vec![];
+    for role in roles {
+        let role_identifier = quote_identifier(&role.name);
+        sql.push(format!(
+            "DO $$ BEGIN IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = '{}') THEN CREATE ROLE {} NOLOGIN; END IF; END $$;",
+            role.name.replace('\'', "''"),
+            role_identifier
+        ));
+        // the GraphQL server connects as the database user and switches to the role
+        sql.push(format!("GRANT {} TO CURRENT_USER;", role_identifier));
+
+        for schema in &schemas {
+            sql.push(format!(
+                "REVOKE ALL ON ALL TABLES IN SCHEMA {schema} FROM {role};\nREVOKE USAGE ON SCHEMA {schema} FROM {role};",
+                schema = schema,
+                role = role_identifier
+            ));
+        }
+
+        for event in &role.events {
+            let Some(contract) = contracts.iter().find(|c| c.name == event.contract_name) else {
+                continue;
+            };
+            let schema = generate_indexer_contract_schema_name(
+                indexer_name,
+                &contract.table_name_readonly(),
+            );
+            sql.push(format!("GRANT USAGE ON SCHEMA {} TO {};", schema, role_identifier));
+            sql.push(event_grant_sql(
+                &format!("{}.{}", schema, camel_to_snake(&event.event_name)),
+                &role.name,
+                event.columns.as_deref(),
+            ));
+        }
+    }
+
+    sql.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_is_granted_only_the_listed_columns() {
+        assert_eq!(
+            event_grant_sql(
+                "indexer_erc20.transfer",
+                "public_api",
+                Some(&["from".to_string(), "block_number".to_string()])
+            ),
+            "GRANT SELECT (\"rindexer_id\", \"from\", \"block_number\") ON indexer_erc20.transfer TO \"public_api\";"
+        );
+        assert_eq!(
+            event_grant_sql("indexer_erc20.transfer", "internal", None),
+            "GRANT SELECT ON indexer_erc20.transfer TO \"internal\";"
+        );
+    }
+}
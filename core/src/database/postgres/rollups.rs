@@ -0,0 +1,590 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use ethers::{providers::ProviderError, types::U64};
+use futures::{stream, StreamExt, TryStreamExt};
+use once_cell::sync::Lazy;
+use tokio::sync::Mutex;
+use tracing::error;
+
+use crate::{
+    database::postgres::{
+        client::{PostgresClient, PostgresError},
+        generate::generate_event_table_full_name,
+    },
+    helpers::camel_to_snake,
+    indexer::reorg_safe_distance_for_chain,
+    is_running,
+    manifest::{
+        contract::Contract,
+        storage::{Rollup, RollupInterval},
+    },
+    provider::JsonRpcCachedProvider,
+    EthereumSqlTypeWrapper,
+};
+
+const BUILD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Bounds the rows a single build reads, a build catching up runs again straight away
+const MAX_BLOCKS_PER_BUILD: u64 = 10_000;
+
+/// How many block timestamps are fetched at the same time
+const TIMESTAMP_CONCURRENCY: usize = 10;
+
+/// A build and a rollback can not interleave, a build would otherwise count the rows of blocks the
+/// rollback just took out
+static BUILD_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(thiserror::Error, Debug)]
+pub enum RollupsError {
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("{0}")]
+    ProviderError(#[from] ProviderError),
+
+    #[error("Could not parse block {0}")]
+    InvalidBlock(String),
+}
+
+pub fn rollups_schema_name(indexer_name: &str) -> String {
+    format!("{}_rollups", camel_to_snake(indexer_name))
+}
+
+fn rollup_table_name(indexer_name: &str, rollup: &Rollup, interval: RollupInterval) -> String {
+    format!(
+        "{}.{}_{}",
+        rollups_schema_name(indexer_name),
+        camel_to_snake(&rollup.name),
+        interval.table_suffix()
+    )
+}
+
+fn addresses_table_name(indexer_name: &str, rollup: &Rollup, interval: RollupInterval) -> String {
+    format!("{}_addresses", rollup_table_name(indexer_name, rollup, interval))
+}
+
+fn cursors_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_rollup_cursors", camel_to_snake(indexer_name))
+}
+
+fn deltas_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_rollup_deltas", camel_to_snake(indexer_name))
+}
+
+fn sum_column(input: &str) -> String {
+    format!("sum_{}", camel_to_snake(input))
+}
+
+/// The start of the bucket of a unix timestamp expression
+fn bucket_sql(epoch: &str, interval: RollupInterval) -> String {
+    format!(
+        "to_timestamp(FLOOR(({}) / {seconds}) * {seconds})",
+        epoch,
+        seconds = interval.seconds()
+    )
+}
+
+pub fn generate_rollups_tables_sql(indexer_name: &str, rollups: &[Rollup]) -> String {
+    if rollups.is_empty() {
+        return String::new();
+    }
+
+    let mut sql = format!(
+        r#"
+        CREATE SCHEMA IF NOT EXISTS {schema};
+        CREATE TABLE IF NOT EXISTS {cursors} (
+            rollup TEXT NOT NULL,
+            network TEXT NOT NULL,
+            block_number NUMERIC NOT NULL,
+            PRIMARY KEY (rollup, network)
+        );
+        CREATE TABLE IF NOT EXISTS {deltas} (
+            rollup TEXT NOT NULL,
+            network TEXT NOT NULL,
+            contract_address CHAR(42) NOT NULL,
+            block_number NUMERIC NOT NULL,
+            hour TIMESTAMPTZ NOT NULL,
+            event_count BIGINT NOT NULL,
+            sums NUMERIC[] NOT NULL,
+            PRIMARY KEY (rollup, network, block_number, contract_address)
+        );
+        "#,
+        schema = rollups_schema_name(indexer_name),
+        cursors = cursors_table_name(indexer_name),
+        deltas = deltas_table_name(indexer_name),
+    );
+
+    for rollup in rollups {
+        for interval in &rollup.intervals {
+            let sums = rollup
+                .sum()
+                .iter()
+                .map(|input| format!("{} NUMERIC NOT NULL DEFAULT 0, ", sum_column(input)))
+                .collect::<String>();
+            let unique_addresses = if rollup.unique_addresses().is_empty() {
+                ""
+            } else {
+                "unique_addresses BIGINT NOT NULL DEFAULT 0, "
+            };
+            sql.push_str(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (network VARCHAR(50) NOT NULL, contract_address CHAR(42) NOT NULL, \
+                 bucket TIMESTAMPTZ NOT NULL, event_count BIGINT NOT NULL DEFAULT 0, {}{}PRIMARY KEY (network, contract_address, bucket));",
+                rollup_table_name(indexer_name, rollup, *interval),
+                sums,
+                unique_addresses
+            ));
+
+            if !rollup.unique_addresses().is_empty() {
+                sql.push_str(&format!(
+                    "CREATE TABLE IF NOT EXISTS {} (network VARCHAR(50) NOT NULL, contract_address CHAR(42) NOT NULL, \
+                     bucket TIMESTAMPTZ NOT NULL, address CHAR(42) NOT NULL, block_number NUMERIC NOT NULL, \
+                     PRIMARY KEY (network, contract_address, bucket, address));",
+                    addresses_table_name(indexer_name, rollup, *interval)
+                ));
+            }
+        }
+    }
+
+    sql
+}
+
+pub fn drop_rollups_tables_sql(indexer_name: &str) -> String {
+    format!(
+        "DROP SCHEMA IF EXISTS {} CASCADE;DROP TABLE IF EXISTS {} CASCADE;DROP TABLE IF EXISTS {} CASCADE;",
+        rollups_schema_name(indexer_name),
+        cursors_table_name(indexer_name),
+        deltas_table_name(indexer_name)
+    )
+}
+
+/// The event table a rollup counts and the networks it is indexed on
+struct RollupSource {
+    table_name: String,
+    last_synced_table_name: String,
+    networks: Vec<String>,
+}
+
+fn rollup_source(
+    indexer_name: &str,
+    contracts: &[Contract],
+    rollup: &Rollup,
+) -> Option<RollupSource> {
+    let contract = contracts.iter().find(|c| c.name == rollup.contract_name)?;
+
+    Some(RollupSource {
+        table_name: generate_event_table_full_name(
+            indexer_name,
+            &contract.table_name_readonly(),
+            &rollup.event_name,
+        ),
+        last_synced_table_name: format!(
+            "rindexer_internal.{}_{}_{}",
+            camel_to_snake(indexer_name),
+            camel_to_snake(&contract.before_modify_name_if_filter_readonly()),
+            camel_to_snake(&rollup.event_name)
+        ),
+        networks: contract.details.iter().map(|d| d.network.clone()).collect(),
+    })
+}
+
+fn parse_block(row: Option<tokio_postgres::Row>) -> Result<U64, RollupsError> {
+    match row {
+        Some(row) => {
+            let block: Option<String> = row.get(0);
+            let block = block.unwrap_or_else(|| "0".to_string());
+            U64::from_dec_str(&block).map_err(|_| RollupsError::InvalidBlock(block))
+        }
+        None => Ok(U64::zero()),
+    }
+}
+
+async fn block_timestamps(
+    provider: &JsonRpcCachedProvider,
+    blocks: Vec<U64>,
+) -> Result<Vec<(U64, u64)>, ProviderError> {
+    stream::iter(blocks)
+        .map(|block_number| async move {
+            let block = provider.get_block(block_number).await?.ok_or_else(|| {
+                ProviderError::CustomError(format!("Block {} not found", block_number))
+            })?;
+            Ok::<_, ProviderError>((block_number, block.timestamp.as_u64()))
+        })
+        .buffer_unordered(TIMESTAMP_CONCURRENCY)
+        .try_collect()
+        .await
+}
+
+/// Counts the events after the cursor up to the block the event is synced to, returns whether
+/// there were blocks to count so a rollup which is behind runs again straight away
+async fn build_rollup(
+    client: &PostgresClient,
+    indexer_name: &str,
+    rollup: &Rollup,
+    source: &RollupSource,
+    network: &str,
+    provider: &JsonRpcCachedProvider,
+    soft_delete: bool,
+) -> Result<bool, RollupsError> {
+    let cursor = parse_block(
+        client
+            .query_one_or_none(
+                &format!(
+                    "SELECT block_number::TEXT FROM {} WHERE rollup = $1 AND network = $2",
+                    cursors_table_name(indexer_name)
+                ),
+                &[&rollup.name, &network],
+            )
+            .await?,
+    )?;
+    let synced = parse_block(
+        client
+            .query_one_or_none(
+                &format!(
+                    "SELECT last_synced_block::TEXT FROM {} WHERE network = $1",
+                    source.last_synced_table_name
+                ),
+                &[&network],
+            )
+            .await?,
+    )?;
+    if synced <= cursor {
+        return Ok(false);
+    }
+    let to_block = synced.min(cursor + MAX_BLOCKS_PER_BUILD);
+
+    let events_filter = format!(
+        "e.network = $1 AND e.block_number > $2 AND e.block_number <= $3{}",
+        if soft_delete { " AND NOT e.removed_by_reorg" } else { "" }
+    );
+    let range = [
+        EthereumSqlTypeWrapper::String(network.to_string()),
+        EthereumSqlTypeWrapper::U64(cursor),
+        EthereumSqlTypeWrapper::U64(to_block),
+    ];
+    let blocks = client
+        .query(
+            &format!(
+                "SELECT DISTINCT e.block_number::TEXT FROM {} e WHERE {}",
+                source.table_name, events_filter
+            ),
+            &[&range[0], &range[1], &range[2]],
+        )
+        .await?
+        .iter()
+        .map(|row| {
+            let block: String = row.get(0);
+            U64::from_dec_str(&block).map_err(|_| RollupsError::InvalidBlock(block))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let (block_numbers, timestamps): (Vec<String>, Vec<String>) =
+        block_timestamps(provider, blocks)
+            .await?
+            .into_iter()
+            .map(|(block_number, timestamp)| (block_number.to_string(), timestamp.to_string()))
+            .unzip();
+
+    // the timestamps are joined on as $4 and $5 after the block range
+    let timestamps_join = "JOIN UNNEST($4::TEXT[], $5::TEXT[]) AS b(block_number, ts) \
+                           ON e.block_number = b.block_number::NUMERIC";
+    let mut events_params = range.to_vec();
+    events_params.push(EthereumSqlTypeWrapper::VecString(block_numbers));
+    events_params.push(EthereumSqlTypeWrapper::VecString(timestamps));
+
+    let deltas_table = deltas_table_name(indexer_name);
+    let mut statements = vec![(
+        format!(
+            "INSERT INTO {deltas} (rollup, network, contract_address, block_number, hour, event_count, sums) \
+             SELECT $6, e.network, e.contract_address, e.block_number, {hour}, COUNT(*), ARRAY[{sums}]::NUMERIC[] \
+             FROM {table} e {join} WHERE {filter} \
+             GROUP BY e.network, e.contract_address, e.block_number, b.ts",
+            deltas = deltas_table,
+            hour = bucket_sql("b.ts::NUMERIC", RollupInterval::Hour),
+            sums = rollup
+                .sum()
+                .iter()
+                // uint256 inputs are stored as text
+                .map(|input| format!("SUM(e.\"{}\"::NUMERIC)", camel_to_snake(input)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            table = source.table_name,
+            join = timestamps_join,
+            filter = events_filter
+        ),
+        [events_params.clone(), vec![EthereumSqlTypeWrapper::String(rollup.name.clone())]]
+            .concat(),
+    )];
+
+    let deltas_params = vec![
+        EthereumSqlTypeWrapper::String(rollup.name.clone()),
+        range[0].clone(),
+        range[1].clone(),
+        range[2].clone(),
+    ];
+    for interval in &rollup.intervals {
+        let table_name = rollup_table_name(indexer_name, rollup, *interval);
+        let sum_columns: Vec<String> = rollup.sum().iter().map(|input| sum_column(input)).collect();
+        statements.push((
+            format!(
+                "INSERT INTO {table} AS t (network, contract_address, bucket, event_count{columns}) \
+                 SELECT network, contract_address, {bucket}, SUM(event_count){sums} FROM {deltas} \
+                 WHERE rollup = $1 AND network = $2 AND block_number > $3 AND block_number <= $4 \
+                 GROUP BY 1, 2, 3 \
+                 ON CONFLICT (network, contract_address, bucket) DO UPDATE SET event_count = t.event_count + EXCLUDED.event_count{updates}",
+                table = table_name,
+                columns = sum_columns.iter().map(|c| format!(", {}", c)).collect::<String>(),
+                bucket = bucket_sql("EXTRACT(EPOCH FROM hour)", *interval),
+                sums = (1..=sum_columns.len())
+                    .map(|i| format!(", SUM(sums[{}])", i))
+                    .collect::<String>(),
+                deltas = deltas_table,
+                updates = sum_columns
+                    .iter()
+                    .map(|c| format!(", {c} = t.{c} + EXCLUDED.{c}", c = c))
+                    .collect::<String>()
+            ),
+            deltas_params.clone(),
+        ));
+
+        if rollup.unique_addresses().is_empty() {
+            continue;
+        }
+
+        // an address is stored with the first block it was seen in the bucket, so a rollback
+        // only takes out the addresses the rolled back blocks added
+        statements.push((
+            format!(
+                "WITH seen AS ( \
+                     INSERT INTO {addresses} (network, contract_address, bucket, address, block_number) \
+                     SELECT DISTINCT ON (e.network, e.contract_address, {bucket}, a.address) \
+                         e.network, e.contract_address, {bucket}, a.address, e.block_number \
+                     FROM {table} e {join} CROSS JOIN LATERAL (VALUES {values}) AS a(address) \
+                     WHERE {filter} AND a.address IS NOT NULL AND a.address <> '0x0000000000000000000000000000000000000000' \
+                     ORDER BY e.network, e.contract_address, {bucket}, a.address, e.block_number \
+                     ON CONFLICT (network, contract_address, bucket, address) DO NOTHING \
+                     RETURNING network, contract_address, bucket \
+                 ) \
+                 UPDATE {rollup_table} t SET unique_addresses = t.unique_addresses + s.new_addresses \
+                 FROM (SELECT network, contract_address, bucket, COUNT(*) AS new_addresses FROM seen GROUP BY 1, 2, 3) s \
+                 WHERE t.network = s.network AND t.contract_address = s.contract_address AND t.bucket = s.bucket",
+                addresses = addresses_table_name(indexer_name, rollup, *interval),
+                bucket = bucket_sql("b.ts::NUMERIC", *interval),
+                table = source.table_name,
+                join = timestamps_join,
+                values = rollup
+                    .unique_addresses()
+                    .iter()
+                    .map(|input| format!("(e.\"{}\")", camel_to_snake(input)))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                filter = events_filter,
+                rollup_table = table_name
+            ),
+            events_params.clone(),
+        ));
+    }
+
+    statements.push((
+        format!(
+            "INSERT INTO {} (rollup, network, block_number) VALUES ($1, $2, $3) \
+             ON CONFLICT (rollup, network) DO UPDATE SET block_number = EXCLUDED.block_number",
+            cursors_table_name(indexer_name)
+        ),
+        vec![
+            EthereumSqlTypeWrapper::String(rollup.name.clone()),
+            range[0].clone(),
+            EthereumSqlTypeWrapper::U64(to_block),
+        ],
+    ));
+    client.execute_in_transaction(&statements).await?;
+
+    Ok(true)
+}
+
+/// Deltas older than the reorg safe distance can not be rolled back anymore
+async fn prune_deltas(
+    client: &PostgresClient,
+    indexer_name: &str,
+    rollup: &Rollup,
+    network: &str,
+    provider: &JsonRpcCachedProvider,
+) -> Result<(), RollupsError> {
+    let chain_id = provider.get_chain_id().await?;
+    let cursor = parse_block(
+        client
+            .query_one_or_none(
+                &format!(
+                    "SELECT block_number::TEXT FROM {} WHERE rollup = $1 AND network = $2",
+                    cursors_table_name(indexer_name)
+                ),
+                &[&rollup.name, &network],
+            )
+            .await?,
+    )?;
+    let prune_below = cursor.saturating_sub(reorg_safe_distance_for_chain(&chain_id));
+
+    client
+        .execute(
+            &format!(
+                "DELETE FROM {} WHERE rollup = $1 AND network = $2 AND block_number < $3",
+                deltas_table_name(indexer_name)
+            ),
+            &[&rollup.name, &network, &EthereumSqlTypeWrapper::U64(prune_below)],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Keeps the rollup tables up to date in the background, every network as far as its events have
+/// been indexed
+pub fn start_rollups_builder(
+    client: Arc<PostgresClient>,
+    indexer_name: &str,
+    contracts: &[Contract],
+    rollups: Vec<Rollup>,
+    providers: HashMap<String, Arc<JsonRpcCachedProvider>>,
+    soft_delete: bool,
+) {
+    let indexer_name = indexer_name.to_string();
+    let builds: Vec<(Rollup, RollupSource)> = rollups
+        .into_iter()
+        .filter_map(|rollup| {
+            let source = rollup_source(&indexer_name, contracts, &rollup)?;
+            Some((rollup, source))
+        })
+        .collect();
+
+    tokio::spawn(async move {
+        while is_running() {
+            for (rollup, source) in &builds {
+                for network in &source.networks {
+                    let Some(provider) = providers.get(network) else {
+                        continue;
+                    };
+
+                    loop {
+                        let _lock = BUILD_LOCK.lock().await;
+                        match build_rollup(
+                            &client,
+                            &indexer_name,
+                            rollup,
+                            source,
+                            network,
+                            provider,
+                            soft_delete,
+                        )
+                        .await
+                        {
+                            Ok(true) if is_running() => continue,
+                            Ok(_) => break,
+                            Err(e) => {
+                                error!(
+                                    "Could not build rollup {} on network {}: {}",
+                                    rollup.name, network, e
+                                );
+                                break;
+                            }
+                        }
+                    }
+
+                    if let Err(e) =
+                        prune_deltas(&client, &indexer_name, rollup, network, provider).await
+                    {
+                        error!("Could not prune the deltas of rollup {}: {}", rollup.name, e);
+                    }
+                }
+            }
+
+            tokio::time::sleep(BUILD_INTERVAL).await;
+        }
+    });
+}
+
+/// Takes the events of the network from `from_block` on back out of the rollups and moves their
+/// cursors back so the canonical events are counted again
+pub async fn rollback_rollups(
+    client: &PostgresClient,
+    indexer_name: &str,
+    rollups: &[Rollup],
+    network: &str,
+    from_block: U64,
+) -> Result<(), RollupsError> {
+    let _lock = BUILD_LOCK.lock().await;
+    let deltas_table = deltas_table_name(indexer_name);
+    for rollup in rollups {
+        let params = vec![
+            EthereumSqlTypeWrapper::String(rollup.name.clone()),
+            EthereumSqlTypeWrapper::String(network.to_string()),
+            EthereumSqlTypeWrapper::U64(from_block),
+        ];
+        let network_params = params[1..].to_vec();
+        let mut statements = vec![];
+        for interval in &rollup.intervals {
+            let table_name = rollup_table_name(indexer_name, rollup, *interval);
+            let sum_columns: Vec<String> =
+                rollup.sum().iter().map(|input| sum_column(input)).collect();
+            statements.push((
+                format!(
+                    "UPDATE {table} t SET event_count = t.event_count - d.event_count{updates} \
+                     FROM (SELECT network, contract_address, {bucket} AS bucket, SUM(event_count) AS event_count{sums} \
+                           FROM {deltas} WHERE rollup = $1 AND network = $2 AND block_number >= $3 GROUP BY 1, 2, 3) d \
+                     WHERE t.network = d.network AND t.contract_address = d.contract_address AND t.bucket = d.bucket",
+                    table = table_name,
+                    updates = sum_columns
+                        .iter()
+                        .map(|c| format!(", {c} = t.{c} - d.{c}", c = c))
+                        .collect::<String>(),
+                    bucket = bucket_sql("EXTRACT(EPOCH FROM hour)", *interval),
+                    sums = sum_columns
+                        .iter()
+                        .enumerate()
+                        .map(|(i, c)| format!(", SUM(sums[{}]) AS {}", i + 1, c))
+                        .collect::<String>(),
+                    deltas = deltas_table
+                ),
+                params.clone(),
+            ));
+
+            if !rollup.unique_addresses().is_empty() {
+                statements.push((
+                    format!(
+                        "WITH removed AS ( \
+                             DELETE FROM {addresses} WHERE network = $1 AND block_number >= $2 \
+                             RETURNING network, contract_address, bucket \
+                         ) \
+                         UPDATE {table} t SET unique_addresses = t.unique_addresses - r.removed_addresses \
+                         FROM (SELECT network, contract_address, bucket, COUNT(*) AS removed_addresses FROM removed GROUP BY 1, 2, 3) r \
+                         WHERE t.network = r.network AND t.contract_address = r.contract_address AND t.bucket = r.bucket",
+                        addresses = addresses_table_name(indexer_name, rollup, *interval),
+                        table = table_name
+                    ),
+                    network_params.clone(),
+                ));
+            }
+
+            statements.push((
+                format!("DELETE FROM {} WHERE network = $1 AND event_count <= 0", table_name),
+                vec![network_params[0].clone()],
+            ));
+        }
+
+        statements.push((
+            format!(
+                "DELETE FROM {} WHERE rollup = $1 AND network = $2 AND block_number >= $3",
+                deltas_table
+            ),
+            params.clone(),
+        ));
+        statements.push((
+            format!(
+                "UPDATE {} SET block_number = LEAST(block_number, $3 - 1) \
+                 WHERE rollup = $1 AND network = $2",
+                cursors_table_name(indexer_name)
+            ),
+            params,
+        ));
+        client.execute_in_transaction(&statements).await?;
+    }
+
+    Ok(())
+}
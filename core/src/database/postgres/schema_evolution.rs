@@ -0,0 +1,309 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tracing::{info, warn};
+
+use crate::{
+    abi::{ABIItem, ParamTypeError, ReadAbiError},
+    database::postgres::{
+        client::{PostgresClient, PostgresError},
+        generate::{
+            generate_columns_names_and_types, generate_indexer_contract_schema_name,
+            DECODED_PARAMS_COLUMN, SPAM_REASON_COLUMN,
+        },
+        migrations::MIGRATIONS_FOLDER,
+    },
+    helpers::camel_to_snake,
+    indexer::enrichment::enrichment_column_type,
+    manifest::{
+        contract::{EncryptedInputs, EventDecimals},
+        core::Manifest,
+        storage::SchemaEvolution,
+    },
+};
+
+/// Columns rindexer adds to every event table, never part of the ABI
+const BASE_COLUMNS: [&str; 11] = [
+    "rindexer_id",
+    "contract_address",
+    "tx_hash",
+    "block_number",
+    "block_hash",
+    "network",
+    "chain_id",
+    "tx_index",
+    "log_index",
+    "removed_by_reorg",
+    "replaced_by_block_hash",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum SchemaEvolutionError {
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("{0}")]
+    ReadAbiError(#[from] ReadAbiError),
+
+    #[error("{0}")]
+    ParamTypeError(#[from] ParamTypeError),
+
+    #[error("Column {column} in {table} is {existing} but the ABI now needs {expected} - this can not be migrated automatically, migrate the data or drop the table")]
+    IncompatibleColumn { table: String, column: String, existing: String, expected: String },
+
+    #[error(
+        "The ABI added columns so {0} was written - review it, it is applied on the next start"
+    )]
+    MigrationFileWritten(String),
+
+    #[error("Could not write the migration file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// The type as postgres reports it with `format_type`, so the generated type can be compared with
+/// what the table has
+fn postgres_type_name(sql_type: &str) -> String {
+    let (base_type, is_array) = match sql_type.strip_suffix("[]") {
+        Some(base_type) => (base_type, true),
+        None => (sql_type, false),
+    };
+
+    let base_type = base_type.to_lowercase();
+    let name = if let Some(length) = base_type.strip_prefix("char(") {
+        format!("character({}", length)
+    } else if let Some(length) = base_type.strip_prefix("varchar(") {
+        format!("character varying({}", length)
+    } else {
+        base_type
+    };
+
+    if is_array {
+        format!("{}[]", name)
+    } else {
+        name
+    }
+}
+
+async fn existing_columns(
+    client: &PostgresClient,
+    schema_name: &str,
+    table_name: &str,
+) -> Result<Vec<(String, String)>, PostgresError> {
+    let rows = client
+        .query(
+            "SELECT a.attname::TEXT, format_type(a.atttypid, a.atttypmod) FROM pg_attribute a \
+             JOIN pg_class c ON c.oid = a.attrelid \
+             JOIN pg_namespace n ON n.oid = c.relnamespace \
+             WHERE n.nspname = $1 AND c.relname = $2 AND a.attnum > 0 AND NOT a.attisdropped",
+            &[&schema_name, &table_name],
+        )
+        .await?;
+
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+/// The statements which bring the table up to the ABI, new parameters become nullable columns
+/// so the rows indexed before keep working
+fn additive_migration(
+    full_table_name: &str,
+    existing: &[(String, String)],
+    expected: &[(String, String)],
+) -> Result<Vec<String>, SchemaEvolutionError> {
+    let mut statements = vec![];
+    for (column, sql_type) in expected {
+        match existing.iter().find(|(name, _)| name == column) {
+            Some((_, existing_type)) if *existing_type != postgres_type_name(sql_type) => {
+                return Err(SchemaEvolutionError::IncompatibleColumn {
+                    table: full_table_name.to_string(),
+                    column: column.clone(),
+                    existing: existing_type.clone(),
+                    expected: postgres_type_name(sql_type),
+                });
+            }
+            Some(_) => {}
+            None => statements.push(format!(
+                "ALTER TABLE {} ADD COLUMN IF NOT EXISTS \"{}\" {};",
+                full_table_name, column, sql_type
+            )),
+        }
+    }
+
+    for (column, _) in existing {
+        if !BASE_COLUMNS.contains(&column.as_str()) &&
+            !expected.iter().any(|(name, _)| name == column)
+        {
+            warn!(
+                "Column {} in {} is no longer in the ABI - it is left in place and stays empty for new rows",
+                column, full_table_name
+            );
+        }
+    }
+
+    Ok(statements)
+}
+
+fn write_migration_file(
+    project_path: &Path,
+    indexer_name: &str,
+    statements: &[String],
+) -> Result<PathBuf, SchemaEvolutionError> {
+    let folder = project_path.join(MIGRATIONS_FOLDER);
+    fs::create_dir_all(&folder)?;
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let path =
+        folder.join(format!("{}_{}_abi_changes.sql", timestamp, camel_to_snake(indexer_name)));
+    fs::write(&path, format!("{}\n", statements.join("\n")))?;
+
+    Ok(path)
+}
+
+/// How the event tables in the database differ from the tables the manifest needs
+#[derive(Debug, Default)]
+pub struct EventTablesDiff {
+    pub existing_tables: Vec<String>,
+    pub missing_tables: Vec<String>,
+    /// The statements adding the columns the existing tables miss
+    pub missing_columns: Vec<String>,
+}
+
+/// Compares the event tables with the ABIs, a column whose type changed can not be added so it
+/// fails the diff
+pub async fn diff_event_tables(
+    client: &PostgresClient,
+    project_path: &Path,
+    manifest: &Manifest,
+) -> Result<EventTablesDiff, SchemaEvolutionError> {
+    let mut diff = EventTablesDiff::default();
+    for contract in &manifest.contracts {
+        let schema_name =
+            generate_indexer_contract_schema_name(&manifest.name, &contract.table_name_readonly());
+        let abi_items = ABIItem::read_abi_items(project_path, contract)?;
+        let events = ABIItem::extract_event_names_and_signatures_from_abi(abi_items)?;
+
+        for event in events {
+            let table_name = camel_to_snake(&event.name);
+            let full_table_name = format!("{}.{}", schema_name, table_name);
+            let existing = existing_columns(client, &schema_name, &table_name).await?;
+            // contracts in a table group share the table
+            if existing.is_empty() {
+                if !diff.missing_tables.contains(&full_table_name) {
+                    diff.missing_tables.push(full_table_name);
+                }
+                continue;
+            }
+            if !diff.existing_tables.contains(&full_table_name) {
+                diff.existing_tables.push(full_table_name.clone());
+            }
+
+            let params_storage = contract.params_storage();
+            let mut expected = if params_storage.typed_columns() {
+                generate_columns_names_and_types(&event.inputs)
+            } else {
+                vec![]
+            };
+            if params_storage.jsonb() {
+                expected.push((DECODED_PARAMS_COLUMN.to_string(), "JSONB".to_string()));
+            }
+            if contract.flags_spam() {
+                expected.push((SPAM_REASON_COLUMN.to_string(), "TEXT".to_string()));
+            }
+            if let Some(decimals) = contract.event_decimals(&event.name) {
+                expected.extend(decimals.inputs.iter().map(|input| {
+                    (EventDecimals::normalized_column_name(input), "NUMERIC".to_string())
+                }));
+            }
+            expected.extend(
+                contract
+                    .event_enrichment(&event.name)
+                    .iter()
+                    .map(|stage| (stage.column_name(), enrichment_column_type(stage).to_string())),
+            );
+            if manifest.storage.postgres_column_encryption().is_some() {
+                expected.extend(contract.event_encrypted_inputs(&event.name).iter().map(|input| {
+                    (EncryptedInputs::encrypted_column_name(input), "BYTEA".to_string())
+                }));
+            }
+            for statement in additive_migration(&full_table_name, &existing, &expected)? {
+                if !diff.missing_columns.contains(&statement) {
+                    diff.missing_columns.push(statement);
+                }
+            }
+        }
+    }
+
+    Ok(diff)
+}
+
+/// Handles the parameters the ABIs added since the event tables were created, inserts would fail
+/// on the missing columns otherwise
+pub async fn evolve_event_tables(
+    client: &PostgresClient,
+    project_path: &Path,
+    manifest: &Manifest,
+) -> Result<(), SchemaEvolutionError> {
+    let mode = manifest.storage.postgres_schema_evolution();
+    if mode == SchemaEvolution::Disabled {
+        return Ok(());
+    }
+
+    let statements = diff_event_tables(client, project_path, manifest).await?.missing_columns;
+    if statements.is_empty() {
+        return Ok(());
+    }
+
+    match mode {
+        SchemaEvolution::Auto => {
+            for statement in &statements {
+                info!("ABI changed - {}", statement);
+            }
+            client.batch_execute(&statements.join("\n")).await?;
+            Ok(())
+        }
+        SchemaEvolution::MigrationFile => {
+            let path = write_migration_file(project_path, &manifest.name, &statements)?;
+            Err(SchemaEvolutionError::MigrationFileWritten(path.display().to_string()))
+        }
+        SchemaEvolution::Disabled => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_postgres_type_name() {
+        assert_eq!(postgres_type_name("CHAR(42)"), "character(42)");
+        assert_eq!(postgres_type_name("VARCHAR(78)"), "character varying(78)");
+        assert_eq!(postgres_type_name("VARCHAR(78)[]"), "character varying(78)[]");
+        assert_eq!(postgres_type_name("NUMERIC"), "numeric");
+        assert_eq!(postgres_type_name("TEXT[]"), "text[]");
+    }
+
+    #[test]
+    fn test_additive_migration() {
+        let existing = vec![
+            ("rindexer_id".to_string(), "integer".to_string()),
+            ("from".to_string(), "character(42)".to_string()),
+        ];
+        let expected = vec![
+            ("from".to_string(), "CHAR(42)".to_string()),
+            ("memo".to_string(), "TEXT".to_string()),
+        ];
+
+        assert_eq!(
+            additive_migration("a.transfer", &existing, &expected).unwrap(),
+            vec!["ALTER TABLE a.transfer ADD COLUMN IF NOT EXISTS \"memo\" TEXT;".to_string()]
+        );
+
+        let changed = vec![("from".to_string(), "TEXT".to_string())];
+        assert!(matches!(
+            additive_migration("a.transfer", &existing, &changed),
+            Err(SchemaEvolutionError::IncompatibleColumn { .. })
+        ));
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    env,
+    io::{self, BufRead, IsTerminal, Write},
+    path::Path,
+};
+
+use tracing::info;
+
+use crate::{
+    database::postgres::{
+        client::PostgresClient,
+        schema_evolution::{diff_event_tables, EventTablesDiff, SchemaEvolutionError},
+    },
+    manifest::{core::Manifest, storage::SchemaEvolution},
+};
+
+/// Set by `rindexer start --auto-approve`, an env var so the rust project process picks it up as
+/// well
+pub const AUTO_APPROVE_SCHEMA_CHANGES_ENV: &str = "RINDEXER_AUTO_APPROVE_SCHEMA_CHANGES";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SchemaPlanError {
+    #[error("{0}")]
+    SchemaEvolution(#[from] SchemaEvolutionError),
+
+    #[error("The schema changes for {0} were not approved - review the plan and run with --auto-approve to apply them")]
+    NotApproved(String),
+
+    #[error("Could not read the approval: {0}")]
+    Io(#[from] io::Error),
+}
+
+fn plan_lines(diff: &EventTablesDiff, add_columns: bool) -> Vec<String> {
+    let mut lines: Vec<String> =
+        diff.missing_tables.iter().map(|table| format!("+ create table {}", table)).collect();
+    if add_columns {
+        lines.extend(diff.missing_columns.iter().map(|statement| format!("+ {}", statement)));
+    }
+    lines
+}
+
+fn auto_approved() -> bool {
+    env::var(AUTO_APPROVE_SCHEMA_CHANGES_ENV).is_ok_and(|value| value == "true")
+}
+
+fn prompt_for_approval() -> Result<bool, io::Error> {
+    print!("Apply these changes? (yes/no): ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "yes" | "y"))
+}
+
+/// Prints the plan and returns whether it still needs approving, which is only the case when it
+/// changes a database that already has some of the event tables and `--auto-approve` is not set
+async fn print_schema_plan(
+    client: &PostgresClient,
+    project_path: &Path,
+    manifest: &Manifest,
+    add_columns: bool,
+) -> Result<bool, SchemaPlanError> {
+    let diff = diff_event_tables(client, project_path, manifest).await?;
+    let lines = plan_lines(&diff, add_columns);
+    if lines.is_empty() {
+        info!("The event tables of {} are up to date", manifest.name);
+        return Ok(false);
+    }
+
+    info!("Schema plan for {}:\n{}", manifest.name, lines.join("\n"));
+    if diff.existing_tables.is_empty() {
+        return Ok(false);
+    }
+    if auto_approved() {
+        info!("Schema changes for {} approved with --auto-approve", manifest.name);
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// Prints the tables and columns setting up postgres adds to the event tables, every change is
+/// additive. A database without any of the tables is set up straight away, changes to one which
+/// already has some are asked about when running on a terminal and applied otherwise.
+pub async fn approve_schema_plan(
+    client: &PostgresClient,
+    project_path: &Path,
+    manifest: &Manifest,
+) -> Result<(), SchemaPlanError> {
+    // columns are only added here when schema evolution applies them itself
+    let add_columns = manifest.storage.postgres_schema_evolution() == SchemaEvolution::Auto;
+    if !print_schema_plan(client, project_path, manifest, add_columns).await? {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        info!("Applying the schema changes for {} as there is no terminal to ask", manifest.name);
+        return Ok(());
+    }
+
+    let approved = tokio::task::spawn_blocking(prompt_for_approval)
+        .await
+        .expect("schema plan prompt task panicked")?;
+    if approved {
+        return Ok(());
+    }
+
+    Err(SchemaPlanError::NotApproved(manifest.name.clone()))
+}
+
+/// The plan for tables created while running, from the admin API. Nobody is there to ask so
+/// changes touching tables which already exist are only applied with `--auto-approve`.
+pub async fn approve_unattended_schema_plan(
+    client: &PostgresClient,
+    project_path: &Path,
+    manifest: &Manifest,
+) -> Result<(), SchemaPlanError> {
+    // only the tables are created, columns are left to schema evolution on the next start
+    if print_schema_plan(client, project_path, manifest, false).await? {
+        return Err(SchemaPlanError::NotApproved(manifest.name.clone()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_lines_only_add_columns_when_applied() {
+        let diff = EventTablesDiff {
+            existing_tables: vec!["indexer_erc20.transfer".to_string()],
+            missing_tables: vec!["indexer_erc20.approval".to_string()],
+            missing_columns: vec![
+                "ALTER TABLE indexer_erc20.transfer ADD COLUMN IF NOT EXISTS \"memo\" TEXT;"
+                    .to_string(),
+            ],
+        };
+
+        assert_eq!(
+            plan_lines(&diff, true),
+            vec![
+                "+ create table indexer_erc20.approval",
+                "+ ALTER TABLE indexer_erc20.transfer ADD COLUMN IF NOT EXISTS \"memo\" TEXT;"
+            ]
+        );
+        assert_eq!(plan_lines(&diff, false), vec!["+ create table indexer_erc20.approval"]);
+    }
+}
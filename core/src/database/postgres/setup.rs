@@ -4,11 +4,26 @@ use tracing::{debug, info};
 
 use crate::{
     database::postgres::{
+        address_labels::{load_address_labels, AddressLabelsError},
+        block_timestamps::{
+            drop_block_timestamps_tables_sql, generate_block_timestamps_tables_sql,
+        },
         client::{PostgresClient, PostgresConnectionError, PostgresError},
+        derived_fields::derived_field_functions_sql,
+        encryption::store_column_encryption_key,
+        entities::{drop_entities_tables_sql, generate_entities_tables_sql},
         generate::{generate_tables_for_indexer_sql, GenerateTablesForIndexerSqlError},
+        materialized_views::create_materialized_views,
+        migrations::{run_migrations, MigrationsError},
+        roles::graphql_roles_sql,
+        rollups::{drop_rollups_tables_sql, generate_rollups_tables_sql},
+        schema_evolution::{evolve_event_tables, SchemaEvolutionError},
+        schema_plan::{approve_schema_plan, SchemaPlanError},
+        state_samples::{drop_state_samples_tables_sql, generate_state_samples_tables_sql},
     },
     drop_tables_for_indexer_sql,
     manifest::core::Manifest,
+    modules::{drop_modules_tables_sql, generate_modules_tables_sql},
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -21,6 +36,18 @@ pub enum SetupPostgresError {
 
     #[error("Error creating tables for indexer: {0}")]
     GeneratingTables(#[from] GenerateTablesForIndexerSqlError),
+
+    #[error("Could not apply the migrations: {0}")]
+    Migrations(#[from] MigrationsError),
+
+    #[error("Could not update the tables for the ABI changes: {0}")]
+    SchemaEvolution(#[from] SchemaEvolutionError),
+
+    #[error("{0}")]
+    SchemaPlan(#[from] SchemaPlanError),
+
+    #[error("Could not load the address labels: {0}")]
+    AddressLabels(#[from] AddressLabelsError),
 }
 
 pub async fn setup_postgres(
@@ -39,9 +66,19 @@ pub async fn setup_postgres(
         );
         let sql = drop_tables_for_indexer_sql(project_path, &manifest.to_indexer());
         client.batch_execute(sql.as_str()).await?;
+        client.batch_execute(&drop_modules_tables_sql(manifest)).await?;
+        client.batch_execute(&drop_entities_tables_sql(&manifest.name)).await?;
+        client.batch_execute(&drop_rollups_tables_sql(&manifest.name)).await?;
+        client.batch_execute(&drop_state_samples_tables_sql(&manifest.name)).await?;
+        client.batch_execute(&drop_block_timestamps_tables_sql(&manifest.name)).await?;
         info!("Dropped all data for {}", manifest.name);
     }
 
+    // before anything is created so the plan shows what this run changes
+    if !disable_event_tables {
+        approve_schema_plan(&client, project_path, manifest).await?;
+    }
+
     if !disable_event_tables {
         info!("Creating tables for {}", manifest.name);
     } else {
@@ -51,14 +88,75 @@ pub async fn setup_postgres(
         project_path,
         &manifest.to_indexer(),
         disable_event_tables,
+        manifest.storage.postgres_soft_delete_reorged_rows(),
+        manifest.storage.postgres_notify_channel(),
+        manifest.storage.postgres_column_encryption(),
+        manifest.labels.is_some(),
     )?;
     debug!("{}", sql);
     client.batch_execute(sql.as_str()).await?;
+    if let Some(encryption) = manifest.storage.postgres_column_encryption() {
+        store_column_encryption_key(&client, &manifest.name, &encryption.key).await?;
+    }
+    client.batch_execute(&generate_modules_tables_sql(manifest)).await?;
+    client
+        .batch_execute(&generate_entities_tables_sql(
+            &manifest.name,
+            manifest.storage.postgres_entities(),
+        ))
+        .await?;
+    client
+        .batch_execute(&generate_rollups_tables_sql(
+            &manifest.name,
+            manifest.storage.postgres_rollups(),
+        ))
+        .await?;
+    client
+        .batch_execute(&generate_state_samples_tables_sql(
+            &manifest.name,
+            manifest.storage.postgres_state_samples(),
+        ))
+        .await?;
+    if manifest.storage.postgres_block_timestamps().is_some() {
+        client.batch_execute(&generate_block_timestamps_tables_sql(&manifest.name)).await?;
+    }
     if !disable_event_tables {
         info!("Created tables for {}", manifest.name);
     } else {
         info!("Created internal rindexer tables for {}", manifest.name);
     }
 
+    // after the generated tables so migrations can build on them
+    run_migrations(&client, project_path, &manifest.name).await?;
+
+    if !disable_event_tables {
+        evolve_event_tables(&client, project_path, manifest).await?;
+    }
+
+    client
+        .batch_execute(&derived_field_functions_sql(
+            &manifest.name,
+            &manifest.contracts,
+            manifest.storage.postgres_entities(),
+            manifest.graphql_derived_fields(),
+        ))
+        .await?;
+
+    let roles = manifest.graphql_roles();
+    if !roles.is_empty() && !disable_event_tables {
+        client
+            .batch_execute(&graphql_roles_sql(&manifest.name, &manifest.contracts, roles))
+            .await?;
+    }
+
+    let materialized_views = manifest.storage.postgres_materialized_views();
+    if !materialized_views.is_empty() {
+        create_materialized_views(&client, &manifest.name, materialized_views).await?;
+    }
+
+    if let Some(labels) = &manifest.labels {
+        load_address_labels(&client, project_path, &manifest.name, labels).await?;
+    }
+
     Ok(client)
 }
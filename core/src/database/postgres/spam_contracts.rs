@@ -0,0 +1,84 @@
+use std::{str::FromStr, sync::Arc};
+
+use ethers::types::Address;
+
+use crate::{
+    database::postgres::client::{PostgresClient, PostgresError},
+    helpers::camel_to_snake,
+    types::code::Code,
+};
+
+pub fn spam_contracts_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_spam_contracts", camel_to_snake(indexer_name))
+}
+
+pub fn generate_spam_contracts_table_sql(indexer_name: &str) -> Code {
+    Code::new(format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {table} (
+            network TEXT NOT NULL,
+            contract_address CHAR(42) NOT NULL,
+            reason TEXT NOT NULL,
+            found_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (network, contract_address)
+        );
+    "#,
+        table = spam_contracts_table_name(indexer_name)
+    ))
+}
+
+pub fn drop_spam_contracts_table_sql(indexer_name: &str) -> Code {
+    Code::new(format!("DROP TABLE IF EXISTS {} CASCADE;", spam_contracts_table_name(indexer_name)))
+}
+
+/// The spam contracts the spam filters found, queries can leave the events of them out when
+/// they were only flagged and the contracts are still spam after a restart
+#[derive(Clone)]
+pub struct SpamContractsStore {
+    client: Arc<PostgresClient>,
+    table: String,
+}
+
+impl SpamContractsStore {
+    pub fn new(client: Arc<PostgresClient>, indexer_name: &str) -> Self {
+        Self { client, table: spam_contracts_table_name(indexer_name) }
+    }
+
+    /// The first reason a contract was found for is kept
+    pub async fn record(
+        &self,
+        network: &str,
+        address: Address,
+        reason: &str,
+    ) -> Result<(), PostgresError> {
+        self.client
+            .execute(
+                &format!(
+                    "INSERT INTO {} (network, contract_address, reason) VALUES ($1, $2, $3)
+                     ON CONFLICT (network, contract_address) DO NOTHING",
+                    self.table
+                ),
+                &[&network, &format!("{:?}", address), &reason],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn load(&self, network: &str) -> Result<Vec<(Address, String)>, PostgresError> {
+        let rows = self
+            .client
+            .query(
+                &format!("SELECT contract_address, reason FROM {} WHERE network = $1", self.table),
+                &[&network],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let address = Address::from_str(row.get::<_, String>(0).trim()).ok()?;
+                Some((address, row.get(1)))
+            })
+            .collect())
+    }
+}
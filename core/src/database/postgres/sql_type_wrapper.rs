@@ -103,7 +103,13 @@ pub enum EthereumSqlTypeWrapper {
     BytesNullable(Bytes),
     VecBytes(Vec<Bytes>),
 
+    // Decimal strings written as NUMERIC with their full precision
+    Numeric(String),
+    NumericNullable(String),
+
     DateTime(DateTime<Utc>),
+
+    Jsonb(Value),
 }
 
 impl EthereumSqlTypeWrapper {
@@ -195,7 +201,10 @@ impl EthereumSqlTypeWrapper {
             EthereumSqlTypeWrapper::BytesNullable(_) => "BytesNullable",
             EthereumSqlTypeWrapper::VecBytes(_) => "VecBytes",
 
+            EthereumSqlTypeWrapper::Numeric(_) => "Numeric",
+            EthereumSqlTypeWrapper::NumericNullable(_) => "NumericNullable",
             EthereumSqlTypeWrapper::DateTime(_) => "DateTime",
+            EthereumSqlTypeWrapper::Jsonb(_) => "Jsonb",
         }
     }
 
@@ -296,8 +305,15 @@ impl EthereumSqlTypeWrapper {
             }
             EthereumSqlTypeWrapper::VecBytes(_) => PgType::BYTEA_ARRAY,
 
+            // Numeric
+            EthereumSqlTypeWrapper::Numeric(_) | EthereumSqlTypeWrapper::NumericNullable(_) => {
+                PgType::NUMERIC
+            }
+
             // DateTime
             EthereumSqlTypeWrapper::DateTime(_) => PgType::TIMESTAMPTZ,
+
+            EthereumSqlTypeWrapper::Jsonb(_) => PgType::JSONB,
         }
     }
 }
@@ -705,7 +721,21 @@ impl ToSql for EthereumSqlTypeWrapper {
                     int_values.to_sql(ty, out)
                 }
             }
+            EthereumSqlTypeWrapper::Numeric(value) => serialize_numeric_string(value, out),
+            EthereumSqlTypeWrapper::NumericNullable(value) => {
+                if value.is_empty() {
+                    return Ok(IsNull::Yes);
+                }
+
+                serialize_numeric_string(value, out)
+            }
             EthereumSqlTypeWrapper::DateTime(value) => value.to_sql(ty, out),
+            EthereumSqlTypeWrapper::Jsonb(value) => {
+                // the binary jsonb format is a version byte followed by the json text
+                out.extend_from_slice(&[1]);
+                out.extend_from_slice(value.to_string().as_bytes());
+                Ok(IsNull::No)
+            }
         }
     }
 
@@ -1223,6 +1253,65 @@ impl From<&Address> for EthereumSqlTypeWrapper {
     }
 }
 
+/// Writes a decimal string in the binary NUMERIC format, unlike `Decimal` which holds 28 digits
+/// this keeps the full precision of a uint256 divided by its decimals
+fn serialize_numeric_string(
+    value: &str,
+    out: &mut BytesMut,
+) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+    let (is_negative, unsigned) = match value.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, value),
+    };
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    if (integer.is_empty() && fraction.is_empty()) ||
+        !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+    {
+        return Err(format!("Invalid numeric value: {}", value).into());
+    }
+
+    // numeric digits are base 10000, so both sides of the point are padded to groups of 4
+    let integer = integer.trim_start_matches('0');
+    let integer_padding = (4 - integer.len() % 4) % 4;
+    let fraction_padding = (4 - fraction.len() % 4) % 4;
+    let padded = format!(
+        "{}{}{}{}",
+        "0".repeat(integer_padding),
+        integer,
+        fraction,
+        "0".repeat(fraction_padding)
+    );
+    let mut digits: Vec<i16> = padded
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| chunk.iter().fold(0i16, |digit, c| digit * 10 + (c - b'0') as i16))
+        .collect();
+    let mut weight = ((integer_padding + integer.len()) / 4) as i16 - 1;
+
+    // leading and trailing zero digits are implied by the weight and scale
+    while digits.first() == Some(&0) {
+        digits.remove(0);
+        weight -= 1;
+    }
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    if digits.is_empty() {
+        weight = 0;
+    }
+
+    let sign: u16 = if is_negative && !digits.is_empty() { 0x4000 } else { 0x0000 };
+    out.extend_from_slice(&(digits.len() as i16).to_be_bytes());
+    out.extend_from_slice(&weight.to_be_bytes());
+    out.extend_from_slice(&sign.to_be_bytes());
+    out.extend_from_slice(&(fraction.len() as u16).to_be_bytes());
+    for digit in digits {
+        out.extend_from_slice(&digit.to_be_bytes());
+    }
+
+    Ok(IsNull::No)
+}
+
 fn serialize_vec_decimal<T: ToString>(
     values: &Vec<T>,
     ty: &PgType,
@@ -1275,6 +1364,28 @@ pub fn map_ethereum_wrapper_to_json(
     transaction_information: &TxInformation,
     is_within_tuple: bool,
 ) -> Value {
+    let mut result = map_ethereum_wrapper_to_json_object(abi_inputs, wrappers);
+
+    // only do this at the top level
+    if !is_within_tuple {
+        result.insert("transaction_information".to_string(), json!(transaction_information));
+    }
+
+    Value::Object(result)
+}
+
+/// The decoded params of an event keyed by input name, without the transaction information
+pub fn map_ethereum_wrapper_to_params_json(
+    abi_inputs: &[ABIInput],
+    wrappers: &[EthereumSqlTypeWrapper],
+) -> Value {
+    Value::Object(map_ethereum_wrapper_to_json_object(abi_inputs, wrappers))
+}
+
+fn map_ethereum_wrapper_to_json_object(
+    abi_inputs: &[ABIInput],
+    wrappers: &[EthereumSqlTypeWrapper],
+) -> serde_json::Map<String, Value> {
     let mut result = serde_json::Map::new();
 
     let mut current_wrapper_index = 0;
@@ -1289,12 +1400,10 @@ pub fn map_ethereum_wrapper_to_json(
                 let components =
                     abi_input.components.as_ref().expect("Tuple should have components defined");
                 let total_properties = count_components(components);
-                let tuple_value = map_ethereum_wrapper_to_json(
+                let tuple_value = Value::Object(map_ethereum_wrapper_to_json_object(
                     components,
                     &wrappers[current_wrapper_index..total_properties],
-                    transaction_information,
-                    true,
-                );
+                ));
                 result.insert(abi_input.name.clone(), tuple_value);
                 for i in current_wrapper_index..total_properties {
                     wrappers_index_processed.push(i);
@@ -1392,9 +1501,12 @@ pub fn map_ethereum_wrapper_to_json(
                     EthereumSqlTypeWrapper::VecBytes(bytes) => {
                         json!(bytes.iter().map(hex::encode).collect::<Vec<_>>())
                     }
+                    EthereumSqlTypeWrapper::Numeric(value) |
+                    EthereumSqlTypeWrapper::NumericNullable(value) => json!(value),
                     EthereumSqlTypeWrapper::DateTime(date_time) => {
                         json!(date_time.to_rfc3339())
                     }
+                    EthereumSqlTypeWrapper::Jsonb(value) => value.clone(),
                 };
                 result.insert(abi_input.name.clone(), value);
                 wrappers_index_processed.push(current_wrapper_index);
@@ -1408,10 +1520,40 @@ pub fn map_ethereum_wrapper_to_json(
         }
     }
 
-    // only do this at the top level
-    if !is_within_tuple {
-        result.insert("transaction_information".to_string(), json!(transaction_information));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numeric_bytes(value: &str) -> Vec<u8> {
+        let mut out = BytesMut::new();
+        serialize_numeric_string(value, &mut out).unwrap();
+        out.to_vec()
     }
 
-    Value::Object(result)
+    #[test]
+    fn test_serialize_numeric_string() {
+        // 12 3456 . 7890 - 3 digits, weight 1, positive, scale 3
+        assert_eq!(
+            numeric_bytes("123456.789"),
+            vec![0, 3, 0, 1, 0, 0, 0, 3, 0, 12, 13, 128, 30, 210]
+        );
+        // 0.0000 1234 - the leading zero digit moves the weight down
+        assert_eq!(numeric_bytes("-0.00001234"), vec![0, 1, 255, 254, 64, 0, 0, 8, 4, 210]);
+        assert_eq!(numeric_bytes("0.000"), vec![0, 0, 0, 0, 0, 0, 0, 3]);
+
+        let mut out = BytesMut::new();
+        assert!(serialize_numeric_string("1e18", &mut out).is_err());
+    }
+
+    #[test]
+    fn test_jsonb_is_written_with_the_version_byte() {
+        let mut out = BytesMut::new();
+        EthereumSqlTypeWrapper::Jsonb(json!({"value": "1"}))
+            .to_sql(&PgType::JSONB, &mut out)
+            .unwrap();
+        assert_eq!(out.to_vec(), [&[1u8][..], br#"{"value":"1"}"#].concat());
+    }
 }
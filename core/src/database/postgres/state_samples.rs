@@ -0,0 +1,283 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use ethers::{
+    abi::{Function, ParamType},
+    types::{Address, Bytes, U64},
+};
+use tracing::{error, info};
+
+use crate::{
+    database::postgres::client::PostgresClient,
+    helpers::camel_to_snake,
+    indexer::enrichment::{parse_view_function, token_text},
+    is_running,
+    manifest::storage::StateSample,
+    provider::JsonRpcCachedProvider,
+    EthereumSqlTypeWrapper,
+};
+
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+const BASE_COLUMNS: [&str; 3] = ["contract_address", "network", "block_number"];
+
+pub fn state_samples_schema_name(indexer_name: &str) -> String {
+    format!("{}_state_samples", camel_to_snake(indexer_name))
+}
+
+fn state_sample_table_name(indexer_name: &str, sample: &StateSample) -> String {
+    format!("{}.{}", state_samples_schema_name(indexer_name), camel_to_snake(&sample.name))
+}
+
+/// A column per output of the function, named after the function alone when it has one output
+pub fn state_sample_columns(function: &Function) -> Vec<String> {
+    let name = camel_to_snake(&function.name);
+    if function.outputs.len() == 1 {
+        return vec![name];
+    }
+
+    function
+        .outputs
+        .iter()
+        .enumerate()
+        .map(|(index, output)| {
+            if output.name.is_empty() {
+                format!("{}_{}", name, index)
+            } else {
+                format!("{}_{}", name, camel_to_snake(&output.name))
+            }
+        })
+        .collect()
+}
+
+fn is_numeric(kind: &ParamType) -> bool {
+    matches!(kind, ParamType::Uint(_) | ParamType::Int(_))
+}
+
+/// The functions are checked when the manifest is validated
+fn sample_functions(sample: &StateSample) -> Vec<Function> {
+    sample.functions.iter().filter_map(|function| parse_view_function(function).ok()).collect()
+}
+
+/// The columns of the function outputs are added after the table so functions can be added to a
+/// sample which already has rows
+pub fn generate_state_samples_tables_sql(indexer_name: &str, samples: &[StateSample]) -> String {
+    if samples.is_empty() {
+        return String::new();
+    }
+
+    let mut sql =
+        format!("CREATE SCHEMA IF NOT EXISTS {};", state_samples_schema_name(indexer_name));
+    for sample in samples {
+        let table_name = state_sample_table_name(indexer_name, sample);
+        sql.push_str(&format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {table} (
+                rindexer_id SERIAL PRIMARY KEY NOT NULL,
+                contract_address CHAR(42) NOT NULL,
+                network TEXT NOT NULL,
+                block_number NUMERIC NOT NULL,
+                sampled_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            );
+            CREATE INDEX IF NOT EXISTS {index_name}_address_block_idx ON {table} (contract_address, block_number);
+            "#,
+            table = table_name,
+            index_name = camel_to_snake(&sample.name)
+        ));
+
+        let columns: Vec<String> = sample_functions(sample)
+            .iter()
+            .flat_map(|function| {
+                state_sample_columns(function).into_iter().zip(&function.outputs).map(
+                    |(column, output)| {
+                        format!(
+                            "ADD COLUMN IF NOT EXISTS \"{}\" {}",
+                            column,
+                            if is_numeric(&output.kind) { "NUMERIC" } else { "TEXT" }
+                        )
+                    },
+                )
+            })
+            .collect();
+        sql.push_str(&format!("ALTER TABLE {} {};", table_name, columns.join(", ")));
+    }
+
+    sql
+}
+
+pub fn drop_state_samples_tables_sql(indexer_name: &str) -> String {
+    format!("DROP SCHEMA IF EXISTS {} CASCADE;", state_samples_schema_name(indexer_name))
+}
+
+struct Sampler {
+    table_name: String,
+    sample: StateSample,
+    functions: Vec<Function>,
+    columns: Vec<String>,
+}
+
+impl Sampler {
+    fn new(indexer_name: &str, sample: StateSample) -> Self {
+        let functions = sample_functions(&sample);
+        let mut columns: Vec<String> =
+            BASE_COLUMNS.iter().map(|column| column.to_string()).collect();
+        columns.extend(functions.iter().flat_map(state_sample_columns));
+
+        Self {
+            table_name: state_sample_table_name(indexer_name, &sample),
+            sample,
+            functions,
+            columns,
+        }
+    }
+
+    /// The row of an address, outputs of a call which reverted are left empty
+    fn row(
+        &self,
+        address: Address,
+        block: U64,
+        results: &[Option<Bytes>],
+    ) -> Vec<EthereumSqlTypeWrapper> {
+        let mut row = vec![
+            EthereumSqlTypeWrapper::Address(address),
+            EthereumSqlTypeWrapper::String(self.sample.network.clone()),
+            EthereumSqlTypeWrapper::U64(block),
+        ];
+
+        for (function, result) in self.functions.iter().zip(results) {
+            let tokens = result.as_ref().and_then(|result| function.decode_output(result).ok());
+            for (index, output) in function.outputs.iter().enumerate() {
+                let value = tokens
+                    .as_ref()
+                    .and_then(|tokens| tokens.get(index))
+                    .map(token_text)
+                    .unwrap_or_default();
+                row.push(if is_numeric(&output.kind) {
+                    EthereumSqlTypeWrapper::NumericNullable(value)
+                } else {
+                    EthereumSqlTypeWrapper::StringNullable(value)
+                });
+            }
+        }
+
+        row
+    }
+
+    /// Calls every function on every address at the head in one multicall
+    async fn take(
+        &self,
+        client: &PostgresClient,
+        provider: &JsonRpcCachedProvider,
+        block: U64,
+    ) -> Result<(), String> {
+        let calls: Vec<(Address, Bytes)> = self
+            .sample
+            .addresses
+            .iter()
+            .flat_map(|address| {
+                self.functions.iter().map(|function| {
+                    let call_data =
+                        function.encode_input(&[]).expect("the functions take no arguments");
+                    (*address, Bytes::from(call_data))
+                })
+            })
+            .collect();
+        let results = provider.call_many_at(calls, block).await.map_err(|e| e.to_string())?;
+
+        let rows: Vec<Vec<EthereumSqlTypeWrapper>> = self
+            .sample
+            .addresses
+            .iter()
+            .zip(results.chunks(self.functions.len()))
+            .map(|(address, results)| self.row(*address, block, results))
+            .collect();
+
+        client.insert_bulk(&self.table_name, &self.columns, &rows).await
+    }
+}
+
+async fn run_sampler(
+    client: Arc<PostgresClient>,
+    sampler: Sampler,
+    provider: Arc<JsonRpcCachedProvider>,
+) {
+    let mut last_sampled_block: Option<u64> = None;
+    while is_running() {
+        match sampler.sample.interval_seconds {
+            Some(interval_seconds) => {
+                tokio::time::sleep(Duration::from_secs(interval_seconds)).await
+            }
+            None => tokio::time::sleep(BLOCK_POLL_INTERVAL).await,
+        }
+
+        let head = match provider.get_block_number().await {
+            Ok(head) => head,
+            Err(e) => {
+                error!("Could not get the block number to sample {}: {}", sampler.sample.name, e);
+                continue;
+            }
+        };
+
+        if let Some(every_blocks) = sampler.sample.every_blocks {
+            if last_sampled_block.is_some_and(|last| head.as_u64() < last + every_blocks) {
+                continue;
+            }
+        }
+
+        match sampler.take(&client, &provider, head).await {
+            Ok(()) => {
+                info!("Sampled state {} at block {}", sampler.sample.name, head);
+                last_sampled_block = Some(head.as_u64());
+            }
+            Err(e) => error!("Could not sample state {}: {}", sampler.sample.name, e),
+        }
+    }
+}
+
+/// Samples every state sample in the background on its own schedule, only the indexer which owns
+/// the tables should run this
+pub fn start_state_samplers(
+    client: Arc<PostgresClient>,
+    indexer_name: &str,
+    samples: &[StateSample],
+    providers: &HashMap<String, Arc<JsonRpcCachedProvider>>,
+) {
+    for sample in samples {
+        let Some(provider) = providers.get(&sample.network) else {
+            error!(
+                "State sample {} samples network {} but nothing is indexed on it - it will not be sampled",
+                sample.name, sample.network
+            );
+            continue;
+        };
+
+        tokio::spawn(run_sampler(
+            Arc::clone(&client),
+            Sampler::new(indexer_name, sample.clone()),
+            Arc::clone(provider),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_sample_columns() {
+        let get_reserves = parse_view_function(
+            "getReserves() returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast)",
+        )
+        .unwrap();
+        assert_eq!(
+            state_sample_columns(&get_reserves),
+            vec![
+                "get_reserves_reserve_0",
+                "get_reserves_reserve_1",
+                "get_reserves_block_timestamp_last"
+            ]
+        );
+
+        let total_supply = parse_view_function("totalSupply() returns (uint256)").unwrap();
+        assert_eq!(state_sample_columns(&total_supply), vec!["total_supply"]);
+    }
+}
@@ -0,0 +1,296 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use ethers::{
+    abi::{
+        token::{LenientTokenizer, Tokenizer},
+        Abi, Token,
+    },
+    middleware::Middleware,
+    providers::ProviderError,
+    types::{Address, Bytes, TransactionRequest, ValueOrArray, U64},
+    utils::{Anvil, AnvilInstance},
+};
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    helpers::get_full_path,
+    manifest::{
+        contract::{Contract, ContractDetails, ContractDevSettings},
+        core::Manifest,
+        network::Network,
+        yaml::{
+            read_manifest_raw, write_manifest, ReadManifestError, WriteManifestError,
+            YAML_CONFIG_NAME,
+        },
+    },
+    provider::{create_client, JsonRpcCachedProvider, RetryClientError},
+    types::single_or_array::StringOrArray,
+};
+
+/// `rindexer dev` runs the indexer from a generated project in this folder so the real
+/// rindexer.yaml is never touched
+pub const DEV_FOLDER: &str = ".rindexer-dev";
+pub const DEV_NETWORK: &str = "anvil";
+
+#[derive(thiserror::Error, Debug)]
+pub enum DevError {
+    #[error("Could not read the manifest: {0}")]
+    ReadManifest(#[from] ReadManifestError),
+
+    #[error("Could not write the dev manifest: {0}")]
+    WriteManifest(#[from] WriteManifestError),
+
+    #[error("No contracts have `dev.foundry_artifact` set so there is nothing to deploy")]
+    NothingToDeploy,
+
+    #[error("Could not read the foundry artifact {0}: {1}")]
+    ReadArtifact(String, String),
+
+    #[error("Contract {0} constructor arguments are invalid: {1}")]
+    InvalidConstructorArgs(String, String),
+
+    #[error("Could not create the provider for anvil: {0}")]
+    Provider(#[from] RetryClientError),
+
+    #[error("Anvil request failed: {0}")]
+    Request(#[from] ProviderError),
+
+    #[error("Deploying {0} failed: {1}")]
+    DeployFailed(String, String),
+
+    #[error("Could not create the dev folder: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Deserialize)]
+struct ArtifactBytecode {
+    object: Bytes,
+}
+
+#[derive(Deserialize)]
+struct FoundryArtifact {
+    abi: Abi,
+    bytecode: ArtifactBytecode,
+}
+
+fn read_foundry_artifact(project_path: &Path, path: &str) -> Result<FoundryArtifact, DevError> {
+    let full_path = get_full_path(project_path, path)
+        .map_err(|e| DevError::ReadArtifact(path.to_string(), e.to_string()))?;
+    let contents = fs::read_to_string(full_path)
+        .map_err(|e| DevError::ReadArtifact(path.to_string(), e.to_string()))?;
+
+    serde_json::from_str(&contents)
+        .map_err(|e| DevError::ReadArtifact(path.to_string(), e.to_string()))
+}
+
+/// The creation code with the abi encoded constructor arguments appended
+fn deploy_code(
+    contract_name: &str,
+    artifact: &FoundryArtifact,
+    settings: &ContractDevSettings,
+) -> Result<Bytes, DevError> {
+    let args = settings.constructor_args.clone().unwrap_or_default();
+    let Some(constructor) = artifact.abi.constructor() else {
+        if !args.is_empty() {
+            return Err(DevError::InvalidConstructorArgs(
+                contract_name.to_string(),
+                "the contract has no constructor".to_string(),
+            ));
+        }
+        return Ok(artifact.bytecode.object.clone());
+    };
+
+    if constructor.inputs.len() != args.len() {
+        return Err(DevError::InvalidConstructorArgs(
+            contract_name.to_string(),
+            format!("expected {} arguments but got {}", constructor.inputs.len(), args.len()),
+        ));
+    }
+
+    let tokens = constructor
+        .inputs
+        .iter()
+        .zip(args.iter())
+        .map(|(input, arg)| LenientTokenizer::tokenize(&input.kind, arg))
+        .collect::<Result<Vec<Token>, _>>()
+        .map_err(|e| DevError::InvalidConstructorArgs(contract_name.to_string(), e.to_string()))?;
+
+    let code = constructor
+        .encode_input(artifact.bytecode.object.to_vec(), &tokens)
+        .map_err(|e| DevError::InvalidConstructorArgs(contract_name.to_string(), e.to_string()))?;
+
+    Ok(Bytes::from(code))
+}
+
+fn absolute_path(project_path: &Path, path: &str) -> String {
+    get_full_path(project_path, path)
+        .map(|full_path| full_path.display().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// The files which trigger a redeploy and restart when they change
+pub fn watched_files(project_path: &Path) -> Result<Vec<PathBuf>, DevError> {
+    let manifest_path = project_path.join(YAML_CONFIG_NAME);
+    let manifest = read_manifest_raw(&manifest_path)?;
+
+    let mut files = vec![manifest_path];
+    for contract in &manifest.contracts {
+        if let Some(dev) = &contract.dev {
+            files.push(PathBuf::from(absolute_path(project_path, &dev.foundry_artifact)));
+        }
+        match &contract.abi {
+            StringOrArray::Single(abi) => {
+                files.push(PathBuf::from(absolute_path(project_path, abi)))
+            }
+            StringOrArray::Multiple(abis) => {
+                files.extend(abis.iter().map(|abi| PathBuf::from(absolute_path(project_path, abi))))
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Remembers when the watched files were last modified, returns true when any of them changed
+/// since the last call
+#[derive(Default)]
+pub struct FileChanges {
+    modified: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl FileChanges {
+    pub fn changed(&mut self, files: &[PathBuf]) -> bool {
+        let mut changed = false;
+        for file in files {
+            let modified = fs::metadata(file).and_then(|metadata| metadata.modified()).ok();
+            if self.modified.insert(file.clone(), modified) != Some(modified) {
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// A local anvil node the dev contracts get deployed to, killed when dropped. Needs `anvil` on the
+/// path.
+pub struct DevChain {
+    instance: AnvilInstance,
+    provider: Arc<JsonRpcCachedProvider>,
+}
+
+impl DevChain {
+    pub fn spawn(port: Option<u16>) -> Result<Self, DevError> {
+        let mut anvil = Anvil::new();
+        if let Some(port) = port {
+            anvil = anvil.port(port);
+        }
+        let instance = anvil.spawn();
+        let provider = create_client(&instance.endpoint(), None, None, HeaderMap::new())?;
+        info!("Anvil running at {}", instance.endpoint());
+
+        Ok(Self { instance, provider })
+    }
+
+    pub fn rpc_url(&self) -> String {
+        self.instance.endpoint()
+    }
+
+    async fn deploy(&self, contract_name: &str, code: Bytes) -> Result<(Address, U64), DevError> {
+        let from = self.instance.addresses()[0];
+        let provider = self.provider.get_inner_provider();
+        let pending = provider
+            .send_transaction(TransactionRequest::new().from(from).data(code), None)
+            .await?;
+        let receipt = pending.await?.ok_or_else(|| {
+            DevError::DeployFailed(contract_name.to_string(), "transaction dropped".to_string())
+        })?;
+
+        let address = receipt.contract_address.ok_or_else(|| {
+            DevError::DeployFailed(contract_name.to_string(), "no contract created".to_string())
+        })?;
+        let block_number = receipt.block_number.unwrap_or_default();
+
+        Ok((address, block_number))
+    }
+
+    /// Deploys every contract with dev settings and writes a project into [`DEV_FOLDER`] which
+    /// indexes them from anvil starting at their deploy block, returns the dev project path
+    pub async fn deploy_project(&self, project_path: &Path) -> Result<PathBuf, DevError> {
+        let mut manifest: Manifest = read_manifest_raw(&project_path.join(YAML_CONFIG_NAME))?;
+
+        let mut contracts: Vec<Contract> = vec![];
+        for mut contract in manifest.contracts.into_iter() {
+            let Some(dev) = contract.dev.clone() else {
+                info!("{} has no dev settings so is not indexed in dev mode", contract.name);
+                continue;
+            };
+
+            let artifact = read_foundry_artifact(project_path, &dev.foundry_artifact)?;
+            let code = deploy_code(&contract.name, &artifact, &dev)?;
+            let (address, block_number) = self.deploy(&contract.name, code).await?;
+            info!("Deployed {} to {:?} in block {}", contract.name, address, block_number);
+
+            contract.details = vec![ContractDetails::new_with_address(
+                DEV_NETWORK.to_string(),
+                ValueOrArray::Value(address),
+                None,
+                Some(block_number),
+                None,
+            )];
+            contract.abi = match contract.abi {
+                StringOrArray::Single(abi) => {
+                    StringOrArray::Single(absolute_path(project_path, &abi))
+                }
+                StringOrArray::Multiple(abis) => StringOrArray::Multiple(
+                    abis.iter().map(|abi| absolute_path(project_path, abi)).collect(),
+                ),
+            };
+            contracts.push(contract);
+        }
+
+        if contracts.is_empty() {
+            return Err(DevError::NothingToDeploy);
+        }
+
+        manifest.contracts = contracts;
+        manifest.networks = vec![Network {
+            name: DEV_NETWORK.to_string(),
+            chain_id: self.instance.chain_id(),
+            rpc: self.rpc_url(),
+            compute_units_per_second: None,
+            max_block_range: None,
+            disable_logs_bloom_checks: None,
+            rpc_headers: None,
+            rpc_bearer_token: None,
+            logs_bloom_scan: None,
+            historical_source: None,
+            cache: None,
+            validate_chain_id: None,
+            fallback_rpcs: None,
+            rpc_retry: None,
+        }];
+        // every restart deploys new contracts so the old data would only get in the way
+        if let Some(postgres) = manifest.storage.postgres.as_mut() {
+            postgres.drop_each_run = Some(true);
+        }
+        // multi process setups and global contracts on other networks make no sense locally
+        manifest.global = None;
+        manifest.leader_election = None;
+        manifest.backfill_jobs = None;
+
+        let dev_project_path = project_path.join(DEV_FOLDER);
+        fs::create_dir_all(&dev_project_path)?;
+        write_manifest(&manifest, &dev_project_path.join(YAML_CONFIG_NAME))?;
+
+        Ok(dev_project_path)
+    }
+}
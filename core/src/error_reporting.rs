@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use ethers::types::U64;
+use once_cell::sync::OnceCell;
+use tracing::info;
+
+use crate::manifest::sentry::SentrySettings;
+
+static SENTRY_GUARD: OnceCell<sentry::ClientInitGuard> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy)]
+pub enum ErrorCategory {
+    Handler,
+    Provider,
+    Database,
+}
+
+impl ErrorCategory {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Handler => "handler",
+            Self::Provider => "provider",
+            Self::Database => "database",
+        }
+    }
+}
+
+/// Where the error happened, every field is optional as not all call sites know all of them
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ErrorContext<'a> {
+    pub indexer_name: Option<&'a str>,
+    pub contract_name: Option<&'a str>,
+    pub event_name: Option<&'a str>,
+    pub network: Option<&'a str>,
+    pub from_block: Option<U64>,
+    pub to_block: Option<U64>,
+}
+
+/// Sets up Sentry, handler panics are captured by its panic integration. Safe to call more than
+/// once, only the first call initializes the client.
+pub fn init_error_reporting(settings: &SentrySettings) {
+    SENTRY_GUARD.get_or_init(|| {
+        info!("Sentry error reporting enabled");
+        sentry::init((
+            settings.dsn.as_str(),
+            sentry::ClientOptions {
+                release: sentry::release_name!(),
+                environment: settings.environment.clone().map(Into::into),
+                sample_rate: settings.sample_rate.unwrap_or(1.0),
+                ..Default::default()
+            },
+        ))
+    });
+}
+
+pub fn report_error(category: ErrorCategory, message: &str, context: ErrorContext) {
+    // without a client sentry drops the event anyway but this avoids building the scope
+    if SENTRY_GUARD.get().is_none() {
+        return;
+    }
+
+    sentry::with_scope(
+        |scope| {
+            scope.set_tag("category", category.as_str());
+            if let Some(indexer_name) = context.indexer_name {
+                scope.set_tag("indexer", indexer_name);
+            }
+            if let Some(contract_name) = context.contract_name {
+                scope.set_tag("contract", contract_name);
+            }
+            if let Some(event_name) = context.event_name {
+                scope.set_tag("event", event_name);
+            }
+            if let Some(network) = context.network {
+                scope.set_tag("network", network);
+            }
+            if let Some(from_block) = context.from_block {
+                scope.set_extra("from_block", from_block.as_u64().into());
+            }
+            if let Some(to_block) = context.to_block {
+                scope.set_extra("to_block", to_block.as_u64().into());
+            }
+        },
+        || sentry::capture_message(message, sentry::Level::Error),
+    );
+}
+
+/// The process exits without running destructors on shutdown so queued events have to be
+/// flushed explicitly
+pub fn flush_error_reporting() {
+    if let Some(guard) = SENTRY_GUARD.get() {
+        guard.flush(Some(Duration::from_secs(2)));
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::RwLock,
+};
+
+use once_cell::sync::Lazy;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::event::callback_registry::TxInformation;
+
+/// The senders of every subscriber, keyed by the type of message they subscribed to. Each value
+/// is a `Vec<UnboundedSender<BusMessage<M>>>` for the `M` of its key.
+static SUBSCRIBERS: Lazy<RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// A message a handler derived from an event, along with where that event was emitted
+#[derive(Debug, Clone)]
+pub struct BusMessage<M> {
+    pub message: M,
+    pub source: TxInformation,
+}
+
+impl<M> BusMessage<M> {
+    fn position(&self) -> (u64, u64, u64) {
+        (
+            self.source.block_number.as_u64(),
+            self.source.transaction_index.as_u64(),
+            self.source.log_index.low_u64(),
+        )
+    }
+}
+
+/// Receives every message of type `M` published after subscribing
+pub struct BusSubscription<M> {
+    receiver: UnboundedReceiver<BusMessage<M>>,
+}
+
+impl<M> BusSubscription<M> {
+    pub async fn recv(&mut self) -> BusMessage<M> {
+        self.receiver.recv().await.expect("the bus keeps the sender of every live subscription")
+    }
+
+    /// Waits for the next message and takes the ones already queued behind it, so a subscriber
+    /// can handle them as a batch like event handlers do
+    pub async fn recv_batch(&mut self, max: usize) -> Vec<BusMessage<M>> {
+        let mut batch = vec![self.recv().await];
+        while batch.len() < max {
+            match self.receiver.try_recv() {
+                Ok(message) => batch.push(message),
+                Err(_) => break,
+            }
+        }
+        batch
+    }
+}
+
+/// Subscribes to the messages of type `M` published by any handler in the process
+pub fn subscribe<M: Clone + Send + Sync + 'static>() -> BusSubscription<M> {
+    let (sender, receiver) = unbounded_channel();
+    SUBSCRIBERS
+        .write()
+        .expect("bus subscribers lock poisoned")
+        .entry(TypeId::of::<M>())
+        .or_insert_with(|| Box::new(Vec::<UnboundedSender<BusMessage<M>>>::new()))
+        .downcast_mut::<Vec<UnboundedSender<BusMessage<M>>>>()
+        .expect("bus subscribers are keyed by their message type")
+        .push(sender);
+    BusSubscription { receiver }
+}
+
+/// Publishes the messages a handler derived from its batch of events. They are sent in the order
+/// of the events they came from, so as a handler gets its events in block order every subscriber
+/// gets the messages of a network in the block order of their source events.
+///
+/// Messages go to the subscribers of the moment, nothing is kept for later ones. Subscribers
+/// which dropped their subscription are removed. Returns how many subscribers got the messages.
+pub fn publish<M: Clone + Send + Sync + 'static>(mut messages: Vec<BusMessage<M>>) -> usize {
+    messages.sort_by_key(|message| message.position());
+
+    let mut subscribers = SUBSCRIBERS.write().expect("bus subscribers lock poisoned");
+    let Some(senders) = subscribers.get_mut(&TypeId::of::<M>()) else {
+        return 0;
+    };
+    let senders = senders
+        .downcast_mut::<Vec<UnboundedSender<BusMessage<M>>>>()
+        .expect("bus subscribers are keyed by their message type");
+
+    // sending under the lock keeps messages of concurrent publishers from interleaving
+    senders.retain(|sender| messages.iter().all(|message| sender.send(message.clone()).is_ok()));
+    senders.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Address, H256, U256, U64};
+
+    use super::*;
+
+    fn source(block_number: u64, log_index: u64) -> TxInformation {
+        TxInformation {
+            network: "ethereum".to_string(),
+            address: Address::zero(),
+            block_hash: H256::zero(),
+            block_number: U64::from(block_number),
+            block_timestamp: None,
+            transaction_hash: H256::zero(),
+            log_index: U256::from(log_index),
+            transaction_index: U64::zero(),
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct LiquidationDetected(u64);
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct OtherMessage;
+
+    #[tokio::test]
+    async fn test_subscribers_get_their_type_in_source_order() {
+        let mut first = subscribe::<LiquidationDetected>();
+        let mut second = subscribe::<LiquidationDetected>();
+        let dropped = subscribe::<LiquidationDetected>();
+        drop(dropped);
+        let mut other = subscribe::<OtherMessage>();
+
+        let published = publish(vec![
+            BusMessage { message: LiquidationDetected(3), source: source(11, 0) },
+            BusMessage { message: LiquidationDetected(1), source: source(10, 0) },
+            BusMessage { message: LiquidationDetected(2), source: source(10, 4) },
+        ]);
+        assert_eq!(published, 2);
+
+        for subscription in [&mut first, &mut second] {
+            let batch = subscription.recv_batch(10).await;
+            let messages: Vec<_> = batch.into_iter().map(|m| m.message).collect();
+            assert_eq!(
+                messages,
+                vec![LiquidationDetected(1), LiquidationDetected(2), LiquidationDetected(3)]
+            );
+        }
+        assert!(other.receiver.try_recv().is_err());
+    }
+}
@@ -1,22 +1,29 @@
-use std::{any::Any, sync::Arc, time::Duration};
+use std::{any::Any, panic::AssertUnwindSafe, sync::Arc, time::Duration};
 
 use ethers::{
     addressbook::Address,
     contract::LogMeta,
     types::{Bytes, Log, H256, U256, U64},
 };
-use futures::future::BoxFuture;
+use futures::{future::BoxFuture, FutureExt};
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::{
+    database::postgres::retry_queue::RetryQueue,
+    error_reporting::{report_error, ErrorCategory, ErrorContext},
     event::contract_setup::{ContractInformation, NetworkContract},
-    indexer::start::ProcessedNetworkContract,
+    indexer::{alerts::record_handler_error, spam::SpamReason, start::ProcessedNetworkContract},
     is_running,
+    manifest::contract::FailurePolicy,
     provider::WrappedLog,
 };
 
+/// A panicking handler is retried like a failed batch this many times before it halts, so a panic
+/// on a flaky dependency recovers while one on bad data goes to the failure policy
+const HANDLER_PANIC_RETRIES: u32 = 3;
+
 pub type Decoder = Arc<dyn Fn(Vec<H256>, Bytes) -> Arc<dyn Any + Send + Sync> + Send + Sync>;
 
 pub fn noop_decoder() -> Decoder {
@@ -41,6 +48,9 @@ pub struct TxInformation {
 pub struct LogFoundInRequest {
     pub from_block: U64,
     pub to_block: U64,
+    /// Set when the ranges of the event are processed concurrently, the checkpoint then only
+    /// moves once every range before the one the log was found in is done
+    pub completes_out_of_order: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -49,6 +59,8 @@ pub struct EventResult {
     pub decoded_data: Arc<dyn Any + Send + Sync>,
     pub tx_information: TxInformation,
     pub found_in_request: LogFoundInRequest,
+    /// Set when the spam filter of the contract flags the emitting contract as spam
+    pub spam: Option<SpamReason>,
 }
 
 impl EventResult {
@@ -73,12 +85,45 @@ impl EventResult {
                 transaction_index: log_meta.transaction_index,
                 log_index: log_meta.log_index,
             },
-            found_in_request: LogFoundInRequest { from_block: start_block, to_block: end_block },
+            found_in_request: LogFoundInRequest {
+                from_block: start_block,
+                to_block: end_block,
+                completes_out_of_order: false,
+            },
+            spam: None,
         }
     }
 }
 
-pub type EventCallbackResult<T> = Result<T, String>;
+/// The error a handler returns decides what the pipeline does with the batch it failed on.
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum HandlerError {
+    /// Transient failure, the batch is retried with backoff until it succeeds
+    #[error("{0}")]
+    Retry(String),
+
+    /// The batch can not be processed, it is logged and indexing moves past it
+    #[error("{0}")]
+    Skip(String),
+
+    /// Unrecoverable failure, indexing of the event stops without checkpointing the batch
+    #[error("{0}")]
+    Halt(String),
+}
+
+impl From<String> for HandlerError {
+    fn from(value: String) -> Self {
+        HandlerError::Retry(value)
+    }
+}
+
+impl From<&str> for HandlerError {
+    fn from(value: &str) -> Self {
+        HandlerError::Retry(value.to_string())
+    }
+}
+
+pub type EventCallbackResult<T> = Result<T, HandlerError>;
 pub type EventCallbackType =
     Arc<dyn Fn(Vec<EventResult>) -> BoxFuture<'static, EventCallbackResult<()>> + Send + Sync>;
 
@@ -88,6 +133,10 @@ pub struct EventCallbackRegistryInformation {
     pub topic_id: H256,
     pub event_name: String,
     pub index_event_in_order: bool,
+    pub group_by_block: bool,
+    pub max_concurrent_callbacks: Option<usize>,
+    pub failure_policy: FailurePolicy,
+    pub archive_raw_logs: bool,
     pub contract: ContractInformation,
     pub callback: EventCallbackType,
 }
@@ -106,12 +155,82 @@ impl Clone for EventCallbackRegistryInformation {
             topic_id: self.topic_id,
             event_name: self.event_name.clone(),
             index_event_in_order: self.index_event_in_order,
+            group_by_block: self.group_by_block,
+            max_concurrent_callbacks: self.max_concurrent_callbacks,
+            failure_policy: self.failure_policy,
+            archive_raw_logs: self.archive_raw_logs,
             contract: self.contract.clone(),
             callback: Arc::clone(&self.callback),
         }
     }
 }
 
+fn report_handler_error(
+    event_information: &EventCallbackRegistryInformation,
+    data: &[EventResult],
+    message: &str,
+) {
+    let first = data.first();
+    report_error(
+        ErrorCategory::Handler,
+        &format!("{} handler failed: {}", event_information.info_log_name(), message),
+        ErrorContext {
+            indexer_name: Some(&event_information.indexer_name),
+            contract_name: Some(&event_information.contract.name),
+            event_name: Some(&event_information.event_name),
+            network: first.map(|r| r.tx_information.network.as_str()),
+            from_block: first.map(|r| r.found_in_request.from_block),
+            to_block: first.map(|r| r.found_in_request.to_block),
+        },
+    );
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(|message| message.as_str()))
+        .unwrap_or("unknown panic")
+}
+
+/// Where the batch is on chain, the handler gets the batch whole so a panic can not be pinned on
+/// a single log
+fn batch_context(data: &[EventResult]) -> String {
+    match (data.first(), data.last()) {
+        (Some(first), Some(last)) => format!(
+            "network {} - {} logs from block {} log {} (tx {:?}) to block {} log {} (tx {:?})",
+            first.tx_information.network,
+            data.len(),
+            first.tx_information.block_number,
+            first.tx_information.log_index,
+            first.tx_information.transaction_hash,
+            last.tx_information.block_number,
+            last.tx_information.log_index,
+            last.tx_information.transaction_hash
+        ),
+        _ => "an empty batch".to_string(),
+    }
+}
+
+/// Runs the handler catching a panic so it does not unwind the indexing task it runs on
+async fn call_handler(
+    event_information: &EventCallbackRegistryInformation,
+    data: Vec<EventResult>,
+) -> Result<EventCallbackResult<()>, String> {
+    AssertUnwindSafe(async { (event_information.callback)(data).await })
+        .catch_unwind()
+        .await
+        .map_err(|panic| panic_message(panic.as_ref()).to_string())
+}
+
+async fn dequeue_retry(retry_queue: Option<&RetryQueue>, range: Option<(U64, U64)>) {
+    if let (Some(retry_queue), Some((from_block, to_block))) = (retry_queue, range) {
+        if let Err(e) = retry_queue.remove(from_block, to_block).await {
+            error!("Could not remove retry for blocks {} to {}: {}", from_block, to_block, e);
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct EventCallbackRegistry {
     pub events: Vec<EventCallbackRegistryInformation>,
@@ -136,9 +255,19 @@ impl EventCallbackRegistry {
         self.events.push(event);
     }
 
-    pub async fn trigger_event(&self, id: &String, data: Vec<EventResult>) {
+    /// Runs the handler for the batch, when a `retry_queue` is passed a batch which goes into
+    /// retries is queued durably until it succeeds so the retry outlives the process.
+    pub async fn trigger_event(
+        &self,
+        id: &String,
+        data: Vec<EventResult>,
+        retry_queue: Option<&RetryQueue>,
+    ) -> Result<(), HandlerError> {
         let mut attempts = 0;
+        let mut panics = 0;
         let mut delay = Duration::from_millis(100);
+        let range =
+            data.first().map(|r| (r.found_in_request.from_block, r.found_in_request.to_block));
 
         if let Some(event_information) = self.find_event(id) {
             debug!("{} - Pushed {} events", data.len(), event_information.info_log_name());
@@ -146,23 +275,85 @@ impl EventCallbackRegistry {
             loop {
                 if !is_running() {
                     info!("Detected shutdown, stopping event trigger");
-                    break;
+                    return Err(HandlerError::Retry("indexer is shutting down".to_string()));
                 }
 
-                match (event_information.callback)(data.clone()).await {
+                let result = match call_handler(event_information, data.clone()).await {
+                    Ok(result) => result,
+                    Err(panic) => {
+                        panics += 1;
+                        let message =
+                            format!("handler panicked on {}: {}", batch_context(&data), panic);
+                        if panics > HANDLER_PANIC_RETRIES {
+                            Err(HandlerError::Halt(message))
+                        } else {
+                            Err(HandlerError::Retry(message))
+                        }
+                    }
+                };
+
+                match result {
                     Ok(_) => {
                         debug!(
                             "Event processing succeeded for id: {} - topic_id: {}",
                             id, event_information.topic_id
                         );
+                        if attempts > 0 {
+                            dequeue_retry(retry_queue, range).await;
+                        }
+                        break;
+                    }
+                    Err(HandlerError::Skip(e)) => {
+                        warn!(
+                            "{} Event processing skipped - id: {} - topic_id: {}. Error: {}",
+                            event_information.info_log_name(),
+                            id,
+                            event_information.topic_id,
+                            e
+                        );
+                        if attempts > 0 {
+                            dequeue_retry(retry_queue, range).await;
+                        }
                         break;
                     }
-                    Err(e) => {
+                    Err(HandlerError::Halt(e)) => {
+                        record_handler_error();
+                        report_handler_error(event_information, &data, &e);
+                        error!(
+                            "{} Event processing halted - id: {} - topic_id: {}. Error: {}",
+                            event_information.info_log_name(),
+                            id,
+                            event_information.topic_id,
+                            e
+                        );
+                        return Err(HandlerError::Halt(e));
+                    }
+                    Err(HandlerError::Retry(e)) => {
                         if !is_running() {
                             info!("Detected shutdown, stopping event trigger");
-                            break;
+                            return Err(HandlerError::Retry(e));
                         }
                         attempts += 1;
+                        record_handler_error();
+                        // only the first failure is reported as retries would flood it
+                        if attempts == 1 {
+                            report_handler_error(event_information, &data, &e);
+                            if let (Some(retry_queue), Some((from_block, to_block))) =
+                                (retry_queue, range)
+                            {
+                                if let Err(queue_error) =
+                                    retry_queue.enqueue(from_block, to_block, &e).await
+                                {
+                                    error!(
+                                        "{} - Could not persist retry for blocks {} to {}: {}",
+                                        event_information.info_log_name(),
+                                        from_block,
+                                        to_block,
+                                        queue_error
+                                    );
+                                }
+                            }
+                        }
                         error!(
                             "{} Event processing failed - id: {} - topic_id: {}. Retrying... (attempt {}). Error: {}",
                             event_information.info_log_name(), id, event_information.topic_id, attempts, e
@@ -177,6 +368,8 @@ impl EventCallbackRegistry {
         } else {
             error!("EventCallbackRegistry: No event found for id: {}", id);
         }
+
+        Ok(())
     }
 
     pub fn complete(&self) -> Arc<Self> {
@@ -210,3 +403,17 @@ impl EventCallbackRegistry {
         self.complete()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message() {
+        let panic = std::panic::catch_unwind(|| panic!("bad log {}", 7)).unwrap_err();
+        assert_eq!(panic_message(panic.as_ref()), "bad log 7");
+
+        let panic = std::panic::catch_unwind(|| panic!("bad log")).unwrap_err();
+        assert_eq!(panic_message(panic.as_ref()), "bad log");
+    }
+}
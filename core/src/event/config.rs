@@ -1,19 +1,26 @@
 use std::{path::PathBuf, sync::Arc};
 
 use ethers::prelude::{H256, U64};
-use tokio::sync::{Mutex, Semaphore};
+use tokio::sync::{oneshot::error::RecvError, Mutex, Semaphore};
 
 use crate::{
+    database::postgres::{raw_logs::RawLogArchive, retry_queue::RetryQueue},
+    error_reporting::ErrorContext,
     event::{
-        callback_registry::{EventCallbackRegistry, EventResult},
+        callback_registry::{EventCallbackRegistry, EventResult, HandlerError},
         contract_setup::NetworkContract,
         BuildRindexerFilterError, RindexerEventFilter,
     },
-    indexer::IndexingEventsProgressState,
-    manifest::storage::CsvDetails,
+    indexer::{
+        completed_ranges::CompletedRanges, overlap::EarlierOverlaps, spam::SpamFilter,
+        write_ahead_log::WriteAheadLog, IndexingEventsProgressState,
+    },
+    indexing_permits::{IndexingPermit, IndexingPermits},
+    manifest::{contract::FailurePolicy, storage::CsvDetails},
     PostgresClient,
 };
 
+#[derive(Clone)]
 pub struct EventProcessingConfig {
     pub id: String,
     pub project_path: PathBuf,
@@ -25,15 +32,31 @@ pub struct EventProcessingConfig {
     pub network_contract: Arc<NetworkContract>,
     pub start_block: U64,
     pub end_block: U64,
-    pub semaphore: Arc<Semaphore>,
+    pub permits: Arc<IndexingPermits>,
     pub registry: Arc<EventCallbackRegistry>,
     pub progress: Arc<Mutex<IndexingEventsProgressState>>,
     pub database: Option<Arc<PostgresClient>>,
     pub csv_details: Option<CsvDetails>,
     pub stream_last_synced_block_file_path: Option<String>,
     pub index_event_in_order: bool,
+    pub group_by_block: bool,
+    pub callback_semaphore: Option<Arc<Semaphore>>,
+    pub failure_policy: FailurePolicy,
     pub live_indexing: bool,
     pub indexing_distance_from_head: U64,
+    /// Off for backfill job ranges which complete out of order
+    pub persist_last_synced_block: bool,
+    pub retry_queue: Option<Arc<RetryQueue>>,
+    pub raw_log_archive: Option<Arc<RawLogArchive>>,
+    pub earlier_overlaps: Option<Arc<EarlierOverlaps>>,
+    /// The `(block, log_index)` of the last log handled before a restart in the middle of a
+    /// block, the logs up to it are not handled again
+    pub resume_after_log: Option<(U64, U64)>,
+    pub write_ahead_log: Option<Arc<WriteAheadLog>>,
+    /// Set when ranges are processed concurrently, the checkpoint then only moves once the
+    /// ranges before it are done
+    pub completed_ranges: Option<Arc<CompletedRanges>>,
+    pub spam_filter: Option<Arc<SpamFilter>>,
 }
 
 impl EventProcessingConfig {
@@ -47,7 +70,23 @@ impl EventProcessingConfig {
         )
     }
 
-    pub async fn trigger_event(&self, fn_data: Vec<EventResult>) {
-        self.registry.trigger_event(&self.id, fn_data).await;
+    /// The indexer, contract, event and network errors of the event are reported with
+    pub fn error_context(&self) -> ErrorContext<'_> {
+        ErrorContext {
+            indexer_name: Some(&self.indexer_name),
+            contract_name: Some(&self.contract_name),
+            event_name: Some(&self.event_name),
+            network: Some(&self.network_contract.network),
+            ..Default::default()
+        }
+    }
+
+    /// A permit for an RPC log request, shared by all the networks of the event
+    pub async fn acquire_indexing_permit(&self) -> Result<IndexingPermit, RecvError> {
+        self.permits.acquire(&self.id).await
+    }
+
+    pub async fn trigger_event(&self, fn_data: Vec<EventResult>) -> Result<(), HandlerError> {
+        self.registry.trigger_event(&self.id, fn_data, self.retry_queue.as_deref()).await
     }
 }
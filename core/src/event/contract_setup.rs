@@ -9,7 +9,12 @@ use serde::{Deserialize, Serialize};
 use crate::{
     event::callback_registry::Decoder,
     generate_random_id,
-    manifest::contract::{Contract, EventInputIndexedFilters},
+    indexer::address_registry::registry_addresses,
+    manifest::{
+        address_list::AddressFilter,
+        contract::{AddressRegistryDetails, Contract, EventInputIndexedFilters},
+        network::HistoricalSource,
+    },
     provider::{CreateNetworkProvider, JsonRpcCachedProvider},
     types::single_or_array::StringOrArray,
 };
@@ -24,6 +29,8 @@ pub struct NetworkContract {
     pub start_block: Option<U64>,
     pub end_block: Option<U64>,
     pub disable_logs_bloom_checks: bool,
+    pub logs_bloom_scan: bool,
+    pub historical_source: Option<HistoricalSource>,
 }
 
 impl NetworkContract {
@@ -76,6 +83,8 @@ impl ContractInformation {
                         start_block: c.start_block,
                         end_block: c.end_block,
                         disable_logs_bloom_checks: provider.disable_logs_bloom_checks,
+                        logs_bloom_scan: provider.logs_bloom_scan,
+                        historical_source: provider.historical_source.clone(),
                     });
                 }
             }
@@ -121,6 +130,32 @@ pub struct FilterDetails {
     pub events: ValueOrArray<String>,
 
     pub indexed_filters: Option<EventInputIndexedFilters>,
+
+    #[serde(skip)]
+    pub address_filter: Option<Arc<AddressFilter>>,
+}
+
+impl FilterDetails {
+    /// Whether the log of an emitting address passes the allowlist and denylist of the contract
+    pub fn allows_address(&self, address: &Address) -> bool {
+        self.address_filter.as_ref().is_none_or(|filter| filter.allows(address))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RegistryDetails {
+    pub network: String,
+
+    pub registry: AddressRegistryDetails,
+
+    pub indexed_filters: Option<Vec<EventInputIndexedFilters>>,
+}
+
+impl RegistryDetails {
+    /// The addresses the registry listed when it was last read
+    pub fn addresses(&self) -> Vec<Address> {
+        registry_addresses(&self.network, &self.registry)
+    }
 }
 
 #[derive(Clone)]
@@ -128,6 +163,7 @@ pub enum IndexingContractSetup {
     Address(AddressDetails),
     Filter(FilterDetails),
     Factory(FactoryDetails),
+    Registry(RegistryDetails),
 }
 
 impl IndexingContractSetup {
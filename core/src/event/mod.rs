@@ -1,3 +1,4 @@
+pub mod bus;
 pub mod callback_registry;
 
 pub mod config;
@@ -9,5 +10,10 @@ pub use rindexer_event_filter::{BuildRindexerFilterError, RindexerEventFilter};
 mod message;
 pub use message::EventMessage;
 
+mod staged_writes;
+pub use staged_writes::{
+    staged_writes_callback, RowMutation, StagedEventCallbackType, StagedWrites,
+};
+
 mod conditions;
 pub use conditions::filter_event_data_by_conditions;
@@ -4,7 +4,7 @@ use ethers::{
     types::ValueOrArray,
 };
 
-use crate::event::contract_setup::IndexingContractSetup;
+use crate::event::contract_setup::{IndexingContractSetup, RegistryDetails};
 
 #[derive(thiserror::Error, Debug)]
 pub enum BuildRindexerFilterError {
@@ -17,6 +17,17 @@ pub struct RindexerEventFilter {
     filter: Filter,
 }
 
+/// An empty address list matches every address on most nodes so an empty registry filters on the
+/// zero address, which emits no logs
+fn registry_address_filter(registry: &RegistryDetails) -> ValueOrArray<Address> {
+    let addresses = registry.addresses();
+    if addresses.is_empty() {
+        ValueOrArray::Value(Address::zero())
+    } else {
+        ValueOrArray::Array(addresses)
+    }
+}
+
 impl RindexerEventFilter {
     fn from_filter(filter: Filter) -> Self {
         if filter.get_to_block().is_none() {
@@ -38,38 +49,22 @@ impl RindexerEventFilter {
     ) -> Result<RindexerEventFilter, BuildRindexerFilterError> {
         match indexing_contract_setup {
             IndexingContractSetup::Address(address_details) => {
-                match &address_details.indexed_filters {
-                    Some(indexed_filters) => {
-                        if let Some(index_filters) =
-                            indexed_filters.iter().find(|&n| n.event_name == event_name)
-                        {
-                            return Ok(RindexerEventFilter::from_filter(
-                                index_filters.extend_filter_indexed(
-                                    Filter::new()
-                                        .address(address_details.address.clone())
-                                        .topic0(*topic_id)
-                                        .from_block(current_block)
-                                        .to_block(next_block),
-                                ),
-                            ));
-                        }
-
-                        Ok(RindexerEventFilter::from_filter(
-                            Filter::new()
-                                .address(address_details.address.clone())
-                                .topic0(*topic_id)
-                                .from_block(current_block)
-                                .to_block(next_block),
-                        ))
-                    }
-                    None => Ok(RindexerEventFilter::from_filter(
-                        Filter::new()
-                            .address(address_details.address.clone())
-                            .topic0(*topic_id)
-                            .from_block(current_block)
-                            .to_block(next_block),
-                    )),
-                }
+                let filter = Filter::new()
+                    .address(address_details.address.clone())
+                    .topic0(*topic_id)
+                    .from_block(current_block)
+                    .to_block(next_block);
+
+                // each event has its own indexed inputs so only its filters apply
+                let event_indexed_filters =
+                    address_details.indexed_filters.as_ref().and_then(|indexed_filters| {
+                        indexed_filters.iter().find(|filters| filters.event_name == event_name)
+                    });
+
+                Ok(RindexerEventFilter::from_filter(match event_indexed_filters {
+                    Some(indexed_filters) => indexed_filters.extend_filter_indexed(filter),
+                    None => filter,
+                }))
             }
             IndexingContractSetup::Filter(filter) => match &filter.indexed_filters {
                 Some(indexed_filters) => Ok(RindexerEventFilter::from_filter(
@@ -84,6 +79,23 @@ impl RindexerEventFilter {
                     Filter::new().topic0(*topic_id).from_block(current_block).to_block(next_block),
                 )),
             },
+            IndexingContractSetup::Registry(registry) => {
+                let filter = Filter::new()
+                    .address(registry_address_filter(registry))
+                    .topic0(*topic_id)
+                    .from_block(current_block)
+                    .to_block(next_block);
+
+                let event_indexed_filters =
+                    registry.indexed_filters.as_ref().and_then(|indexed_filters| {
+                        indexed_filters.iter().find(|filters| filters.event_name == event_name)
+                    });
+
+                Ok(RindexerEventFilter::from_filter(match event_indexed_filters {
+                    Some(indexed_filters) => indexed_filters.extend_filter_indexed(filter),
+                    None => filter,
+                }))
+            }
             IndexingContractSetup::Factory(factory) => {
                 let address = factory
                     .address
@@ -123,6 +135,14 @@ impl RindexerEventFilter {
         self
     }
 
+    /// Picks up the addresses a registry lists now, the other setups keep their addresses
+    pub fn refresh_addresses(mut self, indexing_contract_setup: &IndexingContractSetup) -> Self {
+        if let IndexingContractSetup::Registry(registry) = indexing_contract_setup {
+            self.filter = self.filter.address(registry_address_filter(registry));
+        }
+        self
+    }
+
     pub fn contract_address(&self) -> Option<ValueOrArray<Address>> {
         self.filter.address.clone()
     }
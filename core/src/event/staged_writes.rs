@@ -0,0 +1,318 @@
+use std::sync::Arc;
+
+use ethers::types::U64;
+use futures::future::BoxFuture;
+
+use crate::{
+    database::postgres::generate::generate_event_table_columns_names_sql,
+    event::callback_registry::{EventCallbackResult, EventCallbackType, EventResult, HandlerError},
+    indexer::{checkpoint_statement, Checkpoint},
+    EthereumSqlTypeWrapper, FutureExt, PostgresClient,
+};
+
+#[derive(Debug, Clone)]
+pub enum RowMutation {
+    Insert {
+        table: String,
+        columns: Vec<String>,
+        values: Vec<EthereumSqlTypeWrapper>,
+    },
+    Upsert {
+        table: String,
+        columns: Vec<String>,
+        values: Vec<EthereumSqlTypeWrapper>,
+        conflict_columns: Vec<String>,
+    },
+    Delete {
+        table: String,
+        where_columns: Vec<String>,
+        values: Vec<EthereumSqlTypeWrapper>,
+    },
+}
+
+impl RowMutation {
+    fn placeholders(count: usize) -> String {
+        (1..=count).map(|i| format!("${}", i)).collect::<Vec<_>>().join(", ")
+    }
+
+    fn to_statement(&self) -> (String, Vec<EthereumSqlTypeWrapper>) {
+        match self {
+            RowMutation::Insert { table, columns, values } => (
+                format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    table,
+                    generate_event_table_columns_names_sql(columns),
+                    Self::placeholders(values.len())
+                ),
+                values.clone(),
+            ),
+            RowMutation::Upsert { table, columns, values, conflict_columns } => {
+                let updates = columns
+                    .iter()
+                    .filter(|c| !conflict_columns.contains(c))
+                    .map(|c| format!("\"{}\" = EXCLUDED.\"{}\"", c, c))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let on_conflict = if updates.is_empty() {
+                    "DO NOTHING".to_string()
+                } else {
+                    format!("DO UPDATE SET {}", updates)
+                };
+
+                (
+                    format!(
+                        "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) {}",
+                        table,
+                        generate_event_table_columns_names_sql(columns),
+                        Self::placeholders(values.len()),
+                        generate_event_table_columns_names_sql(conflict_columns),
+                        on_conflict
+                    ),
+                    values.clone(),
+                )
+            }
+            RowMutation::Delete { table, where_columns, values } => (
+                format!(
+                    "DELETE FROM {} WHERE {}",
+                    table,
+                    where_columns
+                        .iter()
+                        .enumerate()
+                        .map(|(i, c)| format!("\"{}\" = ${}", c, i + 1))
+                        .collect::<Vec<_>>()
+                        .join(" AND ")
+                ),
+                values.clone(),
+            ),
+        }
+    }
+}
+
+/// Row mutations a handler wants applied, rindexer commits them in the same transaction as the
+/// last synced block so a crash can never leave the rows and the checkpoint out of step.
+#[derive(Debug, Clone, Default)]
+pub struct StagedWrites {
+    mutations: Vec<RowMutation>,
+}
+
+impl StagedWrites {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(
+        &mut self,
+        table: &str,
+        columns: &[&str],
+        values: Vec<EthereumSqlTypeWrapper>,
+    ) -> &mut Self {
+        self.mutations.push(RowMutation::Insert {
+            table: table.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            values,
+        });
+        self
+    }
+
+    pub fn upsert(
+        &mut self,
+        table: &str,
+        columns: &[&str],
+        values: Vec<EthereumSqlTypeWrapper>,
+        conflict_columns: &[&str],
+    ) -> &mut Self {
+        self.mutations.push(RowMutation::Upsert {
+            table: table.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            values,
+            conflict_columns: conflict_columns.iter().map(|c| c.to_string()).collect(),
+        });
+        self
+    }
+
+    pub fn delete(
+        &mut self,
+        table: &str,
+        where_columns: &[&str],
+        values: Vec<EthereumSqlTypeWrapper>,
+    ) -> &mut Self {
+        self.mutations.push(RowMutation::Delete {
+            table: table.to_string(),
+            where_columns: where_columns.iter().map(|c| c.to_string()).collect(),
+            values,
+        });
+        self
+    }
+
+    pub fn extend(&mut self, other: StagedWrites) -> &mut Self {
+        self.mutations.extend(other.mutations);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mutations.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.mutations.len()
+    }
+}
+
+pub type StagedEventCallbackType = Arc<
+    dyn Fn(Vec<EventResult>) -> BoxFuture<'static, EventCallbackResult<StagedWrites>> + Send + Sync,
+>;
+
+/// The network and checkpoint just past the last log of a batch, `None` when the ranges of the
+/// event complete out of order as the checkpoint then waits for the ranges before them
+fn batch_checkpoint(results: &[EventResult]) -> Option<(String, Checkpoint)> {
+    let last = results.last().filter(|last| !last.found_in_request.completes_out_of_order)?;
+    let checkpoint = Checkpoint::within_block(
+        last.tx_information.block_number,
+        U64::from(last.tx_information.log_index.low_u64()),
+    )?;
+    Some((last.tx_information.network.clone(), checkpoint))
+}
+
+/// Wraps a handler which returns [`StagedWrites`] into a normal event callback. The rows and the
+/// checkpoint of the batch, up to its last log, are committed together in a single transaction.
+pub fn staged_writes_callback(
+    database: Arc<PostgresClient>,
+    indexer_name: &str,
+    contract_name: &str,
+    event_name: &str,
+    callback: StagedEventCallbackType,
+) -> EventCallbackType {
+    let names =
+        Arc::new((indexer_name.to_string(), contract_name.to_string(), event_name.to_string()));
+
+    Arc::new(move |results| {
+        let database = Arc::clone(&database);
+        let callback = Arc::clone(&callback);
+        let names = Arc::clone(&names);
+
+        async move {
+            let checkpoint = batch_checkpoint(&results);
+
+            let writes = callback(results).await?;
+
+            let mut statements: Vec<(String, Vec<EthereumSqlTypeWrapper>)> =
+                writes.mutations.iter().map(|m| m.to_statement()).collect();
+            if let Some((network, checkpoint)) = checkpoint {
+                let (indexer_name, contract_name, event_name) = names.as_ref();
+                statements.push(checkpoint_statement(
+                    indexer_name,
+                    contract_name,
+                    event_name,
+                    &network,
+                    checkpoint,
+                ));
+            }
+            if statements.is_empty() {
+                return Ok(());
+            }
+
+            database
+                .execute_in_transaction(&statements)
+                .await
+                .map_err(|e| HandlerError::Retry(e.to_string()))
+        }
+        .boxed()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Log, H256, U256};
+
+    use super::*;
+    use crate::event::callback_registry::{LogFoundInRequest, TxInformation};
+
+    fn event_result(block_number: u64, log_index: u64) -> EventResult {
+        EventResult {
+            log: Log::default(),
+            decoded_data: Arc::new(()),
+            tx_information: TxInformation {
+                network: "ethereum".to_string(),
+                address: Default::default(),
+                block_hash: H256::zero(),
+                block_number: U64::from(block_number),
+                block_timestamp: None,
+                transaction_hash: H256::zero(),
+                log_index: U256::from(log_index),
+                transaction_index: U64::zero(),
+            },
+            found_in_request: LogFoundInRequest {
+                from_block: U64::from(10),
+                to_block: U64::from(20),
+                completes_out_of_order: false,
+            },
+            spam: None,
+        }
+    }
+
+    #[test]
+    fn test_batch_checkpoint_is_the_last_log_of_the_batch() {
+        let results = vec![
+            event_result(10, 0),
+            event_result(10, 1),
+            event_result(11, 0),
+            event_result(11, 4),
+        ];
+        // a range split over two handler calls
+        let (first, second) = results.split_at(3);
+
+        let (network, checkpoint) = batch_checkpoint(first).unwrap();
+        assert_eq!(network, "ethereum");
+        assert_eq!(checkpoint, Checkpoint::within_block(U64::from(11), U64::zero()).unwrap());
+        // a restart after the first call only handles the logs of block 11 after log 0
+        assert_eq!(checkpoint.resume_from(), (U64::from(11), Some((U64::from(11), U64::zero()))));
+
+        let (_, checkpoint) = batch_checkpoint(second).unwrap();
+        assert_eq!(checkpoint, Checkpoint::within_block(U64::from(11), U64::from(4)).unwrap());
+
+        let mut out_of_order = results.clone();
+        out_of_order.iter_mut().for_each(|r| r.found_in_request.completes_out_of_order = true);
+        assert_eq!(batch_checkpoint(&out_of_order), None);
+        assert_eq!(batch_checkpoint(&[]), None);
+    }
+
+    #[test]
+    fn test_upsert_statement_updates_non_conflict_columns() {
+        let mut writes = StagedWrites::new();
+        writes.upsert(
+            "my_indexer.balances",
+            &["holder", "balance"],
+            vec![
+                EthereumSqlTypeWrapper::String("0x1".to_string()),
+                EthereumSqlTypeWrapper::String("10".to_string()),
+            ],
+            &["holder"],
+        );
+
+        let (sql, params) = writes.mutations[0].to_statement();
+        assert_eq!(
+            sql,
+            "INSERT INTO my_indexer.balances (\"holder\", \"balance\") VALUES ($1, $2) ON CONFLICT (\"holder\") DO UPDATE SET \"balance\" = EXCLUDED.\"balance\""
+        );
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn test_delete_statement() {
+        let mut writes = StagedWrites::new();
+        writes.delete(
+            "my_indexer.balances",
+            &["holder", "network"],
+            vec![
+                EthereumSqlTypeWrapper::String("0x1".to_string()),
+                EthereumSqlTypeWrapper::String("ethereum".to_string()),
+            ],
+        );
+
+        let (sql, _) = writes.mutations[0].to_statement();
+        assert_eq!(
+            sql,
+            "DELETE FROM my_indexer.balances WHERE \"holder\" = $1 AND \"network\" = $2"
+        );
+    }
+}
@@ -278,6 +278,7 @@ pub fn generate_rindexer_handlers(
                         &manifest.name,
                         is_filter,
                         &contract,
+                        &manifest.networks,
                         &manifest.storage,
                     )?
                     .as_str(),
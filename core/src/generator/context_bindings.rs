@@ -25,7 +25,7 @@ fn generate_contract_code(
                     r#"
                         abigen!({contract_name}, "{contract_path}");
 
-                        pub fn {contract_fn_name}_contract() -> {contract_name}<Arc<Provider<RetryClient<Http>>>> {{
+                        pub fn {contract_fn_name}_contract() -> {contract_name}<Arc<Provider<RetryClient<RpcTransport>>>> {{
                             let address: Address = "{contract_address}"
                                 .parse()
                                 .expect("Invalid address");
@@ -47,7 +47,7 @@ fn generate_contract_code(
                     r#"
                         abigen!({contract_name}, "{contract_path}");
 
-                        pub fn {contract_fn_name}_contract(address: Address) -> {contract_name}<Arc<Provider<RetryClient<Http>>>> {{
+                        pub fn {contract_fn_name}_contract(address: Address) -> {contract_name}<Arc<Provider<RetryClient<RpcTransport>>>> {{
                             {contract_name}::new(address, Arc::new({network_fn_name}().clone()))
                         }}
                     "#,
@@ -75,7 +75,8 @@ fn generate_contracts_code(contracts: &[Contract], networks: &[Network]) -> Code
         
         use super::networks::{{{}}};
         use std::sync::Arc;
-        use ethers::{{contract::abigen, abi::Address, providers::{{Provider, Http, RetryClient}}}};
+        use ethers::{{contract::abigen, abi::Address, providers::{{Provider, RetryClient}}}};
+        use rindexer::provider::RpcTransport;
         "#,
         network_imports.join(", ")
     ));
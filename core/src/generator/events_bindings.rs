@@ -14,6 +14,7 @@ use crate::{
     helpers::{camel_to_snake, camel_to_snake_advanced, to_pascal_case},
     manifest::{
         contract::{Contract, ContractDetails, ParseAbiError},
+        network::Network,
         storage::{CsvDetails, Storage},
     },
     types::code::Code,
@@ -70,7 +71,8 @@ fn generate_structs(
                     #[derive(Debug, Clone)]
                     pub struct {struct_result} {{
                         pub event_data: {struct_data},
-                        pub tx_information: TxInformation
+                        pub tx_information: TxInformation,
+                        pub spam: Option<SpamReason>
                     }}
                 "#,
                 struct_result = struct_result,
@@ -314,7 +316,8 @@ fn generate_event_callback_structs_code(
                                 .ok()
                                 .map(|arc| {struct_result} {{
                                     event_data: (*arc).clone(),
-                                    tx_information: item.tx_information
+                                    tx_information: item.tx_information,
+                                    spam: item.spam
                                 }})
                         }})
                         .collect();
@@ -362,7 +365,7 @@ fn generate_event_callback_structs_code(
 fn decoder_contract_fn(contracts_details: Vec<&ContractDetails>, abi_gen_name: &str) -> Code {
     let mut function = String::new();
     function.push_str(&format!(
-        r#"pub fn decoder_contract(network: &str) -> {abi_gen_name}<Arc<Provider<RetryClient<Http>>>> {{"#,
+        r#"pub fn decoder_contract(network: &str) -> {abi_gen_name}<Arc<Provider<RetryClient<RpcTransport>>>> {{"#,
         abi_gen_name = abi_gen_name
     ));
 
@@ -413,7 +416,7 @@ fn build_pub_contract_fn(
 
     if contracts_details.len() > 1 || has_array_addresses || no_address {
         Code::new(format!(
-            r#"pub fn {contract_name}_contract(network: &str, address: Address) -> {abi_gen_name}<Arc<Provider<RetryClient<Http>>>> {{
+            r#"pub fn {contract_name}_contract(network: &str, address: Address) -> {abi_gen_name}<Arc<Provider<RetryClient<RpcTransport>>>> {{
                 {abi_gen_name}::new(
                     address,
                     Arc::new(get_provider_cache_for_network(network).get_inner_provider()),
@@ -435,7 +438,7 @@ fn build_pub_contract_fn(
                 ValueOrArray::Value(address) => {
                     let address = format!("{:?}", address);
                     Code::new(format!(
-                        r#"pub fn {contract_name}_contract(network: &str) -> {abi_gen_name}<Arc<Provider<RetryClient<Http>>>> {{
+                        r#"pub fn {contract_name}_contract(network: &str) -> {abi_gen_name}<Arc<Provider<RetryClient<RpcTransport>>>> {{
                                 let address: Address = "{address}".parse().expect("Invalid address");
                                 {abi_gen_name}::new(
                                     address,
@@ -492,7 +495,7 @@ fn generate_event_bindings_code(
         use std::future::Future;
         use std::pin::Pin;
         use std::path::{{Path, PathBuf}};
-        use ethers::{{providers::{{Http, Provider, RetryClient}}, abi::Address, contract::EthLogDecode, types::{{Bytes, H256}}}};
+        use ethers::{{providers::{{Provider, RetryClient}}, abi::Address, contract::EthLogDecode, types::{{Bytes, H256, U64}}}};
         use rindexer::{{
             async_trait,
             {csv_import}
@@ -505,11 +508,13 @@ fn generate_event_bindings_code(
                 }},
                 contract_setup::{{ContractInformation, NetworkContract}},
             }},
+            indexer::spam::SpamReason,
             manifest::{{
                 contract::{{Contract, ContractDetails}},
                 yaml::read_manifest,
             }},
-            provider::JsonRpcCachedProvider,
+            provider::{{JsonRpcCachedProvider, RpcTransport}},
+            provider_state::StateCallError,
             {postgres_client_import}
         }};
         use super::super::super::super::typings::networks::get_provider_cache_for_network;
@@ -530,6 +535,20 @@ fn generate_event_bindings_code(
             pub extensions: Arc<TExtensions>,
         }}
 
+        impl<TExtensions> EventContext<TExtensions> where TExtensions: Send + Sync {{
+            /// Calls a contract as of the end of the block on the network, use the block of the
+            /// event to read the state it left behind. Results are cached.
+            pub async fn eth_call_at(&self, network: &str, to: Address, data: Bytes, block: U64) -> Result<Bytes, StateCallError> {{
+                get_provider_cache_for_network(network).call_at(to, data, block).await
+            }}
+
+            /// Many calls as of the end of the block batched into one request, calls which revert
+            /// are `None`
+            pub async fn eth_call_many_at(&self, network: &str, calls: Vec<(Address, Bytes)>, block: U64) -> Result<Vec<Option<Bytes>>, StateCallError> {{
+                get_provider_cache_for_network(network).call_many_at(calls, block).await
+            }}
+        }}
+
         // didn't want to use option or none made harder DX
         // so a blank struct makes interface nice
         pub struct NoExtensions {{}}
@@ -590,10 +609,12 @@ fn generate_event_bindings_code(
                         contract_name))
                     .clone();
 
-                  let index_event_in_order = contract_details
-                    .index_event_in_order
-                    .as_ref()
-                    .map_or(false, |vec| vec.contains(&event_name.to_string()));
+                let index_event_in_order = contract_details.is_event_indexed_in_order(event_name);
+
+                let group_by_block = contract_details.is_event_grouped_by_block(event_name);
+                let max_concurrent_callbacks = contract_details.max_concurrent_callbacks(event_name);
+                let failure_policy = contract_details.failure_policy(event_name);
+                let archive_raw_logs = contract_details.archive_raw_logs();
 
                 let contract = ContractInformation {{
                     name: contract_details.before_modify_name_if_filter_readonly().into_owned(),
@@ -613,6 +634,16 @@ fn generate_event_bindings_code(
                                                         .iter()
                                                         .find(|n| n.name == c.network)
                                                         .map_or(false, |n| n.disable_logs_bloom_checks.unwrap_or_default()),
+                            logs_bloom_scan: rindexer_yaml
+                                                        .networks
+                                                        .iter()
+                                                        .find(|n| n.name == c.network)
+                                                        .is_some_and(|n| n.logs_bloom_scan.unwrap_or_default()),
+                            historical_source: rindexer_yaml
+                                                        .networks
+                                                        .iter()
+                                                        .find(|n| n.name == c.network)
+                                                        .and_then(|n| n.historical_source.clone()),
                         }})
                         .collect(),
                     abi: contract_details.abi,
@@ -628,6 +659,10 @@ fn generate_event_bindings_code(
                     indexer_name: "{indexer_name}".to_string(),
                     event_name: event_name.to_string(),
                     index_event_in_order,
+                    group_by_block,
+                    max_concurrent_callbacks,
+                    failure_policy,
+                    archive_raw_logs,
                     topic_id: topic_id.parse::<H256>().unwrap(),
                     contract,
                     callback,
@@ -707,11 +742,29 @@ pub enum GenerateEventHandlersError {
     ParamTypeError(#[from] ParamTypeError),
 }
 
+/// The chain id for the network the event came from, written to tables shared by a table group
+fn generate_chain_id_match(contract: &Contract, networks: &[Network]) -> String {
+    let arms = contract
+        .details
+        .iter()
+        .filter_map(|details| networks.iter().find(|network| network.name == details.network))
+        .map(|network| format!(r#""{}" => {},"#, network.name, network.chain_id))
+        .collect::<Vec<String>>()
+        .join("");
+
+    format!(
+        r#"match result.tx_information.network.as_str() {{ {arms} network => panic!("Network {{}} is not mapped to {contract_name}", network) }}"#,
+        arms = arms,
+        contract_name = contract.name
+    )
+}
+
 pub fn generate_event_handlers(
     project_path: &Path,
     indexer_name: &str,
     is_filter: bool,
     contract: &Contract,
+    networks: &[Network],
     storage: &Storage,
 ) -> Result<Code, GenerateEventHandlersError> {
     let abi_items = ABIItem::get_abi_items(project_path, contract, is_filter)?;
@@ -800,7 +853,7 @@ pub fn generate_event_handlers(
                         let csv_result = context.csv.append_bulk(csv_bulk_data).await;
                         if let Err(e) = csv_result {{
                             rindexer_error!("{event_type_name}::{handler_name} inserting csv data: {{:?}}", e);
-                            return Err(e.to_string());
+                            return Err(e.to_string().into());
                         }}
                       }}
                     "#,
@@ -847,6 +900,12 @@ pub fn generate_event_handlers(
             data.push_str(
                 "EthereumSqlTypeWrapper::String(result.tx_information.network.to_string()),",
             );
+            if contract.table_group.is_some() {
+                data.push_str(&format!(
+                    "EthereumSqlTypeWrapper::I64({}),",
+                    generate_chain_id_match(contract, networks)
+                ));
+            }
             data.push_str("EthereumSqlTypeWrapper::U64(result.tx_information.transaction_index),");
             data.push_str("EthereumSqlTypeWrapper::U256(result.tx_information.log_index)");
             data.push_str("];");
@@ -885,7 +944,7 @@ pub fn generate_event_handlers(
 
                         if let Err(e) = result {{
                             rindexer_error!("{event_type_name}::{handler_name} inserting bulk data via COPY: {{:?}}", e);
-                            return Err(e.to_string());
+                            return Err(e.to_string().into());
                         }}
                         }} else {{
                             let result = context
@@ -899,19 +958,25 @@ pub fn generate_event_handlers(
                             
                             if let Err(e) = result {{
                                 rindexer_error!("{event_type_name}::{handler_name} inserting bulk data via INSERT: {{:?}}", e);
-                                return Err(e.to_string());
+                                return Err(e.to_string().into());
                             }}
                     }}
                 "#,
-                table_name =
-                    generate_event_table_full_name(indexer_name, &contract.name, &event.name),
+                table_name = generate_event_table_full_name(
+                    indexer_name,
+                    contract.table_group.as_deref().unwrap_or(&contract.name),
+                    &event.name
+                ),
                 handler_name = event.name,
                 event_type_name = event_type_name,
-                columns_names = generate_column_names_only_with_base_properties(&event.inputs)
-                    .iter()
-                    .map(|item| format!("\"{}\".to_string()", item))
-                    .collect::<Vec<String>>()
-                    .join(", "),
+                columns_names = generate_column_names_only_with_base_properties(
+                    &event.inputs,
+                    contract.table_group.is_some()
+                )
+                .iter()
+                .map(|item| format!("\"{}\".to_string()", item))
+                .collect::<Vec<String>>()
+                .join(", "),
                 data = data,
                 csv_write = csv_write,
                 csv_bulk_data = if storage.csv_enabled() {
@@ -925,7 +990,7 @@ pub fn generate_event_handlers(
                         let csv_result = context.csv.append_bulk(csv_bulk_data).await;
                         if let Err(e) = csv_result {{
                             rindexer_error!("{event_type_name}::{handler_name} inserting csv data: {{:?}}", e);
-                            return Err(e.to_string());
+                            return Err(e.to_string().into());
                         }}
                     }}"#,
                         event_type_name = event_type_name,
@@ -5,5 +5,6 @@ mod database_bindings;
 mod docker;
 mod events_bindings;
 mod networks_bindings;
+pub mod snapshot;
 
 pub use docker::generate_docker_file;
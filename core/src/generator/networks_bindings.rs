@@ -12,10 +12,65 @@ pub fn network_provider_fn_name(network: &Network) -> String {
     format!("get_{fn_name}", fn_name = network_provider_name(network).to_lowercase())
 }
 
+/// Networks with fallback rpcs are created with every url read from the env like the primary one
+fn generate_network_failover_provider_code(network: &Network, fallback_rpcs: &[String]) -> Code {
+    let read_env = |value: &str| {
+        format!(r#"public_read_env_value("{value}").unwrap_or("{value}".to_string())"#)
+    };
+    let rpc_urls: Vec<String> = std::iter::once(&network.rpc)
+        .chain(fallback_rpcs.iter())
+        .map(|rpc| read_env(rpc))
+        .collect();
+    let retry = network.rpc_retry.clone().unwrap_or_default();
+
+    Code::new(format!(
+        r#"
+            static ref {network_name}: Arc<JsonRpcCachedProvider> = rindexer::provider::create_failover_client(
+                &[{rpc_urls}],
+                {compute_units_per_second:?},
+                {max_block_range},
+                {headers},
+                &rindexer::manifest::network::RpcRetrySettings {{
+                    max_retries: {max_retries:?},
+                    initial_backoff_ms: {initial_backoff_ms:?},
+                    max_backoff_ms: {max_backoff_ms:?},
+                    circuit_breaker_failures: {circuit_breaker_failures:?},
+                    circuit_breaker_cooldown_seconds: {circuit_breaker_cooldown_seconds:?},
+                    request_timeout_seconds: {request_timeout_seconds:?},
+                    chunk_deadline_seconds: {chunk_deadline_seconds:?},
+                    fastest_first: {fastest_first:?},
+                }},
+            ).expect("Error creating provider");
+        "#,
+        network_name = network_provider_name(network),
+        rpc_urls = rpc_urls.join(", "),
+        compute_units_per_second = network.compute_units_per_second,
+        max_block_range = match network.max_block_range {
+            Some(max_block_range) => format!("Some(U64::from({}))", max_block_range),
+            None => "None".to_string(),
+        },
+        headers = generate_network_headers_code(network),
+        max_retries = retry.max_retries,
+        initial_backoff_ms = retry.initial_backoff_ms,
+        max_backoff_ms = retry.max_backoff_ms,
+        circuit_breaker_failures = retry.circuit_breaker_failures,
+        circuit_breaker_cooldown_seconds = retry.circuit_breaker_cooldown_seconds,
+        request_timeout_seconds = retry.request_timeout_seconds,
+        chunk_deadline_seconds = retry.chunk_deadline_seconds,
+        fastest_first = retry.fastest_first,
+    ))
+}
+
 fn generate_network_lazy_provider_code(network: &Network) -> Code {
+    if let Some(fallback_rpcs) = network.fallback_rpcs.as_ref().filter(|rpcs| !rpcs.is_empty()) {
+        if !network.rpc.contains("shadow") {
+            return generate_network_failover_provider_code(network, fallback_rpcs);
+        }
+    }
+
     Code::new(format!(
         r#"
-            static ref {network_name}: Arc<JsonRpcCachedProvider> = {client_fn}(&public_read_env_value("{network_url}").unwrap_or("{network_url}".to_string()), {compute_units_per_second}, {max_block_range} {placeholder_headers}).expect("Error creating provider");
+            static ref {network_name}: Arc<JsonRpcCachedProvider> = {client_fn}(&public_read_env_value("{network_url}").unwrap_or("{network_url}".to_string()), {compute_units_per_second}, {max_block_range} {headers}).expect("Error creating provider");
         "#,
         network_name = network_provider_name(network),
         network_url = network.rpc,
@@ -32,11 +87,40 @@ fn generate_network_lazy_provider_code(network: &Network) -> Code {
         },
         client_fn =
             if network.rpc.contains("shadow") { "create_shadow_client" } else { "create_client" },
-        placeholder_headers =
-            if network.rpc.contains("shadow") { "" } else { ", HeaderMap::new()" },
+        headers = if network.rpc.contains("shadow") {
+            "".to_string()
+        } else {
+            format!(", {}", generate_network_headers_code(network))
+        },
     ))
 }
 
+/// The header values are env variable names in rust projects so the credentials are read at
+/// runtime rather than written into the generated code
+fn generate_network_headers_code(network: &Network) -> String {
+    if network.rpc_headers.is_none() && network.rpc_bearer_token.is_none() {
+        return "HeaderMap::new()".to_string();
+    }
+
+    let read_env = |value: &str| {
+        format!(r#"public_read_env_value("{value}").unwrap_or("{value}".to_string())"#)
+    };
+
+    let headers: Vec<String> = network
+        .rpc_headers
+        .iter()
+        .flatten()
+        .map(|(name, value)| format!(r#"("{}", {})"#, name, read_env(value)))
+        .collect();
+
+    let bearer_token = match &network.rpc_bearer_token {
+        Some(token) => format!("Some({})", read_env(token)),
+        None => "None".to_string(),
+    };
+
+    format!("rindexer::provider::build_rpc_headers(vec![{}], {})", headers.join(", "), bearer_token)
+}
+
 fn generate_network_provider_code(network: &Network) -> Code {
     Code::new(format!(
         r#"
@@ -44,7 +128,7 @@ fn generate_network_provider_code(network: &Network) -> Code {
                 Arc::clone(&{provider_lazy_name})
             }}
             
-            pub fn {fn_name}() -> Arc<Provider<RetryClient<Http>>> {{
+            pub fn {fn_name}() -> Arc<Provider<RetryClient<RpcTransport>>> {{
                 {provider_lazy_name}.get_inner_provider()
             }}
         "#,
@@ -88,11 +172,11 @@ pub fn generate_networks_code(networks: &[Network]) -> Code {
             /// This file was auto generated by rindexer - https://github.com/joshstevens19/rindexer.
             /// Any manual changes to this file will be overwritten.
             
-            use ethers::providers::{Provider, Http, RetryClient};
+            use ethers::providers::{Provider, RetryClient};
             use ethers::types::U64;
             use rindexer::{
                 lazy_static,
-                provider::{create_client, JsonRpcCachedProvider, RetryClientError},
+                provider::{create_client, JsonRpcCachedProvider, RetryClientError, RpcTransport},
                 public_read_env_value, HeaderMap,
             };
             use std::sync::Arc;
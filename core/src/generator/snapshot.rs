@@ -0,0 +1,208 @@
+use std::{fs, path::Path};
+
+use crate::{
+    database::postgres::{
+        block_timestamps::generate_block_timestamps_tables_sql,
+        derived_fields::derived_field_functions_sql,
+        entities::generate_entities_tables_sql,
+        generate::{generate_tables_for_indexer_sql, GenerateTablesForIndexerSqlError},
+        rollups::generate_rollups_tables_sql,
+        state_samples::generate_state_samples_tables_sql,
+    },
+    manifest::core::Manifest,
+    modules::generate_modules_tables_sql,
+};
+
+pub const SNAPSHOTS_FOLDER: &str = "snapshots";
+pub const POSTGRES_SNAPSHOT_FILE: &str = "postgres.sql";
+pub const GRAPHQL_SNAPSHOT_FILE: &str = "schema.graphql";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SnapshotError {
+    #[error("Could not generate the postgres schema: {0}")]
+    GeneratePostgres(#[from] GenerateTablesForIndexerSqlError),
+
+    #[error("Could not read or write the snapshot: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SnapshotResult {
+    Unchanged,
+    Written,
+    /// The generated output no longer matches the snapshot, holds the line diff
+    Changed(String),
+}
+
+/// The generator builds the sql with indentation from the rust source, one trimmed statement per
+/// line keeps the snapshot stable when only the formatting of the generator changes
+fn normalize_sql(sql: &str) -> String {
+    sql.split(';')
+        .map(|statement| {
+            statement
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<&str>>()
+                .join(" ")
+        })
+        .filter(|statement| !statement.is_empty())
+        .map(|statement| format!("{};", statement))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// The postgres DDL rindexer runs on startup for the manifest
+pub fn postgres_schema_snapshot(
+    project_path: &Path,
+    manifest: &Manifest,
+) -> Result<String, SnapshotError> {
+    let sql = generate_tables_for_indexer_sql(
+        project_path,
+        &manifest.to_indexer(),
+        manifest.storage.postgres_disable_create_tables(),
+        manifest.storage.postgres_soft_delete_reorged_rows(),
+        manifest.storage.postgres_notify_channel(),
+        manifest.storage.postgres_column_encryption(),
+        manifest.labels.is_some(),
+    )?;
+
+    let sql = format!(
+        "{}{}{}{}{}{}{}",
+        sql,
+        generate_modules_tables_sql(manifest),
+        generate_entities_tables_sql(&manifest.name, manifest.storage.postgres_entities()),
+        generate_rollups_tables_sql(&manifest.name, manifest.storage.postgres_rollups()),
+        generate_state_samples_tables_sql(
+            &manifest.name,
+            manifest.storage.postgres_state_samples()
+        ),
+        if manifest.storage.postgres_block_timestamps().is_some() {
+            generate_block_timestamps_tables_sql(&manifest.name)
+        } else {
+            String::new()
+        },
+        derived_field_functions_sql(
+            &manifest.name,
+            &manifest.contracts,
+            manifest.storage.postgres_entities(),
+            manifest.graphql_derived_fields()
+        )
+    );
+
+    Ok(format!("{}\n", normalize_sql(&sql)))
+}
+
+/// A minimal line diff, lines only in the snapshot start with `-` and lines only in the new output
+/// with `+`
+fn line_diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // longest common subsequence table so moved lines show as one removal and one addition
+    let mut lcs = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            i += 1;
+            j += 1;
+        } else if i < expected.len() && (j == actual.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(format!("- {}", expected[i]));
+            i += 1;
+        } else {
+            diff.push(format!("+ {}", actual[j]));
+            j += 1;
+        }
+    }
+
+    diff.join("\n")
+}
+
+/// Compares the output with the snapshot file, with `update` the file is written instead so a
+/// missing or outdated snapshot can be accepted
+pub fn check_snapshot(
+    path: &Path,
+    actual: &str,
+    update: bool,
+) -> Result<SnapshotResult, SnapshotError> {
+    let expected = match fs::read_to_string(path) {
+        Ok(expected) => Some(expected),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    if expected.as_deref() == Some(actual) {
+        return Ok(SnapshotResult::Unchanged);
+    }
+
+    if update {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, actual)?;
+        return Ok(SnapshotResult::Written);
+    }
+
+    Ok(SnapshotResult::Changed(line_diff(expected.as_deref().unwrap_or(""), actual)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::manifest::yaml::{read_manifest, YAML_CONFIG_NAME};
+
+    #[test]
+    fn test_normalize_sql() {
+        let sql = "CREATE SCHEMA IF NOT EXISTS a;\n        CREATE TABLE a.b (\n            id INT\n        );\n    ";
+        assert_eq!(
+            normalize_sql(sql),
+            "CREATE SCHEMA IF NOT EXISTS a;\nCREATE TABLE a.b ( id INT );"
+        );
+    }
+
+    #[test]
+    fn test_check_snapshot() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(SNAPSHOTS_FOLDER).join(POSTGRES_SNAPSHOT_FILE);
+
+        assert_eq!(
+            check_snapshot(&path, "a;\nb;\n", false).unwrap(),
+            SnapshotResult::Changed("+ a;\n+ b;".to_string())
+        );
+        assert_eq!(check_snapshot(&path, "a;\nb;\n", true).unwrap(), SnapshotResult::Written);
+        assert_eq!(check_snapshot(&path, "a;\nb;\n", false).unwrap(), SnapshotResult::Unchanged);
+        assert_eq!(
+            check_snapshot(&path, "a;\nc;\n", false).unwrap(),
+            SnapshotResult::Changed("- b;\n+ c;".to_string())
+        );
+    }
+
+    /// Run with `UPDATE_SNAPSHOTS=true` to accept an intended change to the generated schema
+    #[test]
+    fn test_postgres_schema_matches_the_snapshot() {
+        let project_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/snapshot");
+        let manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).unwrap();
+        let sql = postgres_schema_snapshot(&project_path, &manifest).unwrap();
+
+        let update = env::var("UPDATE_SNAPSHOTS").is_ok_and(|value| value == "true");
+        let path = project_path.join(SNAPSHOTS_FOLDER).join(POSTGRES_SNAPSHOT_FILE);
+        if let SnapshotResult::Changed(diff) = check_snapshot(&path, &sql, update).unwrap() {
+            panic!("The generated postgres schema changed, run with UPDATE_SNAPSHOTS=true if it is expected:\n{}", diff);
+        }
+    }
+}
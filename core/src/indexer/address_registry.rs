@@ -0,0 +1,196 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use ethers::{
+    abi::{decode, ParamType, Token},
+    providers::ProviderError,
+    types::{Address, Bytes},
+    utils::id,
+};
+use once_cell::sync::Lazy;
+use thiserror::Error;
+use tokio::{task::JoinHandle, time::Instant};
+use tracing::{error, info};
+
+use crate::{
+    event::{
+        callback_registry::EventCallbackRegistryInformation, contract_setup::IndexingContractSetup,
+    },
+    is_running,
+    manifest::contract::AddressRegistryDetails,
+    provider::JsonRpcCachedProvider,
+    provider_state::StateCallError,
+};
+
+#[derive(Error, Debug)]
+pub enum AddressRegistryError {
+    #[error("Could not get the latest block: {0}")]
+    Provider(#[from] ProviderError),
+
+    #[error("Could not call the registry: {0}")]
+    Call(#[from] StateCallError),
+
+    #[error("Registry {0:?} did not return an address[] from {1}()")]
+    InvalidResponse(Address, String),
+}
+
+/// The addresses each registry listed when it was last read, keyed by network, registry and
+/// function
+static REGISTRY_ADDRESSES: Lazy<RwLock<HashMap<String, Vec<Address>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+fn registry_key(network: &str, registry: &AddressRegistryDetails) -> String {
+    format!("{}-{:?}-{}", network, registry.address, registry.function)
+}
+
+/// Empty until the registry is first read
+pub fn registry_addresses(network: &str, registry: &AddressRegistryDetails) -> Vec<Address> {
+    REGISTRY_ADDRESSES
+        .read()
+        .expect("registry addresses lock poisoned")
+        .get(&registry_key(network, registry))
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn decode_addresses(
+    registry: &AddressRegistryDetails,
+    result: &[u8],
+) -> Result<Vec<Address>, AddressRegistryError> {
+    let invalid =
+        || AddressRegistryError::InvalidResponse(registry.address, registry.function.clone());
+
+    let tokens =
+        decode(&[ParamType::Array(Box::new(ParamType::Address))], result).map_err(|_| invalid())?;
+    let Some(Token::Array(tokens)) = tokens.into_iter().next() else {
+        return Err(invalid());
+    };
+
+    let mut addresses: Vec<Address> = tokens.into_iter().filter_map(Token::into_address).collect();
+    addresses.sort();
+    addresses.dedup();
+    Ok(addresses)
+}
+
+struct WatchedRegistry {
+    network: String,
+    registry: AddressRegistryDetails,
+    provider: Arc<JsonRpcCachedProvider>,
+    next_read: Instant,
+}
+
+impl WatchedRegistry {
+    /// Reads the registry at the head and swaps in its addresses
+    async fn read(&self) -> Result<(), AddressRegistryError> {
+        let head = self.provider.get_block_number().await?;
+        let call_data = Bytes::from(id(format!("{}()", self.registry.function)).to_vec());
+        let result = self.provider.call_at(self.registry.address, call_data, head).await?;
+        let addresses = decode_addresses(&self.registry, &result)?;
+
+        let mut registries = REGISTRY_ADDRESSES.write().expect("registry addresses lock poisoned");
+        let key = registry_key(&self.network, &self.registry);
+        if registries.get(&key) != Some(&addresses) {
+            info!(
+                "Registry {:?} on network {} lists {} addresses at block {}",
+                self.registry.address,
+                self.network,
+                addresses.len(),
+                head
+            );
+            registries.insert(key, addresses);
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads every registry the events are indexed from so the first ranges are fetched with their
+/// addresses, then spawns the loop which re-reads each one on its interval. A registry which can
+/// not be read on a later pass keeps the addresses it listed before.
+pub async fn start_address_registries(
+    events: &[EventCallbackRegistryInformation],
+) -> Result<Option<JoinHandle<()>>, AddressRegistryError> {
+    let mut registries: Vec<WatchedRegistry> = vec![];
+    for event in events {
+        for details in &event.contract.details {
+            let IndexingContractSetup::Registry(setup) = &details.indexing_contract_setup else {
+                continue;
+            };
+            if registries.iter().any(|watched| {
+                watched.network == setup.network && watched.registry == setup.registry
+            }) {
+                continue;
+            }
+
+            registries.push(WatchedRegistry {
+                network: setup.network.clone(),
+                registry: setup.registry.clone(),
+                provider: Arc::clone(&details.cached_provider),
+                next_read: Instant::now(),
+            });
+        }
+    }
+
+    if registries.is_empty() {
+        return Ok(None);
+    }
+
+    for watched in registries.iter_mut() {
+        watched.read().await?;
+        watched.next_read =
+            Instant::now() + Duration::from_secs(watched.registry.refresh_interval_seconds());
+    }
+
+    Ok(Some(tokio::spawn(async move {
+        while is_running() {
+            let next_read = registries
+                .iter()
+                .map(|watched| watched.next_read)
+                .min()
+                .expect("there is at least one registry");
+            tokio::time::sleep_until(next_read).await;
+
+            for watched in
+                registries.iter_mut().filter(|watched| watched.next_read <= Instant::now())
+            {
+                if let Err(e) = watched.read().await {
+                    error!(
+                        "Could not read registry {:?} on network {}: {}",
+                        watched.registry.address, watched.network, e
+                    );
+                }
+                watched.next_read = Instant::now() +
+                    Duration::from_secs(watched.registry.refresh_interval_seconds());
+            }
+        }
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::encode;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_registry_addresses() {
+        let registry = AddressRegistryDetails {
+            address: Address::repeat_byte(9),
+            function: "getTokens".to_string(),
+            refresh_interval_seconds: None,
+        };
+        let listed = [Address::repeat_byte(2), Address::repeat_byte(1), Address::repeat_byte(2)];
+        let result = encode(&[Token::Array(
+            listed.iter().map(|address| Token::Address(*address)).collect(),
+        )]);
+
+        assert_eq!(
+            decode_addresses(&registry, &result).unwrap(),
+            vec![Address::repeat_byte(1), Address::repeat_byte(2)]
+        );
+        assert!(decode_addresses(&registry, &[1, 2, 3]).is_err());
+    }
+}
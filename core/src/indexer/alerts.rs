@@ -0,0 +1,239 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde_json::json;
+use tokio::{sync::Mutex, task::JoinHandle};
+use tracing::{error, info, warn};
+
+use crate::{
+    chat::NotificationClients,
+    event::callback_registry::EventCallbackRegistryInformation,
+    indexer::IndexingEventsProgressState,
+    is_running,
+    manifest::{
+        alerts::{AlertCondition, AlertRule, AlertsConfig},
+        chat::NotificationDestination,
+    },
+    provider::JsonRpcCachedProvider,
+};
+
+const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+static HANDLER_ERRORS: Lazy<AtomicU64> = Lazy::new(|| AtomicU64::new(0));
+
+pub fn record_handler_error() {
+    HANDLER_ERRORS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn handler_errors() -> u64 {
+    HANDLER_ERRORS.load(Ordering::SeqCst)
+}
+
+#[derive(Default)]
+struct AlertRuleState {
+    breaching_since: Option<Instant>,
+    firing: bool,
+}
+
+struct AlertMonitor {
+    rules: Vec<AlertRule>,
+    states: Vec<AlertRuleState>,
+    providers: HashMap<String, Arc<JsonRpcCachedProvider>>,
+    progress: Arc<Mutex<IndexingEventsProgressState>>,
+    http: Client,
+    notifications: NotificationClients,
+    last_handler_errors: u64,
+    last_checked: Instant,
+}
+
+impl AlertMonitor {
+    async fn blocks_behind(&self, network: &str) -> Option<u64> {
+        let provider = self.providers.get(network)?;
+        let head = match provider.get_block_number().await {
+            Ok(head) => head,
+            Err(e) => {
+                warn!("Alerts - failed to get latest block for network {}: {}", network, e);
+                return None;
+            }
+        };
+
+        let progress = self.progress.lock().await;
+        let slowest = progress
+            .events
+            .iter()
+            .filter(|e| e.network == network && e.live_indexing)
+            .map(|e| e.last_synced_block)
+            .min()?;
+
+        Some(head.saturating_sub(slowest).as_u64())
+    }
+
+    /// Returns a summary of the breach when the rule condition is met
+    async fn evaluate(&self, rule: &AlertRule, errors_per_minute: u64) -> Option<String> {
+        match rule.condition {
+            AlertCondition::BlocksBehind => {
+                let networks: Vec<&String> = match &rule.network {
+                    Some(network) => vec![network],
+                    None => self.providers.keys().collect(),
+                };
+
+                for network in networks {
+                    if let Some(behind) = self.blocks_behind(network).await {
+                        if behind > rule.threshold {
+                            return Some(format!(
+                                "network {} is {} blocks behind head",
+                                network, behind
+                            ));
+                        }
+                    }
+                }
+
+                None
+            }
+            AlertCondition::Stalled => {
+                let progress = self.progress.lock().await;
+                let stalled: Vec<String> = progress
+                    .events
+                    .iter()
+                    .filter(|e| e.stalled)
+                    .map(|e| format!("{} on {}", e.info_log, e.network))
+                    .collect();
+
+                if stalled.len() as u64 > rule.threshold {
+                    Some(format!("stalled events: {}", stalled.join(", ")))
+                } else {
+                    None
+                }
+            }
+            AlertCondition::HandlerErrorRate => {
+                if errors_per_minute > rule.threshold {
+                    Some(format!("handlers are erroring {} times a minute", errors_per_minute))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    async fn notify(&self, rule: &AlertRule, resolved: bool, summary: &str) {
+        if let Some(webhooks) = &rule.webhooks {
+            let payload = json!({
+                "alert": rule.name,
+                "status": if resolved { "resolved" } else { "firing" },
+                "summary": summary,
+                "threshold": rule.threshold,
+            });
+            for webhook in webhooks {
+                if let Err(e) = self.http.post(webhook).json(&payload).send().await {
+                    error!("Alerts - failed to send alert {} to webhook: {}", rule.name, e);
+                }
+            }
+        }
+
+        for destination in rule.notify.iter().flatten() {
+            let message = if resolved {
+                format!("rindexer alert {} resolved", rule.name)
+            } else {
+                format!("rindexer alert {} firing: {}", rule.name, summary)
+            };
+            if let Err(e) = self.notifications.send(destination, &message).await {
+                error!("Alerts - failed to send alert {} to {}: {}", rule.name, destination, e);
+            }
+        }
+
+        if let Some(routing_key) = &rule.pagerduty_routing_key {
+            let payload = json!({
+                "routing_key": routing_key,
+                "event_action": if resolved { "resolve" } else { "trigger" },
+                "dedup_key": format!("rindexer-{}", rule.name),
+                "payload": {
+                    "summary": format!("rindexer alert {}: {}", rule.name, summary),
+                    "source": "rindexer",
+                    "severity": "error",
+                },
+            });
+            if let Err(e) = self.http.post(PAGERDUTY_EVENTS_URL).json(&payload).send().await {
+                error!("Alerts - failed to send alert {} to pagerduty: {}", rule.name, e);
+            }
+        }
+    }
+
+    async fn check(&mut self) {
+        let errors = handler_errors();
+        let elapsed = self.last_checked.elapsed().as_secs_f64().max(1.0);
+        let errors_per_minute =
+            ((errors - self.last_handler_errors) as f64 * 60.0 / elapsed).round() as u64;
+        self.last_handler_errors = errors;
+        self.last_checked = Instant::now();
+
+        for index in 0..self.rules.len() {
+            let rule = self.rules[index].clone();
+            let breach = self.evaluate(&rule, errors_per_minute).await;
+            let state = &mut self.states[index];
+
+            match breach {
+                Some(summary) => {
+                    let since = *state.breaching_since.get_or_insert_with(Instant::now);
+                    if !state.firing && since.elapsed().as_secs() >= rule.for_seconds() {
+                        state.firing = true;
+                        warn!("Alert {} firing - {}", rule.name, summary);
+                        self.notify(&rule, false, &summary).await;
+                    }
+                }
+                None => {
+                    state.breaching_since = None;
+                    if state.firing {
+                        state.firing = false;
+                        info!("Alert {} resolved", rule.name);
+                        self.notify(&rule, true, "condition no longer met").await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Spawns the loop which evaluates the alert rules against the indexing progress, it runs until
+/// shutdown or the returned handle is aborted.
+pub fn start_alert_monitor(
+    config: &AlertsConfig,
+    destinations: &[NotificationDestination],
+    events: &[EventCallbackRegistryInformation],
+    progress: Arc<Mutex<IndexingEventsProgressState>>,
+) -> JoinHandle<()> {
+    let mut providers = HashMap::new();
+    for event in events {
+        for details in &event.contract.details {
+            providers
+                .entry(details.network.clone())
+                .or_insert_with(|| Arc::clone(&details.cached_provider));
+        }
+    }
+
+    let mut monitor = AlertMonitor {
+        rules: config.rules.clone(),
+        states: config.rules.iter().map(|_| AlertRuleState::default()).collect(),
+        providers,
+        progress,
+        http: Client::new(),
+        notifications: NotificationClients::new(destinations),
+        last_handler_errors: handler_errors(),
+        last_checked: Instant::now(),
+    };
+    let interval = Duration::from_secs(config.check_interval_seconds());
+
+    tokio::spawn(async move {
+        while is_running() {
+            tokio::time::sleep(interval).await;
+            monitor.check().await;
+        }
+    })
+}
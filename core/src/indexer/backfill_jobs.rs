@@ -0,0 +1,421 @@
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
+
+use ethers::types::U64;
+use rust_decimal::Decimal;
+use tracing::{error, info, warn};
+
+use crate::{
+    database::postgres::client::{PostgresClient, PostgresError},
+    event::config::EventProcessingConfig,
+    helpers::generate_random_id,
+    indexer::process::{process_event, ProcessEventError},
+    is_running,
+    manifest::backfill_jobs::BackfillJobsSettings,
+    EthereumSqlTypeWrapper,
+};
+
+const JOBS_TABLE: &str = "rindexer_internal.jobs";
+
+/// Set on processes started with `rindexer start backfill-worker`, they only work through the
+/// backfill jobs and exit once there are none left
+pub fn is_backfill_worker() -> bool {
+    env::var("RINDEXER_BACKFILL_WORKER").is_ok_and(|value| value == "true")
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum BackfillJobsError {
+    #[error("Backfill jobs need postgres storage enabled")]
+    PostgresNotEnabled,
+
+    #[error("Backfill jobs query failed: {0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("Backfill job {0} was claimed by another worker before it could be marked done")]
+    ClaimLost(i64),
+}
+
+fn job_key(contract_name: &str, event_name: &str, network: &str) -> String {
+    format!("{}.{}.{}", contract_name, event_name, network)
+}
+
+fn config_job_key(config: &EventProcessingConfig) -> String {
+    job_key(&config.contract_name, &config.event_name, &config.network_contract.network)
+}
+
+fn decimal_to_u64(value: Decimal) -> U64 {
+    U64::from_dec_str(&value.to_string()).expect("Failed to parse job block number")
+}
+
+/// Splits the range into chunks aligned to multiples of `chunk_size` so every instance seeding
+/// the same range ends up with the same job boundaries
+fn chunk_range(from_block: U64, to_block: U64, chunk_size: u64) -> Vec<(U64, U64)> {
+    let mut chunks = vec![];
+    let mut current = from_block.as_u64();
+    let to_block = to_block.as_u64();
+
+    while current <= to_block {
+        let chunk_end = std::cmp::min((current / chunk_size + 1) * chunk_size - 1, to_block);
+        chunks.push((U64::from(current), U64::from(chunk_end)));
+        current = chunk_end + 1;
+    }
+
+    chunks
+}
+
+/// How often a worker refreshes the claim on the job it is processing, often enough that a slow
+/// job is never mistaken for an abandoned one and claimed by another worker
+fn heartbeat_interval(claim_timeout_seconds: f64) -> Duration {
+    Duration::from_secs_f64((claim_timeout_seconds / 3.0).max(1.0))
+}
+
+/// The highest block the done jobs cover without a gap starting from `from_block`
+fn contiguous_done_to(from_block: U64, done: &[(U64, U64)]) -> Option<U64> {
+    let mut covered: Option<U64> = None;
+    let mut next = from_block;
+
+    for (job_from, job_to) in done {
+        if *job_from > next {
+            break;
+        }
+        if *job_to >= next {
+            covered = Some(*job_to);
+            next = *job_to + 1;
+        }
+    }
+
+    covered
+}
+
+struct BackfillJob {
+    id: i64,
+    key: String,
+    from_block: U64,
+    to_block: U64,
+}
+
+struct BackfillJobs {
+    database: Arc<PostgresClient>,
+    indexer_name: String,
+    worker_id: String,
+    claim_timeout_seconds: f64,
+}
+
+impl BackfillJobs {
+    async fn create_table(&self) -> Result<(), PostgresError> {
+        self.database
+            .batch_execute(&format!(
+                r#"
+                CREATE TABLE IF NOT EXISTS {table} (
+                    id BIGSERIAL PRIMARY KEY,
+                    indexer_name TEXT NOT NULL,
+                    job_key TEXT NOT NULL,
+                    from_block NUMERIC NOT NULL,
+                    to_block NUMERIC NOT NULL,
+                    status TEXT NOT NULL DEFAULT 'pending',
+                    claimed_by TEXT,
+                    claimed_at TIMESTAMPTZ,
+                    completed_at TIMESTAMPTZ,
+                    UNIQUE (indexer_name, job_key, from_block)
+                );
+                CREATE INDEX IF NOT EXISTS idx_jobs_indexer_status ON {table} (indexer_name, status);
+                "#,
+                table = JOBS_TABLE
+            ))
+            .await
+    }
+
+    async fn seed(
+        &self,
+        config: &EventProcessingConfig,
+        chunk_size: u64,
+    ) -> Result<(), PostgresError> {
+        let key = config_job_key(config);
+        for (from_block, to_block) in chunk_range(config.start_block, config.end_block, chunk_size)
+        {
+            // a pending last chunk grows with the head, a done one leaves the remainder to the
+            // live indexer catching up
+            self.database
+                .execute(
+                    &format!(
+                        "INSERT INTO {table} (indexer_name, job_key, from_block, to_block)
+                        VALUES ($1, $2, $3, $4)
+                        ON CONFLICT (indexer_name, job_key, from_block) DO UPDATE
+                        SET to_block = GREATEST({table}.to_block, EXCLUDED.to_block)
+                        WHERE {table}.status = 'pending'",
+                        table = JOBS_TABLE
+                    ),
+                    &[
+                        &self.indexer_name,
+                        &key,
+                        &EthereumSqlTypeWrapper::U64(from_block),
+                        &EthereumSqlTypeWrapper::U64(to_block),
+                    ],
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn claim(&self, keys: &[String]) -> Result<Option<BackfillJob>, PostgresError> {
+        let row = self
+            .database
+            .query_one_or_none(
+                &format!(
+                    "UPDATE {table} SET status = 'claimed', claimed_by = $2, claimed_at = now()
+                    WHERE id = (
+                        SELECT id FROM {table}
+                        WHERE indexer_name = $1 AND job_key = ANY($3) AND (
+                            status = 'pending' OR
+                            (status = 'claimed' AND claimed_at < now() - make_interval(secs => $4))
+                        )
+                        ORDER BY from_block
+                        LIMIT 1
+                        FOR UPDATE SKIP LOCKED
+                    )
+                    RETURNING id, job_key, from_block, to_block",
+                    table = JOBS_TABLE
+                ),
+                &[&self.indexer_name, &self.worker_id, &keys, &self.claim_timeout_seconds],
+            )
+            .await?;
+
+        Ok(row.map(|row| BackfillJob {
+            id: row.get("id"),
+            key: row.get("job_key"),
+            from_block: decimal_to_u64(row.get("from_block")),
+            to_block: decimal_to_u64(row.get("to_block")),
+        }))
+    }
+
+    /// Refreshes the claim, returns false if the job is no longer claimed by this worker
+    async fn heartbeat(&self, job: &BackfillJob) -> Result<bool, PostgresError> {
+        let updated = self
+            .database
+            .execute(
+                &format!(
+                    "UPDATE {} SET claimed_at = now() WHERE id = $1 AND claimed_by = $2 AND status = 'claimed'",
+                    JOBS_TABLE
+                ),
+                &[&job.id, &self.worker_id],
+            )
+            .await?;
+        Ok(updated > 0)
+    }
+
+    async fn complete(&self, job: &BackfillJob) -> Result<(), BackfillJobsError> {
+        let updated = self
+            .database
+            .execute(
+                &format!(
+                    "UPDATE {} SET status = 'done', completed_at = now() WHERE id = $1 AND claimed_by = $2 AND status = 'claimed'",
+                    JOBS_TABLE
+                ),
+                &[&job.id, &self.worker_id],
+            )
+            .await?;
+
+        if updated == 0 {
+            return Err(BackfillJobsError::ClaimLost(job.id));
+        }
+
+        Ok(())
+    }
+
+    async fn release(&self, job: &BackfillJob) -> Result<(), PostgresError> {
+        self.database
+            .execute(
+                &format!(
+                    "UPDATE {} SET status = 'pending', claimed_by = NULL, claimed_at = NULL WHERE id = $1 AND claimed_by = $2",
+                    JOBS_TABLE
+                ),
+                &[&job.id, &self.worker_id],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn outstanding(&self, keys: &[String]) -> Result<i64, PostgresError> {
+        let row = self
+            .database
+            .query_one(
+                &format!(
+                    "SELECT COUNT(*) AS outstanding FROM {} WHERE indexer_name = $1 AND job_key = ANY($2) AND status <> 'done'",
+                    JOBS_TABLE
+                ),
+                &[&self.indexer_name, &keys],
+            )
+            .await?;
+        Ok(row.get("outstanding"))
+    }
+
+    async fn done_ranges(&self, key: &str) -> Result<Vec<(U64, U64)>, PostgresError> {
+        let rows = self
+            .database
+            .query(
+                &format!(
+                    "SELECT from_block, to_block FROM {} WHERE indexer_name = $1 AND job_key = $2 AND status = 'done' ORDER BY from_block",
+                    JOBS_TABLE
+                ),
+                &[&self.indexer_name, &key],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (decimal_to_u64(row.get("from_block")), decimal_to_u64(row.get("to_block"))))
+            .collect())
+    }
+
+    async fn process(&self, job: &BackfillJob, config: &EventProcessingConfig) -> bool {
+        let mut job_config = config.clone();
+        job_config.start_block = job.from_block;
+        job_config.end_block = job.to_block;
+        job_config.live_indexing = false;
+        // jobs finish out of order so the checkpoint is only moved once they are all done
+        job_config.persist_last_synced_block = false;
+
+        info!(
+            "{} - Backfill job {} claimed blocks {} to {}",
+            config.info_log_name, job.id, job.from_block, job.to_block
+        );
+
+        let work = process_event(job_config, true);
+        tokio::pin!(work);
+        let mut heartbeat = tokio::time::interval(heartbeat_interval(self.claim_timeout_seconds));
+        // the first tick completes straight away and the claim was only just taken
+        heartbeat.tick().await;
+
+        let result: Result<(), ProcessEventError> = loop {
+            tokio::select! {
+                result = &mut work => break result,
+                _ = heartbeat.tick() => match self.heartbeat(job).await {
+                    Ok(true) => {}
+                    Ok(false) => warn!(
+                        "{} - Backfill job {} is no longer claimed by this worker",
+                        config.info_log_name, job.id
+                    ),
+                    Err(e) => error!(
+                        "{} - Backfill job {} failed to refresh its claim: {}",
+                        config.info_log_name, job.id, e
+                    ),
+                },
+            }
+        };
+
+        match result {
+            Ok(()) if is_running() => true,
+            Ok(()) => false,
+            Err(e) => {
+                error!("{} - Backfill job {} failed: {}", config.info_log_name, job.id, e);
+                false
+            }
+        }
+    }
+}
+
+/// Splits the historic range of the events into jobs in the shared jobs table and works through
+/// them alongside any other workers. Returns once every job is done with the last block the jobs
+/// cover for each event config, keyed by network contract id, so the caller can hand off to live
+/// indexing from there.
+pub async fn run_backfill_jobs(
+    settings: &BackfillJobsSettings,
+    database: Option<Arc<PostgresClient>>,
+    indexer_name: &str,
+    configs: &[EventProcessingConfig],
+) -> Result<HashMap<String, U64>, BackfillJobsError> {
+    let database = database.ok_or(BackfillJobsError::PostgresNotEnabled)?;
+    let jobs = BackfillJobs {
+        database,
+        indexer_name: indexer_name.to_string(),
+        worker_id: format!("{}-{}", std::process::id(), generate_random_id(6)),
+        claim_timeout_seconds: settings.claim_timeout_seconds() as f64,
+    };
+
+    jobs.create_table().await?;
+
+    let historic_configs: Vec<&EventProcessingConfig> =
+        configs.iter().filter(|config| config.start_block <= config.end_block).collect();
+    for config in &historic_configs {
+        jobs.seed(config, settings.chunk_size()).await?;
+    }
+
+    let configs_by_key: HashMap<String, &EventProcessingConfig> =
+        historic_configs.iter().map(|config| (config_job_key(config), *config)).collect();
+    let keys: Vec<String> = configs_by_key.keys().cloned().collect();
+    let poll_interval = Duration::from_secs(settings.poll_interval_seconds());
+
+    info!("Backfill jobs - worker {} started", jobs.worker_id);
+
+    while is_running() {
+        match jobs.claim(&keys).await? {
+            Some(job) => {
+                let processed = match configs_by_key.get(&job.key) {
+                    Some(config) => jobs.process(&job, config).await,
+                    None => false,
+                };
+
+                if processed {
+                    jobs.complete(&job).await?;
+                } else {
+                    jobs.release(&job).await?;
+                }
+            }
+            None => {
+                let outstanding = jobs.outstanding(&keys).await?;
+                if outstanding == 0 {
+                    break;
+                }
+
+                info!("Backfill jobs - waiting on {} jobs held by other workers", outstanding);
+                tokio::time::sleep(poll_interval).await;
+            }
+        }
+    }
+
+    let mut covered = HashMap::new();
+    for config in historic_configs {
+        let done = jobs.done_ranges(&config_job_key(config)).await?;
+        if let Some(to_block) = contiguous_done_to(config.start_block, &done) {
+            covered.insert(config.network_contract.id.clone(), to_block);
+        }
+    }
+
+    Ok(covered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_range_aligns_to_chunk_size() {
+        let chunks = chunk_range(U64::from(150), U64::from(420), 100);
+        assert_eq!(
+            chunks,
+            vec![
+                (U64::from(150), U64::from(199)),
+                (U64::from(200), U64::from(299)),
+                (U64::from(300), U64::from(399)),
+                (U64::from(400), U64::from(420)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contiguous_done_to_stops_at_gap() {
+        let done = vec![
+            (U64::from(150), U64::from(199)),
+            (U64::from(200), U64::from(299)),
+            (U64::from(400), U64::from(499)),
+        ];
+        assert_eq!(contiguous_done_to(U64::from(150), &done), Some(U64::from(299)));
+        assert_eq!(contiguous_done_to(U64::from(100), &done), None);
+    }
+
+    #[test]
+    fn test_heartbeat_interval_refreshes_well_within_the_claim_timeout() {
+        assert_eq!(heartbeat_interval(3600.0), Duration::from_secs(1200));
+        assert_eq!(heartbeat_interval(1.0), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use ethers::{middleware::Middleware, providers::ProviderError, types::U256};
+use tracing::{error, info};
+
+use crate::{
+    error_reporting::{flush_error_reporting, report_error, ErrorCategory, ErrorContext},
+    initiate_shutdown, is_running,
+    manifest::network::Network,
+    provider::JsonRpcCachedProvider,
+    provider_usage::RpcMethod,
+};
+
+const CHAIN_ID_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(thiserror::Error, Debug)]
+pub enum ChainIdError {
+    #[error("Network {network} is declared as chain id {expected} but the rpc returned chain id {actual} - check the rpc is for the right network")]
+    Mismatch { network: String, expected: u64, actual: U256 },
+
+    #[error("Could not get the chain id for network {0}: {1}")]
+    Provider(String, ProviderError),
+}
+
+/// Asks the node rather than the provider cache so a load balancer which starts routing to
+/// another chain is noticed
+async fn check_chain_id(
+    network: &Network,
+    provider: &JsonRpcCachedProvider,
+) -> Result<(), ChainIdError> {
+    provider.usage().record(RpcMethod::ChainId);
+    let actual = provider
+        .get_inner_provider()
+        .get_chainid()
+        .await
+        .map_err(|e| ChainIdError::Provider(network.name.clone(), e))?;
+
+    if actual != U256::from(network.chain_id) {
+        return Err(ChainIdError::Mismatch {
+            network: network.name.clone(),
+            expected: network.chain_id,
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
+fn networks_to_check<'a>(
+    networks: &'a [Network],
+    providers: &'a HashMap<String, Arc<JsonRpcCachedProvider>>,
+) -> impl Iterator<Item = (&'a Network, &'a Arc<JsonRpcCachedProvider>)> {
+    networks
+        .iter()
+        .filter(|network| network.validate_chain_id())
+        .filter_map(|network| providers.get(&network.name).map(|provider| (network, provider)))
+}
+
+/// Checks every provider is connected to the chain the manifest declares before any indexing
+/// starts
+pub async fn verify_chain_ids(
+    networks: &[Network],
+    providers: &HashMap<String, Arc<JsonRpcCachedProvider>>,
+) -> Result<(), ChainIdError> {
+    for (network, provider) in networks_to_check(networks, providers) {
+        check_chain_id(network, provider).await?;
+        info!("Network {} - verified the rpc is on chain id {}", network.name, network.chain_id);
+    }
+
+    Ok(())
+}
+
+/// Keeps checking the chain ids while indexing, a provider which drifts onto another chain stops
+/// the indexer as carrying on would write another chain's data into the tables
+pub fn start_chain_id_monitor(
+    networks: Vec<Network>,
+    providers: HashMap<String, Arc<JsonRpcCachedProvider>>,
+) {
+    tokio::spawn(async move {
+        while is_running() {
+            tokio::time::sleep(CHAIN_ID_CHECK_INTERVAL).await;
+
+            for (network, provider) in networks_to_check(&networks, &providers) {
+                match check_chain_id(network, provider).await {
+                    Ok(()) => {}
+                    // a flaky rpc is the retry client's problem, only a wrong chain is fatal
+                    Err(ChainIdError::Provider(_, e)) => {
+                        error!("Network {} - could not check the chain id: {}", network.name, e);
+                    }
+                    Err(e) => {
+                        error!("{}", e);
+                        report_error(
+                            ErrorCategory::Provider,
+                            &e.to_string(),
+                            ErrorContext { network: Some(&network.name), ..Default::default() },
+                        );
+                        initiate_shutdown().await;
+                        flush_error_reporting();
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+
+use ethers::types::U64;
+use tokio::sync::Mutex;
+
+use crate::{
+    database::postgres::{client::PostgresError, ranges::CompletedRangesStore},
+    provider::WrappedLog,
+};
+
+/// The blocks of an event which are done, every block before `next_block` and the merged ranges
+/// after it
+struct CompletedRangesState {
+    next_block: U64,
+    ranges: BTreeMap<U64, U64>,
+    // rows may be stored past the checkpoint, saves a delete per range when they are not
+    stored: bool,
+}
+
+impl CompletedRangesState {
+    fn new(next_block: U64, ranges: impl IntoIterator<Item = (U64, U64)>) -> Self {
+        let mut state = Self { next_block, ranges: BTreeMap::new(), stored: false };
+        for (from_block, to_block) in ranges {
+            state.complete(from_block, to_block);
+        }
+        state
+    }
+
+    /// Records the range, returns the last block of the done prefix when the range moved it
+    fn complete(&mut self, from_block: U64, to_block: U64) -> Option<U64> {
+        if to_block < self.next_block {
+            return None;
+        }
+
+        let (mut from_block, mut to_block) = (from_block.max(self.next_block), to_block);
+        let touching: Vec<U64> = self
+            .ranges
+            .range(..=to_block + 1)
+            .filter(|(_, end)| **end + 1 >= from_block)
+            .map(|(start, _)| *start)
+            .collect();
+        for start in touching {
+            let end = self.ranges.remove(&start).expect("range was just found");
+            from_block = from_block.min(start);
+            to_block = to_block.max(end);
+        }
+
+        if from_block <= self.next_block {
+            self.next_block = to_block + 1;
+            Some(to_block)
+        } else {
+            self.ranges.insert(from_block, to_block);
+            None
+        }
+    }
+
+    fn is_done(&self, block: U64) -> bool {
+        block < self.next_block ||
+            self.ranges.range(..=block).next_back().is_some_and(|(_, end)| *end >= block)
+    }
+
+    /// The first block from `from_block` on which is not done and the last block before the done
+    /// range after it
+    fn next_gap(&self, from_block: U64) -> (U64, Option<U64>) {
+        let mut from_block = from_block.max(self.next_block);
+        if let Some((_, end)) = self.ranges.range(..=from_block).next_back() {
+            if *end >= from_block {
+                from_block = *end + 1;
+            }
+        }
+        let until = self.ranges.range(from_block..).next().map(|(start, _)| *start - 1);
+        (from_block, until)
+    }
+}
+
+/// Tracks which ranges of an event finished when they are processed concurrently, so the
+/// checkpoint only moves past blocks which are all done and a restart only fetches the ranges
+/// which were still missing rather than everything after the checkpoint
+pub struct CompletedRanges {
+    store: CompletedRangesStore,
+    state: Mutex<CompletedRangesState>,
+}
+
+impl CompletedRanges {
+    /// Picks up the ranges a previous run completed past the checkpoint
+    pub async fn load(store: CompletedRangesStore, next_block: U64) -> Result<Self, PostgresError> {
+        let ranges = store.after(next_block).await?;
+        let mut state = CompletedRangesState::new(next_block, ranges);
+        state.stored = !state.ranges.is_empty();
+        Ok(Self { store, state: Mutex::new(state) })
+    }
+
+    /// Every block before it is done
+    pub async fn next_block(&self) -> U64 {
+        self.state.lock().await.next_block
+    }
+
+    pub async fn next_gap(&self, from_block: U64) -> (U64, Option<U64>) {
+        self.state.lock().await.next_gap(from_block)
+    }
+
+    /// Drops the logs of blocks which are done already
+    pub async fn remove_done(&self, logs: &mut Vec<WrappedLog>) {
+        let state = self.state.lock().await;
+        logs.retain(|log| log.inner.block_number.is_none_or(|block| !state.is_done(block)));
+    }
+
+    /// Records a finished range, returns the block the checkpoint can move up to when the range
+    /// completes the blocks before it
+    pub async fn complete(
+        &self,
+        from_block: U64,
+        to_block: U64,
+    ) -> Result<Option<U64>, PostgresError> {
+        let mut state = self.state.lock().await;
+        match state.complete(from_block, to_block) {
+            Some(last_block) => {
+                if state.stored {
+                    self.store.prune(last_block).await?;
+                    state.stored = !state.ranges.is_empty();
+                }
+                Ok(Some(last_block))
+            }
+            None => {
+                self.store.insert(from_block, to_block).await?;
+                state.stored = true;
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u64) -> U64 {
+        U64::from(number)
+    }
+
+    #[test]
+    fn test_checkpoint_only_moves_over_blocks_which_are_all_done() {
+        let mut state = CompletedRangesState::new(block(100), []);
+        assert_eq!(state.complete(block(200), block(299)), None);
+        assert_eq!(state.complete(block(300), block(399)), None);
+        assert_eq!(state.next_gap(block(100)), (block(100), Some(block(199))));
+        assert!(state.is_done(block(250)));
+        assert!(!state.is_done(block(150)));
+
+        // the missing range joins up with the ranges after it
+        assert_eq!(state.complete(block(100), block(199)), Some(block(399)));
+        assert!(state.ranges.is_empty());
+        assert_eq!(state.next_gap(block(100)), (block(400), None));
+    }
+
+    #[test]
+    fn test_restart_skips_ranges_completed_past_the_checkpoint() {
+        let state = CompletedRangesState::new(
+            block(100),
+            [(block(100), block(149)), (block(200), block(249)), (block(250), block(299))],
+        );
+        assert_eq!(state.next_block, block(150));
+        assert_eq!(state.next_gap(block(150)), (block(150), Some(block(199))));
+        assert_eq!(state.next_gap(block(200)), (block(300), None));
+    }
+}
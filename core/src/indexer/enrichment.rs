@@ -0,0 +1,478 @@
+use std::{collections::HashMap, str::FromStr, time::Instant};
+
+use ethers::{
+    abi::{Function, HumanReadableParser, LogParam, ParamType, Token},
+    types::{Address, Bytes, TransactionReceipt, H256, I256, U256, U64},
+    utils::hex,
+};
+use futures::future::join_all;
+use reqwest::Client;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::{
+    manifest::contract::{EnrichmentSource, EnrichmentStage, ReceiptField},
+    provider::JsonRpcCachedProvider,
+    EthereumSqlTypeWrapper,
+};
+
+/// The fields every event has on top of its inputs
+const BASE_FIELDS: [&str; 3] = ["contract_address", "block_number", "tx_hash"];
+
+/// Parses a view function written like `balanceOf(address) returns (uint256)`
+pub fn parse_view_function(signature: &str) -> Result<Function, String> {
+    let signature = signature.trim();
+    let signature = if signature.starts_with("function ") {
+        signature.to_string()
+    } else {
+        format!("function {}", signature)
+    };
+    let function = HumanReadableParser::parse_function(&signature).map_err(|e| e.to_string())?;
+    if function.outputs.is_empty() {
+        return Err(format!("{} returns nothing", function.name));
+    }
+
+    Ok(function)
+}
+
+/// The `{field}` placeholders of a url template
+pub fn template_fields(template: &str) -> Vec<&str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(field, _)| field))
+        .collect()
+}
+
+/// The fields a stage reads which are not event inputs, base fields or earlier stages
+pub fn unknown_stage_fields(
+    stage: &EnrichmentStage,
+    input_names: &[&str],
+    earlier_stages: &[EnrichmentStage],
+) -> Vec<String> {
+    let fields: Vec<&str> = match &stage.source {
+        EnrichmentSource::EthCall { args, .. } => args.iter().map(String::as_str).collect(),
+        EnrichmentSource::Price { url, .. } => template_fields(url),
+        EnrichmentSource::BlockTimestamp | EnrichmentSource::Receipt { .. } => vec![],
+    };
+
+    fields
+        .into_iter()
+        .filter(|field| {
+            !BASE_FIELDS.contains(field) &&
+                !input_names.contains(field) &&
+                !earlier_stages.iter().any(|earlier| earlier.name == *field)
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Numbers are stored as `NUMERIC`, anything else as `TEXT`
+pub fn enrichment_column_type(stage: &EnrichmentStage) -> &'static str {
+    let numeric = match &stage.source {
+        EnrichmentSource::BlockTimestamp | EnrichmentSource::Price { .. } => true,
+        EnrichmentSource::Receipt { field } => *field != ReceiptField::From,
+        EnrichmentSource::EthCall { function, .. } => {
+            parse_view_function(function).is_ok_and(|function| {
+                matches!(function.outputs[0].kind, ParamType::Uint(_) | ParamType::Int(_))
+            })
+        }
+    };
+
+    if numeric {
+        "NUMERIC"
+    } else {
+        "TEXT"
+    }
+}
+
+/// The text a call result is stored as, integers in decimal and bytes in hex
+pub fn token_text(token: &Token) -> String {
+    match token {
+        Token::Address(address) => format!("{:?}", address),
+        Token::Int(value) => I256::from_raw(*value).to_string(),
+        Token::Uint(value) => value.to_string(),
+        Token::Bool(value) => value.to_string(),
+        Token::String(value) => value.clone(),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            format!("[{}]", tokens.iter().map(token_text).collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
+/// An earlier stage's value as the argument type of a call
+fn text_token(kind: &ParamType, value: &str) -> Option<Token> {
+    match kind {
+        ParamType::Address => Address::from_str(value).ok().map(Token::Address),
+        ParamType::Uint(_) => U256::from_dec_str(value).ok().map(Token::Uint),
+        ParamType::Int(_) => {
+            I256::from_dec_str(value).ok().map(|value| Token::Int(value.into_raw()))
+        }
+        ParamType::Bool => value.parse().ok().map(Token::Bool),
+        ParamType::String => Some(Token::String(value.to_string())),
+        _ => None,
+    }
+}
+
+/// Numbers come back as JSON numbers or strings, both are checked to be a decimal
+fn number_text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::Number(number) => number.to_string(),
+        Value::String(text) => text.clone(),
+        _ => return None,
+    };
+
+    Decimal::from_str(&text)
+        .or_else(|_| Decimal::from_scientific(&text))
+        .ok()
+        .map(|number| number.normalize().to_string())
+}
+
+/// An event the stages add values to
+pub struct EnrichmentRow<'a> {
+    pub address: Address,
+    pub block_number: U64,
+    pub transaction_hash: H256,
+    pub log_params: &'a [LogParam],
+}
+
+impl EnrichmentRow<'_> {
+    fn field(&self, name: &str, stages: &[Stage], values: &[Option<String>]) -> Option<String> {
+        match name {
+            "contract_address" => Some(format!("{:?}", self.address)),
+            "block_number" => Some(self.block_number.to_string()),
+            "tx_hash" => Some(format!("{:?}", self.transaction_hash)),
+            _ => match self.log_params.iter().find(|param| param.name == name) {
+                Some(param) => Some(token_text(&param.value)),
+                None => stages
+                    .iter()
+                    .position(|stage| stage.name == name)
+                    .and_then(|index| values.get(index).cloned().flatten()),
+            },
+        }
+    }
+
+    fn field_token(
+        &self,
+        name: &str,
+        kind: &ParamType,
+        stages: &[Stage],
+        values: &[Option<String>],
+    ) -> Option<Token> {
+        match name {
+            "contract_address" => Some(Token::Address(self.address)),
+            "block_number" => Some(Token::Uint(U256::from(self.block_number.as_u64()))),
+            "tx_hash" => Some(Token::FixedBytes(self.transaction_hash.as_bytes().to_vec())),
+            _ => match self.log_params.iter().find(|param| param.name == name) {
+                Some(param) => Some(param.value.clone()),
+                None => text_token(kind, &self.field(name, stages, values)?),
+            },
+        }
+    }
+}
+
+/// What a stage looks up for an event, events sharing a lookup share its value
+enum Lookup {
+    Block(U64),
+    Receipt(H256),
+    Call(Address, Bytes, U64),
+    Http(String),
+}
+
+impl Lookup {
+    fn key(&self) -> String {
+        match self {
+            Lookup::Block(number) => number.to_string(),
+            Lookup::Receipt(hash) => format!("{:?}", hash),
+            Lookup::Call(to, data, block) => format!("{:?}-{}-{}", to, block, data),
+            Lookup::Http(url) => url.clone(),
+        }
+    }
+}
+
+enum StageSource {
+    BlockTimestamp,
+    Receipt(ReceiptField),
+    EthCall { function: Function, args: Vec<String>, to: Option<Address> },
+    Price { url: String, pointer: String },
+}
+
+struct Stage {
+    name: String,
+    numeric: bool,
+    batch_size: usize,
+    cache_size: usize,
+    cache: Mutex<HashMap<String, (Instant, Option<String>)>>,
+    source: StageSource,
+}
+
+impl Stage {
+    fn lookup(
+        &self,
+        row: &EnrichmentRow,
+        stages: &[Stage],
+        values: &[Option<String>],
+    ) -> Option<Lookup> {
+        match &self.source {
+            StageSource::BlockTimestamp => Some(Lookup::Block(row.block_number)),
+            StageSource::Receipt(_) => Some(Lookup::Receipt(row.transaction_hash)),
+            StageSource::EthCall { function, args, to } => {
+                let tokens = args
+                    .iter()
+                    .zip(&function.inputs)
+                    .map(|(arg, input)| row.field_token(arg, &input.kind, stages, values))
+                    .collect::<Option<Vec<Token>>>()?;
+                let data = function.encode_input(&tokens).ok()?;
+                Some(Lookup::Call(to.unwrap_or(row.address), Bytes::from(data), row.block_number))
+            }
+            StageSource::Price { url: template, .. } => {
+                let mut url = template.clone();
+                for field in template_fields(template) {
+                    let value = row.field(field, stages, values)?;
+                    url = url.replace(&format!("{{{}}}", field), &value);
+                }
+                Some(Lookup::Http(url))
+            }
+        }
+    }
+
+    fn receipt_value(field: ReceiptField, receipt: &TransactionReceipt) -> Option<String> {
+        match field {
+            ReceiptField::GasUsed => receipt.gas_used.map(|gas| gas.to_string()),
+            ReceiptField::EffectiveGasPrice => {
+                receipt.effective_gas_price.map(|price| price.to_string())
+            }
+            ReceiptField::Status => receipt.status.map(|status| status.to_string()),
+            ReceiptField::From => Some(format!("{:?}", receipt.from)),
+        }
+    }
+
+    async fn resolve(
+        &self,
+        lookup: &Lookup,
+        provider: &JsonRpcCachedProvider,
+        http: &Client,
+    ) -> Result<Option<String>, String> {
+        match (&self.source, lookup) {
+            (StageSource::BlockTimestamp, Lookup::Block(number)) => Ok(provider
+                .get_block(*number)
+                .await
+                .map_err(|e| e.to_string())?
+                .map(|block| block.timestamp.to_string())),
+            (StageSource::Receipt(field), Lookup::Receipt(hash)) => Ok(provider
+                .get_transaction_receipt(*hash)
+                .await
+                .map_err(|e| e.to_string())?
+                .and_then(|receipt| Self::receipt_value(*field, &receipt))),
+            (StageSource::EthCall { function, .. }, Lookup::Call(to, data, block)) => {
+                let result =
+                    provider.call_at(*to, data.clone(), *block).await.map_err(|e| e.to_string())?;
+                let tokens = function.decode_output(&result).map_err(|e| e.to_string())?;
+                Ok(tokens.first().map(token_text))
+            }
+            (StageSource::Price { pointer, .. }, Lookup::Http(url)) => {
+                let response = http.get(url).send().await.map_err(|e| e.to_string())?;
+                if !response.status().is_success() {
+                    return Err(format!("{} answered with {}", url, response.status()));
+                }
+                let body: Value = response.json().await.map_err(|e| e.to_string())?;
+                Ok(body.pointer(pointer).and_then(number_text))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn cached(&self, key: &str) -> Option<Option<String>> {
+        self.cache.lock().await.get(key).map(|(_, value)| value.clone())
+    }
+
+    async fn cache_value(&self, key: String, value: Option<String>) {
+        if self.cache_size == 0 {
+            return;
+        }
+
+        let mut cache = self.cache.lock().await;
+        if cache.len() >= self.cache_size {
+            if let Some(oldest) =
+                cache.iter().min_by_key(|(_, (inserted, _))| *inserted).map(|(k, _)| k.clone())
+            {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(key, (Instant::now(), value));
+    }
+
+    fn sql_value(&self, value: Option<String>) -> EthereumSqlTypeWrapper {
+        if self.numeric {
+            EthereumSqlTypeWrapper::NumericNullable(value.unwrap_or_default())
+        } else {
+            EthereumSqlTypeWrapper::StringNullable(value.unwrap_or_default())
+        }
+    }
+}
+
+/// Runs the enrichment stages of an event over each batch in the order the manifest lists them,
+/// so a stage can use what the stages before it looked up. Each stage looks up a value once per
+/// batch however many events share it, `batch_size` at a time, and keeps them in its own cache.
+/// A lookup which fails leaves the value empty so the event still gets indexed.
+pub struct EnrichmentPipeline {
+    event_name: String,
+    stages: Vec<Stage>,
+    http: Client,
+}
+
+impl EnrichmentPipeline {
+    /// The stages are checked when the manifest is validated
+    pub fn new(event_name: &str, stages: &[EnrichmentStage]) -> Self {
+        let stages = stages
+            .iter()
+            .filter_map(|stage| {
+                let source = match &stage.source {
+                    EnrichmentSource::BlockTimestamp => StageSource::BlockTimestamp,
+                    EnrichmentSource::Receipt { field } => StageSource::Receipt(*field),
+                    EnrichmentSource::EthCall { function, args, to } => StageSource::EthCall {
+                        function: parse_view_function(function).ok()?,
+                        args: args.clone(),
+                        to: *to,
+                    },
+                    EnrichmentSource::Price { url, pointer } => {
+                        StageSource::Price { url: url.clone(), pointer: pointer.clone() }
+                    }
+                };
+
+                Some(Stage {
+                    name: stage.name.clone(),
+                    numeric: enrichment_column_type(stage) == "NUMERIC",
+                    batch_size: stage.batch_size(),
+                    cache_size: stage.cache_size(),
+                    cache: Mutex::new(HashMap::new()),
+                    source,
+                })
+            })
+            .collect();
+
+        Self { event_name: event_name.to_string(), stages, http: Client::new() }
+    }
+
+    /// The values of each stage for each row, in the order of the stages
+    pub async fn enrich(
+        &self,
+        provider: &JsonRpcCachedProvider,
+        rows: &[EnrichmentRow<'_>],
+    ) -> Vec<Vec<Option<String>>> {
+        let mut values: Vec<Vec<Option<String>>> = vec![Vec::new(); rows.len()];
+
+        for (index, stage) in self.stages.iter().enumerate() {
+            let lookups: Vec<Option<Lookup>> = rows
+                .iter()
+                .zip(&values)
+                .map(|(row, row_values)| stage.lookup(row, &self.stages[..index], row_values))
+                .collect();
+
+            let mut resolved: HashMap<String, Option<String>> = HashMap::new();
+            let mut pending: Vec<(String, &Lookup)> = vec![];
+            for lookup in lookups.iter().flatten() {
+                let key = lookup.key();
+                if resolved.contains_key(&key) || pending.iter().any(|(pending, _)| *pending == key)
+                {
+                    continue;
+                }
+                match stage.cached(&key).await {
+                    Some(value) => {
+                        resolved.insert(key, value);
+                    }
+                    None => pending.push((key, lookup)),
+                }
+            }
+
+            for chunk in pending.chunks(stage.batch_size) {
+                let results = join_all(
+                    chunk.iter().map(|(_, lookup)| stage.resolve(lookup, provider, &self.http)),
+                )
+                .await;
+                for ((key, _), result) in chunk.iter().zip(results) {
+                    match result {
+                        Ok(value) => {
+                            stage.cache_value(key.clone(), value.clone()).await;
+                            resolved.insert(key.clone(), value);
+                        }
+                        // failures are not cached so the next batch tries again
+                        Err(e) => {
+                            warn!(
+                                "{} - enrichment stage {} could not look up {}: {}",
+                                self.event_name, stage.name, key, e
+                            );
+                            resolved.insert(key.clone(), None);
+                        }
+                    }
+                }
+            }
+
+            for (row_values, lookup) in values.iter_mut().zip(&lookups) {
+                row_values.push(
+                    lookup
+                        .as_ref()
+                        .and_then(|lookup| resolved.get(&lookup.key()).cloned().flatten()),
+                );
+            }
+        }
+
+        values
+    }
+
+    /// The values as the column types of their stages
+    pub fn sql_values(&self, values: &[Option<String>]) -> Vec<EthereumSqlTypeWrapper> {
+        self.stages
+            .iter()
+            .zip(values)
+            .map(|(stage, value)| stage.sql_value(value.clone()))
+            .collect()
+    }
+
+    /// The values keyed by stage name, for streams and chats
+    pub fn json_values(&self, values: &[Option<String>]) -> Vec<(String, Value)> {
+        self.stages
+            .iter()
+            .zip(values)
+            .map(|(stage, value)| {
+                (stage.name.clone(), value.clone().map_or(Value::Null, Value::String))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_template_fields() {
+        assert_eq!(
+            template_fields("https://prices.example/{contract_address}?at={timestamp}"),
+            vec!["contract_address", "timestamp"]
+        );
+        assert!(template_fields("https://prices.example/eth").is_empty());
+    }
+
+    #[test]
+    fn test_parse_view_function() {
+        let function = parse_view_function("balanceOf(address) returns (uint256)").unwrap();
+        assert_eq!(function.name, "balanceOf");
+        assert_eq!(function.inputs[0].kind, ParamType::Address);
+        assert_eq!(function.outputs[0].kind, ParamType::Uint(256));
+        assert!(parse_view_function("poke(address)").is_err());
+    }
+
+    #[test]
+    fn test_number_text() {
+        assert_eq!(number_text(&json!(1843.25)), Some("1843.25".to_string()));
+        assert_eq!(number_text(&json!("0.5")), Some("0.5".to_string()));
+        assert_eq!(number_text(&json!(1e-7)), Some("0.0000001".to_string()));
+        assert_eq!(number_text(&json!("n/a")), None);
+    }
+}
@@ -0,0 +1,264 @@
+use std::{path::Path, time::Duration};
+
+use ethers::{
+    providers::ProviderError,
+    types::{H256, U64},
+};
+use tokio::time::Instant;
+
+use crate::{
+    abi::{ABIItem, EventInfo, ParamTypeError, ReadAbiError},
+    event::{contract_setup::IndexingContractSetup, BuildRindexerFilterError, RindexerEventFilter},
+    manifest::core::Manifest,
+    provider::{CreateNetworkProvider, JsonRpcCachedProvider, RetryClientError},
+};
+
+/// How many windows spread over the range of an event are sampled
+const SAMPLE_WINDOWS: u64 = 5;
+
+/// The blocks of a sampled window, halved while the node refuses the range
+const SAMPLE_WINDOW_BLOCKS: u64 = 1_000;
+
+/// Most providers ask for a smaller range past this many logs in one `eth_getLogs`
+const LOGS_PER_CALL: u64 = 10_000;
+
+/// Rough size of an event row besides its log data, the ids, hashes, addresses and indexes
+const ROW_OVERHEAD_BYTES: u64 = 300;
+
+#[derive(thiserror::Error, Debug)]
+pub enum EstimateError {
+    #[error("{0}")]
+    Provider(#[from] RetryClientError),
+
+    #[error("{0}")]
+    ReadAbi(#[from] ReadAbiError),
+
+    #[error("{0}")]
+    ParamType(#[from] ParamTypeError),
+
+    #[error("{0}")]
+    BuildFilter(#[from] BuildRindexerFilterError),
+
+    #[error("Network {0} is not in the manifest")]
+    UnknownNetwork(String),
+
+    #[error("Could not sample the logs on network {0}: {1}")]
+    Rpc(String, ProviderError),
+}
+
+/// What backfilling an event on a network is expected to take, extrapolated from the sampled
+/// windows so events clustered in a few blocks can be well off
+#[derive(Debug)]
+pub struct EventEstimate {
+    pub contract_name: String,
+    pub event_name: String,
+    pub network: String,
+    pub from_block: U64,
+    pub to_block: U64,
+    pub sampled_blocks: u64,
+    pub sampled_logs: u64,
+    pub logs: u64,
+    pub rpc_calls: u64,
+    pub storage_bytes: u64,
+    pub duration: Duration,
+}
+
+/// Evenly spread windows of the range, a short range is sampled whole
+fn sample_windows(from_block: u64, to_block: u64) -> Vec<(u64, u64)> {
+    let blocks = to_block - from_block + 1;
+    if blocks <= SAMPLE_WINDOWS * SAMPLE_WINDOW_BLOCKS {
+        return vec![(from_block, to_block)];
+    }
+
+    let spacing = blocks / SAMPLE_WINDOWS;
+    (0..SAMPLE_WINDOWS)
+        .map(|window| {
+            let start = from_block + window * spacing;
+            (start, start + SAMPLE_WINDOW_BLOCKS - 1)
+        })
+        .collect()
+}
+
+/// A call per `max_block_range` blocks when one is set, and never fewer than the logs take
+fn estimated_rpc_calls(blocks: u64, logs: u64, max_block_range: Option<U64>) -> u64 {
+    let by_logs = logs.div_ceil(LOGS_PER_CALL);
+    let by_range = max_block_range.map_or(1, |range| blocks.div_ceil(range.as_u64().max(1)));
+    by_logs.max(by_range).max(1)
+}
+
+struct Sample {
+    blocks: u64,
+    logs: u64,
+    bytes: u64,
+    calls: u64,
+    elapsed: Duration,
+}
+
+async fn sample_window(
+    provider: &JsonRpcCachedProvider,
+    network: &str,
+    topic_id: &H256,
+    event_name: &str,
+    setup: &IndexingContractSetup,
+    from_block: u64,
+    to_block: u64,
+) -> Result<Sample, EstimateError> {
+    let mut to_block = to_block;
+    let mut calls = 0;
+    let started = Instant::now();
+    loop {
+        let filter = RindexerEventFilter::new(
+            topic_id,
+            event_name,
+            setup,
+            U64::from(from_block),
+            U64::from(to_block),
+        )?;
+        calls += 1;
+        match provider.get_logs(&filter).await {
+            Ok(logs) => {
+                let bytes = logs
+                    .iter()
+                    .map(|log| {
+                        ROW_OVERHEAD_BYTES +
+                            log.inner.data.len() as u64 +
+                            log.inner.topics.len() as u64 * 32
+                    })
+                    .sum();
+                return Ok(Sample {
+                    blocks: to_block - from_block + 1,
+                    logs: logs.len() as u64,
+                    bytes,
+                    calls,
+                    elapsed: started.elapsed(),
+                });
+            }
+            // too many logs in the window for the node, a smaller one still gives the density
+            Err(_) if to_block > from_block => to_block = from_block + (to_block - from_block) / 2,
+            Err(e) => return Err(EstimateError::Rpc(network.to_string(), e)),
+        }
+    }
+}
+
+async fn estimate_event(
+    provider: &JsonRpcCachedProvider,
+    contract_name: &str,
+    network: &str,
+    setup: &IndexingContractSetup,
+    event: &EventInfo,
+    from_block: U64,
+    to_block: U64,
+) -> Result<EventEstimate, EstimateError> {
+    let mut sampled = Sample { blocks: 0, logs: 0, bytes: 0, calls: 0, elapsed: Duration::ZERO };
+    if from_block <= to_block {
+        for (window_from, window_to) in sample_windows(from_block.as_u64(), to_block.as_u64()) {
+            let sample = sample_window(
+                provider,
+                network,
+                &event.topic_id(),
+                &event.name,
+                setup,
+                window_from,
+                window_to,
+            )
+            .await?;
+            sampled.blocks += sample.blocks;
+            sampled.logs += sample.logs;
+            sampled.bytes += sample.bytes;
+            sampled.calls += sample.calls;
+            sampled.elapsed += sample.elapsed;
+        }
+    }
+
+    let blocks = if from_block <= to_block { (to_block - from_block).as_u64() + 1 } else { 0 };
+    let scale = if sampled.blocks == 0 { 0.0 } else { blocks as f64 / sampled.blocks as f64 };
+    let logs = (sampled.logs as f64 * scale).round() as u64;
+    let rpc_calls = estimated_rpc_calls(blocks, logs, provider.max_block_range());
+    let call_duration =
+        if sampled.calls == 0 { Duration::ZERO } else { sampled.elapsed / sampled.calls as u32 };
+
+    Ok(EventEstimate {
+        contract_name: contract_name.to_string(),
+        event_name: event.name.clone(),
+        network: network.to_string(),
+        from_block,
+        to_block,
+        sampled_blocks: sampled.blocks,
+        sampled_logs: sampled.logs,
+        logs,
+        rpc_calls,
+        storage_bytes: (sampled.bytes as f64 * scale).round() as u64,
+        duration: call_duration * rpc_calls.min(u32::MAX as u64) as u32,
+    })
+}
+
+/// Samples the log density of every event over its configured range without writing anything,
+/// the time assumes the calls of an event run one after another as historic indexing does
+pub async fn estimate_manifest(
+    project_path: &Path,
+    manifest: &Manifest,
+) -> Result<Vec<EventEstimate>, EstimateError> {
+    let network_providers = CreateNetworkProvider::create(manifest)?;
+
+    let mut estimates = vec![];
+    for contract in &manifest.contracts {
+        let mut contract = contract.clone();
+        let is_filter = contract.identify_and_modify_filter();
+        let abi_items = ABIItem::get_abi_items(project_path, &contract, is_filter)?;
+        let events = ABIItem::extract_event_names_and_signatures_from_abi(abi_items)?;
+
+        for details in &contract.details {
+            let provider = &network_providers
+                .iter()
+                .find(|provider| provider.network_name == details.network)
+                .ok_or_else(|| EstimateError::UnknownNetwork(details.network.clone()))?
+                .client;
+            let head = provider
+                .get_block_number()
+                .await
+                .map_err(|e| EstimateError::Rpc(details.network.clone(), e))?;
+            let to_block = details.end_block.unwrap_or(head).min(head);
+            // without a start block indexing starts at the head so there is nothing to backfill
+            let from_block = details.start_block.unwrap_or(head);
+            let setup = details.indexing_contract_setup();
+
+            for event in &events {
+                estimates.push(
+                    estimate_event(
+                        provider,
+                        &contract.name,
+                        &details.network,
+                        &setup,
+                        event,
+                        from_block,
+                        to_block,
+                    )
+                    .await?,
+                );
+            }
+        }
+    }
+
+    Ok(estimates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_windows() {
+        assert_eq!(sample_windows(100, 2_000), vec![(100, 2_000)]);
+        assert_eq!(
+            sample_windows(0, 99_999),
+            vec![(0, 999), (20_000, 20_999), (40_000, 40_999), (60_000, 60_999), (80_000, 80_999)]
+        );
+    }
+
+    #[test]
+    fn test_estimated_rpc_calls() {
+        assert_eq!(estimated_rpc_calls(1_000_000, 0, None), 1);
+        assert_eq!(estimated_rpc_calls(1_000_000, 25_000, None), 3);
+        assert_eq!(estimated_rpc_calls(1_000_000, 25_000, Some(U64::from(10_000))), 100);
+    }
+}
@@ -1,39 +1,63 @@
 use std::{error::Error, str::FromStr, sync::Arc, time::Duration};
 
 use ethers::{
-    addressbook::Address,
     middleware::MiddlewareError,
-    prelude::{BlockNumber, JsonRpcError, ValueOrArray, H256, U64},
+    prelude::{BlockNumber, JsonRpcError, ProviderError, H256, U64},
 };
+use futures::{stream, StreamExt, TryStreamExt};
 use regex::Regex;
 use tokio::{
-    sync::{mpsc, Semaphore},
+    sync::{mpsc, oneshot, Mutex},
     time::Instant,
 };
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::{debug, error, info, warn};
 
 use crate::{
-    event::{config::EventProcessingConfig, RindexerEventFilter},
-    indexer::{log_helpers::is_relevant_block, IndexingEventProgressStatus},
+    error_reporting::{report_error, ErrorCategory, ErrorContext},
+    event::{
+        config::EventProcessingConfig, contract_setup::IndexingContractSetup, RindexerEventFilter,
+    },
+    indexer::{
+        hypersync::HyperSyncClient,
+        log_helpers::is_relevant_block,
+        pause::{event_pause_key, wait_while_paused},
+        take_reorg_rewind, IndexingEventProgressStatus, IndexingEventsProgressState,
+    },
+    indexing_permits::IndexingPermits,
+    is_running,
     provider::{JsonRpcCachedProvider, WrappedLog},
+    runtime_tuning::LivePollingBackoff,
 };
 
+/// Ranges longer than this go straight to `eth_getLogs`, a scan costs a block request per block
+/// to save a single `eth_getLogs` so it only pays off for short ranges
+const LOGS_BLOOM_SCAN_MAX_BLOCKS: u64 = 100;
+
+/// How many blocks are fetched at the same time when scanning their logs blooms
+const LOGS_BLOOM_SCAN_CONCURRENCY: usize = 20;
+
 pub struct FetchLogsResult {
     pub logs: Vec<WrappedLog>,
     pub from_block: U64,
     pub to_block: U64,
 }
 
-pub fn fetch_logs_stream(
+/// Fetches the event from its start block up to the block the indexer saw as the head when it
+/// started. The receiver gets the block live indexing carries on from once every range is sent,
+/// it is dropped when fetching stops early on a shutdown or an error.
+pub fn historic_logs_stream(
     config: Arc<EventProcessingConfig>,
-    force_no_live_indexing: bool,
-) -> impl tokio_stream::Stream<Item = Result<FetchLogsResult, Box<dyn Error + Send>>> + Send + Unpin
-{
+) -> (
+    impl tokio_stream::Stream<Item = Result<FetchLogsResult, Box<dyn Error + Send>>> + Send + Unpin,
+    oneshot::Receiver<U64>,
+) {
     let (tx, rx) = mpsc::unbounded_channel();
+    let (handoff_tx, handoff_rx) = oneshot::channel();
 
     let initial_filter = config.to_event_filter().unwrap();
-    let contract_address = initial_filter.contract_address();
+    let pause_key =
+        event_pause_key(&config.indexer_name, &config.contract_name, &config.event_name);
 
     tokio::spawn(async move {
         let snapshot_to_block = initial_filter.get_to_block();
@@ -41,8 +65,9 @@ pub fn fetch_logs_stream(
         let mut current_filter = initial_filter;
 
         // add any max block range limitation before we start processing
-        let mut max_block_range_limitation =
-            config.network_contract.cached_provider.max_block_range;
+        let mut configured_max_block_range =
+            config.network_contract.cached_provider.max_block_range();
+        let mut max_block_range_limitation = configured_max_block_range;
         if max_block_range_limitation.is_some() {
             current_filter = current_filter.set_to_block(calculate_process_historic_log_to_block(
                 &from_block,
@@ -56,9 +81,85 @@ pub fn fetch_logs_stream(
                 max_block_range_limitation.unwrap()
             );
         }
+
+        if let Some(source) = &config.network_contract.historical_source {
+            let Some(next_from_block) = fetch_historic_logs_hypersync(
+                &HyperSyncClient::new(source),
+                &tx,
+                &config,
+                &pause_key,
+                current_filter.clone(),
+                snapshot_to_block,
+            )
+            .await
+            else {
+                return;
+            };
+
+            // anything hypersync could not cover carries on over the rpc
+            current_filter = current_filter.set_from_block(next_from_block).set_to_block(
+                calculate_process_historic_log_to_block(
+                    &next_from_block,
+                    &snapshot_to_block,
+                    &max_block_range_limitation,
+                ),
+            );
+        }
+
+        let mut retry_attempt = 0;
+        let mut split_by_deadline = false;
         while current_filter.get_from_block() <= snapshot_to_block {
-            let semaphore_client = Arc::clone(&config.semaphore);
-            let permit = semaphore_client.acquire_owned().await;
+            wait_while_paused(&pause_key, &config.info_log_name).await;
+            if !is_running() {
+                info!(
+                    "{} - Detected shutdown, stopping fetching new ranges",
+                    &config.info_log_name
+                );
+                return;
+            }
+
+            // pick up a max block range changed by a config reload
+            let max_block_range = config.network_contract.cached_provider.max_block_range();
+            if max_block_range != configured_max_block_range {
+                configured_max_block_range = max_block_range;
+                max_block_range_limitation = max_block_range;
+                split_by_deadline = false;
+                let from_block = current_filter.get_from_block();
+                current_filter =
+                    current_filter.set_to_block(calculate_process_historic_log_to_block(
+                        &from_block,
+                        &snapshot_to_block,
+                        &max_block_range_limitation,
+                    ));
+            }
+
+            current_filter =
+                current_filter.refresh_addresses(&config.network_contract.indexing_contract_setup);
+
+            // skip over the ranges a previous run completed past its checkpoint
+            if let Some(completed_ranges) = &config.completed_ranges {
+                let (gap_from_block, gap_until) =
+                    completed_ranges.next_gap(current_filter.get_from_block()).await;
+                if gap_from_block > snapshot_to_block {
+                    break;
+                }
+                if gap_from_block != current_filter.get_from_block() {
+                    current_filter = current_filter.set_from_block(gap_from_block).set_to_block(
+                        calculate_process_historic_log_to_block(
+                            &gap_from_block,
+                            &snapshot_to_block,
+                            &max_block_range_limitation,
+                        ),
+                    );
+                }
+                if let Some(gap_until) = gap_until {
+                    if current_filter.get_to_block() > gap_until {
+                        current_filter = current_filter.set_to_block(gap_until);
+                    }
+                }
+            }
+
+            let permit = config.acquire_indexing_permit().await;
 
             match permit {
                 Ok(permit) => {
@@ -68,8 +169,13 @@ pub fn fetch_logs_stream(
                         &config.topic_id,
                         current_filter.clone(),
                         max_block_range_limitation,
+                        configured_max_block_range,
+                        split_by_deadline,
                         snapshot_to_block,
                         &config.info_log_name,
+                        retry_attempt,
+                        config.network_contract.logs_bloom_scan,
+                        config.error_context(),
                     )
                     .await;
 
@@ -84,11 +190,23 @@ pub fn fetch_logs_stream(
                         );
                     }
 
-                    if let Some(result) = result {
-                        current_filter = result.next;
-                        max_block_range_limitation = result.max_block_range_limitation;
-                    } else {
-                        break;
+                    match result {
+                        HistoricRange::Next(result) => {
+                            current_filter = result.next;
+                            max_block_range_limitation = result.max_block_range_limitation;
+                            split_by_deadline = result.split_by_deadline;
+                            retry_attempt = result.retry_attempt;
+                            if retry_attempt > 0 {
+                                let backoff = config
+                                    .network_contract
+                                    .cached_provider
+                                    .retry_policy()
+                                    .backoff(retry_attempt - 1);
+                                tokio::time::sleep(backoff).await;
+                            }
+                        }
+                        HistoricRange::Complete => break,
+                        HistoricRange::Stopped => return,
                     }
                 }
                 Err(e) => {
@@ -103,27 +221,52 @@ pub fn fetch_logs_stream(
             }
         }
 
-        info!(
-            "{} - {} - Finished indexing historic events",
+        debug!(
+            "{} - {} - Fetched every historic range up to block {}",
             &config.info_log_name,
-            IndexingEventProgressStatus::Completed.log()
+            IndexingEventProgressStatus::Syncing.log(),
+            snapshot_to_block
         );
 
-        // Live indexing mode
-        if config.live_indexing && !force_no_live_indexing {
-            live_indexing_stream(
-                &config.network_contract.cached_provider,
-                &tx,
-                &contract_address,
-                &config.topic_id,
-                &config.indexing_distance_from_head,
-                current_filter,
-                &config.info_log_name,
-                &config.semaphore,
-                config.network_contract.disable_logs_bloom_checks,
-            )
-            .await;
-        }
+        // every block up to and including the snapshot block has been sent so live indexing
+        // picks up right after it, nothing is skipped or fetched twice
+        let _ = handoff_tx.send(from_block.max(snapshot_to_block + 1));
+    });
+
+    (UnboundedReceiverStream::new(rx), handoff_rx)
+}
+
+/// Follows the head of the chain from the block the historic stage handed over at
+pub fn live_logs_stream(
+    config: Arc<EventProcessingConfig>,
+    from_block: U64,
+) -> impl tokio_stream::Stream<Item = Result<FetchLogsResult, Box<dyn Error + Send>>> + Send + Unpin
+{
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    let filter = config.to_event_filter().unwrap().set_from_block(from_block);
+    let pause_key =
+        event_pause_key(&config.indexer_name, &config.contract_name, &config.event_name);
+
+    tokio::spawn(async move {
+        live_indexing_stream(
+            &config.network_contract.cached_provider,
+            &tx,
+            &config.network_contract.indexing_contract_setup,
+            &config.topic_id,
+            &config.indexing_distance_from_head,
+            filter,
+            &config.info_log_name,
+            &pause_key,
+            &config.permits,
+            &config.id,
+            config.network_contract.disable_logs_bloom_checks,
+            &config.progress,
+            &config.network_contract.id,
+            &config.network_contract.network,
+            config.error_context(),
+        )
+        .await;
     });
 
     UnboundedReceiverStream::new(rx)
@@ -132,17 +275,61 @@ pub fn fetch_logs_stream(
 struct ProcessHistoricLogsStreamResult {
     pub next: RindexerEventFilter,
     pub max_block_range_limitation: Option<U64>,
+    /// The limitation comes from ranges which took too long, it grows back once they are fast
+    pub split_by_deadline: bool,
+    /// Above 0 when the range failed and should be fetched again after a backoff
+    pub retry_attempt: u32,
+}
+
+// handed back once per range and matched on straight away so it is not worth boxing
+#[allow(clippy::large_enum_variant)]
+enum HistoricRange {
+    Next(ProcessHistoricLogsStreamResult),
+    /// Every block up to the snapshot block has been sent
+    Complete,
+    /// Fetching failed or the consumer went away
+    Stopped,
+}
+
+/// The max block range once fetching `from_block` to `to_block` took too long, half the blocks of
+/// the range and never fewer than one
+fn split_max_block_range(from_block: U64, to_block: U64) -> U64 {
+    let blocks = to_block - from_block + 1;
+    (blocks / 2).max(U64::one()) - 1
 }
 
+/// The max block range once a range split for taking too long was fetched in time, twice the
+/// blocks until it is back at the configured max block range, or until it no longer limits the
+/// `remaining` blocks when there is none. Returns whether the ranges are still split.
+fn restore_max_block_range(
+    max_block_range_limitation: U64,
+    configured_max_block_range: Option<U64>,
+    remaining: U64,
+) -> (Option<U64>, bool) {
+    let grown = (max_block_range_limitation + 1) * 2 - 1;
+    match configured_max_block_range {
+        Some(configured) if grown < configured => (Some(grown), true),
+        Some(configured) => (Some(configured), false),
+        None if grown < remaining => (Some(grown), true),
+        None => (None, false),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn fetch_historic_logs_stream(
     cached_provider: &Arc<JsonRpcCachedProvider>,
     tx: &mpsc::UnboundedSender<Result<FetchLogsResult, Box<dyn Error + Send>>>,
     topic_id: &H256,
     current_filter: RindexerEventFilter,
     max_block_range_limitation: Option<U64>,
+    configured_max_block_range: Option<U64>,
+    split_by_deadline: bool,
     snapshot_to_block: U64,
     info_log_name: &str,
-) -> Option<ProcessHistoricLogsStreamResult> {
+    retry_attempt: u32,
+    logs_bloom_scan: bool,
+    error_context: ErrorContext<'_>,
+) -> HistoricRange {
     let from_block = current_filter.get_from_block();
     let to_block = current_filter.get_to_block();
     debug!(
@@ -162,9 +349,11 @@ async fn fetch_historic_logs_stream(
             to_block
         );
 
-        return Some(ProcessHistoricLogsStreamResult {
+        return HistoricRange::Next(ProcessHistoricLogsStreamResult {
             next: current_filter.set_from_block(to_block),
             max_block_range_limitation,
+            split_by_deadline,
+            retry_attempt: 0,
         });
     }
 
@@ -175,7 +364,74 @@ async fn fetch_historic_logs_stream(
         current_filter
     );
 
-    match cached_provider.get_logs(&current_filter).await {
+    let chunk_deadline = cached_provider.retry_policy().chunk_deadline;
+    let fetch_logs = async {
+        if scans_logs_blooms(logs_bloom_scan, from_block, to_block) {
+            match logs_bloom_range(cached_provider, &current_filter, topic_id).await {
+                Ok(Some((first_block, last_block))) => {
+                    cached_provider
+                        .get_logs(
+                            &current_filter
+                                .clone()
+                                .set_from_block(first_block)
+                                .set_to_block(last_block),
+                        )
+                        .await
+                }
+                Ok(None) => {
+                    debug!(
+                    "{} - {} - No logs bloom holds the event, skipping eth_getLogs - blocks: {} - {}",
+                    info_log_name,
+                    IndexingEventProgressStatus::Syncing.log(),
+                    from_block,
+                    to_block
+                );
+                    Ok(vec![])
+                }
+                Err(e) => {
+                    warn!(
+                    "{} - {} - Could not scan the logs blooms of blocks {} - {}, fetching the logs instead: {}",
+                    info_log_name,
+                    IndexingEventProgressStatus::Syncing.log(),
+                    from_block,
+                    to_block,
+                    e
+                );
+                    cached_provider.get_logs(&current_filter).await
+                }
+            }
+        } else {
+            cached_provider.get_logs(&current_filter).await
+        }
+    };
+
+    let logs = match tokio::time::timeout(chunk_deadline, fetch_logs).await {
+        Ok(logs) => logs,
+        // a stalled provider would hold the range forever, a smaller range has a better chance
+        Err(_) if to_block > from_block => {
+            let half_range = split_max_block_range(from_block, to_block);
+            warn!(
+                "{} - {} - Fetching blocks {} - {} took over {:?}, splitting the range",
+                info_log_name,
+                IndexingEventProgressStatus::Syncing.log(),
+                from_block,
+                to_block,
+                chunk_deadline
+            );
+            return HistoricRange::Next(ProcessHistoricLogsStreamResult {
+                next: current_filter.set_to_block(from_block + half_range),
+                max_block_range_limitation: Some(half_range),
+                split_by_deadline: true,
+                retry_attempt: 0,
+            });
+        }
+        Err(_) => Err(ProviderError::CustomError(format!(
+            "Fetching the logs of block {} took over {:?}",
+            from_block, chunk_deadline
+        ))),
+    };
+
+    match logs {
         Ok(logs) => {
             debug!(
                 "{} - {} - topic_id {}, Logs: {} from {} to {}",
@@ -206,9 +462,20 @@ async fn fetch_historic_logs_stream(
                     IndexingEventProgressStatus::Syncing.log(),
                     info_log_name
                 );
-                return None;
+                return HistoricRange::Stopped;
             }
 
+            // the range came back in time so the next ones can be larger again
+            let (max_block_range_limitation, split_by_deadline) =
+                match max_block_range_limitation.filter(|_| split_by_deadline) {
+                    Some(limitation) => restore_max_block_range(
+                        limitation,
+                        configured_max_block_range,
+                        snapshot_to_block.saturating_sub(to_block),
+                    ),
+                    None => (max_block_range_limitation, false),
+                };
+
             if logs_empty {
                 info!(
                     "{} - No events found between blocks {} - {}",
@@ -216,7 +483,7 @@ async fn fetch_historic_logs_stream(
                 );
                 let next_from_block = to_block + 1;
                 return if next_from_block > snapshot_to_block {
-                    None
+                    HistoricRange::Complete
                 } else {
                     let new_to_block = calculate_process_historic_log_to_block(
                         &next_from_block,
@@ -232,11 +499,13 @@ async fn fetch_historic_logs_stream(
                         new_to_block
                     );
 
-                    Some(ProcessHistoricLogsStreamResult {
+                    HistoricRange::Next(ProcessHistoricLogsStreamResult {
                         next: current_filter
                             .set_from_block(next_from_block)
                             .set_to_block(new_to_block),
                         max_block_range_limitation,
+                        split_by_deadline,
+                        retry_attempt: 0,
                     })
                 };
             }
@@ -254,7 +523,7 @@ async fn fetch_historic_logs_stream(
                     next_from_block
                 );
                 return if next_from_block > snapshot_to_block {
-                    None
+                    HistoricRange::Complete
                 } else {
                     let new_to_block = calculate_process_historic_log_to_block(
                         &next_from_block,
@@ -270,11 +539,13 @@ async fn fetch_historic_logs_stream(
                         new_to_block
                     );
 
-                    Some(ProcessHistoricLogsStreamResult {
+                    HistoricRange::Next(ProcessHistoricLogsStreamResult {
                         next: current_filter
                             .set_from_block(next_from_block)
                             .set_to_block(new_to_block),
                         max_block_range_limitation,
+                        split_by_deadline,
+                        retry_attempt: 0,
                     })
                 };
             }
@@ -290,28 +561,175 @@ async fn fetch_historic_logs_stream(
                         IndexingEventProgressStatus::Syncing.log(),
                         retry_result
                     );
-                    return Some(ProcessHistoricLogsStreamResult {
+                    return HistoricRange::Next(ProcessHistoricLogsStreamResult {
                         next: current_filter
                             .set_from_block(retry_result.from)
                             .set_to_block(retry_result.to),
                         max_block_range_limitation: retry_result.max_block_range,
+                        split_by_deadline: false,
+                        retry_attempt: 0,
                     });
                 }
             }
 
+            let max_retries = cached_provider.retry_policy().max_retries;
+            if retry_attempt < max_retries {
+                warn!(
+                    "{} - {} - Error fetching logs, retrying blocks {} - {} (attempt {}/{}): {}",
+                    info_log_name,
+                    IndexingEventProgressStatus::Syncing.log(),
+                    from_block,
+                    to_block,
+                    retry_attempt + 1,
+                    max_retries,
+                    err
+                );
+                return HistoricRange::Next(ProcessHistoricLogsStreamResult {
+                    next: current_filter,
+                    max_block_range_limitation,
+                    split_by_deadline,
+                    retry_attempt: retry_attempt + 1,
+                });
+            }
+
             error!(
                 "{} - {} - Error fetching logs: {}",
                 info_log_name,
                 IndexingEventProgressStatus::Syncing.log(),
                 err
             );
+            report_error(
+                ErrorCategory::Provider,
+                &format!("{} - historic fetch logs failed: {}", info_log_name, err),
+                ErrorContext {
+                    from_block: Some(from_block),
+                    to_block: Some(to_block),
+                    ..error_context
+                },
+            );
 
             let _ = tx.send(Err(Box::new(err)));
+            return HistoricRange::Stopped;
+        }
+    }
+
+    HistoricRange::Complete
+}
+
+/// Whether the logs blooms of the range are checked before calling `eth_getLogs`
+fn scans_logs_blooms(logs_bloom_scan: bool, from_block: U64, to_block: U64) -> bool {
+    logs_bloom_scan && to_block - from_block < U64::from(LOGS_BLOOM_SCAN_MAX_BLOCKS)
+}
+
+/// The first and last block of the range whose logs bloom can hold the event, `None` when no
+/// block can. Blooms have no false negatives so every log of the range is between the two, a
+/// block without a bloom is treated as holding the event.
+async fn logs_bloom_range(
+    cached_provider: &JsonRpcCachedProvider,
+    filter: &RindexerEventFilter,
+    topic_id: &H256,
+) -> Result<Option<(U64, U64)>, ProviderError> {
+    let address = &filter.raw_filter().address;
+    let relevant_blocks: Vec<Option<U64>> =
+        stream::iter(filter.get_from_block().as_u64()..=filter.get_to_block().as_u64())
+            .map(|block_number| async move {
+                let block_number = U64::from(block_number);
+                let relevant = match cached_provider.get_block(block_number).await? {
+                    Some(block) => {
+                        block.logs_bloom.is_none() || is_relevant_block(address, topic_id, &block)
+                    }
+                    None => true,
+                };
+                Ok::<_, ProviderError>(relevant.then_some(block_number))
+            })
+            .buffer_unordered(LOGS_BLOOM_SCAN_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+    let relevant_blocks = relevant_blocks.into_iter().flatten();
+    Ok(relevant_blocks.clone().min().zip(relevant_blocks.max()))
+}
+
+/// Fetches the historic range from hypersync, returns the block to carry on from over the rpc
+/// or `None` when the stream should stop
+async fn fetch_historic_logs_hypersync(
+    client: &HyperSyncClient,
+    tx: &mpsc::UnboundedSender<Result<FetchLogsResult, Box<dyn Error + Send>>>,
+    config: &EventProcessingConfig,
+    pause_key: &str,
+    filter: RindexerEventFilter,
+    snapshot_to_block: U64,
+) -> Option<U64> {
+    let mut from_block = filter.get_from_block();
+    let mut filter = filter.set_to_block(snapshot_to_block);
+
+    info!(
+        "{} - {} - Fetching historic events from hypersync - blocks: {} - {}",
+        config.info_log_name,
+        IndexingEventProgressStatus::Syncing.log(),
+        from_block,
+        snapshot_to_block
+    );
+
+    while from_block <= snapshot_to_block {
+        wait_while_paused(pause_key, &config.info_log_name).await;
+        if !is_running() {
+            info!("{} - Detected shutdown, stopping fetching new ranges", &config.info_log_name);
             return None;
         }
+
+        let permit = config.acquire_indexing_permit().await;
+        let result = client.get_logs(filter.raw_filter()).await;
+        drop(permit);
+
+        match result {
+            Ok(result) => {
+                debug!(
+                    "{} - {} - Fetched {} event logs from hypersync - blocks: {} - {}",
+                    config.info_log_name,
+                    IndexingEventProgressStatus::Syncing.log(),
+                    result.logs.len(),
+                    from_block,
+                    result.to_block
+                );
+
+                let to_block = result.to_block;
+                if tx.send(Ok(FetchLogsResult { logs: result.logs, from_block, to_block })).is_err()
+                {
+                    error!(
+                        "{} - {} - Failed to send logs to stream consumer!",
+                        IndexingEventProgressStatus::Syncing.log(),
+                        config.info_log_name
+                    );
+                    return None;
+                }
+
+                from_block = to_block + 1;
+                filter = filter.set_from_block(from_block);
+            }
+            Err(e) => {
+                warn!(
+                    "{} - {} - HyperSync failed from block {}, falling back to the rpc: {}",
+                    config.info_log_name,
+                    IndexingEventProgressStatus::Syncing.log(),
+                    from_block,
+                    e
+                );
+                report_error(
+                    ErrorCategory::Provider,
+                    &format!("{} - hypersync fetch logs failed: {}", config.info_log_name, e),
+                    ErrorContext {
+                        from_block: Some(from_block),
+                        to_block: Some(snapshot_to_block),
+                        ..config.error_context()
+                    },
+                );
+                break;
+            }
+        }
     }
 
-    None
+    Some(from_block)
 }
 
 /// Handles live indexing mode, continuously checking for new blocks, ensuring they are
@@ -320,29 +738,59 @@ async fn fetch_historic_logs_stream(
 async fn live_indexing_stream(
     cached_provider: &Arc<JsonRpcCachedProvider>,
     tx: &mpsc::UnboundedSender<Result<FetchLogsResult, Box<dyn Error + Send>>>,
-    contract_address: &Option<ValueOrArray<Address>>,
+    indexing_contract_setup: &IndexingContractSetup,
     topic_id: &H256,
     reorg_safe_distance: &U64,
     mut current_filter: RindexerEventFilter,
     info_log_name: &str,
-    semaphore: &Arc<Semaphore>,
+    pause_key: &str,
+    permits: &Arc<IndexingPermits>,
+    permit_key: &str,
     disable_logs_bloom_checks: bool,
+    progress: &Mutex<IndexingEventsProgressState>,
+    progress_id: &str,
+    network: &str,
+    error_context: ErrorContext<'_>,
 ) {
     let mut last_seen_block_number = U64::from(0);
 
     // this is used for less busy chains to make sure they know rindexer is still alive
     let mut last_no_new_block_log_time = Instant::now();
     let log_no_new_block_interval = Duration::from_secs(300);
+    let mut polling = LivePollingBackoff::default();
 
     loop {
-        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        tokio::time::sleep(polling.interval()).await;
+        wait_while_paused(pause_key, info_log_name).await;
+
+        if !is_running() {
+            info!("{} - Detected shutdown, stopping live indexing", info_log_name);
+            break;
+        }
+
+        // the rows of a reorged block were removed so the block replacing it is fetched again
+        if let Some(rewind_to) = take_reorg_rewind(pause_key, network) {
+            if rewind_to < current_filter.get_from_block() {
+                warn!(
+                    "{} - {} - Block {} was reorged, fetching the events again from it",
+                    info_log_name,
+                    IndexingEventProgressStatus::Live.log(),
+                    rewind_to
+                );
+                current_filter = current_filter.set_from_block(rewind_to);
+                last_seen_block_number = U64::zero();
+            }
+        }
 
         let latest_block = cached_provider.get_latest_block().await;
         match latest_block {
             Ok(latest_block) => {
                 if let Some(latest_block) = latest_block {
                     if let Some(latest_block_number) = latest_block.number {
+                        progress.lock().await.record_head(progress_id, latest_block_number);
+
                         if last_seen_block_number == latest_block_number {
+                            polling.no_new_block();
                             debug!(
                                 "{} - {} - No new blocks to process...",
                                 info_log_name,
@@ -357,8 +805,10 @@ async fn live_indexing_stream(
                                 );
                                 last_no_new_block_log_time = Instant::now();
                             }
+                            progress.lock().await.record_live_poll(progress_id);
                             continue;
                         }
+                        polling.new_block();
                         debug!(
                             "{} - {} - New block seen {} - Last seen block {}",
                             info_log_name,
@@ -378,13 +828,20 @@ async fn live_indexing_stream(
                                 from_block,
                                 safe_block_number
                             );
+                            progress.lock().await.record_live_poll(progress_id);
                             continue;
                         }
 
+                        current_filter = current_filter.refresh_addresses(indexing_contract_setup);
+
                         let to_block = safe_block_number;
                         if from_block == to_block &&
                             !disable_logs_bloom_checks &&
-                            !is_relevant_block(contract_address, topic_id, &latest_block)
+                            !is_relevant_block(
+                                &current_filter.contract_address(),
+                                topic_id,
+                                &latest_block,
+                            )
                         {
                             debug!(
                                 "{} - {} - Skipping block {} as it's not relevant",
@@ -400,6 +857,7 @@ async fn live_indexing_stream(
                             );
                             current_filter = current_filter.set_from_block(to_block + 1);
                             last_seen_block_number = to_block;
+                            progress.lock().await.record_fetched_range(progress_id, to_block);
                             continue;
                         }
 
@@ -412,11 +870,22 @@ async fn live_indexing_stream(
                             current_filter
                         );
 
-                        let semaphore_client = Arc::clone(semaphore);
-                        let permit = semaphore_client.acquire_owned().await;
+                        let permit = permits.acquire(permit_key).await;
 
                         if let Ok(permit) = permit {
-                            match cached_provider.get_logs(&current_filter).await {
+                            let chunk_deadline = cached_provider.retry_policy().chunk_deadline;
+                            let logs = tokio::time::timeout(
+                                chunk_deadline,
+                                cached_provider.get_logs(&current_filter),
+                            )
+                            .await
+                            .unwrap_or_else(|_| {
+                                Err(ProviderError::CustomError(format!(
+                                    "Fetching the logs of blocks {} - {} took over {:?}",
+                                    from_block, to_block, chunk_deadline
+                                )))
+                            });
+                            match logs {
                                 Ok(logs) => {
                                     debug!(
                                         "{} - {} - Live topic_id {}, Logs: {} from {} to {}",
@@ -487,6 +956,18 @@ async fn live_indexing_stream(
                                         IndexingEventProgressStatus::Live.log(),
                                         err
                                     );
+                                    report_error(
+                                        ErrorCategory::Provider,
+                                        &format!(
+                                            "{} - live fetch logs failed: {}",
+                                            info_log_name, err
+                                        ),
+                                        ErrorContext {
+                                            from_block: Some(from_block),
+                                            to_block: Some(to_block),
+                                            ..error_context
+                                        },
+                                    );
                                     drop(permit);
                                 }
                             }
@@ -641,3 +1122,52 @@ fn calculate_process_historic_log_to_block(
         *snapshot_to_block
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_short_ranges_scan_logs_blooms() {
+        let from_block = U64::from(1_000);
+
+        assert!(scans_logs_blooms(true, from_block, from_block));
+        assert!(scans_logs_blooms(true, from_block, from_block + LOGS_BLOOM_SCAN_MAX_BLOCKS - 1));
+        assert!(!scans_logs_blooms(true, from_block, from_block + LOGS_BLOOM_SCAN_MAX_BLOCKS));
+        assert!(!scans_logs_blooms(false, from_block, from_block));
+    }
+
+    #[test]
+    fn test_split_ranges_grow_back_to_the_configured_max_block_range() {
+        let remaining = U64::from(1_000_000);
+
+        // 100 blocks split into 50, 2 blocks into 1 which is as small as it gets
+        assert_eq!(split_max_block_range(U64::from(100), U64::from(199)), U64::from(49));
+        assert_eq!(split_max_block_range(U64::from(100), U64::from(101)), U64::zero());
+
+        // a single block range doubles back up to the configured 1000 blocks
+        let mut limitation = U64::zero();
+        let mut split = true;
+        let mut fetches = 0;
+        while split {
+            let (restored, still_split) =
+                restore_max_block_range(limitation, Some(U64::from(1000)), remaining);
+            limitation = restored.unwrap();
+            split = still_split;
+            fetches += 1;
+        }
+        assert_eq!(limitation, U64::from(1000));
+        assert_eq!(fetches, 10);
+
+        assert_eq!(
+            restore_max_block_range(U64::from(49), Some(U64::from(1000)), remaining),
+            (Some(U64::from(99)), true)
+        );
+        // without a configured max block range the limitation goes once it covers the rest
+        assert_eq!(
+            restore_max_block_range(U64::from(49), None, remaining),
+            (Some(U64::from(99)), true)
+        );
+        assert_eq!(restore_max_block_range(U64::from(49), None, U64::from(99)), (None, false));
+    }
+}
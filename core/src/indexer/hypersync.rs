@@ -0,0 +1,197 @@
+use ethers::types::{Address, Bytes, Filter, Log, ValueOrArray, H256, U256, U64};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::{
+    manifest::network::HistoricalSource,
+    provider::{sort_logs, WrappedLog},
+};
+
+const LOG_FIELDS: [&str; 12] = [
+    "removed",
+    "log_index",
+    "transaction_index",
+    "transaction_hash",
+    "block_hash",
+    "block_number",
+    "address",
+    "data",
+    "topic0",
+    "topic1",
+    "topic2",
+    "topic3",
+];
+
+#[derive(Error, Debug)]
+pub enum HyperSyncError {
+    #[error("HyperSync request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("HyperSync returned status {0}: {1}")]
+    Status(u16, String),
+
+    #[error("HyperSync has not indexed block {0} yet")]
+    NotSynced(U64),
+}
+
+#[derive(Deserialize)]
+struct HyperSyncLog {
+    #[serde(default)]
+    removed: Option<bool>,
+    log_index: u64,
+    transaction_index: u64,
+    transaction_hash: H256,
+    block_hash: H256,
+    block_number: u64,
+    address: Address,
+    data: Bytes,
+    topic0: Option<H256>,
+    topic1: Option<H256>,
+    topic2: Option<H256>,
+    topic3: Option<H256>,
+}
+
+impl From<HyperSyncLog> for WrappedLog {
+    fn from(log: HyperSyncLog) -> Self {
+        WrappedLog {
+            inner: Log {
+                address: log.address,
+                topics: [log.topic0, log.topic1, log.topic2, log.topic3]
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+                data: log.data,
+                block_hash: Some(log.block_hash),
+                block_number: Some(U64::from(log.block_number)),
+                transaction_hash: Some(log.transaction_hash),
+                transaction_index: Some(U64::from(log.transaction_index)),
+                log_index: Some(U256::from(log.log_index)),
+                transaction_log_index: None,
+                log_type: None,
+                removed: log.removed,
+            },
+            block_timestamp: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct HyperSyncBatch {
+    #[serde(default)]
+    logs: Vec<HyperSyncLog>,
+}
+
+#[derive(Deserialize)]
+struct HyperSyncResponse {
+    #[serde(default)]
+    data: Vec<HyperSyncBatch>,
+    next_block: u64,
+}
+
+pub struct HyperSyncLogs {
+    pub logs: Vec<WrappedLog>,
+    /// The last block the response covered, HyperSync stops early when a response gets too big
+    pub to_block: U64,
+}
+
+pub struct HyperSyncClient {
+    http: Client,
+    url: String,
+    api_token: Option<String>,
+}
+
+impl HyperSyncClient {
+    pub fn new(source: &HistoricalSource) -> Self {
+        match source {
+            HistoricalSource::HyperSync { url, api_token } => Self {
+                http: Client::new(),
+                url: format!("{}/query", url.trim_end_matches('/')),
+                api_token: api_token.clone(),
+            },
+        }
+    }
+
+    /// Returns the logs matching the filter from its from block, the response may stop before
+    /// the filter to block so callers should carry on from the returned `to_block`
+    pub async fn get_logs(&self, filter: &Filter) -> Result<HyperSyncLogs, HyperSyncError> {
+        let from_block = filter.get_from_block().unwrap_or_default();
+        let to_block = filter.get_to_block().unwrap_or_default();
+
+        let mut request =
+            self.http.post(&self.url).json(&build_query(filter, from_block, to_block));
+        if let Some(api_token) = &self.api_token {
+            request = request.bearer_auth(api_token);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            return Err(HyperSyncError::Status(status, response.text().await.unwrap_or_default()));
+        }
+
+        let response: HyperSyncResponse = response.json().await?;
+
+        let mut logs: Vec<WrappedLog> =
+            response.data.into_iter().flat_map(|batch| batch.logs).map(WrappedLog::from).collect();
+        sort_logs(&mut logs);
+
+        // next_block is exclusive so when it has not moved past the from block nothing was covered
+        if response.next_block <= from_block.as_u64() {
+            return Err(HyperSyncError::NotSynced(from_block));
+        }
+        let covered_to = U64::from(response.next_block - 1);
+
+        Ok(HyperSyncLogs { logs, to_block: covered_to.min(to_block) })
+    }
+}
+
+fn topic_selection(topic: &Option<ValueOrArray<Option<H256>>>) -> Vec<H256> {
+    match topic {
+        None | Some(ValueOrArray::Value(None)) => vec![],
+        Some(ValueOrArray::Value(Some(topic))) => vec![*topic],
+        Some(ValueOrArray::Array(topics)) => topics.iter().flatten().copied().collect(),
+    }
+}
+
+fn build_query(filter: &Filter, from_block: U64, to_block: U64) -> Value {
+    let addresses: Vec<Address> = match &filter.address {
+        None => vec![],
+        Some(ValueOrArray::Value(address)) => vec![*address],
+        Some(ValueOrArray::Array(addresses)) => addresses.clone(),
+    };
+    let topics: Vec<Vec<H256>> = filter.topics.iter().map(topic_selection).collect();
+
+    json!({
+        "from_block": from_block.as_u64(),
+        // hypersync treats the to block as exclusive
+        "to_block": to_block.as_u64() + 1,
+        "logs": [{ "address": addresses, "topics": topics }],
+        "field_selection": { "log": LOG_FIELDS },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_query() {
+        let topic0 = H256::repeat_byte(1);
+        let topic2 = H256::repeat_byte(2);
+        let filter = Filter::new()
+            .address(Address::repeat_byte(3))
+            .topic0(topic0)
+            .topic2(vec![topic2])
+            .from_block(10u64)
+            .to_block(20u64);
+
+        let query = build_query(&filter, U64::from(10), U64::from(20));
+
+        assert_eq!(query["from_block"], 10);
+        assert_eq!(query["to_block"], 21);
+        assert_eq!(query["logs"][0]["address"].as_array().unwrap().len(), 1);
+        assert_eq!(query["logs"][0]["topics"], json!([[topic0], [], [topic2], []]));
+    }
+}
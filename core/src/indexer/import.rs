@@ -0,0 +1,559 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arrow_array::{
+    cast::AsArray,
+    types::{
+        Int32Type, Int64Type, TimestampMicrosecondType, TimestampMillisecondType,
+        TimestampNanosecondType, TimestampSecondType, UInt32Type, UInt64Type,
+    },
+    Array, ArrayRef, RecordBatch,
+};
+use arrow_schema::{DataType, TimeUnit};
+use ethers::{
+    abi::{Abi, Event, RawLog},
+    types::{Address, Bytes, Log, ValueOrArray, H256, U256, U64},
+};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use tracing::{info, warn};
+
+use crate::{
+    database::postgres::{
+        client::PostgresError,
+        setup::{setup_postgres, SetupPostgresError},
+        sql_type_wrapper::EthereumSqlTypeWrapper,
+    },
+    event::{
+        callback_registry::{EventCallbackRegistry, EventResult},
+        contract_setup::NetworkContract,
+    },
+    helpers::camel_to_snake,
+    indexer::no_code::{process_events, ProcessIndexersError},
+    manifest::{contract::ParseAbiError, core::Manifest},
+    provider::{CreateNetworkProvider, RetryClientError, WrappedLog},
+};
+
+/// How many rows of an event go to its handler at once
+const IMPORT_BATCH_SIZE: usize = 1000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ImportError {
+    #[error("Importing writes into the event tables so needs postgres storage enabled")]
+    PostgresNotEnabled,
+
+    #[error("drop_each_run is enabled, the imported rows would be dropped on the next start")]
+    DropEachRun,
+
+    #[error("Contract {0} is not in the manifest")]
+    UnknownContract(String),
+
+    #[error("Contract {0} is not indexed on network {1}")]
+    UnknownNetwork(String, String),
+
+    #[error("Could not read the ABI: {0}")]
+    ParseAbi(#[from] ParseAbiError),
+
+    #[error("Could not parse the ABI: {0}")]
+    AbiJson(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    SetupPostgres(#[from] SetupPostgresError),
+
+    #[error("{0}")]
+    Provider(#[from] RetryClientError),
+
+    #[error("{0}")]
+    ProcessEvents(#[from] ProcessIndexersError),
+
+    #[error("Could not open {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+
+    #[error("Could not read the parquet file {0}: {1}")]
+    Parquet(PathBuf, parquet::errors::ParquetError),
+
+    #[error("Could not read the parquet file {0}: {1}")]
+    Arrow(PathBuf, arrow_schema::ArrowError),
+
+    #[error("{0} has no {1} column")]
+    MissingColumn(PathBuf, &'static str),
+
+    #[error("{0} holds logs of chain {2} but the network is chain {1}")]
+    WrongChain(PathBuf, u64, u64),
+
+    #[error("Handler failed on imported rows of {0}: {1}")]
+    Handler(String, String),
+
+    #[error("Could not set the checkpoint: {0}")]
+    Checkpoint(#[from] PostgresError),
+}
+
+/// A log row as the public datasets lay them out, the BigQuery `crypto_ethereum.logs` table
+/// exported to parquet and cryo `logs` dumps share the column names apart from the topics
+#[derive(Debug, Clone, PartialEq)]
+struct DatasetLog {
+    block_number: u64,
+    block_hash: H256,
+    block_timestamp: Option<u64>,
+    transaction_hash: H256,
+    transaction_index: u64,
+    log_index: u64,
+    address: Address,
+    topics: Vec<H256>,
+    data: Vec<u8>,
+}
+
+impl DatasetLog {
+    fn into_wrapped_log(self) -> WrappedLog {
+        WrappedLog {
+            inner: Log {
+                address: self.address,
+                topics: self.topics,
+                data: Bytes::from(self.data),
+                block_hash: Some(self.block_hash),
+                block_number: Some(U64::from(self.block_number)),
+                transaction_hash: Some(self.transaction_hash),
+                transaction_index: Some(U64::from(self.transaction_index)),
+                log_index: Some(U256::from(self.log_index)),
+                transaction_log_index: None,
+                log_type: None,
+                removed: Some(false),
+            },
+            block_timestamp: self.block_timestamp.map(U256::from),
+        }
+    }
+}
+
+fn hex_bytes(value: &str) -> Option<Vec<u8>> {
+    hex::decode(value.trim().trim_start_matches("0x")).ok()
+}
+
+/// Binary columns as cryo writes them or hex strings as BigQuery and `cryo --hex` do
+fn bytes_at(column: &ArrayRef, row: usize) -> Option<Vec<u8>> {
+    if column.is_null(row) {
+        return None;
+    }
+    match column.data_type() {
+        DataType::Binary => Some(column.as_binary::<i32>().value(row).to_vec()),
+        DataType::LargeBinary => Some(column.as_binary::<i64>().value(row).to_vec()),
+        DataType::FixedSizeBinary(_) => Some(column.as_fixed_size_binary().value(row).to_vec()),
+        DataType::Utf8 => hex_bytes(column.as_string::<i32>().value(row)),
+        DataType::LargeUtf8 => hex_bytes(column.as_string::<i64>().value(row)),
+        _ => None,
+    }
+}
+
+fn h256_at(column: &ArrayRef, row: usize) -> Option<H256> {
+    bytes_at(column, row).filter(|bytes| bytes.len() == 32).map(|bytes| H256::from_slice(&bytes))
+}
+
+fn u64_at(column: &ArrayRef, row: usize) -> Option<u64> {
+    if column.is_null(row) {
+        return None;
+    }
+    match column.data_type() {
+        DataType::UInt32 => Some(column.as_primitive::<UInt32Type>().value(row) as u64),
+        DataType::UInt64 => Some(column.as_primitive::<UInt64Type>().value(row)),
+        DataType::Int32 => u64::try_from(column.as_primitive::<Int32Type>().value(row)).ok(),
+        DataType::Int64 => u64::try_from(column.as_primitive::<Int64Type>().value(row)).ok(),
+        DataType::Utf8 => column.as_string::<i32>().value(row).trim().parse().ok(),
+        DataType::Timestamp(unit, _) => {
+            let seconds = match unit {
+                TimeUnit::Second => column.as_primitive::<TimestampSecondType>().value(row),
+                TimeUnit::Millisecond => {
+                    column.as_primitive::<TimestampMillisecondType>().value(row) / 1_000
+                }
+                TimeUnit::Microsecond => {
+                    column.as_primitive::<TimestampMicrosecondType>().value(row) / 1_000_000
+                }
+                TimeUnit::Nanosecond => {
+                    column.as_primitive::<TimestampNanosecondType>().value(row) / 1_000_000_000
+                }
+            };
+            u64::try_from(seconds).ok()
+        }
+        _ => None,
+    }
+}
+
+/// BigQuery holds the topics as one list of hex strings
+fn topics_list_at(column: &ArrayRef, row: usize) -> Option<Vec<H256>> {
+    if column.is_null(row) {
+        return Some(vec![]);
+    }
+    let topics = match column.data_type() {
+        DataType::List(_) => column.as_list::<i32>().value(row),
+        DataType::LargeList(_) => column.as_list::<i64>().value(row),
+        _ => return None,
+    };
+    (0..topics.len()).map(|index| h256_at(&topics, index)).collect()
+}
+
+/// cryo holds the topics as `topic0` to `topic3`, unused ones are null
+fn topic_columns_at(columns: &[ArrayRef], row: usize) -> Option<Vec<H256>> {
+    let mut topics = vec![];
+    for column in columns {
+        if column.is_null(row) {
+            break;
+        }
+        topics.push(h256_at(column, row)?);
+    }
+    Some(topics)
+}
+
+/// The logs of the batch and how many rows could not be read
+fn read_dataset_logs(
+    path: &Path,
+    batch: &RecordBatch,
+) -> Result<(Vec<DatasetLog>, usize), ImportError> {
+    let column = |name: &'static str| {
+        batch
+            .column_by_name(name)
+            .ok_or_else(|| ImportError::MissingColumn(path.to_path_buf(), name))
+    };
+    let block_number = column("block_number")?;
+    let block_hash = column("block_hash")?;
+    let transaction_hash = column("transaction_hash")?;
+    let transaction_index = column("transaction_index")?;
+    let log_index = column("log_index")?;
+    let address = column("address")?;
+    let data = column("data")?;
+    let block_timestamp = batch.column_by_name("block_timestamp");
+    let topics_list = batch.column_by_name("topics");
+    let topic_columns: Vec<ArrayRef> = ["topic0", "topic1", "topic2", "topic3"]
+        .iter()
+        .map_while(|name| batch.column_by_name(name).cloned())
+        .collect();
+    if topics_list.is_none() && topic_columns.is_empty() {
+        return Err(ImportError::MissingColumn(path.to_path_buf(), "topics"));
+    }
+
+    let mut logs = Vec::with_capacity(batch.num_rows());
+    let mut unreadable = 0;
+    for row in 0..batch.num_rows() {
+        let topics = match topics_list {
+            Some(topics_list) => topics_list_at(topics_list, row),
+            None => topic_columns_at(&topic_columns, row),
+        };
+        let log = (|| {
+            Some(DatasetLog {
+                block_number: u64_at(block_number, row)?,
+                block_hash: h256_at(block_hash, row)?,
+                block_timestamp: block_timestamp.and_then(|column| u64_at(column, row)),
+                transaction_hash: h256_at(transaction_hash, row)?,
+                transaction_index: u64_at(transaction_index, row)?,
+                log_index: u64_at(log_index, row)?,
+                address: bytes_at(address, row)
+                    .filter(|bytes| bytes.len() == 20)
+                    .map(|bytes| Address::from_slice(&bytes))?,
+                topics: topics?,
+                data: bytes_at(data, row).unwrap_or_default(),
+            })
+        })();
+        match log {
+            Some(log) => logs.push(log),
+            None => unreadable += 1,
+        }
+    }
+
+    Ok((logs, unreadable))
+}
+
+/// The rows of the file which belong to the contract, `rejected` counts the ones which can not be
+/// read or look like the contract's events but do not decode with its ABI
+struct ImportedFile {
+    logs: Vec<DatasetLog>,
+    rejected: usize,
+}
+
+fn read_dataset_file(
+    path: &Path,
+    chain_id: u64,
+    addresses: Option<&[Address]>,
+    events: &HashMap<H256, Event>,
+) -> Result<ImportedFile, ImportError> {
+    let file = File::open(path).map_err(|e| ImportError::Io(path.to_path_buf(), e))?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .and_then(|builder| builder.build())
+        .map_err(|e| ImportError::Parquet(path.to_path_buf(), e))?;
+
+    let mut imported = ImportedFile { logs: vec![], rejected: 0 };
+    for batch in reader {
+        let batch = batch.map_err(|e| ImportError::Arrow(path.to_path_buf(), e))?;
+
+        // cryo keeps the chain of every row, a dump of another chain is not a partial match
+        if let Some(column) = batch.column_by_name("chain_id") {
+            if let Some(found) = (0..batch.num_rows()).find_map(|row| u64_at(column, row)) {
+                if found != chain_id {
+                    return Err(ImportError::WrongChain(path.to_path_buf(), chain_id, found));
+                }
+            }
+        }
+
+        let (logs, unreadable) = read_dataset_logs(path, &batch)?;
+        imported.rejected += unreadable;
+        for log in logs {
+            if addresses.is_some_and(|addresses| !addresses.contains(&log.address)) {
+                continue;
+            }
+            let Some(event) = log.topics.first().and_then(|topic| events.get(topic)) else {
+                continue;
+            };
+            let raw_log = RawLog { topics: log.topics.clone(), data: log.data.clone() };
+            if event.parse_log_whole(raw_log).is_err() {
+                imported.rejected += 1;
+                continue;
+            }
+            imported.logs.push(log);
+        }
+    }
+
+    imported.logs.sort_by_key(|log| (log.block_number, log.log_index));
+    Ok(imported)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub rejected: usize,
+    pub checkpoint: Option<U64>,
+}
+
+/// Seeds the event tables of a contract from parquet files of a public dataset, the BigQuery
+/// `crypto_ethereum.logs` table exported to parquet or cryo `logs` dumps, and moves the last
+/// synced block of the events to `to_block` so `rindexer start` carries on from there.
+///
+/// The rows go through the same handlers indexing uses, so the files have to hold every log of
+/// the contract from its start block up to `to_block`, which defaults to the highest block found.
+/// Rows which do not decode with the contract's ABI are left out and counted as rejected.
+pub async fn import_logs(
+    project_path: &Path,
+    manifest: &Manifest,
+    contract_name: &str,
+    network: &str,
+    files: &[PathBuf],
+    to_block: Option<U64>,
+) -> Result<ImportSummary, ImportError> {
+    if !manifest.storage.postgres_enabled() {
+        return Err(ImportError::PostgresNotEnabled);
+    }
+    if manifest.storage.postgres_drop_each_run() {
+        return Err(ImportError::DropEachRun);
+    }
+
+    let contract = manifest
+        .contracts
+        .iter()
+        .find(|contract| contract.name == contract_name)
+        .ok_or_else(|| ImportError::UnknownContract(contract_name.to_string()))?;
+    let details =
+        contract.details.iter().find(|details| details.network == network).ok_or_else(|| {
+            ImportError::UnknownNetwork(contract_name.to_string(), network.to_string())
+        })?;
+    let chain_id =
+        manifest.networks.iter().find(|n| n.name == network).map(|n| n.chain_id).ok_or_else(
+            || ImportError::UnknownNetwork(contract_name.to_string(), network.to_string()),
+        )?;
+    // filter contracts index the event wherever it is emitted
+    let addresses: Option<Vec<Address>> = details.address().map(|address| match address {
+        ValueOrArray::Value(address) => vec![*address],
+        ValueOrArray::Array(addresses) => addresses.clone(),
+    });
+
+    let abi: Abi = serde_json::from_str(&contract.parse_abi(project_path)?)?;
+
+    let postgres = Arc::new(setup_postgres(project_path, manifest).await?);
+
+    let mut import_manifest = manifest.clone();
+    import_manifest.contracts = vec![contract.clone()];
+    import_manifest.contracts[0].details = vec![details.clone()];
+    import_manifest.alerts = None;
+    import_manifest.modules = None;
+    let network_providers = CreateNetworkProvider::create(&import_manifest)?;
+    let registry = EventCallbackRegistry {
+        events: process_events(
+            project_path,
+            &mut import_manifest,
+            Some(Arc::clone(&postgres)),
+            &network_providers,
+        )
+        .await?,
+    };
+
+    let events: HashMap<H256, Event> = abi
+        .events()
+        .filter(|event| registry.events.iter().any(|e| e.topic_id == event.signature()))
+        .map(|event| (event.signature(), event.clone()))
+        .collect();
+    let network_contracts: HashMap<H256, Arc<NetworkContract>> = registry
+        .events
+        .iter()
+        .filter_map(|event| {
+            event
+                .contract
+                .details
+                .iter()
+                .find(|details| details.network == network)
+                .map(|details| (event.topic_id, Arc::new(details.clone())))
+        })
+        .collect();
+
+    let mut summary = ImportSummary::default();
+    let mut highest_block: Option<u64> = None;
+    for path in files {
+        let path = project_path.join(path);
+        let file_path = path.clone();
+        let file_addresses = addresses.clone();
+        let file_events = events.clone();
+        let imported = tokio::task::spawn_blocking(move || {
+            read_dataset_file(&file_path, chain_id, file_addresses.as_deref(), &file_events)
+        })
+        .await
+        .expect("read dataset file task panicked")?;
+
+        if let Some(last) = imported.logs.last() {
+            highest_block = highest_block.max(Some(last.block_number));
+        }
+        summary.rejected += imported.rejected;
+
+        let mut by_event: HashMap<H256, Vec<DatasetLog>> = HashMap::new();
+        for log in imported.logs {
+            by_event.entry(log.topics[0]).or_default().push(log);
+        }
+
+        for event in registry.events.iter() {
+            let Some(logs) = by_event.remove(&event.topic_id) else {
+                continue;
+            };
+            let network_contract = &network_contracts[&event.topic_id];
+            for chunk in logs.chunks(IMPORT_BATCH_SIZE) {
+                let from_block = U64::from(chunk[0].block_number);
+                let to_block = U64::from(chunk[chunk.len() - 1].block_number);
+                let results = chunk
+                    .iter()
+                    .map(|log| {
+                        EventResult::new(
+                            Arc::clone(network_contract),
+                            log.clone().into_wrapped_log(),
+                            from_block,
+                            to_block,
+                        )
+                    })
+                    .collect();
+                registry
+                    .trigger_event(&event.id, results, None)
+                    .await
+                    .map_err(|e| ImportError::Handler(event.info_log_name(), e.to_string()))?;
+                summary.imported += chunk.len();
+            }
+        }
+
+        info!(
+            "Imported {} - {} rows so far, {} rejected",
+            path.display(),
+            summary.imported,
+            summary.rejected
+        );
+    }
+
+    if summary.rejected > 0 {
+        warn!(
+            "{} rows could not be read or did not decode with the {} ABI and were left out",
+            summary.rejected, contract_name
+        );
+    }
+
+    let Some(checkpoint) = to_block.or(highest_block.map(U64::from)) else {
+        return Ok(summary);
+    };
+    for event in registry.events.iter() {
+        postgres
+            .execute(
+                &format!(
+                    "UPDATE rindexer_internal.{}_{}_{} SET last_synced_block = $1 WHERE network = $2 AND $1 > last_synced_block",
+                    camel_to_snake(&event.indexer_name),
+                    camel_to_snake(&event.contract.name),
+                    camel_to_snake(&event.event_name)
+                ),
+                &[&EthereumSqlTypeWrapper::U64(checkpoint), &network],
+            )
+            .await?;
+    }
+    summary.checkpoint = Some(checkpoint);
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{
+        builder::{ListBuilder, StringBuilder},
+        BinaryArray, Int64Array, StringArray, UInt32Array,
+    };
+
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        format!("0x{}", hex::encode(bytes))
+    }
+
+    #[test]
+    fn test_reads_cryo_and_bigquery_layouts_the_same() {
+        let address = Address::repeat_byte(1);
+        let block_hash = H256::repeat_byte(2);
+        let transaction_hash = H256::repeat_byte(3);
+        let topic0 = H256::repeat_byte(4);
+        let topic1 = H256::repeat_byte(5);
+        let data = vec![6u8; 32];
+
+        let cryo = RecordBatch::try_from_iter(vec![
+            ("block_number", Arc::new(UInt32Array::from(vec![10])) as ArrayRef),
+            ("block_hash", Arc::new(BinaryArray::from(vec![block_hash.as_bytes()])) as ArrayRef),
+            ("transaction_index", Arc::new(UInt32Array::from(vec![1])) as ArrayRef),
+            ("log_index", Arc::new(UInt32Array::from(vec![7])) as ArrayRef),
+            (
+                "transaction_hash",
+                Arc::new(BinaryArray::from(vec![transaction_hash.as_bytes()])) as ArrayRef,
+            ),
+            ("address", Arc::new(BinaryArray::from(vec![address.as_bytes()])) as ArrayRef),
+            ("topic0", Arc::new(BinaryArray::from(vec![Some(topic0.as_bytes())])) as ArrayRef),
+            ("topic1", Arc::new(BinaryArray::from(vec![Some(topic1.as_bytes())])) as ArrayRef),
+            ("topic2", Arc::new(BinaryArray::from(vec![None::<&[u8]>])) as ArrayRef),
+            ("data", Arc::new(BinaryArray::from(vec![data.as_slice()])) as ArrayRef),
+        ])
+        .unwrap();
+
+        let mut topics = ListBuilder::new(StringBuilder::new());
+        topics.values().append_value(hex(topic0.as_bytes()));
+        topics.values().append_value(hex(topic1.as_bytes()));
+        topics.append(true);
+        let bigquery = RecordBatch::try_from_iter(vec![
+            ("block_number", Arc::new(Int64Array::from(vec![10])) as ArrayRef),
+            (
+                "block_hash",
+                Arc::new(StringArray::from(vec![hex(block_hash.as_bytes())])) as ArrayRef,
+            ),
+            ("transaction_index", Arc::new(Int64Array::from(vec![1])) as ArrayRef),
+            ("log_index", Arc::new(Int64Array::from(vec![7])) as ArrayRef),
+            (
+                "transaction_hash",
+                Arc::new(StringArray::from(vec![hex(transaction_hash.as_bytes())])) as ArrayRef,
+            ),
+            ("address", Arc::new(StringArray::from(vec![hex(address.as_bytes())])) as ArrayRef),
+            ("topics", Arc::new(topics.finish()) as ArrayRef),
+            ("data", Arc::new(StringArray::from(vec![hex(&data)])) as ArrayRef),
+        ])
+        .unwrap();
+
+        let path = Path::new("logs.parquet");
+        let (cryo_logs, cryo_unreadable) = read_dataset_logs(path, &cryo).unwrap();
+        let (bigquery_logs, bigquery_unreadable) = read_dataset_logs(path, &bigquery).unwrap();
+        assert_eq!((cryo_unreadable, bigquery_unreadable), (0, 0));
+        assert_eq!(cryo_logs, bigquery_logs);
+        assert_eq!(cryo_logs[0].topics, vec![topic0, topic1]);
+        assert_eq!(cryo_logs[0].address, address);
+    }
+}
@@ -10,18 +10,69 @@ use tokio::{
 use tracing::error;
 
 use crate::{
+    database::postgres::client::{PostgresError, ToSql},
+    error_reporting::{report_error, ErrorCategory, ErrorContext},
     event::config::EventProcessingConfig,
     helpers::{camel_to_snake, get_full_path},
     manifest::{storage::CsvDetails, stream::StreamsConfig},
     EthereumSqlTypeWrapper, PostgresClient,
 };
 
+/// How far indexing of an event got, every block up to `block` is processed along with the logs
+/// of the block after it up to `next_block_log_index`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Checkpoint {
+    pub block: U64,
+    pub next_block_log_index: Option<U64>,
+}
+
+impl Checkpoint {
+    pub fn block(block: U64) -> Self {
+        Self { block, next_block_log_index: None }
+    }
+
+    /// The checkpoint after handling the logs of a block up to `log_index`, `None` for the
+    /// genesis block which has no block before it
+    pub fn within_block(block: U64, log_index: U64) -> Option<Self> {
+        block
+            .checked_sub(U64::one())
+            .map(|before| Self { block: before, next_block_log_index: Some(log_index) })
+    }
+
+    /// The block to carry on from and the last log of it already handled
+    pub fn resume_from(&self) -> (U64, Option<(U64, U64)>) {
+        let next_block = self.block + 1;
+        (next_block, self.next_block_log_index.map(|log_index| (next_block, log_index)))
+    }
+
+    /// Files hold the block alone or the block and the log index separated by a space
+    fn parse(value: &str) -> Result<Self, String> {
+        let mut parts = value.split_whitespace();
+        let block = parts.next().ok_or("empty checkpoint")?;
+        let block = U64::from_dec_str(block).map_err(|e| e.to_string())?;
+        let next_block_log_index =
+            parts.next().map(U64::from_dec_str).transpose().map_err(|e| e.to_string())?;
+        Ok(Self { block, next_block_log_index })
+    }
+
+    fn to_file_line(self) -> String {
+        match self.next_block_log_index {
+            Some(log_index) => format!("{} {}", self.block, log_index),
+            None => self.block.to_string(),
+        }
+    }
+
+    fn is_unset(&self) -> bool {
+        self.block.is_zero() && self.next_block_log_index.is_none()
+    }
+}
+
 async fn get_last_synced_block_number_file(
     full_path: &Path,
     contract_name: &str,
     network: &str,
     event_name: &str,
-) -> Result<Option<U64>, UpdateLastSyncedBlockNumberFile> {
+) -> Result<Option<Checkpoint>, UpdateLastSyncedBlockNumberFile> {
     let file_path =
         build_last_synced_block_number_file(full_path, contract_name, network, event_name);
 
@@ -37,12 +88,9 @@ async fn get_last_synced_block_number_file(
 
     if reader.read_line(&mut line).await? > 0 {
         let value = line.trim();
-        let parse = U64::from_dec_str(value);
-        return match parse {
-            Ok(value) => Ok(Some(value)),
-            Err(e) => {
-                Err(UpdateLastSyncedBlockNumberFile::ParseError(value.to_string(), e.to_string()))
-            }
+        return match Checkpoint::parse(value) {
+            Ok(checkpoint) => Ok(Some(checkpoint)),
+            Err(e) => Err(UpdateLastSyncedBlockNumberFile::ParseError(value.to_string(), e)),
         };
     }
 
@@ -77,7 +125,7 @@ pub struct SyncConfig<'a> {
     pub network: &'a str,
 }
 
-pub async fn get_last_synced_block_number(config: SyncConfig<'_>) -> Option<U64> {
+pub async fn get_last_synced_checkpoint(config: SyncConfig<'_>) -> Option<Checkpoint> {
     // Check CSV file for last seen block as no database enabled
     if config.database.is_none() && config.contract_csv_enabled {
         if let Some(csv_details) = config.csv_details {
@@ -91,13 +139,7 @@ pub async fn get_last_synced_block_number(config: SyncConfig<'_>) -> Option<U64>
             )
             .await
             {
-                if let Some(value) = result {
-                    if value.is_zero() {
-                        return None;
-                    }
-                }
-
-                result
+                result.filter(|checkpoint| !checkpoint.is_unset())
             } else {
                 error!("Error fetching last synced block from CSV");
                 None
@@ -127,13 +169,7 @@ pub async fn get_last_synced_block_number(config: SyncConfig<'_>) -> Option<U64>
         )
         .await
         {
-            if let Some(value) = result {
-                if value.is_zero() {
-                    return None;
-                }
-            }
-
-            result
+            result.filter(|checkpoint| !checkpoint.is_unset())
         } else {
             error!("Error fetching last synced block from stream");
             None
@@ -143,7 +179,7 @@ pub async fn get_last_synced_block_number(config: SyncConfig<'_>) -> Option<U64>
     // Query database for last synced block
     if let Some(database) = config.database {
         let query = format!(
-            "SELECT last_synced_block FROM rindexer_internal.{}_{}_{} WHERE network = $1",
+            "SELECT last_synced_block, last_synced_log_index FROM rindexer_internal.{}_{}_{} WHERE network = $1",
             camel_to_snake(config.indexer_name),
             camel_to_snake(config.contract_name),
             camel_to_snake(config.event_name)
@@ -151,14 +187,17 @@ pub async fn get_last_synced_block_number(config: SyncConfig<'_>) -> Option<U64>
 
         match database.query_one(&query, &[&config.network]).await {
             Ok(row) => {
-                let result: Decimal = row.get("last_synced_block");
-                let parsed = U64::from_dec_str(&result.to_string())
-                    .expect("Failed to parse last_synced_block");
-                if parsed.is_zero() {
-                    None
-                } else {
-                    Some(parsed)
-                }
+                let parse = |value: Decimal| {
+                    U64::from_dec_str(&value.to_string())
+                        .expect("Failed to parse last synced block")
+                };
+                let checkpoint = Checkpoint {
+                    block: parse(row.get("last_synced_block")),
+                    next_block_log_index: row
+                        .get::<_, Option<Decimal>>("last_synced_log_index")
+                        .map(parse),
+                };
+                Some(checkpoint).filter(|checkpoint| !checkpoint.is_unset())
             }
             Err(e) => {
                 error!("Error fetching last synced block: {:?}", e);
@@ -182,7 +221,7 @@ pub enum UpdateLastSyncedBlockNumberFile {
 async fn update_last_synced_block_number_for_file(
     config: &Arc<EventProcessingConfig>,
     full_path: &Path,
-    to_block: U64,
+    checkpoint: Checkpoint,
 ) -> Result<(), UpdateLastSyncedBlockNumberFile> {
     let file_path = build_last_synced_block_number_file(
         full_path,
@@ -191,7 +230,7 @@ async fn update_last_synced_block_number_for_file(
         &config.event_name,
     );
 
-    let last_block = get_last_synced_block_number_file(
+    let last_checkpoint = get_last_synced_block_number_file(
         full_path,
         &config.contract_name,
         &config.network_contract.network,
@@ -199,14 +238,11 @@ async fn update_last_synced_block_number_for_file(
     )
     .await?;
 
-    let to_block_higher_then_last_block =
-        if let Some(last_block_value) = last_block { to_block > last_block_value } else { true };
-
-    if last_block.is_none() || to_block_higher_then_last_block {
+    if last_checkpoint.is_none_or(|last_checkpoint| checkpoint > last_checkpoint) {
         let temp_file_path = format!("{}.tmp", file_path);
 
         let mut file = File::create(&temp_file_path).await?;
-        file.write_all(to_block.to_string().as_bytes()).await?;
+        file.write_all(checkpoint.to_file_line().as_bytes()).await?;
         file.sync_all().await?;
 
         fs::rename(temp_file_path, file_path).await?;
@@ -217,7 +253,9 @@ async fn update_last_synced_block_number_for_file(
 
 pub fn update_progress_and_last_synced_task(
     config: Arc<EventProcessingConfig>,
+    from_block: U64,
     to_block: U64,
+    logs: usize,
     on_complete: impl FnOnce() + Send + 'static,
 ) {
     tokio::spawn(async move {
@@ -225,67 +263,241 @@ pub fn update_progress_and_last_synced_task(
             .progress
             .lock()
             .await
-            .update_last_synced_block(&config.network_contract.id, to_block);
+            .update_last_synced_block(&config.network_contract.id, to_block, logs);
 
         if let Err(e) = update_last_synced_block_result {
             error!("Error updating last synced block: {:?}", e);
         }
 
-        if let Some(database) = &config.database {
-            let result = database
-                .execute(
-                    &format!(
-                        "UPDATE rindexer_internal.{}_{}_{} SET last_synced_block = $1 WHERE network = $2 AND $1 > last_synced_block",
-                        camel_to_snake(&config.indexer_name),
-                        camel_to_snake(&config.contract_name),
-                        camel_to_snake(&config.event_name)
-                    ),
-                    &[
-                        &EthereumSqlTypeWrapper::U64(to_block),
-                        &config.network_contract.network,
-                    ],
-                )
-                .await;
-
-            if let Err(e) = result {
-                error!("Error updating last synced block: {:?}", e);
-            }
-        } else if let Some(csv_details) = &config.csv_details {
-            if let Err(e) = update_last_synced_block_number_for_file(
-                &config,
-                &get_full_path(&config.project_path, &csv_details.path).unwrap_or_else(|_| {
-                    panic!("failed to get full path {}", config.project_path.display())
-                }),
-                to_block,
-            )
-            .await
-            {
-                error!(
-                    "Error updating last synced block to CSV - path - {} error - {:?}",
-                    csv_details.path, e
-                );
-            }
-        } else if let Some(stream_last_synced_block_file_path) =
-            &config.stream_last_synced_block_file_path
-        {
-            if let Err(e) = update_last_synced_block_number_for_file(
-                &config,
-                &config
-                    .project_path
-                    .join(stream_last_synced_block_file_path)
-                    .canonicalize()
-                    .expect("Failed to canonicalize path"),
-                to_block,
-            )
-            .await
-            {
-                error!(
-                    "Error updating last synced block to stream - path - {} error - {:?}",
-                    stream_last_synced_block_file_path, e
-                );
+        if config.persist_last_synced_block {
+            match &config.completed_ranges {
+                Some(completed_ranges) => {
+                    match completed_ranges.complete(from_block, to_block).await {
+                        Ok(Some(last_block)) => {
+                            persist_checkpoint(&config, Checkpoint::block(last_block)).await
+                        }
+                        // a range before it is still being processed
+                        Ok(None) => {}
+                        Err(e) => error!("Error recording completed range: {:?}", e),
+                    }
+                }
+                None => persist_checkpoint(&config, Checkpoint::block(to_block)).await,
             }
         }
 
         on_complete();
     });
 }
+
+/// The statement moving the stored checkpoint of the event forward, a checkpoint behind the
+/// stored one is left out as ranges can complete out of order. Every checkpoint write goes through
+/// it so the log index is always written along with the block.
+pub fn checkpoint_statement(
+    indexer_name: &str,
+    contract_name: &str,
+    event_name: &str,
+    network: &str,
+    checkpoint: Checkpoint,
+) -> (String, Vec<EthereumSqlTypeWrapper>) {
+    (
+        format!(
+            "UPDATE rindexer_internal.{}_{}_{} SET last_synced_block = $1, last_synced_log_index = $3::NUMERIC \
+             WHERE network = $2 AND ($1 > last_synced_block OR ($1 = last_synced_block AND COALESCE($3::NUMERIC, -1) > COALESCE(last_synced_log_index, -1)))",
+            camel_to_snake(indexer_name),
+            camel_to_snake(contract_name),
+            camel_to_snake(event_name)
+        ),
+        vec![
+            EthereumSqlTypeWrapper::U64(checkpoint.block),
+            EthereumSqlTypeWrapper::String(network.to_string()),
+            // an empty value is written as NULL
+            EthereumSqlTypeWrapper::NumericNullable(
+                checkpoint
+                    .next_block_log_index
+                    .map(|log_index| log_index.to_string())
+                    .unwrap_or_default(),
+            ),
+        ],
+    )
+}
+
+/// Moves the stored checkpoint of the event forward, a checkpoint behind the stored one is left
+/// out as ranges can complete out of order
+pub async fn persist_checkpoint(config: &Arc<EventProcessingConfig>, checkpoint: Checkpoint) {
+    let to_block = checkpoint.block;
+    if let Some(database) = &config.database {
+        let (sql, params) = checkpoint_statement(
+            &config.indexer_name,
+            &config.contract_name,
+            &config.event_name,
+            &config.network_contract.network,
+            checkpoint,
+        );
+        let params: Vec<&(dyn ToSql + Sync)> =
+            params.iter().map(|param| param as &(dyn ToSql + Sync)).collect();
+        let result = database.execute(&sql, &params).await;
+
+        if let Err(e) = result {
+            error!("Error updating last synced block: {:?}", e);
+            report_error(
+                ErrorCategory::Database,
+                &format!("Error updating last synced block: {:?}", e),
+                ErrorContext {
+                    indexer_name: Some(&config.indexer_name),
+                    contract_name: Some(&config.contract_name),
+                    event_name: Some(&config.event_name),
+                    network: Some(&config.network_contract.network),
+                    to_block: Some(to_block),
+                    ..Default::default()
+                },
+            );
+        }
+    } else if let Some(csv_details) = &config.csv_details {
+        if let Err(e) = update_last_synced_block_number_for_file(
+            config,
+            &get_full_path(&config.project_path, &csv_details.path).unwrap_or_else(|_| {
+                panic!("failed to get full path {}", config.project_path.display())
+            }),
+            checkpoint,
+        )
+        .await
+        {
+            error!(
+                "Error updating last synced block to CSV - path - {} error - {:?}",
+                csv_details.path, e
+            );
+        }
+    } else if let Some(stream_last_synced_block_file_path) =
+        &config.stream_last_synced_block_file_path
+    {
+        if let Err(e) = update_last_synced_block_number_for_file(
+            config,
+            &config
+                .project_path
+                .join(stream_last_synced_block_file_path)
+                .canonicalize()
+                .expect("Failed to canonicalize path"),
+            checkpoint,
+        )
+        .await
+        {
+            error!(
+                "Error updating last synced block to stream - path - {} error - {:?}",
+                stream_last_synced_block_file_path, e
+            );
+        }
+    }
+}
+
+/// Moves the stored checkpoint of the event back to `block` when it is past it, for the reorg
+/// watcher which removed the rows of the blocks after it
+pub async fn rewind_checkpoint(
+    database: &PostgresClient,
+    indexer_name: &str,
+    contract_name: &str,
+    event_name: &str,
+    network: &str,
+    block: U64,
+) -> Result<u64, PostgresError> {
+    database
+        .execute(
+            &format!(
+                "UPDATE rindexer_internal.{}_{}_{} SET last_synced_block = $1, last_synced_log_index = NULL \
+                 WHERE network = $2 AND (last_synced_block > $1 OR (last_synced_block = $1 AND last_synced_log_index IS NOT NULL))",
+                camel_to_snake(indexer_name),
+                camel_to_snake(contract_name),
+                camel_to_snake(event_name)
+            ),
+            &[&EthereumSqlTypeWrapper::U64(block), &network],
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoints_within_a_block_resume_after_the_last_log() {
+        let full = Checkpoint::block(U64::from(99));
+        let partial = Checkpoint::within_block(U64::from(100), U64::from(41)).unwrap();
+
+        assert_eq!(full.resume_from(), (U64::from(100), None));
+        assert_eq!(partial.resume_from(), (U64::from(100), Some((U64::from(100), U64::from(41)))));
+        assert!(partial > full);
+        assert!(Checkpoint::block(U64::from(100)) > partial);
+        assert_eq!(Checkpoint::within_block(U64::zero(), U64::from(3)), None);
+
+        assert_eq!(Checkpoint::parse(&partial.to_file_line()), Ok(partial));
+        assert_eq!(Checkpoint::parse("99"), Ok(full));
+        assert!(Checkpoint::parse("block").is_err());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_checkpoint_statement_only_moves_the_checkpoint_forward() {
+        use crate::{
+            database::postgres::generate::generate_last_synced_table_sql,
+            testing::postgres::TestPostgres,
+        };
+
+        let postgres = TestPostgres::start().await.unwrap();
+        let client = Arc::new(PostgresClient::new().await.unwrap());
+        client
+            .batch_execute(&format!(
+                "CREATE SCHEMA IF NOT EXISTS rindexer_internal;{}",
+                generate_last_synced_table_sql("my_indexer_token", "Transfer", &["ethereum"])
+            ))
+            .await
+            .unwrap();
+        let database = Some(Arc::clone(&client));
+
+        let write = |checkpoint: Checkpoint| {
+            let client = Arc::clone(&client);
+            async move {
+                let (sql, params) =
+                    checkpoint_statement("MyIndexer", "Token", "Transfer", "ethereum", checkpoint);
+                let params: Vec<&(dyn ToSql + Sync)> =
+                    params.iter().map(|param| param as &(dyn ToSql + Sync)).collect();
+                client.execute(&sql, &params).await.unwrap();
+            }
+        };
+        let stored = || {
+            get_last_synced_checkpoint(SyncConfig {
+                project_path: Path::new("."),
+                database: &database,
+                csv_details: &None,
+                stream_details: &None,
+                contract_csv_enabled: false,
+                indexer_name: "MyIndexer",
+                contract_name: "Token",
+                event_name: "Transfer",
+                network: "ethereum",
+            })
+        };
+
+        let partial = Checkpoint::within_block(U64::from(100), U64::from(41)).unwrap();
+        write(partial).await;
+        assert_eq!(stored().await, Some(partial));
+
+        // the staged write of a batch up to block 200 clears the log index of block 100
+        write(Checkpoint::block(U64::from(200))).await;
+        assert_eq!(stored().await, Some(Checkpoint::block(U64::from(200))));
+
+        // a range completing out of order behind it does not move the checkpoint back
+        write(Checkpoint::block(U64::from(150))).await;
+        assert_eq!(stored().await, Some(Checkpoint::block(U64::from(200))));
+
+        let partial = Checkpoint::within_block(U64::from(201), U64::from(7)).unwrap();
+        write(partial).await;
+        assert_eq!(stored().await, Some(partial));
+        assert_eq!(partial.resume_from(), (U64::from(201), Some((U64::from(201), U64::from(7)))));
+
+        // nor do fewer logs of the same block or the block before it without a log index
+        write(Checkpoint::within_block(U64::from(201), U64::from(3)).unwrap()).await;
+        write(Checkpoint::block(U64::from(200))).await;
+        assert_eq!(stored().await, Some(partial));
+
+        drop(postgres);
+    }
+}
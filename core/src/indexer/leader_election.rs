@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::{
+    database::postgres::client::{PostgresClient, PostgresConnectionError, PostgresError},
+    error_reporting::{flush_error_reporting, report_error, ErrorCategory, ErrorContext},
+    initiate_shutdown, is_running,
+    manifest::leader_election::LeaderElectionSettings,
+};
+
+// advisory locks are keyed by a bigint so the lock name is hashed in postgres to keep the key
+// the same for every instance no matter which version of rindexer it runs
+const TRY_LOCK_SQL: &str =
+    "SELECT pg_try_advisory_lock(('x' || substr(md5($1), 1, 16))::bit(64)::bigint)";
+
+#[derive(thiserror::Error, Debug)]
+pub enum LeaderElectionError {
+    #[error("Could not connect to postgres for leader election: {0}")]
+    PostgresConnectionError(#[from] PostgresConnectionError),
+
+    #[error("Leader election query failed: {0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("Shutdown before becoming the leader")]
+    ShutdownWhileStandby,
+}
+
+/// Held by the leader for as long as it indexes, the advisory lock lives on a dedicated
+/// connection so losing that connection hands the lock to a standby instance.
+pub struct LeaderLock {
+    keepalive: JoinHandle<()>,
+}
+
+impl Drop for LeaderLock {
+    fn drop(&mut self) {
+        self.keepalive.abort();
+    }
+}
+
+/// Waits until this instance holds the leader lock, other instances sharing the manifest stay in
+/// hot-standby here until the leader goes away.
+pub async fn acquire_leadership(
+    settings: &LeaderElectionSettings,
+    manifest_name: &str,
+) -> Result<LeaderLock, LeaderElectionError> {
+    let postgres = PostgresClient::new().await?;
+    let connection = postgres.dedicated_connection().await?;
+    let lock_name = settings.lock_name(manifest_name);
+    let retry_interval = Duration::from_secs(settings.retry_interval_seconds());
+
+    let mut logged_standby = false;
+    loop {
+        let acquired: bool = connection
+            .query_one(TRY_LOCK_SQL, &[&lock_name])
+            .await
+            .map_err(PostgresError::PgError)?
+            .get(0);
+
+        if acquired {
+            info!("Leader election - acquired lock {} this instance is now indexing", lock_name);
+            break;
+        }
+
+        if !logged_standby {
+            info!("Leader election - lock {} is held by another instance, standing by", lock_name);
+            logged_standby = true;
+        }
+
+        if !is_running() {
+            return Err(LeaderElectionError::ShutdownWhileStandby);
+        }
+
+        tokio::time::sleep(retry_interval).await;
+    }
+
+    let keepalive = tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(retry_interval).await;
+
+            if let Err(e) = connection.query_one("SELECT 1", &[]).await {
+                // the lock went with the connection so a standby can already be indexing
+                if !is_running() {
+                    return;
+                }
+
+                error!("Leader election - lost the connection holding lock {}: {}", lock_name, e);
+                report_error(
+                    ErrorCategory::Database,
+                    &format!("Leader election - lost the connection holding lock {}", lock_name),
+                    ErrorContext::default(),
+                );
+                initiate_shutdown().await;
+                flush_error_reporting();
+                std::process::exit(1);
+            }
+        }
+    });
+
+    Ok(LeaderLock { keepalive })
+}
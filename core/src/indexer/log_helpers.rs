@@ -3,7 +3,7 @@ use std::str::FromStr;
 use ethers::{
     abi::{Event, Log as ParsedLog, LogParam, RawLog, Token},
     addressbook::Address,
-    prelude::{Block, Bloom, FilteredParams, ValueOrArray, H256, U256},
+    prelude::{Block, Bloom, FilteredParams, ValueOrArray, H256, I256, U256},
     types::{BigEndianHash, Log},
     utils::keccak256,
 };
@@ -73,7 +73,9 @@ pub fn parse_topic(input: &str) -> H256 {
         "true" => H256::from_low_u64_be(1),
         "false" => H256::from_low_u64_be(0),
         _ => {
-            if let Ok(address) = Address::from_str(input) {
+            if input.len() == 66 && input.starts_with("0x") {
+                H256::from_str(input).unwrap_or_else(|_| H256::from(keccak256(input)))
+            } else if let Ok(address) = Address::from_str(input) {
                 H256::from(address)
             } else if let Ok(num) = U256::from_dec_str(input) {
                 H256::from_uint(&num)
@@ -84,6 +86,53 @@ pub fn parse_topic(input: &str) -> H256 {
     }
 }
 
+fn parse_uint(value: &str) -> Option<U256> {
+    match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(value).ok(),
+    }
+}
+
+fn parse_int(value: &str) -> Option<I256> {
+    match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok().map(I256::from_raw),
+        None => I256::from_dec_str(value).ok(),
+    }
+}
+
+/// Encodes an indexed filter value the way an input of the type is put in a topic, values of
+/// dynamic types are hashed. `None` when the value does not fit the type, arrays and tuples are
+/// hashed from their ABI encoding which a yaml value can not express.
+pub fn encode_topic(type_: &str, value: &str) -> Option<H256> {
+    let value = value.trim();
+    match type_ {
+        _ if type_.ends_with(']') => None,
+        "address" => Address::from_str(value).ok().map(H256::from),
+        "bool" => match value.to_lowercase().as_str() {
+            "true" => Some(H256::from_low_u64_be(1)),
+            "false" => Some(H256::zero()),
+            _ => None,
+        },
+        "string" => Some(H256::from(keccak256(value))),
+        "bytes" => {
+            hex::decode(value.trim_start_matches("0x")).ok().map(|b| H256::from(keccak256(b)))
+        }
+        _ if type_.starts_with("uint") => parse_uint(value).map(|n| H256::from_uint(&n)),
+        _ if type_.starts_with("int") => parse_int(value).map(|n| H256::from_uint(&n.into_raw())),
+        _ if type_.starts_with("bytes") => {
+            let size: usize = type_["bytes".len()..].parse().ok()?;
+            let bytes = hex::decode(value.trim_start_matches("0x")).ok()?;
+            if bytes.len() != size || size > 32 {
+                return None;
+            }
+            let mut word = [0u8; 32];
+            word[..size].copy_from_slice(&bytes);
+            Some(H256(word))
+        }
+        _ => None,
+    }
+}
+
 pub fn contract_in_bloom(contract_address: Address, logs_bloom: Bloom) -> bool {
     let address_filter =
         FilteredParams::address_filter(&Some(ValueOrArray::Value(contract_address)));
@@ -1,19 +1,43 @@
+pub mod address_registry;
+pub mod alerts;
+pub mod backfill_jobs;
+pub mod chain_id;
+pub mod completed_ranges;
+pub mod enrichment;
+pub mod estimate;
 mod process;
 mod progress;
 
-pub use progress::{IndexingEventProgressStatus, IndexingEventsProgressState};
+pub use progress::{
+    format_eta, publish_progress, published_progress, render_progress_metrics,
+    IndexingEventProgress, IndexingEventProgressStatus, IndexingEventsProgressState,
+};
 use serde::{Deserialize, Serialize};
 
 mod log_helpers;
-pub use log_helpers::parse_topic;
+pub use log_helpers::{encode_topic, parse_topic};
 mod dependency;
 pub use dependency::ContractEventDependenciesMapFromRelationshipsError;
 mod fetch_logs;
+mod hypersync;
+pub mod import;
 mod last_synced;
+pub use last_synced::{checkpoint_statement, rewind_checkpoint, Checkpoint};
+pub mod leader_election;
 pub mod no_code;
+pub mod overlap;
+pub mod pause;
+pub mod redecode;
 mod reorg;
+pub use reorg::{
+    chain_family, reorg_safe_distance_for_chain, request_reorg_rewind, take_reorg_rewind,
+    ChainFamily,
+};
+pub mod spam;
 pub mod start;
 pub mod task_tracker;
+pub mod watchdog;
+pub mod write_ahead_log;
 
 pub use dependency::{ContractEventDependencies, EventDependencies, EventsDependencyTree};
 
@@ -1,11 +1,16 @@
 use std::{
+    collections::HashMap,
     io,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
 use colored::Colorize;
-use ethers::abi::{Abi, Contract as EthersContract, Event};
+use ethers::{
+    abi::{Abi, Contract as EthersContract, Event, LogParam, Token},
+    types::{Address, I256, U256},
+    utils::{format_units, ParseUnits},
+};
 use serde_json::Value;
 use tokio_postgres::types::Type as PgType;
 use tracing::{debug, error, info, warn};
@@ -17,11 +22,12 @@ use crate::{
         client::PostgresClient,
         generate::{
             generate_column_names_only_with_base_properties, generate_event_table_full_name,
+            DECODED_PARAMS_COLUMN, SPAM_REASON_COLUMN,
         },
         setup::{setup_postgres, SetupPostgresError},
         sql_type_wrapper::{
-            map_ethereum_wrapper_to_json, map_log_params_to_ethereum_wrapper,
-            EthereumSqlTypeWrapper,
+            map_ethereum_wrapper_to_json, map_ethereum_wrapper_to_params_json,
+            map_log_params_to_ethereum_wrapper, EthereumSqlTypeWrapper,
         },
     },
     event::{
@@ -33,15 +39,19 @@ use crate::{
         EventMessage,
     },
     generate_random_id,
-    indexer::log_helpers::{map_log_params_to_raw_values, parse_log},
+    indexer::{
+        enrichment::{EnrichmentPipeline, EnrichmentRow},
+        log_helpers::{map_log_params_to_raw_values, parse_log},
+        spam::spam_reason_column_value,
+    },
     manifest::{
-        contract::ParseAbiError,
+        contract::{EventDecimals, ParamsStorage, ParseAbiError},
         core::Manifest,
         yaml::{read_manifest, ReadManifestError},
     },
-    provider::{CreateNetworkProvider, RetryClientError},
+    provider::{CreateNetworkProvider, JsonRpcCachedProvider, RetryClientError},
     setup_info_logger,
-    streams::StreamsClients,
+    streams::{EventSchema, StreamsClients},
     AsyncCsvAppender, FutureExt, IndexingDetails, StartDetails, StartNoCodeDetails,
 };
 
@@ -138,10 +148,68 @@ struct NoCodeCallbackParams {
     postgres: Option<Arc<PostgresClient>>,
     postgres_event_table_name: String,
     postgres_column_names: Vec<String>,
+    /// The chain id for each network, only set when the table is shared by a table group
+    chain_ids: Option<HashMap<String, u64>>,
+    /// The token amounts also stored divided by the token decimals, after the base columns
+    event_decimals: Option<EventDecimals>,
+    /// The enrichment stage columns, after the normalized columns
+    enrichment: Option<Arc<EnrichmentPipeline>>,
+    params_storage: ParamsStorage,
+    /// The spam filter flags rather than drops, why is stored after the decoded params
+    flag_spam: bool,
+    providers: HashMap<String, Arc<JsonRpcCachedProvider>>,
     streams_clients: Arc<Option<StreamsClients>>,
     chat_clients: Arc<Option<ChatClients>>,
 }
 
+/// The token amounts divided by the token decimals, left empty when the decimals can not be
+/// resolved so the raw values still get indexed
+async fn normalized_values(
+    params: &NoCodeCallbackParams,
+    event_decimals: &EventDecimals,
+    log_params: &[LogParam],
+    address: Address,
+    network: &str,
+) -> Vec<EthereumSqlTypeWrapper> {
+    let decimals = match event_decimals.decimals {
+        Some(decimals) => Some(decimals),
+        None => match params.providers.get(network) {
+            Some(provider) => match provider.get_token_decimals(address).await {
+                Ok(decimals) => Some(decimals),
+                Err(e) => {
+                    warn!(
+                        "{}::{} - Could not get the decimals of {:?}: {}",
+                        params.contract_name, params.event_info.name, address, e
+                    );
+                    None
+                }
+            },
+            None => None,
+        },
+    };
+
+    event_decimals
+        .inputs
+        .iter()
+        .map(|input| {
+            let value = log_params.iter().find(|param| param.name == *input).map(|param| {
+                match &param.value {
+                    Token::Int(value) => ParseUnits::I256(I256::from_raw(*value)),
+                    Token::Uint(value) => ParseUnits::U256(*value),
+                    // validation makes sure only uint and int inputs are normalized
+                    _ => ParseUnits::U256(U256::zero()),
+                }
+            });
+
+            let normalized = decimals
+                .zip(value)
+                .and_then(|(decimals, value)| format_units(value, decimals as u32).ok())
+                .unwrap_or_default();
+            EthereumSqlTypeWrapper::NumericNullable(normalized)
+        })
+        .collect()
+}
+
 fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
     Arc::new(move |results| {
         let params = Arc::clone(&params);
@@ -164,7 +232,7 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                 None => {
                     let error_message = "Unexpected error: no first event despite non-zero length.";
                     error!("{}", error_message);
-                    return Err(error_message.to_string());
+                    return Err(error_message.into());
                 }
             };
 
@@ -196,16 +264,24 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                         map_log_params_to_ethereum_wrapper(&params.event_info.inputs, &log.params);
 
                     let contract_address = EthereumSqlTypeWrapper::Address(address);
-                    let end_global_parameters = vec![
+                    let mut end_global_parameters = vec![
                         EthereumSqlTypeWrapper::H256(transaction_hash),
                         EthereumSqlTypeWrapper::U64(block_number),
                         EthereumSqlTypeWrapper::H256(block_hash),
                         EthereumSqlTypeWrapper::String(network.to_string()),
+                    ];
+                    if let Some(chain_ids) = &params.chain_ids {
+                        // validation makes sure every contract network exists
+                        let chain_id = chain_ids.get(&network).copied().unwrap_or_default();
+                        end_global_parameters.push(EthereumSqlTypeWrapper::I64(chain_id as i64));
+                    }
+                    end_global_parameters.extend(vec![
                         EthereumSqlTypeWrapper::U64(transaction_index),
                         EthereumSqlTypeWrapper::U256(log_index),
-                    ];
+                    ]);
 
                     Some((
+                        result.spam,
                         log.params,
                         address,
                         transaction_hash,
@@ -221,22 +297,46 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                 })
                 .collect();
 
+            // the stages look up what the events of the batch share once
+            let enriched: Vec<Vec<Option<String>>> =
+                match (&params.enrichment, params.providers.get(&network)) {
+                    (Some(enrichment), Some(provider)) => {
+                        let rows: Vec<EnrichmentRow> = owned_results
+                            .iter()
+                            .map(|result| EnrichmentRow {
+                                address: result.2,
+                                block_number: result.6,
+                                transaction_hash: result.3,
+                                log_params: &result.1,
+                            })
+                            .collect();
+                        enrichment.enrich(provider, &rows).await
+                    }
+                    _ => vec![],
+                };
+
             for (
-                log_params,
-                address,
-                transaction_hash,
-                log_index,
-                transaction_index,
-                block_number,
-                block_hash,
-                network,
-                contract_address,
-                event_parameters,
-                end_global_parameters,
-            ) in owned_results
+                index,
+                (
+                    spam,
+                    log_params,
+                    address,
+                    transaction_hash,
+                    log_index,
+                    transaction_index,
+                    block_number,
+                    block_hash,
+                    network,
+                    contract_address,
+                    event_parameters,
+                    end_global_parameters,
+                ),
+            ) in owned_results.into_iter().enumerate()
             {
+                let enriched_values = enriched.get(index).map(Vec::as_slice).unwrap_or_default();
+
                 if params.streams_clients.is_some() || params.chat_clients.is_some() {
-                    let event_result = map_ethereum_wrapper_to_json(
+                    let mut event_result = map_ethereum_wrapper_to_json(
                         &params.event_info.inputs,
                         &event_parameters,
                         &TxInformation {
@@ -251,12 +351,41 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                         },
                         false,
                     );
+                    if let (Some(enrichment), Value::Object(fields)) =
+                        (&params.enrichment, &mut event_result)
+                    {
+                        fields.extend(enrichment.json_values(enriched_values));
+                    }
                     event_message_data.push(event_result);
                 }
 
+                let decoded_params = params.params_storage.jsonb().then(|| {
+                    map_ethereum_wrapper_to_params_json(
+                        &params.event_info.inputs,
+                        &event_parameters,
+                    )
+                });
+
                 let mut all_params: Vec<EthereumSqlTypeWrapper> = vec![contract_address];
-                all_params.extend(event_parameters);
+                if params.params_storage.typed_columns() {
+                    all_params.extend(event_parameters);
+                }
                 all_params.extend(end_global_parameters);
+                if let Some(event_decimals) = &params.event_decimals {
+                    all_params.extend(
+                        normalized_values(&params, event_decimals, &log_params, address, &network)
+                            .await,
+                    );
+                }
+                if let Some(enrichment) = &params.enrichment {
+                    all_params.extend(enrichment.sql_values(enriched_values));
+                }
+                if let Some(decoded_params) = decoded_params {
+                    all_params.push(EthereumSqlTypeWrapper::Jsonb(decoded_params));
+                }
+                if params.flag_spam {
+                    all_params.push(spam_reason_column_value(spam));
+                }
 
                 // Set column types dynamically based on first result
                 if postgres_bulk_column_types.is_empty() {
@@ -304,7 +433,7 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                                 "{}::{} - Error performing bulk insert: {}",
                                 params.contract_name, params.event_info.name, e
                             );
-                            return Err(e.to_string());
+                            return Err(e.to_string().into());
                         }
                     } else if let Err(e) = postgres
                         .bulk_insert(
@@ -318,7 +447,7 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                             "{}::{} - Error performing bulk insert: {}",
                             params.contract_name, params.event_info.name, e
                         );
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 }
             }
@@ -326,7 +455,7 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
             if let Some(csv) = &params.csv {
                 if !csv_bulk_data.is_empty() {
                     if let Err(e) = csv.append_bulk(csv_bulk_data).await {
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 }
             }
@@ -364,7 +493,7 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                     }
                     Err(e) => {
                         error!("Error streaming event: {}", e);
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 }
             }
@@ -405,7 +534,7 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                         }
                         Err(e) => {
                             error!("Error sending chat messages: {}", e);
-                            return Err(e.to_string());
+                            return Err(e.to_string().into());
                         }
                     }
                 }
@@ -463,6 +592,12 @@ pub async fn process_events(
     network_providers: &[CreateNetworkProvider],
 ) -> Result<Vec<EventCallbackRegistryInformation>, ProcessIndexersError> {
     let mut events: Vec<EventCallbackRegistryInformation> = vec![];
+    let chain_ids: HashMap<String, u64> =
+        manifest.networks.iter().map(|network| (network.name.clone(), network.chain_id)).collect();
+    let providers: HashMap<String, Arc<JsonRpcCachedProvider>> = network_providers
+        .iter()
+        .map(|provider| (provider.network_name.clone(), Arc::clone(&provider.client)))
+        .collect();
 
     for contract in &mut manifest.contracts {
         // TODO - this could be shared with `get_abi_items`
@@ -520,33 +655,72 @@ pub async fn process_events(
                 csv = Some(Arc::new(csv_appender));
             }
 
-            let postgres_column_names =
-                generate_column_names_only_with_base_properties(&event_info.inputs);
-            let postgres_event_table_name =
-                generate_event_table_full_name(&manifest.name, &contract.name, &event_info.name);
+            let params_storage = contract.params_storage();
+            let mut postgres_column_names = generate_column_names_only_with_base_properties(
+                if params_storage.typed_columns() { &event_info.inputs } else { &[] },
+                contract.table_group.is_some(),
+            );
+            let event_decimals = contract.event_decimals(&event_info.name).cloned();
+            if let Some(event_decimals) = &event_decimals {
+                postgres_column_names.extend(
+                    event_decimals
+                        .inputs
+                        .iter()
+                        .map(|input| EventDecimals::normalized_column_name(input)),
+                );
+            }
+            let enrichment_stages = contract.event_enrichment(&event_info.name);
+            postgres_column_names.extend(enrichment_stages.iter().map(|stage| stage.column_name()));
+            let enrichment = (!enrichment_stages.is_empty())
+                .then(|| Arc::new(EnrichmentPipeline::new(&event_info.name, enrichment_stages)));
+            if params_storage.jsonb() {
+                postgres_column_names.push(DECODED_PARAMS_COLUMN.to_string());
+            }
+            if contract.flags_spam() {
+                postgres_column_names.push(SPAM_REASON_COLUMN.to_string());
+            }
+            let postgres_event_table_name = generate_event_table_full_name(
+                &manifest.name,
+                contract.table_group.as_deref().unwrap_or(&contract.name),
+                &event_info.name,
+            );
 
             let streams_client = if let Some(streams) = &contract.streams {
-                Some(StreamsClients::new(streams.clone()).await)
+                let mut streams_client = StreamsClients::new(streams.clone()).await;
+                streams_client.add_event_schema(EventSchema::from_abi(
+                    &contract.name,
+                    &event_info.name,
+                    &event_info.inputs,
+                ));
+                Some(streams_client)
             } else {
                 None
             };
 
             let chat_clients = if let Some(chats) = &contract.chat {
-                Some(ChatClients::new(chats.clone()).await)
+                Some(
+                    ChatClients::new(
+                        chats
+                            .clone()
+                            .resolve_routes(manifest.notifications.as_deref().unwrap_or_default()),
+                    )
+                    .await,
+                )
             } else {
                 None
             };
 
-            let index_event_in_order = contract
-                .index_event_in_order
-                .as_ref()
-                .map_or(false, |vec| vec.contains(&event_info.name));
+            let index_event_in_order = contract.is_event_indexed_in_order(&event_info.name);
 
             let event = EventCallbackRegistryInformation {
                 id: generate_random_id(10),
                 indexer_name: manifest.name.clone(),
                 event_name: event_info.name.clone(),
                 index_event_in_order,
+                group_by_block: contract.is_event_grouped_by_block(&event_info.name),
+                max_concurrent_callbacks: contract.max_concurrent_callbacks(&event_info.name),
+                failure_policy: contract.failure_policy(&event_info.name),
+                archive_raw_logs: contract.archive_raw_logs(),
                 topic_id: event_info.topic_id(),
                 contract: contract_information,
                 callback: no_code_callback(Arc::new(NoCodeCallbackParams {
@@ -559,6 +733,12 @@ pub async fn process_events(
                     postgres: postgres.clone(),
                     postgres_event_table_name,
                     postgres_column_names,
+                    chain_ids: contract.table_group.as_ref().map(|_| chain_ids.clone()),
+                    event_decimals,
+                    enrichment,
+                    params_storage,
+                    flag_spam: contract.flags_spam(),
+                    providers: providers.clone(),
                     streams_clients: Arc::new(streams_client),
                     chat_clients: Arc::new(chat_clients),
                 })),
@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use ethers::types::{Log, ValueOrArray, U64};
+
+use crate::{
+    event::{
+        callback_registry::{EventCallbackRegistry, EventCallbackRegistryInformation, EventResult},
+        contract_setup::{IndexingContractSetup, NetworkContract},
+    },
+    manifest::{contract::EventInputIndexedFilters, core::Manifest},
+};
+
+/// What a contract listed earlier in the manifest indexes of an event on a network
+#[derive(Clone)]
+struct EarlierContract {
+    start_block: U64,
+    end_block: Option<U64>,
+    setup: IndexingContractSetup,
+}
+
+fn matches_indexed_filters(filters: Option<&EventInputIndexedFilters>, log: &Log) -> bool {
+    let Some(filters) = filters else {
+        return true;
+    };
+    filters.topics().into_iter().enumerate().all(|(position, topics)| {
+        topics.is_none_or(|topics| {
+            log.topics.get(position + 1).is_some_and(|topic| topics.contains(topic))
+        })
+    })
+}
+
+impl EarlierContract {
+    fn indexes(&self, event_name: &str, log: &Log) -> bool {
+        let Some(block_number) = log.block_number else {
+            return false;
+        };
+        if block_number < self.start_block || self.end_block.is_some_and(|end| block_number > end) {
+            return false;
+        }
+
+        match &self.setup {
+            IndexingContractSetup::Address(details) => {
+                let address_matches = match &details.address {
+                    ValueOrArray::Value(address) => *address == log.address,
+                    ValueOrArray::Array(addresses) => addresses.contains(&log.address),
+                };
+                address_matches &&
+                    matches_indexed_filters(
+                        details.indexed_filters.as_ref().and_then(|filters| {
+                            filters.iter().find(|filters| filters.event_name == event_name)
+                        }),
+                        log,
+                    )
+            }
+            IndexingContractSetup::Filter(details) => {
+                details.allows_address(&log.address) &&
+                    matches_indexed_filters(details.indexed_filters.as_ref(), log)
+            }
+            // the addresses come from the factory events or the registry so are not known up front
+            IndexingContractSetup::Factory(_) | IndexingContractSetup::Registry(_) => false,
+        }
+    }
+}
+
+/// The contracts listed earlier in the manifest which index the same event on the same network.
+/// A log one of them indexes is the same `(tx_hash, log_index)` it gets, so it is left to it and
+/// dropped before the handlers. Which contract gets a log only depends on the manifest so it
+/// holds across restarts however far apart the contracts are in their sync.
+///
+/// Contracts without a `start_block` only index from when they were first started and factory
+/// contracts only know their addresses as they go, neither takes logs from later contracts.
+#[derive(Clone, Default)]
+pub struct EarlierOverlaps {
+    event_name: String,
+    contracts: Vec<EarlierContract>,
+}
+
+impl EarlierOverlaps {
+    pub fn new(event_name: &str) -> Self {
+        Self { event_name: event_name.to_string(), contracts: Vec::new() }
+    }
+
+    pub fn add(&mut self, network_contract: &NetworkContract) {
+        let Some(start_block) = network_contract.start_block else {
+            return;
+        };
+        self.contracts.push(EarlierContract {
+            start_block,
+            end_block: network_contract.end_block,
+            setup: network_contract.indexing_contract_setup.clone(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty()
+    }
+
+    fn indexed_earlier(&self, log: &Log) -> bool {
+        self.contracts.iter().any(|contract| contract.indexes(&self.event_name, log))
+    }
+
+    /// Drops the results an earlier contract indexes, returns how many were dropped
+    pub fn remove_indexed_earlier(&self, results: &mut Vec<EventResult>) -> usize {
+        let before = results.len();
+        results.retain(|result| !self.indexed_earlier(&result.log));
+        before - results.len()
+    }
+}
+
+/// The overlaps of the event on a network, `None` unless its contract sets `deduplicate_logs` and
+/// an earlier contract indexes the event there too
+pub fn earlier_overlaps(
+    manifest: &Manifest,
+    registry: &EventCallbackRegistry,
+    event: &EventCallbackRegistryInformation,
+    network: &str,
+) -> Option<Arc<EarlierOverlaps>> {
+    let position =
+        |contract_name: &str| manifest.contracts.iter().position(|c| c.name == contract_name);
+    let event_position = position(&event.contract.name)?;
+    if !manifest.contracts[event_position].deduplicate_logs() {
+        return None;
+    }
+
+    let mut overlaps = EarlierOverlaps::new(&event.event_name);
+    for earlier in registry.events.iter().filter(|earlier| {
+        earlier.topic_id == event.topic_id &&
+            position(&earlier.contract.name).is_some_and(|p| p < event_position)
+    }) {
+        for network_contract in earlier.contract.details.iter().filter(|d| d.network == network) {
+            overlaps.add(network_contract);
+        }
+    }
+
+    (!overlaps.is_empty()).then(|| Arc::new(overlaps))
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Address, H256};
+
+    use super::*;
+    use crate::event::contract_setup::{AddressDetails, FilterDetails};
+
+    fn log(address: Address, block_number: u64, to: H256) -> Log {
+        Log {
+            address,
+            topics: vec![H256::repeat_byte(0xdd), H256::zero(), to],
+            block_number: Some(U64::from(block_number)),
+            ..Default::default()
+        }
+    }
+
+    fn overlaps(contracts: Vec<EarlierContract>) -> EarlierOverlaps {
+        EarlierOverlaps { event_name: "Transfer".to_string(), contracts }
+    }
+
+    #[test]
+    fn test_logs_of_earlier_contracts_are_left_to_them() {
+        let usdc = Address::repeat_byte(1);
+        let other = Address::repeat_byte(2);
+        let explicit = overlaps(vec![EarlierContract {
+            start_block: U64::from(100),
+            end_block: Some(U64::from(200)),
+            setup: IndexingContractSetup::Address(AddressDetails {
+                address: ValueOrArray::Value(usdc),
+                indexed_filters: None,
+            }),
+        }]);
+
+        assert!(explicit.indexed_earlier(&log(usdc, 150, H256::zero())));
+        assert!(!explicit.indexed_earlier(&log(other, 150, H256::zero())));
+        assert!(!explicit.indexed_earlier(&log(usdc, 99, H256::zero())));
+        assert!(!explicit.indexed_earlier(&log(usdc, 201, H256::zero())));
+    }
+
+    #[test]
+    fn test_indexed_filters_narrow_what_an_earlier_contract_indexes() {
+        let recipient = H256::from(Address::repeat_byte(9));
+        let filter = overlaps(vec![EarlierContract {
+            start_block: U64::zero(),
+            end_block: None,
+            setup: IndexingContractSetup::Filter(FilterDetails {
+                events: ValueOrArray::Value("Transfer".to_string()),
+                indexed_filters: Some(EventInputIndexedFilters {
+                    event_name: "Transfer".to_string(),
+                    indexed_1: None,
+                    indexed_2: Some(vec![format!("{:?}", Address::repeat_byte(9))]),
+                    indexed_3: None,
+                    indexed_types: vec!["address".to_string(), "address".to_string()],
+                }),
+                address_filter: None,
+            }),
+        }]);
+
+        assert!(filter.indexed_earlier(&log(Address::repeat_byte(1), 5, recipient)));
+        assert!(!filter.indexed_earlier(&log(Address::repeat_byte(1), 5, H256::zero())));
+    }
+}
@@ -0,0 +1,83 @@
+use std::{collections::BTreeSet, sync::RwLock, time::Duration};
+
+use once_cell::sync::Lazy;
+use tracing::info;
+
+use crate::is_running;
+
+/// Events whose fetching is paused, keyed by `indexer.contract.event` so projects sharing the
+/// process do not pause each other
+static PAUSED_EVENTS: Lazy<RwLock<BTreeSet<String>>> = Lazy::new(|| RwLock::new(BTreeSet::new()));
+
+const PAUSED_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub fn event_pause_key(indexer_name: &str, contract_name: &str, event_name: &str) -> String {
+    format!("{}.{}.{}", indexer_name, contract_name, event_name)
+}
+
+/// Returns false when the event was already paused
+pub fn pause_event(indexer_name: &str, contract_name: &str, event_name: &str) -> bool {
+    PAUSED_EVENTS.write().expect("paused events lock poisoned").insert(event_pause_key(
+        indexer_name,
+        contract_name,
+        event_name,
+    ))
+}
+
+/// Returns false when the event was not paused
+pub fn resume_event(indexer_name: &str, contract_name: &str, event_name: &str) -> bool {
+    PAUSED_EVENTS.write().expect("paused events lock poisoned").remove(&event_pause_key(
+        indexer_name,
+        contract_name,
+        event_name,
+    ))
+}
+
+/// The `contract.event` names paused for the indexer
+pub fn paused_events(indexer_name: &str) -> Vec<String> {
+    let prefix = format!("{}.", indexer_name);
+    PAUSED_EVENTS
+        .read()
+        .expect("paused events lock poisoned")
+        .iter()
+        .filter_map(|key| key.strip_prefix(&prefix).map(str::to_string))
+        .collect()
+}
+
+fn is_paused(pause_key: &str) -> bool {
+    PAUSED_EVENTS.read().expect("paused events lock poisoned").contains(pause_key)
+}
+
+/// Holds the fetching of an event until it is resumed, nothing is fetched meanwhile so the last
+/// synced block stays where it was and indexing carries on from there
+pub async fn wait_while_paused(pause_key: &str, info_log_name: &str) {
+    if !is_paused(pause_key) {
+        return;
+    }
+
+    info!("{} - Paused, waiting to be resumed", info_log_name);
+    while is_paused(pause_key) && is_running() {
+        tokio::time::sleep(PAUSED_POLL_INTERVAL).await;
+    }
+    info!("{} - Resumed", info_log_name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_and_resume_are_per_indexer() {
+        assert!(pause_event("pause_test", "ERC20", "Transfer"));
+        assert!(!pause_event("pause_test", "ERC20", "Transfer"));
+        assert!(pause_event("other_pause_test", "ERC20", "Approval"));
+
+        assert_eq!(paused_events("pause_test"), vec!["ERC20.Transfer".to_string()]);
+        assert!(is_paused(&event_pause_key("pause_test", "ERC20", "Transfer")));
+
+        assert!(resume_event("pause_test", "ERC20", "Transfer"));
+        assert!(!resume_event("pause_test", "ERC20", "Transfer"));
+        assert!(paused_events("pause_test").is_empty());
+        assert_eq!(paused_events("other_pause_test"), vec!["ERC20.Approval".to_string()]);
+    }
+}
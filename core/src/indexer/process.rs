@@ -1,32 +1,44 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use async_std::prelude::StreamExt;
 use ethers::{
     prelude::ProviderError,
-    types::{H256, U64},
+    types::{H256, U256, U64},
 };
 use futures::future::join_all;
 use tokio::{
-    sync::{Mutex, MutexGuard},
+    sync::{Mutex, MutexGuard, Notify},
     task::{JoinError, JoinHandle},
     time::Instant,
 };
 use tracing::{debug, error, info};
 
 use crate::{
+    database::postgres::client::PostgresError,
     event::{
-        callback_registry::EventResult, config::EventProcessingConfig, BuildRindexerFilterError,
-        RindexerEventFilter,
+        callback_registry::{EventResult, HandlerError},
+        config::EventProcessingConfig,
+        contract_setup::IndexingContractSetup,
+        BuildRindexerFilterError, RindexerEventFilter,
     },
     indexer::{
         dependency::{ContractEventsDependenciesConfig, EventDependencies},
-        fetch_logs::{fetch_logs_stream, FetchLogsResult},
-        last_synced::update_progress_and_last_synced_task,
+        fetch_logs::{historic_logs_stream, live_logs_stream, FetchLogsResult},
+        last_synced::{persist_checkpoint, update_progress_and_last_synced_task, Checkpoint},
         log_helpers::is_relevant_block,
-        progress::IndexingEventProgressStatus,
+        progress::{IndexingEventProgressStatus, SkippedRange},
+        spam::flag_results,
         task_tracker::{indexing_event_processed, indexing_event_processing},
+        write_ahead_log::{recovery_plan, WriteAheadLog},
     },
-    is_running,
+    initiate_shutdown, is_running,
+    manifest::contract::FailurePolicy,
+    provider::WrappedLog,
+    runtime_tuning::{max_logs_per_handler_call, LivePollingBackoff},
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -38,41 +50,190 @@ pub enum ProcessEventError {
     BuildFilterError(#[from] BuildRindexerFilterError),
 }
 
+fn handler_halted_error(error: HandlerError) -> Box<ProviderError> {
+    Box::new(ProviderError::CustomError(format!("Handler halted indexing: {}", error)))
+}
+
 pub async fn process_event(
     config: EventProcessingConfig,
     block_until_indexed: bool,
 ) -> Result<(), ProcessEventError> {
     debug!("{} - Processing events", config.info_log_name);
 
-    process_event_logs(Arc::new(config), false, block_until_indexed).await?;
+    let config = Arc::new(config);
+    if let Err(e) = process_event_logs(Arc::clone(&config), false, block_until_indexed).await {
+        fail_event(&config, &e).await;
+        return Err(e.into());
+    }
 
     Ok(())
 }
 
+/// Reports the event as failed and stops the rest of the indexer when its failure policy says so
+async fn fail_event(config: &EventProcessingConfig, error: &ProviderError) {
+    let last_synced_block =
+        config.progress.lock().await.fail(&config.network_contract.id, error.to_string());
+    error!(
+        "{} - {} - stopped after block {}: {}",
+        config.info_log_name,
+        IndexingEventProgressStatus::Failed.log(),
+        last_synced_block.map_or("unknown".to_string(), |block| block.to_string()),
+        error
+    );
+
+    if config.failure_policy == FailurePolicy::HaltIndexer && is_running() {
+        error!("{} - failure policy is halt_indexer, stopping all events", config.info_log_name);
+        tokio::spawn(initiate_shutdown());
+    }
+}
+
+/// Runs the event pipeline and starts it again from the last synced block whenever the watchdog
+/// signals it has stalled
+pub async fn process_event_with_restarts(
+    mut config: EventProcessingConfig,
+    restart: Arc<Notify>,
+) -> Result<(), ProcessEventError> {
+    loop {
+        tokio::select! {
+            result = process_event(config.clone(), false) => return result,
+            _ = restart.notified() => {
+                let last_synced_block = config
+                    .progress
+                    .lock()
+                    .await
+                    .events
+                    .iter()
+                    .find(|e| e.id == config.network_contract.id)
+                    .map(|e| e.last_synced_block);
+
+                // ranges after a missing one can be synced already, they are skipped as it is fetched
+                if let Some(completed_ranges) = &config.completed_ranges {
+                    config.start_block =
+                        config.start_block.max(completed_ranges.next_block().await);
+                } else if let Some(last_synced_block) = last_synced_block {
+                    config.start_block = config.start_block.max(last_synced_block + 1);
+                }
+
+                info!(
+                    "{} - Restarting stalled pipeline from block {}",
+                    config.info_log_name, config.start_block
+                );
+            }
+        }
+    }
+}
+
+/// Indexes a range a previous run left in the retry queue and takes it off the queue once its
+/// handlers succeed
+pub async fn replay_retry_queue_range(
+    mut config: EventProcessingConfig,
+    from_block: U64,
+    to_block: U64,
+) -> Result<(), ProcessEventError> {
+    config.start_block = from_block;
+    config.end_block = to_block;
+    config.live_indexing = false;
+    // the range is behind the checkpoint already
+    config.persist_last_synced_block = false;
+    let retry_queue = config.retry_queue.clone();
+
+    info!(
+        "{} - Replaying queued retry for blocks {} to {}",
+        config.info_log_name, from_block, to_block
+    );
+    process_event(config, true).await?;
+
+    if let Some(retry_queue) = retry_queue {
+        if is_running() {
+            if let Err(e) = retry_queue.remove(from_block, to_block).await {
+                error!("Could not remove retry for blocks {} to {}: {}", from_block, to_block, e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn process_logs_stream(
+    config: &Arc<EventProcessingConfig>,
+    mut logs_stream: impl tokio_stream::Stream<Item = Result<FetchLogsResult, Box<dyn std::error::Error + Send>>>
+        + Unpin,
+) -> Result<Vec<JoinHandle<Result<(), HandlerError>>>, Box<ProviderError>> {
+    let mut tasks = Vec::new();
+
+    while let Some(result) = logs_stream.next().await {
+        let task = handle_logs_result(Arc::clone(config), result)
+            .await
+            .map_err(|e| Box::new(ProviderError::CustomError(e.to_string())))?;
+
+        tasks.push(task);
+
+        // surface handler failures from finished tasks so a halt stops the stream straight away
+        let (finished, pending): (Vec<_>, Vec<_>) =
+            tasks.into_iter().partition(|task| task.is_finished());
+        tasks = pending;
+        for task in finished {
+            task.await
+                .map_err(|e| Box::new(ProviderError::CustomError(e.to_string())))?
+                .map_err(handler_halted_error)?;
+        }
+    }
+
+    Ok(tasks)
+}
+
+async fn wait_for_handlers(
+    tasks: Vec<JoinHandle<Result<(), HandlerError>>>,
+) -> Result<(), Box<ProviderError>> {
+    // Wait for all tasks in parallel
+    let results = futures::future::try_join_all(tasks)
+        .await
+        .map_err(|e| Box::new(ProviderError::CustomError(e.to_string())))?;
+
+    for result in results {
+        result.map_err(handler_halted_error)?;
+    }
+
+    Ok(())
+}
+
+/// Indexes the historic blocks and then hands over to live indexing at the block after them. The
+/// live stage only starts once the handlers of every historic range are done, so the backfill is
+/// complete for the event by then and the handlers see the blocks in order across the boundary.
+///
 /// note block_until_indexed:
-/// Whether to wait for all indexing tasks to complete for an event before returning
+/// Whether to wait for all live indexing tasks to complete for an event before returning
 //  (needed for dependency indexing)
 async fn process_event_logs(
     config: Arc<EventProcessingConfig>,
     force_no_live_indexing: bool,
     block_until_indexed: bool,
 ) -> Result<(), Box<ProviderError>> {
-    let mut logs_stream = fetch_logs_stream(Arc::clone(&config), force_no_live_indexing);
-    let mut tasks = Vec::new();
+    let (historic_stream, handoff) = historic_logs_stream(Arc::clone(&config));
+    let tasks = process_logs_stream(&config, historic_stream).await?;
+    wait_for_handlers(tasks).await?;
 
-    while let Some(result) = logs_stream.next().await {
-        let task = handle_logs_result(Arc::clone(&config), result)
-            .await
-            .map_err(|e| Box::new(ProviderError::CustomError(e.to_string())))?;
+    // a shutdown stops the historic stage before it reaches the handoff
+    let Ok(handoff_block) = handoff.await else {
+        return Ok(());
+    };
 
-        tasks.push(task);
+    config.progress.lock().await.complete_backfill(&config.network_contract.id);
+    info!(
+        "{} - {} - Backfill complete up to block {}",
+        config.info_log_name,
+        IndexingEventProgressStatus::Completed.log(),
+        handoff_block - 1
+    );
+
+    if !config.live_indexing || force_no_live_indexing {
+        return Ok(());
     }
 
+    let tasks =
+        process_logs_stream(&config, live_logs_stream(Arc::clone(&config), handoff_block)).await?;
     if block_until_indexed {
-        // Wait for all tasks in parallel
-        futures::future::try_join_all(tasks)
-            .await
-            .map_err(|e| Box::new(ProviderError::CustomError(e.to_string())))?;
+        wait_for_handlers(tasks).await?;
     }
 
     Ok(())
@@ -254,11 +415,27 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
 
     // this is used for less busy chains to make sure they know rindexer is still alive
     let log_no_new_block_interval = Duration::from_secs(300);
+    let mut polling = LivePollingBackoff::default();
+    // events whose handler halted, the others carry on unless the failure policy stops them all
+    let mut failed_events: HashSet<String> = HashSet::new();
 
     loop {
-        tokio::time::sleep(Duration::from_millis(200)).await;
+        tokio::time::sleep(polling.interval()).await;
 
+        if !is_running() {
+            info!("Detected shutdown, stopping live indexing for contract event dependencies");
+            break;
+        }
+        if failed_events.len() == live_indexing_events.len() {
+            break;
+        }
+
+        // the events share the backoff so it only grows while none of their heads move
+        polling.no_new_block();
         for (config, _) in live_indexing_events.iter() {
+            if failed_events.contains(&config.network_contract.id) {
+                continue;
+            }
             let mut ordering_live_indexing_details = ordering_live_indexing_details_map
                 .get(&config.topic_id)
                 .expect("Failed to get ordering_live_indexing_details_map")
@@ -272,6 +449,12 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                 Ok(latest_block) => {
                     if let Some(latest_block) = latest_block {
                         if let Some(latest_block_number) = latest_block.number {
+                            config
+                                .progress
+                                .lock()
+                                .await
+                                .record_head(&config.network_contract.id, latest_block_number);
+
                             if ordering_live_indexing_details.last_seen_block_number ==
                                 latest_block_number
                             {
@@ -301,6 +484,7 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                                 }
                                 continue;
                             }
+                            polling.new_block();
                             debug!(
                                 "{} - {} - New block seen {} - Last seen block {}",
                                 &config.info_log_name,
@@ -323,6 +507,11 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                                 continue;
                             }
 
+                            ordering_live_indexing_details.filter =
+                                ordering_live_indexing_details.filter.refresh_addresses(
+                                    &config.network_contract.indexing_contract_setup,
+                                );
+
                             let to_block = safe_block_number;
                             if from_block == to_block &&
                                 !config.network_contract.disable_logs_bloom_checks &&
@@ -369,8 +558,7 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                                 ordering_live_indexing_details.filter
                             );
 
-                            let semaphore_client = Arc::clone(&config.semaphore);
-                            let permit = semaphore_client.acquire_owned().await;
+                            let permit = config.acquire_indexing_permit().await;
 
                             if let Ok(permit) = permit {
                                 match config
@@ -412,16 +600,30 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
 
                                         match result {
                                             Ok(task) => {
-                                                let complete = task.await;
-                                                if let Err(e) = complete {
-                                                    error!(
-                                                        "{} - {} - Error indexing task: {} - will try again in 200ms",
-                                                        &config.info_log_name,
-                                                        IndexingEventProgressStatus::Live.log(),
-                                                        e
-                                                    );
-                                                    drop(permit);
-                                                    break;
+                                                match task.await {
+                                                    Err(e) => {
+                                                        error!(
+                                                            "{} - {} - Error indexing task: {} - will try again in 200ms",
+                                                            &config.info_log_name,
+                                                            IndexingEventProgressStatus::Live.log(),
+                                                            e
+                                                        );
+                                                        drop(permit);
+                                                        break;
+                                                    }
+                                                    Ok(Err(e)) => {
+                                                        drop(permit);
+                                                        fail_event(
+                                                            config,
+                                                            &handler_halted_error(e),
+                                                        )
+                                                        .await;
+                                                        failed_events.insert(
+                                                            config.network_contract.id.clone(),
+                                                        );
+                                                        continue;
+                                                    }
+                                                    Ok(Ok(())) => {}
                                                 }
                                                 ordering_live_indexing_details
                                                     .last_seen_block_number = to_block;
@@ -505,23 +707,309 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
 
 async fn trigger_event(
     config: Arc<EventProcessingConfig>,
-    fn_data: Vec<EventResult>,
+    mut fn_data: Vec<EventResult>,
+    from_block: U64,
     to_block: U64,
-) {
+) -> Result<(), HandlerError> {
     indexing_event_processing();
-    config.trigger_event(fn_data).await;
-    update_progress_and_last_synced_task(config, to_block, indexing_event_processed);
+    if config.completed_ranges.is_some() {
+        for result in fn_data.iter_mut() {
+            result.found_in_request.completes_out_of_order = true;
+        }
+    }
+    if let Some((block, log_index)) = config.resume_after_log {
+        fn_data.retain(|result| {
+            result.tx_information.block_number != block ||
+                result.tx_information.log_index > U256::from(log_index.as_u64())
+        });
+    }
+    if let Some(overlaps) = &config.earlier_overlaps {
+        let removed = overlaps.remove_indexed_earlier(&mut fn_data);
+        if removed > 0 {
+            debug!(
+                "{} - Left {} logs to the contracts listed before it",
+                config.info_log_name, removed
+            );
+        }
+    }
+    if let Err(e) = archive_raw_logs(&config, &fn_data).await {
+        indexing_event_processed();
+        // only fails when shutting down, the range is not checkpointed so it is archived on
+        // restart
+        debug!("{} - Stopped archiving raw logs: {}", config.info_log_name, e);
+        return Ok(());
+    }
+    let write_ahead_log_id = log_batch_ahead(&config, &fn_data, from_block, to_block).await;
+    let logs = fn_data.len();
+    let batches = if config.group_by_block {
+        group_results_by_block(fn_data)
+    } else {
+        split_handler_calls(fn_data, max_logs_per_handler_call())
+    };
+    // a batch can be empty once the logs of earlier contracts are removed
+    let batches: Vec<_> = batches.into_iter().filter(|batch| !batch.is_empty()).collect();
+    let last_batch = batches.len().saturating_sub(1);
+    for (position, batch) in batches.into_iter().enumerate() {
+        // the range is checkpointed as a whole once done, the batches before its last one are
+        // checkpointed at their last log
+        let checkpoint = batch.last().and_then(|last| {
+            Checkpoint::within_block(
+                last.tx_information.block_number,
+                U64::from(last.tx_information.log_index.low_u64()),
+            )
+        });
+        let range = batch.first().map(|first| {
+            if config.group_by_block {
+                (first.tx_information.block_number, first.tx_information.block_number)
+            } else {
+                (first.found_in_request.from_block, first.found_in_request.to_block)
+            }
+        });
+        if let Err(e) = config.trigger_event(batch).await {
+            if let (HandlerError::Halt(error), FailurePolicy::Skip, Some((from_block, to_block))) =
+                (&e, config.failure_policy, range)
+            {
+                error!(
+                    "{} - Skipping blocks {} to {} the handler halted on: {}",
+                    config.info_log_name, from_block, to_block, error
+                );
+                config.progress.lock().await.record_skipped_range(
+                    &config.network_contract.id,
+                    SkippedRange { from_block, to_block, error: error.clone() },
+                );
+                continue;
+            }
+
+            // do not checkpoint a batch the handler halted on so it is picked up again on restart
+            indexing_event_processed();
+            // the same goes for batches interrupted by a shutdown which is not an error
+            if matches!(e, HandlerError::Retry(_)) && !is_running() {
+                return Ok(());
+            }
+            return Err(e);
+        }
+
+        // ranges completing out of order are only checkpointed once the ranges before are done
+        if let Some(checkpoint) = checkpoint.filter(|_| {
+            position < last_batch &&
+                config.persist_last_synced_block &&
+                config.completed_ranges.is_none()
+        }) {
+            persist_checkpoint(&config, checkpoint).await;
+        }
+    }
+    let write_ahead_log = config.write_ahead_log.clone();
+    update_progress_and_last_synced_task(config, from_block, to_block, logs, move || {
+        indexing_event_processed();
+        if let (Some(wal), Some(id)) = (write_ahead_log, write_ahead_log_id) {
+            tokio::spawn(async move {
+                if let Err(e) = wal.complete(id).await {
+                    error!("Could not mark a write-ahead log batch done: {}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Logs the batch to the write-ahead log of the event before its handlers run, the batch is
+/// still indexed when it can not be logged it just is not recovered from the log after a crash.
+/// Ranges without logs are logged too as recovery stops at the first range missing from the log.
+async fn log_batch_ahead(
+    config: &EventProcessingConfig,
+    fn_data: &[EventResult],
+    from_block: U64,
+    to_block: U64,
+) -> Option<u64> {
+    let wal = config.write_ahead_log.as_ref()?;
+    let logs = fn_data
+        .iter()
+        .map(|result| WrappedLog {
+            inner: result.log.clone(),
+            block_timestamp: result.tx_information.block_timestamp,
+        })
+        .collect();
+
+    match wal.append(from_block, to_block, logs).await {
+        Ok(id) => Some(id),
+        Err(e) => {
+            error!(
+                "{} - Could not write the batch to the write-ahead log: {}",
+                config.info_log_name, e
+            );
+            None
+        }
+    }
+}
+
+async fn clear_write_ahead_log(config: &EventProcessingConfig, wal: &WriteAheadLog) {
+    if let Err(e) = wal.clear().await {
+        error!("{} - Could not empty the write-ahead log: {}", config.info_log_name, e);
+    }
+}
+
+/// Hands the batches a crashed run left in flight to the handlers again, returns the block
+/// indexing carries on from which is past every batch recovered. The log is only emptied once
+/// every batch is recovered so a crash while recovering them recovers them again.
+pub async fn recover_write_ahead_log(config: &EventProcessingConfig) -> U64 {
+    let Some(wal) = &config.write_ahead_log else {
+        return config.start_block;
+    };
+    let entries = match wal.read_entries().await {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("{} - Could not read the write-ahead log: {}", config.info_log_name, e);
+            return config.start_block;
+        }
+    };
+
+    let (pending, next_block) = recovery_plan(entries, config.start_block);
+    if next_block == config.start_block {
+        // nothing joins up with the checkpoint so it is all fetched again
+        clear_write_ahead_log(config, wal).await;
+        return config.start_block;
+    }
+
+    // the batches are already in the log so they are not logged again while recovering them
+    let config = Arc::new(EventProcessingConfig { write_ahead_log: None, ..config.clone() });
+    let mut recovered = 0;
+    for entry in pending {
+        let fn_data: Vec<EventResult> = entry
+            .logs
+            .into_iter()
+            .map(|log| {
+                EventResult::new(
+                    Arc::clone(&config.network_contract),
+                    log,
+                    entry.from_block,
+                    entry.to_block,
+                )
+            })
+            .collect();
+        recovered += fn_data.len();
+        if let Err(e) =
+            trigger_event(Arc::clone(&config), fn_data, entry.from_block, entry.to_block).await
+        {
+            // indexing carries on from the batch so it is fetched again rather than recovered
+            error!(
+                "{} - Could not recover blocks {} to {} from the write-ahead log: {}",
+                config.info_log_name, entry.from_block, entry.to_block, e
+            );
+            clear_write_ahead_log(&config, wal).await;
+            return entry.from_block;
+        }
+    }
+
+    if config.persist_last_synced_block {
+        persist_checkpoint(&config, Checkpoint::block(next_block - 1)).await;
+    }
+    clear_write_ahead_log(&config, wal).await;
+    info!(
+        "{} - Recovered {} logs from the write-ahead log up to block {}",
+        config.info_log_name,
+        recovered,
+        next_block - 1
+    );
+    next_block
+}
+
+/// Archives the logs before their handlers run, retrying until it works as the handlers should
+/// not see logs which are missing from the archive
+async fn archive_raw_logs(
+    config: &EventProcessingConfig,
+    fn_data: &[EventResult],
+) -> Result<(), PostgresError> {
+    let Some(archive) = &config.raw_log_archive else {
+        return Ok(());
+    };
+
+    let logs: Vec<_> = fn_data.iter().map(|result| &result.log).collect();
+    let mut delay = Duration::from_millis(100);
+    loop {
+        match archive.archive(&config.network_contract.network, &logs).await {
+            Ok(()) => return Ok(()),
+            Err(e) if is_running() => {
+                error!(
+                    "{} - Could not archive raw logs, retrying in {:?}: {}",
+                    config.info_log_name, delay, e
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(Duration::from_secs(30));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Splits the results into handler calls of at most `max` logs, one call when unbounded
+fn split_handler_calls(results: Vec<EventResult>, max: Option<usize>) -> Vec<Vec<EventResult>> {
+    let Some(max) = max.filter(|max| results.len() > *max) else {
+        return vec![results];
+    };
+    let mut calls = Vec::with_capacity(results.len().div_ceil(max));
+    let mut results = results.into_iter().peekable();
+    while results.peek().is_some() {
+        calls.push(results.by_ref().take(max).collect());
+    }
+    calls
+}
+
+/// Splits the results into batches holding all the logs of a single block, the provider returns
+/// logs in block order so consecutive logs of the same block always sit next to each other
+fn group_results_by_block(results: Vec<EventResult>) -> Vec<Vec<EventResult>> {
+    let mut grouped: Vec<Vec<EventResult>> = Vec::new();
+    for result in results {
+        match grouped.last_mut() {
+            Some(group)
+                if group[0].tx_information.block_number == result.tx_information.block_number =>
+            {
+                group.push(result)
+            }
+            _ => grouped.push(vec![result]),
+        }
+    }
+    grouped
 }
 
 async fn handle_logs_result(
     config: Arc<EventProcessingConfig>,
     result: Result<FetchLogsResult, Box<dyn std::error::Error + Send>>,
-) -> Result<JoinHandle<()>, Box<dyn std::error::Error + Send>> {
+) -> Result<JoinHandle<Result<(), HandlerError>>, Box<dyn std::error::Error + Send>> {
     match result {
-        Ok(result) => {
+        Ok(mut result) => {
             debug!("Processing logs {} - length {}", config.event_name, result.logs.len());
 
-            let fn_data = result
+            config
+                .progress
+                .lock()
+                .await
+                .record_fetched_range(&config.network_contract.id, result.to_block);
+
+            if let Some(completed_ranges) = &config.completed_ranges {
+                completed_ranges.remove_done(&mut result.logs).await;
+            }
+
+            // the node can only filter on the topics so the address lists are applied here
+            if let IndexingContractSetup::Filter(filter) =
+                &config.network_contract.indexing_contract_setup
+            {
+                let fetched = result.logs.len();
+                result.logs.retain(|log| filter.allows_address(&log.inner.address));
+                if result.logs.len() < fetched {
+                    debug!(
+                        "{} - dropped {} logs of addresses outside the address lists",
+                        config.event_name,
+                        fetched - result.logs.len()
+                    );
+                }
+            }
+
+            let flagged_spam = match &config.spam_filter {
+                Some(spam_filter) => spam_filter.apply(&mut result.logs).await,
+                None => HashMap::new(),
+            };
+
+            let mut fn_data = result
                 .logs
                 .into_iter()
                 .map(|log| {
@@ -533,25 +1021,50 @@ async fn handle_logs_result(
                     )
                 })
                 .collect::<Vec<_>>();
+            flag_results(&mut fn_data, &flagged_spam);
 
-            // if shutting down so do not process anymore event
-            while !is_running() {
-                tokio::time::sleep(Duration::from_millis(1000)).await;
+            // if shutting down do not process anymore events, the range is not checkpointed so
+            // it will be fetched again on restart
+            if !is_running() {
+                return Ok(tokio::spawn(async { Ok(()) }));
             }
 
             if !fn_data.is_empty() {
                 return if config.index_event_in_order {
-                    trigger_event(config, fn_data, result.to_block).await;
-                    Ok(tokio::spawn(async {}))
+                    let result =
+                        trigger_event(config, fn_data, result.from_block, result.to_block).await;
+                    Ok(tokio::spawn(async { result }))
                 } else {
                     let task = tokio::spawn(async move {
-                        trigger_event(config, fn_data, result.to_block).await;
+                        // hold the permit for the whole callback to cap concurrent executions
+                        let _permit = match &config.callback_semaphore {
+                            Some(semaphore) => Some(
+                                Arc::clone(semaphore)
+                                    .acquire_owned()
+                                    .await
+                                    .expect("Callback semaphore should never be closed"),
+                            ),
+                            None => None,
+                        };
+                        trigger_event(config, fn_data, result.from_block, result.to_block).await
                     });
                     Ok(task)
                 }
             }
 
-            Ok(tokio::spawn(async {})) // Return a completed task
+            // a range without logs completes too, the ranges after it wait on it
+            if config.completed_ranges.is_some() {
+                indexing_event_processing();
+                update_progress_and_last_synced_task(
+                    config,
+                    result.from_block,
+                    result.to_block,
+                    0,
+                    indexing_event_processed,
+                );
+            }
+
+            Ok(tokio::spawn(async { Ok(()) })) // Return a completed task
         }
         Err(e) => {
             error!("Error fetching logs: {:?}", e);
@@ -559,3 +1072,86 @@ async fn handle_logs_result(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::Log;
+
+    use super::*;
+    use crate::event::callback_registry::{LogFoundInRequest, TxInformation};
+
+    fn event_result(block_number: u64, log_index: u64) -> EventResult {
+        EventResult {
+            log: Log::default(),
+            decoded_data: Arc::new(()),
+            tx_information: TxInformation {
+                network: "ethereum".to_string(),
+                address: Default::default(),
+                block_hash: H256::zero(),
+                block_number: U64::from(block_number),
+                block_timestamp: None,
+                transaction_hash: H256::zero(),
+                log_index: U256::from(log_index),
+                transaction_index: U64::zero(),
+            },
+            found_in_request: LogFoundInRequest {
+                from_block: U64::from(10),
+                to_block: U64::from(20),
+                completes_out_of_order: false,
+            },
+            spam: None,
+        }
+    }
+
+    fn positions(batches: &[Vec<EventResult>]) -> Vec<Vec<(u64, u64)>> {
+        batches
+            .iter()
+            .map(|batch| {
+                batch
+                    .iter()
+                    .map(|result| {
+                        (
+                            result.tx_information.block_number.as_u64(),
+                            result.tx_information.log_index.as_u64(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_handler_calls() {
+        assert_eq!(positions(&split_handler_calls(vec![], Some(2))), vec![vec![]]);
+
+        let results = || vec![event_result(10, 0), event_result(10, 1), event_result(11, 0)];
+        assert_eq!(
+            positions(&split_handler_calls(results(), None)),
+            vec![vec![(10, 0), (10, 1), (11, 0)]]
+        );
+        // a batch at the limit is handed over whole
+        assert_eq!(positions(&split_handler_calls(results(), Some(3))).len(), 1);
+        assert_eq!(
+            positions(&split_handler_calls(results(), Some(2))),
+            vec![vec![(10, 0), (10, 1)], vec![(11, 0)]]
+        );
+    }
+
+    #[test]
+    fn test_group_results_by_block() {
+        assert!(group_results_by_block(vec![]).is_empty());
+        assert_eq!(
+            positions(&group_results_by_block(vec![event_result(10, 0), event_result(10, 1)])),
+            vec![vec![(10, 0), (10, 1)]]
+        );
+        assert_eq!(
+            positions(&group_results_by_block(vec![
+                event_result(10, 0),
+                event_result(10, 1),
+                event_result(11, 0),
+                event_result(13, 2),
+            ])),
+            vec![vec![(10, 0), (10, 1)], vec![(11, 0)], vec![(13, 2)]]
+        );
+    }
+}
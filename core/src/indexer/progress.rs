@@ -1,12 +1,16 @@
 use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write,
     hash::{Hash, Hasher},
-    sync::Arc,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
 };
 
 use colored::{ColoredString, Colorize};
 use ethers::types::U64;
+use once_cell::sync::Lazy;
 use tokio::sync::Mutex;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::event::callback_registry::EventCallbackRegistryInformation;
 
@@ -19,7 +23,7 @@ pub enum IndexingEventProgressStatus {
 }
 
 impl IndexingEventProgressStatus {
-    fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &str {
         match self {
             Self::Syncing => "SYNCING",
             Self::Live => "LIVE",
@@ -29,10 +33,73 @@ impl IndexingEventProgressStatus {
     }
 
     pub fn log(&self) -> ColoredString {
-        self.as_str().green()
+        match self {
+            Self::Failed => self.as_str().red(),
+            _ => self.as_str().green(),
+        }
+    }
+}
+
+/// How far back the throughput is measured over, long enough to smooth out bursty ranges
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Sliding window of (time, last synced block, total logs processed) samples used to work out the
+/// recent throughput of an event
+#[derive(Clone, Debug, Default)]
+pub struct ThroughputWindow {
+    samples: VecDeque<(Instant, U64, u64)>,
+}
+
+impl ThroughputWindow {
+    /// Keeps the newest sample from before the window so the rates always cover all of it
+    fn record(&mut self, at: Instant, block: U64, total_logs: u64) {
+        self.samples.push_back((at, block, total_logs));
+        while let Some((next, _, _)) = self.samples.get(1) {
+            if at.duration_since(*next) > THROUGHPUT_WINDOW {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Returns (blocks per second, logs per second) across the window
+    fn rates(&self) -> (f64, f64) {
+        match (self.samples.front(), self.samples.back()) {
+            (Some((start, start_block, start_logs)), Some((end, end_block, end_logs))) => {
+                let elapsed = end.duration_since(*start).as_secs_f64();
+                if elapsed <= 0.0 {
+                    return (0.0, 0.0);
+                }
+                let blocks = end_block.saturating_sub(*start_block).as_u64() as f64;
+                let logs = end_logs.saturating_sub(*start_logs) as f64;
+                (blocks / elapsed, logs / elapsed)
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+}
+
+pub fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
     }
 }
 
+/// A range the handler halted on which the `skip` failure policy moved past
+#[derive(Clone, Debug)]
+pub struct SkippedRange {
+    pub from_block: U64,
+    pub to_block: U64,
+    pub error: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct IndexingEventProgress {
     pub id: String,
@@ -46,6 +113,26 @@ pub struct IndexingEventProgress {
     pub status: IndexingEventProgressStatus,
     pub progress: f64,
     pub info_log: String,
+    pub logs_processed: u64,
+    pub blocks_per_second: f64,
+    pub logs_per_second: f64,
+    pub eta: Option<Duration>,
+    /// Highest block range fetched, moves on even when no logs are found so shows the pipeline
+    /// is alive
+    pub last_fetched_block: U64,
+    pub last_fetched_at: Instant,
+    pub stalled: bool,
+    /// Every historic range up to the head seen at startup is handled, live indexing carries on
+    /// from the next block
+    pub backfill_complete: bool,
+    /// Why the event stopped indexing, set along with the failed status
+    pub failure: Option<String>,
+    pub skipped_ranges: Vec<SkippedRange>,
+    /// Latest block the head watcher saw on the network, unset until live indexing polls it
+    pub head_block: Option<U64>,
+    /// How far the synced blocks trail the head, the main lag number for a live indexer
+    pub blocks_behind_head: Option<u64>,
+    throughput: ThroughputWindow,
 }
 
 impl Hash for IndexingEventProgress {
@@ -63,6 +150,45 @@ impl Hash for IndexingEventProgress {
 }
 
 impl IndexingEventProgress {
+    fn record_throughput(&mut self, new_last_synced_block: U64, logs: usize) {
+        self.logs_processed += logs as u64;
+        if self.throughput.samples.is_empty() {
+            // seed the window with where this run started so the first range counts
+            self.throughput.record(Instant::now(), self.last_synced_block, 0);
+        }
+        self.throughput.record(Instant::now(), new_last_synced_block, self.logs_processed);
+
+        let (blocks_per_second, logs_per_second) = self.throughput.rates();
+        self.blocks_per_second = blocks_per_second;
+        self.logs_per_second = logs_per_second;
+
+        let remaining = self.syncing_to_block.saturating_sub(new_last_synced_block).as_u64();
+        self.eta = if remaining == 0 {
+            Some(Duration::ZERO)
+        } else if blocks_per_second > 0.0 {
+            Some(Duration::from_secs_f64(remaining as f64 / blocks_per_second))
+        } else {
+            None
+        };
+    }
+
+    fn refresh_blocks_behind_head(&mut self) {
+        self.blocks_behind_head =
+            self.head_block.map(|head| head.saturating_sub(self.last_synced_block).as_u64());
+    }
+
+    fn progress_log(&self) -> String {
+        format!(
+            "{} - network {} - {:.2}% progress - {:.1} blocks/s - {:.1} logs/s - ETA {}",
+            self.info_log,
+            self.network,
+            self.progress * 100.0,
+            self.blocks_per_second,
+            self.logs_per_second,
+            self.eta.map_or("unknown".to_string(), format_eta)
+        )
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn running(
         id: String,
@@ -87,6 +213,19 @@ impl IndexingEventProgress {
             status: IndexingEventProgressStatus::Syncing,
             progress: 0.0,
             info_log,
+            logs_processed: 0,
+            blocks_per_second: 0.0,
+            logs_per_second: 0.0,
+            eta: None,
+            last_fetched_block: last_synced_block,
+            last_fetched_at: Instant::now(),
+            stalled: false,
+            backfill_complete: false,
+            failure: None,
+            skipped_ranges: Vec::new(),
+            head_block: None,
+            blocks_behind_head: None,
+            throughput: ThroughputWindow::default(),
         }
     }
 }
@@ -95,6 +234,88 @@ pub struct IndexingEventsProgressState {
     pub events: Vec<IndexingEventProgress>,
 }
 
+/// The progress of every project indexing in the process keyed by indexer name, read by the APIs
+/// served next to the indexer
+static PUBLISHED_PROGRESS: Lazy<RwLock<HashMap<String, Arc<Mutex<IndexingEventsProgressState>>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+pub fn publish_progress(indexer_name: &str, progress: &Arc<Mutex<IndexingEventsProgressState>>) {
+    PUBLISHED_PROGRESS
+        .write()
+        .expect("published progress lock poisoned")
+        .insert(indexer_name.to_string(), Arc::clone(progress));
+}
+
+/// Unset until the indexer of the project starts in this process
+pub fn published_progress(indexer_name: &str) -> Option<Arc<Mutex<IndexingEventsProgressState>>> {
+    PUBLISHED_PROGRESS.read().expect("published progress lock poisoned").get(indexer_name).cloned()
+}
+
+fn write_progress_metrics(
+    metrics: &mut String,
+    indexer_name: &str,
+    events: &[IndexingEventProgress],
+) {
+    for event in events {
+        let labels = format!(
+            "indexer=\"{}\",contract=\"{}\",event=\"{}\",network=\"{}\"",
+            indexer_name, event.contract_name, event.event_name, event.network
+        );
+        let _ = writeln!(
+            metrics,
+            "rindexer_event_logs_processed_total{{{}}} {}",
+            labels, event.logs_processed
+        );
+        let _ = writeln!(
+            metrics,
+            "rindexer_event_last_synced_block{{{}}} {}",
+            labels, event.last_synced_block
+        );
+        let _ = writeln!(
+            metrics,
+            "rindexer_event_blocks_per_second{{{}}} {}",
+            labels, event.blocks_per_second
+        );
+        let _ = writeln!(
+            metrics,
+            "rindexer_event_logs_per_second{{{}}} {}",
+            labels, event.logs_per_second
+        );
+        // no sample is written while the rate is unknown
+        if let Some(eta) = event.eta {
+            let _ =
+                writeln!(metrics, "rindexer_event_eta_seconds{{{}}} {}", labels, eta.as_secs_f64());
+        }
+    }
+}
+
+/// The throughput and ETA of every event of the projects indexing in the process in the
+/// prometheus text format
+pub async fn render_progress_metrics() -> String {
+    let mut metrics = String::from(
+        "# HELP rindexer_event_logs_processed_total Logs handled per event since the indexer started\n\
+         # TYPE rindexer_event_logs_processed_total counter\n\
+         # HELP rindexer_event_last_synced_block Last block synced per event\n\
+         # TYPE rindexer_event_last_synced_block gauge\n\
+         # HELP rindexer_event_blocks_per_second Blocks synced per second over the last minute\n\
+         # TYPE rindexer_event_blocks_per_second gauge\n\
+         # HELP rindexer_event_logs_per_second Logs handled per second over the last minute\n\
+         # TYPE rindexer_event_logs_per_second gauge\n\
+         # HELP rindexer_event_eta_seconds Estimated seconds until the event reaches the block it syncs to\n\
+         # TYPE rindexer_event_eta_seconds gauge\n",
+    );
+    let published: Vec<(String, Arc<Mutex<IndexingEventsProgressState>>)> = PUBLISHED_PROGRESS
+        .read()
+        .expect("published progress lock poisoned")
+        .iter()
+        .map(|(name, progress)| (name.clone(), Arc::clone(progress)))
+        .collect();
+    for (indexer_name, progress) in published {
+        write_progress_metrics(&mut metrics, &indexer_name, &progress.lock().await.events);
+    }
+    metrics
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum SyncError {
     #[error("Event with id {0} not found")]
@@ -145,13 +366,94 @@ impl IndexingEventsProgressState {
         Arc::new(Mutex::new(Self { events }))
     }
 
+    pub fn record_fetched_range(&mut self, id: &str, to_block: U64) {
+        if let Some(event) = self.events.iter_mut().find(|e| e.id == id) {
+            if to_block > event.last_fetched_block {
+                event.last_fetched_block = to_block;
+                event.last_fetched_at = Instant::now();
+                event.stalled = false;
+            }
+        }
+    }
+
+    /// A live poll which had nothing to fetch yet, no new block or one still within the reorg
+    /// safe distance, shows the pipeline is alive without moving the fetched block
+    pub fn record_live_poll(&mut self, id: &str) {
+        if let Some(event) = self.events.iter_mut().find(|e| e.id == id) {
+            event.last_fetched_at = Instant::now();
+            event.stalled = false;
+        }
+    }
+
+    /// Records the head the live poller saw, the head only moves forward
+    pub fn record_head(&mut self, id: &str, head: U64) {
+        if let Some(event) = self.events.iter_mut().find(|e| e.id == id) {
+            event.head_block = Some(event.head_block.map_or(head, |seen| seen.max(head)));
+            event.refresh_blocks_behind_head();
+        }
+    }
+
+    pub fn complete_backfill(&mut self, id: &str) {
+        if let Some(event) = self.events.iter_mut().find(|e| e.id == id) {
+            event.backfill_complete = true;
+        }
+    }
+
+    /// Marks the event as failed, returns the last block it synced
+    pub fn fail(&mut self, id: &str, error: String) -> Option<U64> {
+        let event = self.events.iter_mut().find(|e| e.id == id)?;
+        event.status = IndexingEventProgressStatus::Failed;
+        event.failure = Some(error);
+        Some(event.last_synced_block)
+    }
+
+    pub fn record_skipped_range(&mut self, id: &str, range: SkippedRange) {
+        if let Some(event) = self.events.iter_mut().find(|e| e.id == id) {
+            event.skipped_ranges.push(range);
+        }
+    }
+
+    /// Logs the events which failed or skipped ranges, once indexing is over
+    pub fn log_terminal_state(&self) {
+        for event in &self.events {
+            if let Some(failure) = &event.failure {
+                error!(
+                    "{} - network {} - {} - stopped after block {}: {}",
+                    event.info_log,
+                    event.network,
+                    IndexingEventProgressStatus::Failed.log(),
+                    event.last_synced_block,
+                    failure
+                );
+            }
+            if !event.skipped_ranges.is_empty() {
+                let ranges = event
+                    .skipped_ranges
+                    .iter()
+                    .map(|range| format!("{}-{}", range.from_block, range.to_block))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                warn!(
+                    "{} - network {} - skipped {} failed ranges: {}",
+                    event.info_log,
+                    event.network,
+                    event.skipped_ranges.len(),
+                    ranges
+                );
+            }
+        }
+    }
+
     pub fn update_last_synced_block(
         &mut self,
         id: &str,
         new_last_synced_block: U64,
+        logs: usize,
     ) -> Result<(), SyncError> {
         for event in &mut self.events {
             if event.id == id {
+                event.record_throughput(new_last_synced_block, logs);
+
                 if event.progress < 1.0 {
                     if event.syncing_to_block > event.last_synced_block {
                         let total_blocks: u64 = event
@@ -190,13 +492,11 @@ impl IndexingEventsProgressState {
 
                     if new_last_synced_block >= event.syncing_to_block {
                         event.progress = 1.0;
-                        info!(
-                            "{} - network {} - {:.2}% progress",
-                            event.info_log,
-                            event.network,
-                            event.progress * 100.0
-                        );
-                        event.status = if event.live_indexing {
+                        info!("{}", event.progress_log());
+                        // handlers still in flight when the event failed do not clear it
+                        event.status = if event.failure.is_some() {
+                            IndexingEventProgressStatus::Failed
+                        } else if event.live_indexing {
                             IndexingEventProgressStatus::Live
                         } else {
                             IndexingEventProgressStatus::Completed
@@ -204,16 +504,12 @@ impl IndexingEventsProgressState {
                     }
 
                     if event.progress != 1.0 {
-                        info!(
-                            "{} - network {} - {:.2}% progress",
-                            event.info_log,
-                            event.network,
-                            event.progress * 100.0
-                        );
+                        info!("{}", event.progress_log());
                     }
                 }
 
                 event.last_synced_block = new_last_synced_block;
+                event.refresh_blocks_behind_head();
                 return Ok(());
             }
         }
@@ -221,3 +517,153 @@ impl IndexingEventsProgressState {
         Err(SyncError::EventNotFound(id.to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throughput_window_rates() {
+        let start = Instant::now();
+        let mut window = ThroughputWindow::default();
+        window.record(start, U64::from(100), 0);
+        window.record(start + Duration::from_secs(10), U64::from(600), 50);
+
+        let (blocks_per_second, logs_per_second) = window.rates();
+        assert_eq!(blocks_per_second, 50.0);
+        assert_eq!(logs_per_second, 5.0);
+    }
+
+    #[test]
+    fn test_throughput_window_drops_old_samples() {
+        let start = Instant::now();
+        let mut window = ThroughputWindow::default();
+        window.record(start, U64::from(0), 0);
+        window.record(start + Duration::from_secs(30), U64::from(30), 0);
+        window.record(start + Duration::from_secs(90), U64::from(90), 0);
+        window.record(start + Duration::from_secs(100), U64::from(100), 0);
+
+        assert_eq!(window.samples.front().unwrap().1, U64::from(30));
+    }
+
+    #[test]
+    fn test_progress_metrics() {
+        let mut event = IndexingEventProgress::running(
+            "id".to_string(),
+            "Contract".to_string(),
+            "Transfer".to_string(),
+            U64::from(0),
+            U64::from(0),
+            U64::from(100),
+            "ethereum".to_string(),
+            false,
+            "Contract::Transfer".to_string(),
+        );
+        let mut metrics = String::new();
+        write_progress_metrics(&mut metrics, "Indexer", std::slice::from_ref(&event));
+        assert!(!metrics.contains("rindexer_event_eta_seconds"));
+
+        event.logs_processed = 7;
+        event.blocks_per_second = 2.5;
+        event.eta = Some(Duration::from_secs(40));
+        let mut metrics = String::new();
+        write_progress_metrics(&mut metrics, "Indexer", &[event]);
+        let labels = r#"indexer="Indexer",contract="Contract",event="Transfer",network="ethereum""#;
+        assert!(metrics.contains(&format!("rindexer_event_logs_processed_total{{{}}} 7\n", labels)));
+        assert!(metrics.contains(&format!("rindexer_event_blocks_per_second{{{}}} 2.5\n", labels)));
+        assert!(metrics.contains(&format!("rindexer_event_eta_seconds{{{}}} 40\n", labels)));
+    }
+
+    #[test]
+    fn test_format_eta() {
+        assert_eq!(format_eta(Duration::from_secs(42)), "42s");
+        assert_eq!(format_eta(Duration::from_secs(125)), "2m 5s");
+        assert_eq!(format_eta(Duration::from_secs(3725)), "1h 2m 5s");
+    }
+
+    #[test]
+    fn test_blocks_behind_head() {
+        let mut state = IndexingEventsProgressState {
+            events: vec![IndexingEventProgress::running(
+                "id".to_string(),
+                "Contract".to_string(),
+                "Transfer".to_string(),
+                U64::from(0),
+                U64::from(0),
+                U64::from(100),
+                "ethereum".to_string(),
+                true,
+                "Contract::Transfer".to_string(),
+            )],
+        };
+        assert_eq!(state.events[0].blocks_behind_head, None);
+
+        state.record_head("id", U64::from(120));
+        state.update_last_synced_block("id", U64::from(100), 1).unwrap();
+        assert_eq!(state.events[0].blocks_behind_head, Some(20));
+
+        // a lagging node answering the poll does not move the head back
+        state.record_head("id", U64::from(110));
+        assert_eq!(state.events[0].head_block, Some(U64::from(120)));
+        state.update_last_synced_block("id", U64::from(125), 1).unwrap();
+        assert_eq!(state.events[0].blocks_behind_head, Some(0));
+    }
+
+    #[test]
+    fn test_failed_event_stays_failed() {
+        let mut state = IndexingEventsProgressState {
+            events: vec![IndexingEventProgress::running(
+                "id".to_string(),
+                "Contract".to_string(),
+                "Transfer".to_string(),
+                U64::from(0),
+                U64::from(0),
+                U64::from(100),
+                "ethereum".to_string(),
+                false,
+                "Contract::Transfer".to_string(),
+            )],
+        };
+        state.update_last_synced_block("id", U64::from(40), 1).unwrap();
+
+        assert_eq!(state.fail("id", "handler halted".to_string()), Some(U64::from(40)));
+        // a handler which was in flight finishing the last range does not complete the event
+        state.update_last_synced_block("id", U64::from(100), 1).unwrap();
+
+        let event = &state.events[0];
+        assert!(matches!(event.status, IndexingEventProgressStatus::Failed));
+        assert_eq!(event.failure.as_deref(), Some("handler halted"));
+    }
+
+    #[test]
+    fn test_live_polls_without_logs_keep_the_event_alive() {
+        let mut state = IndexingEventsProgressState {
+            events: vec![IndexingEventProgress::running(
+                "id".to_string(),
+                "Contract".to_string(),
+                "Transfer".to_string(),
+                U64::from(0),
+                U64::from(100),
+                U64::from(100),
+                "ethereum".to_string(),
+                true,
+                "Contract::Transfer".to_string(),
+            )],
+        };
+        let stale = Instant::now() - Duration::from_secs(600);
+        state.events[0].last_fetched_at = stale;
+        state.events[0].stalled = true;
+
+        // waiting on the reorg safe distance
+        state.record_live_poll("id");
+        assert!(state.events[0].last_fetched_at > stale);
+        assert!(!state.events[0].stalled);
+        assert_eq!(state.events[0].last_fetched_block, U64::from(100));
+
+        // a block the logs bloom showed has no logs of the event
+        state.events[0].last_fetched_at = stale;
+        state.record_fetched_range("id", U64::from(101));
+        assert!(state.events[0].last_fetched_at > stale);
+        assert_eq!(state.events[0].last_fetched_block, U64::from(101));
+    }
+}
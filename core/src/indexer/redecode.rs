@@ -0,0 +1,170 @@
+use std::{path::Path, sync::Arc};
+
+use ethers::types::U64;
+use tracing::info;
+
+use crate::{
+    database::postgres::{
+        client::PostgresError,
+        generate::generate_event_table_full_name,
+        raw_logs::RawLogArchive,
+        setup::{setup_postgres, SetupPostgresError},
+    },
+    event::callback_registry::{EventCallbackRegistry, EventResult},
+    indexer::no_code::{process_events, ProcessIndexersError},
+    manifest::core::Manifest,
+    provider::{CreateNetworkProvider, RetryClientError, WrappedLog},
+};
+
+/// How many archived logs go to the handler at once
+const REDECODE_BATCH_SIZE: i64 = 1000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum RedecodeError {
+    #[error("Redecoding rewrites the event tables so needs postgres storage enabled")]
+    PostgresNotEnabled,
+
+    #[error("Contract {0} is not in the manifest")]
+    UnknownContract(String),
+
+    #[error("Contract {0} is not indexed on network {1}")]
+    UnknownNetwork(String, String),
+
+    #[error("Contract {0} has no event {1} to index")]
+    UnknownEvent(String, String),
+
+    #[error("Contract {0} does not archive its raw logs, set archive_raw_logs to keep them")]
+    NotArchived(String),
+
+    #[error("{0}")]
+    SetupPostgres(#[from] SetupPostgresError),
+
+    #[error("{0}")]
+    Provider(#[from] RetryClientError),
+
+    #[error("{0}")]
+    ProcessEvents(#[from] ProcessIndexersError),
+
+    #[error("{0}")]
+    Postgres(#[from] PostgresError),
+
+    #[error("Handler failed on archived logs of {0}: {1}")]
+    Handler(String, String),
+}
+
+#[derive(Debug, Default)]
+pub struct RedecodeSummary {
+    pub removed: u64,
+    pub redecoded: usize,
+}
+
+/// Decodes the archived raw logs of an event again with the current ABI and rewrites the rows of
+/// the event table they were decoded into, without fetching anything from the nodes. The rows go
+/// through the same handlers indexing uses, the indexer should not be running meanwhile.
+pub async fn redecode_event(
+    project_path: &Path,
+    manifest: &Manifest,
+    contract_name: &str,
+    event_name: &str,
+    network: Option<&str>,
+) -> Result<RedecodeSummary, RedecodeError> {
+    if !manifest.storage.postgres_enabled() {
+        return Err(RedecodeError::PostgresNotEnabled);
+    }
+
+    let contract = manifest
+        .contracts
+        .iter()
+        .find(|contract| contract.name == contract_name)
+        .ok_or_else(|| RedecodeError::UnknownContract(contract_name.to_string()))?;
+    if !contract.archive_raw_logs() {
+        return Err(RedecodeError::NotArchived(contract_name.to_string()));
+    }
+
+    let mut redecode_contract = contract.clone();
+    redecode_contract.include_events = Some(vec![event_name.to_string()]);
+    if let Some(network) = network {
+        redecode_contract.details.retain(|details| details.network == network);
+        if redecode_contract.details.is_empty() {
+            return Err(RedecodeError::UnknownNetwork(
+                contract_name.to_string(),
+                network.to_string(),
+            ));
+        }
+    }
+
+    // brings the table in line with the fixed ABI first
+    let postgres = Arc::new(setup_postgres(project_path, manifest).await?);
+
+    let mut redecode_manifest = manifest.clone();
+    redecode_manifest.contracts = vec![redecode_contract];
+    redecode_manifest.alerts = None;
+    redecode_manifest.modules = None;
+    let network_providers = CreateNetworkProvider::create(&redecode_manifest)?;
+    let registry = EventCallbackRegistry {
+        events: process_events(
+            project_path,
+            &mut redecode_manifest,
+            Some(Arc::clone(&postgres)),
+            &network_providers,
+        )
+        .await?,
+    };
+    let event =
+        registry.events.iter().find(|event| event.event_name == event_name).ok_or_else(|| {
+            RedecodeError::UnknownEvent(contract_name.to_string(), event_name.to_string())
+        })?;
+
+    let archive =
+        RawLogArchive::new(Arc::clone(&postgres), &manifest.name, &event.contract.name, event_name);
+    let event_table = generate_event_table_full_name(
+        &manifest.name,
+        contract.table_group.as_deref().unwrap_or(&contract.name),
+        event_name,
+    );
+
+    let mut summary = RedecodeSummary::default();
+    for network_contract in &event.contract.details {
+        let network = &network_contract.network;
+        let network_contract = Arc::new(network_contract.clone());
+        summary.removed += archive.remove_decoded_rows(&event_table, network).await?;
+
+        let mut after: Option<(U64, U64)> = None;
+        loop {
+            let logs = archive.read_page(network, after, REDECODE_BATCH_SIZE).await?;
+            let Some(last) = logs.last() else {
+                break;
+            };
+            let from_block = logs[0].block_number.unwrap_or_default();
+            let to_block = last.block_number.unwrap_or_default();
+            after = Some((to_block, U64::from(last.log_index.unwrap_or_default().low_u64())));
+
+            let redecoded = logs.len();
+            let results = logs
+                .into_iter()
+                .map(|log| {
+                    EventResult::new(
+                        Arc::clone(&network_contract),
+                        WrappedLog { inner: log, block_timestamp: None },
+                        from_block,
+                        to_block,
+                    )
+                })
+                .collect();
+            registry
+                .trigger_event(&event.id, results, None)
+                .await
+                .map_err(|e| RedecodeError::Handler(event.info_log_name(), e.to_string()))?;
+            summary.redecoded += redecoded;
+        }
+
+        info!(
+            "Redecoded {} on {} - {} archived logs so far",
+            event.info_log_name(),
+            network,
+            summary.redecoded
+        );
+    }
+
+    Ok(summary)
+}
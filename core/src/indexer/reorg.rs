@@ -1,9 +1,73 @@
+use std::{collections::HashMap, sync::RwLock};
+
 use ethers::types::{U256, U64};
+use once_cell::sync::Lazy;
 
-pub fn reorg_safe_distance_for_chain(chain_id: &U256) -> U64 {
+/// The block live indexing of an event fetches again from after the reorg watcher removed the
+/// rows of a replaced block, keyed by the event pause key and network
+static REORG_REWINDS: Lazy<RwLock<HashMap<String, U64>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+fn reorg_rewind_key(pause_key: &str, network: &str) -> String {
+    format!("{}.{}", pause_key, network)
+}
+
+/// The earliest block is kept when the event is rewound again before live indexing picked it up
+pub fn request_reorg_rewind(pause_key: &str, network: &str, from_block: U64) {
+    REORG_REWINDS
+        .write()
+        .expect("reorg rewinds lock poisoned")
+        .entry(reorg_rewind_key(pause_key, network))
+        .and_modify(|block| *block = (*block).min(from_block))
+        .or_insert(from_block);
+}
+
+pub fn take_reorg_rewind(pause_key: &str, network: &str) -> Option<U64> {
+    REORG_REWINDS
+        .write()
+        .expect("reorg rewinds lock poisoned")
+        .remove(&reorg_rewind_key(pause_key, network))
+}
+
+/// Chains whose blocks and finality work differently to mainnet. Arbitrum logs carry the L2 block
+/// number while `block.number` inside its contracts is the L1 one, so block numbers in event
+/// values do not line up with the indexed `block_number`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainFamily {
+    Ethereum,
+    /// 250ms blocks from the sequencer, `safe` is the newest block posted to L1
+    Arbitrum,
+    /// 2s blocks with deposit transactions from L1, `safe` is the newest block derived from L1
+    OpStack,
+    Other,
+}
+
+impl ChainFamily {
+    pub fn is_l2(&self) -> bool {
+        matches!(self, ChainFamily::Arbitrum | ChainFamily::OpStack)
+    }
+}
+
+pub fn chain_family(chain_id: &U256) -> ChainFamily {
     match chain_id.as_u64() {
-        1 => U64::from(12),
-        _ => U64::from(64),
+        1 => ChainFamily::Ethereum,
+        // one, nova and sepolia
+        42161 | 42170 | 421614 => ChainFamily::Arbitrum,
+        // optimism, base, zora, mode, fraxtal, unichain, world chain and their sepolias
+        10 | 8453 | 7777777 | 34443 | 252 | 130 | 480 | 11155420 | 84532 | 999999999 | 919 |
+        1301 | 4801 => ChainFamily::OpStack,
+        _ => ChainFamily::Other,
+    }
+}
+
+/// The L2 distances cover about the same time as the 12 mainnet blocks, counting the L2 blocks
+/// as mainnet blocks would make the window a few seconds
+pub fn reorg_safe_distance_for_chain(chain_id: &U256) -> U64 {
+    match chain_family(chain_id) {
+        ChainFamily::Ethereum => U64::from(12),
+        ChainFamily::Arbitrum => U64::from(600),
+        ChainFamily::OpStack => U64::from(75),
+        ChainFamily::Other => U64::from(64),
     }
 }
 
@@ -23,5 +87,26 @@ mod tests {
 
         let other_chain_id = U256::from(42);
         assert_eq!(reorg_safe_distance_for_chain(&other_chain_id), U64::from(64));
+
+        let arbitrum_chain_id = U256::from(42161);
+        assert_eq!(reorg_safe_distance_for_chain(&arbitrum_chain_id), U64::from(600));
+
+        let base_chain_id = U256::from(8453);
+        assert_eq!(reorg_safe_distance_for_chain(&base_chain_id), U64::from(75));
+        assert!(chain_family(&base_chain_id).is_l2());
+    }
+
+    #[test]
+    fn test_reorg_rewinds_keep_the_earliest_block() {
+        request_reorg_rewind("indexer.Contract.Transfer", "ethereum", U64::from(120));
+        request_reorg_rewind("indexer.Contract.Transfer", "ethereum", U64::from(100));
+        request_reorg_rewind("indexer.Contract.Transfer", "ethereum", U64::from(110));
+
+        assert_eq!(take_reorg_rewind("indexer.Contract.Transfer", "base"), None);
+        assert_eq!(
+            take_reorg_rewind("indexer.Contract.Transfer", "ethereum"),
+            Some(U64::from(100))
+        );
+        assert_eq!(take_reorg_rewind("indexer.Contract.Transfer", "ethereum"), None);
     }
 }
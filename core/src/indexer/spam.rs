@@ -0,0 +1,443 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use ethers::{
+    abi::Event,
+    types::{Address, Log, H256},
+};
+use futures::{stream, StreamExt};
+use once_cell::sync::Lazy;
+use tracing::{error, warn};
+
+use crate::{
+    database::postgres::{
+        spam_contracts::SpamContractsStore, sql_type_wrapper::EthereumSqlTypeWrapper,
+    },
+    event::callback_registry::EventResult,
+    indexer::log_helpers::parse_log,
+    manifest::{
+        abi_resolver::is_verified,
+        contract::{SpamAction, SpamFilterSettings},
+    },
+    provider::WrappedLog,
+};
+
+/// Why the spam filter took a contract for spam
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpamReason {
+    DenyList,
+    UndecodableLog,
+    Airdrop,
+    UnverifiedAbi,
+}
+
+impl SpamReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpamReason::DenyList => "deny_list",
+            SpamReason::UndecodableLog => "undecodable_log",
+            SpamReason::Airdrop => "airdrop",
+            SpamReason::UnverifiedAbi => "unverified_abi",
+        }
+    }
+
+    fn from_name(value: &str) -> Option<Self> {
+        match value {
+            "deny_list" => Some(SpamReason::DenyList),
+            "undecodable_log" => Some(SpamReason::UndecodableLog),
+            "airdrop" => Some(SpamReason::Airdrop),
+            "unverified_abi" => Some(SpamReason::UnverifiedAbi),
+            _ => None,
+        }
+    }
+}
+
+/// Spam contracts per network, a contract one event finds is spam for every event
+static SPAM_CONTRACTS: Lazy<RwLock<HashMap<(String, Address), SpamReason>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// The most verified ABI lookups a batch of logs runs at once
+const MAX_CONCURRENT_VERIFICATION_LOOKUPS: usize = 8;
+
+/// A contract may be verified later on so an unverified one is looked up again after this long
+const UNVERIFIED_RECHECK_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// A failed lookup is not retried for this long so an explorer being down is not hammered
+const FAILED_LOOKUP_RETRY_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// What a verified ABI lookup found and when
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verification {
+    Verified,
+    Unverified(Instant),
+    Failed(Instant),
+}
+
+impl Verification {
+    /// Whether the contract counts as unverified, `None` once the lookup is due again. A failed
+    /// lookup counts as verified so an explorer being down never drops events.
+    fn is_unverified(&self, now: Instant) -> Option<bool> {
+        match self {
+            Verification::Verified => Some(false),
+            Verification::Unverified(at) => {
+                (now.duration_since(*at) < UNVERIFIED_RECHECK_AFTER).then_some(true)
+            }
+            Verification::Failed(at) => {
+                (now.duration_since(*at) < FAILED_LOOKUP_RETRY_AFTER).then_some(false)
+            }
+        }
+    }
+}
+
+/// Verified ABI lookups per chain, a verified contract is only looked up once
+static VERIFIED_CONTRACTS: Lazy<RwLock<HashMap<(u64, Address), Verification>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Every spam filter looks contracts up through the one client so connections are reused
+static VERIFICATION_CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+
+/// Why a contract is spam, `None` when no spam filter found it
+pub fn spam_contract_reason(network: &str, address: &Address) -> Option<SpamReason> {
+    SPAM_CONTRACTS
+        .read()
+        .expect("spam contracts lock poisoned")
+        .get(&(network.to_string(), *address))
+        .copied()
+}
+
+/// Sets why the spam filter flagged the contract which emitted each result
+pub fn flag_results(results: &mut [EventResult], flagged: &HashMap<Address, SpamReason>) {
+    for result in results {
+        result.spam = flagged.get(&result.tx_information.address).copied();
+    }
+}
+
+/// The `spam_reason` column value of an event, NULL when its contract was not flagged
+pub fn spam_reason_column_value(spam: Option<SpamReason>) -> EthereumSqlTypeWrapper {
+    EthereumSqlTypeWrapper::StringNullable(
+        spam.map(|reason| reason.as_str().to_string()).unwrap_or_default(),
+    )
+}
+
+/// The contracts emitting at least `threshold` of the logs in a single transaction
+fn airdrop_contracts<'a>(
+    logs: impl Iterator<Item = &'a Log>,
+    threshold: usize,
+) -> HashSet<Address> {
+    let mut counts: HashMap<(H256, Address), usize> = HashMap::new();
+    for log in logs {
+        if let Some(transaction_hash) = log.transaction_hash {
+            *counts.entry((transaction_hash, log.address)).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count >= threshold)
+        .map(|((_, address), _)| address)
+        .collect()
+}
+
+/// Checks the logs of an event on a network for spam contracts before they are handled
+pub struct SpamFilter {
+    network: String,
+    chain_id: u64,
+    settings: SpamFilterSettings,
+    /// The event in the ABI, logs of the contract should decode as it
+    event: Option<Event>,
+    etherscan_api_key: Option<String>,
+    store: Option<SpamContractsStore>,
+}
+
+impl SpamFilter {
+    pub fn new(
+        network: &str,
+        chain_id: u64,
+        settings: SpamFilterSettings,
+        event: Option<Event>,
+        etherscan_api_key: Option<String>,
+        store: Option<SpamContractsStore>,
+    ) -> Self {
+        Self { network: network.to_string(), chain_id, settings, event, etherscan_api_key, store }
+    }
+
+    /// Picks up the spam contracts found before a restart
+    pub async fn load_found(&self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        match store.load(&self.network).await {
+            Ok(found) => {
+                let mut spam_contracts =
+                    SPAM_CONTRACTS.write().expect("spam contracts lock poisoned");
+                for (address, reason) in found {
+                    if let Some(reason) = SpamReason::from_name(&reason) {
+                        spam_contracts.insert((self.network.clone(), address), reason);
+                    }
+                }
+            }
+            Err(e) => error!("Could not load the spam contracts of {}: {}", self.network, e),
+        }
+    }
+
+    fn is_undecodable(&self, log: &Log) -> bool {
+        self.settings.undecodable_logs() &&
+            self.event.as_ref().is_some_and(|event| parse_log(event, log).is_none())
+    }
+
+    async fn is_unverified(&self, address: Address) -> bool {
+        if let Some(unverified) = VERIFIED_CONTRACTS
+            .read()
+            .expect("verified contracts lock poisoned")
+            .get(&(self.chain_id, address))
+            .and_then(|verification| verification.is_unverified(Instant::now()))
+        {
+            return unverified;
+        }
+
+        let verification = match is_verified(
+            &VERIFICATION_CLIENT,
+            self.etherscan_api_key.as_deref(),
+            self.chain_id,
+            address,
+        )
+        .await
+        {
+            Ok(true) => Verification::Verified,
+            Ok(false) => Verification::Unverified(Instant::now()),
+            Err(e) => {
+                warn!("Could not look up whether {:?} is verified: {}", address, e);
+                Verification::Failed(Instant::now())
+            }
+        };
+        VERIFIED_CONTRACTS
+            .write()
+            .expect("verified contracts lock poisoned")
+            .insert((self.chain_id, address), verification);
+        verification.is_unverified(Instant::now()).unwrap_or_default()
+    }
+
+    async fn remember(&self, address: Address, reason: SpamReason) {
+        let is_new = SPAM_CONTRACTS
+            .write()
+            .expect("spam contracts lock poisoned")
+            .insert((self.network.clone(), address), reason)
+            .is_none();
+        if !is_new {
+            return;
+        }
+
+        warn!("{:?} on {} is spam - {}", address, self.network, reason.as_str());
+        if let Some(store) = &self.store {
+            if let Err(e) = store.record(&self.network, address, reason.as_str()).await {
+                error!("Could not record the spam contract {:?}: {}", address, e);
+            }
+        }
+    }
+
+    /// Drops the logs of spam contracts, or keeps them when they are only flagged and returns
+    /// why each of their contracts is spam
+    pub async fn apply(&self, logs: &mut Vec<WrappedLog>) -> HashMap<Address, SpamReason> {
+        let airdrops = airdrop_contracts(
+            logs.iter().map(|log| &log.inner),
+            self.settings.airdrop_logs_per_transaction(),
+        );
+
+        let mut judged: HashMap<Address, Option<SpamReason>> = HashMap::new();
+        for log in logs.iter() {
+            let address = log.inner.address;
+            if matches!(judged.get(&address), Some(Some(_))) {
+                continue;
+            }
+            let reason = spam_contract_reason(&self.network, &address)
+                .or_else(|| self.settings.denied.contains(&address).then_some(SpamReason::DenyList))
+                .or_else(|| airdrops.contains(&address).then_some(SpamReason::Airdrop))
+                .or_else(|| self.is_undecodable(&log.inner).then_some(SpamReason::UndecodableLog));
+            judged.insert(address, reason);
+        }
+
+        if self.settings.unverified_contracts() {
+            // collected up front as a stream over borrowing closures makes the future not `Send`
+            let candidates: Vec<Address> = judged
+                .iter()
+                .filter(|(_, reason)| reason.is_none())
+                .map(|(address, _)| *address)
+                .collect();
+            let unverified: Vec<Address> = stream::iter(candidates)
+                .map(|address| async move { (address, self.is_unverified(address).await) })
+                .buffer_unordered(MAX_CONCURRENT_VERIFICATION_LOOKUPS)
+                .filter_map(|(address, unverified)| async move { unverified.then_some(address) })
+                .collect()
+                .await;
+            for address in unverified {
+                judged.insert(address, Some(SpamReason::UnverifiedAbi));
+            }
+        }
+
+        let spam: HashMap<Address, SpamReason> = judged
+            .into_iter()
+            .filter_map(|(address, reason)| reason.map(|reason| (address, reason)))
+            .collect();
+        for (address, reason) in &spam {
+            self.remember(*address, *reason).await;
+        }
+
+        match self.settings.action {
+            SpamAction::Drop => {
+                logs.retain(|log| !spam.contains_key(&log.inner.address));
+                HashMap::new()
+            }
+            SpamAction::Flag => spam,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ethers::types::U64;
+
+    use super::*;
+    use crate::event::callback_registry::{LogFoundInRequest, TxInformation};
+
+    fn wrapped_log(address: Address, transaction: u8) -> WrappedLog {
+        WrappedLog {
+            inner: Log {
+                address,
+                transaction_hash: Some(H256::repeat_byte(transaction)),
+                ..Default::default()
+            },
+            block_timestamp: None,
+        }
+    }
+
+    fn spam_filter(network: &str, action: SpamAction, denied: Address) -> SpamFilter {
+        let settings = SpamFilterSettings {
+            action,
+            undecodable_logs: None,
+            airdrop_logs_per_transaction: None,
+            unverified_contracts: None,
+            deny_list: None,
+            denied: Arc::new(HashSet::from([denied])),
+        };
+        SpamFilter::new(network, 1, settings, None, None, None)
+    }
+
+    fn event_result(log: &WrappedLog) -> EventResult {
+        EventResult {
+            log: log.inner.clone(),
+            decoded_data: Arc::new(()),
+            tx_information: TxInformation {
+                network: "ethereum".to_string(),
+                address: log.inner.address,
+                block_hash: H256::zero(),
+                block_number: U64::one(),
+                block_timestamp: None,
+                transaction_hash: log.inner.transaction_hash.unwrap_or_default(),
+                log_index: Default::default(),
+                transaction_index: U64::zero(),
+            },
+            found_in_request: LogFoundInRequest {
+                from_block: U64::one(),
+                to_block: U64::one(),
+                completes_out_of_order: false,
+            },
+            spam: None,
+        }
+    }
+
+    #[test]
+    fn test_verification_lookups_are_cached_until_due() {
+        let now = Instant::now();
+        let later = |after: Duration| now + after;
+
+        assert_eq!(
+            Verification::Verified.is_unverified(later(Duration::from_secs(86_400))),
+            Some(false)
+        );
+        assert_eq!(Verification::Unverified(now).is_unverified(now), Some(true));
+        assert_eq!(
+            Verification::Unverified(now).is_unverified(later(UNVERIFIED_RECHECK_AFTER)),
+            None
+        );
+        assert_eq!(Verification::Failed(now).is_unverified(now), Some(false));
+        assert_eq!(Verification::Failed(now).is_unverified(later(FAILED_LOOKUP_RETRY_AFTER)), None);
+    }
+
+    #[tokio::test]
+    async fn test_apply_drops_or_flags_spam_logs() {
+        let spam = Address::repeat_byte(1);
+        let token = Address::repeat_byte(2);
+        let airdrop = Address::repeat_byte(3);
+        let logs = || {
+            let mut logs = vec![wrapped_log(spam, 1), wrapped_log(token, 2)];
+            logs.extend((0..50).map(|_| wrapped_log(airdrop, 3)));
+            logs
+        };
+
+        let mut dropped = logs();
+        let flagged =
+            spam_filter("spam-drop-test", SpamAction::Drop, spam).apply(&mut dropped).await;
+        assert!(flagged.is_empty());
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].inner.address, token);
+
+        let mut kept = logs();
+        let flagged =
+            spam_filter("spam-flag-apply-test", SpamAction::Flag, spam).apply(&mut kept).await;
+        assert_eq!(kept.len(), 52);
+        assert_eq!(
+            flagged,
+            HashMap::from([(spam, SpamReason::DenyList), (airdrop, SpamReason::Airdrop)])
+        );
+        // a contract found once stays spam on the network
+        assert_eq!(
+            spam_contract_reason("spam-flag-apply-test", &airdrop),
+            Some(SpamReason::Airdrop)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_flagged_logs_keep_their_reason() {
+        let spam = Address::repeat_byte(1);
+        let token = Address::repeat_byte(2);
+        let filter = spam_filter("spam-flag-test", SpamAction::Flag, spam);
+
+        let mut logs = vec![wrapped_log(spam, 1), wrapped_log(token, 2)];
+        let flagged = filter.apply(&mut logs).await;
+        assert_eq!(logs.len(), 2);
+
+        // as the logs are handed to the handlers and written to the no-code tables
+        let mut results: Vec<EventResult> = logs.iter().map(event_result).collect();
+        flag_results(&mut results, &flagged);
+        assert_eq!(results[0].spam, Some(SpamReason::DenyList));
+        assert_eq!(results[1].spam, None);
+        assert!(matches!(
+            spam_reason_column_value(results[0].spam),
+            EthereumSqlTypeWrapper::StringNullable(reason) if reason == "deny_list"
+        ));
+        assert!(matches!(
+            spam_reason_column_value(results[1].spam),
+            EthereumSqlTypeWrapper::StringNullable(reason) if reason.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_airdrop_contracts() {
+        let log = |address: Address, transaction: u8| Log {
+            address,
+            transaction_hash: Some(H256::repeat_byte(transaction)),
+            ..Default::default()
+        };
+        let airdrop = Address::repeat_byte(1);
+        let token = Address::repeat_byte(2);
+        let mut logs = vec![log(airdrop, 1), log(airdrop, 1), log(airdrop, 1)];
+        logs.extend([log(token, 1), log(token, 2), log(token, 3)]);
+
+        assert_eq!(airdrop_contracts(logs.iter(), 3), HashSet::from([airdrop]));
+        assert_eq!(airdrop_contracts(logs.iter(), 4), HashSet::new());
+    }
+}
@@ -1,32 +1,47 @@
-use std::{path::Path, sync::Arc};
+use std::{collections::HashMap, path::Path, sync::Arc};
 
-use ethers::{providers::ProviderError, types::U64};
+use ethers::{abi::Abi, providers::ProviderError, types::U64};
 use futures::future::try_join_all;
 use tokio::{
-    sync::Semaphore,
+    sync::{Notify, Semaphore},
     task::{JoinError, JoinHandle},
     time::Instant,
 };
 use tracing::{error, info};
 
 use crate::{
-    database::postgres::client::PostgresConnectionError,
+    database::postgres::{
+        client::PostgresConnectionError, ranges::CompletedRangesStore, raw_logs::RawLogArchive,
+        retry_queue::RetryQueue, spam_contracts::SpamContractsStore,
+    },
     event::{
         callback_registry::EventCallbackRegistry, config::EventProcessingConfig,
         contract_setup::NetworkContract,
     },
     indexer::{
+        address_registry::{start_address_registries, AddressRegistryError},
+        alerts::start_alert_monitor,
+        backfill_jobs::{is_backfill_worker, run_backfill_jobs, BackfillJobsError},
+        completed_ranges::CompletedRanges,
         dependency::ContractEventsDependenciesConfig,
-        last_synced::{get_last_synced_block_number, SyncConfig},
+        last_synced::{
+            get_last_synced_checkpoint, update_progress_and_last_synced_task, SyncConfig,
+        },
+        overlap::earlier_overlaps,
         process::{
-            process_contracts_events_with_dependencies, process_event,
+            process_contracts_events_with_dependencies, process_event_with_restarts,
+            recover_write_ahead_log, replay_retry_queue_range,
             ProcessContractsEventsWithDependenciesError, ProcessEventError,
         },
-        progress::IndexingEventsProgressState,
+        progress::{publish_progress, IndexingEventsProgressState},
         reorg::reorg_safe_distance_for_chain,
+        spam::SpamFilter,
+        watchdog::{start_watchdog, PipelineRestarts},
+        write_ahead_log::WriteAheadLog,
         ContractEventDependencies,
     },
-    manifest::core::Manifest,
+    manifest::{contract::Contract, core::Manifest},
+    runtime_tuning::indexing_permits,
     PostgresClient,
 };
 
@@ -68,6 +83,12 @@ pub enum StartIndexingError {
 
     #[error("The end block set for {0} is higher than the latest block: {1} - end block: {2}")]
     EndBlockIsHigherThanLatestBlockError(String, U64, U64),
+
+    #[error("{0}")]
+    BackfillJobsError(#[from] BackfillJobsError),
+
+    #[error("Could not read the address registry: {0}")]
+    AddressRegistryError(#[from] AddressRegistryError),
 }
 
 pub struct ProcessedNetworkContract {
@@ -86,9 +107,20 @@ pub async fn start_indexing(
 
     let database = initialize_database(manifest).await?;
     let event_progress_state = IndexingEventsProgressState::monitor(&registry.events).await;
-
-    // we can bring this into the yaml file later if required
-    let semaphore = Arc::new(Semaphore::new(100));
+    publish_progress(&manifest.name, &event_progress_state);
+    // the filters are built from the addresses the registries list so they are read up front
+    let address_registries_handle = start_address_registries(&registry.events).await?;
+    let alert_monitor_handle = manifest.alerts.as_ref().map(|alerts| {
+        start_alert_monitor(
+            alerts,
+            manifest.notification_destinations(),
+            &registry.events,
+            Arc::clone(&event_progress_state),
+        )
+    });
+
+    // sized by the manifest tuning and resized on a config reload
+    let permits = indexing_permits();
     // need this to keep track of dependency_events cross contracts and events
     let mut event_processing_configs: Vec<Arc<EventProcessingConfig>> = vec![];
     // any events which are non-blocking and can be fired in parallel
@@ -100,13 +132,21 @@ pub async fn start_indexing(
     let mut apply_cross_contract_dependency_events_config_after_processing = Vec::new();
 
     let mut processed_network_contracts: Vec<ProcessedNetworkContract> = Vec::new();
+    // only the non-blocking pipelines can be restarted by the watchdog
+    let mut pipeline_restarts: PipelineRestarts = HashMap::new();
+    // non-blocking events which backfill through the shared jobs table before going live
+    let mut backfill_configs: Vec<EventProcessingConfig> = Vec::new();
+
+    // per event callback limits are shared across all the networks the event is indexed on
+    let mut callback_semaphores: HashMap<String, Arc<Semaphore>> = HashMap::new();
 
     for event in registry.events.iter() {
-        let stream_details = manifest
-            .contracts
-            .iter()
-            .find(|c| c.name == event.contract.name)
-            .and_then(|c| c.streams.as_ref());
+        let contract = manifest.contracts.iter().find(|c| c.name == event.contract.name);
+        let stream_details = contract.and_then(|c| c.streams.as_ref());
+        permits.set_quota(
+            &event.id,
+            contract.and_then(|c| c.max_concurrent_requests(&event.event_name)),
+        );
 
         for network_contract in event.contract.details.iter() {
             let config = SyncConfig {
@@ -145,24 +185,34 @@ pub async fn start_indexing(
                 }
             }
 
-            let last_known_start_block = if network_contract.start_block.is_some() {
-                let last_synced_block = get_last_synced_block_number(config).await;
+            // a checkpoint within a block resumes the block after the last log handled
+            let (last_known_start_block, resume_after_log) =
+                if network_contract.start_block.is_some() {
+                    let checkpoint = get_last_synced_checkpoint(config).await;
 
-                if let Some(value) = last_synced_block {
-                    let start_from = value + 1;
-                    info!(
+                    if let Some(checkpoint) = checkpoint {
+                        let (start_from, resume_after_log) = checkpoint.resume_from();
+                        info!(
                         "{} Found last synced block number - {:?} rindexer will start up from {:?}",
                         event.info_log_name(),
-                        value,
+                        checkpoint.block,
                         start_from
                     );
-                    Some(start_from)
+                        if let Some((_, log_index)) = resume_after_log {
+                            info!(
+                                "{} Block {} is handled up to log index {}, carrying on after it",
+                                event.info_log_name(),
+                                start_from,
+                                log_index
+                            );
+                        }
+                        (Some(start_from), resume_after_log)
+                    } else {
+                        (None, None)
+                    }
                 } else {
-                    None
-                }
-            } else {
-                None
-            };
+                    (None, None)
+                };
 
             let start_block = last_known_start_block
                 .unwrap_or(network_contract.start_block.unwrap_or(latest_block));
@@ -189,7 +239,7 @@ pub async fn start_indexing(
                 processed_up_to: end_block,
             });
 
-            let event_processing_config = EventProcessingConfig {
+            let mut event_processing_config = EventProcessingConfig {
                 id: event.id.clone(),
                 project_path: project_path.to_path_buf(),
                 indexer_name: event.indexer_name.clone(),
@@ -200,7 +250,7 @@ pub async fn start_indexing(
                 network_contract: Arc::new(network_contract.clone()),
                 start_block,
                 end_block,
-                semaphore: Arc::clone(&semaphore),
+                permits: Arc::clone(&permits),
                 registry: Arc::clone(&registry),
                 progress: Arc::clone(&event_progress_state),
                 database: database.clone(),
@@ -214,9 +264,144 @@ pub async fn start_indexing(
                     network_contract.is_live_indexing()
                 },
                 index_event_in_order: event.index_event_in_order,
+                group_by_block: event.group_by_block,
+                callback_semaphore: event.max_concurrent_callbacks.map(|max| {
+                    Arc::clone(
+                        callback_semaphores
+                            .entry(event.id.clone())
+                            .or_insert_with(|| Arc::new(Semaphore::new(max))),
+                    )
+                }),
+                failure_policy: event.failure_policy,
                 indexing_distance_from_head,
+                persist_last_synced_block: true,
+                retry_queue: database.as_ref().map(|database| {
+                    Arc::new(RetryQueue::new(
+                        Arc::clone(database),
+                        &event.indexer_name,
+                        &event.contract.name,
+                        &event.event_name,
+                        &network_contract.network,
+                    ))
+                }),
+                raw_log_archive: database.as_ref().filter(|_| event.archive_raw_logs).map(
+                    |database| {
+                        Arc::new(RawLogArchive::new(
+                            Arc::clone(database),
+                            &event.indexer_name,
+                            &event.contract.name,
+                            &event.event_name,
+                        ))
+                    },
+                ),
+                resume_after_log,
+                // backfill workers index ranges of their own, the log is kept by the main process
+                write_ahead_log: contract
+                    .filter(|contract| {
+                        contract.is_event_write_ahead_logged(&event.event_name) &&
+                            !is_backfill_worker()
+                    })
+                    .map(|_| {
+                        Arc::new(WriteAheadLog::new(
+                            project_path,
+                            &event.indexer_name,
+                            &event.contract.name,
+                            &network_contract.network,
+                            &event.event_name,
+                        ))
+                    }),
+                earlier_overlaps: earlier_overlaps(
+                    manifest,
+                    &registry,
+                    event,
+                    &network_contract.network,
+                ),
+                completed_ranges: None,
+                spam_filter: spam_filter(
+                    project_path,
+                    manifest,
+                    contract,
+                    &database,
+                    &event.indexer_name,
+                    &event.event_name,
+                    &network_contract.network,
+                )
+                .await,
             };
 
+            // ranges handled concurrently complete out of order, the ones a previous run
+            // completed past the checkpoint are not fetched again
+            if let Some(database) = database.as_ref().filter(|_| {
+                !event.index_event_in_order &&
+                    network_contract.start_block.is_some() &&
+                    !is_backfill_worker()
+            }) {
+                let store = CompletedRangesStore::new(
+                    Arc::clone(database),
+                    &event.indexer_name,
+                    &event.contract.name,
+                    &event.event_name,
+                    &network_contract.network,
+                );
+                match CompletedRanges::load(store, start_block).await {
+                    Ok(completed_ranges) => {
+                        let next_block = completed_ranges.next_block().await;
+                        if next_block > start_block {
+                            info!(
+                                "{} - Ranges up to block {} completed before the restart, carrying on from block {}",
+                                event.info_log_name(),
+                                next_block - 1,
+                                next_block
+                            );
+                            event_processing_config.start_block = next_block;
+                            event_processing_config.resume_after_log = None;
+                        }
+                        event_processing_config.completed_ranges = Some(Arc::new(completed_ranges));
+                    }
+                    Err(e) => error!(
+                        "{} - Could not read the completed ranges: {}",
+                        event.info_log_name(),
+                        e
+                    ),
+                }
+            }
+
+            if event_processing_config.write_ahead_log.is_some() {
+                event_processing_config.start_block =
+                    recover_write_ahead_log(&event_processing_config).await;
+            }
+
+            if let Some(retry_queue) =
+                event_processing_config.retry_queue.as_ref().filter(|_| !is_backfill_worker())
+            {
+                match retry_queue.pending().await {
+                    Ok(ranges) => {
+                        // ranges after the checkpoint are indexed again by the pipeline itself
+                        if let Err(e) = retry_queue.remove_from(start_block).await {
+                            error!(
+                                "{} - Could not clear the retry queue: {}",
+                                event.info_log_name(),
+                                e
+                            );
+                        }
+                        for (from_block, to_block) in
+                            ranges.into_iter().filter(|(_, to_block)| *to_block < start_block)
+                        {
+                            non_blocking_process_events.push(tokio::spawn(
+                                replay_retry_queue_range(
+                                    event_processing_config.clone(),
+                                    from_block,
+                                    to_block,
+                                ),
+                            ));
+                        }
+                    }
+                    Err(e) => {
+                        error!("{} - Could not read the retry queue: {}", event.info_log_name(), e)
+                    }
+                }
+            }
+
             let dependencies_status = ContractEventDependencies::dependencies_status(
                 &event_processing_config.contract_name,
                 &event_processing_config.event_name,
@@ -245,13 +430,59 @@ pub async fn start_indexing(
                     event_processing_config_arc,
                     dependencies,
                 );
+            } else if manifest.backfill_jobs.is_some() {
+                backfill_configs.push(event_processing_config);
             } else {
-                let process_event = tokio::spawn(process_event(event_processing_config, false));
+                let restart = Arc::new(Notify::new());
+                pipeline_restarts.insert(
+                    event_processing_config.network_contract.id.clone(),
+                    Arc::clone(&restart),
+                );
+                let process_event =
+                    tokio::spawn(process_event_with_restarts(event_processing_config, restart));
                 non_blocking_process_events.push(process_event);
             }
         }
     }
 
+    if let Some(backfill_jobs) = &manifest.backfill_jobs {
+        let covered =
+            run_backfill_jobs(backfill_jobs, database.clone(), &manifest.name, &backfill_configs)
+                .await?;
+
+        if is_backfill_worker() {
+            info!("Backfill jobs - no jobs left, worker exiting");
+            return Ok(processed_network_contracts);
+        }
+
+        for mut event_processing_config in backfill_configs {
+            if let Some(to_block) = covered.get(&event_processing_config.network_contract.id) {
+                // the workers leave the checkpoint alone so it moves once the jobs are all done
+                update_progress_and_last_synced_task(
+                    Arc::new(event_processing_config.clone()),
+                    event_processing_config.start_block,
+                    *to_block,
+                    0,
+                    || {},
+                );
+                event_processing_config.start_block = *to_block + 1;
+            }
+
+            if !event_processing_config.live_indexing &&
+                event_processing_config.start_block > event_processing_config.end_block
+            {
+                continue;
+            }
+
+            let restart = Arc::new(Notify::new());
+            pipeline_restarts
+                .insert(event_processing_config.network_contract.id.clone(), Arc::clone(&restart));
+            let process_event =
+                tokio::spawn(process_event_with_restarts(event_processing_config, restart));
+            non_blocking_process_events.push(process_event);
+        }
+    }
+
     // apply dependency events config after processing to avoid ordering issues
     for apply in apply_cross_contract_dependency_events_config_after_processing {
         let (dependency_in_other_contract, event_processing_config) = apply;
@@ -262,6 +493,15 @@ pub async fn start_indexing(
         );
     }
 
+    let watchdog_handle = manifest.watchdog.as_ref().map(|watchdog| {
+        start_watchdog(
+            watchdog,
+            &registry.events,
+            Arc::clone(&event_progress_state),
+            pipeline_restarts,
+        )
+    });
+
     let dependency_handle: JoinHandle<Result<(), ProcessContractsEventsWithDependenciesError>> =
         tokio::spawn(process_contracts_events_with_dependencies(
             dependency_event_processing_configs,
@@ -285,7 +525,21 @@ pub async fn start_indexing(
         }));
     }
 
-    let results = try_join_all(handles).await?;
+    let results = try_join_all(handles).await;
+
+    // the progress state is rebuilt on the next start_indexing call so the monitor goes with it
+    if let Some(alert_monitor_handle) = alert_monitor_handle {
+        alert_monitor_handle.abort();
+    }
+    if let Some(watchdog_handle) = watchdog_handle {
+        watchdog_handle.abort();
+    }
+    if let Some(address_registries_handle) = address_registries_handle {
+        address_registries_handle.abort();
+    }
+    event_progress_state.lock().await.log_terminal_state();
+
+    let results = results?;
 
     for result in results {
         match result {
@@ -301,6 +555,45 @@ pub async fn start_indexing(
     Ok(processed_network_contracts)
 }
 
+/// The spam filter of the event on a network, the logs are only checked against the event in the
+/// ABI when the ABI can be read
+async fn spam_filter(
+    project_path: &Path,
+    manifest: &Manifest,
+    contract: Option<&Contract>,
+    database: &Option<Arc<PostgresClient>>,
+    indexer_name: &str,
+    event_name: &str,
+    network: &str,
+) -> Option<Arc<SpamFilter>> {
+    let contract = contract?;
+    let settings = contract.spam_filter.clone()?;
+    let chain_id = manifest
+        .networks
+        .iter()
+        .find(|n| n.name == network)
+        .map(|n| n.chain_id)
+        .unwrap_or_default();
+    let event = contract
+        .parse_abi(project_path)
+        .ok()
+        .and_then(|abi| serde_json::from_str::<Abi>(&abi).ok())
+        .and_then(|abi| abi.event(event_name).ok().cloned());
+
+    let spam_filter = SpamFilter::new(
+        network,
+        chain_id,
+        settings,
+        event,
+        manifest.global.as_ref().and_then(|global| global.etherscan_api_key.clone()),
+        database
+            .as_ref()
+            .map(|database| SpamContractsStore::new(Arc::clone(database), indexer_name)),
+    );
+    spam_filter.load_found().await;
+    Some(Arc::new(spam_filter))
+}
+
 async fn initialize_database(
     manifest: &Manifest,
 ) -> Result<Option<Arc<PostgresClient>>, StartIndexingError> {
@@ -339,3 +632,245 @@ async fn calculate_safe_block_number(
     }
     Ok((end_block, indexing_distance_from_head))
 }
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use std::{sync::Mutex, time::Duration};
+
+    use ethers::{
+        abi,
+        contract::EthEvent,
+        types::{Address, ValueOrArray, U256},
+    };
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::{
+        event::{
+            callback_registry::{noop_decoder, EventCallbackRegistryInformation},
+            contract_setup::{AddressDetails, ContractInformation, IndexingContractSetup},
+        },
+        indexer::last_synced::Checkpoint,
+        manifest::contract::FailurePolicy,
+        provider::JsonRpcCachedProvider,
+        testing::{fake_log::FakeLogBuilder, mock_provider::MockProvider},
+        types::single_or_array::StringOrArray,
+    };
+
+    #[derive(Clone, Debug, PartialEq, EthEvent)]
+    #[ethevent(name = "Transfer", abi = "Transfer(address,address,uint256)")]
+    struct Transfer {
+        #[ethevent(indexed)]
+        from: Address,
+        #[ethevent(indexed)]
+        to: Address,
+        value: U256,
+    }
+
+    /// The (block, log index) of the logs in each callback batch along with the range they were
+    /// found in
+    type Batches = Arc<Mutex<Vec<(U64, U64, Vec<(u64, u64)>)>>>;
+
+    fn token_address() -> Address {
+        Address::repeat_byte(9)
+    }
+
+    fn manifest(csv_path: &Path) -> Manifest {
+        serde_yaml::from_str(&format!(
+            r#"
+name: MockIndexer
+project_type: rust
+networks:
+  - name: mock
+    chain_id: 1
+    rpc: http://localhost:8545
+storage:
+  csv:
+    enabled: true
+    path: {}
+contracts:
+  - name: Token
+    details:
+      - network: mock
+        address: "{:?}"
+        start_block: '0'
+    abi: ./abis/erc20-abi.json
+"#,
+            csv_path.display(),
+            token_address()
+        ))
+        .unwrap()
+    }
+
+    fn registry(
+        provider: &Arc<JsonRpcCachedProvider>,
+        end_block: u64,
+        batches: &Batches,
+    ) -> Arc<EventCallbackRegistry> {
+        let batches = Arc::clone(batches);
+        let mut registry = EventCallbackRegistry::new();
+        registry.register_event(EventCallbackRegistryInformation {
+            id: "token-transfer".to_string(),
+            indexer_name: "MockIndexer".to_string(),
+            topic_id: Transfer::signature(),
+            event_name: "Transfer".to_string(),
+            index_event_in_order: true,
+            group_by_block: false,
+            max_concurrent_callbacks: None,
+            failure_policy: FailurePolicy::default(),
+            archive_raw_logs: false,
+            contract: ContractInformation {
+                name: "Token".to_string(),
+                details: vec![NetworkContract {
+                    id: "token-mock".to_string(),
+                    network: "mock".to_string(),
+                    indexing_contract_setup: IndexingContractSetup::Address(AddressDetails {
+                        address: ValueOrArray::Value(token_address()),
+                        indexed_filters: None,
+                    }),
+                    cached_provider: Arc::clone(provider),
+                    decoder: noop_decoder(),
+                    start_block: Some(U64::zero()),
+                    end_block: Some(U64::from(end_block)),
+                    disable_logs_bloom_checks: false,
+                    logs_bloom_scan: false,
+                    historical_source: None,
+                }],
+                abi: StringOrArray::Single("./abis/erc20-abi.json".to_string()),
+                reorg_safe_distance: false,
+            },
+            callback: Arc::new(move |results| {
+                let batches = Arc::clone(&batches);
+                async move {
+                    if let Some(first) = results.first() {
+                        let logs = results
+                            .iter()
+                            .map(|result| {
+                                (
+                                    result.tx_information.block_number.as_u64(),
+                                    result.tx_information.log_index.as_u64(),
+                                )
+                            })
+                            .collect();
+                        batches.lock().unwrap().push((
+                            first.found_in_request.from_block,
+                            first.found_in_request.to_block,
+                            logs,
+                        ));
+                    }
+                    Ok(())
+                }
+                .boxed()
+            }),
+        });
+        registry.complete()
+    }
+
+    async fn wait_for_checkpoint(manifest: &Manifest, project_path: &Path, block: u64) {
+        let config = || SyncConfig {
+            project_path,
+            database: &None,
+            csv_details: &manifest.storage.csv,
+            contract_csv_enabled: manifest.contract_csv_enabled("Token"),
+            stream_details: &None,
+            indexer_name: "MockIndexer",
+            contract_name: "Token",
+            event_name: "Transfer",
+            network: "mock",
+        };
+        // the checkpoint is written by a task of its own once the range is handled
+        for _ in 0..50 {
+            if get_last_synced_checkpoint(config()).await ==
+                Some(Checkpoint::block(U64::from(block)))
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!(
+            "checkpoint never reached block {}, it is {:?}",
+            block,
+            get_last_synced_checkpoint(config()).await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_indexing_chunks_in_order_and_resumes_from_the_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let csv_path = dir.path().join("generated_csv");
+        std::fs::create_dir_all(csv_path.join("Token").join("last-synced-blocks")).unwrap();
+        let manifest = manifest(&csv_path);
+
+        let abi_event = abi::parse_abi(&[
+            "event Transfer(address indexed from, address indexed to, uint256 value)",
+        ])
+        .unwrap()
+        .event("Transfer")
+        .unwrap()
+        .clone();
+        let transfer = Transfer {
+            from: Address::repeat_byte(1),
+            to: Address::repeat_byte(2),
+            value: U256::from(1000),
+        };
+        let logs: Vec<(u64, u64)> =
+            vec![(5, 0), (5, 1), (12, 0), (30, 0), (31, 0), (47, 0), (70, 0), (88, 0)];
+        let provider = MockProvider::new()
+            .with_head(100)
+            .with_logs(logs.iter().map(|(block_number, log_index)| {
+                FakeLogBuilder::new(transfer.clone(), &abi_event)
+                    .network("mock")
+                    .address(token_address())
+                    .block_number(*block_number)
+                    .log_index(*log_index)
+                    .build_wrapped_log()
+            }))
+            .with_max_logs_per_request(2)
+            .build();
+
+        let batches: Batches = Arc::new(Mutex::new(vec![]));
+        tokio::time::timeout(
+            Duration::from_secs(30),
+            start_indexing(&manifest, dir.path(), &[], true, registry(&provider, 50, &batches)),
+        )
+        .await
+        .expect("historic indexing did not finish")
+        .unwrap();
+        wait_for_checkpoint(&manifest, dir.path(), 50).await;
+
+        let first_run = std::mem::take(&mut *batches.lock().unwrap());
+        // the provider only returns 2 logs a request so the range is split up
+        assert!(first_run.len() > 1);
+        // the next range starts after the last log handled so no log is handed over twice
+        let mut previous_block = None;
+        for (from_block, to_block, batch_logs) in &first_run {
+            assert!(batch_logs.len() <= 2, "{:?}", first_run);
+            if let Some(previous_block) = previous_block {
+                assert!(from_block.as_u64() > previous_block, "{:?}", first_run);
+            }
+            assert!(batch_logs.iter().all(|(block_number, _)| {
+                U64::from(*block_number) >= *from_block && U64::from(*block_number) <= *to_block
+            }));
+            previous_block = batch_logs.last().map(|(block_number, _)| *block_number);
+        }
+        let handled: Vec<(u64, u64)> =
+            first_run.into_iter().flat_map(|(_, _, batch_logs)| batch_logs).collect();
+        assert_eq!(handled, logs[..6]);
+
+        // a second run carries on after the checkpoint rather than starting over
+        tokio::time::timeout(
+            Duration::from_secs(30),
+            start_indexing(&manifest, dir.path(), &[], true, registry(&provider, 100, &batches)),
+        )
+        .await
+        .expect("historic indexing did not finish")
+        .unwrap();
+        wait_for_checkpoint(&manifest, dir.path(), 100).await;
+
+        let handled: Vec<(u64, u64)> = std::mem::take(&mut *batches.lock().unwrap())
+            .into_iter()
+            .flat_map(|(_, _, batch_logs)| batch_logs)
+            .collect();
+        assert_eq!(handled, logs[6..]);
+    }
+}
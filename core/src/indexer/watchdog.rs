@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ethers::types::U64;
+use tokio::{
+    sync::{Mutex, Notify},
+    task::JoinHandle,
+};
+use tracing::{error, warn};
+
+use crate::{
+    error_reporting::{report_error, ErrorCategory, ErrorContext},
+    event::callback_registry::EventCallbackRegistryInformation,
+    indexer::IndexingEventsProgressState,
+    is_running,
+    manifest::watchdog::WatchdogSettings,
+    provider::JsonRpcCachedProvider,
+};
+
+/// Restart signals for the pipelines which can be restarted, keyed by network contract id
+pub type PipelineRestarts = HashMap<String, Arc<Notify>>;
+
+struct StalledEvent {
+    id: String,
+    network: String,
+    info_log: String,
+    last_fetched_block: U64,
+}
+
+/// Spawns the loop which looks for live events which stopped fetching new block ranges while the
+/// chain head kept moving, marks them as stalled and restarts their pipelines.
+pub fn start_watchdog(
+    settings: &WatchdogSettings,
+    events: &[EventCallbackRegistryInformation],
+    progress: Arc<Mutex<IndexingEventsProgressState>>,
+    restarts: PipelineRestarts,
+) -> JoinHandle<()> {
+    let mut providers: HashMap<String, Arc<JsonRpcCachedProvider>> = HashMap::new();
+    for event in events {
+        for details in &event.contract.details {
+            providers
+                .entry(details.network.clone())
+                .or_insert_with(|| Arc::clone(&details.cached_provider));
+        }
+    }
+
+    let stall_timeout = Duration::from_secs(settings.stall_timeout_seconds());
+    let interval = Duration::from_secs(settings.check_interval_seconds());
+    let restart = settings.restart();
+
+    tokio::spawn(async move {
+        while is_running() {
+            tokio::time::sleep(interval).await;
+
+            let candidates: Vec<StalledEvent> = progress
+                .lock()
+                .await
+                .events
+                .iter()
+                .filter(|e| e.live_indexing && e.last_fetched_at.elapsed() > stall_timeout)
+                .map(|e| StalledEvent {
+                    id: e.id.clone(),
+                    network: e.network.clone(),
+                    info_log: e.info_log.clone(),
+                    last_fetched_block: e.last_fetched_block,
+                })
+                .collect();
+
+            for candidate in candidates {
+                let Some(provider) = providers.get(&candidate.network) else {
+                    continue;
+                };
+
+                // quiet chains can go a long time without a new block which is not a stall
+                let head = match provider.get_block_number().await {
+                    Ok(head) => head,
+                    Err(e) => {
+                        error!(
+                            "Watchdog - failed to get latest block for network {}: {}",
+                            candidate.network, e
+                        );
+                        continue;
+                    }
+                };
+                if head <= candidate.last_fetched_block {
+                    continue;
+                }
+
+                warn!(
+                    "{} - network {} - stalled at block {} while the head is at {}",
+                    candidate.info_log, candidate.network, candidate.last_fetched_block, head
+                );
+                report_error(
+                    ErrorCategory::Provider,
+                    &format!(
+                        "{} - pipeline stalled at block {} while the head is at {}",
+                        candidate.info_log, candidate.last_fetched_block, head
+                    ),
+                    ErrorContext {
+                        network: Some(&candidate.network),
+                        from_block: Some(candidate.last_fetched_block),
+                        to_block: Some(head),
+                        ..Default::default()
+                    },
+                );
+
+                if let Some(event) =
+                    progress.lock().await.events.iter_mut().find(|e| e.id == candidate.id)
+                {
+                    event.stalled = true;
+                    // give the restarted pipeline a full timeout before checking it again
+                    event.last_fetched_at = Instant::now();
+                }
+
+                if restart {
+                    if let Some(notify) = restarts.get(&candidate.id) {
+                        notify.notify_one();
+                    }
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,250 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use ethers::types::U64;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+use tracing::warn;
+
+use crate::{helpers::camel_to_snake, provider::WrappedLog};
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum WalRecord {
+    Batch { id: u64, from_block: U64, to_block: U64, logs: Vec<WrappedLog> },
+    Done { id: u64 },
+}
+
+/// A batch found in the log of a previous run
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalEntry {
+    pub from_block: U64,
+    pub to_block: U64,
+    pub logs: Vec<WrappedLog>,
+    /// The handlers succeeded on it and it was checkpointed
+    pub done: bool,
+}
+
+/// Reads the records back into batches in block order. A crash while appending leaves a torn
+/// last line which is dropped, its batch never reached the handlers.
+fn parse_entries(contents: &str) -> Vec<WalEntry> {
+    let mut entries: Vec<(u64, WalEntry)> = Vec::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str::<WalRecord>(line) {
+            Ok(WalRecord::Batch { id, from_block, to_block, logs }) => {
+                entries.push((id, WalEntry { from_block, to_block, logs, done: false }))
+            }
+            Ok(WalRecord::Done { id }) => {
+                if let Some((_, entry)) = entries.iter_mut().find(|(entry_id, _)| *entry_id == id) {
+                    entry.done = true;
+                }
+            }
+            Err(e) => warn!("Dropping a torn write-ahead log record: {}", e),
+        }
+    }
+
+    let mut entries: Vec<WalEntry> = entries.into_iter().map(|(_, entry)| entry).collect();
+    entries.sort_by_key(|entry| entry.from_block);
+    entries
+}
+
+/// The batches to hand to the handlers again and the block indexing carries on from. Only the
+/// batches joining up with the checkpoint are recovered, anything after a gap is fetched again
+/// as the blocks in the gap were never logged.
+pub fn recovery_plan(entries: Vec<WalEntry>, start_block: U64) -> (Vec<WalEntry>, U64) {
+    let mut next_block = start_block;
+    let mut pending = Vec::new();
+    for entry in entries {
+        if entry.to_block < next_block {
+            continue;
+        }
+        if entry.from_block > next_block {
+            break;
+        }
+        next_block = entry.to_block + 1;
+        if !entry.done {
+            pending.push(entry);
+        }
+    }
+    (pending, next_block)
+}
+
+#[derive(Default)]
+struct WalState {
+    file: Option<File>,
+    next_id: u64,
+    pending: HashSet<u64>,
+}
+
+/// An append only file of the batches of an event on a network, synced to disk before their
+/// handlers run and emptied once every batch in it is checkpointed. After a crash the batches
+/// which were in flight are handed to the handlers again from the file rather than fetched from
+/// the node again.
+pub struct WriteAheadLog {
+    path: PathBuf,
+    state: Mutex<WalState>,
+}
+
+impl WriteAheadLog {
+    pub fn new(
+        project_path: &Path,
+        indexer_name: &str,
+        contract_name: &str,
+        network: &str,
+        event_name: &str,
+    ) -> Self {
+        let path =
+            project_path.join(".rindexer").join("wal").join(camel_to_snake(indexer_name)).join(
+                format!(
+                    "{}-{}-{}.wal",
+                    contract_name.to_lowercase(),
+                    network.to_lowercase(),
+                    event_name.to_lowercase()
+                ),
+            );
+        Self { path, state: Mutex::new(WalState::default()) }
+    }
+
+    async fn open(&self, state: &mut WalState) -> io::Result<()> {
+        if state.file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            state.file = Some(OpenOptions::new().create(true).append(true).open(&self.path).await?);
+        }
+        Ok(())
+    }
+
+    async fn write_record(state: &mut WalState, record: &WalRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let file = state.file.as_mut().expect("the write-ahead log is opened before writing");
+        file.write_all(&line).await?;
+        file.sync_data().await
+    }
+
+    /// Logs a batch before its handlers run, the id marks it done once checkpointed
+    pub async fn append(
+        &self,
+        from_block: U64,
+        to_block: U64,
+        logs: Vec<WrappedLog>,
+    ) -> io::Result<u64> {
+        let mut state = self.state.lock().await;
+        self.open(&mut state).await?;
+        let id = state.next_id;
+        state.next_id += 1;
+        Self::write_record(&mut state, &WalRecord::Batch { id, from_block, to_block, logs })
+            .await?;
+        state.pending.insert(id);
+        Ok(id)
+    }
+
+    /// Marks the batch done, the file is emptied once no batch in it is in flight
+    pub async fn complete(&self, id: u64) -> io::Result<()> {
+        let mut state = self.state.lock().await;
+        self.open(&mut state).await?;
+        state.pending.remove(&id);
+        if state.pending.is_empty() {
+            let file = state.file.as_mut().expect("the write-ahead log is opened above");
+            file.set_len(0).await?;
+            return file.sync_data().await;
+        }
+        Self::write_record(&mut state, &WalRecord::Done { id }).await
+    }
+
+    /// The batches a previous run left in the file, they stay in it until [`Self::clear`] so a
+    /// crash while recovering them recovers them again
+    pub async fn read_entries(&self) -> io::Result<Vec<WalEntry>> {
+        match fs::read_to_string(&self.path).await {
+            Ok(contents) => Ok(parse_entries(&contents)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Empties the file once the batches of the previous run are recovered
+    pub async fn clear(&self) -> io::Result<()> {
+        let mut state = self.state.lock().await;
+        self.open(&mut state).await?;
+        let file = state.file.as_mut().expect("the write-ahead log is opened above");
+        file.set_len(0).await?;
+        file.sync_data().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(from_block: u64, to_block: u64, done: bool) -> WalEntry {
+        WalEntry {
+            from_block: U64::from(from_block),
+            to_block: U64::from(to_block),
+            logs: vec![],
+            done,
+        }
+    }
+
+    #[test]
+    fn test_parse_entries_marks_done_batches_and_drops_torn_lines() {
+        let contents = [
+            r#"{"op":"batch","id":0,"from_block":"0x1e","to_block":"0x27","logs":[]}"#,
+            r#"{"op":"batch","id":1,"from_block":"0xa","to_block":"0x1d","logs":[]}"#,
+            r#"{"op":"done","id":0}"#,
+            r#"{"op":"batch","id":2,"from_blo"#,
+        ]
+        .join("\n");
+
+        assert_eq!(parse_entries(&contents), vec![entry(10, 29, false), entry(30, 39, true)]);
+    }
+
+    #[tokio::test]
+    async fn test_entries_stay_logged_until_cleared() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = WriteAheadLog::new(dir.path(), "Indexer", "Contract", "ethereum", "Transfer");
+        wal.append(U64::from(10), U64::from(19), vec![]).await.unwrap();
+        // a range without logs is logged so the next one still joins up with it
+        wal.append(U64::from(20), U64::from(29), vec![]).await.unwrap();
+
+        let restarted =
+            WriteAheadLog::new(dir.path(), "Indexer", "Contract", "ethereum", "Transfer");
+        let entries = restarted.read_entries().await.unwrap();
+        assert_eq!(entries, vec![entry(10, 19, false), entry(20, 29, false)]);
+        // a crash while recovering reads them again
+        assert_eq!(restarted.read_entries().await.unwrap(), entries);
+
+        let (pending, next_block) = recovery_plan(entries, U64::from(10));
+        assert_eq!(pending.len(), 2);
+        assert_eq!(next_block, U64::from(30));
+
+        restarted.clear().await.unwrap();
+        assert!(restarted.read_entries().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recovery_plan_stops_at_a_gap() {
+        let entries = vec![
+            entry(0, 9, true),
+            entry(10, 19, false),
+            entry(20, 29, true),
+            entry(30, 39, false),
+            entry(50, 59, false),
+        ];
+
+        let (pending, next_block) = recovery_plan(entries, U64::from(10));
+        assert_eq!(pending, vec![entry(10, 19, false), entry(30, 39, false)]);
+        assert_eq!(next_block, U64::from(40));
+
+        let (pending, next_block) = recovery_plan(vec![entry(20, 29, false)], U64::from(10));
+        assert!(pending.is_empty());
+        assert_eq!(next_block, U64::from(10));
+    }
+}
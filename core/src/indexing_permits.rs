@@ -0,0 +1,210 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{
+    oneshot::{self, error::RecvError},
+    OwnedSemaphorePermit, Semaphore,
+};
+
+use crate::manifest::tuning::PermitScheduling;
+
+struct Waiter {
+    sequence: u64,
+    sender: oneshot::Sender<IndexingPermit>,
+}
+
+/// The permits an event waits for and holds, all networks of an event share one queue
+struct EventQueue {
+    key: String,
+    waiters: VecDeque<Waiter>,
+    held: usize,
+    quota: Option<usize>,
+}
+
+impl EventQueue {
+    fn can_take(&self) -> bool {
+        !self.waiters.is_empty() && self.quota.is_none_or(|quota| self.held < quota)
+    }
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    scheduling: PermitScheduling,
+    queues: Vec<EventQueue>,
+    // the queue round robin looks at first
+    next: usize,
+    sequence: u64,
+}
+
+impl SchedulerState {
+    fn queue_mut(&mut self, key: &str) -> &mut EventQueue {
+        let index = match self.queues.iter().position(|queue| queue.key == key) {
+            Some(index) => index,
+            None => {
+                self.queues.push(EventQueue {
+                    key: key.to_string(),
+                    waiters: VecDeque::new(),
+                    held: 0,
+                    quota: None,
+                });
+                self.queues.len() - 1
+            }
+        };
+        &mut self.queues[index]
+    }
+
+    /// The queue the next permit goes to, events at their quota are passed over
+    fn next_queue(&self) -> Option<usize> {
+        match self.scheduling {
+            PermitScheduling::Fifo => self
+                .queues
+                .iter()
+                .enumerate()
+                .filter(|(_, queue)| queue.can_take())
+                .min_by_key(|(_, queue)| queue.waiters.front().map(|waiter| waiter.sequence))
+                .map(|(index, _)| index),
+            PermitScheduling::RoundRobin => (0..self.queues.len())
+                .map(|offset| (self.next + offset) % self.queues.len())
+                .find(|index| self.queues[*index].can_take()),
+        }
+    }
+}
+
+/// Hands out the permits of the shared indexing semaphore to the events waiting for one. First
+/// come first served by default, round robin takes turns across the events so one event with
+/// thousands of historic ranges to fetch can not crowd the others out. Either way an event with
+/// a quota never holds more permits than it.
+pub struct IndexingPermits {
+    semaphore: Arc<Semaphore>,
+    state: Mutex<SchedulerState>,
+}
+
+/// A permit of the shared indexing semaphore, released back to the waiting events on drop
+pub struct IndexingPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    key: String,
+    // unset once the permit is returned without ever being handed out
+    permits: Option<Arc<IndexingPermits>>,
+}
+
+impl Drop for IndexingPermit {
+    fn drop(&mut self) {
+        drop(self.permit.take());
+        if let Some(permits) = self.permits.take() {
+            permits
+                .state
+                .lock()
+                .expect("indexing permits lock poisoned")
+                .queue_mut(&self.key)
+                .held -= 1;
+            permits.dispatch();
+        }
+    }
+}
+
+impl IndexingPermits {
+    pub fn new(semaphore: Arc<Semaphore>) -> Self {
+        Self { semaphore, state: Mutex::new(SchedulerState::default()) }
+    }
+
+    pub fn set_scheduling(&self, scheduling: PermitScheduling) {
+        self.state.lock().expect("indexing permits lock poisoned").scheduling = scheduling;
+    }
+
+    /// Caps how many permits an event holds at once, `None` leaves it to the shared limit
+    pub fn set_quota(self: &Arc<Self>, key: &str, quota: Option<usize>) {
+        self.state.lock().expect("indexing permits lock poisoned").queue_mut(key).quota = quota;
+        self.dispatch();
+    }
+
+    pub async fn acquire(self: &Arc<Self>, key: &str) -> Result<IndexingPermit, RecvError> {
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut state = self.state.lock().expect("indexing permits lock poisoned");
+            state.sequence += 1;
+            let sequence = state.sequence;
+            state.queue_mut(key).waiters.push_back(Waiter { sequence, sender });
+        }
+        self.dispatch();
+        receiver.await
+    }
+
+    /// Hands the free permits to the waiting events, called whenever a permit is released, a
+    /// waiter arrives or the semaphore grows
+    pub fn dispatch(self: &Arc<Self>) {
+        let mut state = self.state.lock().expect("indexing permits lock poisoned");
+        while let Some(index) = state.next_queue() {
+            let Ok(permit) = Arc::clone(&self.semaphore).try_acquire_owned() else {
+                break;
+            };
+
+            let queue = &mut state.queues[index];
+            let waiter = queue.waiters.pop_front().expect("a queue which can take has waiters");
+            let permit = IndexingPermit {
+                permit: Some(permit),
+                key: queue.key.clone(),
+                permits: Some(Arc::clone(self)),
+            };
+            queue.held += 1;
+            // the waiter gave up, its permit goes straight back without taking the lock again
+            if let Err(mut permit) = waiter.sender.send(permit) {
+                permit.permits = None;
+                queue.held -= 1;
+            }
+
+            if state.scheduling == PermitScheduling::RoundRobin {
+                state.next = index + 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held(permits: &IndexingPermits, key: &str) -> usize {
+        permits.state.lock().unwrap().queue_mut(key).held
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_takes_turns_and_quotas_cap_an_event() {
+        let permits = Arc::new(IndexingPermits::new(Arc::new(Semaphore::new(1))));
+        permits.set_scheduling(PermitScheduling::RoundRobin);
+
+        let first = permits.acquire("busy").await.unwrap();
+        let busy = tokio::spawn({
+            let permits = Arc::clone(&permits);
+            async move { permits.acquire("busy").await.unwrap() }
+        });
+        tokio::task::yield_now().await;
+        let quiet = tokio::spawn({
+            let permits = Arc::clone(&permits);
+            async move { permits.acquire("quiet").await.unwrap() }
+        });
+        tokio::task::yield_now().await;
+
+        // the busy event queued first but the quiet one gets the next turn
+        drop(first);
+        let quiet = quiet.await.unwrap();
+        assert_eq!(held(&permits, "quiet"), 1);
+        assert_eq!(held(&permits, "busy"), 0);
+        drop(quiet);
+        drop(busy.await.unwrap());
+
+        let permits = Arc::new(IndexingPermits::new(Arc::new(Semaphore::new(3))));
+        permits.set_quota("busy", Some(1));
+        let _held = permits.acquire("busy").await.unwrap();
+        let waiting = tokio::spawn({
+            let permits = Arc::clone(&permits);
+            async move { permits.acquire("busy").await.unwrap() }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+        assert_eq!(held(&permits, "busy"), 1);
+        let _other = permits.acquire("quiet").await.unwrap();
+        waiting.abort();
+    }
+}
@@ -6,10 +6,17 @@ pub mod manifest;
 mod system_state;
 pub use system_state::{initiate_shutdown, is_running};
 
+mod indexing_permits;
+mod runtime_tuning;
+
 mod database;
+pub mod dev;
 pub use database::postgres::{
+    address_labels::{AddressLabels, AddressLabelsError},
     client::{PostgresClient, ToSql},
     generate::drop_tables_for_indexer_sql,
+    kv_store::{KeyValueStore, KeyValueStoreError},
+    schema_plan::AUTO_APPROVE_SCHEMA_CHANGES_ENV,
     setup::setup_postgres,
     sql_type_wrapper::EthereumSqlTypeWrapper,
 };
@@ -23,18 +30,36 @@ pub use helpers::{
     public_read_env_value, write_file, WriteFileError,
 };
 mod api;
-pub use api::{generate_graphql_queries, GraphqlOverrideSettings};
+pub use api::{
+    fetch_graphql_sdl, fetch_provider_usage, generate_graphql_queries,
+    generate_graphql_typescript_client, set_event_paused, AdminClientError,
+    GraphqlOverrideSettings,
+};
 
+mod error_reporting;
 mod logger;
+mod modules;
 pub use logger::setup_info_logger;
 mod abi;
 pub use abi::ABIItem;
+mod builder;
+pub use builder::{DecodedEvent, Rindexer, RindexerBuilder, RindexerBuilderError};
 mod chat;
 pub mod event;
 pub mod phantom;
 pub mod provider;
+pub mod provider_blocks;
+pub mod provider_failover;
+pub mod provider_latency;
+pub mod provider_recording;
+pub mod provider_state;
+pub mod provider_throttle;
+pub mod provider_traces;
+pub mod provider_usage;
 mod start;
 mod streams;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod types;
 
 // export 3rd party dependencies
@@ -44,7 +69,8 @@ pub use futures::FutureExt;
 pub use lazy_static::lazy_static;
 pub use reqwest::header::HeaderMap;
 pub use start::{
-    start_rindexer, start_rindexer_no_code, IndexerNoCodeDetails, IndexingDetails, StartDetails,
+    start_rindexer, start_rindexer_no_code, start_rindexer_no_code_projects,
+    start_rindexer_projects, IndexerNoCodeDetails, IndexingDetails, StartDetails,
     StartNoCodeDetails,
 };
 pub use tokio::main as rindexer_main;
@@ -0,0 +1,258 @@
+use std::{fs, path::Path};
+
+use ethers::types::{Address, ValueOrArray};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::info;
+
+use crate::{
+    helpers::{write_file, WriteFileError},
+    manifest::core::Manifest,
+    types::single_or_array::StringOrArray,
+};
+
+const SOURCIFY_API_URL: &str = "https://sourcify.dev/server/v2/contract";
+const ETHERSCAN_API_URL: &str = "https://api.etherscan.io/v2/api";
+
+/// Resolved ABIs are kept next to the manifest so later runs and codegen work offline
+const RESOLVED_ABIS_FOLDER: &str = "abis/resolved";
+
+#[derive(thiserror::Error, Debug)]
+pub enum AbiResolverError {
+    #[error("Contract {0} has no ABI and no address on a configured network to look it up by")]
+    NoAddressToResolve(String),
+
+    #[error("No verified ABI found on sourcify or etherscan for {0:?} on chain {1}")]
+    NotVerified(Address, u64),
+
+    #[error("Could not fetch the ABI: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Could not parse the ABI: {0}")]
+    InvalidAbi(#[from] serde_json::Error),
+
+    #[error("Could not write the resolved ABI: {0}")]
+    Write(#[from] WriteFileError),
+
+    #[error("Could not start the runtime to fetch the ABI: {0}")]
+    Runtime(#[from] std::io::Error),
+}
+
+#[derive(Deserialize)]
+struct SourcifyImplementation {
+    address: Address,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SourcifyProxyResolution {
+    #[serde(default)]
+    is_proxy: bool,
+    #[serde(default)]
+    implementations: Vec<SourcifyImplementation>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SourcifyContract {
+    abi: Option<Value>,
+    proxy_resolution: Option<SourcifyProxyResolution>,
+}
+
+#[derive(Deserialize)]
+struct EtherscanResponse {
+    status: String,
+    result: String,
+}
+
+async fn fetch_sourcify(
+    client: &reqwest::Client,
+    chain_id: u64,
+    address: Address,
+) -> Result<Option<SourcifyContract>, AbiResolverError> {
+    let response = client
+        .get(format!("{}/{}/{:?}", SOURCIFY_API_URL, chain_id, address))
+        .query(&[("fields", "abi,proxyResolution")])
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    Ok(Some(response.error_for_status()?.json().await?))
+}
+
+async fn fetch_etherscan(
+    client: &reqwest::Client,
+    api_key: &str,
+    chain_id: u64,
+    address: Address,
+) -> Result<Option<Value>, AbiResolverError> {
+    let response: EtherscanResponse = client
+        .get(ETHERSCAN_API_URL)
+        .query(&[
+            ("chainid", chain_id.to_string()),
+            ("module", "contract".to_string()),
+            ("action", "getabi".to_string()),
+            ("address", format!("{:?}", address)),
+            ("apikey", api_key.to_string()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    // unverified contracts come back with status 0 and the reason in the result
+    if response.status != "1" {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_str(&response.result)?))
+}
+
+/// The ABIs to merge for the address, a verified proxy on sourcify brings the ABIs of its
+/// implementations along so both the proxy and implementation events decode
+async fn fetch_abis(
+    etherscan_api_key: Option<&str>,
+    chain_id: u64,
+    address: Address,
+) -> Result<Vec<(Address, Value)>, AbiResolverError> {
+    let client = reqwest::Client::new();
+
+    if let Some(contract) = fetch_sourcify(&client, chain_id, address).await? {
+        if let Some(abi) = contract.abi {
+            let mut abis = vec![(address, abi)];
+            let implementations = contract
+                .proxy_resolution
+                .filter(|resolution| resolution.is_proxy)
+                .map(|resolution| resolution.implementations)
+                .unwrap_or_default();
+            for implementation in implementations {
+                if let Some(abi) = fetch_sourcify(&client, chain_id, implementation.address)
+                    .await?
+                    .and_then(|contract| contract.abi)
+                {
+                    abis.push((implementation.address, abi));
+                }
+            }
+            return Ok(abis);
+        }
+    }
+
+    if let Some(api_key) = etherscan_api_key {
+        if let Some(abi) = fetch_etherscan(&client, api_key, chain_id, address).await? {
+            return Ok(vec![(address, abi)]);
+        }
+    }
+
+    Err(AbiResolverError::NotVerified(address, chain_id))
+}
+
+/// Whether the contract has a verified ABI on sourcify, or etherscan when there is an api key
+pub async fn is_verified(
+    client: &reqwest::Client,
+    etherscan_api_key: Option<&str>,
+    chain_id: u64,
+    address: Address,
+) -> Result<bool, AbiResolverError> {
+    if fetch_sourcify(client, chain_id, address).await?.is_some_and(|c| c.abi.is_some()) {
+        return Ok(true);
+    }
+
+    match etherscan_api_key {
+        Some(api_key) => Ok(fetch_etherscan(client, api_key, chain_id, address).await?.is_some()),
+        None => Ok(false),
+    }
+}
+
+fn resolved_abi_path(chain_id: u64, address: Address) -> String {
+    format!("./{}/{}_{:?}.abi.json", RESOLVED_ABIS_FOLDER, chain_id, address)
+}
+
+fn implementations_path(chain_id: u64, address: Address) -> String {
+    format!("./{}/{}_{:?}.implementations.json", RESOLVED_ABIS_FOLDER, chain_id, address)
+}
+
+/// Reading the manifest is sync but happens inside and outside of a runtime, the lookups run on
+/// their own thread so they never block the caller's runtime
+fn fetch_abis_blocking(
+    etherscan_api_key: Option<&str>,
+    chain_id: u64,
+    address: Address,
+) -> Result<Vec<(Address, Value)>, AbiResolverError> {
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()?
+                    .block_on(fetch_abis(etherscan_api_key, chain_id, address))
+            })
+            .join()
+            .expect("abi resolver thread panicked")
+    })
+}
+
+/// Fills in the ABI of contracts which only have an address from the verified source on sourcify,
+/// or etherscan when a `global.etherscan_api_key` is set. Lookups are written to `abis/resolved`
+/// and read from there on later runs.
+pub fn resolve_missing_abis(
+    project_path: &Path,
+    manifest: &mut Manifest,
+) -> Result<(), AbiResolverError> {
+    let etherscan_api_key =
+        manifest.global.as_ref().and_then(|global| global.etherscan_api_key.clone());
+
+    for contract in manifest.contracts.iter_mut().filter(|contract| contract.abi.is_empty()) {
+        let (chain_id, address) = contract
+            .details
+            .iter()
+            .find_map(|detail| {
+                let chain_id =
+                    manifest.networks.iter().find(|n| n.name == detail.network)?.chain_id;
+                let address = match detail.address()? {
+                    ValueOrArray::Value(address) => *address,
+                    ValueOrArray::Array(addresses) => *addresses.first()?,
+                };
+                Some((chain_id, address))
+            })
+            .ok_or_else(|| AbiResolverError::NoAddressToResolve(contract.name.clone()))?;
+
+        let cached_path = resolved_abi_path(chain_id, address);
+        if project_path.join(&cached_path).exists() {
+            let mut abi_paths = vec![cached_path];
+            // the implementations of a proxy are cached next to it
+            if let Ok(implementations) =
+                fs::read_to_string(project_path.join(implementations_path(chain_id, address)))
+            {
+                let implementations: Vec<Address> = serde_json::from_str(&implementations)?;
+                abi_paths
+                    .extend(implementations.into_iter().map(|a| resolved_abi_path(chain_id, a)));
+            }
+            contract.abi = StringOrArray::Multiple(abi_paths);
+            continue;
+        }
+
+        info!("Resolving the ABI of {} from {:?} on chain {}", contract.name, address, chain_id);
+        let abis = fetch_abis_blocking(etherscan_api_key.as_deref(), chain_id, address)?;
+
+        let mut abi_paths = vec![];
+        for (abi_address, abi) in &abis {
+            let abi_path = resolved_abi_path(chain_id, *abi_address);
+            write_file(&project_path.join(&abi_path), &serde_json::to_string_pretty(abi)?)?;
+            abi_paths.push(abi_path);
+        }
+        if abis.len() > 1 {
+            let implementations: Vec<Address> = abis[1..].iter().map(|(a, _)| *a).collect();
+            write_file(
+                &project_path.join(implementations_path(chain_id, address)),
+                &serde_json::to_string(&implementations)?,
+            )?;
+        }
+        contract.abi = StringOrArray::Multiple(abi_paths);
+    }
+
+    Ok(())
+}
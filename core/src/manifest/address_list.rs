@@ -0,0 +1,96 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::get_full_path;
+
+/// Addresses given in the manifest or a file in the project, large lists are best kept in a file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum AddressList {
+    Addresses(Vec<Address>),
+    /// A `.txt` with an address per line or a `.csv` with the address in the first column, blank
+    /// lines, a header and lines starting with `#` are skipped
+    File {
+        file: String,
+    },
+}
+
+fn parse_address_lines(contents: &str) -> Result<HashSet<Address>, String> {
+    let mut addresses = HashSet::new();
+    for (number, line) in contents.lines().enumerate() {
+        let value = line.split(',').next().unwrap_or_default().trim().trim_matches('"');
+        if value.is_empty() || value.starts_with('#') || value.eq_ignore_ascii_case("address") {
+            continue;
+        }
+        let address = value
+            .parse::<Address>()
+            .map_err(|_| format!("line {} is not an address: {}", number + 1, value))?;
+        addresses.insert(address);
+    }
+    Ok(addresses)
+}
+
+impl AddressList {
+    pub fn read(&self, project_path: &Path) -> Result<HashSet<Address>, String> {
+        match self {
+            AddressList::Addresses(addresses) => Ok(addresses.iter().copied().collect()),
+            AddressList::File { file } => {
+                let path = get_full_path(project_path, file).map_err(|e| e.to_string())?;
+                let contents =
+                    fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                parse_address_lines(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+        }
+    }
+}
+
+/// Which emitting addresses the logs of a filter contract are kept for, checked once the logs are
+/// fetched as the node can only filter on the topics
+#[derive(Debug, Default)]
+pub struct AddressFilter {
+    allow: Option<HashSet<Address>>,
+    deny: HashSet<Address>,
+}
+
+impl AddressFilter {
+    pub fn read(
+        project_path: &Path,
+        allowlist: Option<&AddressList>,
+        denylist: Option<&AddressList>,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            allow: allowlist.map(|list| list.read(project_path)).transpose()?,
+            deny: denylist.map(|list| list.read(project_path)).transpose()?.unwrap_or_default(),
+        })
+    }
+
+    /// In the allowlist when there is one and never in the denylist
+    pub fn allows(&self, address: &Address) -> bool {
+        self.allow.as_ref().is_none_or(|allow| allow.contains(address)) &&
+            !self.deny.contains(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_filter_from_lines() {
+        let allowed = Address::repeat_byte(1);
+        let denied = Address::repeat_byte(2);
+        let contents =
+            format!("address,name\n# spam\n\n{:?},token\n{:?},spam token\n", allowed, denied);
+        let listed = parse_address_lines(&contents).unwrap();
+        assert_eq!(listed.len(), 2);
+
+        let filter = AddressFilter { allow: Some(listed), deny: HashSet::from([denied]) };
+        assert!(filter.allows(&allowed));
+        assert!(!filter.allows(&denied));
+        assert!(!filter.allows(&Address::repeat_byte(3)));
+        assert!(AddressFilter::default().allows(&Address::repeat_byte(3)));
+        assert!(parse_address_lines("0x123").is_err());
+    }
+}
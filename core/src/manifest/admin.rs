@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+fn default_port() -> u16 {
+    3010
+}
+
+/// Serves the admin API which changes what the running indexer does, every request needs the
+/// `api_key` as a bearer token so keep it in an environment variable like `${ADMIN_API_KEY}`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AdminSettings {
+    #[serde(default = "default_port")]
+    pub port: u16,
+
+    pub api_key: String,
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::{chat::NotificationDestination, network::Network};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Fires when the indexer is more than `threshold` blocks behind the head of the chain
+    BlocksBehind,
+    /// Fires when the handlers error more than `threshold` times a minute
+    HandlerErrorRate,
+    /// Fires when more than `threshold` events are stalled, needs the watchdog enabled
+    Stalled,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlertRule {
+    pub name: String,
+
+    pub condition: AlertCondition,
+
+    pub threshold: u64,
+
+    /// Only applies to `blocks_behind`, if not set it applies to every network
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+
+    /// How long the condition has to hold before the alert fires, defaults to firing straight away
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub for_seconds: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhooks: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagerduty_routing_key: Option<String>,
+
+    /// Names of the notification destinations to post the alert to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify: Option<Vec<String>>,
+}
+
+impl AlertRule {
+    pub fn for_seconds(&self) -> u64 {
+        self.for_seconds.unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlertsConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_interval_seconds: Option<u64>,
+
+    pub rules: Vec<AlertRule>,
+}
+
+impl AlertsConfig {
+    pub fn check_interval_seconds(&self) -> u64 {
+        self.check_interval_seconds.unwrap_or(30)
+    }
+
+    pub fn validate(
+        &self,
+        networks: &[Network],
+        destinations: &[NotificationDestination],
+    ) -> Result<(), String> {
+        for rule in &self.rules {
+            if let Some(network) = &rule.network {
+                if rule.condition != AlertCondition::BlocksBehind {
+                    return Err(format!(
+                        "Alert {} can only set a network for the blocks_behind condition",
+                        rule.name
+                    ));
+                }
+
+                if !networks.iter().any(|n| &n.name == network) {
+                    return Err(format!(
+                        "Alert {} network {} is not defined in networks",
+                        rule.name, network
+                    ));
+                }
+            }
+
+            for notify in rule.notify.iter().flatten() {
+                if !destinations.iter().any(|d| &d.name == notify) {
+                    return Err(format!(
+                        "Alert {} notify destination {} is not defined in notifications",
+                        rule.name, notify
+                    ));
+                }
+            }
+
+            if rule.webhooks.as_ref().is_none_or(|w| w.is_empty()) &&
+                rule.pagerduty_routing_key.is_none() &&
+                rule.notify.as_ref().is_none_or(|n| n.is_empty())
+            {
+                return Err(format!(
+                    "Alert {} needs at least one webhook, notify destination or a pagerduty_routing_key",
+                    rule.name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
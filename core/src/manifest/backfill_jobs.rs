@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BackfillJobsSettings {
+    /// Size of the block ranges the historic backfill is split into, defaults to 100000 blocks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<u64>,
+
+    /// How long a worker can hold a job before another worker is allowed to take it over,
+    /// defaults to 3600 seconds so keep it well above the time a single chunk takes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claim_timeout_seconds: Option<u64>,
+
+    /// How often to check for jobs while waiting on other workers, defaults to 10 seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_interval_seconds: Option<u64>,
+}
+
+impl BackfillJobsSettings {
+    pub fn chunk_size(&self) -> u64 {
+        self.chunk_size.unwrap_or(100_000).max(1)
+    }
+
+    pub fn claim_timeout_seconds(&self) -> u64 {
+        self.claim_timeout_seconds.unwrap_or(3600)
+    }
+
+    pub fn poll_interval_seconds(&self) -> u64 {
+        self.poll_interval_seconds.unwrap_or(10)
+    }
+}
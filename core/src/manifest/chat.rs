@@ -55,6 +55,41 @@ pub struct SlackEvent {
     pub template_inline: String,
 }
 
+/// A chat destination declared once at the top level of the manifest which contract chat routes
+/// and alerts can target by name
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationDestination {
+    pub name: String,
+
+    #[serde(flatten)]
+    pub sink: NotificationSink,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationSink {
+    Telegram { bot_token: String, chat_id: i64 },
+    Discord { bot_token: String, channel_id: u64 },
+    Slack { bot_token: String, channel: String },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatRouteEvent {
+    pub event_name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<Vec<Map<String, Value>>>,
+
+    pub template_inline: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatRoute {
+    pub destination: String,
+    pub networks: Vec<String>,
+    pub messages: Vec<ChatRouteEvent>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatConfig {
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -65,4 +100,73 @@ pub struct ChatConfig {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub slack: Option<Vec<SlackConfig>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routes: Option<Vec<ChatRoute>>,
+}
+
+impl ChatConfig {
+    /// Expands the routes into the chat configs of the destination they target so they are sent
+    /// exactly like inline chat configs
+    pub fn resolve_routes(mut self, destinations: &[NotificationDestination]) -> Self {
+        for route in self.routes.take().unwrap_or_default() {
+            let Some(destination) = destinations.iter().find(|d| d.name == route.destination)
+            else {
+                continue;
+            };
+
+            match &destination.sink {
+                NotificationSink::Telegram { bot_token, chat_id } => {
+                    self.telegram.get_or_insert_with(Vec::new).push(TelegramConfig {
+                        bot_token: bot_token.clone(),
+                        chat_id: *chat_id,
+                        networks: route.networks,
+                        messages: route
+                            .messages
+                            .into_iter()
+                            .map(|m| TelegramEvent {
+                                event_name: m.event_name,
+                                conditions: m.conditions,
+                                template_inline: m.template_inline,
+                            })
+                            .collect(),
+                    })
+                }
+                NotificationSink::Discord { bot_token, channel_id } => {
+                    self.discord.get_or_insert_with(Vec::new).push(DiscordConfig {
+                        bot_token: bot_token.clone(),
+                        channel_id: *channel_id,
+                        networks: route.networks,
+                        messages: route
+                            .messages
+                            .into_iter()
+                            .map(|m| DiscordEvent {
+                                event_name: m.event_name,
+                                conditions: m.conditions,
+                                template_inline: m.template_inline,
+                            })
+                            .collect(),
+                    })
+                }
+                NotificationSink::Slack { bot_token, channel } => {
+                    self.slack.get_or_insert_with(Vec::new).push(SlackConfig {
+                        bot_token: bot_token.clone(),
+                        channel: channel.clone(),
+                        networks: route.networks,
+                        messages: route
+                            .messages
+                            .into_iter()
+                            .map(|m| SlackEvent {
+                                event_name: m.event_name,
+                                conditions: m.conditions,
+                                template_inline: m.template_inline,
+                            })
+                            .collect(),
+                    })
+                }
+            }
+        }
+
+        self
+    }
 }
@@ -1,8 +1,14 @@
-use std::{borrow::Cow, collections::HashSet, fs, path::Path};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::Arc,
+};
 
 use ethers::{
     addressbook::Address,
-    prelude::{Filter, ValueOrArray, U64},
+    prelude::{Filter, ValueOrArray, H256, U64},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -10,11 +16,15 @@ use serde_json::{json, Value};
 use super::core::{deserialize_option_u64_from_string, serialize_option_u64_as_string};
 use crate::{
     event::contract_setup::{
-        AddressDetails, ContractEventMapping, FilterDetails, IndexingContractSetup,
+        AddressDetails, ContractEventMapping, FilterDetails, IndexingContractSetup, RegistryDetails,
+    },
+    helpers::{camel_to_snake, get_full_path},
+    indexer::{encode_topic, parse_topic},
+    manifest::{
+        address_list::{AddressFilter, AddressList},
+        chat::ChatConfig,
+        stream::StreamsConfig,
     },
-    helpers::get_full_path,
-    indexer::parse_topic,
-    manifest::{chat::ChatConfig, stream::StreamsConfig},
     types::single_or_array::StringOrArray,
 };
 
@@ -30,28 +40,405 @@ pub struct EventInputIndexedFilters {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub indexed_3: Option<Vec<String>>,
+
+    /// The ABI types of the event's indexed inputs in order, resolved when the manifest is read
+    /// so the values are encoded as their input type
+    #[serde(skip)]
+    pub indexed_types: Vec<String>,
 }
 
 impl EventInputIndexedFilters {
+    /// The values of each indexed input in order, `None` where the input is not filtered on
+    pub fn values(&self) -> [Option<&Vec<String>>; 3] {
+        [self.indexed_1.as_ref(), self.indexed_2.as_ref(), self.indexed_3.as_ref()]
+    }
+
+    /// The topics the values filter on, any of the values of an input matches
+    pub fn topics(&self) -> [Option<Vec<H256>>; 3] {
+        let mut topics = [None, None, None];
+        for (position, values) in self.values().into_iter().enumerate() {
+            let Some(values) = values else {
+                continue;
+            };
+            topics[position] = Some(
+                values
+                    .iter()
+                    .map(|value| {
+                        self.indexed_types
+                            .get(position)
+                            .and_then(|type_| encode_topic(type_, value))
+                            .unwrap_or_else(|| parse_topic(value))
+                    })
+                    .collect(),
+            );
+        }
+        topics
+    }
+
     pub fn extend_filter_indexed(&self, mut filter: Filter) -> Filter {
-        if let Some(indexed_1) = &self.indexed_1 {
-            filter = filter.topic1(indexed_1.iter().map(|i| parse_topic(i)).collect::<Vec<_>>());
+        let [topic1, topic2, topic3] = self.topics();
+        if let Some(topic1) = topic1 {
+            filter = filter.topic1(topic1);
         }
-        if let Some(indexed_2) = &self.indexed_2 {
-            filter = filter.topic2(indexed_2.iter().map(|i| parse_topic(i)).collect::<Vec<_>>());
+        if let Some(topic2) = topic2 {
+            filter = filter.topic2(topic2);
         }
-        if let Some(indexed_3) = &self.indexed_3 {
-            filter = filter.topic3(indexed_3.iter().map(|i| parse_topic(i)).collect::<Vec<_>>());
+        if let Some(topic3) = topic3 {
+            filter = filter.topic3(topic3);
         }
         filter
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventConcurrency {
+    pub event_name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_callbacks: Option<usize>,
+
+    /// How many of the shared RPC log request permits the event can hold at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_requests: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_order: Option<bool>,
+}
+
+/// What happens to an event when one of its handlers halts or its logs can not be fetched
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Stops every event of the indexer
+    HaltIndexer,
+    /// Stops only the failed event, the other events carry on indexing
+    #[default]
+    HaltEvent,
+    /// Records the range the handler halted on and moves past it like a skipped batch
+    Skip,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventFailurePolicy {
+    pub event_name: String,
+
+    pub policy: FailurePolicy,
+}
+
+/// How the decoded params of the events are written to postgres
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamsStorage {
+    /// A typed column for each param
+    #[default]
+    Columns,
+    /// Every param in a single `decoded_params` JSONB column, inserts keep working whatever is
+    /// added to the ABI. Only no-code projects can leave the typed columns out.
+    Jsonb,
+    /// The typed columns along with the `decoded_params` JSONB column
+    Both,
+}
+
+impl ParamsStorage {
+    pub fn typed_columns(&self) -> bool {
+        *self != ParamsStorage::Jsonb
+    }
+
+    pub fn jsonb(&self) -> bool {
+        *self != ParamsStorage::Columns
+    }
+}
+
+/// How long the rows of an event are kept, rows older than either limit are pruned
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventRetention {
+    pub event_name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_blocks: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_days: Option<u64>,
+}
+
+/// Token amounts of an event which are stored divided by the token decimals as well as raw, in
+/// `{input}_normalized` NUMERIC columns. No code projects fill them in, rust projects can use
+/// `get_token_decimals` on the provider in their handlers.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventDecimals {
+    pub event_name: String,
+
+    /// The uint and int inputs holding token amounts
+    pub inputs: Vec<String>,
+
+    /// Fixed decimals, otherwise `decimals()` is called on the contract emitting the event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+}
+
+impl EventDecimals {
+    /// The column the normalized value of an input is stored in
+    pub fn normalized_column_name(input: &str) -> String {
+        format!("{}_normalized", camel_to_snake(input))
+    }
+}
+
+/// Inputs of an event whose values are encrypted with pgcrypto when the row is inserted into
+/// postgres. The typed column and the decoded params are left without the value and the
+/// ciphertext goes in `{column}_encrypted`. CSV files and streams still get the values, NOTIFY
+/// payloads only carry the table and row id. The raw log archive and the write-ahead log would
+/// keep the values in plaintext so manifest validation rejects them for these events.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EncryptedInputs {
+    pub event_name: String,
+
+    pub inputs: Vec<String>,
+}
+
+impl EncryptedInputs {
+    pub fn encrypted_column_name(input: &str) -> String {
+        format!("{}_encrypted", camel_to_snake(input))
+    }
+}
+
+/// How postgres compresses the values it moves out of the row (TOAST), `lz4` needs postgres 14 or
+/// later built with lz4
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnCompression {
+    Pglz,
+    Lz4,
+}
+
+impl ColumnCompression {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            ColumnCompression::Pglz => "pglz",
+            ColumnCompression::Lz4 => "lz4",
+        }
+    }
+}
+
+/// What happens to the events of the contracts the spam filter finds
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpamAction {
+    /// The events are neither handled nor stored
+    #[default]
+    Drop,
+    /// The events are handled with the reason set on them so handlers can decide, no-code event
+    /// tables store it in a `spam_reason` column
+    Flag,
+}
+
+/// Finds spam contracts before their events are handled, mostly for filter contracts which index
+/// whatever address emits the event. The contracts found are recorded in postgres when enabled.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpamFilterSettings {
+    #[serde(default)]
+    pub action: SpamAction,
+
+    /// Contracts whose logs do not decode as the event in the ABI, like an ERC721 `Transfer`
+    /// under the ERC20 signature, on by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undecodable_logs: Option<bool>,
+
+    /// A contract emitting the event this many times in one transaction is taken for an airdrop
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub airdrop_logs_per_transaction: Option<usize>,
+
+    /// Contracts without a verified ABI on sourcify, or etherscan when a
+    /// `global.etherscan_api_key` is set. A few contracts are looked up at once, a verified one
+    /// only once and an unverified one again after an hour.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unverified_contracts: Option<bool>,
+
+    /// Known spam contracts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deny_list: Option<AddressList>,
+
+    /// The deny list read into a set, resolved when the manifest is read
+    #[serde(skip)]
+    pub denied: Arc<HashSet<Address>>,
+}
+
+impl SpamFilterSettings {
+    pub fn undecodable_logs(&self) -> bool {
+        self.undecodable_logs.unwrap_or(true)
+    }
+
+    pub fn airdrop_logs_per_transaction(&self) -> usize {
+        self.airdrop_logs_per_transaction.unwrap_or(50)
+    }
+
+    pub fn unverified_contracts(&self) -> bool {
+        self.unverified_contracts.unwrap_or_default()
+    }
+}
+
+/// Storage settings of the table of an event, applied on every start. Postgres only uses them
+/// for the rows written afterwards, existing rows keep how they were stored until rewritten.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventTableStorage {
+    pub event_name: String,
+
+    /// Table storage parameters like `fillfactor: 100` or `toast_tuple_target: 256`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<BTreeMap<String, serde_yaml::Value>>,
+
+    /// Set on every column of the table which can be compressed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<ColumnCompression>,
+}
+
+fn is_storage_parameter_word(value: &str) -> bool {
+    !value.is_empty() &&
+        value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+}
+
+impl EventTableStorage {
+    /// The parameters as `key = value`, the names and values are plain words so they can go in
+    /// the statement as they are
+    pub fn parameters_sql(&self) -> Result<Vec<String>, String> {
+        self.parameters
+            .iter()
+            .flatten()
+            .map(|(name, value)| {
+                if !is_storage_parameter_word(name) {
+                    return Err(format!("{} is not a storage parameter name", name));
+                }
+                let value = match value {
+                    serde_yaml::Value::Bool(value) => value.to_string(),
+                    serde_yaml::Value::Number(value) => value.to_string(),
+                    serde_yaml::Value::String(value) if is_storage_parameter_word(value) => {
+                        value.clone()
+                    }
+                    _ => {
+                        return Err(format!("the value of {} must be a number, bool or word", name))
+                    }
+                };
+                Ok(format!("{} = {}", name, value))
+            })
+            .collect()
+    }
+}
+
+/// The ordered stages which add columns to the rows of an event. No code projects fill them in,
+/// rust projects get the columns and can fill them in their handlers. Address labels come from
+/// the `labels` sources rather than a stage.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventEnrichment {
+    pub event_name: String,
+
+    pub stages: Vec<EnrichmentStage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EnrichmentStage {
+    /// The column the stage stores its value in, later stages can use it by this name
+    pub name: String,
+
+    #[serde(flatten)]
+    pub source: EnrichmentSource,
+
+    /// How many lookups of the stage run at once, defaults to 10
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_size: Option<usize>,
+
+    /// How many looked up values the stage keeps, 0 turns the cache off, defaults to 10000
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_size: Option<usize>,
+}
+
+impl EnrichmentStage {
+    pub fn batch_size(&self) -> usize {
+        self.batch_size.unwrap_or(10).max(1)
+    }
+
+    pub fn cache_size(&self) -> usize {
+        self.cache_size.unwrap_or(10_000)
+    }
+
+    pub fn column_name(&self) -> String {
+        camel_to_snake(&self.name)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptField {
+    GasUsed,
+    EffectiveGasPrice,
+    Status,
+    From,
+}
+
+/// Where a stage gets its value from. Fields are the event inputs, `contract_address`,
+/// `block_number`, `tx_hash` and the names of the stages before it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EnrichmentSource {
+    /// The unix timestamp of the block
+    BlockTimestamp,
+
+    /// A field of the transaction receipt
+    Receipt { field: ReceiptField },
+
+    /// A view function called as of the block of the event, e.g.
+    /// `balanceOf(address) returns (uint256)` with the fields passed as its arguments
+    EthCall {
+        function: String,
+
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+
+        /// The contract called, defaults to the contract emitting the event
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        to: Option<Address>,
+    },
+
+    /// A number read from a JSON API, `{field}` in the url is filled in per event and `pointer`
+    /// is the JSON pointer to the number, e.g. `/usd`
+    Price { url: String, pointer: String },
+}
+
+/// How `rindexer dev` deploys the contract to its local anvil node
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContractDevSettings {
+    /// The Foundry build artifact, for example `./out/Token.sol/Token.json`
+    pub foundry_artifact: String,
+
+    /// Constructor arguments in the order the constructor takes them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constructor_args: Option<Vec<String>>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FilterDetailsYaml {
     pub event_name: String,
 }
 
+/// A contract listing the addresses to index, e.g. a token list or a DAO registry. It is re-read
+/// on an interval so the indexed addresses follow it without editing the manifest, addresses it
+/// adds are indexed from the ranges fetched after they are seen.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AddressRegistryDetails {
+    pub address: Address,
+
+    /// A view function without arguments which returns `address[]`, e.g. `getTokens`
+    pub function: String,
+
+    /// Defaults to 300 seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_interval_seconds: Option<u64>,
+}
+
+impl AddressRegistryDetails {
+    pub fn refresh_interval_seconds(&self) -> u64 {
+        self.refresh_interval_seconds.unwrap_or(300)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ContractDetails {
     pub network: String,
@@ -59,6 +446,9 @@ pub struct ContractDetails {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     address: Option<ValueOrArray<Address>>,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<AddressRegistryDetails>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub filter: Option<ValueOrArray<FilterDetailsYaml>>,
 
@@ -82,6 +472,18 @@ pub struct ContractDetails {
         serialize_with = "serialize_option_u64_as_string"
     )]
     pub end_block: Option<U64>,
+
+    /// Filter contracts only keep the logs of these emitting addresses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_allowlist: Option<AddressList>,
+
+    /// Filter contracts drop the logs of these emitting addresses, like known spam contracts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_denylist: Option<AddressList>,
+
+    /// The allowlist and denylist read into sets, resolved when the manifest is read
+    #[serde(skip)]
+    pub address_filter: Option<Arc<AddressFilter>>,
 }
 
 impl ContractDetails {
@@ -93,21 +495,29 @@ impl ContractDetails {
             })
             // } else if let Some(factory) = &self.factory {
             //     IndexingContractSetup::Factory(factory.clone())
+        } else if let Some(registry) = &self.registry {
+            IndexingContractSetup::Registry(RegistryDetails {
+                network: self.network.clone(),
+                registry: registry.clone(),
+                indexed_filters: self.indexed_filters.clone(),
+            })
         } else if let Some(filter) = &self.filter {
             return match filter {
                 ValueOrArray::Value(filter) => IndexingContractSetup::Filter(FilterDetails {
                     events: ValueOrArray::Value(filter.event_name.clone()),
                     indexed_filters: self.indexed_filters.as_ref().and_then(|f| f.first().cloned()),
+                    address_filter: self.address_filter.clone(),
                 }),
                 ValueOrArray::Array(filters) => IndexingContractSetup::Filter(FilterDetails {
                     events: ValueOrArray::Array(
                         filters.iter().map(|f| f.event_name.clone()).collect(),
                     ),
                     indexed_filters: self.indexed_filters.as_ref().and_then(|f| f.first().cloned()),
+                    address_filter: self.address_filter.clone(),
                 }),
             }
         } else {
-            panic!("Contract details must have an address, factory or filter");
+            panic!("Contract details must have an address, registry, factory or filter");
         }
     }
 
@@ -131,11 +541,15 @@ impl ContractDetails {
         Self {
             network,
             address: Some(address),
+            registry: None,
             filter: None,
             indexed_filters,
             //factory: None,
             start_block,
             end_block,
+            address_allowlist: None,
+            address_denylist: None,
+            address_filter: None,
         }
     }
 
@@ -199,6 +613,8 @@ pub struct Contract {
 
     pub details: Vec<ContractDetails>,
 
+    /// Left out the ABI is looked up from the verified source of the address
+    #[serde(default, skip_serializing_if = "StringOrArray::is_empty")]
     pub abi: StringOrArray,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -207,9 +623,50 @@ pub struct Contract {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub index_event_in_order: Option<Vec<String>>,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_events_by_block: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_concurrency: Option<Vec<EventConcurrency>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_policy: Option<Vec<EventFailurePolicy>>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub dependency_events: Option<DependencyEventTreeYaml>,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention: Option<Vec<EventRetention>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalize_decimals: Option<Vec<EventDecimals>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enrichment: Option<Vec<EventEnrichment>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_inputs: Option<Vec<EncryptedInputs>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table_storage: Option<Vec<EventTableStorage>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params_storage: Option<ParamsStorage>,
+
+    /// Keeps the undecoded logs in postgres so the events can be decoded again after an ABI fix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_raw_logs: Option<bool>,
+
+    /// Leaves logs a contract listed earlier in the manifest also indexes to that contract, so
+    /// handlers do not see a log twice when filters and explicit contracts overlap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deduplicate_logs: Option<bool>,
+
+    /// Events whose batches are written to a local write-ahead log before their handlers run,
+    /// so batches in flight when the process dies are handled again without refetching them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write_ahead_log: Option<Vec<String>>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub reorg_safe_distance: Option<bool>,
 
@@ -221,6 +678,18 @@ pub struct Contract {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub chat: Option<ChatConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dev: Option<ContractDevSettings>,
+
+    /// Contracts with the same table group write their events into one shared set of tables
+    /// with a `chain_id` column, so the same contract deployed on several networks can be
+    /// queried across chains
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table_group: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spam_filter: Option<SpamFilterSettings>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -237,9 +706,120 @@ pub enum ParseAbiError {
 
     #[error("Could not merge ABI: {0}")]
     CouldNotMergeAbis(#[from] serde_json::Error),
+
+    #[error("The ABIs define the event {0} as both {1} and {2}, events are indexed by name")]
+    ConflictingEvent(String, String, String),
+}
+
+/// The canonical type of an ABI input, tuples are written out from their components
+fn abi_input_type(input: &Value) -> String {
+    let type_ = input["type"].as_str().unwrap_or_default();
+    match type_.strip_prefix("tuple") {
+        Some(array_suffix) => {
+            let components = input["components"]
+                .as_array()
+                .map(|components| components.iter().map(abi_input_type).collect::<Vec<_>>())
+                .unwrap_or_default();
+            format!("({}){}", components.join(","), array_suffix)
+        }
+        None => type_.to_string(),
+    }
+}
+
+/// What makes two ABI entries the same, facets and implementations often repeat the same event or
+/// function with other parameter names or internal types so comparing the json is not enough
+fn abi_entry_signature(entry: &Value) -> String {
+    let type_ = entry["type"].as_str().unwrap_or_default();
+    match type_ {
+        "event" | "function" | "error" => {
+            let inputs = entry["inputs"]
+                .as_array()
+                .map(|inputs| inputs.iter().map(abi_input_type).collect::<Vec<_>>())
+                .unwrap_or_default();
+            format!(
+                "{} {}({})",
+                type_,
+                entry["name"].as_str().unwrap_or_default(),
+                inputs.join(",")
+            )
+        }
+        // a proxy and its implementation both have a constructor, only one can be kept
+        _ => type_.to_string(),
+    }
+}
+
+/// Merges the ABIs of a diamond's facets or a proxy and its implementation into one, the first
+/// ABI listed wins when an entry is in more than one
+fn merge_abis(abis: Vec<Value>) -> Result<Vec<Value>, ParseAbiError> {
+    let mut unique_entries = HashSet::new();
+    let mut event_signatures: HashMap<String, String> = HashMap::new();
+    let mut merged_abi_value = Vec::new();
+
+    for abi_value in abis {
+        let Value::Array(abi_arr) = abi_value else {
+            return Err(ParseAbiError::InvalidAbiFormat(format!(
+                "Expected an array but got a single value: {}",
+                abi_value
+            )));
+        };
+
+        for entry in abi_arr {
+            let signature = abi_entry_signature(&entry);
+            if entry["type"] == "event" {
+                let name = entry["name"].as_str().unwrap_or_default().to_string();
+                if let Some(existing) = event_signatures.get(&name) {
+                    if *existing != signature {
+                        return Err(ParseAbiError::ConflictingEvent(
+                            name,
+                            existing.clone(),
+                            signature,
+                        ));
+                    }
+                }
+                event_signatures.insert(name, signature.clone());
+            }
+
+            if unique_entries.insert(signature) {
+                merged_abi_value.push(entry);
+            }
+        }
+    }
+
+    Ok(merged_abi_value)
 }
 
 impl Contract {
+    /// A contract with everything optional left unset, an empty `abi` is looked up by address
+    pub fn new(name: &str, details: Vec<ContractDetails>, abi: StringOrArray) -> Self {
+        Self {
+            name: name.to_string(),
+            details,
+            abi,
+            include_events: None,
+            index_event_in_order: None,
+            group_events_by_block: None,
+            event_concurrency: None,
+            failure_policy: None,
+            dependency_events: None,
+            retention: None,
+            normalize_decimals: None,
+            enrichment: None,
+            encrypted_inputs: None,
+            table_storage: None,
+            params_storage: None,
+            archive_raw_logs: None,
+            deduplicate_logs: None,
+            write_ahead_log: None,
+            reorg_safe_distance: None,
+            generate_csv: None,
+            streams: None,
+            chat: None,
+            dev: None,
+            table_group: None,
+            spam_filter: None,
+        }
+    }
+
     pub fn override_name(&mut self, name: String) {
         self.name = name;
     }
@@ -252,30 +832,16 @@ impl Contract {
                 Ok(abi_str)
             }
             StringOrArray::Multiple(abis) => {
-                let mut unique_entries = HashSet::new();
-                let mut merged_abi_value = Vec::new();
-
-                for abi_path in abis {
-                    let full_path = get_full_path(project_path, abi_path)?;
-                    let abi_str = fs::read_to_string(full_path)?;
-                    let abi_value: Value = serde_json::from_str(&abi_str)?;
-
-                    if let Value::Array(abi_arr) = abi_value {
-                        for entry in abi_arr {
-                            let entry_str = serde_json::to_string(&entry)?;
-                            if unique_entries.insert(entry_str) {
-                                merged_abi_value.push(entry);
-                            }
-                        }
-                    } else {
-                        return Err(ParseAbiError::InvalidAbiFormat(format!(
-                            "Expected an array but got a single value: {}",
-                            abi_value
-                        )));
-                    }
-                }
+                let abi_values = abis
+                    .iter()
+                    .map(|abi_path| {
+                        let full_path = get_full_path(project_path, abi_path)?;
+                        let abi_str = fs::read_to_string(full_path)?;
+                        Ok(serde_json::from_str(&abi_str)?)
+                    })
+                    .collect::<Result<Vec<Value>, ParseAbiError>>()?;
 
-                let merged_abi_str = serde_json::to_string(&json!(merged_abi_value))?;
+                let merged_abi_str = serde_json::to_string(&json!(merge_abis(abi_values)?))?;
                 Ok(merged_abi_str)
             }
         }
@@ -302,6 +868,62 @@ impl Contract {
         }
     }
 
+    pub fn is_event_grouped_by_block(&self, event_name: &str) -> bool {
+        self.group_events_by_block
+            .as_ref()
+            .is_some_and(|events| events.iter().any(|e| e == event_name))
+    }
+
+    pub fn is_event_write_ahead_logged(&self, event_name: &str) -> bool {
+        self.write_ahead_log.as_ref().is_some_and(|events| events.iter().any(|e| e == event_name))
+    }
+
+    fn event_concurrency(&self, event_name: &str) -> Option<&EventConcurrency> {
+        self.event_concurrency.as_ref().and_then(|c| c.iter().find(|e| e.event_name == event_name))
+    }
+
+    /// An event is processed in order if listed in `index_event_in_order` or if its
+    /// `event_concurrency` entry asks for it
+    pub fn is_event_indexed_in_order(&self, event_name: &str) -> bool {
+        self.index_event_in_order
+            .as_ref()
+            .is_some_and(|events| events.iter().any(|e| e == event_name)) ||
+            self.event_concurrency(event_name).and_then(|c| c.in_order).unwrap_or_default()
+    }
+
+    pub fn max_concurrent_callbacks(&self, event_name: &str) -> Option<usize> {
+        self.event_concurrency(event_name).and_then(|c| c.max_concurrent_callbacks)
+    }
+
+    pub fn max_concurrent_requests(&self, event_name: &str) -> Option<usize> {
+        self.event_concurrency(event_name).and_then(|c| c.max_concurrent_requests)
+    }
+
+    pub fn params_storage(&self) -> ParamsStorage {
+        self.params_storage.unwrap_or_default()
+    }
+
+    pub fn archive_raw_logs(&self) -> bool {
+        self.archive_raw_logs.unwrap_or_default()
+    }
+
+    pub fn deduplicate_logs(&self) -> bool {
+        self.deduplicate_logs.unwrap_or_default()
+    }
+
+    pub fn failure_policy(&self, event_name: &str) -> FailurePolicy {
+        self.failure_policy
+            .as_ref()
+            .and_then(|policies| policies.iter().find(|p| p.event_name == event_name))
+            .map(|p| p.policy)
+            .unwrap_or_default()
+    }
+
+    /// The spam filter keeps the events of spam contracts with the reason set on them
+    pub fn flags_spam(&self) -> bool {
+        self.spam_filter.as_ref().is_some_and(|spam_filter| spam_filter.action == SpamAction::Flag)
+    }
+
     pub fn is_filter(&self) -> bool {
         let filter_count = self
             .details
@@ -337,6 +959,35 @@ impl Contract {
         }
     }
 
+    /// The name the event tables are created under, the table group when the contract shares
+    /// its tables with others
+    pub fn table_name_readonly(&self) -> Cow<'_, str> {
+        match &self.table_group {
+            Some(table_group) => Cow::Borrowed(table_group),
+            None => self.before_modify_name_if_filter_readonly(),
+        }
+    }
+
+    pub fn event_decimals(&self, event_name: &str) -> Option<&EventDecimals> {
+        self.normalize_decimals.as_ref()?.iter().find(|decimals| decimals.event_name == event_name)
+    }
+
+    pub fn event_enrichment(&self, event_name: &str) -> &[EnrichmentStage] {
+        self.enrichment
+            .iter()
+            .flatten()
+            .find(|enrichment| enrichment.event_name == event_name)
+            .map_or(&[], |enrichment| enrichment.stages.as_slice())
+    }
+
+    pub fn event_encrypted_inputs(&self, event_name: &str) -> &[String] {
+        self.encrypted_inputs
+            .iter()
+            .flatten()
+            .find(|encrypted| encrypted.event_name == event_name)
+            .map_or(&[], |encrypted| encrypted.inputs.as_slice())
+    }
+
     pub fn identify_and_modify_filter(&mut self) -> bool {
         if self.is_filter() {
             self.override_name(self.contract_name_to_filter_name());
@@ -346,3 +997,92 @@ impl Contract {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ethers::utils::keccak256;
+
+    use super::*;
+
+    #[test]
+    fn test_indexed_filter_topics_are_encoded_as_their_input_type() {
+        let filters = EventInputIndexedFilters {
+            event_name: "Swap".to_string(),
+            indexed_1: Some(vec![
+                "0x0000000000000000000000000000000000000001".to_string(),
+                "0x0000000000000000000000000000000000000002".to_string(),
+            ]),
+            indexed_2: None,
+            indexed_3: Some(vec!["-1".to_string(), "42".to_string()]),
+            indexed_types: vec!["address".to_string(), "uint256".to_string(), "string".to_string()],
+        };
+
+        let [topic1, topic2, topic3] = filters.topics();
+        assert_eq!(topic1.unwrap(), vec![H256::from_low_u64_be(1), H256::from_low_u64_be(2)]);
+        assert!(topic2.is_none());
+        // strings are hashed even when they read as numbers
+        assert_eq!(topic3.unwrap(), vec![H256::from(keccak256("-1")), H256::from(keccak256("42"))]);
+
+        assert_eq!(encode_topic("int24", "-1"), Some(H256::repeat_byte(0xff)));
+        assert_eq!(encode_topic("uint8", "0x10"), Some(H256::from_low_u64_be(16)));
+        assert_eq!(encode_topic("address", "not an address"), None);
+        assert_eq!(encode_topic("uint256[]", "1"), None);
+    }
+
+    #[test]
+    fn test_merge_abis() {
+        let ownable_facet = json!([
+            {"type": "event", "name": "OwnershipTransferred", "anonymous": false, "inputs": [
+                {"name": "previousOwner", "type": "address", "indexed": true},
+                {"name": "newOwner", "type": "address", "indexed": true}
+            ]},
+            {"type": "constructor", "inputs": []}
+        ]);
+        let swap_facet = json!([
+            {"type": "event", "name": "OwnershipTransferred", "anonymous": false, "inputs": [
+                {"name": "from", "type": "address", "indexed": true, "internalType": "address"},
+                {"name": "to", "type": "address", "indexed": true, "internalType": "address"}
+            ]},
+            {"type": "event", "name": "Swap", "anonymous": false, "inputs": [
+                {"name": "route", "type": "tuple[]", "indexed": false, "components": [
+                    {"name": "pool", "type": "address"},
+                    {"name": "amount", "type": "uint256"}
+                ]}
+            ]},
+            {"type": "constructor", "inputs": [{"name": "owner", "type": "address"}]}
+        ]);
+
+        let merged = merge_abis(vec![ownable_facet.clone(), swap_facet]).unwrap();
+        assert_eq!(merged.len(), 3);
+        assert_eq!(abi_entry_signature(&merged[2]), "event Swap((address,uint256)[])");
+
+        let conflicting = json!([
+            {"type": "event", "name": "OwnershipTransferred", "inputs": [
+                {"name": "newOwner", "type": "address", "indexed": true}
+            ]}
+        ]);
+        assert!(matches!(
+            merge_abis(vec![ownable_facet, conflicting]),
+            Err(ParseAbiError::ConflictingEvent(..))
+        ));
+    }
+
+    #[test]
+    fn test_table_storage_parameters_must_be_plain_words() {
+        let storage: EventTableStorage = serde_yaml::from_str(
+            "event_name: Transfer\nparameters:\n  fillfactor: 100\n  toast.autovacuum_enabled: false\ncompression: lz4",
+        )
+        .unwrap();
+        assert_eq!(
+            storage.parameters_sql().unwrap(),
+            vec!["fillfactor = 100", "toast.autovacuum_enabled = false"]
+        );
+        assert_eq!(storage.compression, Some(ColumnCompression::Lz4));
+
+        let storage: EventTableStorage = serde_yaml::from_str(
+            "event_name: Transfer\nparameters:\n  fillfactor: \"100); DROP TABLE x; --\"",
+        )
+        .unwrap();
+        assert!(storage.parameters_sql().is_err());
+    }
+}
@@ -5,8 +5,24 @@ use serde_yaml::Value;
 use crate::{
     indexer::Indexer,
     manifest::{
-        contract::Contract, global::Global, graphql::GraphQLSettings, network::Network,
-        phantom::Phantom, storage::Storage,
+        admin::AdminSettings,
+        alerts::AlertsConfig,
+        backfill_jobs::BackfillJobsSettings,
+        chat::NotificationDestination,
+        contract::Contract,
+        flight::FlightSettings,
+        global::Global,
+        graphql::{DerivedField, GraphQLRole, GraphQLSettings},
+        labels::LabelSource,
+        leader_election::LeaderElectionSettings,
+        metrics::MetricsSettings,
+        modules::ModulesSettings,
+        network::Network,
+        phantom::Phantom,
+        sentry::SentrySettings,
+        storage::Storage,
+        tuning::TuningSettings,
+        watchdog::WatchdogSettings,
     },
 };
 
@@ -76,6 +92,42 @@ pub struct Manifest {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub graphql: Option<GraphQLSettings>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flight: Option<FlightSettings>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin: Option<AdminSettings>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<MetricsSettings>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<Vec<NotificationDestination>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<AlertsConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentry: Option<SentrySettings>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watchdog: Option<WatchdogSettings>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leader_election: Option<LeaderElectionSettings>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backfill_jobs: Option<BackfillJobsSettings>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tuning: Option<TuningSettings>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<LabelSource>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modules: Option<ModulesSettings>,
 }
 
 impl Manifest {
@@ -83,6 +135,18 @@ impl Manifest {
         Indexer { name: self.name.clone(), contracts: self.contracts.clone() }
     }
 
+    pub fn notification_destinations(&self) -> &[NotificationDestination] {
+        self.notifications.as_deref().unwrap_or_default()
+    }
+
+    pub fn graphql_derived_fields(&self) -> &[DerivedField] {
+        self.graphql.as_ref().map(|graphql| graphql.derived_fields()).unwrap_or_default()
+    }
+
+    pub fn graphql_roles(&self) -> &[GraphQLRole] {
+        self.graphql.as_ref().map(|graphql| graphql.roles()).unwrap_or_default()
+    }
+
     pub fn has_any_contracts_live_indexing(&self) -> bool {
         self.contracts.iter().filter(|c| c.details.iter().any(|p| p.end_block.is_none())).count() >
             0
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+fn default_port() -> u16 {
+    50051
+}
+
+/// Serves the indexed tables over Arrow Flight for bulk reads
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FlightSettings {
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
@@ -1,5 +1,15 @@
+use std::path::Path;
+
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    abi::ABIItem,
+    manifest::{
+        contract::Contract,
+        storage::{Entity, ENTITY_EVENT_COLUMNS},
+    },
+};
+
 fn default_port() -> u16 {
     3001
 }
@@ -14,11 +24,197 @@ pub struct GraphQLSettings {
 
     #[serde(default)]
     pub filter_only_on_indexed_columns: bool,
+
+    /// Turns off the explorer page with example queries for every event and the indexing lag
+    #[serde(default)]
+    pub disable_explorer: bool,
+
+    /// Defaults to the port after the GraphQL port
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explorer_port: Option<u16>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derived_fields: Option<Vec<DerivedField>>,
+
+    /// When set every request needs the api key of a role and only sees what the role can read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<GraphQLRole>>,
+
+    /// The role requests without an api key run as, they are refused when it is not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anonymous_role: Option<String>,
 }
 
 impl Default for GraphQLSettings {
     fn default() -> Self {
-        Self { port: 3001, disable_advanced_filters: false, filter_only_on_indexed_columns: false }
+        Self {
+            port: 3001,
+            disable_advanced_filters: false,
+            filter_only_on_indexed_columns: false,
+            disable_explorer: false,
+            explorer_port: None,
+            derived_fields: None,
+            roles: None,
+            anonymous_role: None,
+        }
+    }
+}
+
+/// An event table or an entity table and one of its columns
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DerivedFieldTable {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract_name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<String>,
+
+    pub column: String,
+}
+
+impl DerivedFieldTable {
+    fn validate(
+        &self,
+        project_path: &Path,
+        contracts: &[Contract],
+        entities: &[Entity],
+    ) -> Result<(), String> {
+        match (&self.contract_name, &self.event_name, &self.entity) {
+            (Some(contract_name), Some(event_name), None) => {
+                let contract = contracts
+                    .iter()
+                    .find(|c| c.name == *contract_name)
+                    .ok_or_else(|| format!("contract {} is not defined", contract_name))?;
+                let abi_items = ABIItem::read_abi_items(project_path, contract)
+                    .map_err(|e| format!("can not read the ABI of {}: {}", contract.name, e))?;
+                let abi_event = abi_items
+                    .iter()
+                    .find(|item| item.name == *event_name && item.type_ == "event")
+                    .ok_or_else(|| {
+                        format!("event {} not found in the ABI of {}", event_name, contract_name)
+                    })?;
+                if !ENTITY_EVENT_COLUMNS.contains(&self.column.as_str()) &&
+                    !abi_event.inputs.iter().any(|i| i.name == self.column)
+                {
+                    return Err(format!(
+                        "input {} of event {} not found in the ABI",
+                        self.column, event_name
+                    ));
+                }
+            }
+            (None, None, Some(entity_name)) => {
+                let entity = entities
+                    .iter()
+                    .find(|e| e.name == *entity_name)
+                    .ok_or_else(|| format!("entity {} is not defined", entity_name))?;
+                if entity.column(&self.column).is_none() {
+                    return Err(format!(
+                        "column {} of entity {} is not defined",
+                        self.column, entity_name
+                    ));
+                }
+            }
+            _ => return Err("set either contract_name with event_name or entity".to_string()),
+        }
+
+        Ok(())
+    }
+}
+
+/// A field on the rows of the parent table listing the child rows whose column holds the value
+/// of the parent column on the same network, like `@derivedFrom` in a subgraph
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DerivedField {
+    pub name: String,
+
+    pub parent: DerivedFieldTable,
+
+    pub children: DerivedFieldTable,
+}
+
+impl DerivedField {
+    pub fn validate(
+        &self,
+        project_path: &Path,
+        contracts: &[Contract],
+        entities: &[Entity],
+    ) -> Result<(), String> {
+        self.parent
+            .validate(project_path, contracts, entities)
+            .map_err(|e| format!("parent of derived field {}: {}", self.name, e))?;
+        self.children
+            .validate(project_path, contracts, entities)
+            .map_err(|e| format!("children of derived field {}: {}", self.name, e))
+    }
+}
+
+/// An event table a role can read, only the listed columns when `columns` is set. The columns
+/// are event inputs or the event columns like `block_number`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphQLRoleEvent {
+    pub contract_name: String,
+
+    pub event_name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub columns: Option<Vec<String>>,
+}
+
+impl GraphQLRoleEvent {
+    fn validate(&self, project_path: &Path, contracts: &[Contract]) -> Result<(), String> {
+        let contract = contracts
+            .iter()
+            .find(|c| c.name == self.contract_name)
+            .ok_or_else(|| format!("contract {} is not defined", self.contract_name))?;
+        let abi_items = ABIItem::read_abi_items(project_path, contract)
+            .map_err(|e| format!("can not read the ABI of {}: {}", contract.name, e))?;
+        let abi_event = abi_items
+            .iter()
+            .find(|item| item.name == self.event_name && item.type_ == "event")
+            .ok_or_else(|| {
+                format!("event {} not found in the ABI of {}", self.event_name, contract.name)
+            })?;
+
+        for column in self.columns.iter().flatten() {
+            if !ENTITY_EVENT_COLUMNS.contains(&column.as_str()) &&
+                !abi_event.inputs.iter().any(|i| i.name == *column)
+            {
+                return Err(format!(
+                    "input {} of event {} not found in the ABI",
+                    column, self.event_name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The postgres role the requests with one of its `api_keys` as a bearer token run as, it can
+/// only read the events listed. The role is created when it does not exist so the database user
+/// needs CREATEROLE. The decrypt roles of column encryption can name it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphQLRole {
+    pub name: String,
+
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+
+    pub events: Vec<GraphQLRoleEvent>,
+}
+
+impl GraphQLRole {
+    pub fn validate(&self, project_path: &Path, contracts: &[Contract]) -> Result<(), String> {
+        for event in &self.events {
+            event
+                .validate(project_path, contracts)
+                .map_err(|e| format!("role {}: {}", self.name, e))?;
+        }
+
+        Ok(())
     }
 }
 
@@ -26,4 +222,16 @@ impl GraphQLSettings {
     pub fn set_port(&mut self, port: u16) {
         self.port = port;
     }
+
+    pub fn explorer_port(&self) -> u16 {
+        self.explorer_port.unwrap_or(self.port.saturating_add(1))
+    }
+
+    pub fn derived_fields(&self) -> &[DerivedField] {
+        self.derived_fields.as_deref().unwrap_or_default()
+    }
+
+    pub fn roles(&self) -> &[GraphQLRole] {
+        self.roles.as_deref().unwrap_or_default()
+    }
 }
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// Address labels loaded on start, from a file in the project or a URL returning JSON
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LabelSource {
+    /// Each load replaces the labels the source loaded before
+    pub name: String,
+
+    /// A `.csv` with `address` and `label` columns and an optional `network` column, or a
+    /// `.json` array of objects with the same fields
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+
+    /// Returns the same JSON array as a `.json` file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// The network labels without one apply to, all networks when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+}
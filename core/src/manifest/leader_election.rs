@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LeaderElectionSettings {
+    /// Name of the Postgres advisory lock the instances compete for, defaults to the manifest name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock_name: Option<String>,
+
+    /// How often a standby instance tries to take over the lock, defaults to 5 seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_interval_seconds: Option<u64>,
+}
+
+impl LeaderElectionSettings {
+    pub fn lock_name(&self, manifest_name: &str) -> String {
+        self.lock_name.clone().unwrap_or_else(|| manifest_name.to_string())
+    }
+
+    pub fn retry_interval_seconds(&self) -> u64 {
+        self.retry_interval_seconds.unwrap_or(5)
+    }
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+fn default_port() -> u16 {
+    9464
+}
+
+/// Serves the metrics in the prometheus text format on `/metrics`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricsSettings {
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
@@ -1,10 +1,24 @@
+pub mod abi_resolver;
+pub mod address_list;
+pub mod admin;
+pub mod alerts;
+pub mod backfill_jobs;
 pub mod chat;
 pub mod contract;
 pub mod core;
+pub mod flight;
 pub mod global;
 pub mod graphql;
+pub mod labels;
+pub mod leader_election;
+pub mod metrics;
+pub mod modules;
 pub mod network;
 pub mod phantom;
+pub mod sentry;
 pub mod storage;
 pub mod stream;
+pub mod subgraph;
+pub mod tuning;
+pub mod watchdog;
 pub mod yaml;
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::contract::ContractDetails;
+
+/// Built in indexing for common standards which writes into its own pre-modeled tables, each
+/// module is indexed like a contract named after it
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ModulesSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub erc20_balances: Option<Erc20BalancesModule>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nft_ownership: Option<NftOwnershipModule>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uniswap: Option<UniswapModule>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safe: Option<SafeModule>,
+}
+
+impl ModulesSettings {
+    /// The enabled modules by name with what they index
+    pub fn enabled(&self) -> Vec<(&'static str, &[ContractDetails])> {
+        let mut enabled: Vec<(&'static str, &[ContractDetails])> = vec![];
+        if let Some(erc20_balances) = &self.erc20_balances {
+            enabled.push(("erc20_balances", &erc20_balances.details));
+        }
+        if let Some(nft_ownership) = &self.nft_ownership {
+            enabled.push(("nft_ownership", &nft_ownership.details));
+        }
+        if let Some(uniswap) = &self.uniswap {
+            enabled.push(("uniswap", &uniswap.details));
+        }
+        if let Some(safe) = &self.safe {
+            enabled.push(("safe", &safe.details));
+        }
+
+        enabled
+    }
+}
+
+/// Current and historical holder balances of ERC20 tokens from their Transfer events
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Erc20BalancesModule {
+    /// The tokens, set like contract details so a `Transfer` filter tracks every token on a
+    /// network
+    pub details: Vec<ContractDetails>,
+}
+
+/// Current owners of ERC721 tokens and holder balances of ERC1155 tokens from their transfer
+/// events, batch transfers included
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NftOwnershipModule {
+    /// The collections, set like contract details so a filter tracks every collection on a
+    /// network
+    pub details: Vec<ContractDetails>,
+}
+
+/// Pools, reserves, prices and hourly volume of Uniswap V2 and V3 style pools from their
+/// Sync and Swap events
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UniswapModule {
+    /// The pools and factories, the factories give the tokens and fee of the pools they create.
+    /// Pools created by a factory are only indexed when listed or when a filter is used.
+    pub details: Vec<ContractDetails>,
+}
+
+/// Executed Safe multisig transactions with the call they made, their nonce and the owners who
+/// signed them
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SafeModule {
+    /// The Safes, set like contract details so a filter tracks every Safe on a network
+    pub details: Vec<ContractDetails>,
+}
@@ -1,7 +1,11 @@
+use std::collections::BTreeMap;
+
 use ethers::prelude::U64;
+use reqwest::header::HeaderMap;
 use serde::{Deserialize, Serialize};
 
 use super::core::{deserialize_option_u64_from_string, serialize_option_u64_as_string};
+use crate::provider::build_rpc_headers;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Network {
@@ -24,4 +28,209 @@ pub struct Network {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disable_logs_bloom_checks: Option<bool>,
+
+    /// Extra headers sent with every RPC request, basic auth can also go in the rpc url itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpc_headers: Option<BTreeMap<String, String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpc_bearer_token: Option<String>,
+
+    /// Fetches the historic ranges from a faster source than `eth_getLogs`, live indexing still
+    /// goes over the rpc
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub historical_source: Option<HistoricalSource>,
+
+    /// Checks the logs bloom of every block in a historic range before calling `eth_getLogs`, and
+    /// skips the call when no block can hold the event. The scan costs one `eth_getBlockByNumber`
+    /// per block to save a single `eth_getLogs`, so a 100 block range is 100 requests instead of
+    /// one. It only pays off for very sparse events on nodes where `eth_getLogs` is far more
+    /// expensive than a block, ranges over 100 blocks are never scanned so it needs a
+    /// `max_block_range` of at most 100 to do anything.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logs_bloom_scan: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<ProviderCacheSettings>,
+
+    /// Checks the rpc `eth_chainId` matches `chain_id` on start and while indexing, defaults to
+    /// true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validate_chain_id: Option<bool>,
+
+    /// Used in order while the endpoints before them are failing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_rpcs: Option<Vec<String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpc_retry: Option<RpcRetrySettings>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RpcRetrySettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_backoff_ms: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_backoff_ms: Option<u64>,
+
+    /// Failures in a row before an endpoint is taken out of the rotation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker_failures: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker_cooldown_seconds: Option<u64>,
+
+    /// How long a single request to an http rpc can take before it counts as failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_timeout_seconds: Option<u64>,
+
+    /// How long fetching the logs of a range can take including its retries, a historic range
+    /// which takes longer is split in half and fetched again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_deadline_seconds: Option<u64>,
+
+    /// Sends requests to the endpoint with the lowest recent latency first instead of the primary,
+    /// the latency of every endpoint is on the metrics endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fastest_first: Option<bool>,
+}
+
+impl RpcRetrySettings {
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries.unwrap_or(5)
+    }
+
+    pub fn initial_backoff_ms(&self) -> u64 {
+        self.initial_backoff_ms.unwrap_or(500)
+    }
+
+    pub fn max_backoff_ms(&self) -> u64 {
+        self.max_backoff_ms.unwrap_or(30_000)
+    }
+
+    pub fn circuit_breaker_failures(&self) -> u32 {
+        self.circuit_breaker_failures.unwrap_or(5).max(1)
+    }
+
+    pub fn circuit_breaker_cooldown_seconds(&self) -> u64 {
+        self.circuit_breaker_cooldown_seconds.unwrap_or(30)
+    }
+
+    pub fn request_timeout_seconds(&self) -> u64 {
+        self.request_timeout_seconds.unwrap_or(60).max(1)
+    }
+
+    pub fn chunk_deadline_seconds(&self) -> u64 {
+        self.chunk_deadline_seconds.unwrap_or(300).max(1)
+    }
+
+    pub fn fastest_first(&self) -> bool {
+        self.fastest_first.unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ProviderCacheSettings {
+    /// Sends every request to the rpc, overrides the other settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest_block_ttl_ms: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number_ttl_ms: Option<u64>,
+
+    /// The chain id never changes for a healthy rpc so it is cached until the indexer restarts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logs_ttl_ms: Option<u64>,
+
+    /// Max `eth_getLogs` responses kept in the cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_entries: Option<usize>,
+}
+
+impl ProviderCacheSettings {
+    pub fn disabled(&self) -> bool {
+        self.disabled.unwrap_or_default()
+    }
+
+    pub fn latest_block_ttl_ms(&self) -> u64 {
+        self.latest_block_ttl_ms.unwrap_or(300)
+    }
+
+    pub fn block_number_ttl_ms(&self) -> u64 {
+        self.block_number_ttl_ms.unwrap_or(0)
+    }
+
+    pub fn chain_id(&self) -> bool {
+        self.chain_id.unwrap_or(true)
+    }
+
+    pub fn logs_ttl_ms(&self) -> u64 {
+        self.logs_ttl_ms.unwrap_or(0)
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.max_entries.unwrap_or(1000)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HistoricalSource {
+    #[serde(rename = "hypersync")]
+    HyperSync {
+        /// The HyperSync endpoint for the network, for example https://eth.hypersync.xyz
+        url: String,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        api_token: Option<String>,
+    },
+}
+
+impl Network {
+    pub fn new(name: &str, chain_id: u64, rpc: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            chain_id,
+            rpc: rpc.to_string(),
+            compute_units_per_second: None,
+            max_block_range: None,
+            disable_logs_bloom_checks: None,
+            rpc_headers: None,
+            rpc_bearer_token: None,
+            logs_bloom_scan: None,
+            historical_source: None,
+            cache: None,
+            validate_chain_id: None,
+            fallback_rpcs: None,
+            rpc_retry: None,
+        }
+    }
+
+    /// The primary rpc followed by the fallbacks
+    pub fn rpc_urls(&self) -> Vec<String> {
+        std::iter::once(self.rpc.clone())
+            .chain(self.fallback_rpcs.iter().flatten().cloned())
+            .collect()
+    }
+
+    pub fn validate_chain_id(&self) -> bool {
+        self.validate_chain_id.unwrap_or(true)
+    }
+
+    pub fn rpc_header_map(&self) -> HeaderMap {
+        build_rpc_headers(
+            self.rpc_headers.iter().flatten().map(|(name, value)| (name.as_str(), value.clone())),
+            self.rpc_bearer_token.clone(),
+        )
+    }
 }
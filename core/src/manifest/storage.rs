@@ -1,9 +1,11 @@
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path};
 
+use ethers::types::Address;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
 use crate::{
+    abi::ABIItem,
     database::postgres::{
         indexes::{
             drop_last_known_indexes, prepare_indexes, DropLastKnownIndexesError,
@@ -13,7 +15,9 @@ use crate::{
             create_relationships, drop_last_known_relationships, CreateRelationshipError,
             DropLastKnownRelationshipsError, Relationship,
         },
+        state_samples::state_sample_columns,
     },
+    indexer::enrichment::parse_view_function,
     manifest::contract::Contract,
 };
 
@@ -72,6 +76,393 @@ pub struct PostgresIndexes {
     pub contracts: Option<Vec<ContractEventsIndexes>>,
 }
 
+/// What happens when an ABI adds event parameters the existing tables do not have columns for
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaEvolution {
+    /// Adds the missing columns on start
+    #[default]
+    Auto,
+    /// Writes the statements to a migration file and stops so they can be reviewed first
+    MigrationFile,
+    /// Leaves the tables alone
+    Disabled,
+}
+
+/// When a materialized view is refreshed, set either `interval_seconds` or `every_blocks`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaterializedViewRefresh {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_seconds: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every_blocks: Option<u64>,
+
+    /// The network whose new blocks count towards `every_blocks`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaterializedView {
+    pub name: String,
+
+    pub query: String,
+
+    /// Columns which uniquely identify a row, lets the view refresh without blocking reads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unique_key: Option<Vec<String>>,
+
+    pub refresh: MaterializedViewRefresh,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityColumnType {
+    Number,
+    Address,
+    Text,
+    Bool,
+}
+
+impl EntityColumnType {
+    pub fn sql_type(&self) -> &'static str {
+        match self {
+            EntityColumnType::Number => "NUMERIC",
+            EntityColumnType::Address => "CHAR(42)",
+            EntityColumnType::Text => "TEXT",
+            EntityColumnType::Bool => "BOOLEAN",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntityColumn {
+    pub name: String,
+
+    #[serde(rename = "type")]
+    pub column_type: EntityColumnType,
+}
+
+/// How an event changes an entity, every map goes from an entity column to the event input its
+/// value is read from, the event columns like `contract_address` can be read as well
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntityEvent {
+    pub contract_name: String,
+
+    pub event_name: String,
+
+    /// Finds the entity the event changes, created when it does not exist yet
+    pub key: BTreeMap<String, String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set: Option<BTreeMap<String, String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub add: Option<BTreeMap<String, String>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtract: Option<BTreeMap<String, String>>,
+}
+
+/// Event columns an entity event can read besides the event inputs
+pub const ENTITY_EVENT_COLUMNS: [&str; 6] =
+    ["contract_address", "tx_hash", "block_number", "block_hash", "tx_index", "log_index"];
+
+/// A table built from the events of one or more contracts, the events are applied in chain order
+/// across all of them
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Entity {
+    pub name: String,
+
+    /// The columns which identify an entity on a network
+    pub key: Vec<String>,
+
+    pub columns: Vec<EntityColumn>,
+
+    pub events: Vec<EntityEvent>,
+}
+
+impl Entity {
+    pub fn column(&self, name: &str) -> Option<&EntityColumn> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    pub fn validate(&self, project_path: &Path, contracts: &[Contract]) -> Result<(), String> {
+        for key in &self.key {
+            if self.column(key).is_none() {
+                return Err(format!("key column {} of entity {} is not a column", key, self.name));
+            }
+        }
+
+        for event in &self.events {
+            let contract =
+                contracts.iter().find(|c| c.name == event.contract_name).ok_or_else(|| {
+                    format!(
+                        "entity {} reads contract {} which is not defined",
+                        self.name, event.contract_name
+                    )
+                })?;
+            let abi_items = ABIItem::read_abi_items(project_path, contract)
+                .map_err(|e| format!("can not read the ABI of {}: {}", contract.name, e))?;
+            let abi_event = abi_items
+                .iter()
+                .find(|item| item.name == event.event_name && item.type_ == "event")
+                .ok_or_else(|| {
+                    format!(
+                        "event {} of entity {} not found in the ABI of {}",
+                        event.event_name, self.name, contract.name
+                    )
+                })?;
+
+            for key in &self.key {
+                if !event.key.contains_key(key) {
+                    return Err(format!(
+                        "event {} of entity {} does not map key column {}",
+                        event.event_name, self.name, key
+                    ));
+                }
+            }
+
+            let none = BTreeMap::new();
+            let updates = [
+                (&event.key, false),
+                (event.set.as_ref().unwrap_or(&none), false),
+                (event.add.as_ref().unwrap_or(&none), true),
+                (event.subtract.as_ref().unwrap_or(&none), true),
+            ];
+            for (mapping, numeric) in updates {
+                for (column, input) in mapping {
+                    let column = self.column(column).ok_or_else(|| {
+                        format!("column {} of entity {} is not defined", column, self.name)
+                    })?;
+                    if numeric && column.column_type != EntityColumnType::Number {
+                        return Err(format!(
+                            "column {} of entity {} is added to or subtracted from so must be a number",
+                            column.name, self.name
+                        ));
+                    }
+                    if !ENTITY_EVENT_COLUMNS.contains(&input.as_str()) &&
+                        !abi_event.inputs.iter().any(|i| i.name == *input)
+                    {
+                        return Err(format!(
+                            "input {} of event {} for entity {} not found in the ABI",
+                            input, event.event_name, self.name
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RollupInterval {
+    Hour,
+    Day,
+}
+
+impl RollupInterval {
+    pub fn seconds(&self) -> u64 {
+        match self {
+            RollupInterval::Hour => 3600,
+            RollupInterval::Day => 86400,
+        }
+    }
+
+    pub fn table_suffix(&self) -> &'static str {
+        match self {
+            RollupInterval::Hour => "hourly",
+            RollupInterval::Day => "daily",
+        }
+    }
+}
+
+/// Event counts per time bucket kept up to date as the events are indexed, so charts do not have
+/// to group the raw event rows
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Rollup {
+    pub name: String,
+
+    pub contract_name: String,
+
+    pub event_name: String,
+
+    pub intervals: Vec<RollupInterval>,
+
+    /// Numeric event inputs summed per bucket
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sum: Option<Vec<String>>,
+
+    /// Address event inputs whose distinct values are counted together per bucket
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unique_addresses: Option<Vec<String>>,
+}
+
+impl Rollup {
+    pub fn sum(&self) -> &[String] {
+        self.sum.as_deref().unwrap_or_default()
+    }
+
+    pub fn unique_addresses(&self) -> &[String] {
+        self.unique_addresses.as_deref().unwrap_or_default()
+    }
+
+    pub fn validate(&self, project_path: &Path, contracts: &[Contract]) -> Result<(), String> {
+        if self.intervals.is_empty() {
+            return Err(format!("rollup {} needs at least one interval", self.name));
+        }
+
+        let contract =
+            contracts.iter().find(|c| c.name == self.contract_name).ok_or_else(|| {
+                format!(
+                    "rollup {} reads contract {} which is not defined",
+                    self.name, self.contract_name
+                )
+            })?;
+        let abi_items = ABIItem::read_abi_items(project_path, contract)
+            .map_err(|e| format!("can not read the ABI of {}: {}", contract.name, e))?;
+        let abi_event = abi_items
+            .iter()
+            .find(|item| item.name == self.event_name && item.type_ == "event")
+            .ok_or_else(|| {
+                format!(
+                    "event {} of rollup {} not found in the ABI of {}",
+                    self.event_name, self.name, contract.name
+                )
+            })?;
+
+        let inputs =
+            [(self.sum(), "int", "an integer"), (self.unique_addresses(), "address", "an address")];
+        for (names, type_prefix, description) in inputs {
+            for name in names {
+                let input = abi_event.inputs.iter().find(|i| i.name == *name).ok_or_else(|| {
+                    format!(
+                        "input {} of event {} for rollup {} not found in the ABI",
+                        name, self.event_name, self.name
+                    )
+                })?;
+                // `int` covers the unsigned `uint` types as well
+                if !input.type_.trim_start_matches('u').starts_with(type_prefix) ||
+                    input.type_.ends_with(']')
+                {
+                    return Err(format!(
+                        "input {} of rollup {} is a {} but must be {}",
+                        name, self.name, input.type_, description
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The key the contract `encrypted_inputs` are encrypted with, kept in a table only the indexer
+/// database user can read. Changing the key leaves the rows encrypted before unreadable.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ColumnEncryption {
+    pub key: String,
+
+    /// Postgres roles which can read the decrypted values, the GraphQL API connecting as one of
+    /// them gets a `{column}Decrypted` field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decrypt_roles: Option<Vec<String>>,
+}
+
+impl ColumnEncryption {
+    pub fn decrypt_roles(&self) -> &[String] {
+        self.decrypt_roles.as_deref().unwrap_or_default()
+    }
+}
+
+/// A block number and timestamp recorded every `every_blocks` blocks of each indexed network, so
+/// a time can be mapped to a block on any chain
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BlockTimestamps {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every_blocks: Option<u64>,
+}
+
+impl BlockTimestamps {
+    pub fn every_blocks(&self) -> u64 {
+        self.every_blocks.unwrap_or(100).max(1)
+    }
+}
+
+/// The results of view functions sampled over time, every function is called on every address
+/// each `interval_seconds` or `every_blocks` blocks of the network and stored as a row per address
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StateSample {
+    pub name: String,
+
+    pub network: String,
+
+    pub addresses: Vec<Address>,
+
+    /// View functions without arguments like `getReserves() returns (uint112 reserve0, uint112
+    /// reserve1, uint32 timestamp)`, a column per output
+    pub functions: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_seconds: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every_blocks: Option<u64>,
+}
+
+impl StateSample {
+    pub fn validate(&self, networks: &[String]) -> Result<(), String> {
+        match (self.interval_seconds, self.every_blocks) {
+            (Some(interval_seconds), None) if interval_seconds > 0 => {}
+            (None, Some(every_blocks)) if every_blocks > 0 => {}
+            _ => {
+                return Err(format!(
+                "state sample {} must set exactly one of interval_seconds or every_blocks above 0",
+                self.name
+            ))
+            }
+        }
+
+        if !networks.contains(&self.network) {
+            return Err(format!(
+                "state sample {} samples network {} which is not defined",
+                self.name, self.network
+            ));
+        }
+
+        if self.addresses.is_empty() || self.functions.is_empty() {
+            return Err(format!(
+                "state sample {} needs at least one address and function",
+                self.name
+            ));
+        }
+
+        let mut columns = vec![];
+        for function in &self.functions {
+            let function = parse_view_function(function)
+                .map_err(|e| format!("function of state sample {}: {}", self.name, e))?;
+            if !function.inputs.is_empty() {
+                return Err(format!(
+                    "function {} of state sample {} can not take arguments",
+                    function.name, self.name
+                ));
+            }
+            for column in state_sample_columns(&function) {
+                if columns.contains(&column) {
+                    return Err(format!("state sample {} has column {} twice", self.name, column));
+                }
+                columns.push(column);
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PostgresDetails {
     pub enabled: bool,
@@ -87,6 +478,36 @@ pub struct PostgresDetails {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disable_create_tables: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub materialized_views: Option<Vec<MaterializedView>>,
+
+    /// Rows in blocks which get reorged out are marked `removed_by_reorg` instead of deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub soft_delete_reorged_rows: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_evolution: Option<SchemaEvolution>,
+
+    /// Services connected to the database can LISTEN on the channel for the rows inserted into
+    /// the event tables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_channel: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column_encryption: Option<ColumnEncryption>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entities: Option<Vec<Entity>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollups: Option<Vec<Rollup>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_samples: Option<Vec<StateSample>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_timestamps: Option<BlockTimestamps>,
 }
 
 fn default_csv_path() -> String {
@@ -156,6 +577,83 @@ impl Storage {
         self.postgres.as_ref().map_or(false, |details| details.drop_each_run.unwrap_or_default())
     }
 
+    pub fn postgres_soft_delete_reorged_rows(&self) -> bool {
+        let enabled = self.postgres_enabled();
+        if !enabled {
+            return false;
+        }
+
+        self.postgres
+            .as_ref()
+            .is_some_and(|details| details.soft_delete_reorged_rows.unwrap_or_default())
+    }
+
+    pub fn postgres_notify_channel(&self) -> Option<&str> {
+        if !self.postgres_enabled() {
+            return None;
+        }
+
+        self.postgres.as_ref().and_then(|details| details.notify_channel.as_deref())
+    }
+
+    pub fn postgres_column_encryption(&self) -> Option<&ColumnEncryption> {
+        if !self.postgres_enabled() {
+            return None;
+        }
+
+        self.postgres.as_ref().and_then(|details| details.column_encryption.as_ref())
+    }
+
+    pub fn postgres_block_timestamps(&self) -> Option<&BlockTimestamps> {
+        if !self.postgres_enabled() {
+            return None;
+        }
+
+        self.postgres.as_ref().and_then(|details| details.block_timestamps.as_ref())
+    }
+
+    pub fn postgres_schema_evolution(&self) -> SchemaEvolution {
+        self.postgres.as_ref().and_then(|details| details.schema_evolution).unwrap_or_default()
+    }
+
+    pub fn postgres_materialized_views(&self) -> &[MaterializedView] {
+        if !self.postgres_enabled() {
+            return &[];
+        }
+
+        self.postgres
+            .as_ref()
+            .and_then(|details| details.materialized_views.as_deref())
+            .unwrap_or_default()
+    }
+
+    pub fn postgres_entities(&self) -> &[Entity] {
+        if !self.postgres_enabled() {
+            return &[];
+        }
+
+        self.postgres.as_ref().and_then(|details| details.entities.as_deref()).unwrap_or_default()
+    }
+
+    pub fn postgres_rollups(&self) -> &[Rollup] {
+        if !self.postgres_enabled() {
+            return &[];
+        }
+
+        self.postgres.as_ref().and_then(|details| details.rollups.as_deref()).unwrap_or_default()
+    }
+
+    pub fn postgres_state_samples(&self) -> &[StateSample] {
+        if !self.postgres_enabled() {
+            return &[];
+        }
+
+        self.postgres
+            .as_ref()
+            .and_then(|details| details.state_samples.as_deref())
+            .unwrap_or_default()
+    }
+
     pub fn csv_enabled(&self) -> bool {
         match &self.csv {
             Some(details) => details.enabled,
@@ -5,7 +5,7 @@ use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::{Map, Value};
 use tokio::fs;
 
-use crate::types::aws_config::AwsConfig;
+use crate::{streams::SchemaFormat, types::aws_config::AwsConfig};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StreamEvent {
@@ -15,12 +15,39 @@ pub struct StreamEvent {
     pub conditions: Option<Vec<Map<String, Value>>>,
 }
 
+/// How a stream destination encodes the chunks of events it is sent
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadFormat {
+    /// The chunk as JSON, what every destination got before formats were configurable
+    #[default]
+    Json,
+    /// The same chunk as JSON in MessagePack
+    MessagePack,
+    /// The events of the chunk in an Avro object container file, the schema generated from the
+    /// ABI is in the file
+    Avro,
+    /// The events of the chunk as length delimited protobuf messages of the schema generated
+    /// from the ABI
+    Protobuf,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SNSStreamTopicConfig {
     pub prefix_id: Option<String>,
     pub topic_arn: String,
     pub networks: Vec<String>,
     pub events: Vec<StreamEvent>,
+
+    /// SNS only takes text so binary formats are sent base64 encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<PayloadFormat>,
+}
+
+impl SNSStreamTopicConfig {
+    pub fn format(&self) -> PayloadFormat {
+        self.format.unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -35,6 +62,15 @@ pub struct WebhookStreamConfig {
     pub shared_secret: String,
     pub networks: Vec<String>,
     pub events: Vec<StreamEvent>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<PayloadFormat>,
+}
+
+impl WebhookStreamConfig {
+    pub fn format(&self) -> PayloadFormat {
+        self.format.unwrap_or_default()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize)]
@@ -66,6 +102,15 @@ pub struct RabbitMQStreamQueueConfig {
     pub routing_key: Option<String>,
     pub networks: Vec<String>,
     pub events: Vec<StreamEvent>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<PayloadFormat>,
+}
+
+impl RabbitMQStreamQueueConfig {
+    pub fn format(&self) -> PayloadFormat {
+        self.format.unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -105,6 +150,54 @@ impl RabbitMQStreamConfig {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum KafkaMessageFormat {
+    /// The chunks of events every stream sends
+    #[default]
+    Rindexer,
+    /// A change event per row in the envelope the Debezium connectors write, rows rolled back by
+    /// a reorg are sent as deletes
+    Debezium,
+    /// A message per event encoded with an Avro schema generated from the ABI, the schema is
+    /// registered in the `schema_registry`
+    Avro,
+    /// As `avro` with a protobuf schema
+    Protobuf,
+    /// The chunks of events every stream sends in MessagePack
+    MessagePack,
+}
+
+impl KafkaMessageFormat {
+    pub fn schema_format(&self) -> Option<SchemaFormat> {
+        match self {
+            KafkaMessageFormat::Avro => Some(SchemaFormat::Avro),
+            KafkaMessageFormat::Protobuf => Some(SchemaFormat::Protobuf),
+            _ => None,
+        }
+    }
+
+    /// The format of the chunks of events for the formats which send chunks
+    pub fn payload_format(&self) -> Option<PayloadFormat> {
+        match self {
+            KafkaMessageFormat::Rindexer => Some(PayloadFormat::Json),
+            KafkaMessageFormat::MessagePack => Some(PayloadFormat::MessagePack),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchemaRegistryConfig {
+    pub url: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct KafkaStreamQueueConfig {
     pub topic: String,
@@ -113,6 +206,15 @@ pub struct KafkaStreamQueueConfig {
     pub key: Option<String>,
     pub networks: Vec<String>,
     pub events: Vec<StreamEvent>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<KafkaMessageFormat>,
+}
+
+impl KafkaStreamQueueConfig {
+    pub fn format(&self) -> KafkaMessageFormat {
+        self.format.unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -131,6 +233,23 @@ pub struct KafkaStreamConfig {
 
     pub acks: String,
     pub topics: Vec<KafkaStreamQueueConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_registry: Option<SchemaRegistryConfig>,
+}
+
+impl KafkaStreamConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.schema_registry.is_none() &&
+            self.topics.iter().any(|topic| topic.format().schema_format().is_some())
+        {
+            return Err(
+                "Kafka topics in the avro or protobuf format need a schema_registry".to_string()
+            );
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -148,10 +267,37 @@ pub struct StreamsConfig {
     pub kafka: Option<KafkaStreamConfig>,
 }
 
+impl StreamsConfig {
+    /// Only the kafka topics sending debezium change events, the streams which hear about reorgs
+    pub fn debezium_only(&self) -> Option<StreamsConfig> {
+        let kafka = self.kafka.as_ref()?;
+        let topics: Vec<KafkaStreamQueueConfig> = kafka
+            .topics
+            .iter()
+            .filter(|topic| topic.format() == KafkaMessageFormat::Debezium)
+            .cloned()
+            .collect();
+        if topics.is_empty() {
+            return None;
+        }
+
+        Some(StreamsConfig {
+            sns: None,
+            webhooks: None,
+            rabbitmq: None,
+            kafka: Some(KafkaStreamConfig { topics, ..kafka.clone() }),
+        })
+    }
+}
+
 impl StreamsConfig {
     pub fn validate(&self) -> Result<(), String> {
         if let Some(rabbitmq) = &self.rabbitmq {
-            return rabbitmq.validate();
+            rabbitmq.validate()?;
+        }
+
+        if let Some(kafka) = &self.kafka {
+            kafka.validate()?;
         }
 
         Ok(())
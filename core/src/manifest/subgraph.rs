@@ -0,0 +1,387 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ethers::{
+    addressbook::Address,
+    prelude::{ValueOrArray, U64},
+};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::{
+    helpers::camel_to_snake,
+    manifest::{
+        contract::{Contract, ContractDetails},
+        core::{Manifest, ProjectType},
+        network::Network,
+        storage::{PostgresDetails, Storage},
+    },
+    types::single_or_array::StringOrArray,
+};
+
+pub const SUBGRAPH_YAML_CONFIG_NAME: &str = "subgraph.yaml";
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SubgraphSource {
+    #[serde(default)]
+    pub address: Option<String>,
+
+    pub abi: String,
+
+    #[serde(default)]
+    pub start_block: Option<u64>,
+
+    #[serde(default)]
+    pub end_block: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SubgraphAbi {
+    pub name: String,
+
+    pub file: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SubgraphEventHandler {
+    pub event: String,
+
+    pub handler: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SubgraphMapping {
+    #[serde(default)]
+    pub abis: Vec<SubgraphAbi>,
+
+    #[serde(default)]
+    pub event_handlers: Vec<SubgraphEventHandler>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SubgraphDataSource {
+    pub kind: String,
+
+    pub name: String,
+
+    pub network: String,
+
+    pub source: SubgraphSource,
+
+    pub mapping: SubgraphMapping,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SubgraphManifest {
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub repository: Option<String>,
+
+    #[serde(default)]
+    pub data_sources: Vec<SubgraphDataSource>,
+
+    #[serde(default)]
+    pub templates: Vec<serde_yaml::Value>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConvertSubgraphError {
+    #[error("Could not read subgraph manifest: {0}")]
+    CouldNotReadSubgraphManifest(#[from] std::io::Error),
+
+    #[error("Could not parse subgraph manifest: {0}")]
+    CouldNotParseSubgraphManifest(#[from] serde_yaml::Error),
+
+    #[error("Unknown subgraph network {0} - add it to the networks in rindexer.yaml manually")]
+    UnknownNetwork(String),
+
+    #[error(
+        "Data source {0} does not have an address - only address based data sources are supported"
+    )]
+    DataSourceMissingAddress(String),
+
+    #[error("Data source {0} has an invalid address: {1}")]
+    InvalidAddress(String, String),
+
+    #[error("ABI {0} is not mapped in data source {1}")]
+    AbiNotMapped(String, String),
+}
+
+/// Maps The Graph network names to chain ids
+pub fn subgraph_network_chain_id(network: &str) -> Option<u64> {
+    match network {
+        "mainnet" => Some(1),
+        "sepolia" => Some(11155111),
+        "holesky" => Some(17000),
+        "goerli" => Some(5),
+        "optimism" => Some(10),
+        "optimism-sepolia" => Some(11155420),
+        "bsc" => Some(56),
+        "chapel" => Some(97),
+        "gnosis" | "xdai" => Some(100),
+        "matic" => Some(137),
+        "polygon-amoy" => Some(80002),
+        "fantom" => Some(250),
+        "zksync-era" => Some(324),
+        "base" => Some(8453),
+        "base-sepolia" => Some(84532),
+        "arbitrum-one" => Some(42161),
+        "arbitrum-sepolia" => Some(421614),
+        "celo" => Some(42220),
+        "avalanche" => Some(43114),
+        "linea" => Some(59144),
+        "blast-mainnet" => Some(81457),
+        "scroll" => Some(534352),
+        _ => None,
+    }
+}
+
+/// Subgraph event handlers are declared with the full signature such as
+/// `Transfer(indexed address,indexed address,uint256)` where rindexer only wants the name
+fn event_name_from_signature(signature: &str) -> String {
+    signature.split('(').next().unwrap_or(signature).trim().to_string()
+}
+
+pub struct ConvertedSubgraph {
+    pub manifest: Manifest,
+    /// ABI files to copy from the subgraph into the rindexer project (from, to)
+    pub abis_to_copy: Vec<(PathBuf, PathBuf)>,
+}
+
+pub fn convert_subgraph_manifest(
+    subgraph_manifest_path: &Path,
+    project_name: &str,
+    project_type: ProjectType,
+) -> Result<ConvertedSubgraph, ConvertSubgraphError> {
+    let contents = fs::read_to_string(subgraph_manifest_path)?;
+    let subgraph: SubgraphManifest = serde_yaml::from_str(&contents)?;
+    let subgraph_path = subgraph_manifest_path.parent().unwrap_or(Path::new(""));
+
+    if !subgraph.templates.is_empty() {
+        warn!(
+            "Subgraph has {} templates (dynamic data sources) which are not converted - add the discovered contracts manually",
+            subgraph.templates.len()
+        );
+    }
+
+    let mut networks: Vec<Network> = vec![];
+    let mut contracts: Vec<Contract> = vec![];
+    let mut abis_to_copy: Vec<(PathBuf, PathBuf)> = vec![];
+
+    for data_source in &subgraph.data_sources {
+        if data_source.kind != "ethereum/contract" && data_source.kind != "ethereum" {
+            warn!("Skipping data source {} of kind {}", data_source.name, data_source.kind);
+            continue;
+        }
+
+        if !networks.iter().any(|n| n.name == data_source.network) {
+            let chain_id = subgraph_network_chain_id(&data_source.network)
+                .ok_or_else(|| ConvertSubgraphError::UnknownNetwork(data_source.network.clone()))?;
+            networks.push(Network {
+                name: data_source.network.clone(),
+                chain_id,
+                rpc: format!(
+                    "${{{}_RPC}}",
+                    camel_to_snake(&data_source.network).replace('-', "_").to_uppercase()
+                ),
+                compute_units_per_second: None,
+                max_block_range: None,
+                disable_logs_bloom_checks: None,
+                rpc_headers: None,
+                rpc_bearer_token: None,
+                logs_bloom_scan: None,
+                historical_source: None,
+                cache: None,
+                validate_chain_id: None,
+                fallback_rpcs: None,
+                rpc_retry: None,
+            });
+        }
+
+        let address = data_source
+            .source
+            .address
+            .as_ref()
+            .ok_or_else(|| {
+                ConvertSubgraphError::DataSourceMissingAddress(data_source.name.clone())
+            })?
+            .parse::<Address>()
+            .map_err(|e| {
+                ConvertSubgraphError::InvalidAddress(data_source.name.clone(), e.to_string())
+            })?;
+
+        let abi =
+            data_source.mapping.abis.iter().find(|a| a.name == data_source.source.abi).ok_or_else(
+                || {
+                    ConvertSubgraphError::AbiNotMapped(
+                        data_source.source.abi.clone(),
+                        data_source.name.clone(),
+                    )
+                },
+            )?;
+
+        let abi_file_name = format!("{}.abi.json", abi.name);
+        let abi_destination = PathBuf::from("abis").join(&abi_file_name);
+        if !abis_to_copy.iter().any(|(_, to)| *to == abi_destination) {
+            abis_to_copy.push((subgraph_path.join(&abi.file), abi_destination.clone()));
+        }
+
+        let details = ContractDetails::new_with_address(
+            data_source.network.clone(),
+            ValueOrArray::Value(address),
+            None,
+            data_source.source.start_block.map(U64::from),
+            data_source.source.end_block.map(U64::from),
+        );
+
+        // the same contract can be a data source on many networks
+        if let Some(contract) = contracts.iter_mut().find(|c| c.name == data_source.name) {
+            contract.details.push(details);
+            continue;
+        }
+
+        let include_events: Vec<String> = data_source
+            .mapping
+            .event_handlers
+            .iter()
+            .map(|h| event_name_from_signature(&h.event))
+            .collect();
+
+        contracts.push(Contract {
+            name: data_source.name.clone(),
+            details: vec![details],
+            abi: StringOrArray::Single(format!("./{}", abi_destination.display())),
+            include_events: if include_events.is_empty() { None } else { Some(include_events) },
+            index_event_in_order: None,
+            group_events_by_block: None,
+            event_concurrency: None,
+            failure_policy: None,
+            dependency_events: None,
+            retention: None,
+            normalize_decimals: None,
+            enrichment: None,
+            encrypted_inputs: None,
+            table_storage: None,
+            params_storage: None,
+            archive_raw_logs: None,
+            deduplicate_logs: None,
+            write_ahead_log: None,
+            reorg_safe_distance: None,
+            generate_csv: None,
+            streams: None,
+            chat: None,
+            dev: None,
+            table_group: None,
+            spam_filter: None,
+        });
+    }
+
+    Ok(ConvertedSubgraph {
+        manifest: Manifest {
+            name: project_name.to_string(),
+            description: subgraph.description,
+            repository: subgraph.repository,
+            project_type,
+            networks,
+            storage: Storage {
+                postgres: Some(PostgresDetails {
+                    enabled: true,
+                    drop_each_run: None,
+                    relationships: None,
+                    indexes: None,
+                    disable_create_tables: None,
+                    materialized_views: None,
+                    soft_delete_reorged_rows: None,
+                    schema_evolution: None,
+                    notify_channel: None,
+                    column_encryption: None,
+                    entities: None,
+                    rollups: None,
+                    state_samples: None,
+                    block_timestamps: None,
+                }),
+                csv: None,
+            },
+            contracts,
+            phantom: None,
+            global: None,
+            graphql: None,
+            flight: None,
+            admin: None,
+            metrics: None,
+            notifications: None,
+            alerts: None,
+            sentry: None,
+            watchdog: None,
+            leader_election: None,
+            backfill_jobs: None,
+            tuning: None,
+            labels: None,
+            modules: None,
+        },
+        abis_to_copy,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_event_name_from_signature() {
+        assert_eq!(
+            event_name_from_signature("Transfer(indexed address,indexed address,uint256)"),
+            "Transfer"
+        );
+        assert_eq!(event_name_from_signature("Sync"), "Sync");
+    }
+
+    #[test]
+    fn test_convert_subgraph_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SUBGRAPH_YAML_CONFIG_NAME);
+        let mut file = fs::File::create(&path).unwrap();
+        file.write_all(
+            br#"
+specVersion: 0.0.5
+dataSources:
+  - kind: ethereum/contract
+    name: RocketPoolETH
+    network: mainnet
+    source:
+      address: "0xae78736cd615f374d3085123a210448e74fc6393"
+      abi: RocketTokenRETH
+      startBlock: 18900000
+    mapping:
+      kind: ethereum/events
+      abis:
+        - name: RocketTokenRETH
+          file: ./abis/RocketTokenRETH.json
+      eventHandlers:
+        - event: Transfer(indexed address,indexed address,uint256)
+          handler: handleTransfer
+"#,
+        )
+        .unwrap();
+
+        let converted = convert_subgraph_manifest(&path, "rocket", ProjectType::NoCode).unwrap();
+        assert_eq!(converted.manifest.networks.len(), 1);
+        assert_eq!(converted.manifest.networks[0].chain_id, 1);
+        assert_eq!(converted.manifest.networks[0].rpc, "${MAINNET_RPC}");
+        assert_eq!(converted.manifest.contracts.len(), 1);
+        assert_eq!(
+            converted.manifest.contracts[0].include_events,
+            Some(vec!["Transfer".to_string()])
+        );
+        assert_eq!(converted.abis_to_copy.len(), 1);
+    }
+}
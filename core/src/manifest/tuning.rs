@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+/// How the shared RPC log request permits are handed out to the events waiting for one
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PermitScheduling {
+    /// In the order the events asked for them
+    #[default]
+    Fifo,
+
+    /// Taking turns across the events, so an event with a long history to fetch does not keep
+    /// the others waiting
+    RoundRobin,
+}
+
+/// Settings which can be changed while rindexer is running by editing the manifest and sending
+/// SIGHUP, the per network `max_block_range` is reloaded the same way
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TuningSettings {
+    /// How many RPC log requests can be in flight across all events, defaults to 100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indexing_concurrency: Option<usize>,
+
+    /// How the indexing concurrency is shared between events, defaults to `fifo`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permit_scheduling: Option<PermitScheduling>,
+
+    /// How often live indexing polls for a new block while blocks keep arriving, defaults to
+    /// 200ms
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live_polling_interval_ms: Option<u64>,
+
+    /// Live indexing doubles its polling interval each time the head has not moved, up to this,
+    /// so chains with sporadic blocks are not polled needlessly. Defaults to 2000ms, setting it
+    /// to the polling interval turns the backoff off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live_polling_max_interval_ms: Option<u64>,
+
+    /// Splits the logs of a fetched range into handler calls of at most this many logs with a
+    /// checkpoint after each, so a restart in the middle of a block with a lot of logs carries
+    /// on after the last log handled. Unbounded by default, events grouped by block always get
+    /// whole blocks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_logs_per_handler_call: Option<usize>,
+}
+
+impl TuningSettings {
+    pub fn indexing_concurrency(&self) -> usize {
+        self.indexing_concurrency.unwrap_or(100)
+    }
+
+    pub fn permit_scheduling(&self) -> PermitScheduling {
+        self.permit_scheduling.unwrap_or_default()
+    }
+
+    pub fn live_polling_interval_ms(&self) -> u64 {
+        self.live_polling_interval_ms.unwrap_or(200)
+    }
+
+    pub fn live_polling_max_interval_ms(&self) -> u64 {
+        self.live_polling_max_interval_ms.unwrap_or(2000).max(self.live_polling_interval_ms())
+    }
+
+    pub fn max_logs_per_handler_call(&self) -> Option<usize> {
+        self.max_logs_per_handler_call.filter(|max| *max > 0)
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct WatchdogSettings {
+    /// How long a live event can go without fetching a new block range while the chain head moves
+    /// on before it is treated as stalled, defaults to 300 seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stall_timeout_seconds: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_interval_seconds: Option<u64>,
+
+    /// Restart the pipeline of a stalled event from its last synced block, defaults to true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart: Option<bool>,
+}
+
+impl WatchdogSettings {
+    pub fn stall_timeout_seconds(&self) -> u64 {
+        self.stall_timeout_seconds.unwrap_or(300)
+    }
+
+    pub fn check_interval_seconds(&self) -> u64 {
+        self.check_interval_seconds.unwrap_or(30)
+    }
+
+    pub fn restart(&self) -> bool {
+        self.restart.unwrap_or(true)
+    }
+}
@@ -1,20 +1,30 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     env,
     fs::File,
     io::{Read, Write},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use ethers::types::ValueOrArray;
 use regex::{Captures, Regex};
+use reqwest::header::{HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
 use tracing::error;
 
 use crate::{
     abi::ABIItem,
+    database::postgres::generate::generate_columns_with_data_types,
     helpers::{load_env_from_full_path, replace_env_variable_to_raw_name},
+    indexer::{
+        encode_topic,
+        enrichment::{parse_view_function, unknown_stage_fields},
+    },
     manifest::{
+        abi_resolver::{resolve_missing_abis, AbiResolverError},
+        address_list::AddressFilter,
+        contract::EnrichmentSource,
         core::{Manifest, ProjectType},
         network::Network,
     },
@@ -46,6 +56,9 @@ pub enum ValidateManifestError {
     #[error("Contract name {0} can not include 'Filter' in the name as it is a reserved word")]
     ContractNameCanNotIncludeFilter(String),
 
+    #[error("Contracts {0} and {1} share table group {2} but their events do not match")]
+    TableGroupEventsMismatch(String, String, String),
+
     #[error("Invalid network mapped to contract: network - {0} contract - {1}")]
     InvalidNetworkMappedToContract(String, String),
 
@@ -64,6 +77,9 @@ pub enum ValidateManifestError {
     #[error("Indexed filter defined more than allowed for event {0} for contract {1} - indexed expected: {2} defined: {3}")]
     IndexedFilterDefinedMoreThanAllowed(String, String, usize, usize),
 
+    #[error("Indexed filter value {0} is not a valid {1} for event {2} for contract {3}")]
+    IndexedFilterValueInvalid(String, String, String, String),
+
     #[error("Relationship contract {0} not found")]
     RelationshipContractNotFound(String),
 
@@ -73,8 +89,193 @@ pub enum ValidateManifestError {
     #[error("Streams config is invalid: {0}")]
     StreamsConfigValidationError(String),
 
+    #[error("Event {0} in retention for contract {1} not found in ABI")]
+    RetentionEventNotFoundInABI(String, String),
+
+    #[error("Retention for event {0} in contract {1} must set keep_blocks or keep_days above 0")]
+    RetentionMustKeepSomething(String, String),
+
+    #[error("Event {0} in normalize_decimals for contract {1} not found in ABI")]
+    NormalizeDecimalsEventNotFoundInABI(String, String),
+
+    #[error("Input {0} of event {1} in normalize_decimals for contract {2} must be a uint or int")]
+    NormalizeDecimalsInputNotAnAmount(String, String, String),
+
+    #[error("Event {0} in enrichment for contract {1} not found in ABI")]
+    EnrichmentEventNotFoundInABI(String, String),
+
+    #[error("Encrypted inputs of contract {0} need postgres storage with column_encryption set")]
+    EncryptedInputsRequireColumnEncryption(String),
+
+    #[error("Input {0} of event {1} in encrypted_inputs for contract {2} must be an event input which is not a tuple")]
+    EncryptedInputNotFoundInABI(String, String, String),
+
+    #[error("Contract {0} has encrypted inputs so it can not set archive_raw_logs, the raw logs hold them in plaintext")]
+    EncryptedInputsWithRawLogArchive(String),
+
+    #[error("Event {0} of contract {1} has encrypted inputs so it can not be in write_ahead_log, the log holds them in plaintext")]
+    EncryptedInputsWithWriteAheadLog(String, String),
+
+    #[error("Event {0} in table_storage for contract {1} not found in ABI")]
+    TableStorageEventNotFoundInABI(String, String),
+
+    #[error("Table storage of event {0} for contract {1} is invalid: {2}")]
+    TableStorageInvalid(String, String, String),
+
+    #[error("Enrichment stage {0} of event {1} for contract {2} is invalid: {3}")]
+    EnrichmentStageInvalid(String, String, String, String),
+
+    #[error("Event {0} in group_events_by_block for contract {1} not found in ABI")]
+    GroupEventsByBlockEventNotFoundInABI(String, String),
+
+    #[error("Event {0} in event_concurrency for contract {1} not found in ABI")]
+    EventConcurrencyEventNotFoundInABI(String, String),
+
+    #[error(
+        "Event {0} in event_concurrency for contract {1} must allow at least 1 concurrent callback"
+    )]
+    EventConcurrencyMaxCallbacksMustBeAboveZero(String, String),
+
+    #[error(
+        "Event {0} in event_concurrency for contract {1} must allow at least 1 concurrent request"
+    )]
+    EventConcurrencyMaxRequestsMustBeAboveZero(String, String),
+
+    #[error("Contract {0} can only store its params as jsonb without the typed columns in a no-code project, use both in a rust project")]
+    ParamsStorageJsonbRequiresNoCode(String),
+
+    #[error("Contract {0} archives its raw logs which needs postgres storage enabled")]
+    ArchiveRawLogsRequiresPostgres(String),
+
+    #[error("Event {0} in failure_policy for contract {1} not found in ABI")]
+    FailurePolicyEventNotFoundInABI(String, String),
+
+    #[error("Notification destination names {0} must be unique")]
+    NotificationDestinationNameMustBeUnique(String),
+
+    #[error(
+        "Chat route for contract {0} targets notification destination {1} which is not defined"
+    )]
+    ChatRouteDestinationNotFound(String, String),
+
+    #[error("Alerts config is invalid: {0}")]
+    AlertsConfigValidationError(String),
+
+    #[error("Contract {0} can only set an address_allowlist or address_denylist on a filter")]
+    AddressListsRequireFilter(String),
+
+    #[error("The spam filter of {0} needs airdrop_logs_per_transaction of at least 2")]
+    SpamFilterAirdropThresholdTooLow(String),
+
+    #[error("Address labels need postgres storage enabled")]
+    LabelsRequirePostgres,
+
+    #[error("Label source {0} must set exactly one of file or url")]
+    LabelSourceMustSetFileOrUrl(String),
+
+    #[error("Label source names {0} must be unique")]
+    LabelSourceNameMustBeUnique(String),
+
+    #[error("Leader election needs postgres storage enabled")]
+    LeaderElectionRequiresPostgres,
+
+    #[error("Leader election can not be used with postgres drop_each_run as every instance would drop the tables on start")]
+    LeaderElectionWithDropEachRun,
+
+    #[error("Backfill jobs need postgres storage enabled")]
+    BackfillJobsRequiresPostgres,
+
+    #[error("Materialized view names {0} must be unique")]
+    MaterializedViewNameMustBeUnique(String),
+
+    #[error("Materialized view {0} must set exactly one of refresh interval_seconds or every_blocks above 0")]
+    MaterializedViewInvalidRefresh(String),
+
+    #[error("Materialized view {0} refreshes every_blocks on network {1} which is not defined")]
+    MaterializedViewNetworkNotFound(String, String),
+
+    #[error("Entities are built from the event tables so need postgres storage enabled with create tables")]
+    EntitiesRequireEventTables,
+
+    #[error("Entity names {0} must be unique")]
+    EntityNameMustBeUnique(String),
+
+    #[error("Entity is not valid: {0}")]
+    EntityInvalid(String),
+
+    #[error("Rollups are built from the event tables so need postgres storage enabled with create tables")]
+    RollupsRequireEventTables,
+
+    #[error("Rollup names {0} must be unique")]
+    RollupNameMustBeUnique(String),
+
+    #[error("Rollup is not valid: {0}")]
+    RollupInvalid(String),
+
+    #[error("Derived fields read the event and entity tables so need postgres storage enabled with create tables")]
+    DerivedFieldsRequireTables,
+
+    #[error("Derived field is not valid: {0}")]
+    DerivedFieldInvalid(String),
+
+    #[error("Modules need postgres storage enabled")]
+    ModulesRequirePostgres,
+
+    #[error("Module {0} indexes network {1} which is not defined")]
+    ModuleNetworkNotFound(String, String),
+
+    #[error("Backfill jobs can not be used with postgres drop_each_run as every worker would drop the tables on start")]
+    BackfillJobsWithDropEachRun,
+
+    #[error("Network {0} has an invalid rpc header {1}")]
+    InvalidRpcHeader(String, String),
+
+    #[error("Postgres notify channel {0} must be between 1 and 63 characters")]
+    NotifyChannelInvalid(String),
+
+    #[error(
+        "GraphQL roles are postgres roles so need postgres storage enabled with create tables"
+    )]
+    GraphQLRolesRequireTables,
+
+    #[error("GraphQL role names {0} must be unique")]
+    GraphQLRoleNameMustBeUnique(String),
+
+    #[error("GraphQL api keys must be unique across the roles")]
+    GraphQLApiKeyMustBeUnique,
+
+    #[error("GraphQL anonymous role {0} is not one of the roles")]
+    GraphQLAnonymousRoleNotFound(String),
+
+    #[error("GraphQL role is not valid: {0}")]
+    GraphQLRoleInvalid(String),
+
+    #[error("State sample names {0} must be unique")]
+    StateSampleNameMustBeUnique(String),
+
+    #[error("State sample is not valid: {0}")]
+    StateSampleInvalid(String),
+
     #[error("Global ABI can only be a single string")]
     GlobalAbiCanOnlyBeASingleString(String),
+
+    #[error("Admin api_key can not be empty as an empty bearer token would be accepted")]
+    AdminApiKeyEmpty,
+}
+
+/// The event tables and their columns the abi creates
+type TableGroupColumns = Vec<(String, Vec<String>)>;
+
+fn table_group_columns(abi_items: &[ABIItem]) -> Result<TableGroupColumns, String> {
+    let events = ABIItem::extract_event_names_and_signatures_from_abi(abi_items.to_vec())
+        .map_err(|e| e.to_string())?;
+    let mut columns: TableGroupColumns = events
+        .iter()
+        .map(|event| (event.name.clone(), generate_columns_with_data_types(&event.inputs)))
+        .collect();
+    columns.sort();
+
+    Ok(columns)
 }
 
 fn validate_manifest(
@@ -94,6 +295,7 @@ fn validate_manifest(
         ));
     }
 
+    let mut table_groups: HashMap<&String, (&String, TableGroupColumns)> = HashMap::new();
     for contract in &manifest.contracts {
         if contract.name.to_lowercase().contains("filter") {
             return Err(ValidateManifestError::ContractNameCanNotIncludeFilter(
@@ -104,6 +306,48 @@ fn validate_manifest(
         let events = ABIItem::read_abi_items(project_path, contract)
             .map_err(|e| ValidateManifestError::InvalidABI(contract.name.clone(), e.to_string()))?;
 
+        if contract.archive_raw_logs() && !manifest.storage.postgres_enabled() {
+            return Err(ValidateManifestError::ArchiveRawLogsRequiresPostgres(
+                contract.name.clone(),
+            ));
+        }
+
+        // the generated rust handlers insert into the typed columns
+        if !contract.params_storage().typed_columns() && manifest.project_type == ProjectType::Rust
+        {
+            return Err(ValidateManifestError::ParamsStorageJsonbRequiresNoCode(
+                contract.name.clone(),
+            ));
+        }
+
+        if let Some(table_group) = &contract.table_group {
+            // the shared tables are created from whichever contract comes first so the columns
+            // have to line up
+            let columns = table_group_columns(&events)
+                .map_err(|e| ValidateManifestError::InvalidABI(contract.name.clone(), e))?;
+            match table_groups.get(table_group) {
+                Some((first_contract, first_columns)) if *first_columns != columns => {
+                    return Err(ValidateManifestError::TableGroupEventsMismatch(
+                        first_contract.to_string(),
+                        contract.name.clone(),
+                        table_group.clone(),
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    table_groups.insert(table_group, (&contract.name, columns));
+                }
+            }
+        }
+
+        if contract.spam_filter.as_ref().is_some_and(|spam_filter| {
+            spam_filter.airdrop_logs_per_transaction.is_some_and(|logs| logs < 2)
+        }) {
+            return Err(ValidateManifestError::SpamFilterAirdropThresholdTooLow(
+                contract.name.clone(),
+            ));
+        }
+
         for detail in &contract.details {
             let has_network = manifest.networks.iter().any(|n| n.name == detail.network);
             if !has_network {
@@ -113,6 +357,12 @@ fn validate_manifest(
                 ));
             }
 
+            if (detail.address_allowlist.is_some() || detail.address_denylist.is_some()) &&
+                detail.filter.is_none()
+            {
+                return Err(ValidateManifestError::AddressListsRequireFilter(contract.name.clone()));
+            }
+
             if let Some(filter_details) = &detail.filter {
                 match filter_details {
                     ValueOrArray::Value(filter_details) => {
@@ -144,21 +394,38 @@ fn validate_manifest(
                 for indexed_filter in indexed_filters.iter() {
                     let event = events.iter().find(|e| e.name == indexed_filter.event_name);
                     if let Some(event) = event {
-                        let indexed_allowed_length =
-                            event.inputs.iter().filter(|i| i.indexed.unwrap_or(false)).count();
+                        let indexed_types: Vec<&str> = event
+                            .inputs
+                            .iter()
+                            .filter(|i| i.indexed.unwrap_or(false))
+                            .map(|i| i.type_.as_str())
+                            .collect();
+                        let values = indexed_filter.values();
+                        // the topics are positional so `indexed_3` needs three indexed inputs
                         let indexed_filter_defined =
-                            indexed_filter.indexed_1.as_ref().map_or(0, |_| 1) +
-                                indexed_filter.indexed_2.as_ref().map_or(0, |_| 1) +
-                                indexed_filter.indexed_3.as_ref().map_or(0, |_| 1);
+                            values.iter().rposition(Option::is_some).map_or(0, |last| last + 1);
 
-                        if indexed_filter_defined > indexed_allowed_length {
+                        if indexed_filter_defined > indexed_types.len() {
                             return Err(ValidateManifestError::IndexedFilterDefinedMoreThanAllowed(
                                 indexed_filter.event_name.clone(),
                                 contract.name.clone(),
-                                indexed_allowed_length,
+                                indexed_types.len(),
                                 indexed_filter_defined,
                             ));
                         }
+
+                        for (values, type_) in values.iter().zip(&indexed_types) {
+                            for value in values.iter().flat_map(|values| values.iter()) {
+                                if encode_topic(type_, value).is_none() {
+                                    return Err(ValidateManifestError::IndexedFilterValueInvalid(
+                                        value.clone(),
+                                        type_.to_string(),
+                                        indexed_filter.event_name.clone(),
+                                        contract.name.clone(),
+                                    ));
+                                }
+                            }
+                        }
                     } else {
                         return Err(ValidateManifestError::IndexedFilterEventNotFoundInABI(
                             indexed_filter.event_name.clone(),
@@ -180,6 +447,192 @@ fn validate_manifest(
             }
         }
 
+        for event_name in contract.group_events_by_block.iter().flatten() {
+            if !events.iter().any(|e| e.name == *event_name && e.type_ == "event") {
+                return Err(ValidateManifestError::GroupEventsByBlockEventNotFoundInABI(
+                    event_name.clone(),
+                    contract.name.clone(),
+                ));
+            }
+        }
+
+        if let Some(event_concurrency) = &contract.event_concurrency {
+            for concurrency in event_concurrency {
+                if !events.iter().any(|e| e.name == concurrency.event_name && e.type_ == "event") {
+                    return Err(ValidateManifestError::EventConcurrencyEventNotFoundInABI(
+                        concurrency.event_name.clone(),
+                        contract.name.clone(),
+                    ));
+                }
+
+                if concurrency.max_concurrent_callbacks == Some(0) {
+                    return Err(ValidateManifestError::EventConcurrencyMaxCallbacksMustBeAboveZero(
+                        concurrency.event_name.clone(),
+                        contract.name.clone(),
+                    ));
+                }
+
+                if concurrency.max_concurrent_requests == Some(0) {
+                    return Err(ValidateManifestError::EventConcurrencyMaxRequestsMustBeAboveZero(
+                        concurrency.event_name.clone(),
+                        contract.name.clone(),
+                    ));
+                }
+            }
+        }
+
+        for failure_policy in contract.failure_policy.iter().flatten() {
+            if !events.iter().any(|e| e.name == failure_policy.event_name && e.type_ == "event") {
+                return Err(ValidateManifestError::FailurePolicyEventNotFoundInABI(
+                    failure_policy.event_name.clone(),
+                    contract.name.clone(),
+                ));
+            }
+        }
+
+        for retention in contract.retention.iter().flatten() {
+            if !events.iter().any(|e| e.name == retention.event_name && e.type_ == "event") {
+                return Err(ValidateManifestError::RetentionEventNotFoundInABI(
+                    retention.event_name.clone(),
+                    contract.name.clone(),
+                ));
+            }
+
+            if retention.keep_blocks.unwrap_or_default() == 0 &&
+                retention.keep_days.unwrap_or_default() == 0
+            {
+                return Err(ValidateManifestError::RetentionMustKeepSomething(
+                    retention.event_name.clone(),
+                    contract.name.clone(),
+                ));
+            }
+        }
+
+        for decimals in contract.normalize_decimals.iter().flatten() {
+            let event = events
+                .iter()
+                .find(|e| e.name == decimals.event_name && e.type_ == "event")
+                .ok_or_else(|| {
+                    ValidateManifestError::NormalizeDecimalsEventNotFoundInABI(
+                        decimals.event_name.clone(),
+                        contract.name.clone(),
+                    )
+                })?;
+
+            for input in &decimals.inputs {
+                let is_amount = event.inputs.iter().any(|i| {
+                    i.name == *input &&
+                        (i.type_.starts_with("uint") || i.type_.starts_with("int")) &&
+                        !i.type_.ends_with(']')
+                });
+                if !is_amount {
+                    return Err(ValidateManifestError::NormalizeDecimalsInputNotAnAmount(
+                        input.clone(),
+                        decimals.event_name.clone(),
+                        contract.name.clone(),
+                    ));
+                }
+            }
+        }
+
+        for enrichment in contract.enrichment.iter().flatten() {
+            let event = events
+                .iter()
+                .find(|e| e.name == enrichment.event_name && e.type_ == "event")
+                .ok_or_else(|| {
+                    ValidateManifestError::EnrichmentEventNotFoundInABI(
+                        enrichment.event_name.clone(),
+                        contract.name.clone(),
+                    )
+                })?;
+            let input_names: Vec<&str> = event.inputs.iter().map(|i| i.name.as_str()).collect();
+
+            for (index, stage) in enrichment.stages.iter().enumerate() {
+                let invalid = |reason: String| {
+                    ValidateManifestError::EnrichmentStageInvalid(
+                        stage.name.clone(),
+                        enrichment.event_name.clone(),
+                        contract.name.clone(),
+                        reason,
+                    )
+                };
+                let earlier_stages = &enrichment.stages[..index];
+
+                if earlier_stages.iter().any(|earlier| earlier.column_name() == stage.column_name())
+                {
+                    return Err(invalid("another stage has the same name".to_string()));
+                }
+
+                if let EnrichmentSource::EthCall { function, args, .. } = &stage.source {
+                    let function = parse_view_function(function).map_err(invalid)?;
+                    if function.inputs.len() != args.len() {
+                        return Err(invalid(format!(
+                            "{} takes {} arguments but {} are given",
+                            function.name,
+                            function.inputs.len(),
+                            args.len()
+                        )));
+                    }
+                }
+
+                let unknown = unknown_stage_fields(stage, &input_names, earlier_stages);
+                if !unknown.is_empty() {
+                    return Err(invalid(format!("unknown fields {}", unknown.join(", "))));
+                }
+            }
+        }
+
+        if contract.encrypted_inputs.is_some() &&
+            manifest.storage.postgres_column_encryption().is_none()
+        {
+            return Err(ValidateManifestError::EncryptedInputsRequireColumnEncryption(
+                contract.name.clone(),
+            ));
+        }
+        if contract.encrypted_inputs.is_some() && contract.archive_raw_logs() {
+            return Err(ValidateManifestError::EncryptedInputsWithRawLogArchive(
+                contract.name.clone(),
+            ));
+        }
+        for encrypted in contract.encrypted_inputs.iter().flatten() {
+            if contract.is_event_write_ahead_logged(&encrypted.event_name) {
+                return Err(ValidateManifestError::EncryptedInputsWithWriteAheadLog(
+                    encrypted.event_name.clone(),
+                    contract.name.clone(),
+                ));
+            }
+            let event =
+                events.iter().find(|e| e.name == encrypted.event_name && e.type_ == "event");
+            for input in &encrypted.inputs {
+                let found = event.is_some_and(|event| {
+                    event.inputs.iter().any(|i| i.name == *input && i.type_ != "tuple")
+                });
+                if !found {
+                    return Err(ValidateManifestError::EncryptedInputNotFoundInABI(
+                        input.clone(),
+                        encrypted.event_name.clone(),
+                        contract.name.clone(),
+                    ));
+                }
+            }
+        }
+
+        for storage in contract.table_storage.iter().flatten() {
+            if !events.iter().any(|e| e.name == storage.event_name && e.type_ == "event") {
+                return Err(ValidateManifestError::TableStorageEventNotFoundInABI(
+                    storage.event_name.clone(),
+                    contract.name.clone(),
+                ));
+            }
+            storage.parameters_sql().map_err(|e| {
+                ValidateManifestError::TableStorageInvalid(
+                    storage.event_name.clone(),
+                    contract.name.clone(),
+                    e,
+                )
+            })?;
+        }
+
         if let Some(_dependency_events) = &contract.dependency_events {
             // TODO - validate the events all exist in the contract ABIs
         }
@@ -189,6 +642,18 @@ fn validate_manifest(
                 return Err(ValidateManifestError::StreamsConfigValidationError(e));
             }
         }
+
+        if let Some(routes) = contract.chat.as_ref().and_then(|c| c.routes.as_ref()) {
+            for route in routes {
+                if !manifest.notification_destinations().iter().any(|d| d.name == route.destination)
+                {
+                    return Err(ValidateManifestError::ChatRouteDestinationNotFound(
+                        contract.name.clone(),
+                        route.destination.clone(),
+                    ));
+                }
+            }
+        }
     }
 
     if let Some(postgres) = &manifest.storage.postgres {
@@ -213,6 +678,214 @@ fn validate_manifest(
         }
     }
 
+    let mut seen_destinations = HashSet::new();
+    for destination in manifest.notification_destinations() {
+        if !seen_destinations.insert(&destination.name) {
+            return Err(ValidateManifestError::NotificationDestinationNameMustBeUnique(
+                destination.name.clone(),
+            ));
+        }
+    }
+
+    if let Some(alerts) = &manifest.alerts {
+        alerts
+            .validate(&manifest.networks, manifest.notification_destinations())
+            .map_err(ValidateManifestError::AlertsConfigValidationError)?;
+    }
+
+    if let Some(labels) = &manifest.labels {
+        if !manifest.storage.postgres_enabled() {
+            return Err(ValidateManifestError::LabelsRequirePostgres);
+        }
+
+        let mut names = HashSet::new();
+        for source in labels {
+            if source.file.is_some() == source.url.is_some() {
+                return Err(ValidateManifestError::LabelSourceMustSetFileOrUrl(source.name.clone()));
+            }
+
+            if !names.insert(&source.name) {
+                return Err(ValidateManifestError::LabelSourceNameMustBeUnique(source.name.clone()));
+            }
+        }
+    }
+
+    if manifest.leader_election.is_some() {
+        if !manifest.storage.postgres_enabled() {
+            return Err(ValidateManifestError::LeaderElectionRequiresPostgres);
+        }
+
+        if manifest.storage.postgres_drop_each_run() {
+            return Err(ValidateManifestError::LeaderElectionWithDropEachRun);
+        }
+    }
+
+    for network in &manifest.networks {
+        for (name, value) in network.rpc_headers.iter().flatten() {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() ||
+                HeaderValue::from_str(value).is_err()
+            {
+                return Err(ValidateManifestError::InvalidRpcHeader(
+                    network.name.clone(),
+                    name.clone(),
+                ));
+            }
+        }
+
+        if let Some(token) = &network.rpc_bearer_token {
+            if HeaderValue::from_str(&format!("Bearer {}", token)).is_err() {
+                return Err(ValidateManifestError::InvalidRpcHeader(
+                    network.name.clone(),
+                    "Authorization".to_string(),
+                ));
+            }
+        }
+    }
+
+    if let Some(modules) = &manifest.modules {
+        for (module_name, details) in modules.enabled() {
+            if !manifest.storage.postgres_enabled() {
+                return Err(ValidateManifestError::ModulesRequirePostgres);
+            }
+
+            for detail in details {
+                if !manifest.networks.iter().any(|n| n.name == detail.network) {
+                    return Err(ValidateManifestError::ModuleNetworkNotFound(
+                        module_name.to_string(),
+                        detail.network.clone(),
+                    ));
+                }
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for view in manifest.storage.postgres_materialized_views() {
+        if !seen.insert(&view.name) {
+            return Err(ValidateManifestError::MaterializedViewNameMustBeUnique(view.name.clone()));
+        }
+
+        match (view.refresh.interval_seconds, view.refresh.every_blocks) {
+            (Some(interval_seconds), None) if interval_seconds > 0 => {}
+            (None, Some(every_blocks)) if every_blocks > 0 => {
+                let network = view.refresh.network.clone().unwrap_or_default();
+                if !manifest.networks.iter().any(|n| n.name == network) {
+                    return Err(ValidateManifestError::MaterializedViewNetworkNotFound(
+                        view.name.clone(),
+                        network,
+                    ));
+                }
+            }
+            _ => {
+                return Err(ValidateManifestError::MaterializedViewInvalidRefresh(view.name.clone()))
+            }
+        }
+    }
+
+    if let Some(channel) = manifest.storage.postgres_notify_channel() {
+        // postgres rejects channel names longer than an identifier
+        if channel.is_empty() || channel.len() > 63 {
+            return Err(ValidateManifestError::NotifyChannelInvalid(channel.to_string()));
+        }
+    }
+
+    let entities = manifest.storage.postgres_entities();
+    if !entities.is_empty() &&
+        (!manifest.storage.postgres_enabled() ||
+            manifest.storage.postgres_disable_create_tables())
+    {
+        return Err(ValidateManifestError::EntitiesRequireEventTables);
+    }
+    let mut seen = HashSet::new();
+    for entity in entities {
+        if !seen.insert(&entity.name) {
+            return Err(ValidateManifestError::EntityNameMustBeUnique(entity.name.clone()));
+        }
+        entity
+            .validate(project_path, &manifest.contracts)
+            .map_err(ValidateManifestError::EntityInvalid)?;
+    }
+
+    let rollups = manifest.storage.postgres_rollups();
+    if !rollups.is_empty() &&
+        (!manifest.storage.postgres_enabled() ||
+            manifest.storage.postgres_disable_create_tables())
+    {
+        return Err(ValidateManifestError::RollupsRequireEventTables);
+    }
+    let mut seen = HashSet::new();
+    for rollup in rollups {
+        if !seen.insert(&rollup.name) {
+            return Err(ValidateManifestError::RollupNameMustBeUnique(rollup.name.clone()));
+        }
+        rollup
+            .validate(project_path, &manifest.contracts)
+            .map_err(ValidateManifestError::RollupInvalid)?;
+    }
+
+    let networks: Vec<String> = manifest.networks.iter().map(|n| n.name.clone()).collect();
+    let mut seen = HashSet::new();
+    for sample in manifest.storage.postgres_state_samples() {
+        if !seen.insert(&sample.name) {
+            return Err(ValidateManifestError::StateSampleNameMustBeUnique(sample.name.clone()));
+        }
+        sample.validate(&networks).map_err(ValidateManifestError::StateSampleInvalid)?;
+    }
+
+    let derived_fields = manifest.graphql_derived_fields();
+    if !derived_fields.is_empty() &&
+        (!manifest.storage.postgres_enabled() ||
+            manifest.storage.postgres_disable_create_tables())
+    {
+        return Err(ValidateManifestError::DerivedFieldsRequireTables);
+    }
+    for field in derived_fields {
+        field
+            .validate(project_path, &manifest.contracts, manifest.storage.postgres_entities())
+            .map_err(ValidateManifestError::DerivedFieldInvalid)?;
+    }
+
+    let roles = manifest.graphql_roles();
+    if !roles.is_empty() &&
+        (!manifest.storage.postgres_enabled() ||
+            manifest.storage.postgres_disable_create_tables())
+    {
+        return Err(ValidateManifestError::GraphQLRolesRequireTables);
+    }
+    let mut seen = HashSet::new();
+    let mut seen_api_keys = HashSet::new();
+    for role in roles {
+        if !seen.insert(&role.name) {
+            return Err(ValidateManifestError::GraphQLRoleNameMustBeUnique(role.name.clone()));
+        }
+        if !role.api_keys.iter().all(|api_key| seen_api_keys.insert(api_key)) {
+            return Err(ValidateManifestError::GraphQLApiKeyMustBeUnique);
+        }
+        role.validate(project_path, &manifest.contracts)
+            .map_err(ValidateManifestError::GraphQLRoleInvalid)?;
+    }
+    if let Some(anonymous_role) =
+        manifest.graphql.as_ref().and_then(|graphql| graphql.anonymous_role.as_ref())
+    {
+        if !roles.iter().any(|role| role.name == *anonymous_role) {
+            return Err(ValidateManifestError::GraphQLAnonymousRoleNotFound(anonymous_role.clone()));
+        }
+    }
+
+    if manifest.admin.as_ref().is_some_and(|admin| admin.api_key.trim().is_empty()) {
+        return Err(ValidateManifestError::AdminApiKeyEmpty);
+    }
+
+    if manifest.backfill_jobs.is_some() {
+        if !manifest.storage.postgres_enabled() {
+            return Err(ValidateManifestError::BackfillJobsRequiresPostgres);
+        }
+
+        if manifest.storage.postgres_drop_each_run() {
+            return Err(ValidateManifestError::BackfillJobsWithDropEachRun);
+        }
+    }
+
     if let Some(global) = &manifest.global {
         if let Some(contracts) = &global.contracts {
             for contract in contracts {
@@ -250,6 +923,12 @@ pub enum ReadManifestError {
 
     #[error("No project path found using parent of manifest path")]
     NoProjectPathFoundUsingParentOfManifestPath,
+
+    #[error("Could not resolve ABI: {0}")]
+    CouldNotResolveAbi(#[from] AbiResolverError),
+
+    #[error("Could not read the address lists of {0}: {1}")]
+    CouldNotReadAddressLists(String, String),
 }
 
 pub fn read_manifest_raw(file_path: &PathBuf) -> Result<Manifest, ReadManifestError> {
@@ -285,6 +964,73 @@ fn extract_environment_path(contents: &str, file_path: &Path) -> Option<PathBuf>
     })
 }
 
+/// What reading a manifest does after parsing it, for manifests built in code
+pub fn prepare_manifest(
+    project_path: &Path,
+    manifest: &mut Manifest,
+) -> Result<(), ReadManifestError> {
+    resolve_missing_abis(project_path, manifest)?;
+    validate_manifest(project_path, manifest)?;
+    resolve_indexed_filter_types(project_path, manifest);
+    resolve_address_lists(project_path, manifest)?;
+    Ok(())
+}
+
+/// Reads the address lists once so the logs are checked against sets
+fn resolve_address_lists(
+    project_path: &Path,
+    manifest: &mut Manifest,
+) -> Result<(), ReadManifestError> {
+    for contract in &mut manifest.contracts {
+        if let Some(spam_filter) = &mut contract.spam_filter {
+            if let Some(deny_list) = &spam_filter.deny_list {
+                let denied = deny_list.read(project_path).map_err(|e| {
+                    ReadManifestError::CouldNotReadAddressLists(contract.name.clone(), e)
+                })?;
+                spam_filter.denied = Arc::new(denied);
+            }
+        }
+        for detail in &mut contract.details {
+            if detail.address_allowlist.is_none() && detail.address_denylist.is_none() {
+                continue;
+            }
+            let filter = AddressFilter::read(
+                project_path,
+                detail.address_allowlist.as_ref(),
+                detail.address_denylist.as_ref(),
+            )
+            .map_err(|e| ReadManifestError::CouldNotReadAddressLists(contract.name.clone(), e))?;
+            detail.address_filter = Some(Arc::new(filter));
+        }
+    }
+    Ok(())
+}
+
+/// Indexed filter values are encoded as the type of the input they filter on, the validation
+/// already checked the ABIs can be read and the events exist
+fn resolve_indexed_filter_types(project_path: &Path, manifest: &mut Manifest) {
+    for contract in &mut manifest.contracts {
+        let Ok(abi_items) = ABIItem::read_abi_items(project_path, contract) else {
+            continue;
+        };
+        for detail in &mut contract.details {
+            for indexed_filter in detail.indexed_filters.iter_mut().flatten() {
+                if let Some(event) = abi_items
+                    .iter()
+                    .find(|item| item.type_ == "event" && item.name == indexed_filter.event_name)
+                {
+                    indexed_filter.indexed_types = event
+                        .inputs
+                        .iter()
+                        .filter(|input| input.indexed.unwrap_or(false))
+                        .map(|input| input.type_.clone())
+                        .collect();
+                }
+            }
+        }
+    }
+}
+
 pub fn read_manifest(file_path: &PathBuf) -> Result<Manifest, ReadManifestError> {
     let mut file = File::open(file_path)?;
     let mut contents = String::new();
@@ -303,19 +1049,33 @@ pub fn read_manifest(file_path: &PathBuf) -> Result<Manifest, ReadManifestError>
     let mut manifest_after_transform: Manifest = serde_yaml::from_str(&contents)?;
 
     // as we don't want to inject the RPC URL in rust projects in clear text we should change
-    // the networks.rpc back to what it was before and the generated code will handle it
+    // the networks.rpc back to what it was before and the generated code will handle it, the
+    // same goes for the rpc credentials
     if manifest_after_transform.project_type == ProjectType::Rust {
         let manifest_networks_only: ManifestNetworksOnly =
             serde_yaml::from_str(&contents_before_transform)?;
         for network in &mut manifest_after_transform.networks {
-            network.rpc = manifest_networks_only
-                .networks
-                .iter()
-                .find(|n| n.name == network.name)
-                .map_or_else(
-                    || replace_env_variable_to_raw_name(&network.rpc),
-                    |n| replace_env_variable_to_raw_name(&n.rpc),
-                );
+            let raw_network =
+                manifest_networks_only.networks.iter().find(|n| n.name == network.name);
+            network.rpc = raw_network.map_or_else(
+                || replace_env_variable_to_raw_name(&network.rpc),
+                |n| replace_env_variable_to_raw_name(&n.rpc),
+            );
+            if let Some(raw_network) = raw_network {
+                network.rpc_headers = raw_network.rpc_headers.as_ref().map(|headers| {
+                    headers
+                        .iter()
+                        .map(|(name, value)| {
+                            (name.clone(), replace_env_variable_to_raw_name(value))
+                        })
+                        .collect()
+                });
+                network.rpc_bearer_token =
+                    raw_network.rpc_bearer_token.as_deref().map(replace_env_variable_to_raw_name);
+                network.fallback_rpcs = raw_network.fallback_rpcs.as_ref().map(|rpcs| {
+                    rpcs.iter().map(|rpc| replace_env_variable_to_raw_name(rpc)).collect()
+                });
+            }
         }
     }
 
@@ -323,7 +1083,7 @@ pub fn read_manifest(file_path: &PathBuf) -> Result<Manifest, ReadManifestError>
     match project_path {
         None => Err(ReadManifestError::NoProjectPathFoundUsingParentOfManifestPath),
         Some(project_path) => {
-            validate_manifest(project_path, &manifest_after_transform)?;
+            prepare_manifest(project_path, &mut manifest_after_transform)?;
             Ok(manifest_after_transform)
         }
     }
@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use ethers::types::{Address, Log, U256};
+use futures::FutureExt;
+
+use crate::{
+    database::postgres::client::{PostgresClient, PostgresError},
+    event::callback_registry::{EventCallbackType, EventResult, HandlerError},
+    manifest::{contract::ContractDetails, modules::Erc20BalancesModule},
+    modules::{IndexerModule, ModuleContext, ModuleEvent},
+    EthereumSqlTypeWrapper,
+};
+
+const EVENTS: &[ModuleEvent] =
+    &[ModuleEvent { name: "Transfer", signature: "Transfer(address,address,uint256)" }];
+
+pub struct Erc20Balances {
+    settings: Erc20BalancesModule,
+}
+
+impl Erc20Balances {
+    pub fn new(settings: Erc20BalancesModule) -> Self {
+        Self { settings }
+    }
+}
+
+/// A signed change to the balance of a holder, in the order the transfers happened
+#[derive(Debug, PartialEq)]
+struct BalanceChange {
+    token: Address,
+    holder: Address,
+    delta: String,
+}
+
+/// The balance changes of an ERC20 transfer, mints and burns only change the other side and a
+/// transfer to yourself changes nothing. ERC721 transfers share the signature but index the token
+/// id as a 4th topic so are ignored.
+fn transfer_balance_changes(log: &Log) -> Vec<BalanceChange> {
+    if log.topics.len() != 3 || log.data.len() < 32 {
+        return vec![];
+    }
+
+    let from = Address::from(log.topics[1]);
+    let to = Address::from(log.topics[2]);
+    let value = U256::from_big_endian(&log.data[..32]);
+    if value.is_zero() || from == to {
+        return vec![];
+    }
+
+    let mut changes = vec![];
+    if !from.is_zero() {
+        changes.push(BalanceChange {
+            token: log.address,
+            holder: from,
+            delta: format!("-{}", value),
+        });
+    }
+    if !to.is_zero() {
+        changes.push(BalanceChange { token: log.address, holder: to, delta: value.to_string() });
+    }
+
+    changes
+}
+
+/// Applies the changes in one statement so a batch is either fully applied or not at all, a
+/// change already recorded is skipped so a batch replayed after a restart is not counted twice
+async fn apply_balance_changes(
+    client: &PostgresClient,
+    schema_name: &str,
+    results: &[EventResult],
+) -> Result<(), PostgresError> {
+    let mut networks = vec![];
+    let mut tokens = vec![];
+    let mut holders = vec![];
+    let mut deltas = vec![];
+    let mut block_numbers = vec![];
+    let mut tx_hashes = vec![];
+    let mut log_indexes = vec![];
+    for result in results {
+        for change in transfer_balance_changes(&result.log) {
+            networks.push(result.tx_information.network.clone());
+            tokens.push(format!("{:?}", change.token));
+            holders.push(format!("{:?}", change.holder));
+            deltas.push(change.delta);
+            block_numbers.push(result.tx_information.block_number.to_string());
+            tx_hashes.push(format!("{:?}", result.tx_information.transaction_hash));
+            log_indexes.push(result.tx_information.log_index.to_string());
+        }
+    }
+
+    if networks.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .execute(
+            &format!(
+                r#"
+                WITH changes AS (
+                    SELECT c.network, c.token, c.holder, c.delta::NUMERIC AS delta,
+                        COALESCE(b.balance, 0) + SUM(c.delta::NUMERIC) OVER (
+                            PARTITION BY c.network, c.token, c.holder ORDER BY c.ordinal
+                        ) AS balance,
+                        c.block_number::NUMERIC AS block_number, c.tx_hash, c.log_index::NUMERIC AS log_index
+                    FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[])
+                        WITH ORDINALITY AS c(network, token, holder, delta, block_number, tx_hash, log_index, ordinal)
+                    LEFT JOIN {schema}.balances b
+                        ON b.network = c.network AND b.token = c.token AND b.holder = c.holder
+                ), recorded AS (
+                    INSERT INTO {schema}.balance_changes (network, token, holder, delta, balance, block_number, tx_hash, log_index)
+                    SELECT network, token, holder, delta, balance, block_number, tx_hash, log_index FROM changes
+                    ON CONFLICT (network, tx_hash, log_index, holder) DO NOTHING
+                    RETURNING network, token, holder, delta, block_number
+                )
+                INSERT INTO {schema}.balances (network, token, holder, balance, last_block_number)
+                SELECT network, token, holder, SUM(delta), MAX(block_number) FROM recorded
+                GROUP BY network, token, holder
+                ON CONFLICT (network, token, holder) DO UPDATE
+                SET balance = balances.balance + EXCLUDED.balance, last_block_number = EXCLUDED.last_block_number
+                "#,
+                schema = schema_name
+            ),
+            &[
+                &EthereumSqlTypeWrapper::VecString(networks),
+                &EthereumSqlTypeWrapper::VecString(tokens),
+                &EthereumSqlTypeWrapper::VecString(holders),
+                &EthereumSqlTypeWrapper::VecString(deltas),
+                &EthereumSqlTypeWrapper::VecString(block_numbers),
+                &EthereumSqlTypeWrapper::VecString(tx_hashes),
+                &EthereumSqlTypeWrapper::VecString(log_indexes),
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+impl IndexerModule for Erc20Balances {
+    fn name(&self) -> &'static str {
+        "erc20_balances"
+    }
+
+    fn details(&self) -> &[ContractDetails] {
+        &self.settings.details
+    }
+
+    fn events(&self) -> &'static [ModuleEvent] {
+        EVENTS
+    }
+
+    /// `balances` holds the current balance, `balance_changes` every change with the balance after
+    /// it so the balance at any block can be queried
+    fn tables_sql(&self, schema_name: &str) -> String {
+        format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {schema}.balances (
+                network VARCHAR(50) NOT NULL,
+                token CHAR(42) NOT NULL,
+                holder CHAR(42) NOT NULL,
+                balance NUMERIC NOT NULL,
+                last_block_number NUMERIC NOT NULL,
+                PRIMARY KEY (network, token, holder)
+            );
+            CREATE TABLE IF NOT EXISTS {schema}.balance_changes (
+                rindexer_id BIGSERIAL PRIMARY KEY,
+                network VARCHAR(50) NOT NULL,
+                token CHAR(42) NOT NULL,
+                holder CHAR(42) NOT NULL,
+                delta NUMERIC NOT NULL,
+                balance NUMERIC NOT NULL,
+                block_number NUMERIC NOT NULL,
+                tx_hash CHAR(66) NOT NULL,
+                log_index NUMERIC NOT NULL,
+                UNIQUE (network, tx_hash, log_index, holder)
+            );
+            CREATE INDEX IF NOT EXISTS balance_changes_holder_idx ON {schema}.balance_changes (network, token, holder, block_number);
+            "#,
+            schema = schema_name
+        )
+    }
+
+    fn callback(&self, _event_name: &str, context: Arc<ModuleContext>) -> EventCallbackType {
+        Arc::new(move |results| {
+            let context = Arc::clone(&context);
+            async move {
+                apply_balance_changes(&context.client, &context.schema_name, &results)
+                    .await
+                    .map_err(|e| HandlerError::Retry(e.to_string()))
+            }
+            .boxed()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Bytes, H256};
+
+    use super::*;
+
+    fn transfer_log(from: Address, to: Address, value: u64) -> Log {
+        let mut data = [0u8; 32];
+        U256::from(value).to_big_endian(&mut data);
+        Log {
+            address: Address::repeat_byte(0xaa),
+            topics: vec![H256::repeat_byte(0x01), H256::from(from), H256::from(to)],
+            data: Bytes::from(data.to_vec()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_transfer_balance_changes() {
+        let alice = Address::repeat_byte(0x01);
+        let bob = Address::repeat_byte(0x02);
+
+        assert_eq!(
+            transfer_balance_changes(&transfer_log(alice, bob, 5)),
+            vec![
+                BalanceChange {
+                    token: Address::repeat_byte(0xaa),
+                    holder: alice,
+                    delta: "-5".to_string()
+                },
+                BalanceChange {
+                    token: Address::repeat_byte(0xaa),
+                    holder: bob,
+                    delta: "5".to_string()
+                },
+            ]
+        );
+
+        // a mint only credits the recipient
+        assert_eq!(transfer_balance_changes(&transfer_log(Address::zero(), bob, 5)).len(), 1);
+        assert!(transfer_balance_changes(&transfer_log(alice, alice, 5)).is_empty());
+
+        // an ERC721 transfer has the token id as the 4th topic
+        let mut nft_transfer = transfer_log(alice, bob, 0);
+        nft_transfer.topics.push(H256::from_low_u64_be(1));
+        assert!(transfer_balance_changes(&nft_transfer).is_empty());
+    }
+}
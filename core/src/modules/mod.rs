@@ -0,0 +1,261 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ethers::{
+    providers::ProviderError,
+    types::{H256, U64},
+    utils::keccak256,
+};
+
+use crate::{
+    database::postgres::{
+        client::{PostgresClient, PostgresConnectionError},
+        generate::{generate_indexer_contract_schema_name, generate_last_synced_table_sql},
+    },
+    event::{
+        callback_registry::{
+            noop_decoder, EventCallbackRegistry, EventCallbackRegistryInformation,
+            EventCallbackType, EventResult,
+        },
+        contract_setup::{ContractInformation, CreateContractInformationError},
+    },
+    generate_random_id,
+    helpers::camel_to_snake,
+    manifest::{
+        contract::{Contract, ContractDetails},
+        core::Manifest,
+    },
+    provider::{CreateNetworkProvider, JsonRpcCachedProvider},
+    types::single_or_array::StringOrArray,
+};
+
+mod erc20_balances;
+mod nft_ownership;
+mod safe;
+mod uniswap;
+
+/// An event a module indexes, the signature gives the topic
+pub struct ModuleEvent {
+    pub name: &'static str,
+    pub signature: &'static str,
+}
+
+impl ModuleEvent {
+    fn topic_id(&self) -> H256 {
+        H256::from(keccak256(self.signature))
+    }
+}
+
+/// What a module callback writes with, shared by every event of the module
+pub struct ModuleContext {
+    pub client: Arc<PostgresClient>,
+    pub schema_name: String,
+    providers: HashMap<String, Arc<JsonRpcCachedProvider>>,
+}
+
+impl ModuleContext {
+    pub fn provider(&self, network: &str) -> Result<&Arc<JsonRpcCachedProvider>, ProviderError> {
+        self.providers.get(network).ok_or_else(|| {
+            ProviderError::CustomError(format!("No provider for network {}", network))
+        })
+    }
+
+    /// The timestamps of the blocks of the results by network and block number, fetched when the
+    /// logs did not come with them
+    pub async fn block_timestamps(
+        &self,
+        results: &[EventResult],
+    ) -> Result<HashMap<(String, U64), u64>, ProviderError> {
+        let mut timestamps = HashMap::new();
+        for result in results {
+            let key = (result.tx_information.network.clone(), result.tx_information.block_number);
+            if timestamps.contains_key(&key) {
+                continue;
+            }
+
+            let timestamp = match result.tx_information.block_timestamp {
+                Some(timestamp) => timestamp.as_u64(),
+                None => {
+                    let provider = self.provider(&key.0)?;
+                    provider
+                        .get_block(key.1)
+                        .await?
+                        .map(|block| block.timestamp.as_u64())
+                        .ok_or_else(|| {
+                            ProviderError::CustomError(format!("Block {} not found", key.1))
+                        })?
+                }
+            };
+            timestamps.insert(key, timestamp);
+        }
+
+        Ok(timestamps)
+    }
+}
+
+/// A built in module, it is indexed like a contract named after the module and owns the tables
+/// in its schema
+trait IndexerModule: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn details(&self) -> &[ContractDetails];
+
+    fn events(&self) -> &'static [ModuleEvent];
+
+    fn tables_sql(&self, schema_name: &str) -> String;
+
+    fn callback(&self, event_name: &str, context: Arc<ModuleContext>) -> EventCallbackType;
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RegisterModulesError {
+    #[error("{0}")]
+    PostgresConnection(#[from] PostgresConnectionError),
+
+    #[error("{0}")]
+    CreateContractInformation(#[from] CreateContractInformationError),
+}
+
+fn enabled_modules(manifest: &Manifest) -> Vec<Box<dyn IndexerModule>> {
+    let mut modules: Vec<Box<dyn IndexerModule>> = vec![];
+    if let Some(settings) = &manifest.modules {
+        if let Some(erc20_balances) = &settings.erc20_balances {
+            modules.push(Box::new(erc20_balances::Erc20Balances::new(erc20_balances.clone())));
+        }
+        if let Some(nft_ownership) = &settings.nft_ownership {
+            modules.push(Box::new(nft_ownership::NftOwnership::new(nft_ownership.clone())));
+        }
+        if let Some(uniswap) = &settings.uniswap {
+            modules.push(Box::new(uniswap::Uniswap::new(uniswap.clone())));
+        }
+        if let Some(safe) = &settings.safe {
+            modules.push(Box::new(safe::Safe::new(safe.clone())));
+        }
+    }
+
+    modules
+}
+
+fn module_schema_name(indexer_name: &str, module_name: &str) -> String {
+    generate_indexer_contract_schema_name(indexer_name, module_name)
+}
+
+/// The schemas of the enabled modules, exposed over GraphQL next to the contract schemas
+pub fn modules_schema_names(manifest: &Manifest) -> Vec<String> {
+    enabled_modules(manifest)
+        .iter()
+        .map(|module| module_schema_name(&manifest.name, module.name()))
+        .collect()
+}
+
+pub fn generate_modules_tables_sql(manifest: &Manifest) -> String {
+    let mut sql = String::new();
+    for module in enabled_modules(manifest) {
+        let schema_name = module_schema_name(&manifest.name, module.name());
+        sql.push_str(&format!("CREATE SCHEMA IF NOT EXISTS {};", schema_name));
+        sql.push_str(&module.tables_sql(&schema_name));
+
+        let networks: Vec<&str> = module.details().iter().map(|d| d.network.as_str()).collect();
+        for event in module.events() {
+            sql.push_str(&generate_last_synced_table_sql(&schema_name, event.name, &networks));
+        }
+    }
+
+    sql
+}
+
+pub fn drop_modules_tables_sql(manifest: &Manifest) -> String {
+    let mut sql = String::new();
+    for module in enabled_modules(manifest) {
+        let schema_name = module_schema_name(&manifest.name, module.name());
+        sql.push_str(&format!("DROP SCHEMA IF EXISTS {} CASCADE;", schema_name));
+        for event in module.events() {
+            sql.push_str(&format!(
+                "DROP TABLE IF EXISTS rindexer_internal.{}_{} CASCADE;",
+                schema_name,
+                camel_to_snake(event.name)
+            ));
+        }
+    }
+
+    sql
+}
+
+/// Modules only ever see blocks past the reorg safe distance so their tables never hold state
+/// from a reorged block
+fn module_contract(module: &dyn IndexerModule) -> Contract {
+    Contract {
+        name: module.name().to_string(),
+        details: module.details().to_vec(),
+        abi: StringOrArray::Multiple(vec![]),
+        include_events: None,
+        index_event_in_order: None,
+        group_events_by_block: None,
+        event_concurrency: None,
+        failure_policy: None,
+        dependency_events: None,
+        retention: None,
+        normalize_decimals: None,
+        enrichment: None,
+        encrypted_inputs: None,
+        table_storage: None,
+        params_storage: None,
+        archive_raw_logs: None,
+        deduplicate_logs: None,
+        write_ahead_log: None,
+        reorg_safe_distance: Some(true),
+        generate_csv: Some(false),
+        streams: None,
+        chat: None,
+        dev: None,
+        table_group: None,
+        spam_filter: None,
+    }
+}
+
+/// Registers the events of the enabled modules, each indexed in order as the module tables build
+/// on the events before
+pub async fn register_modules(
+    manifest: &Manifest,
+    registry: &mut EventCallbackRegistry,
+    network_providers: &[CreateNetworkProvider],
+) -> Result<(), RegisterModulesError> {
+    let modules = enabled_modules(manifest);
+    if modules.is_empty() {
+        return Ok(());
+    }
+
+    let client = Arc::new(PostgresClient::new().await?);
+    let providers: HashMap<String, Arc<JsonRpcCachedProvider>> = network_providers
+        .iter()
+        .map(|provider| (provider.network_name.clone(), Arc::clone(&provider.client)))
+        .collect();
+    for module in modules {
+        let context = Arc::new(ModuleContext {
+            client: Arc::clone(&client),
+            schema_name: module_schema_name(&manifest.name, module.name()),
+            providers: providers.clone(),
+        });
+        let contract = module_contract(module.as_ref());
+        for event in module.events() {
+            registry.register_event(EventCallbackRegistryInformation {
+                id: generate_random_id(10),
+                indexer_name: manifest.name.clone(),
+                topic_id: event.topic_id(),
+                event_name: event.name.to_string(),
+                index_event_in_order: true,
+                group_by_block: false,
+                max_concurrent_callbacks: None,
+                failure_policy: contract.failure_policy(event.name),
+                archive_raw_logs: contract.archive_raw_logs(),
+                contract: ContractInformation::create(
+                    &contract,
+                    network_providers,
+                    noop_decoder(),
+                )?,
+                callback: module.callback(event.name, Arc::clone(&context)),
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,447 @@
+use std::sync::Arc;
+
+use ethers::{
+    abi::{decode, ParamType, Token},
+    types::{Address, Log, U256},
+};
+use futures::FutureExt;
+
+use crate::{
+    database::postgres::client::{PostgresClient, PostgresError},
+    event::callback_registry::{EventCallbackType, EventResult, HandlerError},
+    manifest::{contract::ContractDetails, modules::NftOwnershipModule},
+    modules::{IndexerModule, ModuleContext, ModuleEvent},
+    EthereumSqlTypeWrapper,
+};
+
+const EVENTS: &[ModuleEvent] = &[
+    ModuleEvent { name: "Transfer", signature: "Transfer(address,address,uint256)" },
+    ModuleEvent {
+        name: "TransferSingle",
+        signature: "TransferSingle(address,address,address,uint256,uint256)",
+    },
+    ModuleEvent {
+        name: "TransferBatch",
+        signature: "TransferBatch(address,address,address,uint256[],uint256[])",
+    },
+];
+
+pub struct NftOwnership {
+    settings: NftOwnershipModule,
+}
+
+impl NftOwnership {
+    pub fn new(settings: NftOwnershipModule) -> Self {
+        Self { settings }
+    }
+}
+
+/// The new owner of an ERC721 token, a burned token is owned by the zero address
+#[derive(Debug, PartialEq)]
+struct OwnerChange {
+    collection: Address,
+    token_id: U256,
+    owner: Address,
+}
+
+/// A signed change to the balance an ERC1155 holder has of a token, `batch_index` is the position
+/// in a batch transfer so a token sent twice in one batch is counted twice
+#[derive(Debug, PartialEq)]
+struct BalanceChange {
+    collection: Address,
+    token_id: U256,
+    holder: Address,
+    delta: String,
+    batch_index: usize,
+}
+
+/// ERC20 transfers share the signature but do not index the value so have 3 topics
+fn erc721_owner_change(log: &Log) -> Option<OwnerChange> {
+    if log.topics.len() != 4 {
+        return None;
+    }
+
+    Some(OwnerChange {
+        collection: log.address,
+        token_id: U256::from_big_endian(log.topics[3].as_bytes()),
+        owner: Address::from(log.topics[2]),
+    })
+}
+
+/// The `from` and `to` of an ERC1155 transfer are topics 2 and 3, the ids and values are in the
+/// data as single values for `TransferSingle` and arrays for `TransferBatch`
+fn erc1155_balance_changes(event_name: &str, log: &Log) -> Vec<BalanceChange> {
+    if log.topics.len() != 4 {
+        return vec![];
+    }
+
+    let ids_and_values: Vec<(U256, U256)> = match event_name {
+        "TransferSingle" => {
+            match decode(&[ParamType::Uint(256), ParamType::Uint(256)], &log.data) {
+                Ok(tokens) => match (&tokens[0], &tokens[1]) {
+                    (Token::Uint(id), Token::Uint(value)) => vec![(*id, *value)],
+                    _ => vec![],
+                },
+                Err(_) => vec![],
+            }
+        }
+        "TransferBatch" => {
+            let array = ParamType::Array(Box::new(ParamType::Uint(256)));
+            match decode(&[array.clone(), array], &log.data) {
+                Ok(tokens) => match (&tokens[0], &tokens[1]) {
+                    (Token::Array(ids), Token::Array(values)) => ids
+                        .iter()
+                        .zip(values)
+                        .filter_map(|(id, value)| {
+                            Some((id.clone().into_uint()?, value.clone().into_uint()?))
+                        })
+                        .collect(),
+                    _ => vec![],
+                },
+                Err(_) => vec![],
+            }
+        }
+        _ => vec![],
+    };
+
+    let from = Address::from(log.topics[2]);
+    let to = Address::from(log.topics[3]);
+    if from == to {
+        return vec![];
+    }
+
+    let mut changes = vec![];
+    for (batch_index, (token_id, value)) in ids_and_values.into_iter().enumerate() {
+        if value.is_zero() {
+            continue;
+        }
+        if !from.is_zero() {
+            changes.push(BalanceChange {
+                collection: log.address,
+                token_id,
+                holder: from,
+                delta: format!("-{}", value),
+                batch_index,
+            });
+        }
+        if !to.is_zero() {
+            changes.push(BalanceChange {
+                collection: log.address,
+                token_id,
+                holder: to,
+                delta: value.to_string(),
+                batch_index,
+            });
+        }
+    }
+
+    changes
+}
+
+/// Only the last transfer of a token in the batch is written and only over an older owner, so a
+/// batch replayed after a restart leaves the owners as they are
+async fn apply_owner_changes(
+    client: &PostgresClient,
+    schema_name: &str,
+    results: &[EventResult],
+) -> Result<(), PostgresError> {
+    let mut networks = vec![];
+    let mut collections = vec![];
+    let mut token_ids = vec![];
+    let mut owners = vec![];
+    let mut block_numbers = vec![];
+    let mut log_indexes = vec![];
+    for result in results {
+        if let Some(change) = erc721_owner_change(&result.log) {
+            networks.push(result.tx_information.network.clone());
+            collections.push(format!("{:?}", change.collection));
+            token_ids.push(change.token_id.to_string());
+            owners.push(format!("{:?}", change.owner));
+            block_numbers.push(result.tx_information.block_number.to_string());
+            log_indexes.push(result.tx_information.log_index.to_string());
+        }
+    }
+
+    if networks.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .execute(
+            &format!(
+                r#"
+                INSERT INTO {schema}.erc721_owners (network, collection, token_id, owner, block_number, log_index)
+                SELECT DISTINCT ON (network, collection, token_id)
+                    network, collection, token_id::NUMERIC, owner, block_number::NUMERIC, log_index::NUMERIC
+                FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[])
+                    AS c(network, collection, token_id, owner, block_number, log_index)
+                ORDER BY network, collection, token_id, block_number::NUMERIC DESC, log_index::NUMERIC DESC
+                ON CONFLICT (network, collection, token_id) DO UPDATE
+                SET owner = EXCLUDED.owner, block_number = EXCLUDED.block_number, log_index = EXCLUDED.log_index
+                WHERE (erc721_owners.block_number, erc721_owners.log_index) < (EXCLUDED.block_number, EXCLUDED.log_index)
+                "#,
+                schema = schema_name
+            ),
+            &[
+                &EthereumSqlTypeWrapper::VecString(networks),
+                &EthereumSqlTypeWrapper::VecString(collections),
+                &EthereumSqlTypeWrapper::VecString(token_ids),
+                &EthereumSqlTypeWrapper::VecString(owners),
+                &EthereumSqlTypeWrapper::VecString(block_numbers),
+                &EthereumSqlTypeWrapper::VecString(log_indexes),
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// `TransferSingle` and `TransferBatch` are indexed independently so the balances are built from
+/// changes which can be applied in any order, a change already recorded is skipped
+async fn apply_balance_changes(
+    client: &PostgresClient,
+    schema_name: &str,
+    event_name: &str,
+    results: &[EventResult],
+) -> Result<(), PostgresError> {
+    let mut networks = vec![];
+    let mut collections = vec![];
+    let mut token_ids = vec![];
+    let mut holders = vec![];
+    let mut deltas = vec![];
+    let mut block_numbers = vec![];
+    let mut tx_hashes = vec![];
+    let mut log_indexes = vec![];
+    let mut batch_indexes = vec![];
+    for result in results {
+        for change in erc1155_balance_changes(event_name, &result.log) {
+            networks.push(result.tx_information.network.clone());
+            collections.push(format!("{:?}", change.collection));
+            token_ids.push(change.token_id.to_string());
+            holders.push(format!("{:?}", change.holder));
+            deltas.push(change.delta);
+            block_numbers.push(result.tx_information.block_number.to_string());
+            tx_hashes.push(format!("{:?}", result.tx_information.transaction_hash));
+            log_indexes.push(result.tx_information.log_index.to_string());
+            batch_indexes.push(change.batch_index.to_string());
+        }
+    }
+
+    if networks.is_empty() {
+        return Ok(());
+    }
+
+    client
+        .execute(
+            &format!(
+                r#"
+                WITH recorded AS (
+                    INSERT INTO {schema}.erc1155_balance_changes (network, collection, token_id, holder, delta, block_number, tx_hash, log_index, batch_index)
+                    SELECT network, collection, token_id::NUMERIC, holder, delta::NUMERIC, block_number::NUMERIC, tx_hash, log_index::NUMERIC, batch_index::INTEGER
+                    FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::TEXT[], $9::TEXT[])
+                        AS c(network, collection, token_id, holder, delta, block_number, tx_hash, log_index, batch_index)
+                    ON CONFLICT (network, tx_hash, log_index, batch_index, holder) DO NOTHING
+                    RETURNING network, collection, token_id, holder, delta, block_number
+                )
+                INSERT INTO {schema}.erc1155_balances (network, collection, token_id, holder, balance, last_block_number)
+                SELECT network, collection, token_id, holder, SUM(delta), MAX(block_number) FROM recorded
+                GROUP BY network, collection, token_id, holder
+                ON CONFLICT (network, collection, token_id, holder) DO UPDATE
+                SET balance = erc1155_balances.balance + EXCLUDED.balance,
+                    last_block_number = GREATEST(erc1155_balances.last_block_number, EXCLUDED.last_block_number)
+                "#,
+                schema = schema_name
+            ),
+            &[
+                &EthereumSqlTypeWrapper::VecString(networks),
+                &EthereumSqlTypeWrapper::VecString(collections),
+                &EthereumSqlTypeWrapper::VecString(token_ids),
+                &EthereumSqlTypeWrapper::VecString(holders),
+                &EthereumSqlTypeWrapper::VecString(deltas),
+                &EthereumSqlTypeWrapper::VecString(block_numbers),
+                &EthereumSqlTypeWrapper::VecString(tx_hashes),
+                &EthereumSqlTypeWrapper::VecString(log_indexes),
+                &EthereumSqlTypeWrapper::VecString(batch_indexes),
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+impl IndexerModule for NftOwnership {
+    fn name(&self) -> &'static str {
+        "nft_ownership"
+    }
+
+    fn details(&self) -> &[ContractDetails] {
+        &self.settings.details
+    }
+
+    fn events(&self) -> &'static [ModuleEvent] {
+        EVENTS
+    }
+
+    /// `erc721_owners` holds the owner of every token, `erc1155_balances` what every holder has of
+    /// a token. A holder who sent everything keeps a row with a zero balance.
+    fn tables_sql(&self, schema_name: &str) -> String {
+        format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {schema}.erc721_owners (
+                network VARCHAR(50) NOT NULL,
+                collection CHAR(42) NOT NULL,
+                token_id NUMERIC NOT NULL,
+                owner CHAR(42) NOT NULL,
+                block_number NUMERIC NOT NULL,
+                log_index NUMERIC NOT NULL,
+                PRIMARY KEY (network, collection, token_id)
+            );
+            CREATE INDEX IF NOT EXISTS erc721_owners_owner_idx ON {schema}.erc721_owners (network, owner);
+            CREATE TABLE IF NOT EXISTS {schema}.erc1155_balances (
+                network VARCHAR(50) NOT NULL,
+                collection CHAR(42) NOT NULL,
+                token_id NUMERIC NOT NULL,
+                holder CHAR(42) NOT NULL,
+                balance NUMERIC NOT NULL,
+                last_block_number NUMERIC NOT NULL,
+                PRIMARY KEY (network, collection, token_id, holder)
+            );
+            CREATE INDEX IF NOT EXISTS erc1155_balances_holder_idx ON {schema}.erc1155_balances (network, holder);
+            CREATE TABLE IF NOT EXISTS {schema}.erc1155_balance_changes (
+                rindexer_id BIGSERIAL PRIMARY KEY,
+                network VARCHAR(50) NOT NULL,
+                collection CHAR(42) NOT NULL,
+                token_id NUMERIC NOT NULL,
+                holder CHAR(42) NOT NULL,
+                delta NUMERIC NOT NULL,
+                block_number NUMERIC NOT NULL,
+                tx_hash CHAR(66) NOT NULL,
+                log_index NUMERIC NOT NULL,
+                batch_index INTEGER NOT NULL,
+                UNIQUE (network, tx_hash, log_index, batch_index, holder)
+            );
+            "#,
+            schema = schema_name
+        )
+    }
+
+    fn callback(&self, event_name: &str, context: Arc<ModuleContext>) -> EventCallbackType {
+        let event_name = event_name.to_string();
+        Arc::new(move |results| {
+            let context = Arc::clone(&context);
+            let event_name = event_name.clone();
+            async move {
+                let result = if event_name == "Transfer" {
+                    apply_owner_changes(&context.client, &context.schema_name, &results).await
+                } else {
+                    apply_balance_changes(
+                        &context.client,
+                        &context.schema_name,
+                        &event_name,
+                        &results,
+                    )
+                    .await
+                };
+                result.map_err(|e| HandlerError::Retry(e.to_string()))
+            }
+            .boxed()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        abi::encode,
+        types::{Bytes, H256},
+    };
+
+    use super::*;
+
+    fn erc1155_log(from: Address, to: Address, data: Vec<u8>) -> Log {
+        Log {
+            address: Address::repeat_byte(0xaa),
+            topics: vec![
+                H256::repeat_byte(0x01),
+                H256::repeat_byte(0x02),
+                H256::from(from),
+                H256::from(to),
+            ],
+            data: Bytes::from(data),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_erc721_owner_change() {
+        let bob = Address::repeat_byte(0x02);
+        let mut log = Log {
+            address: Address::repeat_byte(0xaa),
+            topics: vec![
+                H256::repeat_byte(0x01),
+                H256::from(Address::zero()),
+                H256::from(bob),
+                H256::from_low_u64_be(7),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            erc721_owner_change(&log),
+            Some(OwnerChange {
+                collection: Address::repeat_byte(0xaa),
+                token_id: U256::from(7),
+                owner: bob
+            })
+        );
+
+        // an ERC20 transfer has the value in the data
+        log.topics.pop();
+        assert_eq!(erc721_owner_change(&log), None);
+    }
+
+    #[test]
+    fn test_erc1155_balance_changes() {
+        let alice = Address::repeat_byte(0x01);
+        let bob = Address::repeat_byte(0x02);
+
+        let single = erc1155_log(
+            alice,
+            bob,
+            encode(&[Token::Uint(U256::from(3)), Token::Uint(U256::from(10))]),
+        );
+        let changes = erc1155_balance_changes("TransferSingle", &single);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].holder, alice);
+        assert_eq!(changes[0].delta, "-10");
+        assert_eq!(changes[1].token_id, U256::from(3));
+
+        let batch = erc1155_log(
+            Address::zero(),
+            bob,
+            encode(&[
+                Token::Array(vec![Token::Uint(U256::from(1)), Token::Uint(U256::from(2))]),
+                Token::Array(vec![Token::Uint(U256::from(5)), Token::Uint(U256::from(6))]),
+            ]),
+        );
+        assert_eq!(
+            erc1155_balance_changes("TransferBatch", &batch),
+            vec![
+                BalanceChange {
+                    collection: Address::repeat_byte(0xaa),
+                    token_id: U256::from(1),
+                    holder: bob,
+                    delta: "5".to_string(),
+                    batch_index: 0,
+                },
+                BalanceChange {
+                    collection: Address::repeat_byte(0xaa),
+                    token_id: U256::from(2),
+                    holder: bob,
+                    delta: "6".to_string(),
+                    batch_index: 1,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,627 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+};
+
+use ethers::{
+    abi::{decode, encode, ParamType, Token},
+    middleware::Middleware,
+    providers::ProviderError,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, Bytes, Log,
+        Signature, TransactionRequest, H256, U256,
+    },
+    utils::{hash_message, keccak256},
+};
+use futures::FutureExt;
+
+use crate::{
+    event::callback_registry::{EventCallbackType, EventResult, HandlerError},
+    manifest::{contract::ContractDetails, modules::SafeModule},
+    modules::{IndexerModule, ModuleContext, ModuleEvent},
+    provider::TransactionCall,
+    provider_usage::RpcMethod,
+    EthereumSqlTypeWrapper,
+};
+
+const EVENTS: &[ModuleEvent] = &[
+    ModuleEvent { name: "ExecutionSuccess", signature: "ExecutionSuccess(bytes32,uint256)" },
+    ModuleEvent { name: "ExecutionFailure", signature: "ExecutionFailure(bytes32,uint256)" },
+];
+
+/// execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)
+const EXEC_TRANSACTION_SELECTOR: [u8; 4] = [0x6a, 0x76, 0x12, 0x02];
+
+/// multiSend(bytes)
+const MULTI_SEND_SELECTOR: [u8; 4] = [0x8d, 0x80, 0xff, 0x0a];
+
+/// nonce()
+const NONCE_SELECTOR: [u8; 4] = [0xaf, 0xfe, 0xd0, 0xe0];
+
+/// How many nonces past the nonce at the start of the block are tried, one per execution of the
+/// Safe earlier in the block
+const MAX_NONCES_PER_BLOCK: u64 = 16;
+
+pub struct Safe {
+    settings: SafeModule,
+}
+
+impl Safe {
+    pub fn new(settings: SafeModule) -> Self {
+        Self { settings }
+    }
+}
+
+/// A call the Safe made, `operation` 0 is a call and 1 a delegate call
+#[derive(Debug, PartialEq, Clone)]
+struct SafeCall {
+    to: Address,
+    value: U256,
+    data: Vec<u8>,
+    operation: u8,
+}
+
+/// The parameters of `execTransaction`, the Safe transaction hash commits to all of them but the
+/// signatures
+#[derive(Debug, PartialEq)]
+struct ExecTransaction {
+    call: SafeCall,
+    safe_tx_gas: U256,
+    base_gas: U256,
+    gas_price: U256,
+    gas_token: Address,
+    refund_receiver: Address,
+    signatures: Vec<u8>,
+}
+
+struct Execution {
+    safe: Address,
+    safe_tx_hash: H256,
+    payment: U256,
+    success: bool,
+    nonce: Option<U256>,
+    exec_transaction: Option<ExecTransaction>,
+    executor: Option<Address>,
+    owners: Vec<Address>,
+    multi_send_calls: Vec<SafeCall>,
+}
+
+/// Safe 1.3 does not index the hash while later versions do, the topic is the same for both
+fn decode_execution_event(log: &Log) -> Option<(H256, U256)> {
+    if log.topics.len() == 2 {
+        let payment = decode(&[ParamType::Uint(256)], &log.data).ok()?;
+        return Some((log.topics[1], payment[0].clone().into_uint()?));
+    }
+
+    let tokens = decode(&[ParamType::FixedBytes(32), ParamType::Uint(256)], &log.data).ok()?;
+    Some((H256::from_slice(&tokens[0].clone().into_fixed_bytes()?), tokens[1].clone().into_uint()?))
+}
+
+fn decode_exec_transaction(input: &[u8]) -> Option<ExecTransaction> {
+    if input.len() < 4 || input[..4] != EXEC_TRANSACTION_SELECTOR {
+        return None;
+    }
+
+    let tokens = decode(
+        &[
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Bytes,
+            ParamType::Uint(8),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Address,
+            ParamType::Address,
+            ParamType::Bytes,
+        ],
+        &input[4..],
+    )
+    .ok()?;
+    let mut tokens = tokens.into_iter();
+    let mut next = || tokens.next();
+
+    Some(ExecTransaction {
+        call: SafeCall {
+            to: next()?.into_address()?,
+            value: next()?.into_uint()?,
+            data: next()?.into_bytes()?,
+            operation: next()?.into_uint()?.low_u32() as u8,
+        },
+        safe_tx_gas: next()?.into_uint()?,
+        base_gas: next()?.into_uint()?,
+        gas_price: next()?.into_uint()?,
+        gas_token: next()?.into_address()?,
+        refund_receiver: next()?.into_address()?,
+        signatures: next()?.into_bytes()?,
+    })
+}
+
+/// The calls of a MultiSend, packed as operation, to, value, data length and data one after the
+/// other
+fn decode_multi_send(data: &[u8]) -> Vec<SafeCall> {
+    if data.len() < 4 || data[..4] != MULTI_SEND_SELECTOR {
+        return vec![];
+    }
+
+    let packed = match decode(&[ParamType::Bytes], &data[4..])
+        .ok()
+        .and_then(|tokens| tokens.into_iter().next())
+        .and_then(Token::into_bytes)
+    {
+        Some(packed) => packed,
+        None => return vec![],
+    };
+
+    let mut calls = vec![];
+    let mut position = 0;
+    while position + 85 <= packed.len() {
+        let data_length = U256::from_big_endian(&packed[position + 53..position + 85]);
+        let data_start = position + 85;
+        if data_length > U256::from(packed.len() - data_start) {
+            break;
+        }
+        let data_end = data_start + data_length.as_usize();
+
+        calls.push(SafeCall {
+            operation: packed[position],
+            to: Address::from_slice(&packed[position + 1..position + 21]),
+            value: U256::from_big_endian(&packed[position + 21..position + 53]),
+            data: packed[data_start..data_end].to_vec(),
+        });
+        position = data_end;
+    }
+
+    calls
+}
+
+/// The owners who signed, by signature type: 0 is a contract signature and 1 an approved hash
+/// which both carry the owner in `r`, above 30 an `eth_sign` signature and otherwise an EIP712
+/// signature of the Safe transaction hash
+fn recover_owners(safe_tx_hash: H256, signatures: &[u8]) -> Vec<Address> {
+    let mut owners = vec![];
+    // the data of contract signatures follows the signatures
+    let mut end = signatures.len();
+    let mut position = 0;
+    while position + 65 <= end {
+        let chunk = &signatures[position..position + 65];
+        let r = U256::from_big_endian(&chunk[..32]);
+        let s = U256::from_big_endian(&chunk[32..64]);
+        let v = chunk[64];
+
+        let owner = match v {
+            0 => {
+                if s < U256::from(end) {
+                    end = s.as_usize();
+                }
+                Some(Address::from_slice(&chunk[12..32]))
+            }
+            1 => Some(Address::from_slice(&chunk[12..32])),
+            v if v > 30 => Signature { r, s, v: (v - 4) as u64 }
+                .recover(hash_message(safe_tx_hash.as_bytes()))
+                .ok(),
+            v => Signature { r, s, v: v as u64 }.recover(safe_tx_hash).ok(),
+        };
+        owners.extend(owner);
+        position += 65;
+    }
+
+    owners
+}
+
+/// The EIP712 hash Safe owners sign, Safes before 1.3 leave the chain id out of the domain
+fn safe_tx_hash(
+    safe: Address,
+    chain_id: Option<U256>,
+    exec_transaction: &ExecTransaction,
+    nonce: U256,
+) -> H256 {
+    let type_hash = keccak256(
+        "SafeTx(address to,uint256 value,bytes data,uint8 operation,uint256 safeTxGas,uint256 baseGas,uint256 gasPrice,address gasToken,address refundReceiver,uint256 nonce)",
+    );
+    let call = &exec_transaction.call;
+    let struct_hash = keccak256(encode(&[
+        Token::FixedBytes(type_hash.to_vec()),
+        Token::Address(call.to),
+        Token::Uint(call.value),
+        Token::FixedBytes(keccak256(&call.data).to_vec()),
+        Token::Uint(U256::from(call.operation)),
+        Token::Uint(exec_transaction.safe_tx_gas),
+        Token::Uint(exec_transaction.base_gas),
+        Token::Uint(exec_transaction.gas_price),
+        Token::Address(exec_transaction.gas_token),
+        Token::Address(exec_transaction.refund_receiver),
+        Token::Uint(nonce),
+    ]));
+
+    let domain_separator = match chain_id {
+        Some(chain_id) => keccak256(encode(&[
+            Token::FixedBytes(
+                keccak256("EIP712Domain(uint256 chainId,address verifyingContract)").to_vec(),
+            ),
+            Token::Uint(chain_id),
+            Token::Address(safe),
+        ])),
+        None => keccak256(encode(&[
+            Token::FixedBytes(keccak256("EIP712Domain(address verifyingContract)").to_vec()),
+            Token::Address(safe),
+        ])),
+    };
+
+    let mut message = vec![0x19, 0x01];
+    message.extend_from_slice(&domain_separator);
+    message.extend_from_slice(&struct_hash);
+    H256::from(keccak256(message))
+}
+
+fn method_id(data: &[u8]) -> String {
+    if data.len() < 4 {
+        return String::new();
+    }
+
+    format!("0x{}", hex::encode(&data[..4]))
+}
+
+/// The nonce is not in the calldata, it is found by hashing the transaction with the nonces from
+/// the one at the start of the block until the hash matches the event
+async fn resolve_nonce(
+    context: &ModuleContext,
+    network: &str,
+    block_number: u64,
+    safe: Address,
+    event_safe_tx_hash: H256,
+    exec_transaction: &ExecTransaction,
+) -> Result<Option<U256>, ProviderError> {
+    let provider = context.provider(network)?;
+    let call: TypedTransaction =
+        TransactionRequest::new().to(safe).data(Bytes::from(NONCE_SELECTOR.to_vec())).into();
+    provider.usage().record(RpcMethod::Call);
+    let result = provider
+        .get_inner_provider()
+        .call(
+            &call,
+            Some(BlockId::Number(BlockNumber::Number(block_number.saturating_sub(1).into()))),
+        )
+        .await?;
+    if result.len() < 32 {
+        return Ok(None);
+    }
+    let start_nonce = U256::from_big_endian(&result[..32]);
+    let chain_id = provider.get_chain_id().await?;
+
+    for offset in 0..MAX_NONCES_PER_BLOCK {
+        let nonce = start_nonce + offset;
+        for domain_chain_id in [Some(chain_id), None] {
+            if safe_tx_hash(safe, domain_chain_id, exec_transaction, nonce) == event_safe_tx_hash {
+                return Ok(Some(nonce));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// The calldata is only known when the transaction called the Safe directly, executions through
+/// a relayer contract or a module only get the event values
+async fn resolve_executions<'a>(
+    context: &ModuleContext,
+    success: bool,
+    results: &'a [EventResult],
+) -> Result<Vec<(&'a EventResult, Execution)>, ProviderError> {
+    let mut transactions: HashMap<H256, Option<TransactionCall>> = HashMap::new();
+    let mut executions = vec![];
+    for result in results {
+        let Some((event_safe_tx_hash, payment)) = decode_execution_event(&result.log) else {
+            continue;
+        };
+        let network = &result.tx_information.network;
+        let safe = result.log.address;
+
+        let tx_hash = result.tx_information.transaction_hash;
+        if let Entry::Vacant(entry) = transactions.entry(tx_hash) {
+            let provider = context.provider(network)?;
+            // not a typed transaction so deposits from L1 on rollups parse as well
+            entry.insert(provider.get_transaction_call(tx_hash).await?);
+        }
+        let transaction = transactions.get(&tx_hash).and_then(|transaction| transaction.as_ref());
+
+        let exec_transaction = transaction
+            .filter(|transaction| transaction.to == Some(safe))
+            .and_then(|transaction| decode_exec_transaction(&transaction.input));
+        let (nonce, owners, multi_send_calls) = match &exec_transaction {
+            Some(exec_transaction) => (
+                resolve_nonce(
+                    context,
+                    network,
+                    result.tx_information.block_number.as_u64(),
+                    safe,
+                    event_safe_tx_hash,
+                    exec_transaction,
+                )
+                .await?,
+                recover_owners(event_safe_tx_hash, &exec_transaction.signatures),
+                decode_multi_send(&exec_transaction.call.data),
+            ),
+            None => (None, vec![], vec![]),
+        };
+
+        executions.push((
+            result,
+            Execution {
+                safe,
+                safe_tx_hash: event_safe_tx_hash,
+                payment,
+                success,
+                nonce,
+                exec_transaction,
+                executor: transaction.map(|transaction| transaction.from),
+                owners,
+                multi_send_calls,
+            },
+        ));
+    }
+
+    Ok(executions)
+}
+
+/// Every table is keyed on the log so a batch replayed after a restart changes nothing
+fn execution_statements(
+    schema_name: &str,
+    executions: &[(&EventResult, Execution)],
+) -> Vec<(String, Vec<EthereumSqlTypeWrapper>)> {
+    let mut transactions: [Vec<String>; 15] = Default::default();
+    let mut owners: [Vec<String>; 5] = Default::default();
+    let mut calls: [Vec<String>; 9] = Default::default();
+    for (result, execution) in executions {
+        let network = result.tx_information.network.clone();
+        let tx_hash = format!("{:?}", result.tx_information.transaction_hash);
+        let log_index = result.tx_information.log_index.to_string();
+        let safe = format!("{:?}", execution.safe);
+        let safe_tx_hash = format!("{:?}", execution.safe_tx_hash);
+        let call = execution.exec_transaction.as_ref().map(|exec| &exec.call);
+
+        transactions[0].push(network.clone());
+        transactions[1].push(safe.clone());
+        transactions[2].push(safe_tx_hash.clone());
+        transactions[3].push(execution.nonce.map(|nonce| nonce.to_string()).unwrap_or_default());
+        transactions[4].push(execution.success.to_string());
+        transactions[5].push(execution.payment.to_string());
+        transactions[6].push(call.map(|call| format!("{:?}", call.to)).unwrap_or_default());
+        transactions[7].push(call.map(|call| call.value.to_string()).unwrap_or_default());
+        transactions[8]
+            .push(call.map(|call| format!("0x{}", hex::encode(&call.data))).unwrap_or_default());
+        transactions[9].push(call.map(|call| call.operation.to_string()).unwrap_or_default());
+        transactions[10].push(call.map(|call| method_id(&call.data)).unwrap_or_default());
+        transactions[11]
+            .push(execution.executor.map(|executor| format!("{:?}", executor)).unwrap_or_default());
+        transactions[12].push(result.tx_information.block_number.to_string());
+        transactions[13].push(tx_hash.clone());
+        transactions[14].push(log_index.clone());
+
+        for owner in &execution.owners {
+            owners[0].push(network.clone());
+            owners[1].push(safe.clone());
+            owners[2].push(safe_tx_hash.clone());
+            owners[3].push(format!("{:?}", owner));
+            owners[4].push(result.tx_information.block_number.to_string());
+        }
+
+        for (index, call) in execution.multi_send_calls.iter().enumerate() {
+            calls[0].push(network.clone());
+            calls[1].push(safe.clone());
+            calls[2].push(tx_hash.clone());
+            calls[3].push(log_index.clone());
+            calls[4].push(index.to_string());
+            calls[5].push(call.operation.to_string());
+            calls[6].push(format!("{:?}", call.to));
+            calls[7].push(call.value.to_string());
+            calls[8].push(format!("0x{}", hex::encode(&call.data)));
+        }
+    }
+
+    let has_owners = !owners[0].is_empty();
+    let has_calls = !calls[0].is_empty();
+    let mut statements = vec![(
+        format!(
+            r#"
+            INSERT INTO {schema}.transactions (network, safe, safe_tx_hash, nonce, success, payment, to_address, value, data, operation, method, executor, block_number, tx_hash, log_index)
+            SELECT network, safe, safe_tx_hash, NULLIF(nonce, '')::NUMERIC, success::BOOLEAN, payment::NUMERIC,
+                NULLIF(to_address, ''), NULLIF(value, '')::NUMERIC, NULLIF(data, ''), NULLIF(operation, '')::SMALLINT,
+                NULLIF(method, ''), NULLIF(executor, ''), block_number::NUMERIC, tx_hash, log_index::NUMERIC
+            FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::TEXT[], $9::TEXT[], $10::TEXT[], $11::TEXT[], $12::TEXT[], $13::TEXT[], $14::TEXT[], $15::TEXT[])
+                AS t(network, safe, safe_tx_hash, nonce, success, payment, to_address, value, data, operation, method, executor, block_number, tx_hash, log_index)
+            ON CONFLICT (network, tx_hash, log_index) DO NOTHING
+            "#,
+            schema = schema_name
+        ),
+        transactions.into_iter().map(EthereumSqlTypeWrapper::VecString).collect(),
+    )];
+
+    if has_owners {
+        statements.push((
+            format!(
+                r#"
+                INSERT INTO {schema}.confirmations (network, safe, safe_tx_hash, owner, block_number)
+                SELECT network, safe, safe_tx_hash, owner, block_number::NUMERIC
+                FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[])
+                    AS c(network, safe, safe_tx_hash, owner, block_number)
+                ON CONFLICT (network, safe, safe_tx_hash, owner) DO NOTHING
+                "#,
+                schema = schema_name
+            ),
+            owners.into_iter().map(EthereumSqlTypeWrapper::VecString).collect(),
+        ));
+    }
+
+    if has_calls {
+        statements.push((
+            format!(
+                r#"
+                INSERT INTO {schema}.multi_send_calls (network, safe, tx_hash, log_index, call_index, operation, to_address, value, data, method)
+                SELECT network, safe, tx_hash, log_index::NUMERIC, call_index::INTEGER, operation::SMALLINT, to_address,
+                    value::NUMERIC, data, NULLIF(SUBSTRING(data FROM 1 FOR 10), '0x')
+                FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::TEXT[], $9::TEXT[])
+                    AS c(network, safe, tx_hash, log_index, call_index, operation, to_address, value, data)
+                ON CONFLICT (network, tx_hash, log_index, call_index) DO NOTHING
+                "#,
+                schema = schema_name
+            ),
+            calls.into_iter().map(EthereumSqlTypeWrapper::VecString).collect(),
+        ));
+    }
+
+    statements
+}
+
+async fn handle_results(
+    context: &ModuleContext,
+    success: bool,
+    results: &[EventResult],
+) -> Result<(), String> {
+    let executions =
+        resolve_executions(context, success, results).await.map_err(|e| e.to_string())?;
+    if executions.is_empty() {
+        return Ok(());
+    }
+
+    context
+        .client
+        .execute_in_transaction(&execution_statements(&context.schema_name, &executions))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+impl IndexerModule for Safe {
+    fn name(&self) -> &'static str {
+        "safe"
+    }
+
+    fn details(&self) -> &[ContractDetails] {
+        &self.settings.details
+    }
+
+    fn events(&self) -> &'static [ModuleEvent] {
+        EVENTS
+    }
+
+    /// `transactions` holds every execution with the call it made, `confirmations` the owners who
+    /// signed it and `multi_send_calls` the calls of a MultiSend batch. `method` is the selector of
+    /// the call, decode the rest with the ABI of the contract called.
+    fn tables_sql(&self, schema_name: &str) -> String {
+        format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {schema}.transactions (
+                rindexer_id BIGSERIAL PRIMARY KEY,
+                network VARCHAR(50) NOT NULL,
+                safe CHAR(42) NOT NULL,
+                safe_tx_hash CHAR(66) NOT NULL,
+                nonce NUMERIC,
+                success BOOLEAN NOT NULL,
+                payment NUMERIC NOT NULL,
+                to_address CHAR(42),
+                value NUMERIC,
+                data TEXT,
+                operation SMALLINT,
+                method CHAR(10),
+                executor CHAR(42),
+                block_number NUMERIC NOT NULL,
+                tx_hash CHAR(66) NOT NULL,
+                log_index NUMERIC NOT NULL,
+                UNIQUE (network, tx_hash, log_index)
+            );
+            CREATE INDEX IF NOT EXISTS transactions_safe_idx ON {schema}.transactions (network, safe, nonce);
+            CREATE TABLE IF NOT EXISTS {schema}.confirmations (
+                network VARCHAR(50) NOT NULL,
+                safe CHAR(42) NOT NULL,
+                safe_tx_hash CHAR(66) NOT NULL,
+                owner CHAR(42) NOT NULL,
+                block_number NUMERIC NOT NULL,
+                PRIMARY KEY (network, safe, safe_tx_hash, owner)
+            );
+            CREATE INDEX IF NOT EXISTS confirmations_owner_idx ON {schema}.confirmations (network, owner);
+            CREATE TABLE IF NOT EXISTS {schema}.multi_send_calls (
+                network VARCHAR(50) NOT NULL,
+                safe CHAR(42) NOT NULL,
+                tx_hash CHAR(66) NOT NULL,
+                log_index NUMERIC NOT NULL,
+                call_index INTEGER NOT NULL,
+                operation SMALLINT NOT NULL,
+                to_address CHAR(42) NOT NULL,
+                value NUMERIC NOT NULL,
+                data TEXT NOT NULL,
+                method CHAR(10),
+                PRIMARY KEY (network, tx_hash, log_index, call_index)
+            );
+            "#,
+            schema = schema_name
+        )
+    }
+
+    fn callback(&self, event_name: &str, context: Arc<ModuleContext>) -> EventCallbackType {
+        let success = event_name == "ExecutionSuccess";
+        Arc::new(move |results| {
+            let context = Arc::clone(&context);
+            async move {
+                handle_results(&context, success, &results).await.map_err(HandlerError::Retry)
+            }
+            .boxed()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::signers::{LocalWallet, Signer};
+
+    use super::*;
+
+    #[test]
+    fn test_decode_multi_send() {
+        let mut packed = vec![0u8];
+        packed.extend_from_slice(Address::repeat_byte(0x11).as_bytes());
+        packed.extend_from_slice(H256::from_low_u64_be(5).as_bytes());
+        packed.extend_from_slice(H256::from_low_u64_be(2).as_bytes());
+        packed.extend_from_slice(&[0xab, 0xcd]);
+        packed.push(1);
+        packed.extend_from_slice(Address::repeat_byte(0x22).as_bytes());
+        packed.extend_from_slice(H256::zero().as_bytes());
+        packed.extend_from_slice(H256::zero().as_bytes());
+
+        let mut data = MULTI_SEND_SELECTOR.to_vec();
+        data.extend(encode(&[Token::Bytes(packed)]));
+
+        assert_eq!(
+            decode_multi_send(&data),
+            vec![
+                SafeCall {
+                    to: Address::repeat_byte(0x11),
+                    value: U256::from(5),
+                    data: vec![0xab, 0xcd],
+                    operation: 0
+                },
+                SafeCall {
+                    to: Address::repeat_byte(0x22),
+                    value: U256::zero(),
+                    data: vec![],
+                    operation: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recover_owners() {
+        let wallet: LocalWallet =
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80".parse().unwrap();
+        let safe_tx_hash = H256::repeat_byte(0x42);
+
+        let mut signatures = wallet.sign_hash(safe_tx_hash).unwrap().to_vec();
+        // an approved hash of another owner
+        let approver = Address::repeat_byte(0x33);
+        signatures.extend_from_slice(H256::from(approver).as_bytes());
+        signatures.extend_from_slice(H256::zero().as_bytes());
+        signatures.push(1);
+
+        assert_eq!(recover_owners(safe_tx_hash, &signatures), vec![wallet.address(), approver]);
+    }
+}
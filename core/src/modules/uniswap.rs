@@ -0,0 +1,511 @@
+use std::sync::Arc;
+
+use ethers::{
+    abi::{decode, ParamType, Token},
+    types::{Address, Log, I256, U256},
+};
+use futures::FutureExt;
+
+use crate::{
+    event::callback_registry::{EventCallbackType, EventResult, HandlerError},
+    manifest::{contract::ContractDetails, modules::UniswapModule},
+    modules::{IndexerModule, ModuleContext, ModuleEvent},
+    EthereumSqlTypeWrapper,
+};
+
+const EVENTS: &[ModuleEvent] = &[
+    ModuleEvent { name: "PairCreated", signature: "PairCreated(address,address,address,uint256)" },
+    ModuleEvent {
+        name: "PoolCreated",
+        signature: "PoolCreated(address,address,uint24,int24,address)",
+    },
+    ModuleEvent { name: "Sync", signature: "Sync(uint112,uint112)" },
+    ModuleEvent {
+        name: "Swap",
+        signature: "Swap(address,uint256,uint256,uint256,uint256,address)",
+    },
+    ModuleEvent {
+        name: "SwapV3",
+        signature: "Swap(address,address,int256,int256,uint160,uint128,int24)",
+    },
+];
+
+/// 2^192, a V3 price is `sqrtPriceX96^2 / 2^192`
+const Q192: &str = "6277101735386680763835789423207666416102355444464034512896";
+
+pub struct Uniswap {
+    settings: UniswapModule,
+}
+
+impl Uniswap {
+    pub fn new(settings: UniswapModule) -> Self {
+        Self { settings }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Pool {
+    pool: Address,
+    version: u8,
+    token0: Address,
+    token1: Address,
+    fee: Option<u32>,
+}
+
+/// V2 pools have reserves, V3 pools a sqrt price, liquidity and tick
+#[derive(Debug, PartialEq, Default)]
+struct PoolState {
+    pool: Address,
+    reserve0: Option<U256>,
+    reserve1: Option<U256>,
+    sqrt_price_x96: Option<U256>,
+    liquidity: Option<U256>,
+    tick: Option<i32>,
+}
+
+/// The amounts are what the pool received, negative for what it sent
+#[derive(Debug, PartialEq)]
+struct Swap {
+    pool: Address,
+    sender: Address,
+    recipient: Address,
+    amount0: I256,
+    amount1: I256,
+}
+
+fn uint(token: &Token) -> Option<U256> {
+    token.clone().into_uint()
+}
+
+fn int(token: &Token) -> Option<I256> {
+    token.clone().into_int().map(I256::from_raw)
+}
+
+fn decode_pool_created(event_name: &str, log: &Log) -> Option<Pool> {
+    match event_name {
+        "PairCreated" if log.topics.len() == 3 => {
+            let tokens = decode(&[ParamType::Address, ParamType::Uint(256)], &log.data).ok()?;
+            Some(Pool {
+                pool: tokens[0].clone().into_address()?,
+                version: 2,
+                token0: Address::from(log.topics[1]),
+                token1: Address::from(log.topics[2]),
+                fee: None,
+            })
+        }
+        "PoolCreated" if log.topics.len() == 4 => {
+            let tokens = decode(&[ParamType::Int(24), ParamType::Address], &log.data).ok()?;
+            Some(Pool {
+                pool: tokens[1].clone().into_address()?,
+                version: 3,
+                token0: Address::from(log.topics[1]),
+                token1: Address::from(log.topics[2]),
+                fee: Some(U256::from_big_endian(log.topics[3].as_bytes()).low_u32()),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn decode_sync(log: &Log) -> Option<PoolState> {
+    if log.topics.len() != 1 {
+        return None;
+    }
+
+    let tokens = decode(&[ParamType::Uint(112), ParamType::Uint(112)], &log.data).ok()?;
+    Some(PoolState {
+        pool: log.address,
+        reserve0: Some(uint(&tokens[0])?),
+        reserve1: Some(uint(&tokens[1])?),
+        ..Default::default()
+    })
+}
+
+fn decode_swap_v2(log: &Log) -> Option<Swap> {
+    if log.topics.len() != 3 {
+        return None;
+    }
+
+    let tokens = decode(
+        &[ParamType::Uint(256), ParamType::Uint(256), ParamType::Uint(256), ParamType::Uint(256)],
+        &log.data,
+    )
+    .ok()?;
+    let amount = |index: usize| uint(&tokens[index]).map(I256::from_raw);
+    Some(Swap {
+        pool: log.address,
+        sender: Address::from(log.topics[1]),
+        recipient: Address::from(log.topics[2]),
+        amount0: amount(0)? - amount(2)?,
+        amount1: amount(1)? - amount(3)?,
+    })
+}
+
+fn decode_swap_v3(log: &Log) -> Option<(Swap, PoolState)> {
+    if log.topics.len() != 3 {
+        return None;
+    }
+
+    let tokens = decode(
+        &[
+            ParamType::Int(256),
+            ParamType::Int(256),
+            ParamType::Uint(160),
+            ParamType::Uint(128),
+            ParamType::Int(24),
+        ],
+        &log.data,
+    )
+    .ok()?;
+    Some((
+        Swap {
+            pool: log.address,
+            sender: Address::from(log.topics[1]),
+            recipient: Address::from(log.topics[2]),
+            amount0: int(&tokens[0])?,
+            amount1: int(&tokens[1])?,
+        },
+        PoolState {
+            pool: log.address,
+            sqrt_price_x96: Some(uint(&tokens[2])?),
+            liquidity: Some(uint(&tokens[3])?),
+            tick: Some(int(&tokens[4])?.as_i32()),
+            ..Default::default()
+        },
+    ))
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// A pool is only ever created once so a replayed batch changes nothing
+fn pools_statement(
+    schema_name: &str,
+    pools: Vec<(&EventResult, Pool)>,
+) -> (String, Vec<EthereumSqlTypeWrapper>) {
+    let mut columns: [Vec<String>; 7] = Default::default();
+    for (result, pool) in pools {
+        columns[0].push(result.tx_information.network.clone());
+        columns[1].push(format!("{:?}", pool.pool));
+        columns[2].push(pool.version.to_string());
+        columns[3].push(format!("{:?}", pool.token0));
+        columns[4].push(format!("{:?}", pool.token1));
+        columns[5].push(optional(pool.fee));
+        columns[6].push(result.tx_information.block_number.to_string());
+    }
+
+    (
+        format!(
+            r#"
+            INSERT INTO {schema}.pools (network, pool, version, token0, token1, fee, block_number)
+            SELECT network, pool, version::SMALLINT, token0, token1, NULLIF(fee, '')::INTEGER, block_number::NUMERIC
+            FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[])
+                AS p(network, pool, version, token0, token1, fee, block_number)
+            ON CONFLICT (network, pool) DO NOTHING
+            "#,
+            schema = schema_name
+        ),
+        columns.into_iter().map(EthereumSqlTypeWrapper::VecString).collect(),
+    )
+}
+
+/// Only the last state of a pool in the batch is written and only over an older state, so a batch
+/// replayed after a restart leaves the states as they are
+fn pool_states_statement(
+    schema_name: &str,
+    states: Vec<(&EventResult, PoolState)>,
+) -> (String, Vec<EthereumSqlTypeWrapper>) {
+    let mut columns: [Vec<String>; 9] = Default::default();
+    for (result, state) in states {
+        columns[0].push(result.tx_information.network.clone());
+        columns[1].push(format!("{:?}", state.pool));
+        columns[2].push(optional(state.reserve0));
+        columns[3].push(optional(state.reserve1));
+        columns[4].push(optional(state.sqrt_price_x96));
+        columns[5].push(optional(state.liquidity));
+        columns[6].push(optional(state.tick));
+        columns[7].push(result.tx_information.block_number.to_string());
+        columns[8].push(result.tx_information.log_index.to_string());
+    }
+
+    (
+        format!(
+            r#"
+            INSERT INTO {schema}.pool_states (network, pool, reserve0, reserve1, sqrt_price_x96, liquidity, tick, price, block_number, log_index)
+            SELECT DISTINCT ON (network, pool)
+                network, pool, reserve0, reserve1, sqrt_price_x96, liquidity, tick,
+                COALESCE(reserve1 / NULLIF(reserve0, 0), sqrt_price_x96 * sqrt_price_x96 / {q192}),
+                block_number, log_index
+            FROM (
+                SELECT network, pool, NULLIF(reserve0, '')::NUMERIC AS reserve0, NULLIF(reserve1, '')::NUMERIC AS reserve1,
+                    NULLIF(sqrt_price_x96, '')::NUMERIC AS sqrt_price_x96, NULLIF(liquidity, '')::NUMERIC AS liquidity,
+                    NULLIF(tick, '')::INTEGER AS tick, block_number::NUMERIC AS block_number, log_index::NUMERIC AS log_index
+                FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::TEXT[], $9::TEXT[])
+                    AS s(network, pool, reserve0, reserve1, sqrt_price_x96, liquidity, tick, block_number, log_index)
+            ) s
+            ORDER BY network, pool, block_number DESC, log_index DESC
+            ON CONFLICT (network, pool) DO UPDATE
+            SET reserve0 = EXCLUDED.reserve0, reserve1 = EXCLUDED.reserve1, sqrt_price_x96 = EXCLUDED.sqrt_price_x96,
+                liquidity = EXCLUDED.liquidity, tick = EXCLUDED.tick, price = EXCLUDED.price,
+                block_number = EXCLUDED.block_number, log_index = EXCLUDED.log_index
+            WHERE (pool_states.block_number, pool_states.log_index) < (EXCLUDED.block_number, EXCLUDED.log_index)
+            "#,
+            schema = schema_name,
+            q192 = Q192
+        ),
+        columns.into_iter().map(EthereumSqlTypeWrapper::VecString).collect(),
+    )
+}
+
+/// Records the swaps and adds the ones not recorded before to the hourly volume, the volume is the
+/// absolute amounts in the raw token units
+fn swaps_statement(
+    schema_name: &str,
+    swaps: Vec<(&EventResult, Swap, u64)>,
+) -> (String, Vec<EthereumSqlTypeWrapper>) {
+    let mut columns: [Vec<String>; 10] = Default::default();
+    for (result, swap, timestamp) in swaps {
+        columns[0].push(result.tx_information.network.clone());
+        columns[1].push(format!("{:?}", swap.pool));
+        columns[2].push(format!("{:?}", swap.sender));
+        columns[3].push(format!("{:?}", swap.recipient));
+        columns[4].push(swap.amount0.to_string());
+        columns[5].push(swap.amount1.to_string());
+        columns[6].push(result.tx_information.block_number.to_string());
+        columns[7].push(timestamp.to_string());
+        columns[8].push(format!("{:?}", result.tx_information.transaction_hash));
+        columns[9].push(result.tx_information.log_index.to_string());
+    }
+
+    (
+        format!(
+            r#"
+            WITH recorded AS (
+                INSERT INTO {schema}.swaps (network, pool, sender, recipient, amount0, amount1, price, block_number, block_timestamp, tx_hash, log_index)
+                SELECT network, pool, sender, recipient, amount0::NUMERIC, amount1::NUMERIC,
+                    ABS(amount1::NUMERIC) / NULLIF(ABS(amount0::NUMERIC), 0),
+                    block_number::NUMERIC, to_timestamp(block_timestamp::BIGINT), tx_hash, log_index::NUMERIC
+                FROM UNNEST($1::TEXT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::TEXT[], $9::TEXT[], $10::TEXT[])
+                    AS s(network, pool, sender, recipient, amount0, amount1, block_number, block_timestamp, tx_hash, log_index)
+                ON CONFLICT (network, tx_hash, log_index) DO NOTHING
+                RETURNING network, pool, amount0, amount1, block_timestamp
+            )
+            INSERT INTO {schema}.pool_volume_hourly (network, pool, hour, volume0, volume1, swap_count)
+            SELECT network, pool, to_timestamp(FLOOR(EXTRACT(EPOCH FROM block_timestamp) / 3600) * 3600), SUM(ABS(amount0)), SUM(ABS(amount1)), COUNT(*)
+            FROM recorded
+            GROUP BY 1, 2, 3
+            ON CONFLICT (network, pool, hour) DO UPDATE
+            SET volume0 = pool_volume_hourly.volume0 + EXCLUDED.volume0,
+                volume1 = pool_volume_hourly.volume1 + EXCLUDED.volume1,
+                swap_count = pool_volume_hourly.swap_count + EXCLUDED.swap_count
+            "#,
+            schema = schema_name
+        ),
+        columns.into_iter().map(EthereumSqlTypeWrapper::VecString).collect(),
+    )
+}
+
+async fn handle_results(
+    context: &ModuleContext,
+    event_name: &str,
+    results: &[EventResult],
+) -> Result<(), String> {
+    let schema_name = &context.schema_name;
+    let mut statements = vec![];
+    match event_name {
+        "PairCreated" | "PoolCreated" => {
+            let pools: Vec<_> = results
+                .iter()
+                .filter_map(|result| Some((result, decode_pool_created(event_name, &result.log)?)))
+                .collect();
+            if !pools.is_empty() {
+                statements.push(pools_statement(schema_name, pools));
+            }
+        }
+        "Sync" => {
+            let states: Vec<_> = results
+                .iter()
+                .filter_map(|result| Some((result, decode_sync(&result.log)?)))
+                .collect();
+            if !states.is_empty() {
+                statements.push(pool_states_statement(schema_name, states));
+            }
+        }
+        "Swap" | "SwapV3" => {
+            let timestamps = context.block_timestamps(results).await.map_err(|e| e.to_string())?;
+            let mut swaps = vec![];
+            let mut states = vec![];
+            for result in results {
+                let decoded = if event_name == "Swap" {
+                    decode_swap_v2(&result.log).map(|swap| (swap, None))
+                } else {
+                    decode_swap_v3(&result.log).map(|(swap, state)| (swap, Some(state)))
+                };
+                let Some((swap, state)) = decoded else {
+                    continue;
+                };
+
+                let key =
+                    (result.tx_information.network.clone(), result.tx_information.block_number);
+                swaps.push((result, swap, timestamps.get(&key).copied().unwrap_or_default()));
+                if let Some(state) = state {
+                    states.push((result, state));
+                }
+            }
+
+            if !swaps.is_empty() {
+                statements.push(swaps_statement(schema_name, swaps));
+            }
+            if !states.is_empty() {
+                statements.push(pool_states_statement(schema_name, states));
+            }
+        }
+        _ => {}
+    }
+
+    if statements.is_empty() {
+        return Ok(());
+    }
+
+    context.client.execute_in_transaction(&statements).await.map_err(|e| e.to_string())
+}
+
+impl IndexerModule for Uniswap {
+    fn name(&self) -> &'static str {
+        "uniswap"
+    }
+
+    fn details(&self) -> &[ContractDetails] {
+        &self.settings.details
+    }
+
+    fn events(&self) -> &'static [ModuleEvent] {
+        EVENTS
+    }
+
+    /// `pools` holds the tokens of the pools seen being created, `pool_states` the current
+    /// reserves and price of every pool and `swaps` with `pool_volume_hourly` the trading. Prices
+    /// are token1 per token0 in raw units, divide by the token decimals to get the display price.
+    fn tables_sql(&self, schema_name: &str) -> String {
+        format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {schema}.pools (
+                network VARCHAR(50) NOT NULL,
+                pool CHAR(42) NOT NULL,
+                version SMALLINT NOT NULL,
+                token0 CHAR(42) NOT NULL,
+                token1 CHAR(42) NOT NULL,
+                fee INTEGER,
+                block_number NUMERIC NOT NULL,
+                PRIMARY KEY (network, pool)
+            );
+            CREATE TABLE IF NOT EXISTS {schema}.pool_states (
+                network VARCHAR(50) NOT NULL,
+                pool CHAR(42) NOT NULL,
+                reserve0 NUMERIC,
+                reserve1 NUMERIC,
+                sqrt_price_x96 NUMERIC,
+                liquidity NUMERIC,
+                tick INTEGER,
+                price NUMERIC,
+                block_number NUMERIC NOT NULL,
+                log_index NUMERIC NOT NULL,
+                PRIMARY KEY (network, pool)
+            );
+            CREATE TABLE IF NOT EXISTS {schema}.swaps (
+                rindexer_id BIGSERIAL PRIMARY KEY,
+                network VARCHAR(50) NOT NULL,
+                pool CHAR(42) NOT NULL,
+                sender CHAR(42) NOT NULL,
+                recipient CHAR(42) NOT NULL,
+                amount0 NUMERIC NOT NULL,
+                amount1 NUMERIC NOT NULL,
+                price NUMERIC,
+                block_number NUMERIC NOT NULL,
+                block_timestamp TIMESTAMPTZ NOT NULL,
+                tx_hash CHAR(66) NOT NULL,
+                log_index NUMERIC NOT NULL,
+                UNIQUE (network, tx_hash, log_index)
+            );
+            CREATE INDEX IF NOT EXISTS swaps_pool_idx ON {schema}.swaps (network, pool, block_number);
+            CREATE TABLE IF NOT EXISTS {schema}.pool_volume_hourly (
+                network VARCHAR(50) NOT NULL,
+                pool CHAR(42) NOT NULL,
+                hour TIMESTAMPTZ NOT NULL,
+                volume0 NUMERIC NOT NULL,
+                volume1 NUMERIC NOT NULL,
+                swap_count BIGINT NOT NULL,
+                PRIMARY KEY (network, pool, hour)
+            );
+            "#,
+            schema = schema_name
+        )
+    }
+
+    fn callback(&self, event_name: &str, context: Arc<ModuleContext>) -> EventCallbackType {
+        let event_name = event_name.to_string();
+        Arc::new(move |results| {
+            let context = Arc::clone(&context);
+            let event_name = event_name.clone();
+            async move {
+                handle_results(&context, &event_name, &results).await.map_err(HandlerError::Retry)
+            }
+            .boxed()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        abi::encode,
+        types::{Bytes, H256},
+    };
+
+    use super::*;
+
+    fn swap_log(data: Vec<u8>) -> Log {
+        Log {
+            address: Address::repeat_byte(0xaa),
+            topics: vec![
+                H256::repeat_byte(0x01),
+                H256::from(Address::repeat_byte(0x02)),
+                H256::from(Address::repeat_byte(0x03)),
+            ],
+            data: Bytes::from(data),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_decode_swaps() {
+        // 100 token0 in for 250 token1 out
+        let v2 = swap_log(encode(&[
+            Token::Uint(U256::from(100)),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::zero()),
+            Token::Uint(U256::from(250)),
+        ]));
+        let swap = decode_swap_v2(&v2).unwrap();
+        assert_eq!(swap.amount0, I256::from(100));
+        assert_eq!(swap.amount1, I256::from(-250));
+        assert_eq!(swap.recipient, Address::repeat_byte(0x03));
+
+        let v3 = swap_log(encode(&[
+            Token::Int(I256::from(-40).into_raw()),
+            Token::Int(I256::from(90).into_raw()),
+            Token::Uint(U256::one() << 96),
+            Token::Uint(U256::from(1000)),
+            Token::Int(I256::from(-12).into_raw()),
+        ]));
+        let (swap, state) = decode_swap_v3(&v3).unwrap();
+        assert_eq!(swap.amount0, I256::from(-40));
+        assert_eq!(swap.amount1, I256::from(90));
+        assert_eq!(state.sqrt_price_x96, Some(U256::one() << 96));
+        assert_eq!(state.tick, Some(-12));
+
+        // a V2 swap is not a V3 swap
+        assert!(decode_swap_v3(&v2).is_none());
+    }
+}
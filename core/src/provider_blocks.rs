@@ -0,0 +1,198 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ethers::{
+    middleware::Middleware,
+    providers::ProviderError,
+    types::{Block, BlockNumber, H256, U64},
+};
+use tokio::sync::Mutex;
+
+use crate::{provider::JsonRpcCachedProvider, provider_usage::RpcMethod};
+
+/// How often the finalized block is looked up again, about an ethereum slot
+const FINALIZED_REFRESH_INTERVAL: Duration = Duration::from_secs(12);
+
+/// Blocks looked up by the contracts of a network, the provider is shared between them so one
+/// lookup serves every event needing the block's timestamp or hash.
+///
+/// A hash always points at the same block so blocks by hash never go stale. Which block a number
+/// points at can change on a reorg until the number is finalized, so above the finalized block
+/// the number only resolves from the cache for the latest block ttl.
+#[derive(Debug, Default)]
+pub struct BlockCache {
+    state: Mutex<BlockCacheState>,
+}
+
+#[derive(Debug, Default)]
+struct BlockCacheState {
+    // the order blocks were inserted in, the oldest is evicted first
+    by_hash: HashMap<H256, (u64, Arc<Block<H256>>)>,
+    by_number: HashMap<U64, (Instant, H256)>,
+    inserted: u64,
+    finalized: Option<U64>,
+    finalized_checked: Option<Instant>,
+}
+
+impl BlockCacheState {
+    fn is_finalized(&self, number: U64) -> bool {
+        self.finalized.is_some_and(|finalized| number <= finalized)
+    }
+
+    fn get_by_number(&self, number: U64, unfinalized_ttl: Duration) -> Option<Arc<Block<H256>>> {
+        let (inserted, hash) = self.by_number.get(&number)?;
+        if !self.is_finalized(number) && inserted.elapsed() >= unfinalized_ttl {
+            return None;
+        }
+        self.by_hash.get(hash).map(|(_, block)| Arc::clone(block))
+    }
+
+    fn get_by_hash(&self, hash: &H256) -> Option<Arc<Block<H256>>> {
+        self.by_hash.get(hash).map(|(_, block)| Arc::clone(block))
+    }
+
+    /// A block replacing another at its number is a reorg, the replaced one stays by hash
+    fn insert(&mut self, block: Arc<Block<H256>>, max_entries: usize) {
+        let (Some(hash), Some(number)) = (block.hash, block.number) else {
+            return;
+        };
+        if max_entries == 0 {
+            return;
+        }
+
+        if self.by_hash.len() >= max_entries {
+            if let Some(oldest) =
+                self.by_hash.iter().min_by_key(|(_, (inserted, _))| *inserted).map(|(k, _)| *k)
+            {
+                self.by_hash.remove(&oldest);
+                self.by_number.retain(|_, (_, hash)| *hash != oldest);
+            }
+        }
+        self.inserted += 1;
+        self.by_hash.insert(hash, (self.inserted, block));
+        self.by_number.insert(number, (Instant::now(), hash));
+    }
+
+    fn finalized_refresh_due(&self) -> bool {
+        self.finalized_checked.is_none_or(|checked| checked.elapsed() >= FINALIZED_REFRESH_INTERVAL)
+    }
+}
+
+impl JsonRpcCachedProvider {
+    async fn fetch_block(&self, number: U64) -> Result<Option<Arc<Block<H256>>>, ProviderError> {
+        self.usage().record(RpcMethod::GetBlockByNumber);
+        let block = self.throttled(self.get_inner_provider().get_block(number)).await?;
+        Ok(block.map(Arc::new))
+    }
+
+    /// Nodes without the `finalized` tag leave nothing finalized, so every number expires
+    async fn refresh_finalized(&self) {
+        if !self.blocks.state.lock().await.finalized_refresh_due() {
+            return;
+        }
+
+        self.usage().record(RpcMethod::GetBlockByNumber);
+        let finalized = self
+            .throttled(self.get_inner_provider().get_block(BlockNumber::Finalized))
+            .await
+            .ok()
+            .flatten()
+            .and_then(|block| block.number);
+
+        let mut state = self.blocks.state.lock().await;
+        state.finalized_checked = Some(Instant::now());
+        if finalized.is_some() {
+            state.finalized = state.finalized.max(finalized);
+        }
+    }
+
+    async fn cache_block(&self, block: &Arc<Block<H256>>) {
+        let max_entries = self.cache_policy().max_entries;
+        let finalized = {
+            let mut state = self.blocks.state.lock().await;
+            state.insert(Arc::clone(block), max_entries);
+            block.number.is_none_or(|number| state.is_finalized(number))
+        };
+        if !finalized {
+            self.refresh_finalized().await;
+        }
+    }
+
+    /// `eth_getBlockByNumber` through the network's block cache
+    pub async fn get_block(&self, number: U64) -> Result<Option<Arc<Block<H256>>>, ProviderError> {
+        let ttl = self.cache_policy().latest_block_ttl;
+        if let Some(block) = self.blocks.state.lock().await.get_by_number(number, ttl) {
+            return Ok(Some(block));
+        }
+
+        let block = self.fetch_block(number).await?;
+        if let Some(block) = &block {
+            self.cache_block(block).await;
+        }
+        Ok(block)
+    }
+
+    /// `eth_getBlockByHash` through the network's block cache
+    pub async fn get_block_by_hash(
+        &self,
+        hash: H256,
+    ) -> Result<Option<Arc<Block<H256>>>, ProviderError> {
+        if let Some(block) = self.blocks.state.lock().await.get_by_hash(&hash) {
+            return Ok(Some(block));
+        }
+
+        self.usage().record(RpcMethod::GetBlockByHash);
+        let block = self.throttled(self.get_inner_provider().get_block(hash)).await?.map(Arc::new);
+        if let Some(block) = &block {
+            self.cache_block(block).await;
+        }
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u64, hash: u8) -> Arc<Block<H256>> {
+        Arc::new(Block {
+            number: Some(U64::from(number)),
+            hash: Some(H256::repeat_byte(hash)),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_numbers_above_finalized_expire_and_reorgs_replace() {
+        let mut state = BlockCacheState::default();
+        state.insert(block(10, 1), 100);
+        state.insert(block(20, 2), 100);
+        state.finalized = Some(U64::from(15));
+
+        // a finalized number resolves however old, an unfinalized one only within the ttl
+        assert!(state.get_by_number(U64::from(10), Duration::ZERO).is_some());
+        assert!(state.get_by_number(U64::from(20), Duration::ZERO).is_none());
+        assert!(state.get_by_number(U64::from(20), Duration::from_secs(60)).is_some());
+
+        state.insert(block(20, 3), 100);
+        let reorged = state.get_by_number(U64::from(20), Duration::from_secs(60)).unwrap();
+        assert_eq!(reorged.hash, Some(H256::repeat_byte(3)));
+        assert!(state.get_by_hash(&H256::repeat_byte(2)).is_some());
+    }
+
+    #[test]
+    fn test_evicts_the_oldest_block_when_full() {
+        let mut state = BlockCacheState::default();
+        state.insert(block(1, 1), 2);
+        state.insert(block(2, 2), 2);
+        state.insert(block(3, 3), 2);
+
+        assert_eq!(state.by_hash.len(), 2);
+        assert!(state.get_by_hash(&H256::repeat_byte(1)).is_none());
+        assert!(!state.by_number.contains_key(&U64::from(1)));
+        assert!(state.get_by_hash(&H256::repeat_byte(3)).is_some());
+    }
+}
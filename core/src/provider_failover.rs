@@ -0,0 +1,231 @@
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use ethers::providers::{JsonRpcClient, RpcError};
+use rand::Rng;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::warn;
+
+use crate::{
+    manifest::network::RpcRetrySettings,
+    provider::{RpcTransport, RpcTransportError},
+    provider_latency::average_rpc_latency,
+};
+
+/// How the log fetching retries a failed request before giving up on the range
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcRetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Bounds fetching the logs of a range so a stalled provider can not hold it forever
+    pub chunk_deadline: Duration,
+}
+
+impl Default for RpcRetryPolicy {
+    fn default() -> Self {
+        RpcRetryPolicy::from(&RpcRetrySettings::default())
+    }
+}
+
+impl From<&RpcRetrySettings> for RpcRetryPolicy {
+    fn from(settings: &RpcRetrySettings) -> Self {
+        RpcRetryPolicy {
+            max_retries: settings.max_retries(),
+            initial_backoff: Duration::from_millis(settings.initial_backoff_ms()),
+            max_backoff: Duration::from_millis(settings.max_backoff_ms()),
+            chunk_deadline: Duration::from_secs(settings.chunk_deadline_seconds()),
+        }
+    }
+}
+
+impl RpcRetryPolicy {
+    /// Exponential backoff with jitter between half and the full delay so every event retrying
+    /// after the same outage does not hit the rpc at the same time
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay =
+            self.initial_backoff.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_backoff);
+        let half = delay / 2;
+        half + delay.saturating_sub(half).mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+/// Opens after `failure_threshold` failures in a row, while open the endpoint is skipped until the
+/// cooldown passes and it gets tried again
+#[derive(Debug)]
+struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: AtomicU32,
+    open_until: Mutex<Option<Instant>>,
+}
+
+impl CircuitBreaker {
+    fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            consecutive_failures: AtomicU32::new(0),
+            open_until: Mutex::new(None),
+        }
+    }
+
+    fn open_until(&self) -> Option<Instant> {
+        let open_until = *self.open_until.lock().expect("circuit breaker lock poisoned");
+        open_until.filter(|until| *until > Instant::now())
+    }
+
+    fn is_open(&self) -> bool {
+        self.open_until().is_some()
+    }
+
+    /// Returns true when this failure opened the breaker
+    fn record_failure(&self) -> bool {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+        if failures < self.failure_threshold {
+            return false;
+        }
+
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+        *self.open_until.lock().expect("circuit breaker lock poisoned") =
+            Some(Instant::now() + self.cooldown);
+        true
+    }
+
+    fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+        *self.open_until.lock().expect("circuit breaker lock poisoned") = None;
+    }
+}
+
+#[derive(Debug)]
+struct FailoverEndpoint {
+    name: String,
+    transport: RpcTransport,
+    breaker: CircuitBreaker,
+}
+
+/// Sends requests to the first endpoint whose circuit breaker is closed, the primary rpc first
+/// and then the fallbacks in the order they are configured unless the fastest goes first
+#[derive(Debug)]
+pub struct FailoverTransport {
+    endpoints: Vec<FailoverEndpoint>,
+    fastest_first: bool,
+}
+
+impl FailoverTransport {
+    pub fn new(endpoints: Vec<(String, RpcTransport)>, settings: &RpcRetrySettings) -> Self {
+        let cooldown = Duration::from_secs(settings.circuit_breaker_cooldown_seconds());
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|(name, transport)| FailoverEndpoint {
+                    name,
+                    transport,
+                    breaker: CircuitBreaker::new(settings.circuit_breaker_failures(), cooldown),
+                })
+                .collect(),
+            fastest_first: settings.fastest_first(),
+        }
+    }
+
+    /// The endpoints to try in order, when every breaker is open the one which opened first is
+    /// tried rather than failing without a request
+    fn candidates(&self) -> Vec<&FailoverEndpoint> {
+        let mut closed: Vec<&FailoverEndpoint> =
+            self.endpoints.iter().filter(|endpoint| !endpoint.breaker.is_open()).collect();
+        if !closed.is_empty() {
+            // an endpoint which was not called yet goes first so every endpoint gets measured
+            if self.fastest_first {
+                closed.sort_by_key(|endpoint| {
+                    average_rpc_latency(&endpoint.name).unwrap_or(Duration::ZERO)
+                });
+            }
+            return closed;
+        }
+
+        self.endpoints
+            .iter()
+            .min_by_key(|endpoint| endpoint.breaker.open_until())
+            .into_iter()
+            .collect()
+    }
+
+    pub async fn request<T, R>(&self, method: &str, params: T) -> Result<R, RpcTransportError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        // serialized once as passing `&params` down to the inner transports would instantiate the
+        // transport request for ever deeper references
+        let params = serde_json::to_value(params).map_err(RpcTransportError::Params)?;
+        let mut last_error = None;
+        for endpoint in self.candidates() {
+            match endpoint.transport.request(method, params.clone()).await {
+                Ok(result) => {
+                    endpoint.breaker.record_success();
+                    return Ok(result);
+                }
+                // the node answered so the endpoint is healthy, the request itself is the problem
+                Err(e) if e.as_error_response().is_some() => {
+                    endpoint.breaker.record_success();
+                    return Err(e);
+                }
+                Err(e) => {
+                    if endpoint.breaker.record_failure() {
+                        warn!(
+                            "RPC endpoint {} removed from rotation for {}s after repeated failures: {}",
+                            endpoint.name,
+                            endpoint.breaker.cooldown.as_secs(),
+                            e
+                        );
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.expect("failover transport always has at least one endpoint"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RpcRetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1000),
+            chunk_deadline: Duration::from_secs(300),
+        };
+
+        for _ in 0..20 {
+            let first = policy.backoff(0);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+
+            let capped = policy.backoff(10);
+            assert!(capped >= Duration::from_millis(500) && capped <= Duration::from_millis(1000));
+        }
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_after_threshold() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        assert!(!breaker.record_failure());
+        assert!(!breaker.is_open());
+        assert!(breaker.record_failure());
+        assert!(breaker.is_open());
+
+        breaker.record_success();
+        assert!(!breaker.is_open());
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use ethers::providers::JsonRpcClient;
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::provider::{RpcTransport, RpcTransportError};
+
+/// Upper bounds of the latency buckets in seconds, slower requests only count towards the total
+const BUCKET_BOUNDS_SECONDS: [f64; 11] =
+    [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// How much the latest request moves the average latency the fastest first routing goes by
+const AVERAGE_WEIGHT: f64 = 0.2;
+
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; BUCKET_BOUNDS_SECONDS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if let Some(bucket) = BUCKET_BOUNDS_SECONDS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// The requests at or under each bound, cumulative the way prometheus expects them
+    fn cumulative_buckets(&self) -> Vec<u64> {
+        self.buckets
+            .iter()
+            .scan(0, |total, bucket| {
+                *total += bucket.load(Ordering::Relaxed);
+                Some(*total)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct EndpointLatency {
+    methods: RwLock<BTreeMap<String, Arc<LatencyHistogram>>>,
+    /// Moving average over the requests of every method, unset until the first request
+    average_micros: AtomicU64,
+}
+
+impl EndpointLatency {
+    fn histogram(&self, method: &str) -> Arc<LatencyHistogram> {
+        if let Some(histogram) =
+            self.methods.read().expect("latency lock poisoned").get(method).cloned()
+        {
+            return histogram;
+        }
+        Arc::clone(
+            self.methods
+                .write()
+                .expect("latency lock poisoned")
+                .entry(method.to_string())
+                .or_default(),
+        )
+    }
+
+    fn record_average(&self, elapsed: Duration) {
+        let micros = elapsed.as_micros() as u64;
+        let _ = self.average_micros.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |average| {
+            Some(if average == 0 {
+                micros
+            } else {
+                (average as f64 * (1.0 - AVERAGE_WEIGHT) + micros as f64 * AVERAGE_WEIGHT) as u64
+            })
+        });
+    }
+}
+
+/// Kept for the life of the process so the histograms cover provider reloads, keyed by endpoint
+/// host
+static ENDPOINT_LATENCIES: Lazy<RwLock<BTreeMap<String, Arc<EndpointLatency>>>> =
+    Lazy::new(|| RwLock::new(BTreeMap::new()));
+
+fn endpoint_latency(endpoint: &str) -> Arc<EndpointLatency> {
+    if let Some(latency) =
+        ENDPOINT_LATENCIES.read().expect("latency lock poisoned").get(endpoint).cloned()
+    {
+        return latency;
+    }
+    Arc::clone(
+        ENDPOINT_LATENCIES
+            .write()
+            .expect("latency lock poisoned")
+            .entry(endpoint.to_string())
+            .or_default(),
+    )
+}
+
+pub fn record_rpc_latency(endpoint: &str, method: &str, elapsed: Duration) {
+    let latency = endpoint_latency(endpoint);
+    latency.histogram(method).record(elapsed);
+    latency.record_average(elapsed);
+}
+
+/// Weighted towards the latest requests, `None` until the endpoint was called
+pub fn average_rpc_latency(endpoint: &str) -> Option<Duration> {
+    let latency =
+        ENDPOINT_LATENCIES.read().expect("latency lock poisoned").get(endpoint).cloned()?;
+    match latency.average_micros.load(Ordering::Relaxed) {
+        0 => None,
+        micros => Some(Duration::from_micros(micros)),
+    }
+}
+
+/// The histograms in the prometheus text format
+pub fn render_rpc_latency_metrics() -> String {
+    let mut metrics = String::from(
+        "# HELP rindexer_rpc_request_duration_seconds How long RPC requests take per endpoint host and method\n\
+         # TYPE rindexer_rpc_request_duration_seconds histogram\n",
+    );
+    for (endpoint, latency) in ENDPOINT_LATENCIES.read().expect("latency lock poisoned").iter() {
+        for (method, histogram) in latency.methods.read().expect("latency lock poisoned").iter() {
+            let labels = format!("endpoint=\"{}\",method=\"{}\"", endpoint, method);
+            for (bound, count) in BUCKET_BOUNDS_SECONDS.iter().zip(histogram.cumulative_buckets()) {
+                let _ = writeln!(
+                    metrics,
+                    "rindexer_rpc_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, count
+                );
+            }
+            let count = histogram.count.load(Ordering::Relaxed);
+            let _ = writeln!(
+                metrics,
+                "rindexer_rpc_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, count
+            );
+            let _ = writeln!(
+                metrics,
+                "rindexer_rpc_request_duration_seconds_sum{{{}}} {}",
+                labels,
+                histogram.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0
+            );
+            let _ = writeln!(
+                metrics,
+                "rindexer_rpc_request_duration_seconds_count{{{}}} {}",
+                labels, count
+            );
+        }
+    }
+    metrics
+}
+
+/// Records how long every request to the endpoint takes, failed ones included
+#[derive(Debug)]
+pub struct TimedTransport {
+    endpoint: String,
+    transport: Box<RpcTransport>,
+}
+
+impl TimedTransport {
+    pub fn new(endpoint: String, transport: RpcTransport) -> Self {
+        Self { endpoint, transport: Box::new(transport) }
+    }
+
+    pub async fn request<T, R>(&self, method: &str, params: T) -> Result<R, RpcTransportError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let started = Instant::now();
+        let result = self.transport.request(method, params).await;
+        record_rpc_latency(&self.endpoint, method, started.elapsed());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cumulative_buckets() {
+        let histogram = LatencyHistogram::default();
+        histogram.record(Duration::from_millis(3));
+        histogram.record(Duration::from_millis(40));
+        histogram.record(Duration::from_secs(20));
+
+        assert_eq!(histogram.cumulative_buckets(), vec![1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2]);
+        assert_eq!(histogram.count.load(Ordering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use ethers::{
+    providers::{JsonRpcClient, JsonRpcError, RpcError},
+    utils::keccak256,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+
+use crate::provider::{RpcTransport, RpcTransportError};
+
+pub const RPC_RECORD_DIR_ENV: &str = "RINDEXER_RPC_RECORD_DIR";
+pub const RPC_REPLAY_DIR_ENV: &str = "RINDEXER_RPC_REPLAY_DIR";
+
+/// Set by `rindexer start --record <dir>` or `--replay <dir>`, env vars so the rust project
+/// process picks it up as well
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcRecordingMode {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl RpcRecordingMode {
+    pub fn from_env() -> Option<Self> {
+        if let Ok(dir) = env::var(RPC_REPLAY_DIR_ENV) {
+            return Some(RpcRecordingMode::Replay(PathBuf::from(dir)));
+        }
+
+        env::var(RPC_RECORD_DIR_ENV).ok().map(|dir| RpcRecordingMode::Record(PathBuf::from(dir)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RecordedResponse {
+    Result(Value),
+    Error(RecordedError),
+}
+
+/// ethers only deserializes a `JsonRpcError` so it is kept on disk as its fields
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedError {
+    code: i64,
+    message: String,
+    #[serde(default)]
+    data: Option<Value>,
+}
+
+impl From<&JsonRpcError> for RecordedError {
+    fn from(error: &JsonRpcError) -> Self {
+        Self { code: error.code, message: error.message.clone(), data: error.data.clone() }
+    }
+}
+
+impl From<RecordedError> for JsonRpcError {
+    fn from(error: RecordedError) -> Self {
+        Self { code: error.code, message: error.message, data: error.data }
+    }
+}
+
+/// One file per distinct request, the responses are kept in the order they came back so requests
+/// like `eth_blockNumber` replay the chain moving forward
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Fixture {
+    method: String,
+    params: Value,
+    responses: Vec<RecordedResponse>,
+}
+
+fn fixture_key(method: &str, params: &Value) -> String {
+    hex::encode(keccak256(format!("{}{}", method, params)))
+}
+
+/// Each rpc gets its own directory so networks sharing a host do not share fixtures, the url is
+/// hashed so no api keys end up on disk
+pub fn fixture_dir(root: &Path, endpoint_name: &str, rpc_url: &str) -> PathBuf {
+    root.join(format!("{}-{}", endpoint_name, &hex::encode(keccak256(rpc_url))[..12]))
+}
+
+fn to_params(params: impl Serialize) -> Result<Value, RpcTransportError> {
+    serde_json::to_value(params).map_err(|e| RpcTransportError::Fixture(e.to_string()))
+}
+
+fn from_result<R: DeserializeOwned>(result: Value) -> Result<R, RpcTransportError> {
+    serde_json::from_value(result).map_err(|e| RpcTransportError::Fixture(e.to_string()))
+}
+
+/// Passes requests through to the real transport and writes every response to the fixture
+/// directory
+#[derive(Debug)]
+pub struct RecordingTransport {
+    inner: Box<RpcTransport>,
+    dir: PathBuf,
+    fixtures: tokio::sync::Mutex<HashMap<String, Fixture>>,
+}
+
+impl RecordingTransport {
+    pub fn new(inner: RpcTransport, dir: PathBuf) -> Result<Self, RpcTransportError> {
+        fs::create_dir_all(&dir).map_err(|e| RpcTransportError::Fixture(e.to_string()))?;
+        info!("Recording RPC responses to {}", dir.display());
+        Ok(Self { inner: Box::new(inner), dir, fixtures: tokio::sync::Mutex::new(HashMap::new()) })
+    }
+
+    pub async fn request<T, R>(&self, method: &str, params: T) -> Result<R, RpcTransportError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = to_params(params)?;
+        let result: Result<Value, RpcTransportError> =
+            self.inner.request(method, params.clone()).await;
+
+        let response = match &result {
+            Ok(value) => RecordedResponse::Result(value.clone()),
+            Err(e) => match e.as_error_response() {
+                Some(error) => RecordedResponse::Error(error.into()),
+                // transport failures are retried so they are not part of the recording
+                None => return Err(result.unwrap_err()),
+            },
+        };
+
+        let key = fixture_key(method, &params);
+        let mut fixtures = self.fixtures.lock().await;
+        let fixture = fixtures.entry(key.clone()).or_insert_with(|| Fixture {
+            method: method.to_string(),
+            params,
+            responses: vec![],
+        });
+        fixture.responses.push(response);
+
+        let contents = serde_json::to_vec_pretty(fixture)
+            .map_err(|e| RpcTransportError::Fixture(e.to_string()))?;
+        tokio::fs::write(self.dir.join(format!("{}.json", key)), contents)
+            .await
+            .map_err(|e| RpcTransportError::Fixture(e.to_string()))?;
+        drop(fixtures);
+
+        from_result(result?)
+    }
+}
+
+#[derive(Debug)]
+struct ReplayFixture {
+    responses: Vec<RecordedResponse>,
+    next: usize,
+}
+
+/// Answers requests from a recording without any network access, once a request runs out of
+/// recorded responses the last one keeps being returned
+#[derive(Debug)]
+pub struct ReplayTransport {
+    dir: PathBuf,
+    fixtures: Mutex<HashMap<String, ReplayFixture>>,
+}
+
+impl ReplayTransport {
+    pub fn new(dir: PathBuf) -> Result<Self, RpcTransportError> {
+        let entries = fs::read_dir(&dir).map_err(|e| {
+            RpcTransportError::Fixture(format!("Could not read {}: {}", dir.display(), e))
+        })?;
+
+        let mut fixtures = HashMap::new();
+        for entry in entries {
+            let path = entry.map_err(|e| RpcTransportError::Fixture(e.to_string()))?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+
+            let contents =
+                fs::read(&path).map_err(|e| RpcTransportError::Fixture(e.to_string()))?;
+            let fixture: Fixture = serde_json::from_slice(&contents).map_err(|e| {
+                RpcTransportError::Fixture(format!("Invalid fixture {}: {}", path.display(), e))
+            })?;
+            fixtures.insert(
+                fixture_key(&fixture.method, &fixture.params),
+                ReplayFixture { responses: fixture.responses, next: 0 },
+            );
+        }
+
+        info!("Replaying {} recorded RPC requests from {}", fixtures.len(), dir.display());
+        Ok(Self { dir, fixtures: Mutex::new(fixtures) })
+    }
+
+    pub async fn request<T, R>(&self, method: &str, params: T) -> Result<R, RpcTransportError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = to_params(params)?;
+        let response = {
+            let mut fixtures = self.fixtures.lock().expect("replay fixtures lock poisoned");
+            let fixture = fixtures.get_mut(&fixture_key(method, &params)).ok_or_else(|| {
+                RpcTransportError::Fixture(format!(
+                    "No recorded response in {} for {} {}",
+                    self.dir.display(),
+                    method,
+                    params
+                ))
+            })?;
+
+            let index = fixture.next.min(fixture.responses.len().saturating_sub(1));
+            fixture.next += 1;
+            fixture.responses.get(index).cloned().ok_or_else(|| {
+                RpcTransportError::Fixture(format!("Empty recording for {} {}", method, params))
+            })?
+        };
+
+        match response {
+            RecordedResponse::Result(result) => from_result(result),
+            RecordedResponse::Error(error) => Err(RpcTransportError::Replayed(error.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replay_returns_responses_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let params = Value::Null;
+        let fixture = Fixture {
+            method: "eth_blockNumber".to_string(),
+            params: params.clone(),
+            responses: vec![
+                RecordedResponse::Result(json!("0x1")),
+                RecordedResponse::Error(RecordedError {
+                    code: -32000,
+                    message: "header not found".to_string(),
+                    data: None,
+                }),
+                RecordedResponse::Result(json!("0x2")),
+            ],
+        };
+        fs::write(
+            dir.path().join(format!("{}.json", fixture_key("eth_blockNumber", &params))),
+            serde_json::to_vec(&fixture).unwrap(),
+        )
+        .unwrap();
+
+        let replay = ReplayTransport::new(dir.path().to_path_buf()).unwrap();
+        let first: String = replay.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(first, "0x1");
+        assert!(matches!(
+            replay.request::<_, String>("eth_blockNumber", ()).await,
+            Err(RpcTransportError::Replayed(_))
+        ));
+        for _ in 0..2 {
+            let last: String = replay.request("eth_blockNumber", ()).await.unwrap();
+            assert_eq!(last, "0x2");
+        }
+
+        assert!(replay.request::<_, String>("eth_chainId", ()).await.is_err());
+    }
+}
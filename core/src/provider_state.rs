@@ -0,0 +1,263 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+use ethers::{
+    abi::{decode, encode, ParamType, Token},
+    middleware::Middleware,
+    providers::ProviderError,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockId, Bytes, TransactionRequest, U64,
+    },
+};
+use futures::future::join_all;
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+use crate::{provider::JsonRpcCachedProvider, provider_usage::RpcMethod};
+
+/// Multicall3 has the same address on every chain it is deployed to
+const MULTICALL3_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+
+/// aggregate3((address,bool,bytes)[])
+const AGGREGATE3_SELECTOR: [u8; 4] = [0x82, 0xad, 0x56, 0xcb];
+
+#[derive(Error, Debug)]
+pub enum StateCallError {
+    #[error("The node no longer has the state of block {0}, historic calls need an archive node")]
+    ArchiveRequired(U64),
+
+    #[error("Provider error: {0}")]
+    Provider(#[from] ProviderError),
+}
+
+/// The contract, calldata and block of a call
+type StateCallKey = (Address, Bytes, U64);
+
+/// Results of calls at a block never change so they are kept until the cache is full, and the
+/// newest block the node was missing the state of so older blocks fail without a request
+#[derive(Debug, Default)]
+pub struct StateCallCache {
+    results: Mutex<HashMap<StateCallKey, (Instant, Bytes)>>,
+    missing_state_through: AtomicU64,
+}
+
+/// Nodes word it differently, geth says `missing trie node` and erigon and reth `state ... not
+/// available` or `pruned`. `header not found` is left out as a lagging node says it for new blocks.
+fn is_missing_state_error(error: &ProviderError) -> bool {
+    let message = error.to_string().to_lowercase();
+    ["missing trie node", "pruned", "historical state", "not available"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+fn aggregate3_call_data(calls: &[(Address, Bytes)]) -> Bytes {
+    let calls = calls
+        .iter()
+        .map(|(to, data)| {
+            Token::Tuple(vec![Token::Address(*to), Token::Bool(true), Token::Bytes(data.to_vec())])
+        })
+        .collect();
+
+    [AGGREGATE3_SELECTOR.to_vec(), encode(&[Token::Array(calls)])].concat().into()
+}
+
+/// The result of each call, `None` for the ones which reverted. Anything else than the expected
+/// results means multicall is not deployed at the block.
+fn decode_aggregate3_result(result: &[u8], calls: usize) -> Option<Vec<Option<Bytes>>> {
+    let tokens = decode(
+        &[ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Bool, ParamType::Bytes])))],
+        result,
+    )
+    .ok()?;
+    let results = tokens.into_iter().next()?.into_array()?;
+    if results.len() != calls {
+        return None;
+    }
+
+    results
+        .into_iter()
+        .map(|result| {
+            let mut fields = result.into_tuple()?.into_iter();
+            let success = fields.next()?.into_bool()?;
+            let data = fields.next()?.into_bytes()?;
+            Some(success.then(|| Bytes::from(data)))
+        })
+        .collect()
+}
+
+impl JsonRpcCachedProvider {
+    async fn uncached_call_at(
+        &self,
+        to: Address,
+        data: Bytes,
+        block: U64,
+    ) -> Result<Bytes, StateCallError> {
+        if block.as_u64() <= self.state_calls.missing_state_through.load(Ordering::SeqCst) {
+            return Err(StateCallError::ArchiveRequired(block));
+        }
+
+        let call: TypedTransaction = TransactionRequest::new().to(to).data(data).into();
+        self.usage().record(RpcMethod::Call);
+        match self
+            .throttled(self.get_inner_provider().call(&call, Some(BlockId::from(block))))
+            .await
+        {
+            Ok(result) => Ok(result),
+            Err(e) if is_missing_state_error(&e) => {
+                self.state_calls.missing_state_through.fetch_max(block.as_u64(), Ordering::SeqCst);
+                Err(StateCallError::ArchiveRequired(block))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn cached_call_result(&self, to: Address, data: &Bytes, block: U64) -> Option<Bytes> {
+        self.state_calls
+            .results
+            .lock()
+            .await
+            .get(&(to, data.clone(), block))
+            .map(|(_, result)| result.clone())
+    }
+
+    async fn cache_call_result(&self, to: Address, data: Bytes, block: U64, result: Bytes) {
+        let max_entries = self.cache_policy().max_entries;
+        if max_entries == 0 {
+            return;
+        }
+
+        let mut cache = self.state_calls.results.lock().await;
+        if cache.len() >= max_entries {
+            if let Some(oldest) =
+                cache.iter().min_by_key(|(_, (inserted, _))| *inserted).map(|(k, _)| k.clone())
+            {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert((to, data, block), (Instant::now(), result));
+    }
+
+    /// `eth_call` as of the end of the block, so a handler can read the state an event left
+    /// behind. Reverts are returned as errors like any other failed call.
+    pub async fn call_at(
+        &self,
+        to: Address,
+        data: Bytes,
+        block: U64,
+    ) -> Result<Bytes, StateCallError> {
+        if let Some(result) = self.cached_call_result(to, &data, block).await {
+            return Ok(result);
+        }
+
+        let result = self.uncached_call_at(to, data.clone(), block).await?;
+        self.cache_call_result(to, data, block, result.clone()).await;
+        Ok(result)
+    }
+
+    /// Many calls as of the end of the block in one request through Multicall3, falling back to
+    /// a request per call where it is not deployed. Calls which revert are `None`.
+    pub async fn call_many_at(
+        &self,
+        calls: Vec<(Address, Bytes)>,
+        block: U64,
+    ) -> Result<Vec<Option<Bytes>>, StateCallError> {
+        let mut results: Vec<Option<Bytes>> = Vec::with_capacity(calls.len());
+        let mut uncached = vec![];
+        for (index, (to, data)) in calls.iter().enumerate() {
+            let cached = self.cached_call_result(*to, data, block).await;
+            if cached.is_none() {
+                uncached.push(index);
+            }
+            results.push(cached);
+        }
+
+        if uncached.is_empty() {
+            return Ok(results);
+        }
+
+        let uncached_calls: Vec<(Address, Bytes)> =
+            uncached.iter().map(|index| calls[*index].clone()).collect();
+        let multicall = match self
+            .uncached_call_at(
+                MULTICALL3_ADDRESS.parse().expect("multicall3 address is valid"),
+                aggregate3_call_data(&uncached_calls),
+                block,
+            )
+            .await
+        {
+            Ok(multicall) => multicall,
+            Err(StateCallError::ArchiveRequired(block)) => {
+                return Err(StateCallError::ArchiveRequired(block))
+            }
+            // falls back to a call each below
+            Err(StateCallError::Provider(_)) => Bytes::new(),
+        };
+
+        let uncached_results = match decode_aggregate3_result(&multicall, uncached_calls.len()) {
+            Some(uncached_results) => uncached_results,
+            None => join_all(
+                uncached_calls
+                    .iter()
+                    .map(|(to, data)| self.uncached_call_at(*to, data.clone(), block)),
+            )
+            .await
+            .into_iter()
+            .map(|result| match result {
+                Ok(result) => Ok(Some(result)),
+                Err(StateCallError::Provider(e)) if e.to_string().contains("revert") => Ok(None),
+                Err(e) => Err(e),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        for (index, result) in uncached.into_iter().zip(uncached_results) {
+            if let Some(result) = &result {
+                let (to, data) = calls[index].clone();
+                self.cache_call_result(to, data, block, result.clone()).await;
+            }
+            results[index] = result;
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate3_round_trip() {
+        let calls = vec![
+            (Address::repeat_byte(0x01), Bytes::from(vec![0x31, 0x3c, 0xe5, 0x67])),
+            (Address::repeat_byte(0x02), Bytes::from(vec![0x18, 0x16, 0x0d, 0xdd])),
+        ];
+        assert_eq!(aggregate3_call_data(&calls)[..4], AGGREGATE3_SELECTOR);
+
+        let result = encode(&[Token::Array(vec![
+            Token::Tuple(vec![Token::Bool(true), Token::Bytes(vec![0x12])]),
+            Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![])]),
+        ])]);
+        assert_eq!(
+            decode_aggregate3_result(&result, 2),
+            Some(vec![Some(Bytes::from(vec![0x12])), None])
+        );
+
+        // nothing deployed at the multicall address returns no data
+        assert_eq!(decode_aggregate3_result(&[], 2), None);
+        assert_eq!(decode_aggregate3_result(&result, 3), None);
+    }
+
+    #[test]
+    fn test_is_missing_state_error() {
+        assert!(is_missing_state_error(&ProviderError::CustomError(
+            "missing trie node 1a2b (path ) state 0x1a2b is not available".to_string()
+        )));
+        assert!(!is_missing_state_error(&ProviderError::CustomError(
+            "execution reverted".to_string()
+        )));
+    }
+}
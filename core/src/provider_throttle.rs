@@ -0,0 +1,159 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use ethers::providers::{HttpClientError, RpcError};
+use reqwest::StatusCode;
+use tracing::{info, warn};
+
+use crate::provider::RpcTransportError;
+
+const MIN_DELAY: Duration = Duration::from_millis(25);
+const MAX_DELAY: Duration = Duration::from_secs(5);
+// successful requests needed before the delay between requests is lowered again
+const RAMP_UP_EVERY: u32 = 20;
+
+fn contains_rate_limit_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("rate limit") ||
+        message.contains("too many requests") ||
+        (message.contains("exceeded") && message.contains("limit"))
+}
+
+/// HTTP 429, the `-32005` limit exceeded error and the messages providers put in the body when
+/// they do not use either
+pub fn is_rate_limit_error(error: &RpcTransportError) -> bool {
+    if let RpcTransportError::Http(http_error) = error {
+        match http_error {
+            HttpClientError::ReqwestError(e)
+                if e.status() == Some(StatusCode::TOO_MANY_REQUESTS) =>
+            {
+                return true
+            }
+            HttpClientError::SerdeJson { text, .. } if contains_rate_limit_message(text) => {
+                return true
+            }
+            _ => {}
+        }
+    }
+
+    match error.as_error_response() {
+        Some(response) => {
+            response.code == 429 ||
+                response.code == -32005 ||
+                contains_rate_limit_message(&response.message)
+        }
+        None => false,
+    }
+}
+
+#[derive(Debug)]
+struct ThrottleState {
+    delay: Duration,
+    next_slot: Instant,
+    successes: u32,
+}
+
+/// Spaces out the requests of a provider once it starts rate limiting, the gap doubles on every
+/// rate limit and shrinks again while requests succeed until the provider is unthrottled
+#[derive(Debug)]
+pub struct AdaptiveThrottle {
+    state: Mutex<ThrottleState>,
+}
+
+impl Default for AdaptiveThrottle {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(ThrottleState {
+                delay: Duration::ZERO,
+                next_slot: Instant::now(),
+                successes: 0,
+            }),
+        }
+    }
+}
+
+impl AdaptiveThrottle {
+    pub fn current_delay(&self) -> Duration {
+        self.state.lock().expect("throttle lock poisoned").delay
+    }
+
+    /// Waits for the next free slot, returns straight away while the provider is not throttled
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut state = self.state.lock().expect("throttle lock poisoned");
+            if state.delay.is_zero() {
+                return;
+            }
+
+            let now = Instant::now();
+            let slot = state.next_slot.max(now);
+            state.next_slot = slot + state.delay;
+            slot - now
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    pub fn record_rate_limited(&self) {
+        let mut state = self.state.lock().expect("throttle lock poisoned");
+        let delay = (state.delay * 2).clamp(MIN_DELAY, MAX_DELAY);
+        if delay != state.delay {
+            warn!("RPC provider is rate limiting - spacing requests {}ms apart", delay.as_millis());
+        }
+        state.delay = delay;
+        state.successes = 0;
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().expect("throttle lock poisoned");
+        if state.delay.is_zero() {
+            return;
+        }
+
+        state.successes += 1;
+        if state.successes < RAMP_UP_EVERY {
+            return;
+        }
+
+        state.successes = 0;
+        state.delay = state.delay.mul_f64(0.75);
+        if state.delay < MIN_DELAY {
+            state.delay = Duration::ZERO;
+            info!("RPC provider stopped rate limiting - requests are no longer throttled");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_backs_off_and_ramps_up() {
+        let throttle = AdaptiveThrottle::default();
+        assert!(throttle.current_delay().is_zero());
+
+        throttle.record_rate_limited();
+        assert_eq!(throttle.current_delay(), MIN_DELAY);
+        throttle.record_rate_limited();
+        assert_eq!(throttle.current_delay(), MIN_DELAY * 2);
+
+        for _ in 0..RAMP_UP_EVERY * 10 {
+            throttle.record_success();
+        }
+        assert!(throttle.current_delay().is_zero());
+    }
+
+    #[test]
+    fn test_rate_limit_messages() {
+        assert!(contains_rate_limit_message(
+            "Your app has exceeded its compute units per second capacity limit"
+        ));
+        assert!(contains_rate_limit_message("Too Many Requests"));
+        assert!(!contains_rate_limit_message("execution reverted"));
+    }
+}
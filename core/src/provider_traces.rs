@@ -0,0 +1,296 @@
+use ethers::{
+    middleware::{Middleware, MiddlewareError},
+    providers::{Provider, ProviderError, RetryClient},
+    types::{Action, Address, BlockNumber, Bytes, Res, Trace, TraceFilter, H256, U256, U64},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+
+use crate::{
+    provider::{JsonRpcCachedProvider, RpcTransport},
+    provider_usage::RpcMethod,
+};
+
+/// Which trace api the node supports, `trace_filter` is preferred as it can filter a whole block
+/// range in one call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceCapability {
+    TraceFilter,
+    DebugTrace,
+    Unsupported,
+}
+
+#[derive(Error, Debug)]
+pub enum TraceError {
+    #[error("The node does not support trace_filter or debug_traceBlockByNumber")]
+    Unsupported,
+
+    #[error("Provider error: {0}")]
+    Provider(#[from] ProviderError),
+}
+
+/// A call or contract creation inside a transaction, the same shape whichever trace api the node
+/// supports
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceCall {
+    pub block_number: U64,
+    pub transaction_hash: Option<H256>,
+    pub transaction_position: Option<usize>,
+    /// The path to the call in the call tree, empty for the top level call
+    pub trace_address: Vec<usize>,
+    /// Lowercase call type, for example `call`, `delegatecall`, `staticcall` or `create`
+    pub call_type: String,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub input: Bytes,
+    pub output: Option<Bytes>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GethCallFrame {
+    #[serde(rename = "type")]
+    call_type: String,
+    from: Address,
+    #[serde(default)]
+    to: Option<Address>,
+    #[serde(default)]
+    value: Option<U256>,
+    #[serde(default)]
+    input: Bytes,
+    #[serde(default)]
+    output: Option<Bytes>,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    calls: Vec<GethCallFrame>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GethTxTrace {
+    #[serde(default)]
+    tx_hash: Option<H256>,
+    result: GethCallFrame,
+}
+
+fn from_parity_trace(trace: Trace) -> Option<TraceCall> {
+    let (call_type, from, to, value, input) = match trace.action {
+        Action::Call(call) => (
+            format!("{:?}", call.call_type).to_lowercase(),
+            call.from,
+            Some(call.to),
+            call.value,
+            call.input,
+        ),
+        Action::Create(create) => {
+            ("create".to_string(), create.from, None, create.value, create.init)
+        }
+        Action::Suicide(_) | Action::Reward(_) => return None,
+    };
+
+    let (to, output) = match trace.result {
+        Some(Res::Call(result)) => (to, Some(result.output)),
+        Some(Res::Create(result)) => (Some(result.address), Some(result.code)),
+        _ => (to, None),
+    };
+
+    Some(TraceCall {
+        block_number: U64::from(trace.block_number),
+        transaction_hash: trace.transaction_hash,
+        transaction_position: trace.transaction_position,
+        trace_address: trace.trace_address,
+        call_type,
+        from,
+        to,
+        value,
+        input,
+        output,
+        error: trace.error,
+    })
+}
+
+/// Flattens the geth call tree depth first which is the same order parity returns the traces in
+fn flatten_call_frame(
+    frame: GethCallFrame,
+    block_number: U64,
+    transaction_hash: Option<H256>,
+    transaction_position: usize,
+    trace_address: Vec<usize>,
+    calls: &mut Vec<TraceCall>,
+) {
+    calls.push(TraceCall {
+        block_number,
+        transaction_hash,
+        transaction_position: Some(transaction_position),
+        trace_address: trace_address.clone(),
+        call_type: frame.call_type.to_lowercase(),
+        from: frame.from,
+        to: frame.to,
+        value: frame.value.unwrap_or_default(),
+        input: frame.input,
+        output: frame.output,
+        error: frame.error,
+    });
+
+    for (index, child) in frame.calls.into_iter().enumerate() {
+        let mut child_address = trace_address.clone();
+        child_address.push(index);
+        flatten_call_frame(
+            child,
+            block_number,
+            transaction_hash,
+            transaction_position,
+            child_address,
+            calls,
+        );
+    }
+}
+
+/// Method not found and friends come back as json rpc error responses, anything else is a
+/// transport problem and the detection should be tried again later
+fn is_unsupported(error: &ProviderError) -> bool {
+    error.as_error_response().is_some()
+}
+
+pub async fn detect_trace_capability(
+    provider: &Provider<RetryClient<RpcTransport>>,
+) -> Result<TraceCapability, ProviderError> {
+    let latest = provider.get_block_number().await?;
+
+    let filter = TraceFilter::default().from_block(latest).to_block(latest);
+    match provider.trace_filter(filter).await {
+        Ok(_) => return Ok(TraceCapability::TraceFilter),
+        Err(e) if !is_unsupported(&e) => return Err(e),
+        Err(_) => {}
+    }
+
+    let result: Result<Vec<GethTxTrace>, ProviderError> = provider
+        .request(
+            "debug_traceBlockByNumber",
+            (BlockNumber::Number(latest), json!({ "tracer": "callTracer" })),
+        )
+        .await;
+    match result {
+        Ok(_) => Ok(TraceCapability::DebugTrace),
+        Err(e) if !is_unsupported(&e) => Err(e),
+        Err(_) => Ok(TraceCapability::Unsupported),
+    }
+}
+
+impl JsonRpcCachedProvider {
+    /// Returns the calls made between the blocks (inclusive), optionally only the calls to the
+    /// given addresses
+    pub async fn get_call_traces(
+        &self,
+        from_block: U64,
+        to_block: U64,
+        to_addresses: &[Address],
+    ) -> Result<Vec<TraceCall>, TraceError> {
+        let provider = self.get_inner_provider();
+
+        match self.trace_capability().await? {
+            TraceCapability::TraceFilter => {
+                let mut filter = TraceFilter::default().from_block(from_block).to_block(to_block);
+                if !to_addresses.is_empty() {
+                    filter = filter.to_address(to_addresses.to_vec());
+                }
+
+                self.usage().record(RpcMethod::TraceFilter);
+                let traces = provider.trace_filter(filter).await?;
+
+                Ok(traces.into_iter().filter_map(from_parity_trace).collect())
+            }
+            TraceCapability::DebugTrace => {
+                let mut calls = vec![];
+                let mut block_number = from_block;
+                while block_number <= to_block {
+                    self.usage().record(RpcMethod::DebugTraceBlockByNumber);
+                    let traces: Vec<GethTxTrace> = provider
+                        .request(
+                            "debug_traceBlockByNumber",
+                            (BlockNumber::Number(block_number), json!({ "tracer": "callTracer" })),
+                        )
+                        .await?;
+
+                    for (position, trace) in traces.into_iter().enumerate() {
+                        flatten_call_frame(
+                            trace.result,
+                            block_number,
+                            trace.tx_hash,
+                            position,
+                            vec![],
+                            &mut calls,
+                        );
+                    }
+
+                    block_number += U64::from(1);
+                }
+
+                if !to_addresses.is_empty() {
+                    calls.retain(|call| call.to.is_some_and(|to| to_addresses.contains(&to)));
+                }
+
+                Ok(calls)
+            }
+            TraceCapability::Unsupported => Err(TraceError::Unsupported),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_call_frame() {
+        let frame: GethCallFrame = serde_json::from_value(json!({
+            "type": "CALL",
+            "from": "0x0000000000000000000000000000000000000001",
+            "to": "0x0000000000000000000000000000000000000002",
+            "value": "0x1",
+            "input": "0x",
+            "calls": [
+                {
+                    "type": "DELEGATECALL",
+                    "from": "0x0000000000000000000000000000000000000002",
+                    "to": "0x0000000000000000000000000000000000000003",
+                    "input": "0x",
+                    "calls": [{
+                        "type": "STATICCALL",
+                        "from": "0x0000000000000000000000000000000000000003",
+                        "to": "0x0000000000000000000000000000000000000004",
+                        "input": "0x"
+                    }]
+                },
+                {
+                    "type": "CREATE",
+                    "from": "0x0000000000000000000000000000000000000002",
+                    "input": "0x",
+                    "error": "out of gas"
+                }
+            ]
+        }))
+        .unwrap();
+
+        let mut calls = vec![];
+        flatten_call_frame(frame, U64::from(10), None, 0, vec![], &mut calls);
+
+        let summary: Vec<(String, Vec<usize>)> =
+            calls.iter().map(|c| (c.call_type.clone(), c.trace_address.clone())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("call".to_string(), vec![]),
+                ("delegatecall".to_string(), vec![0]),
+                ("staticcall".to_string(), vec![0, 0]),
+                ("create".to_string(), vec![1]),
+            ]
+        );
+        assert_eq!(calls[0].value, U256::from(1));
+        assert_eq!(calls[3].error.as_deref(), Some("out of gas"));
+    }
+}
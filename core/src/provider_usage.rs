@@ -0,0 +1,255 @@
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::provider::JsonRpcCachedProvider;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcMethod {
+    GetLogs,
+    GetBlockByNumber,
+    GetBlockByHash,
+    BlockNumber,
+    ChainId,
+    GetTransactionReceipt,
+    TraceFilter,
+    DebugTraceBlockByNumber,
+    Call,
+    GetTransactionByHash,
+}
+
+impl RpcMethod {
+    pub const ALL: [RpcMethod; 10] = [
+        RpcMethod::GetLogs,
+        RpcMethod::GetBlockByNumber,
+        RpcMethod::GetBlockByHash,
+        RpcMethod::BlockNumber,
+        RpcMethod::ChainId,
+        RpcMethod::GetTransactionReceipt,
+        RpcMethod::TraceFilter,
+        RpcMethod::DebugTraceBlockByNumber,
+        RpcMethod::Call,
+        RpcMethod::GetTransactionByHash,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RpcMethod::GetLogs => "eth_getLogs",
+            RpcMethod::GetBlockByNumber => "eth_getBlockByNumber",
+            RpcMethod::GetBlockByHash => "eth_getBlockByHash",
+            RpcMethod::BlockNumber => "eth_blockNumber",
+            RpcMethod::ChainId => "eth_chainId",
+            RpcMethod::GetTransactionReceipt => "eth_getTransactionReceipt",
+            RpcMethod::TraceFilter => "trace_filter",
+            RpcMethod::DebugTraceBlockByNumber => "debug_traceBlockByNumber",
+            RpcMethod::Call => "eth_call",
+            RpcMethod::GetTransactionByHash => "eth_getTransactionByHash",
+        }
+    }
+
+    /// Alchemy compute units per call - https://docs.alchemy.com/reference/compute-unit-costs
+    pub fn compute_units(&self) -> u64 {
+        match self {
+            RpcMethod::GetLogs => 75,
+            RpcMethod::GetBlockByNumber => 16,
+            RpcMethod::GetBlockByHash => 16,
+            RpcMethod::BlockNumber => 10,
+            RpcMethod::ChainId => 0,
+            RpcMethod::GetTransactionReceipt => 15,
+            RpcMethod::TraceFilter => 40,
+            RpcMethod::DebugTraceBlockByNumber => 40,
+            RpcMethod::Call => 26,
+            RpcMethod::GetTransactionByHash => 17,
+        }
+    }
+}
+
+/// Counts the RPC calls a provider made, handlers calling the inner provider directly can record
+/// their own calls so they show up in the report
+#[derive(Debug, Default)]
+pub struct ProviderUsage {
+    counts: [AtomicU64; RpcMethod::ALL.len()],
+}
+
+impl ProviderUsage {
+    pub fn record(&self, method: RpcMethod) {
+        self.counts[method as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self, method: RpcMethod) -> u64 {
+        self.counts[method as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn estimated_compute_units(&self) -> u64 {
+        RpcMethod::ALL.iter().map(|method| self.count(*method) * method.compute_units()).sum()
+    }
+
+    pub fn report(&self, network: &str) -> NetworkUsage {
+        NetworkUsage {
+            network: network.to_string(),
+            methods: RpcMethod::ALL
+                .iter()
+                .filter(|method| self.count(**method) > 0)
+                .map(|method| MethodUsage {
+                    method: method.name().to_string(),
+                    calls: self.count(*method),
+                    compute_units: self.count(*method) * method.compute_units(),
+                })
+                .collect(),
+            estimated_compute_units: self.estimated_compute_units(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MethodUsage {
+    pub method: String,
+    pub calls: u64,
+    pub compute_units: u64,
+}
+
+/// The RPC calls made on a network, only the methods called are listed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NetworkUsage {
+    pub network: String,
+    pub methods: Vec<MethodUsage>,
+    pub estimated_compute_units: u64,
+}
+
+impl NetworkUsage {
+    pub fn summary(&self) -> String {
+        self.methods
+            .iter()
+            .map(|usage| format!("{} {}", usage.method, usage.calls))
+            .collect::<Vec<String>>()
+            .join(" - ")
+    }
+}
+
+type TrackedProviders = Mutex<Vec<(String, Arc<JsonRpcCachedProvider>)>>;
+
+static TRACKED_PROVIDERS: Lazy<TrackedProviders> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Includes the network provider in the usage report
+pub fn track_provider_usage(network: &str, provider: &Arc<JsonRpcCachedProvider>) {
+    let mut tracked = TRACKED_PROVIDERS.lock().unwrap();
+    if !tracked.iter().any(|(name, _)| name == network) {
+        tracked.push((network.to_string(), Arc::clone(provider)));
+    }
+}
+
+/// The RPC calls made so far on every tracked network
+pub fn provider_usage_report() -> Vec<NetworkUsage> {
+    TRACKED_PROVIDERS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(network, provider)| provider.usage().report(network))
+        .collect()
+}
+
+/// Logs the RPC calls made per network and the compute units they are estimated to cost
+pub fn log_provider_usage_report() {
+    let report = provider_usage_report();
+    if report.is_empty() {
+        return;
+    }
+
+    for usage in &report {
+        info!(
+            "RPC usage - network {} - {} - estimated {} compute units",
+            usage.network,
+            usage.summary(),
+            usage.estimated_compute_units
+        );
+    }
+    info!(
+        "RPC usage - estimated {} compute units in total",
+        report.iter().map(|usage| usage.estimated_compute_units).sum::<u64>()
+    );
+}
+
+fn write_provider_usage_metrics(metrics: &mut String, report: &[NetworkUsage]) {
+    metrics.push_str(
+        "# HELP rindexer_rpc_calls_total RPC calls made per network and method\n\
+         # TYPE rindexer_rpc_calls_total counter\n",
+    );
+    for usage in report {
+        for method in &usage.methods {
+            let _ = writeln!(
+                metrics,
+                "rindexer_rpc_calls_total{{network=\"{}\",method=\"{}\"}} {}",
+                usage.network, method.method, method.calls
+            );
+        }
+    }
+
+    metrics.push_str(
+        "# HELP rindexer_rpc_estimated_compute_units_total Alchemy compute units the RPC calls are estimated to cost per network\n\
+         # TYPE rindexer_rpc_estimated_compute_units_total counter\n",
+    );
+    for usage in report {
+        let _ = writeln!(
+            metrics,
+            "rindexer_rpc_estimated_compute_units_total{{network=\"{}\"}} {}",
+            usage.network, usage.estimated_compute_units
+        );
+    }
+}
+
+/// The RPC calls and estimated compute units of every tracked network in the prometheus text
+/// format
+pub fn render_provider_usage_metrics() -> String {
+    let mut metrics = String::new();
+    write_provider_usage_metrics(&mut metrics, &provider_usage_report());
+    metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimated_compute_units() {
+        let usage = ProviderUsage::default();
+        usage.record(RpcMethod::GetLogs);
+        usage.record(RpcMethod::GetLogs);
+        usage.record(RpcMethod::BlockNumber);
+        usage.record(RpcMethod::ChainId);
+
+        assert_eq!(usage.count(RpcMethod::GetLogs), 2);
+        assert_eq!(usage.estimated_compute_units(), 160);
+        assert_eq!(
+            usage.report("ethereum").summary(),
+            "eth_getLogs 2 - eth_blockNumber 1 - eth_chainId 1"
+        );
+    }
+
+    #[test]
+    fn test_provider_usage_metrics() {
+        let usage = ProviderUsage::default();
+        usage.record(RpcMethod::GetLogs);
+        usage.record(RpcMethod::GetTransactionReceipt);
+
+        let mut metrics = String::new();
+        write_provider_usage_metrics(&mut metrics, &[usage.report("base")]);
+
+        assert!(metrics
+            .contains("rindexer_rpc_calls_total{network=\"base\",method=\"eth_getLogs\"} 1\n"));
+        assert!(metrics.contains(
+            "rindexer_rpc_calls_total{network=\"base\",method=\"eth_getTransactionReceipt\"} 1\n"
+        ));
+        assert!(!metrics.contains("eth_chainId"));
+        assert!(
+            metrics.contains("rindexer_rpc_estimated_compute_units_total{network=\"base\"} 90\n")
+        );
+    }
+}
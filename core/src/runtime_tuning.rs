@@ -0,0 +1,227 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use tokio::sync::Semaphore;
+use tracing::{error, info};
+
+use crate::{
+    indexing_permits::IndexingPermits,
+    manifest::{core::Manifest, yaml::read_manifest},
+    provider::{JsonRpcCachedProvider, ProviderCachePolicy},
+    provider_failover::RpcRetryPolicy,
+};
+
+const DEFAULT_INDEXING_CONCURRENCY: usize = 100;
+
+static INDEXING_SEMAPHORE: Lazy<Arc<Semaphore>> =
+    Lazy::new(|| Arc::new(Semaphore::new(DEFAULT_INDEXING_CONCURRENCY)));
+static INDEXING_PERMITS: Lazy<Arc<IndexingPermits>> =
+    Lazy::new(|| Arc::new(IndexingPermits::new(Arc::clone(&INDEXING_SEMAPHORE))));
+static INDEXING_CONCURRENCY: AtomicUsize = AtomicUsize::new(DEFAULT_INDEXING_CONCURRENCY);
+/// Permits a shrink still has to take back as the requests holding them finish
+static INDEXING_PERMIT_DEFICIT: Lazy<Arc<AtomicUsize>> =
+    Lazy::new(|| Arc::new(AtomicUsize::new(0)));
+static LIVE_POLLING_INTERVAL_MS: AtomicU64 = AtomicU64::new(200);
+static LIVE_POLLING_MAX_INTERVAL_MS: AtomicU64 = AtomicU64::new(2000);
+// zero leaves the handler calls unbounded
+static MAX_LOGS_PER_HANDLER_CALL: AtomicUsize = AtomicUsize::new(0);
+
+/// The permits every event shares for RPC log requests, resized when the tuning changes
+pub fn indexing_permits() -> Arc<IndexingPermits> {
+    Arc::clone(&INDEXING_PERMITS)
+}
+
+pub fn live_polling_interval() -> Duration {
+    Duration::from_millis(LIVE_POLLING_INTERVAL_MS.load(Ordering::SeqCst))
+}
+
+pub fn live_polling_max_interval() -> Duration {
+    Duration::from_millis(LIVE_POLLING_MAX_INTERVAL_MS.load(Ordering::SeqCst))
+}
+
+pub fn max_logs_per_handler_call() -> Option<usize> {
+    Some(MAX_LOGS_PER_HANDLER_CALL.load(Ordering::SeqCst)).filter(|max| *max > 0)
+}
+
+/// How long live indexing waits before polling for a new block, doubling while the head has not
+/// moved and dropping back to the polling interval once it does. The bounds are read on every
+/// poll so a reloaded tuning applies straight away.
+#[derive(Debug, Default)]
+pub struct LivePollingBackoff {
+    idle_polls: u32,
+}
+
+impl LivePollingBackoff {
+    pub fn interval(&self) -> Duration {
+        backoff_interval(self.idle_polls, live_polling_interval(), live_polling_max_interval())
+    }
+
+    pub fn new_block(&mut self) {
+        self.idle_polls = 0;
+    }
+
+    pub fn no_new_block(&mut self) {
+        self.idle_polls = self.idle_polls.saturating_add(1);
+    }
+}
+
+fn backoff_interval(idle_polls: u32, min: Duration, max: Duration) -> Duration {
+    min.saturating_mul(2u32.saturating_pow(idle_polls)).min(max.max(min))
+}
+
+/// Takes up to `permits` off the deficit, returns how many it took
+fn pay_off_deficit(deficit: &AtomicUsize, permits: usize) -> usize {
+    let paid = deficit
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |owed| Some(owed.saturating_sub(permits)))
+        .expect("the deficit update always succeeds");
+    paid.min(permits)
+}
+
+/// Adds the permits a deficit does not take back, returns how many were added
+fn grow_permits(semaphore: &Semaphore, deficit: &AtomicUsize, permits: usize) -> usize {
+    let added = permits - pay_off_deficit(deficit, permits);
+    if added > 0 {
+        semaphore.add_permits(added);
+    }
+    added
+}
+
+/// Requests in flight keep their permits so the surplus is taken back one permit at a time as
+/// they finish, a grow in the meantime pays the deficit off rather than feeding the take back
+fn shrink_permits(semaphore: Arc<Semaphore>, deficit: Arc<AtomicUsize>, permits: usize) {
+    deficit.fetch_add(permits, Ordering::SeqCst);
+    tokio::spawn(async move {
+        while deficit.load(Ordering::SeqCst) > 0 {
+            let Ok(permit) = semaphore.acquire().await else {
+                break;
+            };
+            if pay_off_deficit(&deficit, 1) == 0 {
+                break;
+            }
+            permit.forget();
+        }
+    });
+}
+
+fn set_indexing_concurrency(permits: usize) {
+    let permits = permits.max(1);
+    let previous = INDEXING_CONCURRENCY.swap(permits, Ordering::SeqCst);
+
+    if permits > previous {
+        if grow_permits(&INDEXING_SEMAPHORE, &INDEXING_PERMIT_DEFICIT, permits - previous) > 0 {
+            INDEXING_PERMITS.dispatch();
+        }
+    } else if permits < previous {
+        shrink_permits(
+            Arc::clone(&INDEXING_SEMAPHORE),
+            Arc::clone(&INDEXING_PERMIT_DEFICIT),
+            previous - permits,
+        );
+    }
+}
+
+/// Applies the tuning settings and the network max block ranges and cache policies of the
+/// manifest to the running indexer
+pub fn apply_runtime_tuning(
+    manifest: &Manifest,
+    providers: &HashMap<String, Arc<JsonRpcCachedProvider>>,
+) {
+    let tuning = manifest.tuning.clone().unwrap_or_default();
+    set_indexing_concurrency(tuning.indexing_concurrency());
+    INDEXING_PERMITS.set_scheduling(tuning.permit_scheduling());
+    LIVE_POLLING_INTERVAL_MS.store(tuning.live_polling_interval_ms(), Ordering::SeqCst);
+    LIVE_POLLING_MAX_INTERVAL_MS.store(tuning.live_polling_max_interval_ms(), Ordering::SeqCst);
+    MAX_LOGS_PER_HANDLER_CALL
+        .store(tuning.max_logs_per_handler_call().unwrap_or_default(), Ordering::SeqCst);
+
+    for network in &manifest.networks {
+        if let Some(provider) = providers.get(&network.name) {
+            provider.set_max_block_range(network.max_block_range);
+            provider.set_cache_policy(ProviderCachePolicy::from(
+                &network.cache.clone().unwrap_or_default(),
+            ));
+            provider.set_retry_policy(RpcRetryPolicy::from(
+                &network.rpc_retry.clone().unwrap_or_default(),
+            ));
+        }
+    }
+}
+
+/// Re-reads the manifest and applies its tuning, a broken manifest keeps the current settings
+pub fn reload_runtime_tuning(
+    manifest_path: &PathBuf,
+    providers: &HashMap<String, Arc<JsonRpcCachedProvider>>,
+) {
+    match read_manifest(manifest_path) {
+        Ok(manifest) => {
+            apply_runtime_tuning(&manifest, providers);
+            let tuning = manifest.tuning.unwrap_or_default();
+            info!(
+                "Reloaded tuning - indexing concurrency {} - live polling interval {}ms to {}ms",
+                tuning.indexing_concurrency(),
+                tuning.live_polling_interval_ms(),
+                tuning.live_polling_max_interval_ms()
+            );
+        }
+        Err(e) => {
+            error!("Could not reload the manifest, keeping the current tuning: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_interval_doubles_up_to_the_max() {
+        let min = Duration::from_millis(200);
+        let max = Duration::from_millis(2000);
+
+        assert_eq!(backoff_interval(0, min, max), min);
+        assert_eq!(backoff_interval(1, min, max), Duration::from_millis(400));
+        assert_eq!(backoff_interval(3, min, max), Duration::from_millis(1600));
+        assert_eq!(backoff_interval(4, min, max), max);
+        assert_eq!(backoff_interval(u32::MAX, min, max), max);
+        // a max below the min turns the backoff off
+        assert_eq!(backoff_interval(5, min, Duration::from_millis(100)), min);
+    }
+
+    async fn settle() {
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_grow_pays_off_a_pending_shrink() {
+        let semaphore = Arc::new(Semaphore::new(4));
+        let deficit = Arc::new(AtomicUsize::new(0));
+        let in_flight = Arc::clone(&semaphore).acquire_many_owned(4).await.unwrap();
+
+        // 4 -> 2 while every permit is in flight, then back to 4 before any finishes
+        shrink_permits(Arc::clone(&semaphore), Arc::clone(&deficit), 2);
+        settle().await;
+        assert_eq!(grow_permits(&semaphore, &deficit, 2), 0);
+        assert_eq!(deficit.load(Ordering::SeqCst), 0);
+
+        drop(in_flight);
+        settle().await;
+        assert_eq!(semaphore.available_permits(), 4);
+
+        // 4 -> 1 takes the permits back as they are free, 1 -> 3 adds them again
+        shrink_permits(Arc::clone(&semaphore), Arc::clone(&deficit), 3);
+        settle().await;
+        assert_eq!(semaphore.available_permits(), 1);
+        assert_eq!(grow_permits(&semaphore, &deficit, 2), 2);
+        assert_eq!(semaphore.available_permits(), 3);
+    }
+}
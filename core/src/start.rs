@@ -1,18 +1,39 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Once},
+    time::Duration,
+};
 
-use tokio::signal;
+use futures::future::join_all;
+use tokio::{signal, task::JoinHandle};
 use tracing::{error, info};
 
 use crate::{
-    api::{start_graphql_server, GraphqlOverrideSettings, StartGraphqlServerError},
+    api::{
+        start_admin_server, start_flight_server, start_graphql_explorer, start_graphql_server,
+        start_metrics_server, GraphqlOverrideSettings, StartGraphqlServerError,
+    },
     database::postgres::{
-        client::PostgresConnectionError,
+        block_timestamps::{block_timestamps_schema_name, start_block_timestamps_recorder},
+        client::{PostgresClient, PostgresConnectionError},
+        entities::{entities_schema_name, start_entities_builder},
         indexes::{ApplyPostgresIndexesError, PostgresIndexResult},
+        kv_store::start_kv_store_history_pruner,
+        materialized_views::{materialized_views_schema_name, start_materialized_views_refresh},
         relationship::{ApplyAllRelationships, Relationship},
+        reorg::start_reorg_watcher,
+        retention::start_retention_pruner,
+        rollups::{rollups_schema_name, start_rollups_builder},
         setup::{setup_postgres, SetupPostgresError},
+        state_samples::{start_state_samplers, state_samples_schema_name},
     },
+    error_reporting::{flush_error_reporting, init_error_reporting},
     event::callback_registry::EventCallbackRegistry,
     indexer::{
+        backfill_jobs::is_backfill_worker,
+        chain_id::{start_chain_id_monitor, verify_chain_ids, ChainIdError},
+        leader_election::{acquire_leadership, LeaderElectionError},
         no_code::{setup_no_code, SetupNoCodeError},
         start::{start_indexing, StartIndexingError},
         ContractEventDependencies, ContractEventDependenciesMapFromRelationshipsError,
@@ -20,13 +41,20 @@ use crate::{
     initiate_shutdown,
     logger::mark_shutdown_started,
     manifest::{
-        core::ProjectType,
+        core::{Manifest, ProjectType},
+        metrics::MetricsSettings,
         storage::RelationshipsAndIndexersError,
         yaml::{read_manifest, ReadManifestError},
     },
+    modules::{modules_schema_names, register_modules, RegisterModulesError},
+    provider::{CreateNetworkProvider, RetryClientError},
+    provider_usage::{log_provider_usage_report, track_provider_usage},
+    runtime_tuning::{apply_runtime_tuning, reload_runtime_tuning},
     setup_info_logger,
 };
 
+const PROVIDER_USAGE_REPORT_SECONDS: u64 = 15 * 60;
+
 pub struct IndexingDetails {
     pub registry: EventCallbackRegistry,
 }
@@ -57,6 +85,12 @@ pub enum StartRindexerError {
     #[error("Could not start indexing: {0}")]
     CouldNotStartIndexing(#[from] StartIndexingError),
 
+    #[error("Could not register the modules: {0}")]
+    RegisterModules(#[from] RegisterModulesError),
+
+    #[error("{0}")]
+    RetryClientError(#[from] RetryClientError),
+
     #[error("{0}")]
     PostgresConnectionError(#[from] PostgresConnectionError),
 
@@ -74,6 +108,21 @@ pub enum StartRindexerError {
     #[error("{0}")]
     RelationshipsAndIndexersError(#[from] RelationshipsAndIndexersError),
 
+    #[error("{0}")]
+    LeaderElectionError(#[from] LeaderElectionError),
+
+    #[error("{0}")]
+    ChainIdError(#[from] ChainIdError),
+
+    #[error("A backfill worker needs backfill_jobs set in the manifest")]
+    BackfillWorkerWithoutBackfillJobs,
+
+    #[error("More than one project is named {0}, the name is what keeps their tables apart")]
+    DuplicateProjectName(String),
+
+    #[error("GraphQL port {0} of {1} is already used by another project")]
+    DuplicateGraphqlPort(u16, String),
+
     #[error("Shutdown handler failed with error: {0}")]
     ShutdownHandlerFailed(String),
 }
@@ -83,163 +132,530 @@ async fn handle_shutdown(signal: &str) {
     mark_shutdown_started();
     info!("Received {} signal gracefully shutting down...", signal);
     initiate_shutdown().await;
+    log_provider_usage_report();
+    flush_error_reporting();
     // These info! calls work because they're before/after the shutdown process
     info!("Graceful shutdown completed for {}", signal);
     std::process::exit(0);
 }
 
 pub async fn start_rindexer(details: StartDetails<'_>) -> Result<(), StartRindexerError> {
-    let project_path = details.manifest_path.parent();
-    match project_path {
-        Some(project_path) => {
-            #[cfg(unix)]
-            let shutdown_handle = {
-                let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate())
-                    .map_err(|e| StartRindexerError::ShutdownHandlerFailed(e.to_string()))?;
-                let mut sigint = signal::unix::signal(signal::unix::SignalKind::interrupt())
-                    .map_err(|e| StartRindexerError::ShutdownHandlerFailed(e.to_string()))?;
-                let mut sigquit = signal::unix::signal(signal::unix::SignalKind::quit())
-                    .map_err(|e| StartRindexerError::ShutdownHandlerFailed(e.to_string()))?;
+    let project_path = details
+        .manifest_path
+        .parent()
+        .ok_or(StartRindexerError::NoProjectPathFoundUsingParentOfManifestPath)?;
+    let manifest = read_manifest(details.manifest_path)?;
+
+    start_rindexer_with_manifest(
+        project_path,
+        manifest,
+        Some(details.manifest_path),
+        details.indexing_details,
+        details.graphql_details,
+    )
+    .await
+}
 
-                tokio::spawn(async move {
-                    tokio::select! {
-                        _ = sigterm.recv() => handle_shutdown("SIGTERM").await,
-                        _ = sigint.recv() => handle_shutdown("SIGINT (Ctrl+C)").await,
-                        _ = sigquit.recv() => handle_shutdown("SIGQUIT").await,
-                    }
-                })
+/// The signal handlers shut the whole process down, so they are installed once however many
+/// projects run in it
+fn install_shutdown_handler() -> Result<JoinHandle<()>, StartRindexerError> {
+    #[cfg(unix)]
+    let shutdown_handle = {
+        let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate())
+            .map_err(|e| StartRindexerError::ShutdownHandlerFailed(e.to_string()))?;
+        let mut sigint = signal::unix::signal(signal::unix::SignalKind::interrupt())
+            .map_err(|e| StartRindexerError::ShutdownHandlerFailed(e.to_string()))?;
+        let mut sigquit = signal::unix::signal(signal::unix::SignalKind::quit())
+            .map_err(|e| StartRindexerError::ShutdownHandlerFailed(e.to_string()))?;
+
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = sigterm.recv() => handle_shutdown("SIGTERM").await,
+                _ = sigint.recv() => handle_shutdown("SIGINT (Ctrl+C)").await,
+                _ = sigquit.recv() => handle_shutdown("SIGQUIT").await,
+            }
+        })
+    };
+
+    // On Windows, we just use Ctrl+C to trigger shutdown
+    #[cfg(windows)]
+    let shutdown_handle = tokio::spawn(async move {
+        if let Err(e) = signal::ctrl_c().await {
+            error!("Failed to register Ctrl+C handler: {}", e);
+            panic!("Ctrl+C handler failed: {}", e);
+        }
+        handle_shutdown("Ctrl+C").await
+    });
+
+    Ok(shutdown_handle)
+}
+
+async fn wait_for_shutdown(shutdown_handle: JoinHandle<()>) -> Result<(), StartRindexerError> {
+    shutdown_handle.await.map_err(|e| {
+        error!("Shutdown handler failed: {:?}", e);
+        StartRindexerError::ShutdownHandlerFailed(e.to_string())
+    })
+}
+
+/// Runs an indexer from a manifest built in code, paths in it like the ABIs are relative to
+/// `project_path`. Without a `manifest_path` SIGHUP does not reload the tuning.
+pub(crate) async fn start_rindexer_with_manifest(
+    project_path: &Path,
+    manifest: Manifest,
+    manifest_path: Option<&PathBuf>,
+    indexing_details: Option<IndexingDetails>,
+    graphql_details: GraphqlOverrideSettings,
+) -> Result<(), StartRindexerError> {
+    let shutdown_handle = install_shutdown_handler()?;
+
+    if let Some(sentry) = &manifest.sentry {
+        init_error_reporting(sentry);
+    }
+
+    let indexing = indexing_details.is_some();
+    run_project(project_path, Arc::new(manifest), manifest_path, indexing_details, graphql_details)
+        .await?;
+
+    // a backfill worker is done once the jobs are, it has nothing to serve
+    if indexing && is_backfill_worker() {
+        return Ok(());
+    }
+
+    wait_for_shutdown(shutdown_handle).await
+}
+
+/// Runs several projects in one process, each indexes into the schemas of its own name while
+/// the RPC providers and the indexing concurrency are shared between them. A project which fails
+/// to start is logged and does not stop the others.
+pub async fn start_rindexer_projects(
+    projects: Vec<StartDetails<'_>>,
+) -> Result<(), StartRindexerError> {
+    let mut loaded = vec![];
+    let mut names = HashSet::new();
+    let mut graphql_ports = HashSet::new();
+    for details in projects {
+        let project_path = details
+            .manifest_path
+            .parent()
+            .ok_or(StartRindexerError::NoProjectPathFoundUsingParentOfManifestPath)?;
+        let manifest = read_manifest(details.manifest_path)?;
+
+        if !names.insert(manifest.name.clone()) {
+            return Err(StartRindexerError::DuplicateProjectName(manifest.name));
+        }
+        if details.graphql_details.enabled {
+            let port = match details.graphql_details.override_port {
+                Some(port) => port,
+                None => manifest.graphql.clone().unwrap_or_default().port,
             };
+            if !graphql_ports.insert(port) {
+                return Err(StartRindexerError::DuplicateGraphqlPort(port, manifest.name));
+            }
+        }
 
-            // On Windows, we just use Ctrl+C to trigger shutdown
-            #[cfg(windows)]
-            let shutdown_handle = tokio::spawn(async move {
-                if let Err(e) = signal::ctrl_c().await {
-                    error!("Failed to register Ctrl+C handler: {}", e);
-                    panic!("Ctrl+C handler failed: {}", e);
-                }
-                handle_shutdown("Ctrl+C").await
-            });
+        loaded.push((project_path, manifest, details));
+    }
+
+    let shutdown_handle = install_shutdown_handler()?;
+
+    // sentry is process wide so the first project which configures it reports for all of them
+    if let Some(sentry) = loaded.iter().find_map(|(_, manifest, _)| manifest.sentry.as_ref()) {
+        init_error_reporting(sentry);
+    }
+
+    let runs = loaded.into_iter().map(|(project_path, manifest, details)| async move {
+        let name = manifest.name.clone();
+        if let Err(e) = run_project(
+            project_path,
+            Arc::new(manifest),
+            Some(details.manifest_path),
+            details.indexing_details,
+            details.graphql_details,
+        )
+        .await
+        {
+            error!("Project {} stopped: {}", name, e);
+        }
+    });
+    join_all(runs).await;
+
+    if is_backfill_worker() {
+        return Ok(());
+    }
+
+    wait_for_shutdown(shutdown_handle).await
+}
 
-            let manifest = Arc::new(read_manifest(details.manifest_path)?);
+/// Logs the RPC usage on an interval, the report covers every project in the process so it is
+/// only started once
+fn start_provider_usage_report() {
+    static STARTED: Once = Once::new();
+    STARTED.call_once(|| {
+        // the report is logged again on shutdown for the final numbers
+        tokio::spawn(async {
+            loop {
+                tokio::time::sleep(Duration::from_secs(PROVIDER_USAGE_REPORT_SECONDS)).await;
+                log_provider_usage_report();
+            }
+        });
+    });
+}
 
-            if manifest.project_type != ProjectType::NoCode {
-                setup_info_logger();
-                info!("Starting rindexer rust project");
+/// Serves the metrics of every project in the process, the first project with metrics set starts
+/// it
+fn start_metrics(settings: &MetricsSettings) {
+    static STARTED: Once = Once::new();
+    STARTED.call_once(|| {
+        let settings = settings.clone();
+        tokio::spawn(async move {
+            if let Err(e) = start_metrics_server(&settings).await {
+                error!("Failed to start the metrics server: {}", e);
             }
+        });
+    });
+}
 
-            // Spawn a separate task for the GraphQL server if specified
-            let graphql_server_handle =
-                if details.graphql_details.enabled && manifest.storage.postgres_enabled() {
-                    let manifest_clone = Arc::clone(&manifest);
-                    let indexer = manifest_clone.to_indexer();
-                    let mut graphql_settings = manifest.graphql.clone().unwrap_or_default();
-                    if let Some(override_port) = &details.graphql_details.override_port {
-                        graphql_settings.set_port(*override_port);
-                    }
-                    Some(tokio::spawn(async move {
-                        if let Err(e) = start_graphql_server(&indexer, &graphql_settings).await {
-                            error!("Failed to start GraphQL server: {:?}", e);
-                        }
-                    }))
-                } else {
-                    None
-                };
+async fn run_project(
+    project_path: &Path,
+    manifest: Arc<Manifest>,
+    manifest_path: Option<&PathBuf>,
+    indexing_details: Option<IndexingDetails>,
+    graphql_details: GraphqlOverrideSettings,
+) -> Result<(), StartRindexerError> {
+    if manifest.project_type != ProjectType::NoCode {
+        setup_info_logger();
+        info!("Starting rindexer rust project");
+    }
 
-            if graphql_server_handle.is_none() && details.graphql_details.enabled {
-                error!("GraphQL can not run without postgres storage enabled, you have tried to run GraphQL which will now be skipped.");
+    // materialized views, modules, entities, rollups, state samples and block timestamps live in
+    // their own schemas
+    let mut extra_schemas = modules_schema_names(&manifest);
+    if !manifest.storage.postgres_materialized_views().is_empty() {
+        extra_schemas.push(materialized_views_schema_name(&manifest.name));
+    }
+    if !manifest.storage.postgres_entities().is_empty() {
+        extra_schemas.push(entities_schema_name(&manifest.name));
+    }
+    if !manifest.storage.postgres_rollups().is_empty() {
+        extra_schemas.push(rollups_schema_name(&manifest.name));
+    }
+    if !manifest.storage.postgres_state_samples().is_empty() {
+        extra_schemas.push(state_samples_schema_name(&manifest.name));
+    }
+    if manifest.storage.postgres_block_timestamps().is_some() {
+        extra_schemas.push(block_timestamps_schema_name(&manifest.name));
+    }
+
+    // Spawn a separate task for the GraphQL server if specified
+    let graphql_server_handle = if graphql_details.enabled && manifest.storage.postgres_enabled() {
+        let manifest_clone = Arc::clone(&manifest);
+        let indexer = manifest_clone.to_indexer();
+        let mut graphql_settings = manifest.graphql.clone().unwrap_or_default();
+        if let Some(override_port) = &graphql_details.override_port {
+            graphql_settings.set_port(*override_port);
+        }
+        let extra_schemas = extra_schemas.clone();
+        let project_path = project_path.to_path_buf();
+        Some(tokio::spawn(async move {
+            if let Err(e) = start_graphql_server(&indexer, &graphql_settings, extra_schemas).await {
+                error!("Failed to start GraphQL server: {:?}", e);
+                return;
+            }
+            // serves until shutdown so the task awaited for the GraphQL server does not wait on it
+            if !graphql_settings.disable_explorer {
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        start_graphql_explorer(&project_path, &indexer, &graphql_settings).await
+                    {
+                        error!("Failed to start the GraphQL explorer: {}", e);
+                    }
+                });
             }
+        }))
+    } else {
+        None
+    };
+
+    if graphql_server_handle.is_none() && graphql_details.enabled {
+        error!("GraphQL can not run without postgres storage enabled, you have tried to run GraphQL which will now be skipped.");
+    }
 
-            if let Some(mut indexing_details) = details.indexing_details {
-                let postgres_enabled = &manifest.storage.postgres_enabled();
+    if let Some(metrics_settings) = &manifest.metrics {
+        start_metrics(metrics_settings);
+    }
 
-                // setup postgres is already called in no-code startup
-                if manifest.project_type != ProjectType::NoCode && *postgres_enabled {
-                    setup_postgres(project_path, &manifest).await?;
+    if let Some(flight_settings) = manifest.flight.clone() {
+        if manifest.storage.postgres_enabled() {
+            let indexer = manifest.to_indexer();
+            let networks = manifest.networks.iter().map(|n| n.name.clone()).collect();
+            tokio::spawn(async move {
+                if let Err(e) =
+                    start_flight_server(&indexer, &flight_settings, networks, extra_schemas).await
+                {
+                    error!("Failed to start arrow flight server: {}", e);
                 }
+            });
+        } else {
+            error!(
+                "Arrow flight can not run without postgres storage enabled, it will be skipped."
+            );
+        }
+    }
 
-                let (relationships, postgres_indexes) = manifest
-                    .storage
-                    .create_relationships_and_indexes(
-                        project_path,
-                        &manifest.name,
-                        &manifest.contracts,
-                    )
-                    .await?;
-
-                let mut dependencies: Vec<ContractEventDependencies> =
-                    ContractEventDependencies::parse(&manifest);
+    if let Some(mut indexing_details) = indexing_details {
+        let backfill_worker = is_backfill_worker();
+        if backfill_worker && manifest.backfill_jobs.is_none() {
+            return Err(StartRindexerError::BackfillWorkerWithoutBackfillJobs);
+        }
 
-                let processed_network_contracts = start_indexing(
-                    &manifest,
-                    project_path,
-                    &dependencies,
-                    // we index all the historic data first before then applying FKs
-                    !relationships.is_empty(),
-                    indexing_details.registry.complete(),
-                )
-                .await?;
+        // kept alive until the end of indexing, dropping it releases the lock
+        let _leader_lock = match &manifest.leader_election {
+            Some(settings) if !backfill_worker => {
+                Some(acquire_leadership(settings, &manifest.name).await?)
+            }
+            _ => None,
+        };
+
+        if manifest.modules.is_some() {
+            // the modules share the providers the contracts already use
+            let mut network_providers = CreateNetworkProvider::create(&manifest)?;
+            for network_provider in &mut network_providers {
+                let existing = indexing_details
+                    .registry
+                    .events
+                    .iter()
+                    .flat_map(|event| &event.contract.details)
+                    .find(|details| details.network == network_provider.network_name);
+                if let Some(existing) = existing {
+                    network_provider.client = Arc::clone(&existing.cached_provider);
+                }
+            }
+            register_modules(&manifest, &mut indexing_details.registry, &network_providers).await?;
+        }
 
-                // TODO if graphql isn't up yet, and we apply this on graphql wont refresh we need
-                // to handle this
-                info!(
-                    "Applying indexes if any back to the database as historic resync is complete"
+        let mut providers = HashMap::new();
+        for event in &indexing_details.registry.events {
+            for details in &event.contract.details {
+                providers
+                    .entry(details.network.clone())
+                    .or_insert_with(|| Arc::clone(&details.cached_provider));
+            }
+        }
+        verify_chain_ids(&manifest.networks, &providers).await?;
+        start_chain_id_monitor(manifest.networks.clone(), providers.clone());
+        for (network, provider) in &providers {
+            track_provider_usage(network, provider);
+        }
+        start_provider_usage_report();
+        // the first refresh waits for the schedule so the views exist by then
+        let materialized_views = manifest.storage.postgres_materialized_views();
+        if !backfill_worker && !materialized_views.is_empty() {
+            start_materialized_views_refresh(
+                Arc::new(PostgresClient::new().await?),
+                &manifest.name,
+                materialized_views,
+                &providers,
+            );
+        }
+        if !backfill_worker &&
+            !manifest.storage.postgres_disable_create_tables() &&
+            manifest.contracts.iter().any(|contract| contract.retention.is_some())
+        {
+            start_retention_pruner(
+                Arc::new(PostgresClient::new().await?),
+                &manifest.name,
+                &manifest.contracts,
+                providers.clone(),
+            );
+        }
+        if !backfill_worker && manifest.storage.postgres_enabled() {
+            start_kv_store_history_pruner(
+                Arc::new(PostgresClient::new().await?),
+                &manifest.name,
+                providers.clone(),
+            );
+        }
+        if !backfill_worker &&
+            !manifest.storage.postgres_disable_create_tables() &&
+            manifest.has_any_contracts_live_indexing()
+        {
+            start_reorg_watcher(
+                Arc::new(PostgresClient::new().await?),
+                project_path,
+                &manifest.name,
+                &manifest.contracts,
+                manifest.storage.postgres_entities().to_vec(),
+                manifest.storage.postgres_rollups().to_vec(),
+                providers.clone(),
+                manifest.storage.postgres_soft_delete_reorged_rows(),
+            );
+        }
+        let entities = manifest.storage.postgres_entities();
+        if !backfill_worker && !entities.is_empty() {
+            start_entities_builder(
+                Arc::new(PostgresClient::new().await?),
+                &manifest.name,
+                &manifest.contracts,
+                entities.to_vec(),
+                providers.clone(),
+                manifest.storage.postgres_soft_delete_reorged_rows(),
+            );
+        }
+        let state_samples = manifest.storage.postgres_state_samples();
+        if !backfill_worker && !state_samples.is_empty() {
+            start_state_samplers(
+                Arc::new(PostgresClient::new().await?),
+                &manifest.name,
+                state_samples,
+                &providers,
+            );
+        }
+        if let Some(block_timestamps) = manifest.storage.postgres_block_timestamps() {
+            if !backfill_worker {
+                start_block_timestamps_recorder(
+                    Arc::new(PostgresClient::new().await?),
+                    &manifest.name,
+                    block_timestamps,
+                    &manifest.contracts,
+                    &providers,
                 );
-                PostgresIndexResult::apply_indexes(postgres_indexes).await?;
-
-                if !relationships.is_empty() {
-                    // TODO if graphql isn't up yet, and we apply this on graphql wont refresh we
-                    // need to handle this
-                    info!("Applying constraints relationships back to the database as historic resync is complete");
-                    Relationship::apply_all(&relationships).await?;
-
-                    if manifest.has_any_contracts_live_indexing() {
-                        info!("Starting live indexing now relationship re-applied..");
-
-                        if dependencies.is_empty() {
-                            dependencies =
-                                ContractEventDependencies::map_from_relationships(&relationships)?;
-                        } else {
-                            info!("Manual dependency_events found, skipping auto-applying the dependency_events with the relationships");
+            }
+        }
+        let rollups = manifest.storage.postgres_rollups();
+        if !backfill_worker && !rollups.is_empty() {
+            start_rollups_builder(
+                Arc::new(PostgresClient::new().await?),
+                &manifest.name,
+                &manifest.contracts,
+                rollups.to_vec(),
+                providers.clone(),
+                manifest.storage.postgres_soft_delete_reorged_rows(),
+            );
+        }
+
+        apply_runtime_tuning(&manifest, &providers);
+
+        // SIGHUP reloads the tuning so an overloaded RPC can be throttled without a
+        // restart losing the warm caches
+        #[cfg(unix)]
+        if let Some(manifest_path) = manifest_path {
+            match signal::unix::signal(signal::unix::SignalKind::hangup()) {
+                Ok(mut sighup) => {
+                    let manifest_path = manifest_path.clone();
+                    tokio::spawn(async move {
+                        while sighup.recv().await.is_some() {
+                            info!("Received SIGHUP reloading tuning from the manifest");
+                            reload_runtime_tuning(&manifest_path, &providers);
                         }
+                    });
+                }
+                Err(e) => error!("Could not listen for SIGHUP to reload tuning: {}", e),
+            }
+        }
+
+        let postgres_enabled = &manifest.storage.postgres_enabled();
+
+        // setup postgres is already called in no-code startup
+        if manifest.project_type != ProjectType::NoCode && *postgres_enabled {
+            setup_postgres(project_path, &manifest).await?;
+        }
 
-                        start_indexing(
-                            &manifest,
-                            project_path,
-                            &dependencies,
-                            false,
-                            indexing_details
-                                .registry
-                                .reapply_after_historic(processed_network_contracts),
-                        )
+        if let Some(admin_settings) = manifest.admin.clone().filter(|_| !backfill_worker) {
+            let project_path = project_path.to_path_buf();
+            let manifest = Arc::clone(&manifest);
+            let manifest_path = manifest_path.cloned();
+            tokio::spawn(async move {
+                if let Err(e) =
+                    start_admin_server(&admin_settings, &project_path, &manifest, manifest_path)
                         .await
-                        .map_err(StartRindexerError::CouldNotStartIndexing)?;
-                    }
+                {
+                    error!("Failed to start the admin API: {}", e);
                 }
+            });
+        }
 
-                // Do not need now with the main shutdown keeping around in-case
-                // if details.graphql_details.enabled {
-                //     signal::ctrl_c()
-                //         .await
-                //         .map_err(|_| StartRindexerError::FailedToListenToGraphqlSocket)?;
-                // }
-            }
+        // workers only take backfill jobs, the main indexer owns the indexes,
+        // relationships and live indexing
+        if backfill_worker {
+            start_indexing(
+                &manifest,
+                project_path,
+                &[],
+                false,
+                indexing_details.registry.complete(),
+            )
+            .await?;
+
+            return Ok(());
+        }
 
-            // Await the GraphQL server task if it was started
-            if let Some(handle) = graphql_server_handle {
-                handle.await.unwrap_or_else(|e| {
-                    error!("GraphQL server task failed: {:?}", e);
-                });
+        let (relationships, postgres_indexes) = manifest
+            .storage
+            .create_relationships_and_indexes(project_path, &manifest.name, &manifest.contracts)
+            .await?;
+
+        let mut dependencies: Vec<ContractEventDependencies> =
+            ContractEventDependencies::parse(&manifest);
+
+        let processed_network_contracts = start_indexing(
+            &manifest,
+            project_path,
+            &dependencies,
+            // we index all the historic data first before then applying FKs
+            !relationships.is_empty(),
+            indexing_details.registry.complete(),
+        )
+        .await?;
+
+        // TODO if graphql isn't up yet, and we apply this on graphql wont refresh we need
+        // to handle this
+        info!("Applying indexes if any back to the database as historic resync is complete");
+        PostgresIndexResult::apply_indexes(postgres_indexes).await?;
+
+        if !relationships.is_empty() {
+            // TODO if graphql isn't up yet, and we apply this on graphql wont refresh we
+            // need to handle this
+            info!("Applying constraints relationships back to the database as historic resync is complete");
+            Relationship::apply_all(&relationships).await?;
+
+            if manifest.has_any_contracts_live_indexing() {
+                info!("Starting live indexing now relationship re-applied..");
+
+                if dependencies.is_empty() {
+                    dependencies =
+                        ContractEventDependencies::map_from_relationships(&relationships)?;
+                } else {
+                    info!("Manual dependency_events found, skipping auto-applying the dependency_events with the relationships");
+                }
+
+                start_indexing(
+                    &manifest,
+                    project_path,
+                    &dependencies,
+                    false,
+                    indexing_details.registry.reapply_after_historic(processed_network_contracts),
+                )
+                .await
+                .map_err(StartRindexerError::CouldNotStartIndexing)?;
             }
+        }
 
-            shutdown_handle.await.map_err(|e| {
-                error!("Shutdown handler failed: {:?}", e);
-                StartRindexerError::ShutdownHandlerFailed(e.to_string())
-            })?;
+        // Do not need now with the main shutdown keeping around in-case
+        // if graphql_details.enabled {
+        //     signal::ctrl_c()
+        //         .await
+        //         .map_err(|_| StartRindexerError::FailedToListenToGraphqlSocket)?;
+        // }
+    }
 
-            Ok(())
-        }
-        None => Err(StartRindexerError::NoProjectPathFoundUsingParentOfManifestPath),
+    // Await the GraphQL server task if it was started
+    if let Some(handle) = graphql_server_handle {
+        handle.await.unwrap_or_else(|e| {
+            error!("GraphQL server task failed: {:?}", e);
+        });
     }
+
+    Ok(())
 }
 
 pub struct IndexerNoCodeDetails {
@@ -268,3 +684,15 @@ pub async fn start_rindexer_no_code(
 
     start_rindexer(start_details).await.map_err(StartRindexerNoCode::StartRindexerError)
 }
+
+/// Sets up every no-code project and runs them together in this process
+pub async fn start_rindexer_no_code_projects(
+    projects: Vec<StartNoCodeDetails<'_>>,
+) -> Result<(), StartRindexerNoCode> {
+    let mut start_details = vec![];
+    for details in projects {
+        start_details.push(setup_no_code(details).await?);
+    }
+
+    start_rindexer_projects(start_details).await.map_err(StartRindexerNoCode::StartRindexerError)
+}
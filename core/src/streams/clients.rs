@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use aws_sdk_sns::{config::http::HttpResponse, error::SdkError, operation::publish::PublishError};
 use futures::future::join_all;
@@ -13,11 +13,15 @@ use tracing::error;
 use crate::{
     event::{filter_event_data_by_conditions, EventMessage},
     manifest::stream::{
-        KafkaStreamConfig, KafkaStreamQueueConfig, RabbitMQStreamConfig, RabbitMQStreamQueueConfig,
-        SNSStreamTopicConfig, StreamEvent, StreamsConfig, WebhookStreamConfig,
+        KafkaMessageFormat, KafkaStreamConfig, KafkaStreamQueueConfig, PayloadFormat,
+        RabbitMQStreamConfig, RabbitMQStreamQueueConfig, SNSStreamTopicConfig, StreamEvent,
+        StreamsConfig, WebhookStreamConfig,
     },
     streams::{
+        debezium::{DebeziumOp, DebeziumRow},
         kafka::{Kafka, KafkaError},
+        payload::{encode_payload, Payload, PayloadError},
+        schema::EventSchema,
         RabbitMQ, RabbitMQError, Webhook, WebhookError, SNS,
     },
 };
@@ -48,6 +52,9 @@ pub enum StreamError {
     #[error("Kafka could not publish: {0}")]
     KafkaCouldNotPublish(#[from] KafkaError),
 
+    #[error("Could not encode payload: {0}")]
+    CouldNotEncodePayload(#[from] PayloadError),
+
     #[error("Task failed: {0}")]
     JoinError(JoinError),
 }
@@ -73,6 +80,8 @@ pub struct StreamsClients {
     webhook: Option<WebhookStream>,
     rabbitmq: Option<RabbitMQStream>,
     kafka: Option<KafkaStream>,
+    // the schemas typed formats encode the events with, by event name
+    event_schemas: HashMap<String, Arc<EventSchema>>,
 }
 
 impl StreamsClients {
@@ -113,7 +122,12 @@ impl StreamsClients {
             None
         };
 
-        Self { sns, webhook, rabbitmq, kafka }
+        Self { sns, webhook, rabbitmq, kafka, event_schemas: HashMap::new() }
+    }
+
+    /// Gives the typed formats the schema to encode an event with
+    pub fn add_event_schema(&mut self, schema: EventSchema) {
+        self.event_schemas.insert(schema.event_name().to_string(), Arc::new(schema));
     }
 
     fn has_any_streams(&self) -> bool {
@@ -149,24 +163,23 @@ impl StreamsClients {
         chunks
     }
 
-    fn create_chunk_message_raw(&self, event_message: &EventMessage, chunk: &[Value]) -> String {
-        let chunk_message = EventMessage {
-            event_name: event_message.event_name.clone(),
-            event_data: Value::Array(chunk.to_vec()),
-            network: event_message.network.clone(),
-        };
-
-        serde_json::to_string(&chunk_message).unwrap()
-    }
-
-    fn create_chunk_message_json(&self, event_message: &EventMessage, chunk: &[Value]) -> Value {
+    fn create_chunk_payload(
+        &self,
+        format: PayloadFormat,
+        event_message: &EventMessage,
+        chunk: &[Value],
+    ) -> Result<Payload, PayloadError> {
         let chunk_message = EventMessage {
             event_name: event_message.event_name.clone(),
             event_data: Value::Array(chunk.to_vec()),
             network: event_message.network.clone(),
         };
 
-        serde_json::to_value(&chunk_message).unwrap()
+        encode_payload(
+            format,
+            &chunk_message,
+            self.event_schemas.get(&event_message.event_name).map(Arc::as_ref),
+        )
     }
 
     fn generate_publish_message_id(
@@ -231,8 +244,10 @@ impl StreamsClients {
                     self.generate_publish_message_id(id, index, &config.prefix_id);
                 let client = Arc::clone(&client);
                 let topic_arn = config.topic_arn.clone();
-                let publish_message = self.create_chunk_message_raw(event_message, &filtered_chunk);
+                let payload =
+                    self.create_chunk_payload(config.format(), event_message, &filtered_chunk);
                 task::spawn(async move {
+                    let publish_message = payload?.text();
                     let _ =
                         client.publish(&publish_message_id, &topic_arn, &publish_message).await?;
 
@@ -266,11 +281,11 @@ impl StreamsClients {
                 let endpoint = config.endpoint.clone();
                 let shared_secret = config.shared_secret.clone();
                 let client = Arc::clone(&client);
-                let publish_message =
-                    self.create_chunk_message_json(event_message, &filtered_chunk);
+                let payload =
+                    self.create_chunk_payload(config.format(), event_message, &filtered_chunk);
                 task::spawn(async move {
                     client
-                        .publish(&publish_message_id, &endpoint, &shared_secret, &publish_message)
+                        .publish(&publish_message_id, &endpoint, &shared_secret, &payload?)
                         .await?;
 
                     Ok(filtered_chunk.len())
@@ -304,8 +319,8 @@ impl StreamsClients {
                 let exchange = config.exchange.clone();
                 let exchange_type = config.exchange_type.clone();
                 let routing_key = config.routing_key.clone();
-                let publish_message =
-                    self.create_chunk_message_json(event_message, &filtered_chunk);
+                let payload =
+                    self.create_chunk_payload(config.format(), event_message, &filtered_chunk);
 
                 task::spawn(async move {
                     client
@@ -314,7 +329,7 @@ impl StreamsClients {
                             &exchange,
                             &exchange_type,
                             &routing_key,
-                            &publish_message,
+                            &payload?,
                         )
                         .await?;
                     Ok(filtered_chunk.len())
@@ -346,11 +361,61 @@ impl StreamsClients {
                 let client = Arc::clone(&client);
                 let exchange = config.topic.clone();
                 let routing_key = config.key.clone();
-                let publish_message =
-                    self.create_chunk_message_json(event_message, &filtered_chunk);
+
+                if config.format() == KafkaMessageFormat::Debezium {
+                    let event_name = event_message.event_name.clone();
+                    return task::spawn(async move {
+                        for row in &filtered_chunk {
+                            let Some(debezium_row) = DebeziumRow::from_streamed(row) else {
+                                continue;
+                            };
+                            client
+                                .publish(
+                                    &publish_message_id,
+                                    &exchange,
+                                    &Some(debezium_row.key()),
+                                    &debezium_row.envelope(DebeziumOp::Create, &event_name, row),
+                                )
+                                .await?;
+                        }
+                        Ok(filtered_chunk.len())
+                    });
+                }
+
+                if let Some(format) = config.format().schema_format() {
+                    let schema = self.event_schemas.get(&event_message.event_name).cloned();
+                    let event_name = event_message.event_name.clone();
+                    return task::spawn(async move {
+                        let schema = schema.ok_or(KafkaError::NoEventSchema(event_name))?;
+                        for row in &filtered_chunk {
+                            client
+                                .publish_with_schema(
+                                    &publish_message_id,
+                                    &exchange,
+                                    &routing_key,
+                                    &schema,
+                                    format,
+                                    row,
+                                )
+                                .await?;
+                        }
+                        Ok(filtered_chunk.len())
+                    });
+                }
+
+                let payload = self.create_chunk_payload(
+                    config.format().payload_format().unwrap_or_default(),
+                    event_message,
+                    &filtered_chunk,
+                );
                 task::spawn(async move {
                     client
-                        .publish(&publish_message_id, &exchange, &routing_key, &publish_message)
+                        .publish_payload(
+                            &publish_message_id,
+                            &exchange,
+                            &routing_key,
+                            &payload?.body,
+                        )
                         .await?;
                     Ok(filtered_chunk.len())
                 })
@@ -359,6 +424,51 @@ impl StreamsClients {
         tasks
     }
 
+    /// Sends the rows a reorg removed as deletes to the kafka topics in the debezium format, the
+    /// other streams and formats have no way to take back an event
+    pub async fn stream_reorg_removals(
+        &self,
+        event_name: &str,
+        network: &str,
+        rows: &[Value],
+    ) -> Result<usize, StreamError> {
+        let Some(kafka) = &self.kafka else {
+            return Ok(0);
+        };
+
+        let mut streamed = 0;
+        for config in &kafka.config.topics {
+            if config.format() != KafkaMessageFormat::Debezium ||
+                !config.events.iter().any(|e| e.event_name == event_name) ||
+                !config.networks.iter().any(|n| n == network)
+            {
+                continue;
+            }
+
+            for row in rows {
+                let Some(debezium_row) = DebeziumRow::from_table(row) else {
+                    continue;
+                };
+                let id = format!(
+                    "rindexer_stream__reorg-{}-{}",
+                    debezium_row.transaction_hash, debezium_row.log_index
+                );
+                kafka
+                    .client
+                    .publish(
+                        &id,
+                        &config.topic,
+                        &Some(debezium_row.key()),
+                        &debezium_row.envelope(DebeziumOp::Delete, event_name, row),
+                    )
+                    .await?;
+                streamed += 1;
+            }
+        }
+
+        Ok(streamed)
+    }
+
     pub async fn stream(
         &self,
         id: String,
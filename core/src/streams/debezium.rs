@@ -0,0 +1,140 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ethers::types::U256;
+use serde_json::{json, Value};
+
+/// Debezium's op codes, rindexer only ever inserts rows or removes them on a reorg
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebeziumOp {
+    Create,
+    Delete,
+}
+
+impl DebeziumOp {
+    fn code(&self) -> &'static str {
+        match self {
+            DebeziumOp::Create => "c",
+            DebeziumOp::Delete => "d",
+        }
+    }
+}
+
+/// Streamed values hold numbers as hex and table rows as decimal strings or numbers, keys have to
+/// match between the create and delete of a row so they are always decimal
+fn decimal(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(number) => Some(number.to_string()),
+        Value::String(value) => match value.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16).ok().map(|v| v.to_string()),
+            None => Some(value.trim().to_string()),
+        },
+        _ => None,
+    }
+}
+
+/// Where a row comes from, what Debezium calls the source
+pub struct DebeziumRow<'a> {
+    pub network: &'a str,
+    pub transaction_hash: &'a str,
+    pub log_index: String,
+    pub block_number: Option<String>,
+}
+
+impl<'a> DebeziumRow<'a> {
+    /// A row as sent by the streams, the position of the log is in its `transaction_information`
+    pub fn from_streamed(row: &'a Value) -> Option<Self> {
+        let tx = &row["transaction_information"];
+        Some(Self {
+            network: tx["network"].as_str()?,
+            transaction_hash: tx["transaction_hash"].as_str()?,
+            log_index: decimal(&tx["log_index"])?,
+            block_number: decimal(&tx["block_number"]),
+        })
+    }
+
+    /// A row of an event table
+    pub fn from_table(row: &'a Value) -> Option<Self> {
+        Some(Self {
+            network: row["network"].as_str()?.trim(),
+            transaction_hash: row["tx_hash"].as_str()?.trim(),
+            log_index: decimal(&row["log_index"])?,
+            block_number: decimal(&row["block_number"]),
+        })
+    }
+
+    /// A log is unique by its network, transaction and index so that is the key of the message,
+    /// like the primary key is for a table
+    pub fn key(&self) -> String {
+        json!({
+            "network": self.network,
+            "transaction_hash": self.transaction_hash,
+            "log_index": self.log_index,
+        })
+        .to_string()
+    }
+
+    pub fn envelope(&self, op: DebeziumOp, event_name: &str, row: &Value) -> Value {
+        let ts_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or_default();
+        let (before, after) = match op {
+            DebeziumOp::Create => (Value::Null, row.clone()),
+            DebeziumOp::Delete => (row.clone(), Value::Null),
+        };
+
+        json!({
+            "before": before,
+            "after": after,
+            "source": {
+                "version": env!("CARGO_PKG_VERSION"),
+                "connector": "rindexer",
+                "name": "rindexer",
+                "ts_ms": ts_ms,
+                "snapshot": "false",
+                "db": self.network,
+                "table": event_name,
+                "block_number": self.block_number,
+                "transaction_hash": self.transaction_hash,
+            },
+            "op": op.code(),
+            "ts_ms": ts_ms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_match_between_streamed_and_table_rows() {
+        let streamed = json!({
+            "from": "0x0000000000000000000000000000000000000001",
+            "transaction_information": {
+                "network": "ethereum",
+                "transaction_hash": "0xabc",
+                "log_index": "0x1a",
+                "block_number": "0x10",
+            }
+        });
+        let table = json!({
+            "from": "0x0000000000000000000000000000000000000001",
+            "network": "ethereum",
+            "tx_hash": "0xabc",
+            "log_index": "26",
+            "block_number": 16,
+        });
+
+        let created = DebeziumRow::from_streamed(&streamed).unwrap();
+        let deleted = DebeziumRow::from_table(&table).unwrap();
+        assert_eq!(created.key(), deleted.key());
+        assert_eq!(created.block_number, deleted.block_number);
+
+        let envelope = deleted.envelope(DebeziumOp::Delete, "Transfer", &table);
+        assert_eq!(envelope["op"], "d");
+        assert_eq!(envelope["after"], Value::Null);
+        assert_eq!(envelope["before"], table);
+        assert_eq!(envelope["source"]["table"], "Transfer");
+    }
+}
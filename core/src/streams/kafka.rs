@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 #[cfg(not(windows))]
 use rdkafka::{
@@ -10,7 +10,14 @@ use rdkafka::{
 use serde_json::Value;
 use thiserror::Error;
 
-use crate::{manifest::stream::KafkaStreamConfig, streams::STREAM_MESSAGE_ID_KEY};
+use crate::{
+    manifest::stream::KafkaStreamConfig,
+    streams::{
+        schema::{EventSchema, SchemaError, SchemaFormat},
+        schema_registry::{confluent_framed, SchemaRegistry, SchemaRegistryError},
+        STREAM_MESSAGE_ID_KEY,
+    },
+};
 
 #[derive(Error, Debug)]
 pub enum KafkaError {
@@ -19,12 +26,25 @@ pub enum KafkaError {
 
     #[error("Could not parse message: {0}")]
     CouldNotParseMessage(#[from] serde_json::Error),
+
+    #[error("Could not encode message: {0}")]
+    CouldNotEncodeMessage(#[from] SchemaError),
+
+    #[error("{0}")]
+    SchemaRegistry(#[from] SchemaRegistryError),
+
+    #[error("No schema_registry to register the {0} schemas in")]
+    NoSchemaRegistry(String),
+
+    #[error("No schema was generated for event {0}")]
+    NoEventSchema(String),
 }
 
 #[derive(Clone)]
 pub struct Kafka {
     #[cfg(not(windows))]
     producer: FutureProducer,
+    schema_registry: Option<Arc<SchemaRegistry>>,
 }
 
 impl Kafka {
@@ -52,7 +72,13 @@ impl Kafka {
             let producer: FutureProducer =
                 client_config.create().map_err(|e| KafkaError::RdkafkaError(e.to_string()))?;
 
-            Ok(Self { producer })
+            Ok(Self {
+                producer,
+                schema_registry: config
+                    .schema_registry
+                    .as_ref()
+                    .map(|registry| Arc::new(SchemaRegistry::new(registry))),
+            })
         }
 
         #[cfg(windows)]
@@ -67,18 +93,46 @@ impl Kafka {
         topic: &str,
         key: &Option<String>,
         message: &Value,
+    ) -> Result<(), KafkaError> {
+        self.publish_payload(id, topic, key, &serde_json::to_vec(message)?).await
+    }
+
+    /// Publishes a single event encoded with the schema of the event, registering the schema
+    /// first if it is not registered yet
+    pub async fn publish_with_schema(
+        &self,
+        id: &str,
+        topic: &str,
+        key: &Option<String>,
+        schema: &EventSchema,
+        format: SchemaFormat,
+        event: &Value,
+    ) -> Result<(), KafkaError> {
+        let registry = self
+            .schema_registry
+            .as_ref()
+            .ok_or_else(|| KafkaError::NoSchemaRegistry(schema.full_name()))?;
+        let schema_id = registry.schema_id(topic, schema, format).await?;
+        let payload = confluent_framed(schema_id, format, schema.encode(format, event)?);
+        self.publish_payload(id, topic, key, &payload).await
+    }
+
+    pub async fn publish_payload(
+        &self,
+        id: &str,
+        topic: &str,
+        key: &Option<String>,
+        message_body: &[u8],
     ) -> Result<(), KafkaError> {
         #[cfg(not(windows))]
         {
-            let message_body = serde_json::to_vec(message)?;
-
             let record = if key.is_some() {
-                FutureRecord::to(topic).key(key.as_ref().unwrap()).payload(&message_body).headers(
+                FutureRecord::to(topic).key(key.as_ref().unwrap()).payload(message_body).headers(
                     OwnedHeaders::new()
                         .insert(Header { key: STREAM_MESSAGE_ID_KEY, value: Some(id) }),
                 )
             } else {
-                FutureRecord::to(topic).payload(&message_body).headers(
+                FutureRecord::to(topic).payload(message_body).headers(
                     OwnedHeaders::new()
                         .insert(Header { key: STREAM_MESSAGE_ID_KEY, value: Some(id) }),
                 )
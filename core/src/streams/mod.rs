@@ -7,8 +7,16 @@ pub use webhook::{Webhook, WebhookError};
 mod rabbitmq;
 pub use rabbitmq::{RabbitMQ, RabbitMQError};
 
+mod debezium;
 mod kafka;
 
+mod payload;
+
+mod schema;
+pub use schema::{EventSchema, SchemaFormat};
+
+mod schema_registry;
+
 mod clients;
 pub use clients::StreamsClients;
 
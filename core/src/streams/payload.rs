@@ -0,0 +1,160 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    event::EventMessage,
+    manifest::stream::PayloadFormat,
+    streams::schema::{EventSchema, SchemaError, SchemaFormat},
+};
+
+#[derive(Error, Debug)]
+pub enum PayloadError {
+    #[error("Could not serialize message: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Could not encode message: {0}")]
+    Schema(#[from] SchemaError),
+
+    #[error("No schema was generated for event {0}")]
+    NoEventSchema(String),
+}
+
+/// A chunk of events encoded in the format of a stream destination
+pub struct Payload {
+    pub format: PayloadFormat,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl Payload {
+    /// The body for destinations which only take text, binary formats are base64 encoded
+    pub fn text(&self) -> String {
+        match self.format {
+            PayloadFormat::Json => String::from_utf8_lossy(&self.body).into_owned(),
+            _ => STANDARD.encode(&self.body),
+        }
+    }
+}
+
+/// Encodes a chunk of events, JSON and MessagePack send the chunk as it is while Avro and
+/// Protobuf send its events with the schema generated from the ABI
+pub fn encode_payload(
+    format: PayloadFormat,
+    chunk_message: &EventMessage,
+    schema: Option<&EventSchema>,
+) -> Result<Payload, PayloadError> {
+    let schema_encoded = |schema_format: SchemaFormat| {
+        let schema =
+            schema.ok_or_else(|| PayloadError::NoEventSchema(chunk_message.event_name.clone()))?;
+        let events = chunk_message.event_data.as_array().map(Vec::as_slice).unwrap_or_default();
+        Ok::<_, PayloadError>((schema.full_name(), schema.encode_batch(schema_format, events)?))
+    };
+
+    let (content_type, body) = match format {
+        PayloadFormat::Json => ("application/json".to_string(), serde_json::to_vec(chunk_message)?),
+        PayloadFormat::MessagePack => {
+            let mut body = Vec::new();
+            encode_msgpack(&serde_json::to_value(chunk_message)?, &mut body);
+            ("application/msgpack".to_string(), body)
+        }
+        PayloadFormat::Avro => {
+            ("application/avro".to_string(), schema_encoded(SchemaFormat::Avro)?.1)
+        }
+        PayloadFormat::Protobuf => {
+            let (message_type, body) = schema_encoded(SchemaFormat::Protobuf)?;
+            (format!("application/x-protobuf; messageType={}; delimited=true", message_type), body)
+        }
+    };
+
+    Ok(Payload { format, content_type, body })
+}
+
+fn msgpack_length(len: usize, fix: u8, fix_max: usize, markers: [u8; 3], out: &mut Vec<u8>) {
+    if len <= fix_max {
+        out.push(fix | len as u8);
+    } else if len <= u8::MAX as usize && markers[0] != 0 {
+        out.extend_from_slice(&[markers[0], len as u8]);
+    } else if len <= u16::MAX as usize {
+        out.push(markers[1]);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(markers[2]);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+fn encode_msgpack(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(0xc0),
+        Value::Bool(value) => out.push(if *value { 0xc3 } else { 0xc2 }),
+        Value::Number(number) => {
+            if let Some(value) = number.as_u64() {
+                match value {
+                    0..=0x7f => out.push(value as u8),
+                    0x80..=0xff => out.extend_from_slice(&[0xcc, value as u8]),
+                    0x100..=0xffff => {
+                        out.push(0xcd);
+                        out.extend_from_slice(&(value as u16).to_be_bytes());
+                    }
+                    0x1_0000..=0xffff_ffff => {
+                        out.push(0xce);
+                        out.extend_from_slice(&(value as u32).to_be_bytes());
+                    }
+                    _ => {
+                        out.push(0xcf);
+                        out.extend_from_slice(&value.to_be_bytes());
+                    }
+                }
+            } else if let Some(value) = number.as_i64() {
+                if value >= -32 {
+                    out.push(value as i8 as u8);
+                } else {
+                    out.push(0xd3);
+                    out.extend_from_slice(&value.to_be_bytes());
+                }
+            } else {
+                out.push(0xcb);
+                out.extend_from_slice(&number.as_f64().unwrap_or_default().to_be_bytes());
+            }
+        }
+        Value::String(value) => {
+            msgpack_length(value.len(), 0xa0, 31, [0xd9, 0xda, 0xdb], out);
+            out.extend_from_slice(value.as_bytes());
+        }
+        Value::Array(values) => {
+            // arrays and maps have no 8 bit length
+            msgpack_length(values.len(), 0x90, 15, [0, 0xdc, 0xdd], out);
+            for value in values {
+                encode_msgpack(value, out);
+            }
+        }
+        Value::Object(map) => {
+            msgpack_length(map.len(), 0x80, 15, [0, 0xde, 0xdf], out);
+            for (key, value) in map {
+                encode_msgpack(&Value::String(key.clone()), out);
+                encode_msgpack(value, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_message_pack_encodes_the_chunk() {
+        let mut out = Vec::new();
+        encode_msgpack(&json!({ "a": [1, -1, 300, true, null], "b": "hi" }), &mut out);
+        assert_eq!(
+            out,
+            vec![
+                0x82, 0xa1, b'a', 0x95, 0x01, 0xff, 0xcd, 0x01, 0x2c, 0xc3, 0xc0, 0xa1, b'b', 0xa2,
+                b'h', b'i'
+            ]
+        );
+    }
+}
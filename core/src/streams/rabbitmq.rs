@@ -1,9 +1,8 @@
 use deadpool::managed::PoolError;
 use deadpool_lapin::{Manager, Pool};
 use lapin::{options::*, types::FieldTable, BasicProperties, ConnectionProperties, ExchangeKind};
-use serde_json::Value;
 
-use crate::manifest::stream::ExchangeKindWrapper;
+use crate::{manifest::stream::ExchangeKindWrapper, streams::payload::Payload};
 
 #[derive(thiserror::Error, Debug)]
 pub enum RabbitMQError {
@@ -36,10 +35,8 @@ impl RabbitMQ {
         exchange: &str,
         exchange_type: &ExchangeKindWrapper,
         routing_key: &Option<String>,
-        message: &Value,
+        payload: &Payload,
     ) -> Result<(), RabbitMQError> {
-        let message_body = serde_json::to_vec(message)?;
-
         let conn = self.pool.get().await?;
         let channel = conn.create_channel().await?;
 
@@ -60,10 +57,10 @@ impl RabbitMQ {
                     _ => routing_key.as_ref().expect("Routing key should be defined"),
                 },
                 BasicPublishOptions::default(),
-                &message_body,
+                &payload.body,
                 BasicProperties::default()
                     .with_message_id(id.into())
-                    .with_content_type("application/json".into()),
+                    .with_content_type(payload.content_type.as_str().into()),
             )
             .await?;
 
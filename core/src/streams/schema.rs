@@ -0,0 +1,583 @@
+use ethers::{types::U256, utils::keccak256};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::abi::ABIInput;
+
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    #[error("Field {0} is not a {1}: {2}")]
+    UnexpectedValue(String, &'static str, Value),
+}
+
+/// The typed encodings a streamed event can be sent in, their schemas come from the ABI
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaFormat {
+    Avro,
+    Protobuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FieldType {
+    Bool,
+    Long,
+    String,
+    /// Numbers which do not fit a long, sent as decimal strings
+    BigInt,
+    Optional(Box<FieldType>),
+    Array(Box<FieldType>),
+    Record {
+        name: String,
+        fields: Vec<Field>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    name: String,
+    // the key of the value in the streamed event, unnamed ABI inputs have an empty key
+    key: String,
+    field_type: FieldType,
+}
+
+/// Avro and protobuf names are identifiers, anything else becomes an underscore
+fn schema_name(name: &str) -> String {
+    let name: String =
+        name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    match name.chars().next() {
+        Some(first) if !first.is_ascii_digit() => name,
+        _ => format!("_{}", name),
+    }
+}
+
+fn abi_field_type(type_: &str, components: Option<&Vec<ABIInput>>, record_name: &str) -> FieldType {
+    if let Some(position) = type_.strip_suffix(']').and_then(|t| t.rfind('[')) {
+        return FieldType::Array(Box::new(abi_field_type(
+            &type_[..position],
+            components,
+            record_name,
+        )));
+    }
+
+    let bits =
+        |prefix: &str| type_.strip_prefix(prefix).map(|bits| bits.parse::<u32>().unwrap_or(256));
+    match type_ {
+        "bool" => FieldType::Bool,
+        "tuple" => FieldType::Record {
+            name: record_name.to_string(),
+            fields: abi_fields(components.map(Vec::as_slice).unwrap_or_default(), record_name),
+        },
+        _ => match (bits("uint"), bits("int")) {
+            (Some(bits), _) if bits < 64 => FieldType::Long,
+            (None, Some(bits)) if bits <= 64 => FieldType::Long,
+            (Some(_), _) | (_, Some(_)) => FieldType::BigInt,
+            // addresses, strings and bytes all stream as strings
+            _ => FieldType::String,
+        },
+    }
+}
+
+fn abi_fields(inputs: &[ABIInput], record_name: &str) -> Vec<Field> {
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(position, input)| {
+            let name = if input.name.is_empty() {
+                format!("param{}", position)
+            } else {
+                schema_name(&input.name)
+            };
+            let field_type = abi_field_type(
+                &input.type_,
+                input.components.as_ref(),
+                &format!("{}_{}", record_name, name),
+            );
+            Field { name, key: input.name.clone(), field_type }
+        })
+        .collect()
+}
+
+fn field(name: &str, field_type: FieldType) -> Field {
+    Field { name: name.to_string(), key: name.to_string(), field_type }
+}
+
+/// The schema of an event as it is streamed, its ABI inputs followed by the
+/// `transaction_information` every streamed event carries
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventSchema {
+    event_name: String,
+    namespace: String,
+    name: String,
+    fields: Vec<Field>,
+}
+
+impl EventSchema {
+    pub fn from_abi(contract_name: &str, event_name: &str, inputs: &[ABIInput]) -> Self {
+        let name = schema_name(event_name);
+        let mut fields = abi_fields(inputs, &name);
+        fields.push(field(
+            "transaction_information",
+            FieldType::Record {
+                name: format!("{}_transaction_information", name),
+                fields: vec![
+                    field("network", FieldType::String),
+                    field("address", FieldType::String),
+                    field("block_hash", FieldType::String),
+                    field("block_number", FieldType::Long),
+                    field("block_timestamp", FieldType::Optional(Box::new(FieldType::BigInt))),
+                    field("transaction_hash", FieldType::String),
+                    field("log_index", FieldType::BigInt),
+                    field("transaction_index", FieldType::Long),
+                ],
+            },
+        ));
+
+        Self {
+            event_name: event_name.to_string(),
+            namespace: format!("rindexer.{}", schema_name(&contract_name.to_lowercase())),
+            name,
+            fields,
+        }
+    }
+
+    pub fn event_name(&self) -> &str {
+        &self.event_name
+    }
+
+    /// The namespaced name of the record, unique across the contracts streaming to a topic
+    pub fn full_name(&self) -> String {
+        format!("{}.{}", self.namespace, self.name)
+    }
+
+    pub fn schema(&self, format: SchemaFormat) -> String {
+        match format {
+            SchemaFormat::Avro => {
+                let mut schema = avro_type(&FieldType::Record {
+                    name: self.name.clone(),
+                    fields: self.fields.clone(),
+                });
+                schema["namespace"] = json!(self.namespace);
+                schema.to_string()
+            }
+            SchemaFormat::Protobuf => {
+                format!(
+                    "syntax = \"proto3\";\npackage {};\n\n{}",
+                    self.namespace,
+                    proto_message(&self.name, &self.fields, 0)
+                )
+            }
+        }
+    }
+
+    /// Encodes a streamed event without any framing
+    pub fn encode(&self, format: SchemaFormat, event: &Value) -> Result<Vec<u8>, SchemaError> {
+        let mut out = Vec::new();
+        match format {
+            SchemaFormat::Avro => {
+                for field in &self.fields {
+                    encode_avro(&field.key, &field.field_type, &event[&field.key], &mut out)?;
+                }
+            }
+            SchemaFormat::Protobuf => encode_proto_fields(&self.fields, event, &mut out)?,
+        }
+        Ok(out)
+    }
+
+    /// Encodes a batch of streamed events so it can be decoded without a schema registry, an
+    /// Avro object container file which carries its schema or length delimited protobuf
+    /// messages
+    pub fn encode_batch(
+        &self,
+        format: SchemaFormat,
+        events: &[Value],
+    ) -> Result<Vec<u8>, SchemaError> {
+        let mut out = Vec::new();
+        match format {
+            SchemaFormat::Avro => {
+                let schema = self.schema(SchemaFormat::Avro);
+                // any 16 bytes do as the sync marker as long as they are the same in the file
+                let sync = &keccak256(schema.as_bytes())[..16];
+                out.extend_from_slice(b"Obj\x01");
+                avro_long(2, &mut out);
+                avro_bytes(b"avro.schema", &mut out);
+                avro_bytes(schema.as_bytes(), &mut out);
+                avro_bytes(b"avro.codec", &mut out);
+                avro_bytes(b"null", &mut out);
+                avro_long(0, &mut out);
+                out.extend_from_slice(sync);
+
+                if !events.is_empty() {
+                    let mut block = Vec::new();
+                    for event in events {
+                        block.extend(self.encode(SchemaFormat::Avro, event)?);
+                    }
+                    avro_long(events.len() as i64, &mut out);
+                    avro_bytes(&block, &mut out);
+                    out.extend_from_slice(sync);
+                }
+            }
+            SchemaFormat::Protobuf => {
+                for event in events {
+                    let message = self.encode(SchemaFormat::Protobuf, event)?;
+                    proto_varint(message.len() as u64, &mut out);
+                    out.extend(message);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn as_bool(key: &str, value: &Value) -> Result<bool, SchemaError> {
+    value
+        .as_bool()
+        .ok_or_else(|| SchemaError::UnexpectedValue(key.to_string(), "bool", value.clone()))
+}
+
+/// Longs stream as numbers or as hex strings for `U64`s
+fn as_long(key: &str, value: &Value) -> Result<i64, SchemaError> {
+    let long = match value {
+        Value::Number(number) => number.as_i64().or_else(|| number.as_u64().map(|n| n as i64)),
+        Value::String(value) => match value.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok().map(|n| n as i64),
+            None => value.trim().parse().ok(),
+        },
+        _ => None,
+    };
+    long.ok_or_else(|| SchemaError::UnexpectedValue(key.to_string(), "long", value.clone()))
+}
+
+fn as_string(key: &str, field_type: &FieldType, value: &Value) -> Result<String, SchemaError> {
+    match (field_type, value) {
+        (FieldType::BigInt, Value::String(value)) => match value.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16)
+                .map(|n| n.to_string())
+                .map_err(|_| SchemaError::UnexpectedValue(key.to_string(), "number", json!(value))),
+            None => Ok(value.trim().to_string()),
+        },
+        (_, Value::String(value)) => Ok(value.clone()),
+        (_, Value::Number(number)) => Ok(number.to_string()),
+        _ => Err(SchemaError::UnexpectedValue(key.to_string(), "string", value.clone())),
+    }
+}
+
+fn as_array<'a>(key: &str, value: &'a Value) -> Result<&'a Vec<Value>, SchemaError> {
+    value
+        .as_array()
+        .ok_or_else(|| SchemaError::UnexpectedValue(key.to_string(), "array", value.clone()))
+}
+
+fn avro_type(field_type: &FieldType) -> Value {
+    match field_type {
+        FieldType::Bool => json!("boolean"),
+        FieldType::Long => json!("long"),
+        FieldType::String | FieldType::BigInt => json!("string"),
+        FieldType::Optional(inner) => json!(["null", avro_type(inner)]),
+        FieldType::Array(inner) => json!({ "type": "array", "items": avro_type(inner) }),
+        FieldType::Record { name, fields } => json!({
+            "type": "record",
+            "name": name,
+            "fields": fields
+                .iter()
+                .map(|field| match field.field_type {
+                    FieldType::Optional(_) => json!({
+                        "name": field.name,
+                        "type": avro_type(&field.field_type),
+                        "default": null,
+                    }),
+                    _ => json!({ "name": field.name, "type": avro_type(&field.field_type) }),
+                })
+                .collect::<Vec<_>>(),
+        }),
+    }
+}
+
+fn avro_long(value: i64, out: &mut Vec<u8>) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        out.push((zigzag as u8) | 0x80);
+        zigzag >>= 7;
+    }
+    out.push(zigzag as u8);
+}
+
+fn avro_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    avro_long(bytes.len() as i64, out);
+    out.extend_from_slice(bytes);
+}
+
+fn encode_avro(
+    key: &str,
+    field_type: &FieldType,
+    value: &Value,
+    out: &mut Vec<u8>,
+) -> Result<(), SchemaError> {
+    match field_type {
+        FieldType::Bool => out.push(as_bool(key, value)? as u8),
+        FieldType::Long => avro_long(as_long(key, value)?, out),
+        FieldType::String | FieldType::BigInt => {
+            avro_bytes(as_string(key, field_type, value)?.as_bytes(), out)
+        }
+        FieldType::Optional(inner) => {
+            if value.is_null() {
+                avro_long(0, out);
+            } else {
+                avro_long(1, out);
+                encode_avro(key, inner, value, out)?;
+            }
+        }
+        FieldType::Array(inner) => {
+            let items = as_array(key, value)?;
+            if !items.is_empty() {
+                avro_long(items.len() as i64, out);
+                for item in items {
+                    encode_avro(key, inner, item, out)?;
+                }
+            }
+            avro_long(0, out);
+        }
+        FieldType::Record { fields, .. } => {
+            for field in fields {
+                encode_avro(&field.key, &field.field_type, &value[&field.key], out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The protobuf type of a field, records and arrays of arrays are declared as nested messages
+fn proto_type(
+    field_type: &FieldType,
+    name: &str,
+    depth: usize,
+    nested: &mut Vec<String>,
+) -> String {
+    match field_type {
+        FieldType::Bool => "bool".to_string(),
+        FieldType::Long => "int64".to_string(),
+        FieldType::String | FieldType::BigInt => "string".to_string(),
+        FieldType::Optional(inner) => {
+            format!("optional {}", proto_type(inner, name, depth, nested))
+        }
+        FieldType::Array(inner) => {
+            if let FieldType::Array(_) = inner.as_ref() {
+                // repeated fields can not repeat, each inner array is wrapped in a message
+                let wrapper = format!("{}_list", name);
+                nested.push(proto_message(
+                    &wrapper,
+                    &[Field {
+                        name: "values".to_string(),
+                        key: String::new(),
+                        field_type: inner.as_ref().clone(),
+                    }],
+                    depth,
+                ));
+                format!("repeated {}", wrapper)
+            } else {
+                format!("repeated {}", proto_type(inner, name, depth, nested))
+            }
+        }
+        FieldType::Record { name, fields } => {
+            nested.push(proto_message(name, fields, depth));
+            name.clone()
+        }
+    }
+}
+
+fn proto_message(name: &str, fields: &[Field], depth: usize) -> String {
+    let indent = "  ".repeat(depth + 1);
+    let mut nested = Vec::new();
+    let lines: Vec<String> = fields
+        .iter()
+        .enumerate()
+        .map(|(position, field)| {
+            format!(
+                "{}{} {} = {};",
+                indent,
+                proto_type(
+                    &field.field_type,
+                    &format!("{}_{}", name, field.name),
+                    depth + 1,
+                    &mut nested
+                ),
+                field.name,
+                position + 1
+            )
+        })
+        .collect();
+
+    format!(
+        "{}message {} {{\n{}{}\n{}}}\n",
+        "  ".repeat(depth),
+        name,
+        nested.concat(),
+        lines.join("\n"),
+        "  ".repeat(depth)
+    )
+}
+
+fn proto_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn proto_length_delimited(number: usize, bytes: &[u8], out: &mut Vec<u8>) {
+    proto_varint(((number as u64) << 3) | 2, out);
+    proto_varint(bytes.len() as u64, out);
+    out.extend_from_slice(bytes);
+}
+
+fn encode_proto_fields(
+    fields: &[Field],
+    value: &Value,
+    out: &mut Vec<u8>,
+) -> Result<(), SchemaError> {
+    for (position, field) in fields.iter().enumerate() {
+        encode_proto(position + 1, &field.key, &field.field_type, &value[&field.key], out)?;
+    }
+    Ok(())
+}
+
+fn encode_proto(
+    number: usize,
+    key: &str,
+    field_type: &FieldType,
+    value: &Value,
+    out: &mut Vec<u8>,
+) -> Result<(), SchemaError> {
+    match field_type {
+        FieldType::Bool => {
+            proto_varint((number as u64) << 3, out);
+            proto_varint(as_bool(key, value)? as u64, out);
+        }
+        FieldType::Long => {
+            proto_varint((number as u64) << 3, out);
+            proto_varint(as_long(key, value)? as u64, out);
+        }
+        FieldType::String | FieldType::BigInt => {
+            proto_length_delimited(number, as_string(key, field_type, value)?.as_bytes(), out)
+        }
+        FieldType::Optional(inner) => {
+            if !value.is_null() {
+                encode_proto(number, key, inner, value, out)?;
+            }
+        }
+        FieldType::Array(inner) => {
+            let items = as_array(key, value)?;
+            match inner.as_ref() {
+                // repeated scalars are packed
+                FieldType::Bool | FieldType::Long => {
+                    let mut packed = Vec::new();
+                    for item in items {
+                        let item = match inner.as_ref() {
+                            FieldType::Bool => as_bool(key, item)? as u64,
+                            _ => as_long(key, item)? as u64,
+                        };
+                        proto_varint(item, &mut packed);
+                    }
+                    proto_length_delimited(number, &packed, out);
+                }
+                FieldType::Array(_) => {
+                    for item in items {
+                        let mut wrapper = Vec::new();
+                        encode_proto(1, key, inner, item, &mut wrapper)?;
+                        proto_length_delimited(number, &wrapper, out);
+                    }
+                }
+                _ => {
+                    for item in items {
+                        encode_proto(number, key, inner, item, out)?;
+                    }
+                }
+            }
+        }
+        FieldType::Record { fields, .. } => {
+            let mut message = Vec::new();
+            encode_proto_fields(fields, value, &mut message)?;
+            proto_length_delimited(number, &message, out);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(name: &str, type_: &str) -> ABIInput {
+        ABIInput {
+            indexed: None,
+            name: name.to_string(),
+            type_: type_.to_string(),
+            components: None,
+        }
+    }
+
+    fn transfer() -> EventSchema {
+        EventSchema::from_abi(
+            "USDC",
+            "Transfer",
+            &[input("from", "address"), input("value", "uint256"), input("ids", "uint32[]")],
+        )
+    }
+
+    fn row() -> Value {
+        json!({
+            "from": "0x01",
+            "value": "1000",
+            "ids": [1, 2],
+            "transaction_information": {
+                "network": "ethereum",
+                "address": "0x02",
+                "block_hash": "0x03",
+                "block_number": "0x10",
+                "block_timestamp": null,
+                "transaction_hash": "0x04",
+                "log_index": "0x1a",
+                "transaction_index": "0x1",
+            }
+        })
+    }
+
+    #[test]
+    fn test_avro_schema_and_encoding_follow_the_abi() {
+        let schema: Value = serde_json::from_str(&transfer().schema(SchemaFormat::Avro)).unwrap();
+        assert_eq!(schema["namespace"], "rindexer.usdc");
+        assert_eq!(schema["name"], "Transfer");
+        assert_eq!(schema["fields"][1], json!({ "name": "value", "type": "string" }));
+        assert_eq!(schema["fields"][2]["type"], json!({ "type": "array", "items": "long" }));
+        assert_eq!(schema["fields"][3]["type"]["name"], "Transfer_transaction_information");
+
+        let encoded = transfer().encode(SchemaFormat::Avro, &row()).unwrap();
+        let mut expected = vec![8, b'0', b'x', b'0', b'1', 8, b'1', b'0', b'0', b'0', 4, 2, 4, 0];
+        expected.extend_from_slice(&[16]);
+        expected.extend_from_slice(b"ethereum");
+        expected.extend_from_slice(&[8, b'0', b'x', b'0', b'2', 8, b'0', b'x', b'0', b'3', 32, 0]);
+        expected.extend_from_slice(&[8, b'0', b'x', b'0', b'4', 4, b'2', b'6', 2]);
+        assert_eq!(encoded, expected);
+    }
+
+    #[test]
+    fn test_protobuf_schema_and_encoding_follow_the_abi() {
+        let schema = transfer().schema(SchemaFormat::Protobuf);
+        assert!(schema
+            .starts_with("syntax = \"proto3\";\npackage rindexer.usdc;\n\nmessage Transfer {\n"));
+        assert!(schema.contains("  repeated int64 ids = 3;\n"));
+        assert!(schema.contains("  Transfer_transaction_information transaction_information = 4;"));
+        assert!(schema.contains("    optional string block_timestamp = 5;\n"));
+
+        let encoded = transfer().encode(SchemaFormat::Protobuf, &row()).unwrap();
+        assert_eq!(
+            &encoded[..20],
+            &[
+                10, 4, b'0', b'x', b'0', b'1', 18, 4, b'1', b'0', b'0', b'0', 26, 2, 1, 2, 34, 36,
+                10, 8
+            ]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use crate::{
+    manifest::stream::SchemaRegistryConfig,
+    streams::schema::{EventSchema, SchemaFormat},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum SchemaRegistryError {
+    #[error("Request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+
+    #[error("Schema registry rejected the schema of {0}: {1}")]
+    Rejected(String, String),
+}
+
+#[derive(Deserialize)]
+struct RegisteredSchema {
+    id: u32,
+}
+
+/// A Confluent compatible schema registry the schemas of the streamed events are registered in,
+/// each message then carries the id of its schema so consumers can decode it
+pub struct SchemaRegistry {
+    client: Client,
+    config: SchemaRegistryConfig,
+    // schema ids by subject, registered once per run
+    ids: Mutex<HashMap<String, u32>>,
+}
+
+impl SchemaRegistry {
+    pub fn new(config: &SchemaRegistryConfig) -> Self {
+        Self { client: Client::new(), config: config.clone(), ids: Mutex::new(HashMap::new()) }
+    }
+
+    /// Topics can carry several events so the subject is named after the topic and the record,
+    /// the registry's `TopicRecordNameStrategy`
+    fn subject(topic: &str, schema: &EventSchema) -> String {
+        format!("{}-{}", topic, schema.full_name())
+    }
+
+    /// Registers the schema under its subject, registering a schema the subject already has
+    /// hands back its existing id
+    pub async fn schema_id(
+        &self,
+        topic: &str,
+        schema: &EventSchema,
+        format: SchemaFormat,
+    ) -> Result<u32, SchemaRegistryError> {
+        let subject = Self::subject(topic, schema);
+        let mut ids = self.ids.lock().await;
+        if let Some(id) = ids.get(&subject) {
+            return Ok(*id);
+        }
+
+        let mut body = json!({ "schema": schema.schema(format) });
+        if format == SchemaFormat::Protobuf {
+            body["schemaType"] = json!("PROTOBUF");
+        }
+        let mut request = self
+            .client
+            .post(format!(
+                "{}/subjects/{}/versions",
+                self.config.url.trim_end_matches('/'),
+                subject
+            ))
+            .header("Content-Type", "application/vnd.schemaregistry.v1+json")
+            .json(&body);
+        if let Some(username) = &self.config.username {
+            request = request.basic_auth(username, self.config.password.as_ref());
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(SchemaRegistryError::Rejected(subject, response.text().await?));
+        }
+        let id = response.json::<RegisteredSchema>().await?.id;
+        ids.insert(subject, id);
+        Ok(id)
+    }
+}
+
+/// The Confluent wire format, a zero magic byte and the schema id ahead of the payload. Protobuf
+/// payloads also say which message of the schema they are, always the first here.
+pub fn confluent_framed(schema_id: u32, format: SchemaFormat, payload: Vec<u8>) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(payload.len() + 6);
+    framed.push(0);
+    framed.extend_from_slice(&schema_id.to_be_bytes());
+    if format == SchemaFormat::Protobuf {
+        framed.push(0);
+    }
+    framed.extend(payload);
+    framed
+}
@@ -1,7 +1,6 @@
 use reqwest::Client;
-use serde_json::Value;
 
-use crate::streams::STREAM_MESSAGE_ID_KEY;
+use crate::streams::{payload::Payload, STREAM_MESSAGE_ID_KEY};
 
 #[derive(thiserror::Error, Debug)]
 pub enum WebhookError {
@@ -27,15 +26,15 @@ impl Webhook {
         id: &str,
         endpoint: &str,
         shared_secret: &str,
-        message: &Value,
+        payload: &Payload,
     ) -> Result<(), WebhookError> {
         let response = self
             .client
             .post(endpoint)
-            .header("Content-Type", "application/json")
+            .header("Content-Type", payload.content_type.as_str())
             .header("x-rindexer-shared-secret", shared_secret)
             .header(STREAM_MESSAGE_ID_KEY, id)
-            .json(message)
+            .body(payload.body.clone())
             .send()
             .await?;
 
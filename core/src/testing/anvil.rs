@@ -0,0 +1,162 @@
+use std::{path::PathBuf, sync::Arc};
+
+use ethers::{
+    middleware::Middleware,
+    providers::ProviderError,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Bytes, TransactionReceipt,
+        TransactionRequest, U256, U64,
+    },
+    utils::{Anvil, AnvilInstance},
+};
+use reqwest::header::HeaderMap;
+use thiserror::Error;
+
+use crate::{
+    manifest::yaml::{read_manifest_raw, write_manifest, ReadManifestError, WriteManifestError},
+    provider::{create_client, JsonRpcCachedProvider, RetryClientError},
+};
+
+pub const ANVIL_MANIFEST_FILE_NAME: &str = "rindexer.anvil.yaml";
+
+#[derive(Error, Debug)]
+pub enum AnvilForkError {
+    #[error("Could not create the provider for anvil: {0}")]
+    Provider(#[from] RetryClientError),
+
+    #[error("Anvil request failed: {0}")]
+    Request(#[from] ProviderError),
+
+    #[error("Transaction {0} was dropped before it was mined")]
+    TransactionDropped(String),
+
+    #[error("Deploy transaction did not create a contract")]
+    NoContractDeployed,
+
+    #[error("Could not read the manifest: {0}")]
+    ReadManifest(#[from] ReadManifestError),
+
+    #[error("Could not write the anvil manifest: {0}")]
+    WriteManifest(#[from] WriteManifestError),
+
+    #[error("Network {0} is not in the manifest")]
+    NetworkNotFound(String),
+}
+
+/// An anvil node forked from a real network, killed when dropped. Needs `anvil` on the path.
+pub struct AnvilFork {
+    instance: AnvilInstance,
+    provider: Arc<JsonRpcCachedProvider>,
+}
+
+impl AnvilFork {
+    pub fn spawn(fork_url: &str, fork_block_number: Option<u64>) -> Result<Self, AnvilForkError> {
+        let mut anvil = Anvil::new().fork(fork_url);
+        if let Some(fork_block_number) = fork_block_number {
+            anvil = anvil.fork_block_number(fork_block_number);
+        }
+        let instance = anvil.spawn();
+        let provider = create_client(&instance.endpoint(), None, None, HeaderMap::new())?;
+
+        Ok(Self { instance, provider })
+    }
+
+    pub fn rpc_url(&self) -> String {
+        self.instance.endpoint()
+    }
+
+    pub fn provider(&self) -> Arc<JsonRpcCachedProvider> {
+        Arc::clone(&self.provider)
+    }
+
+    pub async fn block_number(&self) -> Result<U64, AnvilForkError> {
+        Ok(self.provider.get_inner_provider().get_block_number().await?)
+    }
+
+    pub async fn mine(&self, blocks: u64) -> Result<(), AnvilForkError> {
+        let _: () =
+            self.provider.get_inner_provider().request("anvil_mine", [U256::from(blocks)]).await?;
+        Ok(())
+    }
+
+    pub async fn set_balance(&self, address: Address, balance: U256) -> Result<(), AnvilForkError> {
+        let _: () = self
+            .provider
+            .get_inner_provider()
+            .request("anvil_setBalance", (address, balance))
+            .await?;
+        Ok(())
+    }
+
+    /// Lets transactions be sent from the address without its key, for example a token whale
+    pub async fn impersonate(&self, address: Address) -> Result<(), AnvilForkError> {
+        let _: () = self
+            .provider
+            .get_inner_provider()
+            .request("anvil_impersonateAccount", [address])
+            .await?;
+        Ok(())
+    }
+
+    pub async fn stop_impersonating(&self, address: Address) -> Result<(), AnvilForkError> {
+        let _: () = self
+            .provider
+            .get_inner_provider()
+            .request("anvil_stopImpersonatingAccount", [address])
+            .await?;
+        Ok(())
+    }
+
+    /// Sends the transaction from an impersonated or anvil dev account and waits for it to be
+    /// mined
+    pub async fn send_transaction(
+        &self,
+        transaction: impl Into<TypedTransaction> + Send + Sync,
+    ) -> Result<TransactionReceipt, AnvilForkError> {
+        let provider = self.provider.get_inner_provider();
+        let pending = provider.send_transaction(transaction, None).await?;
+        let hash = format!("{:?}", *pending);
+
+        pending.await?.ok_or(AnvilForkError::TransactionDropped(hash))
+    }
+
+    pub async fn deploy(&self, from: Address, bytecode: Bytes) -> Result<Address, AnvilForkError> {
+        let receipt =
+            self.send_transaction(TransactionRequest::new().from(from).data(bytecode)).await?;
+
+        receipt.contract_address.ok_or(AnvilForkError::NoContractDeployed)
+    }
+
+    /// Writes a copy of the manifest next to it which indexes the network from anvil and stops at
+    /// `end_block`, so a test can run the whole pipeline over what it mined
+    pub fn fork_manifest(
+        &self,
+        manifest_path: &PathBuf,
+        network: &str,
+        end_block: U64,
+    ) -> Result<PathBuf, AnvilForkError> {
+        let mut manifest = read_manifest_raw(manifest_path)?;
+
+        let forked_network = manifest
+            .networks
+            .iter_mut()
+            .find(|n| n.name == network)
+            .ok_or_else(|| AnvilForkError::NetworkNotFound(network.to_string()))?;
+        forked_network.rpc = self.rpc_url();
+        forked_network.fallback_rpcs = None;
+        forked_network.historical_source = None;
+        forked_network.rpc_headers = None;
+        forked_network.rpc_bearer_token = None;
+
+        for contract in &mut manifest.contracts {
+            for details in contract.details.iter_mut().filter(|d| d.network == network) {
+                details.end_block = Some(end_block);
+            }
+        }
+
+        let anvil_manifest_path = manifest_path.with_file_name(ANVIL_MANIFEST_FILE_NAME);
+        write_manifest(&manifest, &anvil_manifest_path)?;
+
+        Ok(anvil_manifest_path)
+    }
+}
@@ -0,0 +1,239 @@
+use std::{any::Any, sync::Arc};
+
+use ethers::{
+    abi::{self, Event, ParamType, Token, Tokenizable},
+    contract::EthEvent,
+    types::{Address, Bytes, Log, H256, U256, U64},
+    utils::keccak256,
+};
+
+use crate::{
+    event::callback_registry::{EventResult, LogFoundInRequest, TxInformation},
+    provider::WrappedLog,
+};
+
+fn event_tokens<E: Tokenizable>(event: E) -> Vec<Token> {
+    match event.into_token() {
+        Token::Tuple(tokens) => tokens,
+        token => vec![token],
+    }
+}
+
+/// Value types go in the topic as they are, anything dynamic is only stored as its hash
+fn indexed_topic(kind: &ParamType, token: Token) -> H256 {
+    match (kind, token) {
+        (ParamType::String, Token::String(value)) => H256::from(keccak256(value.as_bytes())),
+        (ParamType::Bytes, Token::Bytes(value)) => H256::from(keccak256(value)),
+        (ParamType::Array(_) | ParamType::FixedArray(_, _) | ParamType::Tuple(_), token) => {
+            H256::from(keccak256(abi::encode(&[token])))
+        }
+        (_, token) => H256::from_slice(&abi::encode(&[token])),
+    }
+}
+
+/// Builds the log a contract would emit for a typed event, with the topics and data encoded the
+/// way the chain does it so the decoding is tested as well.
+///
+/// The abi event says which fields are indexed, take it from the generated abi, for example
+/// `RINDEXERERC20FILTERGEN_ABI.event("Transfer")`.
+#[derive(Debug, Clone)]
+pub struct FakeLogBuilder<E> {
+    event: E,
+    abi_event: Event,
+    network: String,
+    address: Address,
+    block_number: U64,
+    block_hash: Option<H256>,
+    block_timestamp: Option<U256>,
+    transaction_hash: Option<H256>,
+    transaction_index: U64,
+    log_index: U256,
+    removed: bool,
+}
+
+impl<E> FakeLogBuilder<E>
+where
+    E: EthEvent + Tokenizable + Clone + Send + Sync + 'static,
+{
+    pub fn new(event: E, abi_event: &Event) -> Self {
+        Self {
+            event,
+            abi_event: abi_event.clone(),
+            network: "ethereum".to_string(),
+            address: Address::zero(),
+            block_number: U64::one(),
+            block_hash: None,
+            block_timestamp: None,
+            transaction_hash: None,
+            transaction_index: U64::zero(),
+            log_index: U256::zero(),
+            removed: false,
+        }
+    }
+
+    pub fn network(mut self, network: &str) -> Self {
+        self.network = network.to_string();
+        self
+    }
+
+    pub fn address(mut self, address: Address) -> Self {
+        self.address = address;
+        self
+    }
+
+    pub fn block_number(mut self, block_number: u64) -> Self {
+        self.block_number = U64::from(block_number);
+        self
+    }
+
+    /// Defaults to a hash derived from the block number so logs in the same block agree
+    pub fn block_hash(mut self, block_hash: H256) -> Self {
+        self.block_hash = Some(block_hash);
+        self
+    }
+
+    pub fn block_timestamp(mut self, block_timestamp: u64) -> Self {
+        self.block_timestamp = Some(U256::from(block_timestamp));
+        self
+    }
+
+    /// Defaults to a hash derived from the block number and transaction index
+    pub fn transaction_hash(mut self, transaction_hash: H256) -> Self {
+        self.transaction_hash = Some(transaction_hash);
+        self
+    }
+
+    pub fn transaction_index(mut self, transaction_index: u64) -> Self {
+        self.transaction_index = U64::from(transaction_index);
+        self
+    }
+
+    pub fn log_index(mut self, log_index: u64) -> Self {
+        self.log_index = U256::from(log_index);
+        self
+    }
+
+    /// Marks the log as removed by a reorg
+    pub fn removed(mut self) -> Self {
+        self.removed = true;
+        self
+    }
+
+    fn block_hash_or_default(&self) -> H256 {
+        self.block_hash
+            .unwrap_or_else(|| H256::from(keccak256(self.block_number.as_u64().to_be_bytes())))
+    }
+
+    fn transaction_hash_or_default(&self) -> H256 {
+        self.transaction_hash.unwrap_or_else(|| {
+            let mut seed = self.block_number.as_u64().to_be_bytes().to_vec();
+            seed.extend(self.transaction_index.as_u64().to_be_bytes());
+            H256::from(keccak256(seed))
+        })
+    }
+
+    pub fn build_log(&self) -> Log {
+        let mut topics = vec![];
+        if !self.abi_event.anonymous {
+            topics.push(E::signature());
+        }
+
+        let mut data = vec![];
+        for (param, token) in self.abi_event.inputs.iter().zip(event_tokens(self.event.clone())) {
+            if param.indexed {
+                topics.push(indexed_topic(&param.kind, token));
+            } else {
+                data.push(token);
+            }
+        }
+
+        Log {
+            address: self.address,
+            topics,
+            data: Bytes::from(abi::encode(&data)),
+            block_hash: Some(self.block_hash_or_default()),
+            block_number: Some(self.block_number),
+            transaction_hash: Some(self.transaction_hash_or_default()),
+            transaction_index: Some(self.transaction_index),
+            log_index: Some(self.log_index),
+            transaction_log_index: None,
+            log_type: None,
+            removed: Some(self.removed),
+        }
+    }
+
+    pub fn build_wrapped_log(&self) -> WrappedLog {
+        WrappedLog { inner: self.build_log(), block_timestamp: self.block_timestamp }
+    }
+
+    pub fn tx_information(&self) -> TxInformation {
+        TxInformation {
+            network: self.network.clone(),
+            address: self.address,
+            block_hash: self.block_hash_or_default(),
+            block_number: self.block_number,
+            block_timestamp: self.block_timestamp,
+            transaction_hash: self.transaction_hash_or_default(),
+            log_index: self.log_index,
+            transaction_index: self.transaction_index,
+        }
+    }
+
+    /// The event as the indexer hands it to the generated callbacks, already decoded into `E`
+    pub fn build_event_result(&self) -> EventResult {
+        let decoded_data: Arc<dyn Any + Send + Sync> = Arc::new(self.event.clone());
+        EventResult {
+            log: self.build_log(),
+            decoded_data,
+            tx_information: self.tx_information(),
+            found_in_request: LogFoundInRequest {
+                from_block: self.block_number,
+                to_block: self.block_number,
+                completes_out_of_order: false,
+            },
+            spam: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::RawLog;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, EthEvent)]
+    #[ethevent(name = "Transfer", abi = "Transfer(address,address,uint256)")]
+    struct Transfer {
+        #[ethevent(indexed)]
+        from: Address,
+        #[ethevent(indexed)]
+        to: Address,
+        value: U256,
+    }
+
+    #[test]
+    fn test_build_log_round_trips_through_decoding() {
+        let abi_event = abi::parse_abi(&[
+            "event Transfer(address indexed from, address indexed to, uint256 value)",
+        ])
+        .unwrap()
+        .event("Transfer")
+        .unwrap()
+        .clone();
+        let transfer = Transfer {
+            from: Address::repeat_byte(1),
+            to: Address::repeat_byte(2),
+            value: U256::from(1000),
+        };
+
+        let log = FakeLogBuilder::new(transfer.clone(), &abi_event)
+            .block_number(10)
+            .log_index(3)
+            .build_log();
+        assert_eq!(log.topics.len(), 3);
+
+        let decoded = <Transfer as EthEvent>::decode_log(&RawLog::from(log)).unwrap();
+        assert_eq!(decoded, transfer);
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use ethers::{
+    abi::ethereum_types::BloomInput,
+    providers::{JsonRpcError, Provider, RetryClientBuilder},
+    types::{Block, BlockNumber, Bloom, Filter, ValueOrArray, H256, U256, U64},
+    utils::keccak256,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::provider::{JsonRpcCachedProvider, RpcTransport, RpcTransportError, WrappedLog};
+
+fn invalid_params(message: String) -> RpcTransportError {
+    RpcTransportError::Mock(JsonRpcError { code: -32602, message, data: None })
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value, RpcTransportError> {
+    serde_json::to_value(value).map_err(|e| invalid_params(e.to_string()))
+}
+
+fn block_hash(number: u64) -> H256 {
+    H256::from(keccak256(number.to_be_bytes()))
+}
+
+fn topic_matches(topic: &Option<ValueOrArray<Option<H256>>>, log_topic: Option<&H256>) -> bool {
+    match topic {
+        None | Some(ValueOrArray::Value(None)) => true,
+        Some(ValueOrArray::Value(Some(topic))) => log_topic == Some(topic),
+        Some(ValueOrArray::Array(topics)) => {
+            topics.iter().all(|t| t.is_none()) ||
+                topics.iter().flatten().any(|t| log_topic == Some(t))
+        }
+    }
+}
+
+fn log_matches(filter: &Filter, log: &WrappedLog, from_block: U64, to_block: U64) -> bool {
+    let block_number = log.inner.block_number.unwrap_or_default();
+    if block_number < from_block || block_number > to_block {
+        return false;
+    }
+
+    let address_matches = match &filter.address {
+        None => true,
+        Some(ValueOrArray::Value(address)) => *address == log.inner.address,
+        Some(ValueOrArray::Array(addresses)) => addresses.contains(&log.inner.address),
+    };
+
+    address_matches &&
+        filter
+            .topics
+            .iter()
+            .enumerate()
+            .all(|(index, topic)| topic_matches(topic, log.inner.topics.get(index)))
+}
+
+#[derive(Debug)]
+struct MockChain {
+    chain_id: u64,
+    head: AtomicU64,
+    logs: Mutex<Vec<WrappedLog>>,
+    max_logs_per_request: Option<usize>,
+}
+
+/// Answers the json rpc calls the indexer makes from the logs it is given, every block exists up
+/// to the head even when it has no logs. Clones share the same chain so a test can keep one to
+/// add blocks while the indexer is running
+#[derive(Debug, Clone)]
+pub struct MockTransport {
+    chain: Arc<MockChain>,
+}
+
+impl MockTransport {
+    pub fn head(&self) -> u64 {
+        self.chain.head.load(Ordering::SeqCst)
+    }
+
+    pub fn set_head(&self, head: u64) {
+        self.chain.head.store(head, Ordering::SeqCst);
+    }
+
+    /// Adds the logs and moves the head up to the highest block among them
+    pub fn add_logs(&self, logs: impl IntoIterator<Item = WrappedLog>) {
+        let mut chain_logs = self.chain.logs.lock().expect("mock logs lock poisoned");
+        chain_logs.extend(logs);
+        chain_logs.sort_by_key(|log| (log.inner.block_number, log.inner.log_index));
+
+        if let Some(highest) = chain_logs.last().and_then(|log| log.inner.block_number) {
+            self.chain.head.fetch_max(highest.as_u64(), Ordering::SeqCst);
+        }
+    }
+
+    fn resolve_block(&self, block: Option<BlockNumber>) -> U64 {
+        let head = U64::from(self.head());
+        match block {
+            Some(BlockNumber::Number(number)) => number.min(head),
+            Some(BlockNumber::Earliest) => U64::zero(),
+            _ => head,
+        }
+    }
+
+    fn block(&self, number: U64) -> Block<H256> {
+        let mut logs_bloom = Bloom::default();
+        for log in self.chain.logs.lock().expect("mock logs lock poisoned").iter() {
+            if log.inner.block_number == Some(number) {
+                logs_bloom.accrue(BloomInput::Raw(log.inner.address.as_bytes()));
+                for topic in &log.inner.topics {
+                    logs_bloom.accrue(BloomInput::Raw(topic.as_bytes()));
+                }
+            }
+        }
+
+        Block {
+            hash: Some(block_hash(number.as_u64())),
+            parent_hash: block_hash(number.as_u64().saturating_sub(1)),
+            number: Some(number),
+            timestamp: U256::from(number.as_u64() * 12),
+            logs_bloom: Some(logs_bloom),
+            ..Default::default()
+        }
+    }
+
+    fn get_logs(&self, filter: Filter) -> Result<Vec<WrappedLog>, RpcTransportError> {
+        let from_block = self.resolve_block(filter.block_option.get_from_block().copied());
+        let to_block = self.resolve_block(filter.block_option.get_to_block().copied());
+
+        let logs: Vec<WrappedLog> = self
+            .chain
+            .logs
+            .lock()
+            .expect("mock logs lock poisoned")
+            .iter()
+            .filter(|log| log_matches(&filter, log, from_block, to_block))
+            .cloned()
+            .collect();
+
+        // mimic the providers which tell you a smaller range to retry with
+        if let Some(max_logs) = self.chain.max_logs_per_request {
+            if logs.len() > max_logs && from_block < to_block {
+                let retry_to = from_block + (to_block - from_block) / 2;
+                return Err(invalid_params(format!(
+                    "query returned more than {} results. Try with this block range [{:#x}, {:#x}].",
+                    max_logs, from_block, retry_to
+                )));
+            }
+        }
+
+        Ok(logs)
+    }
+
+    fn handle(&self, method: &str, params: Value) -> Result<Value, RpcTransportError> {
+        let param = |index: usize| params.get(index).cloned().unwrap_or(Value::Null);
+
+        match method {
+            "eth_chainId" => to_value(&U64::from(self.chain.chain_id)),
+            "eth_blockNumber" => to_value(&U64::from(self.head())),
+            "eth_getBlockByNumber" => {
+                let block: BlockNumber =
+                    serde_json::from_value(param(0)).map_err(|e| invalid_params(e.to_string()))?;
+                let number = self.resolve_block(Some(block));
+                to_value(&self.block(number))
+            }
+            "eth_getLogs" => {
+                let filter: Filter =
+                    serde_json::from_value(param(0)).map_err(|e| invalid_params(e.to_string()))?;
+                to_value(&self.get_logs(filter)?)
+            }
+            _ => Err(RpcTransportError::Mock(JsonRpcError {
+                code: -32601,
+                message: format!("the method {} does not exist/is not available", method),
+                data: None,
+            })),
+        }
+    }
+
+    pub async fn request<T, R>(&self, method: &str, params: T) -> Result<R, RpcTransportError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params).map_err(|e| invalid_params(e.to_string()))?;
+        let result = self.handle(method, params)?;
+
+        serde_json::from_value(result).map_err(|e| invalid_params(e.to_string()))
+    }
+}
+
+/// Builds a [`JsonRpcCachedProvider`] which serves canned logs and blocks instead of talking to a
+/// node, so the indexing pipeline can be tested without a network
+pub struct MockProvider {
+    chain_id: u64,
+    head: u64,
+    logs: Vec<WrappedLog>,
+    max_logs_per_request: Option<usize>,
+    max_block_range: Option<U64>,
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self {
+            chain_id: 1,
+            head: 0,
+            logs: vec![],
+            max_logs_per_request: None,
+            max_block_range: None,
+        }
+    }
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// The latest block, defaults to the highest block of the logs
+    pub fn with_head(mut self, head: u64) -> Self {
+        self.head = head;
+        self
+    }
+
+    pub fn with_logs(mut self, logs: impl IntoIterator<Item = WrappedLog>) -> Self {
+        self.logs.extend(logs);
+        self
+    }
+
+    /// Errors like a provider with a response size limit when a request matches more logs
+    pub fn with_max_logs_per_request(mut self, max_logs: usize) -> Self {
+        self.max_logs_per_request = Some(max_logs);
+        self
+    }
+
+    pub fn with_max_block_range(mut self, max_block_range: u64) -> Self {
+        self.max_block_range = Some(U64::from(max_block_range));
+        self
+    }
+
+    pub fn build(self) -> Arc<JsonRpcCachedProvider> {
+        self.build_with_transport().0
+    }
+
+    /// Also returns a handle to the chain so the test can mine more blocks after the provider is
+    /// handed to the indexer
+    pub fn build_with_transport(self) -> (Arc<JsonRpcCachedProvider>, MockTransport) {
+        let transport = MockTransport {
+            chain: Arc::new(MockChain {
+                chain_id: self.chain_id,
+                head: AtomicU64::new(self.head),
+                logs: Mutex::new(vec![]),
+                max_logs_per_request: self.max_logs_per_request,
+            }),
+        };
+        transport.add_logs(self.logs);
+
+        let client = RetryClientBuilder::default()
+            .build(RpcTransport::Mock(transport.clone()), Box::<NoRetryPolicy>::default());
+        let provider =
+            Arc::new(JsonRpcCachedProvider::new(Provider::new(client), self.max_block_range));
+
+        (provider, transport)
+    }
+}
+
+#[derive(Debug, Default)]
+struct NoRetryPolicy;
+
+impl ethers::providers::RetryPolicy<RpcTransportError> for NoRetryPolicy {
+    fn should_retry(&self, _error: &RpcTransportError) -> bool {
+        false
+    }
+
+    fn backoff_hint(&self, _error: &RpcTransportError) -> Option<std::time::Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Address, Log};
+
+    use super::*;
+
+    fn log(block_number: u64, address: Address, topic: H256) -> WrappedLog {
+        WrappedLog {
+            inner: Log {
+                address,
+                topics: vec![topic],
+                block_number: Some(U64::from(block_number)),
+                log_index: Some(U256::zero()),
+                ..Default::default()
+            },
+            block_timestamp: None,
+        }
+    }
+
+    #[test]
+    fn test_get_logs_filters_range_address_and_topic() {
+        let address = Address::repeat_byte(1);
+        let topic = H256::repeat_byte(2);
+        let (_, transport) = MockProvider::new()
+            .with_head(100)
+            .with_logs(vec![
+                log(10, address, topic),
+                log(20, address, H256::repeat_byte(3)),
+                log(30, Address::repeat_byte(4), topic),
+                log(90, address, topic),
+            ])
+            .with_max_logs_per_request(1)
+            .build_with_transport();
+        assert_eq!(transport.head(), 100);
+
+        let filter = Filter::new().address(address).topic0(topic).from_block(0u64).to_block(50u64);
+        let logs = transport.get_logs(filter).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].inner.block_number, Some(U64::from(10)));
+
+        let filter = Filter::new().address(address).topic0(topic).from_block(0u64).to_block(100u64);
+        assert!(transport.get_logs(filter).is_err());
+    }
+}
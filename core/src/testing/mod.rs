@@ -0,0 +1,6 @@
+//! Helpers for testing rindexer projects, only built with the `testing` feature.
+
+pub mod anvil;
+pub mod fake_log;
+pub mod mock_provider;
+pub mod postgres;
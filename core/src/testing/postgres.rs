@@ -0,0 +1,163 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use testcontainers_modules::{
+    postgres::Postgres,
+    testcontainers::{runners::AsyncRunner, ContainerAsync, TestcontainersError},
+};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio_postgres::Row;
+
+use crate::{
+    database::postgres::{
+        client::{PostgresConnectionError, PostgresError},
+        generate::generate_event_table_full_name,
+        setup::SetupPostgresError,
+    },
+    manifest::yaml::{read_manifest, ReadManifestError},
+    provider_recording::RPC_REPLAY_DIR_ENV,
+    setup_postgres, start_rindexer_no_code, GraphqlOverrideSettings, IndexerNoCodeDetails,
+    PostgresClient, StartNoCodeDetails,
+};
+
+#[derive(Error, Debug)]
+pub enum TestPostgresError {
+    #[error("Could not start the postgres container: {0}")]
+    Container(#[from] TestcontainersError),
+
+    #[error("Could not connect to the postgres container: {0}")]
+    Connection(#[from] PostgresConnectionError),
+
+    #[error("Could not read the manifest: {0}")]
+    ReadManifest(#[from] ReadManifestError),
+
+    #[error("Could not setup postgres: {0}")]
+    Setup(#[from] SetupPostgresError),
+
+    #[error("Query failed: {0}")]
+    Query(#[from] PostgresError),
+
+    #[error(
+        "Timed out after {timeout:?} waiting for {table} to have {expected} rows, it has {actual}"
+    )]
+    Timeout { table: String, expected: i64, actual: i64, timeout: Duration },
+}
+
+/// A disposable postgres in docker, removed when dropped. `DATABASE_URL` is pointed at it so
+/// everything in the process which connects to postgres uses it, run tests using it one at a time.
+pub struct TestPostgres {
+    _container: ContainerAsync<Postgres>,
+    connection_string: String,
+    client: PostgresClient,
+}
+
+impl TestPostgres {
+    pub async fn start() -> Result<Self, TestPostgresError> {
+        let container = Postgres::default().start().await?;
+        let host = container.get_host().await?;
+        let port = container.get_host_port_ipv4(5432).await?;
+        let connection_string = format!("postgres://postgres:postgres@{}:{}/postgres", host, port);
+
+        env::set_var("DATABASE_URL", &connection_string);
+        let client = PostgresClient::new().await?;
+
+        Ok(Self { _container: container, connection_string, client })
+    }
+
+    pub fn connection_string(&self) -> &str {
+        &self.connection_string
+    }
+
+    pub fn client(&self) -> &PostgresClient {
+        &self.client
+    }
+
+    /// Creates the tables for the manifest the same way `rindexer start` does
+    pub async fn setup(&self, manifest_path: &Path) -> Result<(), TestPostgresError> {
+        let manifest = read_manifest(&manifest_path.to_path_buf())?;
+        let project_path = manifest_path.parent().unwrap_or(Path::new("."));
+        setup_postgres(project_path, &manifest).await?;
+        Ok(())
+    }
+
+    /// Starts the no-code indexer for the manifest in the background, with a replay directory the
+    /// RPC responses come from a recording made with `rindexer start --record` so the run is the
+    /// same every time. The indexer keeps running so wait for the rows you expect instead of the
+    /// handle.
+    pub fn run_indexer(&self, manifest_path: &Path, replay_dir: Option<&Path>) -> JoinHandle<()> {
+        if let Some(replay_dir) = replay_dir {
+            env::set_var(RPC_REPLAY_DIR_ENV, replay_dir);
+        }
+
+        let manifest_path = PathBuf::from(manifest_path);
+        tokio::spawn(async move {
+            let details = StartNoCodeDetails {
+                manifest_path: &manifest_path,
+                indexing_details: IndexerNoCodeDetails { enabled: true },
+                graphql_details: GraphqlOverrideSettings { enabled: false, override_port: None },
+            };
+
+            if let Err(e) = start_rindexer_no_code(details).await {
+                panic!("Indexer failed: {}", e);
+            }
+        })
+    }
+
+    /// The table rindexer writes the event to, pass the table group for contracts sharing tables
+    pub fn event_table(indexer_name: &str, contract_name: &str, event_name: &str) -> String {
+        generate_event_table_full_name(indexer_name, contract_name, event_name)
+    }
+
+    pub async fn query(&self, sql: &str) -> Result<Vec<Row>, TestPostgresError> {
+        Ok(self.client.query(sql, &[]).await?)
+    }
+
+    pub async fn row_count(&self, table: &str) -> Result<i64, TestPostgresError> {
+        let row = self.client.query_one(&format!("SELECT COUNT(*) FROM {}", table), &[]).await?;
+        Ok(row.get(0))
+    }
+
+    /// Polls until the table has at least `expected` rows, tables which do not exist yet count as
+    /// empty
+    pub async fn wait_for_rows(
+        &self,
+        table: &str,
+        expected: i64,
+        timeout: Duration,
+    ) -> Result<(), TestPostgresError> {
+        let started = Instant::now();
+        loop {
+            let actual = self.row_count(table).await.unwrap_or(0);
+            if actual >= expected {
+                return Ok(());
+            }
+
+            if started.elapsed() > timeout {
+                return Err(TestPostgresError::Timeout {
+                    table: table.to_string(),
+                    expected,
+                    actual,
+                    timeout,
+                });
+            }
+
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    }
+
+    pub async fn assert_row_count(&self, table: &str, expected: i64) {
+        let actual = self.row_count(table).await.expect("could not count the rows");
+        assert_eq!(actual, expected, "{} has {} rows, expected {}", table, actual, expected);
+    }
+
+    /// Asserts the query returns exactly the expected values for a single text column
+    pub async fn assert_column(&self, sql: &str, expected: &[&str]) {
+        let rows = self.query(sql).await.expect("query failed");
+        let actual: Vec<String> = rows.iter().map(|row| row.get::<_, String>(0)).collect();
+        assert_eq!(actual, expected, "unexpected results for {}", sql);
+    }
+}
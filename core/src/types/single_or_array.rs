@@ -6,3 +6,15 @@ pub enum StringOrArray {
     Single(String),
     Multiple(Vec<String>),
 }
+
+impl Default for StringOrArray {
+    fn default() -> Self {
+        StringOrArray::Multiple(vec![])
+    }
+}
+
+impl StringOrArray {
+    pub fn is_empty(&self) -> bool {
+        matches!(self, StringOrArray::Multiple(values) if values.is_empty())
+    }
+}
@@ -0,0 +1,44 @@
+CREATE SCHEMA IF NOT EXISTS rindexer_internal;
+CREATE OR REPLACE FUNCTION rindexer_internal.notify_new_event() RETURNS TRIGGER AS $$ BEGIN PERFORM pg_notify(TG_ARGV[0], json_build_object('table', TG_TABLE_SCHEMA || '.' || TG_TABLE_NAME, 'rindexer_id', NEW.rindexer_id)::text);
+RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+CREATE TABLE IF NOT EXISTS rindexer_internal.snapshot_indexer_address_labels ( address TEXT NOT NULL, network TEXT NOT NULL DEFAULT '', label TEXT NOT NULL, source TEXT, PRIMARY KEY (address, network) );
+CREATE INDEX IF NOT EXISTS idx_snapshot_indexer_address_labels_source ON rindexer_internal.snapshot_indexer_address_labels (source);
+CREATE SCHEMA IF NOT EXISTS snapshot_indexer_rocket_pool_eth;
+CREATE TABLE IF NOT EXISTS snapshot_indexer_rocket_pool_eth.approval (rindexer_id SERIAL PRIMARY KEY NOT NULL, contract_address CHAR(66) NOT NULL, "owner" CHAR(42), "spender" CHAR(42), "value" VARCHAR(78), tx_hash CHAR(66) NOT NULL, block_number NUMERIC NOT NULL, block_hash CHAR(66) NOT NULL, network VARCHAR(50) NOT NULL,  tx_index NUMERIC NOT NULL, log_index VARCHAR(78) NOT NULL);
+DROP TRIGGER IF EXISTS rindexer_notify_new_event ON snapshot_indexer_rocket_pool_eth.approval;
+CREATE TABLE IF NOT EXISTS snapshot_indexer_rocket_pool_eth.transfer (rindexer_id SERIAL PRIMARY KEY NOT NULL, contract_address CHAR(66) NOT NULL, "from" CHAR(42), "to" CHAR(42), "value" VARCHAR(78), tx_hash CHAR(66) NOT NULL, block_number NUMERIC NOT NULL, block_hash CHAR(66) NOT NULL, network VARCHAR(50) NOT NULL,  tx_index NUMERIC NOT NULL, log_index VARCHAR(78) NOT NULL);
+DROP TRIGGER IF EXISTS rindexer_notify_new_event ON snapshot_indexer_rocket_pool_eth.transfer;
+COMMENT ON TABLE snapshot_indexer_rocket_pool_eth.transfer IS E'@name RocketPoolETHTransfer';
+CREATE TABLE IF NOT EXISTS rindexer_internal.snapshot_indexer_rocket_pool_eth_approval ("network" TEXT PRIMARY KEY, "last_synced_block" NUMERIC);
+ALTER TABLE rindexer_internal.snapshot_indexer_rocket_pool_eth_approval ADD COLUMN IF NOT EXISTS "last_synced_log_index" NUMERIC;
+INSERT INTO rindexer_internal.snapshot_indexer_rocket_pool_eth_approval ("network", "last_synced_block") VALUES ('ethereum', 0) ON CONFLICT ("network") DO NOTHING;
+CREATE TABLE IF NOT EXISTS rindexer_internal.snapshot_indexer_rocket_pool_eth_transfer ("network" TEXT PRIMARY KEY, "last_synced_block" NUMERIC);
+ALTER TABLE rindexer_internal.snapshot_indexer_rocket_pool_eth_transfer ADD COLUMN IF NOT EXISTS "last_synced_log_index" NUMERIC;
+INSERT INTO rindexer_internal.snapshot_indexer_rocket_pool_eth_transfer ("network", "last_synced_block") VALUES ('ethereum', 0) ON CONFLICT ("network") DO NOTHING;
+CREATE SCHEMA IF NOT EXISTS snapshot_indexer_erc_20_filter;
+CREATE TABLE IF NOT EXISTS snapshot_indexer_erc_20_filter.transfer (rindexer_id SERIAL PRIMARY KEY NOT NULL, contract_address CHAR(66) NOT NULL, "from" CHAR(42), "to" CHAR(42), "value" VARCHAR(78), tx_hash CHAR(66) NOT NULL, block_number NUMERIC NOT NULL, block_hash CHAR(66) NOT NULL, network VARCHAR(50) NOT NULL,  tx_index NUMERIC NOT NULL, log_index VARCHAR(78) NOT NULL);
+DROP TRIGGER IF EXISTS rindexer_notify_new_event ON snapshot_indexer_erc_20_filter.transfer;
+CREATE INDEX IF NOT EXISTS transfer_contract_address_idx ON snapshot_indexer_erc_20_filter.transfer (contract_address);
+DO $$ BEGIN IF to_regclass('snapshot_indexer_erc_20_filter.transfer_contracts_seen') IS NULL THEN CREATE TABLE snapshot_indexer_erc_20_filter.transfer_contracts_seen ( network VARCHAR(50) NOT NULL, contract_address CHAR(66) NOT NULL, first_seen_block NUMERIC NOT NULL, last_seen_block NUMERIC NOT NULL, events BIGINT NOT NULL, PRIMARY KEY (network, contract_address) );
+INSERT INTO snapshot_indexer_erc_20_filter.transfer_contracts_seen (network, contract_address, first_seen_block, last_seen_block, events) SELECT network, contract_address, MIN(block_number), MAX(block_number), COUNT(*) FROM snapshot_indexer_erc_20_filter.transfer GROUP BY network, contract_address;
+END IF;
+END $$;
+CREATE OR REPLACE FUNCTION snapshot_indexer_erc_20_filter.transfer_contracts_seen_record() RETURNS trigger AS $$ BEGIN INSERT INTO snapshot_indexer_erc_20_filter.transfer_contracts_seen AS seen (network, contract_address, first_seen_block, last_seen_block, events) SELECT network, contract_address, MIN(block_number), MAX(block_number), COUNT(*) FROM inserted GROUP BY network, contract_address ON CONFLICT (network, contract_address) DO UPDATE SET first_seen_block = LEAST(seen.first_seen_block, EXCLUDED.first_seen_block), last_seen_block = GREATEST(seen.last_seen_block, EXCLUDED.last_seen_block), events = seen.events + EXCLUDED.events;
+RETURN NULL;
+END $$ LANGUAGE plpgsql;
+DROP TRIGGER IF EXISTS rindexer_contracts_seen ON snapshot_indexer_erc_20_filter.transfer;
+CREATE TRIGGER rindexer_contracts_seen AFTER INSERT ON snapshot_indexer_erc_20_filter.transfer REFERENCING NEW TABLE AS inserted FOR EACH STATEMENT EXECUTE FUNCTION snapshot_indexer_erc_20_filter.transfer_contracts_seen_record();
+COMMENT ON TABLE snapshot_indexer_erc_20_filter.transfer_contracts_seen IS E'The contracts which emitted the event, the total count is the distinct contracts seen';
+COMMENT ON TABLE snapshot_indexer_erc_20_filter.transfer IS E'@name ERC20Transfer';
+CREATE TABLE IF NOT EXISTS rindexer_internal.snapshot_indexer_erc_20_filter_transfer ("network" TEXT PRIMARY KEY, "last_synced_block" NUMERIC);
+ALTER TABLE rindexer_internal.snapshot_indexer_erc_20_filter_transfer ADD COLUMN IF NOT EXISTS "last_synced_log_index" NUMERIC;
+INSERT INTO rindexer_internal.snapshot_indexer_erc_20_filter_transfer ("network", "last_synced_block") VALUES ('base', 0) ON CONFLICT ("network") DO NOTHING;
+CREATE TABLE IF NOT EXISTS rindexer_internal.snapshot_indexer_last_known_relationship_dropping_sql ( key INT PRIMARY KEY, value TEXT NOT NULL );
+CREATE TABLE IF NOT EXISTS rindexer_internal.snapshot_indexer_last_known_indexes_dropping_sql ( key INT PRIMARY KEY, value TEXT NOT NULL );
+CREATE TABLE IF NOT EXISTS rindexer_internal.snapshot_indexer_kv_store ( namespace TEXT NOT NULL, key TEXT NOT NULL, value JSONB NOT NULL, PRIMARY KEY (namespace, key) );
+CREATE TABLE IF NOT EXISTS rindexer_internal.snapshot_indexer_kv_store_history ( id BIGSERIAL PRIMARY KEY, namespace TEXT NOT NULL, key TEXT NOT NULL, network TEXT NOT NULL, block_number NUMERIC NOT NULL, previous_value JSONB );
+CREATE INDEX IF NOT EXISTS idx_snapshot_indexer_kv_store_history_network_block ON rindexer_internal.snapshot_indexer_kv_store_history (network, block_number);
+CREATE TABLE IF NOT EXISTS rindexer_internal.snapshot_indexer_retry_queue ( contract_name TEXT NOT NULL, event_name TEXT NOT NULL, network TEXT NOT NULL, from_block NUMERIC NOT NULL, to_block NUMERIC NOT NULL, attempts INT NOT NULL DEFAULT 1, last_error TEXT NOT NULL, created_at TIMESTAMPTZ NOT NULL DEFAULT now(), PRIMARY KEY (contract_name, event_name, network, from_block, to_block) );
+CREATE TABLE IF NOT EXISTS rindexer_internal.ranges ( indexer_name TEXT NOT NULL, contract_name TEXT NOT NULL, event_name TEXT NOT NULL, network TEXT NOT NULL, from_block NUMERIC NOT NULL, to_block NUMERIC NOT NULL, completed_at TIMESTAMPTZ NOT NULL DEFAULT now(), PRIMARY KEY (indexer_name, contract_name, event_name, network, from_block, to_block) );
@@ -71,7 +71,7 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
                 let csv_result = context.csv.append_bulk(csv_bulk_data).await;
                 if let Err(e) = csv_result {
                     rindexer_error!("PlaygroundTypesFilterEventType::Swap inserting csv data: {:?}", e);
-                    return Err(e.to_string());
+                    return Err(e.to_string().into());
                 }
             }
 
@@ -118,7 +118,7 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
 
                     if let Err(e) = result {
                         rindexer_error!("PlaygroundTypesFilterEventType::Swap inserting bulk data via COPY: {:?}", e);
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 } else {
                     let result = context
@@ -153,7 +153,7 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
 
                     if let Err(e) = result {
                         rindexer_error!("PlaygroundTypesFilterEventType::Swap inserting bulk data via INSERT: {:?}", e);
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 }
 
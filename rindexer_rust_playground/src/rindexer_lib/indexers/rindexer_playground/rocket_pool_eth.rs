@@ -55,7 +55,7 @@ async fn approval_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                             "RocketPoolETHEventType::Approval inserting csv data: {:?}",
                             e
                         );
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 }
 
@@ -95,7 +95,7 @@ async fn approval_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                             "RocketPoolETHEventType::Approval inserting bulk data via COPY: {:?}",
                             e
                         );
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 } else {
                     let result = context
@@ -123,7 +123,7 @@ async fn approval_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                             "RocketPoolETHEventType::Approval inserting bulk data via INSERT: {:?}",
                             e
                         );
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 }
 
@@ -187,7 +187,7 @@ async fn transfer_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                             "RocketPoolETHEventType::Transfer inserting csv data: {:?}",
                             e
                         );
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 }
 
@@ -227,7 +227,7 @@ async fn transfer_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                             "RocketPoolETHEventType::Transfer inserting bulk data via COPY: {:?}",
                             e
                         );
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 } else {
                     let result = context
@@ -255,7 +255,7 @@ async fn transfer_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                             "RocketPoolETHEventType::Transfer inserting bulk data via INSERT: {:?}",
                             e
                         );
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 }
 
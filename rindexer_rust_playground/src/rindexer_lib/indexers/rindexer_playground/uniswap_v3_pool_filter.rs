@@ -59,7 +59,7 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
                 let csv_result = context.csv.append_bulk(csv_bulk_data).await;
                 if let Err(e) = csv_result {
                     rindexer_error!("UniswapV3PoolFilterEventType::Swap inserting csv data: {:?}", e);
-                    return Err(e.to_string());
+                    return Err(e.to_string().into());
                 }
             }
 
@@ -100,7 +100,7 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
 
                     if let Err(e) = result {
                         rindexer_error!("UniswapV3PoolFilterEventType::Swap inserting bulk data via COPY: {:?}", e);
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 } else {
                     let result = context
@@ -128,7 +128,7 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
                         .await;
                     if let Err(e) = result {
                         rindexer_error!("UniswapV3PoolFilterEventType::Swap inserting bulk data via INSERT: {:?}", e);
-                        return Err(e.to_string());
+                        return Err(e.to_string().into());
                     }
                 }
 
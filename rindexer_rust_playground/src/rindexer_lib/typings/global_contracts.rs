@@ -3,8 +3,9 @@ use std::sync::Arc;
 use ethers::{
     abi::Address,
     contract::abigen,
-    providers::{Http, Provider, RetryClient},
+    providers::{Provider, RetryClient},
 };
+use rindexer::provider::RpcTransport;
 
 /// THIS IS A GENERATED FILE. DO NOT MODIFY MANUALLY.
 ///
@@ -14,7 +15,7 @@ use super::networks::{get_base_provider, get_ethereum_provider};
 
 abigen!(USDT, "./abis/erc20-abi.json");
 
-pub fn usdt_contract() -> USDT<Arc<Provider<RetryClient<Http>>>> {
+pub fn usdt_contract() -> USDT<Arc<Provider<RetryClient<RpcTransport>>>> {
     let address: Address =
         "0xdac17f958d2ee523a2206206994597c13d831ec7".parse().expect("Invalid address");
 
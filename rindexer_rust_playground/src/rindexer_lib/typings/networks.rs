@@ -4,11 +4,11 @@ use std::sync::Arc;
 ///
 /// This file was auto generated by rindexer - https://github.com/joshstevens19/rindexer.
 /// Any manual changes to this file will be overwritten.
-use ethers::providers::{Http, Provider, RetryClient};
+use ethers::providers::{Provider, RetryClient};
 use ethers::types::U64;
 use rindexer::{
     lazy_static,
-    provider::{create_client, JsonRpcCachedProvider, RetryClientError},
+    provider::{create_client, JsonRpcCachedProvider, RetryClientError, RpcTransport},
     public_read_env_value, HeaderMap,
 };
 
@@ -48,7 +48,7 @@ pub fn get_ethereum_provider_cache() -> Arc<JsonRpcCachedProvider> {
     Arc::clone(&ETHEREUM_PROVIDER)
 }
 
-pub fn get_ethereum_provider() -> Arc<Provider<RetryClient<Http>>> {
+pub fn get_ethereum_provider() -> Arc<Provider<RetryClient<RpcTransport>>> {
     ETHEREUM_PROVIDER.get_inner_provider()
 }
 
@@ -56,7 +56,7 @@ pub fn get_base_provider_cache() -> Arc<JsonRpcCachedProvider> {
     Arc::clone(&BASE_PROVIDER)
 }
 
-pub fn get_base_provider() -> Arc<Provider<RetryClient<Http>>> {
+pub fn get_base_provider() -> Arc<Provider<RetryClient<RpcTransport>>> {
     BASE_PROVIDER.get_inner_provider()
 }
 
@@ -17,8 +17,8 @@ use std::{
 
 use ethers::{
     abi::Address,
-    providers::{Http, Provider, RetryClient},
-    types::{Bytes, H256},
+    providers::{Provider, RetryClient},
+    types::{Bytes, H256, U64},
 };
 use rindexer::{
     async_trait,
@@ -30,11 +30,13 @@ use rindexer::{
         contract_setup::{ContractInformation, NetworkContract},
     },
     generate_random_id,
+    indexer::spam::SpamReason,
     manifest::{
         contract::{Contract, ContractDetails},
         yaml::read_manifest,
     },
-    provider::JsonRpcCachedProvider,
+    provider::{JsonRpcCachedProvider, RpcTransport},
+    provider_state::StateCallError,
     AsyncCsvAppender, FutureExt, PostgresClient,
 };
 
@@ -53,6 +55,7 @@ pub type ApprovalData = rindexer_erc20_filter_gen::ApprovalFilter;
 pub struct ApprovalResult {
     pub event_data: ApprovalData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 pub type TransferData = rindexer_erc20_filter_gen::TransferFilter;
@@ -61,6 +64,7 @@ pub type TransferData = rindexer_erc20_filter_gen::TransferFilter;
 pub struct TransferResult {
     pub event_data: TransferData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
@@ -79,6 +83,34 @@ where
     pub extensions: Arc<TExtensions>,
 }
 
+impl<TExtensions> EventContext<TExtensions>
+where
+    TExtensions: Send + Sync,
+{
+    /// Calls a contract as of the end of the block on the network, use the block of the
+    /// event to read the state it left behind. Results are cached.
+    pub async fn eth_call_at(
+        &self,
+        network: &str,
+        to: Address,
+        data: Bytes,
+        block: U64,
+    ) -> Result<Bytes, StateCallError> {
+        get_provider_cache_for_network(network).call_at(to, data, block).await
+    }
+
+    /// Many calls as of the end of the block batched into one request, calls which revert
+    /// are `None`
+    pub async fn eth_call_many_at(
+        &self,
+        network: &str,
+        calls: Vec<(Address, Bytes)>,
+        block: U64,
+    ) -> Result<Vec<Option<Bytes>>, StateCallError> {
+        get_provider_cache_for_network(network).call_many_at(calls, block).await
+    }
+}
+
 // didn't want to use option or none made harder DX
 // so a blank struct makes interface nice
 pub struct NoExtensions {}
@@ -173,6 +205,7 @@ where
                 item.decoded_data.downcast::<ApprovalData>().ok().map(|arc| ApprovalResult {
                     event_data: (*arc).clone(),
                     tx_information: item.tx_information,
+                    spam: item.spam,
                 })
             })
             .collect();
@@ -268,6 +301,7 @@ where
                 item.decoded_data.downcast::<TransferData>().ok().map(|arc| TransferResult {
                     event_data: (*arc).clone(),
                     tx_information: item.tx_information,
+                    spam: item.spam,
                 })
             })
             .collect();
@@ -287,14 +321,16 @@ where
 pub fn erc_20_filter_contract(
     network: &str,
     address: Address,
-) -> RindexerERC20FilterGen<Arc<Provider<RetryClient<Http>>>> {
+) -> RindexerERC20FilterGen<Arc<Provider<RetryClient<RpcTransport>>>> {
     RindexerERC20FilterGen::new(
         address,
         Arc::new(get_provider_cache_for_network(network).get_inner_provider()),
     )
 }
 
-pub fn decoder_contract(network: &str) -> RindexerERC20FilterGen<Arc<Provider<RetryClient<Http>>>> {
+pub fn decoder_contract(
+    network: &str,
+) -> RindexerERC20FilterGen<Arc<Provider<RetryClient<RpcTransport>>>> {
     if network == "ethereum" {
         RindexerERC20FilterGen::new(
             // do not care about address here its decoding makes it easier to handle ValueOrArray
@@ -377,10 +413,12 @@ where
             })
             .clone();
 
-        let index_event_in_order = contract_details
-            .index_event_in_order
-            .as_ref()
-            .map_or(false, |vec| vec.contains(&event_name.to_string()));
+        let index_event_in_order = contract_details.is_event_indexed_in_order(event_name);
+
+        let group_by_block = contract_details.is_event_grouped_by_block(event_name);
+        let max_concurrent_callbacks = contract_details.max_concurrent_callbacks(event_name);
+        let failure_policy = contract_details.failure_policy(event_name);
+        let archive_raw_logs = contract_details.archive_raw_logs();
 
         let contract = ContractInformation {
             name: contract_details.before_modify_name_if_filter_readonly().into_owned(),
@@ -400,6 +438,16 @@ where
                         .iter()
                         .find(|n| n.name == c.network)
                         .map_or(false, |n| n.disable_logs_bloom_checks.unwrap_or_default()),
+                    logs_bloom_scan: rindexer_yaml
+                        .networks
+                        .iter()
+                        .find(|n| n.name == c.network)
+                        .is_some_and(|n| n.logs_bloom_scan.unwrap_or_default()),
+                    historical_source: rindexer_yaml
+                        .networks
+                        .iter()
+                        .find(|n| n.name == c.network)
+                        .and_then(|n| n.historical_source.clone()),
                 })
                 .collect(),
             abi: contract_details.abi,
@@ -431,6 +479,10 @@ where
             indexer_name: "RindexerPlayground".to_string(),
             event_name: event_name.to_string(),
             index_event_in_order,
+            group_by_block,
+            max_concurrent_callbacks,
+            failure_policy,
+            archive_raw_logs,
             topic_id: topic_id.parse::<H256>().unwrap(),
             contract,
             callback,
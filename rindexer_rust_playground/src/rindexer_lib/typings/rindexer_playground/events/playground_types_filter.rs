@@ -17,8 +17,8 @@ use std::{
 
 use ethers::{
     abi::Address,
-    providers::{Http, Provider, RetryClient},
-    types::{Bytes, H256},
+    providers::{Provider, RetryClient},
+    types::{Bytes, H256, U64},
 };
 use rindexer::{
     async_trait,
@@ -30,11 +30,13 @@ use rindexer::{
         contract_setup::{ContractInformation, NetworkContract},
     },
     generate_random_id,
+    indexer::spam::SpamReason,
     manifest::{
         contract::{Contract, ContractDetails},
         yaml::read_manifest,
     },
-    provider::JsonRpcCachedProvider,
+    provider::{JsonRpcCachedProvider, RpcTransport},
+    provider_state::StateCallError,
     AsyncCsvAppender, FutureExt, PostgresClient,
 };
 
@@ -55,6 +57,7 @@ pub type SwapData = rindexer_playground_types_filter_gen::SwapFilter;
 pub struct SwapResult {
     pub event_data: SwapData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
@@ -73,6 +76,34 @@ where
     pub extensions: Arc<TExtensions>,
 }
 
+impl<TExtensions> EventContext<TExtensions>
+where
+    TExtensions: Send + Sync,
+{
+    /// Calls a contract as of the end of the block on the network, use the block of the
+    /// event to read the state it left behind. Results are cached.
+    pub async fn eth_call_at(
+        &self,
+        network: &str,
+        to: Address,
+        data: Bytes,
+        block: U64,
+    ) -> Result<Bytes, StateCallError> {
+        get_provider_cache_for_network(network).call_at(to, data, block).await
+    }
+
+    /// Many calls as of the end of the block batched into one request, calls which revert
+    /// are `None`
+    pub async fn eth_call_many_at(
+        &self,
+        network: &str,
+        calls: Vec<(Address, Bytes)>,
+        block: U64,
+    ) -> Result<Vec<Option<Bytes>>, StateCallError> {
+        get_provider_cache_for_network(network).call_many_at(calls, block).await
+    }
+}
+
 // didn't want to use option or none made harder DX
 // so a blank struct makes interface nice
 pub struct NoExtensions {}
@@ -165,6 +196,7 @@ where
                 item.decoded_data.downcast::<SwapData>().ok().map(|arc| SwapResult {
                     event_data: (*arc).clone(),
                     tx_information: item.tx_information,
+                    spam: item.spam,
                 })
             })
             .collect();
@@ -183,7 +215,7 @@ where
 pub fn playground_types_filter_contract(
     network: &str,
     address: Address,
-) -> RindexerPlaygroundTypesFilterGen<Arc<Provider<RetryClient<Http>>>> {
+) -> RindexerPlaygroundTypesFilterGen<Arc<Provider<RetryClient<RpcTransport>>>> {
     RindexerPlaygroundTypesFilterGen::new(
         address,
         Arc::new(get_provider_cache_for_network(network).get_inner_provider()),
@@ -192,7 +224,7 @@ pub fn playground_types_filter_contract(
 
 pub fn decoder_contract(
     network: &str,
-) -> RindexerPlaygroundTypesFilterGen<Arc<Provider<RetryClient<Http>>>> {
+) -> RindexerPlaygroundTypesFilterGen<Arc<Provider<RetryClient<RpcTransport>>>> {
     if network == "base" {
         RindexerPlaygroundTypesFilterGen::new(
             // do not care about address here its decoding makes it easier to handle ValueOrArray
@@ -266,10 +298,12 @@ where
             })
             .clone();
 
-        let index_event_in_order = contract_details
-            .index_event_in_order
-            .as_ref()
-            .map_or(false, |vec| vec.contains(&event_name.to_string()));
+        let index_event_in_order = contract_details.is_event_indexed_in_order(event_name);
+
+        let group_by_block = contract_details.is_event_grouped_by_block(event_name);
+        let max_concurrent_callbacks = contract_details.max_concurrent_callbacks(event_name);
+        let failure_policy = contract_details.failure_policy(event_name);
+        let archive_raw_logs = contract_details.archive_raw_logs();
 
         let contract = ContractInformation {
             name: contract_details.before_modify_name_if_filter_readonly().into_owned(),
@@ -289,6 +323,16 @@ where
                         .iter()
                         .find(|n| n.name == c.network)
                         .map_or(false, |n| n.disable_logs_bloom_checks.unwrap_or_default()),
+                    logs_bloom_scan: rindexer_yaml
+                        .networks
+                        .iter()
+                        .find(|n| n.name == c.network)
+                        .is_some_and(|n| n.logs_bloom_scan.unwrap_or_default()),
+                    historical_source: rindexer_yaml
+                        .networks
+                        .iter()
+                        .find(|n| n.name == c.network)
+                        .and_then(|n| n.historical_source.clone()),
                 })
                 .collect(),
             abi: contract_details.abi,
@@ -312,6 +356,10 @@ where
             indexer_name: "RindexerPlayground".to_string(),
             event_name: event_name.to_string(),
             index_event_in_order,
+            group_by_block,
+            max_concurrent_callbacks,
+            failure_policy,
+            archive_raw_logs,
             topic_id: topic_id.parse::<H256>().unwrap(),
             contract,
             callback,
@@ -17,8 +17,8 @@ use std::{
 
 use ethers::{
     abi::Address,
-    providers::{Http, Provider, RetryClient},
-    types::{Bytes, H256},
+    providers::{Provider, RetryClient},
+    types::{Bytes, H256, U64},
 };
 use rindexer::{
     async_trait,
@@ -30,11 +30,13 @@ use rindexer::{
         contract_setup::{ContractInformation, NetworkContract},
     },
     generate_random_id,
+    indexer::spam::SpamReason,
     manifest::{
         contract::{Contract, ContractDetails},
         yaml::read_manifest,
     },
-    provider::JsonRpcCachedProvider,
+    provider::{JsonRpcCachedProvider, RpcTransport},
+    provider_state::StateCallError,
     AsyncCsvAppender, FutureExt, PostgresClient,
 };
 
@@ -55,6 +57,7 @@ pub type BurnData = rindexer_uniswap_v3_pool_filter_gen::BurnFilter;
 pub struct BurnResult {
     pub event_data: BurnData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 pub type CollectData = rindexer_uniswap_v3_pool_filter_gen::CollectFilter;
@@ -63,6 +66,7 @@ pub type CollectData = rindexer_uniswap_v3_pool_filter_gen::CollectFilter;
 pub struct CollectResult {
     pub event_data: CollectData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 pub type CollectProtocolData = rindexer_uniswap_v3_pool_filter_gen::CollectProtocolFilter;
@@ -71,6 +75,7 @@ pub type CollectProtocolData = rindexer_uniswap_v3_pool_filter_gen::CollectProto
 pub struct CollectProtocolResult {
     pub event_data: CollectProtocolData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 pub type FlashData = rindexer_uniswap_v3_pool_filter_gen::FlashFilter;
@@ -79,6 +84,7 @@ pub type FlashData = rindexer_uniswap_v3_pool_filter_gen::FlashFilter;
 pub struct FlashResult {
     pub event_data: FlashData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 pub type IncreaseObservationCardinalityNextData =
@@ -88,6 +94,7 @@ pub type IncreaseObservationCardinalityNextData =
 pub struct IncreaseObservationCardinalityNextResult {
     pub event_data: IncreaseObservationCardinalityNextData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 pub type InitializeData = rindexer_uniswap_v3_pool_filter_gen::InitializeFilter;
@@ -96,6 +103,7 @@ pub type InitializeData = rindexer_uniswap_v3_pool_filter_gen::InitializeFilter;
 pub struct InitializeResult {
     pub event_data: InitializeData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 pub type MintData = rindexer_uniswap_v3_pool_filter_gen::MintFilter;
@@ -104,6 +112,7 @@ pub type MintData = rindexer_uniswap_v3_pool_filter_gen::MintFilter;
 pub struct MintResult {
     pub event_data: MintData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 pub type SetFeeProtocolData = rindexer_uniswap_v3_pool_filter_gen::SetFeeProtocolFilter;
@@ -112,6 +121,7 @@ pub type SetFeeProtocolData = rindexer_uniswap_v3_pool_filter_gen::SetFeeProtoco
 pub struct SetFeeProtocolResult {
     pub event_data: SetFeeProtocolData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 pub type SwapData = rindexer_uniswap_v3_pool_filter_gen::SwapFilter;
@@ -120,6 +130,7 @@ pub type SwapData = rindexer_uniswap_v3_pool_filter_gen::SwapFilter;
 pub struct SwapResult {
     pub event_data: SwapData,
     pub tx_information: TxInformation,
+    pub spam: Option<SpamReason>,
 }
 
 type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
@@ -138,6 +149,34 @@ where
     pub extensions: Arc<TExtensions>,
 }
 
+impl<TExtensions> EventContext<TExtensions>
+where
+    TExtensions: Send + Sync,
+{
+    /// Calls a contract as of the end of the block on the network, use the block of the
+    /// event to read the state it left behind. Results are cached.
+    pub async fn eth_call_at(
+        &self,
+        network: &str,
+        to: Address,
+        data: Bytes,
+        block: U64,
+    ) -> Result<Bytes, StateCallError> {
+        get_provider_cache_for_network(network).call_at(to, data, block).await
+    }
+
+    /// Many calls as of the end of the block batched into one request, calls which revert
+    /// are `None`
+    pub async fn eth_call_many_at(
+        &self,
+        network: &str,
+        calls: Vec<(Address, Bytes)>,
+        block: U64,
+    ) -> Result<Vec<Option<Bytes>>, StateCallError> {
+        get_provider_cache_for_network(network).call_many_at(calls, block).await
+    }
+}
+
 // didn't want to use option or none made harder DX
 // so a blank struct makes interface nice
 pub struct NoExtensions {}
@@ -230,6 +269,7 @@ where
                 item.decoded_data.downcast::<SwapData>().ok().map(|arc| SwapResult {
                     event_data: (*arc).clone(),
                     tx_information: item.tx_information,
+                    spam: item.spam,
                 })
             })
             .collect();
@@ -248,7 +288,7 @@ where
 pub fn uniswap_v3_pool_filter_contract(
     network: &str,
     address: Address,
-) -> RindexerUniswapV3PoolFilterGen<Arc<Provider<RetryClient<Http>>>> {
+) -> RindexerUniswapV3PoolFilterGen<Arc<Provider<RetryClient<RpcTransport>>>> {
     RindexerUniswapV3PoolFilterGen::new(
         address,
         Arc::new(get_provider_cache_for_network(network).get_inner_provider()),
@@ -257,7 +297,7 @@ pub fn uniswap_v3_pool_filter_contract(
 
 pub fn decoder_contract(
     network: &str,
-) -> RindexerUniswapV3PoolFilterGen<Arc<Provider<RetryClient<Http>>>> {
+) -> RindexerUniswapV3PoolFilterGen<Arc<Provider<RetryClient<RpcTransport>>>> {
     if network == "base" {
         RindexerUniswapV3PoolFilterGen::new(
             // do not care about address here its decoding makes it easier to handle ValueOrArray
@@ -331,10 +371,12 @@ where
             })
             .clone();
 
-        let index_event_in_order = contract_details
-            .index_event_in_order
-            .as_ref()
-            .map_or(false, |vec| vec.contains(&event_name.to_string()));
+        let index_event_in_order = contract_details.is_event_indexed_in_order(event_name);
+
+        let group_by_block = contract_details.is_event_grouped_by_block(event_name);
+        let max_concurrent_callbacks = contract_details.max_concurrent_callbacks(event_name);
+        let failure_policy = contract_details.failure_policy(event_name);
+        let archive_raw_logs = contract_details.archive_raw_logs();
 
         let contract = ContractInformation {
             name: contract_details.before_modify_name_if_filter_readonly().into_owned(),
@@ -354,6 +396,16 @@ where
                         .iter()
                         .find(|n| n.name == c.network)
                         .map_or(false, |n| n.disable_logs_bloom_checks.unwrap_or_default()),
+                    logs_bloom_scan: rindexer_yaml
+                        .networks
+                        .iter()
+                        .find(|n| n.name == c.network)
+                        .is_some_and(|n| n.logs_bloom_scan.unwrap_or_default()),
+                    historical_source: rindexer_yaml
+                        .networks
+                        .iter()
+                        .find(|n| n.name == c.network)
+                        .and_then(|n| n.historical_source.clone()),
                 })
                 .collect(),
             abi: contract_details.abi,
@@ -377,6 +429,10 @@ where
             indexer_name: "RindexerPlayground".to_string(),
             event_name: event_name.to_string(),
             index_event_in_order,
+            group_by_block,
+            max_concurrent_callbacks,
+            failure_policy,
+            archive_raw_logs,
             topic_id: topic_id.parse::<H256>().unwrap(),
             contract,
             callback,